@@ -0,0 +1,94 @@
+//! Query planning benchmarks
+//!
+//! By default, the operations of `cli/fixtures/queries` are planned against the
+//! `cli/fixtures/starstuff.graphql` supergraph. Set `PLANNER_BENCH_SUPERGRAPH` and
+//! `PLANNER_BENCH_OPERATIONS` to benchmark another supergraph and operation corpus:
+//!
+//! ```sh
+//! PLANNER_BENCH_SUPERGRAPH=supergraph.graphql PLANNER_BENCH_OPERATIONS=operations/ \
+//!     cargo bench -p apollo-federation --bench query_planning
+//! ```
+
+use std::path::PathBuf;
+
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::query_plan::query_planner::QueryPlanner;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::Supergraph;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+
+fn fixture_path(variable: &str, default: &str) -> PathBuf {
+    std::env::var_os(variable)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(default))
+}
+
+fn load_supergraph() -> Supergraph {
+    let path = fixture_path("PLANNER_BENCH_SUPERGRAPH", "cli/fixtures/starstuff.graphql");
+    let schema = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {err}", path.display()));
+    Supergraph::new(&schema).expect("valid supergraph")
+}
+
+/// Returns the operations of the corpus, named after their file
+fn load_operations() -> Vec<(String, String)> {
+    let path = fixture_path("PLANNER_BENCH_OPERATIONS", "cli/fixtures/queries");
+    let mut operations = std::fs::read_dir(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {err}", path.display()))
+        .map(|entry| entry.expect("valid directory entry").path())
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let name = path
+                .file_name()
+                .expect("file name")
+                .to_string_lossy()
+                .to_string();
+            let source = std::fs::read_to_string(&path).expect("readable operation");
+            (name, source)
+        })
+        .collect::<Vec<_>>();
+    operations.sort();
+    operations
+}
+
+fn bench_planner_creation(c: &mut Criterion) {
+    let supergraph = load_supergraph();
+    c.bench_function("query_planner_new", |b| {
+        b.iter(|| QueryPlanner::new(&supergraph, QueryPlannerConfig::default()).unwrap())
+    });
+}
+
+fn bench_query_planning(c: &mut Criterion) {
+    let supergraph = load_supergraph();
+    let planner = QueryPlanner::new(&supergraph, QueryPlannerConfig::default()).unwrap();
+
+    let mut group = c.benchmark_group("build_query_plan");
+    for (name, source) in load_operations() {
+        let document =
+            match ExecutableDocument::parse_and_validate(supergraph.schema.schema(), source, &name)
+            {
+                Ok(document) => document,
+                Err(err) => {
+                    eprintln!("skipping invalid operation {name}: {err}");
+                    continue;
+                }
+            };
+        if let Err(err) = planner.build_query_plan(&document, None, Default::default()) {
+            eprintln!("skipping operation {name} that cannot be planned: {err}");
+            continue;
+        }
+        group.bench_function(&name, |b| {
+            b.iter(|| {
+                planner
+                    .build_query_plan(&document, None, Default::default())
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_planner_creation, bench_query_planning);
+criterion_main!(benches);
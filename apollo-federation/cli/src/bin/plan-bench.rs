@@ -0,0 +1,305 @@
+//! Measures the query planning latency and memory of an operation corpus against a supergraph,
+//! and writes a JSON report that can be compared with a previous one to detect regressions.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::query_plan::query_planner::QueryPlanner;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::Supergraph;
+use clap::Parser;
+use serde_json::json;
+use serde_json::Value;
+
+/// Counts the bytes allocated while planning
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+fn record_allocation(size: usize) {
+    TOTAL.fetch_add(size, Ordering::Relaxed);
+    let current = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                record_allocation(new_size - layout.size());
+            } else {
+                ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Memory used by one run, in bytes
+struct MemoryUsage {
+    /// Highest amount of memory allocated during the run, on top of what was allocated before
+    peak: usize,
+    /// Sum of all the allocations of the run
+    allocated: usize,
+}
+
+fn measure_memory<T>(f: impl FnOnce() -> T) -> (T, MemoryUsage) {
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    TOTAL.store(0, Ordering::Relaxed);
+    let result = f();
+    let usage = MemoryUsage {
+        peak: PEAK.load(Ordering::Relaxed).saturating_sub(baseline),
+        allocated: TOTAL.load(Ordering::Relaxed),
+    };
+    (result, usage)
+}
+
+/// CLI arguments. See <https://docs.rs/clap/latest/clap/_derive/index.html>
+#[derive(Parser)]
+struct Args {
+    /// The path to the supergraph schema file
+    supergraph_schema: PathBuf,
+    /// The path to the directory that contains all operations to plan
+    operations_dir: PathBuf,
+    /// Number of measured plannings of each operation
+    #[arg(long, default_value_t = 10)]
+    iterations: usize,
+    /// Number of plannings of each operation before measuring
+    #[arg(long, default_value_t = 2)]
+    warmup: usize,
+    /// Set the `debug.max_evaluated_plans` option.
+    #[arg(long)]
+    max_evaluated_plans: Option<NonZeroU32>,
+    /// Write the JSON report to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// A previous JSON report to compare the median latencies with
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+    /// Increase of the median latency, in percent, reported as a regression
+    #[arg(long, default_value_t = 10.0)]
+    threshold: f64,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns false if regressions were found against the baseline
+fn run(args: &Args) -> Result<bool, String> {
+    let schema = read(&args.supergraph_schema)?;
+    let supergraph = Supergraph::new(&schema).map_err(|err| err.to_string())?;
+    let mut config = QueryPlannerConfig::default();
+    if let Some(max_evaluated_plans) = args.max_evaluated_plans {
+        config.debug.max_evaluated_plans = max_evaluated_plans;
+    }
+
+    let now = Instant::now();
+    let (planner, planner_memory) =
+        measure_memory(|| QueryPlanner::new(&supergraph, config.clone()));
+    let planner_creation = now.elapsed();
+    let planner = planner.map_err(|err| err.to_string())?;
+
+    let mut entries = std::fs::read_dir(&args.operations_dir)
+        .map_err(|err| format!("cannot read {}: {err}", args.operations_dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+    entries.retain(|path| path.is_file());
+    entries.sort();
+
+    let mut operations = Vec::with_capacity(entries.len());
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        operations.push(bench_operation(
+            &supergraph,
+            &planner,
+            file_name,
+            read(&path)?,
+            args,
+        ));
+    }
+
+    let report = json!({
+        "supergraph": args.supergraph_schema.display().to_string(),
+        "iterations": args.iterations,
+        "planner_creation": {
+            "latency_ms": as_ms(planner_creation),
+            "memory": {
+                "peak_bytes": planner_memory.peak,
+                "allocated_bytes": planner_memory.allocated,
+            },
+        },
+        "operations": operations,
+    });
+    let report_str = serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?;
+    match &args.output {
+        Some(output) => std::fs::write(output, report_str)
+            .map_err(|err| format!("cannot write {}: {err}", output.display()))?,
+        None => println!("{report_str}"),
+    }
+
+    match &args.baseline {
+        Some(baseline) => {
+            let baseline: Value = serde_json::from_str(&read(baseline)?)
+                .map_err(|err| format!("invalid baseline report: {err}"))?;
+            Ok(compare(&baseline, &report, args.threshold))
+        }
+        None => Ok(true),
+    }
+}
+
+fn bench_operation(
+    supergraph: &Supergraph,
+    planner: &QueryPlanner,
+    file_name: String,
+    source: String,
+    args: &Args,
+) -> Value {
+    let document = match ExecutableDocument::parse_and_validate(
+        supergraph.schema.schema(),
+        source,
+        &file_name,
+    ) {
+        Ok(document) => document,
+        Err(err) => {
+            return json!({ "file_name": file_name, "error": err.to_string() });
+        }
+    };
+
+    for _ in 0..args.warmup {
+        let _ = planner.build_query_plan(&document, None, Default::default());
+    }
+
+    let mut latencies = Vec::with_capacity(args.iterations);
+    let mut memory = Vec::with_capacity(args.iterations);
+    let mut evaluated_plans = None;
+    for _ in 0..args.iterations {
+        let now = Instant::now();
+        let (plan, usage) =
+            measure_memory(|| planner.build_query_plan(&document, None, Default::default()));
+        latencies.push(now.elapsed());
+        memory.push(usage);
+        match plan {
+            Ok(plan) => {
+                evaluated_plans = Some(plan.statistics.evaluated_plan_count.into_inner());
+            }
+            Err(err) => {
+                return json!({ "file_name": file_name, "error": err.to_string() });
+            }
+        }
+    }
+    latencies.sort();
+
+    json!({
+        "file_name": file_name,
+        "evaluated_plans": evaluated_plans,
+        "latency_ms": {
+            "min": latencies.first().copied().map(as_ms),
+            "mean": mean(&latencies).map(as_ms),
+            "median": percentile(&latencies, 50.0).map(as_ms),
+            "p95": percentile(&latencies, 95.0).map(as_ms),
+            "max": latencies.last().copied().map(as_ms),
+        },
+        "memory": {
+            "peak_bytes": memory.iter().map(|usage| usage.peak).max(),
+            "allocated_bytes": memory.iter().map(|usage| usage.allocated).max(),
+        },
+    })
+}
+
+/// Prints the operations which median latency increased more than the threshold
+fn compare(baseline: &Value, report: &Value, threshold: f64) -> bool {
+    let medians = |report: &Value| -> HashMap<String, f64> {
+        report["operations"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|operation| {
+                Some((
+                    operation["file_name"].as_str()?.to_string(),
+                    operation["latency_ms"]["median"].as_f64()?,
+                ))
+            })
+            .collect()
+    };
+    let previous = medians(baseline);
+    let mut current = medians(report).into_iter().collect::<Vec<_>>();
+    current.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut no_regression = true;
+    for (file_name, median) in current {
+        if let Some(previous) = previous.get(&file_name) {
+            let increase = (median - previous) / previous * 100.0;
+            if increase > threshold {
+                no_regression = false;
+                eprintln!(
+                    "regression: {file_name} median latency went from {previous:.3}ms to {median:.3}ms (+{increase:.1}%)"
+                );
+            }
+        }
+    }
+    no_regression
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|err| format!("cannot read {}: {err}", path.display()))
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn mean(sorted: &[Duration]) -> Option<Duration> {
+    let count = u32::try_from(sorted.len())
+        .ok()
+        .filter(|count| *count > 0)?;
+    Some(sorted.iter().sum::<Duration>() / count)
+}
+
+fn percentile(sorted: &[Duration], percentile: f64) -> Option<Duration> {
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).copied()
+}
@@ -38,6 +38,9 @@ struct QueryPlannerArgs {
     /// Set the `debug.paths_limit` option.
     #[arg(long)]
     paths_limit: Option<u32>,
+    /// Set the `debug.max_fetch_nodes` option.
+    #[arg(long)]
+    max_fetch_nodes: Option<u32>,
 }
 
 /// CLI arguments. See <https://docs.rs/clap/latest/clap/_derive/index.html>
@@ -74,16 +77,27 @@ enum Command {
         schemas: Vec<PathBuf>,
         #[command(flatten)]
         planner: QueryPlannerArgs,
+        /// Print the query plan as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Validate one supergraph schema file or multiple subgraph schemas
     Validate {
         /// Path(s) to one supergraph schema file, `-` for stdin or multiple subgraph schemas.
         schemas: Vec<PathBuf>,
     },
+    /// Validate the federation directives of a subgraph schema
+    ValidateSubgraph {
+        /// Path to the subgraph schema file, or `-` for stdin
+        schema: PathBuf,
+    },
     /// Compose a supergraph schema from multiple subgraph schemas
     Compose {
         /// Path(s) to subgraph schemas.
         schemas: Vec<PathBuf>,
+        /// Output the supergraph schema and composition diagnostics as JSON.
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
     /// Extract subgraph schemas from a supergraph schema to stdout (or in a directory if specified)
     Extract {
@@ -112,6 +126,7 @@ impl QueryPlannerArgs {
             config.debug.max_evaluated_plans = max_evaluated_plans;
         }
         config.debug.paths_limit = self.paths_limit;
+        config.debug.max_fetch_nodes = self.max_fetch_nodes;
     }
 }
 
@@ -136,9 +151,11 @@ fn main() -> ExitCode {
             query,
             schemas,
             planner,
-        } => cmd_plan(&query, &schemas, planner),
+            json,
+        } => cmd_plan(&query, &schemas, planner, json),
         Command::Validate { schemas } => cmd_validate(&schemas),
-        Command::Compose { schemas } => cmd_compose(&schemas),
+        Command::ValidateSubgraph { schema } => return cmd_validate_subgraph(&schema),
+        Command::Compose { schemas, json } => return cmd_compose(&schemas, json),
         Command::Extract {
             supergraph_schema,
             destination_dir,
@@ -171,15 +188,15 @@ fn cmd_api_schema(file_paths: &[PathBuf], enable_defer: bool) -> Result<(), Fede
     let supergraph = load_supergraph(file_paths)?;
     let api_schema = supergraph.to_api_schema(apollo_federation::ApiSchemaOptions {
         include_defer: enable_defer,
-        include_stream: false,
+        ..Default::default()
     })?;
     println!("{}", api_schema.schema());
     Ok(())
 }
 
-/// Compose a supergraph from multiple subgraph files.
-fn compose_files(file_paths: &[PathBuf]) -> Result<apollo_federation::Supergraph, FederationError> {
-    let schemas: Vec<_> = file_paths
+/// Parse subgraph files, named after their file stem.
+fn parse_subgraph_files(file_paths: &[PathBuf]) -> Vec<subgraph::ValidSubgraph> {
+    file_paths
         .iter()
         .map(|pathname| {
             let doc_str = std::fs::read_to_string(pathname).unwrap();
@@ -187,7 +204,12 @@ fn compose_files(file_paths: &[PathBuf]) -> Result<apollo_federation::Supergraph
             let basename = pathname.file_stem().unwrap().to_str().unwrap();
             subgraph::Subgraph::parse_and_expand(basename, &url, &doc_str).unwrap()
         })
-        .collect();
+        .collect()
+}
+
+/// Compose a supergraph from multiple subgraph files.
+fn compose_files(file_paths: &[PathBuf]) -> Result<apollo_federation::Supergraph, FederationError> {
+    let schemas = parse_subgraph_files(file_paths);
     let supergraph = apollo_federation::Supergraph::compose(schemas.iter().collect()).unwrap();
     Ok(supergraph)
 }
@@ -239,6 +261,7 @@ fn cmd_plan(
     query_path: &Path,
     schema_paths: &[PathBuf],
     planner: QueryPlannerArgs,
+    json: bool,
 ) -> Result<(), FederationError> {
     let query = read_input(query_path);
     let supergraph = load_supergraph(schema_paths)?;
@@ -248,10 +271,12 @@ fn cmd_plan(
 
     let query_doc =
         ExecutableDocument::parse_and_validate(planner.api_schema().schema(), query, query_path)?;
-    print!(
-        "{}",
-        planner.build_query_plan(&query_doc, None, Default::default())?
-    );
+    let query_plan = planner.build_query_plan(&query_doc, None, Default::default())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&query_plan).unwrap());
+    } else {
+        print!("{query_plan}");
+    }
     Ok(())
 }
 
@@ -261,10 +286,69 @@ fn cmd_validate(file_paths: &[PathBuf]) -> Result<(), FederationError> {
     Ok(())
 }
 
-fn cmd_compose(file_paths: &[PathBuf]) -> Result<(), FederationError> {
-    let supergraph = compose_files(file_paths)?;
-    println!("{}", supergraph.schema.schema());
-    Ok(())
+fn cmd_validate_subgraph(file_path: &Path) -> ExitCode {
+    let doc_str = read_input(file_path);
+    let name = file_path.file_stem().unwrap().to_str().unwrap();
+    let url = format!("file://{}", file_path.to_str().unwrap());
+    let subgraph = match subgraph::Subgraph::parse_and_expand(name, &url, &doc_str) {
+        Ok(subgraph) => subgraph,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match subgraph.validate_federation_directives() {
+        Ok(()) => {
+            println!("[SUCCESS]");
+            ExitCode::SUCCESS
+        }
+        Err(errors) => {
+            for error in errors.errors {
+                eprintln!("[{}] {error}", error.code().definition().code());
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn cmd_compose(file_paths: &[PathBuf], json: bool) -> ExitCode {
+    let schemas = parse_subgraph_files(file_paths);
+    let result = apollo_federation::Supergraph::compose_with_diagnostics(schemas.iter().collect());
+    if json {
+        let output = match &result {
+            Ok((supergraph, hints)) => serde_json::json!({
+                "supergraph": supergraph.schema.schema().to_string(),
+                "errors": [],
+                "hints": hints,
+            }),
+            Err(failure) => serde_json::json!({
+                "supergraph": null,
+                "errors": failure.errors,
+                "hints": failure.composition_hints,
+            }),
+        };
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    } else {
+        match &result {
+            Ok((supergraph, hints)) => {
+                for hint in hints {
+                    eprintln!("{hint}");
+                }
+                println!("{}", supergraph.schema.schema());
+            }
+            Err(failure) => {
+                for hint in &failure.composition_hints {
+                    eprintln!("{hint}");
+                }
+                eprintln!("{failure}");
+            }
+        }
+    }
+    if result.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
 fn cmd_extract(file_path: &Path, dest: Option<&PathBuf>) -> Result<(), FederationError> {
@@ -1,10 +1,14 @@
 //! Implements API schema generation.
+use apollo_compiler::collections::IndexSet;
 use apollo_compiler::name;
 use apollo_compiler::schema::DirectiveDefinition;
 use apollo_compiler::schema::DirectiveLocation;
+use apollo_compiler::schema::ExtendedType;
 use apollo_compiler::schema::InputValueDefinition;
 use apollo_compiler::ty;
+use apollo_compiler::Name;
 use apollo_compiler::Node;
+use apollo_compiler::Schema;
 
 use crate::error::FederationError;
 use crate::link::inaccessible_spec_definition::InaccessibleSpecDefinition;
@@ -12,17 +16,31 @@ use crate::schema::position;
 use crate::schema::FederationSchema;
 use crate::schema::ValidFederationSchema;
 
-/// Remove types and directives imported by `@link`.
-fn remove_core_feature_elements(schema: &mut FederationSchema) -> Result<(), FederationError> {
+/// Remove types and directives imported by `@link`, except for the `retained_directives` and the
+/// types used by their arguments.
+fn remove_core_feature_elements(
+    schema: &mut FederationSchema,
+    retained_directives: &[Name],
+) -> Result<(), FederationError> {
     let Some(metadata) = schema.metadata() else {
         return Ok(());
     };
 
+    let retained_types = retained_directives
+        .iter()
+        .filter_map(|name| schema.schema().directive_definitions.get(name))
+        .flat_map(|definition| definition.arguments.iter())
+        .map(|argument| argument.ty.inner_named_type().clone())
+        .collect::<IndexSet<_>>();
+
     // First collect the things to be removed so we do not hold any immutable references
     // to the schema while mutating it below.
     let types_for_removal = schema
         .get_types()
-        .filter(|position| metadata.source_link_of_type(position.type_name()).is_some())
+        .filter(|position| {
+            metadata.source_link_of_type(position.type_name()).is_some()
+                && !retained_types.contains(position.type_name())
+        })
         .collect::<Vec<_>>();
 
     let directives_for_removal = schema
@@ -31,6 +49,7 @@ fn remove_core_feature_elements(schema: &mut FederationSchema) -> Result<(), Fed
             metadata
                 .source_link_of_directive(&position.directive_name)
                 .is_some()
+                && !retained_directives.contains(&position.directive_name)
         })
         .collect::<Vec<_>>();
 
@@ -108,6 +127,13 @@ fn remove_core_feature_elements(schema: &mut FederationSchema) -> Result<(), Fed
 pub struct ApiSchemaOptions {
     pub include_defer: bool,
     pub include_stream: bool,
+    /// Directives imported by `@link` that should be kept in the API schema, along with their
+    /// applications, by their name in the supergraph (for example `tag` or a directive passed
+    /// through composition with `@composeDirective`). Built-in directives like `@deprecated` and
+    /// `@specifiedBy` are always kept.
+    pub retain_directives: Vec<Name>,
+    /// Remove all descriptions from the API schema.
+    pub strip_descriptions: bool,
 }
 
 pub(crate) fn to_api_schema(
@@ -132,10 +158,14 @@ pub(crate) fn to_api_schema(
         inaccessible_spec.remove_inaccessible_elements(&mut api_schema)?;
     }
 
-    remove_core_feature_elements(&mut api_schema)?;
+    remove_core_feature_elements(&mut api_schema, &options.retain_directives)?;
 
     let mut schema = api_schema.into_inner();
 
+    if options.strip_descriptions {
+        strip_descriptions(&mut schema);
+    }
+
     if options.include_defer {
         schema
             .directive_definitions
@@ -153,6 +183,61 @@ pub(crate) fn to_api_schema(
     ValidFederationSchema::new(schema.validate()?)
 }
 
+/// Remove the descriptions of the schema definition, types, fields, arguments, enum values and
+/// directive definitions.
+fn strip_descriptions(schema: &mut Schema) {
+    fn strip_arguments(arguments: &mut [Node<InputValueDefinition>]) {
+        for argument in arguments {
+            argument.make_mut().description = None;
+        }
+    }
+
+    schema.schema_definition.make_mut().description = None;
+    for directive in schema.directive_definitions.values_mut() {
+        let directive = directive.make_mut();
+        directive.description = None;
+        strip_arguments(&mut directive.arguments);
+    }
+    for ty in schema.types.values_mut() {
+        match ty {
+            ExtendedType::Scalar(scalar) => scalar.make_mut().description = None,
+            ExtendedType::Object(object) => {
+                let object = object.make_mut();
+                object.description = None;
+                for field in object.fields.values_mut() {
+                    let field = field.make_mut();
+                    field.description = None;
+                    strip_arguments(&mut field.arguments);
+                }
+            }
+            ExtendedType::Interface(interface) => {
+                let interface = interface.make_mut();
+                interface.description = None;
+                for field in interface.fields.values_mut() {
+                    let field = field.make_mut();
+                    field.description = None;
+                    strip_arguments(&mut field.arguments);
+                }
+            }
+            ExtendedType::Union(union_) => union_.make_mut().description = None,
+            ExtendedType::Enum(enum_) => {
+                let enum_ = enum_.make_mut();
+                enum_.description = None;
+                for value in enum_.values.values_mut() {
+                    value.make_mut().description = None;
+                }
+            }
+            ExtendedType::InputObject(input_object) => {
+                let input_object = input_object.make_mut();
+                input_object.description = None;
+                for field in input_object.fields.values_mut() {
+                    field.make_mut().description = None;
+                }
+            }
+        }
+    }
+}
+
 fn defer_definition() -> Node<DirectiveDefinition> {
     Node::new(DirectiveDefinition {
         description: None,
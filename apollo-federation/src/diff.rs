@@ -0,0 +1,682 @@
+//! Structured diff between two versions of a schema, to check whether a schema update is safe to
+//! deploy.
+//!
+//! Supergraphs are compared through their API schemas, as this is what clients can observe.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use apollo_compiler::ast::DirectiveList;
+use apollo_compiler::ast::InputValueDefinition;
+use apollo_compiler::ast::Type;
+use apollo_compiler::ast::Value;
+use apollo_compiler::collections::IndexMap;
+use apollo_compiler::collections::IndexSet;
+use apollo_compiler::schema::Component;
+use apollo_compiler::schema::ComponentName;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::schema::FieldDefinition;
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+use apollo_compiler::Schema;
+use serde::Serialize;
+
+use crate::error::FederationError;
+use crate::Supergraph;
+
+/// How a change can affect existing clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChangeSeverity {
+    /// Existing operations may stop validating or receive differently shaped data
+    Breaking,
+    /// Existing operations keep working, but clients may see values they don't expect, like a new
+    /// enum value or a new possible type
+    Dangerous,
+    /// No existing client is affected
+    Safe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    TypeKindChanged,
+    FieldAdded,
+    FieldRemoved,
+    FieldTypeChanged,
+    FieldDeprecated,
+    ArgumentAdded,
+    ArgumentRemoved,
+    ArgumentTypeChanged,
+    ArgumentDefaultValueChanged,
+    InputFieldAdded,
+    InputFieldRemoved,
+    InputFieldTypeChanged,
+    InputFieldDefaultValueChanged,
+    EnumValueAdded,
+    EnumValueRemoved,
+    EnumValueDeprecated,
+    UnionMemberAdded,
+    UnionMemberRemoved,
+    InterfaceImplementationAdded,
+    InterfaceImplementationRemoved,
+    DirectiveAdded,
+    DirectiveRemoved,
+}
+
+/// A single difference between two schemas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaChange {
+    pub kind: ChangeKind,
+    pub severity: ChangeSeverity,
+    /// Schema coordinate of the changed element, e.g. `Query.products(first:)`
+    pub coordinate: String,
+    pub message: String,
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Lists the changes between the API schemas of two supergraphs.
+pub fn diff_supergraphs(
+    old: &Supergraph,
+    new: &Supergraph,
+) -> Result<Vec<SchemaChange>, FederationError> {
+    let old_api_schema = old.to_api_schema(Default::default())?;
+    let new_api_schema = new.to_api_schema(Default::default())?;
+    Ok(diff_schemas(
+        old_api_schema.schema(),
+        new_api_schema.schema(),
+    ))
+}
+
+/// Lists the changes between two schemas.
+pub fn diff_schemas(old: &Schema, new: &Schema) -> Vec<SchemaChange> {
+    let mut diff = Diff::default();
+    diff.types(old, new);
+    diff.directive_definitions(old, new);
+    diff.changes
+}
+
+#[derive(Default)]
+struct Diff {
+    changes: Vec<SchemaChange>,
+}
+
+impl Diff {
+    fn push(
+        &mut self,
+        kind: ChangeKind,
+        severity: ChangeSeverity,
+        coordinate: impl Into<String>,
+        message: String,
+    ) {
+        self.changes.push(SchemaChange {
+            kind,
+            severity,
+            coordinate: coordinate.into(),
+            message,
+        });
+    }
+
+    fn types(&mut self, old: &Schema, new: &Schema) {
+        for (name, old_type) in old.types.iter().filter(|(_, ty)| !ty.is_built_in()) {
+            let Some(new_type) = new.types.get(name) else {
+                self.push(
+                    ChangeKind::TypeRemoved,
+                    ChangeSeverity::Breaking,
+                    name.as_str(),
+                    format!("Type `{name}` was removed"),
+                );
+                continue;
+            };
+            match (old_type, new_type) {
+                (ExtendedType::Object(old_object), ExtendedType::Object(new_object)) => {
+                    self.implementations(
+                        name,
+                        &old_object.implements_interfaces,
+                        &new_object.implements_interfaces,
+                    );
+                    self.fields(name, &old_object.fields, &new_object.fields);
+                }
+                (
+                    ExtendedType::Interface(old_interface),
+                    ExtendedType::Interface(new_interface),
+                ) => {
+                    self.implementations(
+                        name,
+                        &old_interface.implements_interfaces,
+                        &new_interface.implements_interfaces,
+                    );
+                    self.fields(name, &old_interface.fields, &new_interface.fields);
+                }
+                (ExtendedType::Union(old_union), ExtendedType::Union(new_union)) => {
+                    for member in old_union.members.difference(&new_union.members) {
+                        self.push(
+                            ChangeKind::UnionMemberRemoved,
+                            ChangeSeverity::Breaking,
+                            name.as_str(),
+                            format!("Member `{}` was removed from union `{name}`", member.name),
+                        );
+                    }
+                    for member in new_union.members.difference(&old_union.members) {
+                        self.push(
+                            ChangeKind::UnionMemberAdded,
+                            ChangeSeverity::Dangerous,
+                            name.as_str(),
+                            format!("Member `{}` was added to union `{name}`", member.name),
+                        );
+                    }
+                }
+                (ExtendedType::Enum(old_enum), ExtendedType::Enum(new_enum)) => {
+                    for (value_name, old_value) in &old_enum.values {
+                        let coordinate = format!("{name}.{value_name}");
+                        match new_enum.values.get(value_name) {
+                            None => self.push(
+                                ChangeKind::EnumValueRemoved,
+                                ChangeSeverity::Breaking,
+                                coordinate.clone(),
+                                format!("Enum value `{coordinate}` was removed"),
+                            ),
+                            Some(new_value) => {
+                                if is_newly_deprecated(&old_value.directives, &new_value.directives)
+                                {
+                                    self.push(
+                                        ChangeKind::EnumValueDeprecated,
+                                        ChangeSeverity::Safe,
+                                        coordinate.clone(),
+                                        format!("Enum value `{coordinate}` was deprecated"),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    for value_name in new_enum.values.keys() {
+                        if !old_enum.values.contains_key(value_name) {
+                            let coordinate = format!("{name}.{value_name}");
+                            self.push(
+                                ChangeKind::EnumValueAdded,
+                                ChangeSeverity::Dangerous,
+                                coordinate.clone(),
+                                format!("Enum value `{coordinate}` was added"),
+                            );
+                        }
+                    }
+                }
+                (ExtendedType::InputObject(old_input), ExtendedType::InputObject(new_input)) => {
+                    self.input_fields(name, &old_input.fields, &new_input.fields);
+                }
+                (ExtendedType::Scalar(_), ExtendedType::Scalar(_)) => {}
+                _ => self.push(
+                    ChangeKind::TypeKindChanged,
+                    ChangeSeverity::Breaking,
+                    name.as_str(),
+                    format!(
+                        "Type `{name}` changed from {} to {}",
+                        kind_name(old_type),
+                        kind_name(new_type)
+                    ),
+                ),
+            }
+        }
+        for name in new.types.keys() {
+            if !old.types.contains_key(name) {
+                self.push(
+                    ChangeKind::TypeAdded,
+                    ChangeSeverity::Safe,
+                    name.as_str(),
+                    format!("Type `{name}` was added"),
+                );
+            }
+        }
+    }
+
+    fn implementations(
+        &mut self,
+        type_name: &Name,
+        old: &IndexSet<ComponentName>,
+        new: &IndexSet<ComponentName>,
+    ) {
+        for interface in old.difference(new) {
+            self.push(
+                ChangeKind::InterfaceImplementationRemoved,
+                ChangeSeverity::Breaking,
+                type_name.as_str(),
+                format!(
+                    "`{type_name}` no longer implements interface `{}`",
+                    interface.name
+                ),
+            );
+        }
+        for interface in new.difference(old) {
+            self.push(
+                ChangeKind::InterfaceImplementationAdded,
+                ChangeSeverity::Dangerous,
+                type_name.as_str(),
+                format!(
+                    "`{type_name}` now implements interface `{}`",
+                    interface.name
+                ),
+            );
+        }
+    }
+
+    fn fields(
+        &mut self,
+        type_name: &Name,
+        old: &IndexMap<Name, Component<FieldDefinition>>,
+        new: &IndexMap<Name, Component<FieldDefinition>>,
+    ) {
+        for (field_name, old_field) in old {
+            let coordinate = format!("{type_name}.{field_name}");
+            let Some(new_field) = new.get(field_name) else {
+                self.push(
+                    ChangeKind::FieldRemoved,
+                    ChangeSeverity::Breaking,
+                    coordinate.clone(),
+                    format!("Field `{coordinate}` was removed"),
+                );
+                continue;
+            };
+            if old_field.ty != new_field.ty {
+                let severity = if is_safe_output_type_change(&old_field.ty, &new_field.ty) {
+                    ChangeSeverity::Safe
+                } else {
+                    ChangeSeverity::Breaking
+                };
+                self.push(
+                    ChangeKind::FieldTypeChanged,
+                    severity,
+                    coordinate.clone(),
+                    format!(
+                        "Field `{coordinate}` changed type from `{}` to `{}`",
+                        old_field.ty, new_field.ty
+                    ),
+                );
+            }
+            if is_newly_deprecated(&old_field.directives, &new_field.directives) {
+                self.push(
+                    ChangeKind::FieldDeprecated,
+                    ChangeSeverity::Safe,
+                    coordinate.clone(),
+                    format!("Field `{coordinate}` was deprecated"),
+                );
+            }
+            self.arguments(&coordinate, &old_field.arguments, &new_field.arguments);
+        }
+        for field_name in new.keys() {
+            if !old.contains_key(field_name) {
+                let coordinate = format!("{type_name}.{field_name}");
+                self.push(
+                    ChangeKind::FieldAdded,
+                    ChangeSeverity::Safe,
+                    coordinate.clone(),
+                    format!("Field `{coordinate}` was added"),
+                );
+            }
+        }
+    }
+
+    fn arguments(
+        &mut self,
+        field_coordinate: &str,
+        old: &[Node<InputValueDefinition>],
+        new: &[Node<InputValueDefinition>],
+    ) {
+        for old_argument in old {
+            let coordinate = format!("{field_coordinate}({}:)", old_argument.name);
+            let Some(new_argument) = new.iter().find(|arg| arg.name == old_argument.name) else {
+                self.push(
+                    ChangeKind::ArgumentRemoved,
+                    ChangeSeverity::Breaking,
+                    coordinate.clone(),
+                    format!("Argument `{coordinate}` was removed"),
+                );
+                continue;
+            };
+            self.input_value_changes(
+                &coordinate,
+                "Argument",
+                old_argument,
+                new_argument,
+                ChangeKind::ArgumentTypeChanged,
+                ChangeKind::ArgumentDefaultValueChanged,
+            );
+        }
+        for new_argument in new {
+            if !old.iter().any(|arg| arg.name == new_argument.name) {
+                let coordinate = format!("{field_coordinate}({}:)", new_argument.name);
+                let (severity, message) = if is_required(new_argument) {
+                    (
+                        ChangeSeverity::Breaking,
+                        format!("Required argument `{coordinate}` was added"),
+                    )
+                } else {
+                    (
+                        ChangeSeverity::Safe,
+                        format!("Optional argument `{coordinate}` was added"),
+                    )
+                };
+                self.push(ChangeKind::ArgumentAdded, severity, coordinate, message);
+            }
+        }
+    }
+
+    fn input_fields(
+        &mut self,
+        type_name: &Name,
+        old: &IndexMap<Name, Component<InputValueDefinition>>,
+        new: &IndexMap<Name, Component<InputValueDefinition>>,
+    ) {
+        for (field_name, old_field) in old {
+            let coordinate = format!("{type_name}.{field_name}");
+            let Some(new_field) = new.get(field_name) else {
+                self.push(
+                    ChangeKind::InputFieldRemoved,
+                    ChangeSeverity::Breaking,
+                    coordinate.clone(),
+                    format!("Input field `{coordinate}` was removed"),
+                );
+                continue;
+            };
+            self.input_value_changes(
+                &coordinate,
+                "Input field",
+                old_field,
+                new_field,
+                ChangeKind::InputFieldTypeChanged,
+                ChangeKind::InputFieldDefaultValueChanged,
+            );
+        }
+        for (field_name, new_field) in new {
+            if !old.contains_key(field_name) {
+                let coordinate = format!("{type_name}.{field_name}");
+                let (severity, message) = if is_required(new_field) {
+                    (
+                        ChangeSeverity::Breaking,
+                        format!("Required input field `{coordinate}` was added"),
+                    )
+                } else {
+                    (
+                        ChangeSeverity::Dangerous,
+                        format!("Optional input field `{coordinate}` was added"),
+                    )
+                };
+                self.push(ChangeKind::InputFieldAdded, severity, coordinate, message);
+            }
+        }
+    }
+
+    fn input_value_changes(
+        &mut self,
+        coordinate: &str,
+        element: &str,
+        old: &InputValueDefinition,
+        new: &InputValueDefinition,
+        type_changed: ChangeKind,
+        default_value_changed: ChangeKind,
+    ) {
+        if old.ty != new.ty {
+            // Input positions accept more values when they become less strict
+            let severity = if is_safe_output_type_change(&new.ty, &old.ty) {
+                ChangeSeverity::Safe
+            } else {
+                ChangeSeverity::Breaking
+            };
+            self.push(
+                type_changed,
+                severity,
+                coordinate,
+                format!(
+                    "{element} `{coordinate}` changed type from `{}` to `{}`",
+                    old.ty, new.ty
+                ),
+            );
+        }
+        if old.default_value != new.default_value {
+            let display = |value: &Option<Node<Value>>| {
+                value
+                    .as_ref()
+                    .map_or_else(|| "none".to_string(), |value| format!("`{value}`"))
+            };
+            self.push(
+                default_value_changed,
+                ChangeSeverity::Dangerous,
+                coordinate,
+                format!(
+                    "{element} `{coordinate}` changed default value from {} to {}",
+                    display(&old.default_value),
+                    display(&new.default_value)
+                ),
+            );
+        }
+    }
+
+    fn directive_definitions(&mut self, old: &Schema, new: &Schema) {
+        for name in old.directive_definitions.keys() {
+            if !new.directive_definitions.contains_key(name) {
+                self.push(
+                    ChangeKind::DirectiveRemoved,
+                    ChangeSeverity::Breaking,
+                    format!("@{name}"),
+                    format!("Directive `@{name}` was removed"),
+                );
+            }
+        }
+        for name in new.directive_definitions.keys() {
+            if !old.directive_definitions.contains_key(name) {
+                self.push(
+                    ChangeKind::DirectiveAdded,
+                    ChangeSeverity::Safe,
+                    format!("@{name}"),
+                    format!("Directive `@{name}` was added"),
+                );
+            }
+        }
+    }
+}
+
+fn kind_name(ty: &ExtendedType) -> &'static str {
+    match ty {
+        ExtendedType::Scalar(_) => "a scalar",
+        ExtendedType::Object(_) => "an object type",
+        ExtendedType::Interface(_) => "an interface",
+        ExtendedType::Union(_) => "a union",
+        ExtendedType::Enum(_) => "an enum",
+        ExtendedType::InputObject(_) => "an input object type",
+    }
+}
+
+fn is_newly_deprecated(old: &DirectiveList, new: &DirectiveList) -> bool {
+    !old.has("deprecated") && new.has("deprecated")
+}
+
+fn is_required(input_value: &InputValueDefinition) -> bool {
+    input_value.ty.is_non_null() && input_value.default_value.is_none()
+}
+
+/// Whether values of the `new` type are always valid values of the `old` type, which is the case
+/// when `new` only adds non-null constraints.
+fn is_safe_output_type_change(old: &Type, new: &Type) -> bool {
+    match (old, new) {
+        (Type::Named(old), Type::Named(new) | Type::NonNullNamed(new))
+        | (Type::NonNullNamed(old), Type::NonNullNamed(new)) => old == new,
+        (Type::List(old), Type::List(new) | Type::NonNullList(new))
+        | (Type::NonNullList(old), Type::NonNullList(new)) => is_safe_output_type_change(old, new),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str) -> Vec<(ChangeKind, ChangeSeverity, String)> {
+        let old = Schema::parse_and_validate(old, "old.graphql").unwrap();
+        let new = Schema::parse_and_validate(new, "new.graphql").unwrap();
+        diff_schemas(&old, &new)
+            .into_iter()
+            .map(|change| (change.kind, change.severity, change.coordinate))
+            .collect()
+    }
+
+    #[test]
+    fn identical_schemas_have_no_changes() {
+        let schema = r#"
+            type Query { products(first: Int = 10): [Product!] }
+            type Product { id: ID! name: String }
+        "#;
+        assert_eq!(changes(schema, schema), []);
+    }
+
+    #[test]
+    fn classifies_field_changes() {
+        let old = r#"
+            type Query { products(first: Int = 10): [Product!] }
+            type Product { id: ID! name: String price: Int weight: Int }
+        "#;
+        let new = r#"
+            type Query { products(first: Int = 20, after: String!): [Product!]! }
+            type Product { id: ID! name: String! price: Float weight: Int @deprecated reviews: [String] }
+        "#;
+        assert_eq!(
+            changes(old, new),
+            [
+                (
+                    ChangeKind::FieldTypeChanged,
+                    ChangeSeverity::Safe,
+                    "Query.products".to_string()
+                ),
+                (
+                    ChangeKind::ArgumentDefaultValueChanged,
+                    ChangeSeverity::Dangerous,
+                    "Query.products(first:)".to_string()
+                ),
+                (
+                    ChangeKind::ArgumentAdded,
+                    ChangeSeverity::Breaking,
+                    "Query.products(after:)".to_string()
+                ),
+                (
+                    ChangeKind::FieldTypeChanged,
+                    ChangeSeverity::Safe,
+                    "Product.name".to_string()
+                ),
+                (
+                    ChangeKind::FieldTypeChanged,
+                    ChangeSeverity::Breaking,
+                    "Product.price".to_string()
+                ),
+                (
+                    ChangeKind::FieldDeprecated,
+                    ChangeSeverity::Safe,
+                    "Product.weight".to_string()
+                ),
+                (
+                    ChangeKind::FieldAdded,
+                    ChangeSeverity::Safe,
+                    "Product.reviews".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_type_changes() {
+        let old = r#"
+            type Query { a: A u: U e: E }
+            type A { id: ID }
+            type B { id: ID }
+            union U = A
+            enum E { ONE TWO }
+            input I { x: Int }
+            scalar Removed
+        "#;
+        let new = r#"
+            type Query { a: A u: U e: E }
+            type A { id: ID }
+            type B { id: ID }
+            union U = A | B
+            enum E { ONE THREE }
+            scalar I
+            scalar Added
+        "#;
+        assert_eq!(
+            changes(old, new),
+            [
+                (
+                    ChangeKind::UnionMemberAdded,
+                    ChangeSeverity::Dangerous,
+                    "U".to_string()
+                ),
+                (
+                    ChangeKind::EnumValueRemoved,
+                    ChangeSeverity::Breaking,
+                    "E.TWO".to_string()
+                ),
+                (
+                    ChangeKind::EnumValueAdded,
+                    ChangeSeverity::Dangerous,
+                    "E.THREE".to_string()
+                ),
+                (
+                    ChangeKind::TypeKindChanged,
+                    ChangeSeverity::Breaking,
+                    "I".to_string()
+                ),
+                (
+                    ChangeKind::TypeRemoved,
+                    ChangeSeverity::Breaking,
+                    "Removed".to_string()
+                ),
+                (
+                    ChangeKind::TypeAdded,
+                    ChangeSeverity::Safe,
+                    "Added".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_input_changes() {
+        let old = r#"
+            type Query { search(filter: Filter): [ID] }
+            input Filter { name: String! limit: Int }
+        "#;
+        let new = r#"
+            type Query { search(filter: Filter): [ID] }
+            input Filter { name: String limit: Int! category: String tag: String! }
+        "#;
+        assert_eq!(
+            changes(old, new),
+            [
+                (
+                    ChangeKind::InputFieldTypeChanged,
+                    ChangeSeverity::Safe,
+                    "Filter.name".to_string()
+                ),
+                (
+                    ChangeKind::InputFieldTypeChanged,
+                    ChangeSeverity::Breaking,
+                    "Filter.limit".to_string()
+                ),
+                (
+                    ChangeKind::InputFieldAdded,
+                    ChangeSeverity::Dangerous,
+                    "Filter.category".to_string()
+                ),
+                (
+                    ChangeKind::InputFieldAdded,
+                    ChangeSeverity::Breaking,
+                    "Filter.tag".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Write;
 use std::sync::LazyLock;
+use std::time::Duration;
 
 use apollo_compiler::validation::DiagnosticList;
 use apollo_compiler::validation::WithErrors;
@@ -296,6 +297,12 @@ pub enum SingleFederationError {
     InterfaceKeyMissingImplementationType { message: String },
     #[error("@defer is not supported on subscriptions")]
     DeferredSubscriptionUnsupported,
+    #[error("Query plan too large: planning the operation requires more than {max_fetch_nodes} subgraph fetches")]
+    QueryPlanTooLarge { max_fetch_nodes: u32 },
+    #[error("Query planning timed out after {timeout:?}")]
+    QueryPlanningTimeout { timeout: Duration },
+    #[error("Query planning was cancelled")]
+    QueryPlanningCancelled,
 }
 
 impl SingleFederationError {
@@ -487,6 +494,9 @@ impl SingleFederationError {
                 ErrorCode::InterfaceKeyMissingImplementationType
             }
             SingleFederationError::DeferredSubscriptionUnsupported => ErrorCode::Internal,
+            SingleFederationError::QueryPlanTooLarge { .. } => ErrorCode::QueryPlanTooLarge,
+            SingleFederationError::QueryPlanningTimeout { .. } => ErrorCode::QueryPlanningTimeout,
+            SingleFederationError::QueryPlanningCancelled => ErrorCode::QueryPlanningCancelled,
         }
     }
 }
@@ -1453,6 +1463,31 @@ static UNSUPPORTED_FEDERATION_DIRECTIVE: LazyLock<ErrorCodeDefinition> = LazyLoc
     )
 });
 
+static QUERY_PLAN_TOO_LARGE: LazyLock<ErrorCodeDefinition> = LazyLock::new(|| {
+    ErrorCodeDefinition::new(
+        "QUERY_PLAN_TOO_LARGE".to_owned(),
+        "The query plan of the operation has more subgraph fetches than the configured limit"
+            .to_owned(),
+        None,
+    )
+});
+
+static QUERY_PLANNING_TIMEOUT: LazyLock<ErrorCodeDefinition> = LazyLock::new(|| {
+    ErrorCodeDefinition::new(
+        "QUERY_PLANNING_TIMEOUT".to_owned(),
+        "Planning the operation took longer than the configured timeout".to_owned(),
+        None,
+    )
+});
+
+static QUERY_PLANNING_CANCELLED: LazyLock<ErrorCodeDefinition> = LazyLock::new(|| {
+    ErrorCodeDefinition::new(
+        "QUERY_PLANNING_CANCELLED".to_owned(),
+        "Planning the operation was cancelled before it completed".to_owned(),
+        None,
+    )
+});
+
 #[derive(Debug, strum_macros::EnumIter)]
 pub enum ErrorCode {
     Internal,
@@ -1534,6 +1569,9 @@ pub enum ErrorCode {
     InterfaceKeyMissingImplementationType,
     UnsupportedFederationVersion,
     UnsupportedFederationDirective,
+    QueryPlanTooLarge,
+    QueryPlanningTimeout,
+    QueryPlanningCancelled,
 }
 
 impl ErrorCode {
@@ -1633,6 +1671,9 @@ impl ErrorCode {
             }
             ErrorCode::UnsupportedFederationVersion => &UNSUPPORTED_FEDERATION_VERSION,
             ErrorCode::UnsupportedFederationDirective => &UNSUPPORTED_FEDERATION_DIRECTIVE,
+            ErrorCode::QueryPlanTooLarge => &QUERY_PLAN_TOO_LARGE,
+            ErrorCode::QueryPlanningTimeout => &QUERY_PLANNING_TIMEOUT,
+            ErrorCode::QueryPlanningCancelled => &QUERY_PLANNING_CANCELLED,
         }
     }
 }
@@ -27,6 +27,7 @@
 
 mod api_schema;
 mod compat;
+pub mod diff;
 mod display_helpers;
 pub mod error;
 pub mod link;
@@ -51,17 +52,37 @@ use crate::error::FederationError;
 use crate::error::SingleFederationError;
 use crate::link::context_spec_definition::ContextSpecDefinition;
 use crate::link::context_spec_definition::CONTEXT_VERSIONS;
+use crate::link::cost_spec_definition::COST_VERSIONS;
+use crate::link::inaccessible_spec_definition::INACCESSIBLE_VERSIONS;
 use crate::link::join_spec_definition::JoinSpecDefinition;
 use crate::link::link_spec_definition::LinkSpecDefinition;
+use crate::link::link_spec_definition::CORE_VERSIONS;
+use crate::link::link_spec_definition::LINK_VERSIONS;
 use crate::link::spec::Identity;
+use crate::link::spec::Url;
 use crate::link::spec_definition::SpecDefinitions;
 use crate::merge::merge_subgraphs;
+use crate::merge::CompositionDiagnostic;
 use crate::merge::MergeFailure;
 use crate::schema::ValidFederationSchema;
 use crate::subgraph::ValidSubgraph;
 pub use crate::supergraph::ValidFederationSubgraph;
 pub use crate::supergraph::ValidFederationSubgraphs;
 
+/// The URLs of the specs a supergraph can link for query planning, like
+/// `https://specs.apollo.dev/join/v0.5`.
+pub fn router_supported_supergraph_specs() -> Vec<Url> {
+    CORE_VERSIONS
+        .urls()
+        .chain(LINK_VERSIONS.urls())
+        .chain(JOIN_VERSIONS.urls())
+        .chain(CONTEXT_VERSIONS.urls())
+        .chain(INACCESSIBLE_VERSIONS.urls())
+        .chain(COST_VERSIONS.urls())
+        .cloned()
+        .collect()
+}
+
 pub(crate) type SupergraphSpecs = (
     &'static LinkSpecDefinition,
     &'static JoinSpecDefinition,
@@ -143,10 +164,24 @@ impl Supergraph {
     }
 
     pub fn compose(subgraphs: Vec<&ValidSubgraph>) -> Result<Self, MergeFailure> {
-        let schema = merge_subgraphs(subgraphs)?.schema;
-        Ok(Self {
-            schema: ValidFederationSchema::new(schema).map_err(Into::<MergeFailure>::into)?,
-        })
+        Self::compose_with_diagnostics(subgraphs).map(|(supergraph, _hints)| supergraph)
+    }
+
+    /// Composes a supergraph, also returning the composition hints.
+    ///
+    /// Errors and hints are [`CompositionDiagnostic`]s, carrying a stable code, the subgraph and
+    /// source locations involved, and can be serialized to JSON for tooling.
+    pub fn compose_with_diagnostics(
+        subgraphs: Vec<&ValidSubgraph>,
+    ) -> Result<(Self, Vec<CompositionDiagnostic>), MergeFailure> {
+        let merged = merge_subgraphs(subgraphs)?;
+        let supergraph = Self {
+            schema: ValidFederationSchema::new(merged.schema).map_err(|err| MergeFailure {
+                composition_hints: merged.composition_hints.clone(),
+                ..err.into()
+            })?,
+        };
+        Ok((supergraph, merged.composition_hints))
     }
 
     /// Generates an API Schema from this supergraph schema. The API Schema represents the combined
@@ -161,6 +196,12 @@ impl Supergraph {
     pub fn extract_subgraphs(&self) -> Result<ValidFederationSubgraphs, FederationError> {
         supergraph::extract_subgraphs_from_supergraph(&self.schema, None)
     }
+
+    /// Prints the supergraph SDL in a canonical form, with sorted definitions, so that
+    /// supergraphs composed from the same subgraphs print identically.
+    pub fn to_sdl(&self) -> String {
+        schema::printer::print_sdl(self.schema.schema())
+    }
 }
 
 const _: () = {
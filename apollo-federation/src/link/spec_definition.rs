@@ -167,4 +167,8 @@ impl<T: SpecDefinition> SpecDefinitions<T> {
     pub(crate) fn versions(&self) -> Keys<Version, T> {
         self.definitions.keys()
     }
+
+    pub(crate) fn urls(&self) -> impl Iterator<Item = &Url> {
+        self.definitions.values().map(|definition| definition.url())
+    }
 }
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::iter;
+use std::ops::Range;
 use std::sync::Arc;
 
 use apollo_compiler::ast::Argument;
@@ -11,10 +12,14 @@ use apollo_compiler::ast::DirectiveLocation;
 use apollo_compiler::ast::EnumValueDefinition;
 use apollo_compiler::ast::FieldDefinition;
 use apollo_compiler::ast::NamedType;
+use apollo_compiler::ast::Type;
 use apollo_compiler::ast::Value;
 use apollo_compiler::collections::IndexMap;
 use apollo_compiler::collections::IndexSet;
 use apollo_compiler::name;
+use apollo_compiler::parser::LineColumn;
+use apollo_compiler::parser::SourceMap;
+use apollo_compiler::parser::SourceSpan;
 use apollo_compiler::schema::Component;
 use apollo_compiler::schema::EnumType;
 use apollo_compiler::schema::ExtendedType;
@@ -33,8 +38,11 @@ use indexmap::map::Entry::Occupied;
 use indexmap::map::Entry::Vacant;
 use indexmap::map::Iter;
 use itertools::Itertools;
+use serde::Serialize;
 
+use crate::error::ErrorCode;
 use crate::error::FederationError;
+use crate::error::SingleFederationError;
 use crate::link::federation_spec_definition::FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC;
 use crate::link::federation_spec_definition::FEDERATION_FIELDS_ARGUMENT_NAME;
 use crate::link::federation_spec_definition::FEDERATION_FROM_ARGUMENT_NAME;
@@ -50,19 +58,248 @@ use crate::link::join_spec_definition::JOIN_OVERRIDE_LABEL_ARGUMENT_NAME;
 use crate::link::spec::Identity;
 use crate::link::spec::Version;
 use crate::link::spec_definition::SpecDefinition;
+use crate::link::Import;
+use crate::link::Link;
 use crate::link::LinksMetadata;
+use crate::schema::field_set::collect_target_fields_from_field_set;
 use crate::schema::ValidFederationSchema;
+use crate::subgraph::spec::COMPOSE_DIRECTIVE_NAME;
 use crate::subgraph::ValidSubgraph;
 use crate::ValidFederationSubgraph;
 use crate::ValidFederationSubgraphs;
 
-type MergeWarning = String;
-type MergeError = String;
+type MergeWarning = CompositionDiagnostic;
+type MergeError = CompositionDiagnostic;
+
+/// A composition error or hint, with enough context for tooling to point users at the
+/// definitions involved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionDiagnostic {
+    /// Stable code identifying the kind of diagnostic, e.g. `INVALID_SUBGRAPH_NAME`
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    /// The name of the subgraph the diagnostic originates from, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subgraph: Option<String>,
+    /// Locations of the definitions involved, in the subgraph schema sources
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<DiagnosticLocation>,
+    /// A suggestion on how to fix the issue
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// How much attention a diagnostic deserves. Composition fails on errors only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Severity {
+    Error,
+    Warn,
+    Info,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warn => f.write_str("warning"),
+            Severity::Info => f.write_str("info"),
+        }
+    }
+}
+
+/// Non-fatal issues found during composition.
+///
+/// Codes match the ones of the JS composition where an equivalent hint exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintCode {
+    /// A type, field or enum value has different descriptions across subgraphs
+    InconsistentDescription,
+    /// A field has compatible types across subgraphs, that differ by nullability, list wrapping
+    /// or subtyping
+    InconsistentButCompatibleFieldType,
+    /// An argument or input field has a default value in some subgraphs only
+    InconsistentDefaultValuePresence,
+    /// An argument or input field has different default values across subgraphs
+    InconsistentDefaultValue,
+    /// `@override` names a subgraph that is not part of the composition
+    FromSubgraphDoesNotExist,
+    /// An `@external` field is not used by any `@key`, `@requires` or `@provides`
+    UnusedExternal,
+    /// An executable directive is not defined in all subgraphs, or its definitions have no
+    /// location in common, so it is left out of the supergraph
+    InconsistentExecutableDirectivePresence,
+}
+
+impl HintCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            HintCode::InconsistentDescription => "INCONSISTENT_DESCRIPTION",
+            HintCode::InconsistentButCompatibleFieldType => {
+                "INCONSISTENT_BUT_COMPATIBLE_FIELD_TYPE"
+            }
+            HintCode::InconsistentDefaultValuePresence => "INCONSISTENT_DEFAULT_VALUE_PRESENCE",
+            HintCode::InconsistentDefaultValue => "INCONSISTENT_DEFAULT_VALUE",
+            HintCode::FromSubgraphDoesNotExist => "FROM_SUBGRAPH_DOES_NOT_EXIST",
+            HintCode::UnusedExternal => "UNUSED_EXTERNAL",
+            HintCode::InconsistentExecutableDirectivePresence => {
+                "INCONSISTENT_EXECUTABLE_DIRECTIVE_PRESENCE"
+            }
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            HintCode::InconsistentButCompatibleFieldType => Severity::Info,
+            HintCode::InconsistentDescription
+            | HintCode::InconsistentDefaultValuePresence
+            | HintCode::InconsistentDefaultValue
+            | HintCode::FromSubgraphDoesNotExist
+            | HintCode::UnusedExternal
+            | HintCode::InconsistentExecutableDirectivePresence => Severity::Warn,
+        }
+    }
+}
+
+/// A range of a schema source file, as 1-based line and column numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl CompositionDiagnostic {
+    fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            severity: Severity::Error,
+            message: message.into(),
+            subgraph: None,
+            locations: Vec::new(),
+            suggestion: None,
+        }
+    }
+
+    fn hint(hint: HintCode, message: impl Into<String>) -> Self {
+        Self {
+            severity: hint.severity(),
+            ..Self::new(hint.code(), message)
+        }
+    }
+
+    fn with_subgraph(mut self, subgraph: impl Into<String>) -> Self {
+        self.subgraph = Some(subgraph.into());
+        self
+    }
+
+    fn with_locations(mut self, locations: impl IntoIterator<Item = DiagnosticLocation>) -> Self {
+        self.locations.extend(locations);
+        self
+    }
+
+    fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl std::fmt::Display for CompositionDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: [{}]", self.severity, self.code)?;
+        if let Some(subgraph) = &self.subgraph {
+            write!(f, " [{subgraph}]")?;
+        }
+        write!(f, " {}", self.message)?;
+        for location in &self.locations {
+            write!(f, "\n  at ")?;
+            if let Some(file) = &location.file {
+                write!(f, "{file}:")?;
+            }
+            write!(f, "{}:{}", location.start_line, location.start_column)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n  suggestion: {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+impl DiagnosticLocation {
+    fn from_range(file: Option<String>, range: Range<LineColumn>) -> Self {
+        Self {
+            file,
+            start_line: range.start.line,
+            start_column: range.start.column,
+            end_line: range.end.line,
+            end_column: range.end.column,
+        }
+    }
+
+    fn from_span(span: Option<SourceSpan>, sources: &SourceMap) -> Option<Self> {
+        let span = span?;
+        let file = sources
+            .get(&span.file_id())
+            .map(|file| file.path().display().to_string());
+        let range = span.line_column_range(sources)?;
+        Some(Self::from_range(file, range))
+    }
+}
+
+fn diagnostics_from_error(error: SingleFederationError) -> Vec<CompositionDiagnostic> {
+    let code = error.code().definition().code().to_string();
+    match error {
+        SingleFederationError::InvalidGraphQL { diagnostics } => diagnostics
+            .iter()
+            .map(|diagnostic| {
+                CompositionDiagnostic::new(code.clone(), diagnostic.error.to_string())
+                    .with_locations(
+                        diagnostic
+                            .line_column_range()
+                            .map(|range| DiagnosticLocation::from_range(None, range)),
+                    )
+            })
+            .collect(),
+        error => vec![CompositionDiagnostic::new(code, error.to_string())],
+    }
+}
 
 struct Merger {
     errors: Vec<MergeError>,
     composition_hints: Vec<MergeWarning>,
     needs_inaccessible: bool,
+    /// Original subgraph names, by their `join__Graph` enum value
+    subgraph_names: IndexMap<Name, String>,
+    /// Sources of all the subgraph schemas, to resolve diagnostic locations
+    sources: SourceMap,
+    /// The subgraph, kind and location of the first definition of each type
+    type_definitions: IndexMap<Name, (Name, &'static str, Option<SourceSpan>)>,
+    /// The definitions of each field, argument and input field, checked once all the subgraphs
+    /// are merged since output types may differ by subtyping
+    field_definitions: IndexMap<String, Vec<(Name, Type, Option<SourceSpan>)>>,
+    /// The values of each enum, by subgraph
+    enum_definitions: IndexMap<Name, Vec<(Name, IndexSet<Name>, Option<SourceSpan>)>>,
+    /// Enums used as the type of an argument or input field in any subgraph
+    input_enums: IndexSet<Name>,
+    /// Directives kept in the supergraph with `@composeDirective`, by name
+    composed_directives: IndexMap<Name, ComposedDirective>,
+    /// The definitions of each executable directive, by subgraph
+    executable_directives: IndexMap<Name, Vec<(Name, Node<DirectiveDefinition>)>>,
+}
+
+/// The first definition of a directive composed with `@composeDirective`, and the `@link` it is
+/// imported from.
+struct ComposedDirective {
+    subgraph: Name,
+    definition: Node<DirectiveDefinition>,
+    link: Arc<Link>,
+    import: Option<Arc<Import>>,
 }
 
 pub struct MergeSuccess {
@@ -73,20 +310,28 @@ pub struct MergeSuccess {
 impl From<FederationError> for MergeFailure {
     fn from(err: FederationError) -> Self {
         // TODO: Consider an easier transition / interop between MergeFailure and FederationError
-        // TODO: This is most certainly not the right error kind. MergeFailure's
-        // errors need to be in an enum that could be matched on rather than a
-        // str.
+        let errors = match err {
+            FederationError::SingleFederationError(error) => vec![error],
+            FederationError::MultipleFederationErrors(errors) => errors.errors,
+            FederationError::AggregateFederationError(errors) => errors.causes,
+        };
         MergeFailure {
             schema: None,
-            errors: vec![err.to_string()],
+            errors: errors
+                .into_iter()
+                .flat_map(diagnostics_from_error)
+                .collect(),
             composition_hints: vec![],
         }
     }
 }
 
+#[derive(Serialize)]
 pub struct MergeFailure {
+    #[serde(skip)]
     pub schema: Option<Box<Schema>>,
     pub errors: Vec<MergeError>,
+    #[serde(rename = "hints")]
     pub composition_hints: Vec<MergeWarning>,
 }
 
@@ -99,6 +344,16 @@ impl Debug for MergeFailure {
     }
 }
 
+impl std::fmt::Display for MergeFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Composition failed:")?;
+        for error in &self.errors {
+            write!(f, "\n{error}")?;
+        }
+        Ok(())
+    }
+}
+
 pub fn merge_subgraphs(subgraphs: Vec<&ValidSubgraph>) -> Result<MergeSuccess, MergeFailure> {
     let mut merger = Merger::new();
     let mut federation_subgraphs = ValidFederationSubgraphs::new();
@@ -125,6 +380,14 @@ impl Merger {
             composition_hints: Vec::new(),
             errors: Vec::new(),
             needs_inaccessible: false,
+            subgraph_names: IndexMap::default(),
+            sources: Default::default(),
+            type_definitions: IndexMap::default(),
+            field_definitions: IndexMap::default(),
+            enum_definitions: IndexMap::default(),
+            input_enums: IndexSet::default(),
+            composed_directives: IndexMap::default(),
+            executable_directives: IndexMap::default(),
         }
     }
 
@@ -136,14 +399,32 @@ impl Merger {
         subgraphs.sort_by(|s1, s2| s1.name.cmp(&s2.name));
         let mut subgraphs_and_enum_values: Vec<(&ValidFederationSubgraph, Name)> = Vec::new();
         for subgraph in &subgraphs {
+            let sources = Arc::make_mut(&mut self.sources);
+            for (key, source) in subgraph.schema.schema().sources.iter() {
+                sources.entry(*key).or_insert_with(|| source.clone());
+            }
+
             // TODO: Implement JS codebase's name transform (which always generates a valid GraphQL
             // name and avoids collisions).
             if let Ok(subgraph_name) = Name::new(&subgraph.name.to_uppercase()) {
+                self.subgraph_names
+                    .insert(subgraph_name.clone(), subgraph.name.clone());
                 subgraphs_and_enum_values.push((subgraph, subgraph_name));
             } else {
-                self.errors.push(String::from(
-                    "Subgraph name couldn't be transformed into valid GraphQL name",
-                ));
+                self.errors.push(
+                    CompositionDiagnostic::new(
+                        ErrorCode::InvalidSubgraphName.definition().code(),
+                        format!(
+                            "Subgraph name \"{}\" couldn't be transformed into valid GraphQL name",
+                            subgraph.name
+                        ),
+                    )
+                    .with_subgraph(subgraph.name.clone())
+                    .with_suggestion(
+                        "Use a subgraph name made of ASCII letters, digits and underscores, \
+                         not starting with a digit",
+                    ),
+                );
             }
         }
         if !self.errors.is_empty() {
@@ -155,7 +436,6 @@ impl Merger {
         }
 
         let mut supergraph = Schema::new();
-        // TODO handle @compose
 
         // add core features
         // TODO verify federation versions across subgraphs
@@ -169,17 +449,24 @@ impl Merger {
                 sources.entry(*key).or_insert_with(|| source.clone());
             }
 
-            self.merge_schema(&mut supergraph, subgraph);
-            // TODO merge directives
+            self.merge_schema(&mut supergraph, subgraph_name, subgraph);
 
             let metadata = subgraph.schema.metadata();
-            let relevant_directives = DirectiveNames::for_metadata(&metadata);
+            let mut relevant_directives = DirectiveNames::for_metadata(&metadata);
+            relevant_directives.composed =
+                self.collect_composed_directives(subgraph_name, subgraph, &relevant_directives);
+            self.hint_unused_external_fields(subgraph_name, subgraph, &relevant_directives);
+            self.check_interface_keys(subgraph_name, subgraph, &relevant_directives);
+            self.collect_input_enums(subgraph.schema.schema());
 
             for (type_name, ty) in &subgraph.schema.schema().types {
                 if ty.is_built_in() || !is_mergeable_type(type_name) {
                     // skip built-ins and federation specific types
                     continue;
                 }
+                if !self.check_type_kind(subgraph_name, type_name, ty, &relevant_directives) {
+                    continue;
+                }
 
                 match ty {
                     ExtendedType::Enum(value) => self.merge_enum_type(
@@ -231,10 +518,15 @@ impl Merger {
                 }
             }
 
-            // merge executable directives
-            for (_, directive) in subgraph.schema.schema().directive_definitions.iter() {
-                if is_executable_directive(directive) {
-                    merge_directive(&mut supergraph.directive_definitions, directive);
+            // collect executable directives, merged once all the subgraphs are known to define them
+            for directive in subgraph.schema.schema().directive_definitions.values() {
+                if is_executable_directive(directive)
+                    && !relevant_directives.composed.contains(&directive.name)
+                {
+                    self.executable_directives
+                        .entry(directive.name.clone())
+                        .or_default()
+                        .push((subgraph_name.clone(), directive.clone()));
                 }
             }
         }
@@ -242,6 +534,11 @@ impl Merger {
         if self.needs_inaccessible {
             add_core_feature_inaccessible(&mut supergraph);
         }
+        self.merge_executable_directives(&mut supergraph, subgraphs_and_enum_values.len());
+        self.add_composed_directives(&mut supergraph);
+
+        self.check_field_types(&supergraph);
+        self.check_input_enum_values();
 
         if self.errors.is_empty() {
             // TODO: validate here and extend `MergeFailure` to propagate validation errors
@@ -259,24 +556,450 @@ impl Merger {
         }
     }
 
-    fn merge_descriptions<T: Eq + Clone>(&mut self, merged: &mut Option<T>, new: &Option<T>) {
+    fn merge_descriptions<T: Eq + Clone>(
+        &mut self,
+        merged: &mut Option<T>,
+        new: &Option<T>,
+        subgraph_name: &Name,
+        element: &str,
+        location: Option<SourceSpan>,
+    ) {
         match (&mut *merged, new) {
             (_, None) => {}
             (None, Some(_)) => merged.clone_from(new),
             (Some(a), Some(b)) => {
                 if a != b {
-                    // TODO add info about type and from/to subgraph
-                    self.composition_hints
-                        .push(String::from("conflicting descriptions"));
+                    self.push_hint(
+                        HintCode::InconsistentDescription,
+                        format!(
+                            "The description of {element} is inconsistent across subgraphs, \
+                             the first description encountered is used"
+                        ),
+                        subgraph_name,
+                        location,
+                        format!("Use the same description for {element} in all subgraphs"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn push_hint(
+        &mut self,
+        hint: HintCode,
+        message: String,
+        subgraph_name: &Name,
+        location: Option<SourceSpan>,
+        suggestion: String,
+    ) {
+        let hint = CompositionDiagnostic::hint(hint, message)
+            .with_subgraph(self.subgraph_display_name(subgraph_name))
+            .with_locations(DiagnosticLocation::from_span(location, &self.sources))
+            .with_suggestion(suggestion);
+        self.composition_hints.push(hint);
+    }
+
+    fn push_error(
+        &mut self,
+        code: ErrorCode,
+        message: String,
+        subgraph_name: &Name,
+        locations: impl IntoIterator<Item = Option<SourceSpan>>,
+        suggestion: String,
+    ) {
+        let locations: Vec<_> = locations
+            .into_iter()
+            .filter_map(|location| DiagnosticLocation::from_span(location, &self.sources))
+            .collect();
+        let error = CompositionDiagnostic::new(code.definition().code(), message)
+            .with_subgraph(self.subgraph_display_name(subgraph_name))
+            .with_locations(locations)
+            .with_suggestion(suggestion);
+        self.errors.push(error);
+    }
+
+    /// Records the type of a field, argument or input field in a subgraph, and hints when it
+    /// only differs from the merged type by nullability or list wrapping.
+    ///
+    /// Types with different named types are checked by [`Self::check_field_types`].
+    fn merge_field_type(
+        &mut self,
+        coordinate: &str,
+        merged: &Type,
+        new: &Type,
+        subgraph_name: &Name,
+        location: Option<SourceSpan>,
+    ) {
+        self.field_definitions
+            .entry(coordinate.to_string())
+            .or_default()
+            .push((subgraph_name.clone(), new.clone(), location));
+        if merged == new || merged.inner_named_type() != new.inner_named_type() {
+            return;
+        }
+        self.push_hint(
+            HintCode::InconsistentButCompatibleFieldType,
+            format!(
+                "Field `{coordinate}` has type `{new}` in this subgraph \
+                 but type `{merged}` in a previous subgraph, the latter is used"
+            ),
+            subgraph_name,
+            location,
+            format!("Use the same type for field `{coordinate}` in all subgraphs"),
+        );
+    }
+
+    /// Checks that a type has the same kind as in the previous subgraphs defining it, returning
+    /// `false` if it doesn't and must not be merged
+    fn check_type_kind(
+        &mut self,
+        subgraph_name: &Name,
+        type_name: &Name,
+        ty: &ExtendedType,
+        directive_names: &DirectiveNames,
+    ) -> bool {
+        let kind = match ty {
+            ExtendedType::Object(object)
+                if object.directives.has(&directive_names.interface_object) =>
+            {
+                "an interface type"
+            }
+            ExtendedType::Object(_) => "an object type",
+            ExtendedType::Interface(_) => "an interface type",
+            ExtendedType::Union(_) => "a union type",
+            ExtendedType::Enum(_) => "an enum type",
+            ExtendedType::InputObject(_) => "an input object type",
+            ExtendedType::Scalar(_) => "a scalar type",
+        };
+        let location = type_location(ty);
+        let (first_subgraph, first_kind, first_location) = self
+            .type_definitions
+            .entry(type_name.clone())
+            .or_insert_with(|| (subgraph_name.clone(), kind, location))
+            .clone();
+        if first_kind == kind {
+            return true;
+        }
+        self.push_error(
+            ErrorCode::TypeKindMismatch,
+            format!(
+                "Type `{type_name}` is defined as {kind} in subgraph \"{}\" \
+                 but as {first_kind} in subgraph \"{}\"",
+                self.subgraph_display_name(subgraph_name),
+                self.subgraph_display_name(&first_subgraph),
+            ),
+            subgraph_name,
+            [location, first_location],
+            format!("Define `{type_name}` with the same kind in all subgraphs"),
+        );
+        false
+    }
+
+    /// Checks that the named types of each field, argument and input field are the same in all
+    /// subgraphs, or for output fields, that they are subtypes of the merged type.
+    fn check_field_types(&mut self, supergraph: &Schema) {
+        let field_definitions = std::mem::take(&mut self.field_definitions);
+        for (coordinate, definitions) in &field_definitions {
+            let Some((first_subgraph, merged, first_location)) = definitions.first() else {
+                continue;
+            };
+            for (subgraph_name, ty, location) in &definitions[1..] {
+                let (merged_name, name) = (merged.inner_named_type(), ty.inner_named_type());
+                if merged_name == name {
+                    continue;
+                }
+                if is_subtype(supergraph, name, merged_name)
+                    || is_subtype(supergraph, merged_name, name)
+                {
+                    self.push_hint(
+                        HintCode::InconsistentButCompatibleFieldType,
+                        format!(
+                            "Field `{coordinate}` has type `{ty}` in this subgraph \
+                             but type `{merged}` in a previous subgraph, the latter is used"
+                        ),
+                        subgraph_name,
+                        *location,
+                        format!("Use the same type for field `{coordinate}` in all subgraphs"),
+                    );
+                    continue;
+                }
+                self.push_error(
+                    ErrorCode::FieldTypeMismatch,
+                    format!(
+                        "Field `{coordinate}` has incompatible types across subgraphs: \
+                         `{ty}` in subgraph \"{}\" but `{merged}` in subgraph \"{}\"",
+                        self.subgraph_display_name(subgraph_name),
+                        self.subgraph_display_name(first_subgraph),
+                    ),
+                    subgraph_name,
+                    [*location, *first_location],
+                    format!("Use the same type for field `{coordinate}` in all subgraphs"),
+                );
+            }
+        }
+    }
+
+    /// Collects the enums used as the type of an argument or input field
+    fn collect_input_enums(&mut self, schema: &Schema) {
+        let mut input_types: Vec<&Type> = Vec::new();
+        for ty in schema.types.values() {
+            match ty {
+                ExtendedType::InputObject(input) => {
+                    input_types.extend(input.fields.values().map(|field| &*field.ty));
+                }
+                ExtendedType::Object(object) => input_types.extend(
+                    object
+                        .fields
+                        .values()
+                        .flat_map(|field| field.arguments.iter().map(|arg| &*arg.ty)),
+                ),
+                ExtendedType::Interface(interface) => input_types.extend(
+                    interface
+                        .fields
+                        .values()
+                        .flat_map(|field| field.arguments.iter().map(|arg| &*arg.ty)),
+                ),
+                _ => {}
+            }
+        }
+        for ty in input_types {
+            let name = ty.inner_named_type();
+            if matches!(schema.types.get(name), Some(ExtendedType::Enum(_))) {
+                self.input_enums.insert(name.clone());
+            }
+        }
+    }
+
+    /// Checks that enums used as input types have the same values in all subgraphs, as a value
+    /// sent to a subgraph that doesn't define it would fail.
+    fn check_input_enum_values(&mut self) {
+        let enum_definitions = std::mem::take(&mut self.enum_definitions);
+        for (enum_name, definitions) in &enum_definitions {
+            if !self.input_enums.contains(enum_name) {
+                continue;
+            }
+            let values: IndexSet<&Name> = definitions
+                .iter()
+                .flat_map(|(_, values, _)| values)
+                .collect();
+            for value in values {
+                let missing: Vec<_> = definitions
+                    .iter()
+                    .filter(|(_, values, _)| !values.contains(value))
+                    .collect();
+                let Some((subgraph_name, _, _)) = missing.first() else {
+                    continue;
+                };
+                let missing_names = missing
+                    .iter()
+                    .map(|(name, _, _)| format!("\"{}\"", self.subgraph_display_name(name)))
+                    .join(", ");
+                self.push_error(
+                    ErrorCode::EnumValueMismatch,
+                    format!(
+                        "Enum type `{enum_name}` is used as an input type, \
+                         but value `{value}` is not defined in subgraphs {missing_names}"
+                    ),
+                    subgraph_name,
+                    missing.iter().map(|(_, _, location)| *location),
+                    format!(
+                        "Define `{enum_name}.{value}` in all the subgraphs defining `{enum_name}`"
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks that the implementations of an interface with a `@key` in a subgraph have the same
+    /// key, so that the interface can be resolved through any of them.
+    fn check_interface_keys(
+        &mut self,
+        subgraph_name: &Name,
+        subgraph: &ValidFederationSubgraph,
+        directive_names: &DirectiveNames,
+    ) {
+        let schema = subgraph.schema.schema();
+        let normalize = |fields: &str| fields.split_whitespace().join(" ");
+        for (interface_name, ty) in &schema.types {
+            let ExtendedType::Interface(interface) = ty else {
+                continue;
+            };
+            for key in interface.directives.get_all(&directive_names.key) {
+                let Some(fields) =
+                    directive_string_arg_value(key, &FEDERATION_FIELDS_ARGUMENT_NAME)
+                else {
+                    continue;
+                };
+                for (object_name, object) in schema.types.iter().filter_map(|(name, ty)| match ty {
+                    ExtendedType::Object(object)
+                        if object.implements_interfaces.contains(interface_name) =>
+                    {
+                        Some((name, object))
+                    }
+                    _ => None,
+                }) {
+                    let has_key = object.directives.get_all(&directive_names.key).any(|key| {
+                        directive_string_arg_value(key, &FEDERATION_FIELDS_ARGUMENT_NAME)
+                            .is_some_and(|object_fields| {
+                                normalize(object_fields) == normalize(fields)
+                            })
+                    });
+                    if has_key {
+                        continue;
+                    }
+                    self.push_error(
+                        ErrorCode::InterfaceKeyNotOnImplementation,
+                        format!(
+                            "Key `@key(fields: \"{fields}\")` on interface `{interface_name}` \
+                             should be resolvable on all its implementations, \
+                             but is not a key of `{object_name}`"
+                        ),
+                        subgraph_name,
+                        [object.location(), key.location()],
+                        format!("Add `@key(fields: \"{fields}\")` to `{object_name}`"),
+                    );
                 }
             }
         }
     }
 
-    fn merge_schema(&mut self, supergraph_schema: &mut Schema, subgraph: &ValidFederationSubgraph) {
+    fn hint_inconsistent_default_value(
+        &mut self,
+        coordinate: &str,
+        merged: &Option<Node<Value>>,
+        new: &Option<Node<Value>>,
+        subgraph_name: &Name,
+        location: Option<SourceSpan>,
+    ) {
+        let message = match (merged, new) {
+            (Some(merged), Some(new)) if merged != new => format!(
+                "`{coordinate}` has default value `{}` in this subgraph \
+                 but `{}` in a previous subgraph, the latter is used",
+                &**new, &**merged,
+            ),
+            (Some(_), None) | (None, Some(_)) => {
+                format!("`{coordinate}` has a default value in some subgraphs but not in others")
+            }
+            _ => return,
+        };
+        let hint = if merged.is_some() && new.is_some() {
+            HintCode::InconsistentDefaultValue
+        } else {
+            HintCode::InconsistentDefaultValuePresence
+        };
+        self.push_hint(
+            hint,
+            message,
+            subgraph_name,
+            location,
+            format!("Use the same default value for `{coordinate}` in all subgraphs"),
+        );
+    }
+
+    /// Hints about `@external` fields that no `@key`, `@requires` or `@provides` of the subgraph
+    /// reference, as they have no effect.
+    fn hint_unused_external_fields(
+        &mut self,
+        subgraph_name: &Name,
+        subgraph: &ValidFederationSubgraph,
+        directive_names: &DirectiveNames,
+    ) {
+        let schema = subgraph.schema.schema();
+        let mut used_fields: IndexSet<(Name, Name)> = IndexSet::default();
+        let mut collect_used_fields = |parent_type_name: &Name, directive: &Directive| {
+            let Some(field_set) =
+                directive_string_arg_value(directive, &FEDERATION_FIELDS_ARGUMENT_NAME)
+            else {
+                return;
+            };
+            // Invalid field sets are reported by subgraph validation
+            if let Ok(fields) =
+                collect_target_fields_from_field_set(schema, parent_type_name.clone(), field_set)
+            {
+                used_fields.extend(
+                    fields
+                        .iter()
+                        .map(|field| (field.type_name().clone(), field.field_name().clone())),
+                );
+            }
+        };
+        for (type_name, ty) in &schema.types {
+            for key in ty.directives().get_all(&directive_names.key) {
+                collect_used_fields(type_name, key);
+            }
+            let fields = match ty {
+                ExtendedType::Object(object) => &object.fields,
+                ExtendedType::Interface(interface) => &interface.fields,
+                _ => continue,
+            };
+            for field in fields.values() {
+                for requires in field.directives.get_all(&directive_names.requires) {
+                    collect_used_fields(type_name, requires);
+                }
+                for provides in field.directives.get_all(&directive_names.provides) {
+                    collect_used_fields(field.ty.inner_named_type(), provides);
+                }
+            }
+        }
+
+        for (type_name, ty) in &schema.types {
+            let ExtendedType::Object(object) = ty else {
+                continue;
+            };
+            for (field_name, field) in &object.fields {
+                if !field.directives.has(&directive_names.external)
+                    || used_fields.contains(&(type_name.clone(), field_name.clone()))
+                {
+                    continue;
+                }
+                // Fields implementing an interface field are needed for the type to be valid
+                let implements_interface_field = object.implements_interfaces.iter().any(|intf| {
+                    schema
+                        .get_interface(intf)
+                        .is_some_and(|intf| intf.fields.contains_key(field_name))
+                });
+                if implements_interface_field {
+                    continue;
+                }
+                self.push_hint(
+                    HintCode::UnusedExternal,
+                    format!(
+                        "Field `{type_name}.{field_name}` is marked @external \
+                         but is not used by any @key, @requires or @provides"
+                    ),
+                    subgraph_name,
+                    field.location(),
+                    format!(
+                        "Remove @external from `{type_name}.{field_name}`, \
+                         or the field itself if this subgraph does not resolve it"
+                    ),
+                );
+            }
+        }
+    }
+
+    fn subgraph_display_name(&self, subgraph_name: &Name) -> String {
+        self.subgraph_names
+            .get(subgraph_name)
+            .cloned()
+            .unwrap_or_else(|| subgraph_name.to_string())
+    }
+
+    fn merge_schema(
+        &mut self,
+        supergraph_schema: &mut Schema,
+        subgraph_name: &Name,
+        subgraph: &ValidFederationSubgraph,
+    ) {
         let supergraph_def = &mut supergraph_schema.schema_definition.make_mut();
         let subgraph_def = &subgraph.schema.schema().schema_definition;
-        self.merge_descriptions(&mut supergraph_def.description, &subgraph_def.description);
+        self.merge_descriptions(
+            &mut supergraph_def.description,
+            &subgraph_def.description,
+            subgraph_name,
+            "the schema definition",
+            subgraph_def.location(),
+        );
 
         if subgraph_def.query.is_some() {
             supergraph_def.query.clone_from(&subgraph_def.query);
@@ -304,20 +1027,35 @@ impl Merger {
     ) {
         let existing_type = types
             .entry(enum_name.clone())
-            .or_insert(copy_enum_type(enum_name, enum_type));
+            .or_insert(copy_enum_type(enum_name.clone(), enum_type));
+
+        self.enum_definitions
+            .entry(enum_name.clone())
+            .or_default()
+            .push((
+                subgraph_name.clone(),
+                enum_type.values.keys().cloned().collect(),
+                enum_type.location(),
+            ));
 
         if let ExtendedType::Enum(e) = existing_type {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             e.make_mut().directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 metadata,
                 &mut e.make_mut().directives,
                 &enum_type.directives,
             );
 
-            self.merge_descriptions(&mut e.make_mut().description, &enum_type.description);
+            self.merge_descriptions(
+                &mut e.make_mut().description,
+                &enum_type.description,
+                &subgraph_name,
+                &format!("type `{enum_name}`"),
+                enum_type.location(),
+            );
 
             // TODO we need to merge those fields LAST so we know whether enum is used as input/output/both as different merge rules will apply
             // below logic only works for output enums
@@ -331,9 +1069,15 @@ impl Merger {
                         description: None,
                         directives: Default::default(),
                     }));
-                self.merge_descriptions(&mut ev.make_mut().description, &enum_value.description);
+                self.merge_descriptions(
+                    &mut ev.make_mut().description,
+                    &enum_value.description,
+                    &subgraph_name,
+                    &format!("enum value `{enum_name}.{enum_value_name}`"),
+                    enum_value.location(),
+                );
 
-                self.add_inaccessible(
+                self.merge_applied_directives(
                     metadata,
                     &mut ev.make_mut().directives,
                     &enum_value.directives,
@@ -362,17 +1106,21 @@ impl Merger {
         input_object_name: NamedType,
         input_object: &Node<InputObjectType>,
     ) {
-        let existing_type = types
-            .entry(input_object_name.clone())
-            .or_insert(copy_input_object_type(input_object_name, input_object));
+        let existing_type =
+            types
+                .entry(input_object_name.clone())
+                .or_insert(copy_input_object_type(
+                    input_object_name.clone(),
+                    input_object,
+                ));
 
         if let ExtendedType::InputObject(obj) = existing_type {
             let join_type_directives =
-                join_type_applied_directive(subgraph_name, iter::empty(), false);
+                join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_object.directives,
                 &input_object.directives,
@@ -386,15 +1134,27 @@ impl Merger {
                         // TODO warning - mismatch on input fields
                     }
                     Occupied(mut i) => {
-                        self.add_inaccessible(
+                        self.merge_field_type(
+                            &format!("{input_object_name}.{field_name}"),
+                            &i.get().ty,
+                            &field.ty,
+                            &subgraph_name,
+                            field.location(),
+                        );
+                        self.merge_applied_directives(
                             directive_names,
                             &mut i.get_mut().make_mut().directives,
                             &field.directives,
                         );
+                        self.hint_inconsistent_default_value(
+                            &format!("{input_object_name}.{field_name}"),
+                            &i.get().default_value,
+                            &field.default_value,
+                            &subgraph_name,
+                            field.location(),
+                        );
                         // merge_options(&i.get_mut().description, &field.description);
                         // TODO check description
-                        // TODO check type
-                        // TODO check default value
                         // TODO process directives
                     }
                 }
@@ -414,16 +1174,16 @@ impl Merger {
     ) {
         let existing_type = types
             .entry(interface_name.clone())
-            .or_insert(copy_interface_type(interface_name, interface));
+            .or_insert(copy_interface_type(interface_name.clone(), interface));
 
         if let ExtendedType::Interface(intf) = existing_type {
             let key_directives = interface.directives.get_all(&directive_names.key);
             let join_type_directives =
-                join_type_applied_directive(subgraph_name, key_directives, false);
+                join_type_applied_directive(subgraph_name.clone(), key_directives, false);
             let mutable_intf = intf.make_mut();
             mutable_intf.directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_intf.directives,
                 &interface.directives,
@@ -434,6 +1194,13 @@ impl Merger {
                 match existing_field {
                     Vacant(i) => {
                         // TODO warning mismatch missing fields
+                        self.merge_field_type(
+                            &format!("{interface_name}.{field_name}"),
+                            &field.ty,
+                            &field.ty,
+                            &subgraph_name,
+                            field.location(),
+                        );
                         let f = i.insert(Component::new(FieldDefinition {
                             name: field.name.clone(),
                             description: field.description.clone(),
@@ -442,15 +1209,21 @@ impl Merger {
                             directives: Default::default(),
                         }));
 
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut f.make_mut().directives,
                             &field.directives,
                         );
                     }
-                    Occupied(_i) => {
+                    Occupied(i) => {
+                        self.merge_field_type(
+                            &format!("{interface_name}.{field_name}"),
+                            &i.get().ty,
+                            &field.ty,
+                            &subgraph_name,
+                            field.location(),
+                        );
                         // TODO check description
-                        // TODO check type
                         // TODO check default value
                         // TODO process directives
                     }
@@ -484,8 +1257,14 @@ impl Merger {
                 join_type_applied_directive(subgraph_name.clone(), key_directives, false);
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
-            self.merge_descriptions(&mut mutable_object.description, &object.description);
-            self.add_inaccessible(
+            self.merge_descriptions(
+                &mut mutable_object.description,
+                &object.description,
+                &subgraph_name,
+                &format!("type `{object_name}`"),
+                object.location(),
+            );
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_object.directives,
                 &object.directives,
@@ -509,38 +1288,72 @@ impl Merger {
                 let existing_field = mutable_object.fields.entry(field_name.clone());
                 let supergraph_field = match existing_field {
                     Occupied(f) => {
-                        // check description
-                        // check type
-                        // check args
+                        self.merge_field_type(
+                            &format!("{object_name}.{field_name}"),
+                            &f.get().ty,
+                            &field.ty,
+                            &subgraph_name,
+                            field.location(),
+                        );
                         f.into_mut()
                     }
-                    Vacant(f) => f.insert(Component::new(FieldDefinition {
-                        name: field.name.clone(),
-                        description: field.description.clone(),
-                        arguments: vec![],
-                        directives: Default::default(),
-                        ty: field.ty.clone(),
-                    })),
+                    Vacant(f) => {
+                        self.merge_field_type(
+                            &format!("{object_name}.{field_name}"),
+                            &field.ty,
+                            &field.ty,
+                            &subgraph_name,
+                            field.location(),
+                        );
+                        f.insert(Component::new(FieldDefinition {
+                            name: field.name.clone(),
+                            description: field.description.clone(),
+                            arguments: vec![],
+                            directives: Default::default(),
+                            ty: field.ty.clone(),
+                        }))
+                    }
                 };
                 self.merge_descriptions(
                     &mut supergraph_field.make_mut().description,
                     &field.description,
+                    &subgraph_name,
+                    &format!("field `{object_name}.{field_name}`"),
+                    field.location(),
                 );
 
-                self.add_inaccessible(
+                self.merge_applied_directives(
                     directive_names,
                     &mut supergraph_field.make_mut().directives,
                     &field.directives,
                 );
 
                 for arg in field.arguments.iter() {
+                    let coordinate = format!("{object_name}.{field_name}({}:)", arg.name);
                     let arguments_to_merge = &mut supergraph_field.make_mut().arguments;
                     let argument_to_merge = arguments_to_merge
                         .iter_mut()
                         .find_map(|a| (a.name == arg.name).then(|| a.make_mut()));
 
+                    let merged_ty = argument_to_merge
+                        .as_ref()
+                        .map_or(&arg.ty, |argument| &argument.ty);
+                    self.merge_field_type(
+                        &coordinate,
+                        merged_ty,
+                        &arg.ty,
+                        &subgraph_name,
+                        arg.location(),
+                    );
                     if let Some(argument) = argument_to_merge {
-                        self.add_inaccessible(
+                        self.hint_inconsistent_default_value(
+                            &coordinate,
+                            &argument.default_value,
+                            &arg.default_value,
+                            &subgraph_name,
+                            arg.location(),
+                        );
+                        self.merge_applied_directives(
                             directive_names,
                             &mut argument.directives,
                             &arg.directives,
@@ -554,7 +1367,7 @@ impl Merger {
                             default_value: arg.default_value.clone(),
                         };
 
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut argument.directives,
                             &arg.directives,
@@ -587,6 +1400,24 @@ impl Merger {
                         overrides_from.map(|from| (from, overrides_label))
                     });
 
+                if let Some((from, _)) = overrides_directive_option {
+                    if !self.subgraph_names.values().any(|name| name == from) {
+                        self.push_hint(
+                            HintCode::FromSubgraphDoesNotExist,
+                            format!(
+                                "Field `{object_name}.{field_name}` overrides subgraph \"{from}\" \
+                                 which is not part of the composition"
+                            ),
+                            &subgraph_name,
+                            field.location(),
+                            format!(
+                                "Remove @override from `{object_name}.{field_name}` \
+                                 if \"{from}\" was removed, or fix the subgraph name"
+                            ),
+                        );
+                    }
+                }
+
                 let external_field = field
                     .directives
                     .get_all(&directive_names.external)
@@ -636,7 +1467,7 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             u.make_mut().directives.extend(join_type_directives);
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut u.make_mut().directives,
                 &union.directives,
@@ -678,7 +1509,7 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             s.make_mut().directives.extend(join_type_directives);
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut s.make_mut().directives,
                 &ty.directives,
@@ -688,8 +1519,10 @@ impl Merger {
         }
     }
 
+    /// Copies `@inaccessible` and the directives composed with `@composeDirective` applied in a
+    /// subgraph to the supergraph.
     // generic so it handles ast::DirectiveList and schema::DirectiveList
-    fn add_inaccessible<I>(
+    fn merge_applied_directives<I>(
         &mut self,
         directive_names: &DirectiveNames,
         new_directives: &mut Vec<I>,
@@ -714,6 +1547,295 @@ impl Merger {
                 .into(),
             );
         }
+
+        for directive in original_directives {
+            let name = &directive.as_ref().name;
+            let Some(composed) = directive_names
+                .composed
+                .contains(name)
+                .then(|| self.composed_directives.get(name))
+                .flatten()
+            else {
+                continue;
+            };
+            // identical applications from several subgraphs are only kept once, and
+            // non-repeatable directives keep their first application
+            let already_applied = new_directives.iter().any(|new| {
+                new.as_ref().name == *name
+                    && (!composed.definition.repeatable || new.as_ref() == directive.as_ref())
+            });
+            if !already_applied {
+                new_directives.push(directive.clone());
+            }
+        }
+    }
+
+    /// Validates the directives a subgraph keeps in the supergraph with `@composeDirective`,
+    /// records their definitions and returns their names.
+    ///
+    /// Composed directives must be imported with `@link` from a spec other than federation and
+    /// link, and have the same definition in all the subgraphs composing them.
+    fn collect_composed_directives(
+        &mut self,
+        subgraph_name: &Name,
+        subgraph: &ValidFederationSubgraph,
+        directive_names: &DirectiveNames,
+    ) -> IndexSet<Name> {
+        let schema = subgraph.schema.schema();
+        let metadata = subgraph.schema.metadata();
+        let mut composed = IndexSet::default();
+        for application in schema
+            .schema_definition
+            .directives
+            .get_all(&directive_names.compose_directive)
+        {
+            let Some(name) = directive_string_arg_value(application, &name!("name")) else {
+                continue;
+            };
+            let Some(definition) = name
+                .strip_prefix('@')
+                .and_then(|name| schema.directive_definitions.get(name))
+            else {
+                self.push_error(
+                    ErrorCode::DirectiveCompositionError,
+                    format!(
+                        "Could not find a definition for directive \"{name}\" \
+                         passed to @composeDirective"
+                    ),
+                    subgraph_name,
+                    [application.location()],
+                    "Pass the name of a directive defined in the subgraph, prefixed with \"@\""
+                        .to_string(),
+                );
+                continue;
+            };
+            let linked = metadata
+                .and_then(|metadata| metadata.source_link_of_directive(&definition.name))
+                .filter(|linked| {
+                    linked.link.url.identity != Identity::federation_identity()
+                        && linked.link.url.identity != Identity::link_identity()
+                });
+            let Some(linked) = linked else {
+                self.push_error(
+                    ErrorCode::DirectiveCompositionError,
+                    format!(
+                        "Directive \"{name}\" passed to @composeDirective \
+                         is not imported from a linked spec"
+                    ),
+                    subgraph_name,
+                    [application.location(), definition.location()],
+                    format!(
+                        "Import \"{name}\" with @link from the spec defining it, \
+                         federation and link directives cannot be composed"
+                    ),
+                );
+                continue;
+            };
+            composed.insert(definition.name.clone());
+
+            match self.composed_directives.get(&definition.name) {
+                Some(first)
+                    if first.link.url.identity != linked.link.url.identity
+                        || !same_directive_definition(&first.definition, definition) =>
+                {
+                    let first_subgraph = self.subgraph_display_name(&first.subgraph);
+                    let first_location = first.definition.location();
+                    self.push_error(
+                        ErrorCode::DirectiveCompositionError,
+                        format!(
+                            "Composed directive \"{name}\" has a different definition \
+                             than in subgraph \"{first_subgraph}\""
+                        ),
+                        subgraph_name,
+                        [definition.location(), first_location],
+                        format!(
+                            "Use the same definition for \"{name}\", \
+                             imported from the same spec, in all subgraphs"
+                        ),
+                    );
+                }
+                Some(first) => {
+                    // the supergraph links the most recent version of the spec
+                    if first.link.url.version < linked.link.url.version {
+                        if let Some(first) = self.composed_directives.get_mut(&definition.name) {
+                            first.link = linked.link;
+                            first.import = linked.import;
+                        }
+                    }
+                }
+                None => {
+                    self.composed_directives.insert(
+                        definition.name.clone(),
+                        ComposedDirective {
+                            subgraph: subgraph_name.clone(),
+                            definition: definition.clone(),
+                            link: linked.link,
+                            import: linked.import,
+                        },
+                    );
+                }
+            }
+        }
+        composed
+    }
+
+    /// Adds the executable directives defined in all the subgraphs to the supergraph, with the
+    /// executable locations and arguments their definitions have in common.
+    fn merge_executable_directives(&mut self, supergraph: &mut Schema, subgraph_count: usize) {
+        for (name, definitions) in std::mem::take(&mut self.executable_directives) {
+            if self.composed_directives.contains_key(&name) {
+                continue;
+            }
+            let (first_subgraph, first) = &definitions[0];
+            if definitions.len() < subgraph_count {
+                self.push_hint(
+                    HintCode::InconsistentExecutableDirectivePresence,
+                    format!(
+                        "Executable directive \"@{name}\" is not defined in all subgraphs, \
+                         it is left out of the supergraph"
+                    ),
+                    first_subgraph,
+                    first.location(),
+                    format!("Define \"@{name}\" in all subgraphs to use it in operations"),
+                );
+                continue;
+            }
+
+            let mut merged = first.as_ref().clone();
+            merged.repeatable = definitions.iter().all(|(_, other)| other.repeatable);
+            merged.locations.retain(|location| {
+                EXECUTABLE_DIRECTIVE_LOCATIONS.contains(location)
+                    && definitions
+                        .iter()
+                        .all(|(_, other)| other.locations.contains(location))
+            });
+            merged.arguments.retain(|argument| {
+                definitions.iter().all(|(_, other)| {
+                    other
+                        .arguments
+                        .iter()
+                        .any(|other| other.name == argument.name)
+                })
+            });
+            if merged.locations.is_empty() {
+                self.push_hint(
+                    HintCode::InconsistentExecutableDirectivePresence,
+                    format!(
+                        "Executable directive \"@{name}\" has no executable location \
+                         common to all subgraphs, it is left out of the supergraph"
+                    ),
+                    first_subgraph,
+                    first.location(),
+                    format!("Use the same locations for \"@{name}\" in all subgraphs"),
+                );
+                continue;
+            }
+
+            let first_subgraph = self.subgraph_display_name(first_subgraph);
+            let mut conflicting = false;
+            for (subgraph_name, definition) in &definitions[1..] {
+                for argument in &merged.arguments {
+                    let Some(other) = definition
+                        .arguments
+                        .iter()
+                        .find(|other| other.name == argument.name)
+                    else {
+                        continue;
+                    };
+                    if other.ty != argument.ty {
+                        conflicting = true;
+                        self.push_error(
+                            ErrorCode::DirectiveCompositionError,
+                            format!(
+                                "Argument \"@{name}({}:)\" has type `{}` in this subgraph \
+                                 but type `{}` in subgraph \"{}\"",
+                                argument.name, other.ty, argument.ty, first_subgraph,
+                            ),
+                            subgraph_name,
+                            [other.location(), argument.location()],
+                            format!(
+                                "Use the same type for argument \"@{name}({}:)\" \
+                                 in all subgraphs",
+                                argument.name
+                            ),
+                        );
+                    }
+                }
+            }
+            if !conflicting {
+                supergraph
+                    .directive_definitions
+                    .insert(name, Node::new(merged));
+            }
+        }
+    }
+
+    /// Adds the directives composed with `@composeDirective` to the supergraph, along with an
+    /// `@link` importing them from their spec.
+    fn add_composed_directives(&self, supergraph: &mut Schema) {
+        let mut links: Vec<(&Link, Vec<Node<Value>>)> = Vec::new();
+        for composed in self.composed_directives.values() {
+            supergraph.directive_definitions.insert(
+                composed.definition.name.clone(),
+                composed.definition.clone(),
+            );
+
+            let index = links
+                .iter()
+                .position(|(link, _)| link.url.identity == composed.link.url.identity)
+                .unwrap_or_else(|| {
+                    links.push((&*composed.link, Vec::new()));
+                    links.len() - 1
+                });
+            let (link, imports) = &mut links[index];
+            if link.url.version < composed.link.url.version {
+                *link = &*composed.link;
+            }
+            if let Some(import) = &composed.import {
+                let value = if import.alias.is_some() {
+                    Value::Object(vec![
+                        (
+                            name!("name"),
+                            import.element_display_name().to_string().into(),
+                        ),
+                        (
+                            name!("as"),
+                            import.imported_display_name().to_string().into(),
+                        ),
+                    ])
+                } else {
+                    import.imported_display_name().to_string().into()
+                };
+                imports.push(value.into());
+            }
+        }
+
+        for (link, imports) in links {
+            let mut arguments = vec![Node::new(Argument {
+                name: name!("url"),
+                value: link.url.to_string().into(),
+            })];
+            if let Some(alias) = &link.spec_alias {
+                arguments.push(Node::new(Argument {
+                    name: name!("as"),
+                    value: alias.as_str().into(),
+                }));
+            }
+            if !imports.is_empty() {
+                arguments.push(Node::new(Argument {
+                    name: name!("import"),
+                    value: Value::List(imports).into(),
+                }));
+            }
+            supergraph
+                .schema_definition
+                .make_mut()
+                .directives
+                .push(Component::new(Directive {
+                    name: name!("link"),
+                    arguments,
+                }));
+        }
     }
 }
 
@@ -725,6 +1847,9 @@ struct DirectiveNames {
     interface_object: Name,
     r#override: Name,
     inaccessible: Name,
+    compose_directive: Name,
+    /// Directives of the subgraph composed with `@composeDirective`
+    composed: IndexSet<Name>,
 }
 
 impl DirectiveNames {
@@ -762,6 +1887,10 @@ impl DirectiveNames {
             .map(|link| link.directive_name_in_schema(&INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC))
             .unwrap_or(INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC);
 
+        let compose_directive = federation_identity
+            .map(|link| link.directive_name_in_schema(&COMPOSE_DIRECTIVE_NAME))
+            .unwrap_or(COMPOSE_DIRECTIVE_NAME);
+
         Self {
             key,
             requires,
@@ -770,6 +1899,8 @@ impl DirectiveNames {
             interface_object,
             r#override,
             inaccessible,
+            compose_directive,
+            composed: IndexSet::default(),
         }
     }
 }
@@ -794,6 +1925,32 @@ fn is_executable_directive(directive: &Node<DirectiveDefinition>) -> bool {
 // TODO handle federation specific types - skip if any of the link/fed spec
 // TODO this info should be coming from other module
 const FEDERATION_TYPES: [&str; 4] = ["_Any", "_Entity", "_Service", "@key"];
+fn type_location(ty: &ExtendedType) -> Option<SourceSpan> {
+    match ty {
+        ExtendedType::Scalar(ty) => ty.location(),
+        ExtendedType::Object(ty) => ty.location(),
+        ExtendedType::Interface(ty) => ty.location(),
+        ExtendedType::Union(ty) => ty.location(),
+        ExtendedType::Enum(ty) => ty.location(),
+        ExtendedType::InputObject(ty) => ty.location(),
+    }
+}
+
+/// Whether `sub` implements the `sup` interface or is a member of the `sup` union
+fn is_subtype(schema: &Schema, sub: &Name, sup: &Name) -> bool {
+    match schema.types.get(sup) {
+        Some(ExtendedType::Interface(_)) => match schema.types.get(sub) {
+            Some(ExtendedType::Object(object)) => object.implements_interfaces.contains(sup),
+            Some(ExtendedType::Interface(interface)) => {
+                interface.implements_interfaces.contains(sup)
+            }
+            _ => false,
+        },
+        Some(ExtendedType::Union(union)) => union.members.contains(sub),
+        _ => false,
+    }
+}
+
 fn is_mergeable_type(type_name: &str) -> bool {
     if type_name.starts_with("federation__") || type_name.starts_with("link__") {
         return false;
@@ -1578,13 +2735,22 @@ fn add_core_feature_inaccessible(supergraph: &mut Schema) {
     );
 }
 
-fn merge_directive(
-    supergraph_directives: &mut IndexMap<Name, Node<DirectiveDefinition>>,
-    directive: &Node<DirectiveDefinition>,
-) {
-    if !supergraph_directives.contains_key(&directive.name.clone()) {
-        supergraph_directives.insert(directive.name.clone(), directive.clone());
-    }
+/// Whether two directive definitions have the same arguments, locations and repeatability,
+/// regardless of order and descriptions.
+fn same_directive_definition(a: &DirectiveDefinition, b: &DirectiveDefinition) -> bool {
+    a.repeatable == b.repeatable
+        && a.locations.len() == b.locations.len()
+        && a.locations
+            .iter()
+            .all(|location| b.locations.contains(location))
+        && a.arguments.len() == b.arguments.len()
+        && a.arguments.iter().all(|argument| {
+            b.arguments.iter().any(|other| {
+                other.name == argument.name
+                    && other.ty == argument.ty
+                    && other.default_value == argument.default_value
+            })
+        })
 }
 
 #[cfg(test)]
@@ -738,6 +738,7 @@ impl QueryGraph {
         let mut candidates = self.out_edges(node).into_iter().filter_map(|edge_ref| {
             let edge_weight = edge_ref.weight();
             let QueryGraphEdgeTransition::FieldCollection {
+                source,
                 field_definition_position,
                 ..
             } = &edge_weight.transition
@@ -745,7 +746,9 @@ impl QueryGraph {
                 return None;
             };
 
-            if !edge_weight.satisfies_override_conditions(override_conditions) {
+            if !edge_weight.satisfies_override_conditions(override_conditions)
+                || override_conditions.avoids_field(source, field_definition_position)
+            {
                 return None;
             }
 
@@ -1,30 +1,49 @@
 // Output module for query graphs
 // - Corresponds to the `graphviz` and `mermaid` modules from the JS federation.
+// - Also exports query graphs and operation traversals as JSON, for tooling.
 
 use std::fmt::Write;
 use std::sync::Arc;
 
+use apollo_compiler::collections::IndexMap;
+use apollo_compiler::collections::IndexSet;
+use apollo_compiler::executable::Selection;
+use apollo_compiler::executable::SelectionSet;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Name;
 use petgraph::dot::Config;
 use petgraph::dot::Dot;
 use petgraph::graph::EdgeIndex;
+use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::StableGraph;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
 
+use crate::error::FederationError;
+use crate::error::SingleFederationError;
 use crate::query_graph::QueryGraph;
 use crate::query_graph::QueryGraphEdge;
+use crate::query_graph::QueryGraphEdgeTransition;
 use crate::query_graph::QueryGraphNode;
+use crate::query_graph::QueryGraphNodeType;
+use crate::schema::position::SchemaRootDefinitionKind;
 
 type StableInnerGraph = StableGraph<QueryGraphNode, QueryGraphEdge>;
 
 //////////////////////////////////////////////////////////////////////////////
 // GraphViz output for QueryGraph
 
-fn label_edge(edge: &QueryGraphEdge) -> String {
+fn label_edge(edge: &QueryGraphEdge, highlighted: bool) -> String {
     let label = edge.to_string();
-    if label.is_empty() {
-        String::new()
-    } else {
-        format!("label=\"{}\"", edge)
+    let mut attributes = Vec::new();
+    if !label.is_empty() {
+        attributes.push(format!("label=\"{}\"", label));
     }
+    if highlighted {
+        attributes.push(r#"color="red", penwidth=2"#.to_string());
+    }
+    attributes.join(", ")
 }
 
 fn label_node(node: &QueryGraphNode) -> String {
@@ -32,8 +51,23 @@ fn label_node(node: &QueryGraphNode) -> String {
 }
 
 pub fn to_dot(graph: &QueryGraph) -> String {
+    to_dot_highlighted(graph, &IndexSet::default())
+}
+
+/// Outputs the query graph with the edges taken by an operation traversal highlighted.
+pub fn to_dot_with_traversal(graph: &QueryGraph, traversal: &OperationTraversal) -> String {
+    let highlighted = traversal
+        .fields
+        .iter()
+        .flat_map(|field| field.edges.iter().copied())
+        .collect();
+    to_dot_highlighted(graph, &highlighted)
+}
+
+fn to_dot_highlighted(graph: &QueryGraph, highlighted: &IndexSet<usize>) -> String {
     if graph.sources.len() > 1 {
-        return to_dot_federated(graph).expect("Failed to generate the federated graph");
+        return to_dot_federated(graph, highlighted)
+            .expect("Failed to generate the federated graph");
     }
 
     // Note: Use label_edge/label_node as `attr_getters` in order to create custom label
@@ -42,13 +76,16 @@ pub fn to_dot(graph: &QueryGraph) -> String {
     Dot::with_attr_getters(
         &graph.graph,
         &config,
-        &(|_, er| label_edge(er.weight())),
+        &(|_, er| label_edge(er.weight(), highlighted.contains(&er.id().index()))),
         &(|_, (_, node)| label_node(node)),
     )
     .to_string()
 }
 
-fn to_dot_federated(graph: &QueryGraph) -> Result<String, std::fmt::Error> {
+fn to_dot_federated(
+    graph: &QueryGraph,
+    highlighted: &IndexSet<usize>,
+) -> Result<String, std::fmt::Error> {
     fn edge_within_cluster(
         graph: &StableInnerGraph,
         cluster_name: &Arc<str>,
@@ -109,7 +146,7 @@ fn to_dot_federated(graph: &QueryGraph) -> Result<String, std::fmt::Error> {
         let s = Dot::with_attr_getters(
             &filtered_graph,
             &cluster_dot_config,
-            &(|_, er| label_edge(er.weight())),
+            &(|_, er| label_edge(er.weight(), highlighted.contains(&er.id().index()))),
             &(|_, (_, node)| label_cluster_node(node)),
         )
         .to_string();
@@ -140,7 +177,7 @@ fn to_dot_federated(graph: &QueryGraph) -> Result<String, std::fmt::Error> {
                     "  {} -> {} [{}]",
                     n1.index(),
                     n2.index(),
-                    label_edge(edge)
+                    label_edge(edge, highlighted.contains(&i.index()))
                 )?;
             }
         }
@@ -149,3 +186,398 @@ fn to_dot_federated(graph: &QueryGraph) -> Result<String, std::fmt::Error> {
     writeln!(dot_str, "}}")?;
     Ok(dot_str)
 }
+
+//////////////////////////////////////////////////////////////////////////////
+// JSON output for QueryGraph
+
+/// A query graph in a serializable form, with nodes and edges identified by their index.
+#[derive(Debug, Serialize)]
+pub struct QueryGraphExport {
+    pub name: String,
+    pub nodes: Vec<QueryGraphNodeExport>,
+    pub edges: Vec<QueryGraphEdgeExport>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryGraphNodeExport {
+    pub id: usize,
+    /// The type the node points to, or the root kind in brackets for federated root nodes
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The subgraph the node belongs to
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provide_id: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryGraphEdgeExport {
+    pub id: usize,
+    pub head: usize,
+    pub tail: usize,
+    /// What the edge represents: `field`, `downcast`, `key`, `rootType`, `subgraphEntering` or
+    /// `interfaceObjectDowncast`
+    pub kind: &'static str,
+    pub transition: String,
+    /// The selections required to take the edge, for `@key` and `@requires`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_condition: Option<String>,
+}
+
+pub fn to_json(graph: &QueryGraph) -> QueryGraphExport {
+    let nodes = graph
+        .graph
+        .node_indices()
+        .map(|index| {
+            let node = &graph.graph[index];
+            QueryGraphNodeExport {
+                id: index.index(),
+                type_: node.type_.to_string(),
+                source: node.source.to_string(),
+                provide_id: node.provide_id,
+            }
+        })
+        .collect();
+    let edges = graph
+        .graph
+        .edge_references()
+        .map(|edge| {
+            let weight = edge.weight();
+            QueryGraphEdgeExport {
+                id: edge.id().index(),
+                head: edge.source().index(),
+                tail: edge.target().index(),
+                kind: match weight.transition {
+                    QueryGraphEdgeTransition::FieldCollection { .. } => "field",
+                    QueryGraphEdgeTransition::Downcast { .. } => "downcast",
+                    QueryGraphEdgeTransition::KeyResolution => "key",
+                    QueryGraphEdgeTransition::RootTypeResolution { .. } => "rootType",
+                    QueryGraphEdgeTransition::SubgraphEnteringTransition => "subgraphEntering",
+                    QueryGraphEdgeTransition::InterfaceObjectFakeDownCast { .. } => {
+                        "interfaceObjectDowncast"
+                    }
+                },
+                transition: weight.transition.to_string(),
+                conditions: weight
+                    .conditions
+                    .as_ref()
+                    .map(|conditions| conditions.to_string()),
+                override_condition: weight
+                    .override_condition
+                    .as_ref()
+                    .map(|condition| condition.to_string()),
+            }
+        })
+        .collect();
+    QueryGraphExport {
+        name: graph.name().to_string(),
+        nodes,
+        edges,
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+// Operation traversals
+
+/// Where the fields of an operation can be collected in a federated query graph.
+///
+/// Each field is looked up from every node its parent was reached at, after taking any key, root
+/// type or subgraph entering edge. Key and `@requires` conditions are not checked, so a field
+/// reached in some subgraph may still fail to plan, but a field reached in no subgraph cannot be
+/// planned at all.
+#[derive(Debug, Serialize)]
+pub struct OperationTraversal {
+    pub fields: Vec<FieldTraversal>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldTraversal {
+    /// The response path of the field, e.g. `products.reviews.author`
+    pub path: String,
+    /// The subgraphs the parent type of the field was reached in
+    pub parent_subgraphs: Vec<String>,
+    /// The subgraphs the field can be collected from, empty if it cannot be reached
+    pub subgraphs: Vec<String>,
+    /// The edges collecting the field, and the key and root type edges taken to reach them
+    pub edges: Vec<usize>,
+}
+
+/// Traverses the federated query graph along the fields of an operation, see
+/// [`OperationTraversal`].
+pub fn traverse_operation(
+    graph: &QueryGraph,
+    document: &Valid<ExecutableDocument>,
+    operation_name: Option<&str>,
+) -> Result<OperationTraversal, FederationError> {
+    let operation = document.operations.get(operation_name).map_err(|_| {
+        if operation_name.is_some() {
+            SingleFederationError::UnknownOperation
+        } else {
+            SingleFederationError::OperationNameNotProvided
+        }
+    })?;
+    let root_kind = SchemaRootDefinitionKind::from(operation.operation_type);
+    let root = *graph
+        .root_kinds_to_nodes()?
+        .get(&root_kind)
+        .ok_or_else(|| {
+            FederationError::internal(format!("The supergraph has no {root_kind} root type"))
+        })?;
+
+    let mut traversal = Traversal {
+        graph,
+        document,
+        fields: Vec::new(),
+    };
+    traversal.selection_set(&operation.selection_set, "", &IndexSet::from_iter([root]));
+    Ok(OperationTraversal {
+        fields: traversal.fields,
+    })
+}
+
+struct Traversal<'a> {
+    graph: &'a QueryGraph,
+    document: &'a ExecutableDocument,
+    fields: Vec<FieldTraversal>,
+}
+
+impl Traversal<'_> {
+    fn selection_set(
+        &mut self,
+        selection_set: &SelectionSet,
+        path: &str,
+        nodes: &IndexSet<NodeIndex>,
+    ) {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    if field.name.as_str() == "__typename" {
+                        continue;
+                    }
+                    let path = if path.is_empty() {
+                        field.response_key().to_string()
+                    } else {
+                        format!("{path}.{}", field.response_key())
+                    };
+                    let (reached, jumps) = self.jumps(nodes);
+                    let mut next = IndexSet::default();
+                    let mut edges = IndexSet::default();
+                    for &node in &reached {
+                        for edge in self.graph.out_edges(node) {
+                            let QueryGraphEdgeTransition::FieldCollection {
+                                field_definition_position,
+                                ..
+                            } = &edge.weight().transition
+                            else {
+                                continue;
+                            };
+                            if *field_definition_position.field_name() != field.name {
+                                continue;
+                            }
+                            next.insert(edge.target());
+                            edges.insert(edge.id().index());
+                            // the jumps leading to the node the field is collected from
+                            let mut head = node;
+                            while let Some(&jump) = jumps.get(&head) {
+                                if !edges.insert(jump.index()) {
+                                    break;
+                                }
+                                let Some((source, _)) = self.graph.graph.edge_endpoints(jump)
+                                else {
+                                    break;
+                                };
+                                head = source;
+                            }
+                        }
+                    }
+                    self.fields.push(FieldTraversal {
+                        path: path.clone(),
+                        parent_subgraphs: self.subgraphs(&reached),
+                        subgraphs: self.subgraphs(&next),
+                        edges: edges.into_iter().collect(),
+                    });
+                    if !next.is_empty() {
+                        self.selection_set(&field.selection_set, &path, &next);
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let nodes = match &fragment.type_condition {
+                        Some(type_condition) => self.downcast(nodes, type_condition),
+                        None => nodes.clone(),
+                    };
+                    self.selection_set(&fragment.selection_set, path, &nodes);
+                }
+                Selection::FragmentSpread(spread) => {
+                    if let Some(fragment) = self.document.fragments.get(&spread.fragment_name) {
+                        let nodes = self.downcast(nodes, fragment.type_condition());
+                        self.selection_set(&fragment.selection_set, path, &nodes);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The nodes reachable from the given ones by taking edges that do not collect any
+    /// operation element, with the edge each of them was first reached by.
+    fn jumps(
+        &self,
+        nodes: &IndexSet<NodeIndex>,
+    ) -> (IndexSet<NodeIndex>, IndexMap<NodeIndex, EdgeIndex>) {
+        let mut reached = nodes.clone();
+        let mut jumps = IndexMap::default();
+        let mut queue: Vec<_> = nodes.iter().copied().collect();
+        while let Some(node) = queue.pop() {
+            for edge in self.graph.out_edges(node) {
+                if !edge.weight().transition.collect_operation_elements()
+                    && reached.insert(edge.target())
+                {
+                    jumps.insert(edge.target(), edge.id());
+                    queue.push(edge.target());
+                }
+            }
+        }
+        (reached, jumps)
+    }
+
+    /// The nodes of the given type among the given nodes, or reachable from them by a downcast
+    /// within their subgraph.
+    fn downcast(&self, nodes: &IndexSet<NodeIndex>, type_name: &Name) -> IndexSet<NodeIndex> {
+        let mut downcast = IndexSet::default();
+        for &node in nodes {
+            if self.type_name(node) == Some(type_name) {
+                downcast.insert(node);
+            }
+            for edge in self.graph.out_edges(node) {
+                let to_type_name = match &edge.weight().transition {
+                    QueryGraphEdgeTransition::Downcast {
+                        to_type_position, ..
+                    } => to_type_position.type_name(),
+                    QueryGraphEdgeTransition::InterfaceObjectFakeDownCast {
+                        to_type_name, ..
+                    } => to_type_name,
+                    _ => continue,
+                };
+                if to_type_name == type_name {
+                    downcast.insert(edge.target());
+                }
+            }
+        }
+        downcast
+    }
+
+    fn type_name(&self, node: NodeIndex) -> Option<&Name> {
+        match &self.graph.graph[node].type_ {
+            QueryGraphNodeType::SchemaType(position) => Some(position.type_name()),
+            QueryGraphNodeType::FederatedRootType(_) => None,
+        }
+    }
+
+    fn subgraphs(&self, nodes: &IndexSet<NodeIndex>) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|&node| &self.graph.graph[node].source)
+            .filter(|source| *source != self.graph.name())
+            .map(|source| source.to_string())
+            .collect::<IndexSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_compiler::ExecutableDocument;
+
+    use super::*;
+    use crate::query_graph::build_federated_query_graph;
+    use crate::subgraph::Subgraph;
+    use crate::Supergraph;
+
+    fn query_graph() -> QueryGraph {
+        let products = Subgraph::parse_and_expand(
+            "products",
+            "http://products",
+            r#"
+            type Query {
+              products: [Product]
+            }
+
+            type Product @key(fields: "id") {
+              id: ID!
+              name: String
+            }
+            "#,
+        )
+        .unwrap();
+        let prices = Subgraph::parse_and_expand(
+            "prices",
+            "http://prices",
+            r#"
+            type Product @key(fields: "id") {
+              id: ID!
+              price: Int
+            }
+            "#,
+        )
+        .unwrap();
+        let supergraph = Supergraph::compose(vec![&products, &prices]).unwrap();
+        let api_schema = supergraph.to_api_schema(Default::default()).unwrap();
+        build_federated_query_graph(supergraph.schema, api_schema, None, None).unwrap()
+    }
+
+    #[test]
+    fn exports_query_graph_as_json() {
+        let graph = query_graph();
+        let export = to_json(&graph);
+        assert_eq!(export.nodes.len(), graph.graph.node_count());
+        assert_eq!(export.edges.len(), graph.graph.edge_count());
+        let key = export
+            .edges
+            .iter()
+            .find(|edge| edge.kind == "key")
+            .expect("a key edge");
+        assert!(key
+            .conditions
+            .as_deref()
+            .is_some_and(|conditions| conditions.contains("id")));
+    }
+
+    #[test]
+    fn traverses_operation_fields() {
+        let graph = query_graph();
+        let schema = graph.supergraph_schema().unwrap();
+        let document = ExecutableDocument::parse_and_validate(
+            schema.schema(),
+            "{ products { ... on Product { name price } } }",
+            "operation.graphql",
+        )
+        .unwrap();
+
+        let traversal = traverse_operation(&graph, &document, None).unwrap();
+        let fields: Vec<_> = traversal
+            .fields
+            .iter()
+            .map(|field| (field.path.as_str(), field.subgraphs.clone()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("products", vec!["products".to_string()]),
+                ("products.name", vec!["products".to_string()]),
+                ("products.price", vec!["prices".to_string()]),
+            ]
+        );
+        // the price is reached through the key edge from the products subgraph
+        let price = &traversal.fields[2];
+        assert_eq!(price.parent_subgraphs, ["products", "prices"]);
+        assert_eq!(price.edges.len(), 2);
+
+        let dot = to_dot_with_traversal(&graph, &traversal);
+        assert_eq!(dot.matches(r#"color="red""#).count(), 5);
+    }
+}
@@ -708,6 +708,12 @@ impl FetchDependencyGraph {
         self.root_nodes_by_subgraph.iter()
     }
 
+    /// The number of fetches of this graph. Once the graph is optimized, they are the fetches of
+    /// the plan it results in.
+    pub(crate) fn fetch_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
     /// Must be called every time the "shape" of the graph is modified
     /// to know that the graph may not be minimal/optimized anymore.
     fn on_modification(&mut self) {
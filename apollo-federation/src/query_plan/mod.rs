@@ -66,6 +66,37 @@ pub enum PlanNode {
     Condition(Box<ConditionNode>),
 }
 
+impl PlanNode {
+    /// The number of subgraph fetches of this node and its children
+    pub(crate) fn subgraph_fetches(&self) -> usize {
+        match self {
+            PlanNode::Fetch(_) => 1,
+            PlanNode::Sequence(SequenceNode { nodes })
+            | PlanNode::Parallel(ParallelNode { nodes }) => {
+                nodes.iter().map(PlanNode::subgraph_fetches).sum()
+            }
+            PlanNode::Flatten(node) => node.node.subgraph_fetches(),
+            PlanNode::Defer(node) => node
+                .primary
+                .node
+                .iter()
+                .chain(
+                    node.deferred
+                        .iter()
+                        .filter_map(|deferred| deferred.node.as_ref()),
+                )
+                .map(|node| node.subgraph_fetches())
+                .sum(),
+            PlanNode::Condition(node) => node
+                .if_clause
+                .iter()
+                .chain(node.else_clause.iter())
+                .map(|node| node.subgraph_fetches())
+                .sum(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct FetchNode {
     pub subgraph_name: Arc<str>,
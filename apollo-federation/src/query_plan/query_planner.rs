@@ -1,7 +1,10 @@
 use std::cell::Cell;
 use std::num::NonZeroU32;
 use std::ops::Deref;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
 use apollo_compiler::collections::IndexMap;
 use apollo_compiler::collections::IndexSet;
@@ -25,6 +28,7 @@ use crate::operation::SelectionSet;
 use crate::query_graph::build_federated_query_graph;
 use crate::query_graph::path_tree::OpPathTree;
 use crate::query_graph::QueryGraph;
+use crate::query_graph::QueryGraphEdgeTransition;
 use crate::query_graph::QueryGraphNodeType;
 use crate::query_plan::fetch_dependency_graph::compute_nodes_for_tree;
 use crate::query_plan::fetch_dependency_graph::FetchDependencyGraph;
@@ -34,6 +38,7 @@ use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToC
 use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToQueryPlanProcessor;
 use crate::query_plan::query_planning_traversal::convert_type_from_subgraph;
 use crate::query_plan::query_planning_traversal::BestQueryPlanInfo;
+use crate::query_plan::query_planning_traversal::PlanningDeadline;
 use crate::query_plan::query_planning_traversal::QueryPlanningParameters;
 use crate::query_plan::query_planning_traversal::QueryPlanningTraversal;
 use crate::query_plan::PlanNode;
@@ -42,6 +47,7 @@ use crate::query_plan::SequenceNode;
 use crate::query_plan::TopLevelPlanNode;
 use crate::schema::position::AbstractTypeDefinitionPosition;
 use crate::schema::position::CompositeTypeDefinitionPosition;
+use crate::schema::position::FieldDefinitionPosition;
 use crate::schema::position::InterfaceTypeDefinitionPosition;
 use crate::schema::position::ObjectTypeDefinitionPosition;
 use crate::schema::position::OutputTypeDefinitionPosition;
@@ -52,7 +58,7 @@ use crate::utils::logging::snapshot;
 use crate::ApiSchemaOptions;
 use crate::Supergraph;
 
-#[derive(Debug, Clone, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct QueryPlannerConfig {
     /// If enabled, the query planner will extract inline fragments into fragment
     /// definitions before sending queries to subgraphs. This can significantly
@@ -104,7 +110,7 @@ impl Default for QueryPlannerConfig {
     }
 }
 
-#[derive(Debug, Clone, Default, Hash, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize)]
 pub struct QueryPlanIncrementalDeliveryConfig {
     /// Enables `@defer` support in the query planner, breaking up the query plan with [DeferNode]s
     /// as appropriate.
@@ -119,7 +125,7 @@ pub struct QueryPlanIncrementalDeliveryConfig {
     pub enable_defer: bool,
 }
 
-#[derive(Debug, Clone, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct QueryPlannerDebugConfig {
     /// Query planning is an exploratory process. Depending on the specificities and feature used by
     /// subgraphs, there could exist may different theoretical valid (if not always efficient) plans
@@ -153,6 +159,27 @@ pub struct QueryPlannerDebugConfig {
     ///
     /// The default value is None, which specifies no limit.
     pub paths_limit: Option<u32>,
+
+    /// Pathological operations, like many aliases of fields resolved by other subgraphs, can
+    /// generate query plans with so many fetches that building them takes a lot of time and
+    /// memory, and executing them overloads the subgraphs.
+    ///
+    /// This config limits the number of fetches of the query plan. While planning, the candidate
+    /// plans with more fetches than this limit once optimized are discarded, which also prunes
+    /// the candidates extending them. Query planning then fails with a
+    /// [`SingleFederationError::QueryPlanTooLarge`] error when the chosen plan still has more
+    /// fetches than this limit.
+    ///
+    /// The default value is None, which specifies no limit.
+    pub max_fetch_nodes: Option<u32>,
+
+    /// Limits the time spent planning an operation. The deadline is checked periodically while
+    /// enumerating paths and generating plans, and planning aborts with a
+    /// [`SingleFederationError::QueryPlanningTimeout`] error once it has passed, so that a
+    /// pathological operation does not keep a thread busy for a long time.
+    ///
+    /// The default value is None, which specifies no limit.
+    pub planning_timeout: Option<Duration>,
 }
 
 impl Default for QueryPlannerDebugConfig {
@@ -160,6 +187,8 @@ impl Default for QueryPlannerDebugConfig {
         Self {
             max_evaluated_plans: NonZeroU32::new(10_000).unwrap(),
             paths_limit: None,
+            max_fetch_nodes: None,
+            planning_timeout: None,
         }
     }
 }
@@ -179,16 +208,123 @@ pub struct QueryPlanOptions {
     /// progressive @override feature.
     // PORT_NOTE: In JS implementation this was a Map
     pub override_conditions: Vec<String>,
+    /// Names of subgraphs that are temporarily unavailable. The planner avoids fetching fields
+    /// from these subgraphs when another subgraph can resolve them. Fields that only a disabled
+    /// subgraph can resolve are still planned against it.
+    pub disabled_subgraphs: Vec<String>,
+    /// Setting this token to `true` aborts query planning with a
+    /// [`SingleFederationError::QueryPlanningCancelled`] error. It is checked along with the
+    /// deadline of [`QueryPlannerDebugConfig::planning_timeout`], so planning can be cancelled
+    /// from another thread once its result is no longer needed.
+    pub cancellation_token: Option<Arc<AtomicBool>>,
+}
+
+impl QueryPlanOptions {
+    /// Builds options enabling the progressive @override labels whose resolution applies to this
+    /// plan. `roll` returns a random number in `[0, 1)`, and is called once for each label
+    /// resolved with a percentage.
+    pub fn with_override_resolutions<'a>(
+        resolutions: impl IntoIterator<Item = (&'a str, OverrideLabelResolution)>,
+        mut roll: impl FnMut() -> f64,
+    ) -> Self {
+        let mut override_conditions = resolutions
+            .into_iter()
+            .filter(|(_, resolution)| resolution.is_enabled(&mut roll))
+            .map(|(label, _)| label.to_string())
+            .collect::<Vec<_>>();
+        // The override conditions are part of the query plan cache key in the router, so keep
+        // them in a stable order
+        override_conditions.sort();
+        override_conditions.dedup();
+        Self {
+            override_conditions,
+            ..Default::default()
+        }
+    }
+}
+
+/// How a progressive @override label is resolved when planning a query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverrideLabelResolution {
+    /// The override is applied to all plans
+    Enabled,
+    /// The override is not applied
+    Disabled,
+    /// The override is applied to the given percentage (between 0 and 100) of plans
+    Percentage(f64),
+}
+
+impl OverrideLabelResolution {
+    /// Resolution of a label from its name: `percent(<value>)` labels are applied to that
+    /// percentage of plans. Other labels have to be resolved by the caller, so this returns `None`
+    /// for them.
+    pub fn from_label(label: &str) -> Option<Self> {
+        label
+            .strip_prefix("percent(")
+            .and_then(|label| label.strip_suffix(')'))
+            .and_then(|percentage| percentage.parse::<f64>().ok())
+            .filter(|percentage| (0.0..=100.0).contains(percentage))
+            .map(OverrideLabelResolution::Percentage)
+    }
+
+    /// Whether the override applies to a plan. `roll` returns a random number in `[0, 1)`, and is
+    /// only called for labels resolved with a percentage.
+    pub fn is_enabled(&self, roll: impl FnOnce() -> f64) -> bool {
+        match self {
+            OverrideLabelResolution::Enabled => true,
+            OverrideLabelResolution::Disabled => false,
+            OverrideLabelResolution::Percentage(percentage) => roll() * 100.0 < *percentage,
+        }
+    }
 }
 
+/// The enabled progressive @override labels, along with the subgraphs to avoid while planning.
+/// This derefs to the set of enabled labels.
 #[derive(Debug, Default, Clone)]
-pub(crate) struct EnabledOverrideConditions(IndexSet<String>);
+pub(crate) struct EnabledOverrideConditions {
+    labels: IndexSet<String>,
+    disabled_subgraphs: IndexSet<Arc<str>>,
+    /// The `(type, field)` coordinates that at least one non-disabled subgraph can resolve.
+    fields_in_enabled_subgraphs: Arc<IndexSet<(Name, Name)>>,
+}
+
+impl EnabledOverrideConditions {
+    fn new(planner: &QueryPlanner, options: QueryPlanOptions) -> Self {
+        let labels = IndexSet::from_iter(options.override_conditions);
+        let mut disabled_subgraphs: Vec<Arc<str>> = options
+            .disabled_subgraphs
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+        disabled_subgraphs.sort();
+        disabled_subgraphs.dedup();
+        let fields_in_enabled_subgraphs = if disabled_subgraphs.is_empty() {
+            Default::default()
+        } else {
+            planner.fields_in_enabled_subgraphs(&disabled_subgraphs)
+        };
+        Self {
+            labels,
+            disabled_subgraphs: disabled_subgraphs.into_iter().collect(),
+            fields_in_enabled_subgraphs,
+        }
+    }
+
+    /// Whether the field collected from the `source` subgraph should be avoided because that
+    /// subgraph is disabled and another subgraph can resolve the same field.
+    pub(crate) fn avoids_field(&self, source: &str, field: &FieldDefinitionPosition) -> bool {
+        self.disabled_subgraphs.contains(source)
+            && self
+                .fields_in_enabled_subgraphs
+                .contains(&(field.type_name().clone(), field.field_name().clone()))
+    }
+}
 
 impl Deref for EnabledOverrideConditions {
     type Target = IndexSet<String>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.labels
     }
 }
 
@@ -205,6 +341,10 @@ pub struct QueryPlanner {
     // PORT_NOTE: Named `inconsistentAbstractTypesRuntimes` in the JS codebase, which was slightly
     // confusing.
     abstract_types_with_inconsistent_runtime_types: IndexSet<AbstractTypeDefinitionPosition>,
+    /// The `(type, field)` coordinates that at least one subgraph can resolve, for each sorted
+    /// set of disabled subgraphs. The disabled subgraphs come from the configuration, so there
+    /// are only a few of these sets.
+    fields_in_enabled_subgraphs: Mutex<IndexMap<Vec<Arc<str>>, Arc<IndexSet<(Name, Name)>>>>,
 }
 
 impl QueryPlanner {
@@ -306,19 +446,90 @@ impl QueryPlanner {
             api_schema,
             interface_types_with_interface_objects,
             abstract_types_with_inconsistent_runtime_types,
+            fields_in_enabled_subgraphs: Default::default(),
         })
     }
 
+    /// The `(type, field)` coordinates that a subgraph other than the `disabled_subgraphs` can
+    /// resolve. This goes over all the edges of the query graph, so it is computed once for each
+    /// set of disabled subgraphs.
+    fn fields_in_enabled_subgraphs(
+        &self,
+        disabled_subgraphs: &[Arc<str>],
+    ) -> Arc<IndexSet<(Name, Name)>> {
+        let mut cache = self
+            .fields_in_enabled_subgraphs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(fields) = cache.get(disabled_subgraphs) {
+            return fields.clone();
+        }
+        let fields: Arc<IndexSet<(Name, Name)>> = Arc::new(
+            self.federated_query_graph
+                .graph()
+                .edge_weights()
+                .filter_map(|edge| match &edge.transition {
+                    QueryGraphEdgeTransition::FieldCollection {
+                        source,
+                        field_definition_position,
+                        ..
+                    } if !disabled_subgraphs.contains(source) => Some((
+                        field_definition_position.type_name().clone(),
+                        field_definition_position.field_name().clone(),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+        );
+        cache.insert(disabled_subgraphs.to_vec(), fields.clone());
+        fields
+    }
+
     pub fn subgraph_schemas(&self) -> &IndexMap<Arc<str>, ValidFederationSchema> {
         self.federated_query_graph.subgraph_schemas()
     }
 
+    /// The labels of the progressive @override in the supergraph, which can be enabled through
+    /// [`QueryPlanOptions::override_conditions`].
+    pub fn override_labels(&self) -> IndexSet<String> {
+        self.federated_query_graph
+            .graph()
+            .edge_weights()
+            .filter_map(|edge| edge.override_condition.as_ref())
+            .map(|condition| condition.label.clone())
+            .collect()
+    }
+
     // PORT_NOTE: this receives an `Operation` object in JS which is a concept that doesn't exist in apollo-rs.
     #[cfg_attr(
         feature = "snapshot_tracing",
         tracing::instrument(level = "trace", skip_all, name = "QueryPlanner::build_query_plan")
     )]
     pub fn build_query_plan(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<Name>,
+        mut options: QueryPlanOptions,
+    ) -> Result<QueryPlan, FederationError> {
+        if !options.disabled_subgraphs.is_empty() {
+            match self.build_query_plan_with_options(
+                document,
+                operation_name.clone(),
+                options.clone(),
+            ) {
+                Ok(plan) => return Ok(plan),
+                Err(error) => {
+                    // Some parts of the operation can only be reached through the disabled
+                    // subgraphs: plan as if they were available, their fetches will fail.
+                    trace!("Could not plan while avoiding the disabled subgraphs: {error}");
+                    options.disabled_subgraphs.clear();
+                }
+            }
+        }
+        self.build_query_plan_with_options(document, operation_name, options)
+    }
+
+    fn build_query_plan_with_options(
         &self,
         document: &Valid<ExecutableDocument>,
         operation_name: Option<Name>,
@@ -342,6 +553,12 @@ impl QueryPlanner {
         let is_subscription = operation.is_subscription();
 
         let statistics = QueryPlanningStatistics::default();
+        let deadline = self
+            .config
+            .debug
+            .planning_timeout
+            .map(PlanningDeadline::after);
+        let cancellation_token = options.cancellation_token.clone();
 
         let normalized_operation = normalize_operation(
             operation,
@@ -411,10 +628,10 @@ impl QueryPlanner {
                 .clone()
                 .into(),
             config: self.config.clone(),
-            override_conditions: EnabledOverrideConditions(IndexSet::from_iter(
-                options.override_conditions,
-            )),
+            override_conditions: EnabledOverrideConditions::new(self, options),
             fetch_id_generator: Arc::new(FetchIdGenerator::new()),
+            deadline,
+            cancellation_token,
         };
 
         let root_node = if !defer_conditions.is_empty() {
@@ -423,6 +640,14 @@ impl QueryPlanner {
             compute_plan_internal(&mut parameters, &mut processor, has_defers)
         }?;
 
+        if let (Some(max_fetch_nodes), Some(root_node)) =
+            (self.config.debug.max_fetch_nodes, &root_node)
+        {
+            if root_node.subgraph_fetches() > max_fetch_nodes as usize {
+                return Err(SingleFederationError::QueryPlanTooLarge { max_fetch_nodes }.into());
+            }
+        }
+
         let root_node = match root_node {
             // If this is a subscription, we want to make sure that we return a SubscriptionNode rather than a PlanNode
             // We potentially will need to separate "primary" from "rest"
@@ -1250,4 +1475,86 @@ type User
         }
         "###);
     }
+
+    #[test]
+    fn resolves_percentage_override_labels() {
+        assert_eq!(
+            OverrideLabelResolution::from_label("percent(25.5)"),
+            Some(OverrideLabelResolution::Percentage(25.5))
+        );
+        assert_eq!(OverrideLabelResolution::from_label("percent(150)"), None);
+        assert_eq!(OverrideLabelResolution::from_label("percent(x)"), None);
+        assert_eq!(OverrideLabelResolution::from_label("my-label"), None);
+    }
+
+    #[test]
+    fn plans_with_resolved_override_labels() {
+        let supergraph = Supergraph::new(include_str!(
+            "../../tests/query_plan/supergraphs/it_handles_progressive_override_on_root_fields.graphql"
+        ))
+        .unwrap();
+        let planner = QueryPlanner::new(&supergraph, Default::default()).unwrap();
+        assert_eq!(
+            planner.override_labels().into_iter().collect::<Vec<_>>(),
+            ["test"]
+        );
+
+        let document = ExecutableDocument::parse_and_validate(
+            planner.api_schema().schema(),
+            "{ hello }",
+            "operation.graphql",
+        )
+        .unwrap();
+        let service_for = |resolution, roll: f64| {
+            let options =
+                QueryPlanOptions::with_override_resolutions([("test", resolution)], || roll);
+            let plan = planner.build_query_plan(&document, None, options).unwrap();
+            match plan.node {
+                Some(TopLevelPlanNode::Fetch(fetch)) => fetch.subgraph_name.to_string(),
+                node => panic!("unexpected plan node: {node:?}"),
+            }
+        };
+
+        assert_eq!(service_for(OverrideLabelResolution::Enabled, 0.0), "s2");
+        assert_eq!(service_for(OverrideLabelResolution::Disabled, 0.0), "s1");
+        assert_eq!(
+            service_for(OverrideLabelResolution::Percentage(30.0), 0.2),
+            "s2"
+        );
+        assert_eq!(
+            service_for(OverrideLabelResolution::Percentage(30.0), 0.5),
+            "s1"
+        );
+    }
+
+    #[test]
+    fn fields_in_enabled_subgraphs_are_computed_once() {
+        let supergraph = Supergraph::new(TEST_SUPERGRAPH).unwrap();
+        let planner = QueryPlanner::new(&supergraph, Default::default()).unwrap();
+        let conditions = |disabled_subgraphs: &[&str]| {
+            EnabledOverrideConditions::new(
+                &planner,
+                QueryPlanOptions {
+                    disabled_subgraphs: disabled_subgraphs
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect(),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let first = conditions(&["reviews", "accounts"]);
+        let second = conditions(&["accounts", "reviews"]);
+        assert!(Arc::ptr_eq(
+            &first.fields_in_enabled_subgraphs,
+            &second.fields_in_enabled_subgraphs
+        ));
+        let other = conditions(&["reviews"]);
+        assert!(!Arc::ptr_eq(
+            &first.fields_in_enabled_subgraphs,
+            &other.fields_in_enabled_subgraphs
+        ));
+        assert_eq!(planner.fields_in_enabled_subgraphs.lock().unwrap().len(), 2);
+    }
 }
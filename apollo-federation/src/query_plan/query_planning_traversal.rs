@@ -1,4 +1,8 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use apollo_compiler::collections::IndexSet;
 use petgraph::graph::EdgeIndex;
@@ -9,6 +13,7 @@ use tracing::trace;
 use super::fetch_dependency_graph::FetchIdGenerator;
 use crate::ensure;
 use crate::error::FederationError;
+use crate::error::SingleFederationError;
 use crate::operation::Operation;
 use crate::operation::Selection;
 use crate::operation::SelectionSet;
@@ -86,6 +91,52 @@ pub(crate) struct QueryPlanningParameters<'a> {
     pub(crate) config: QueryPlannerConfig,
     pub(crate) statistics: &'a QueryPlanningStatistics,
     pub(crate) override_conditions: EnabledOverrideConditions,
+    /// The deadline of query planning, from `QueryPlannerDebugConfig::planning_timeout`.
+    pub(crate) deadline: Option<PlanningDeadline>,
+    /// The token cancelling query planning, from `QueryPlanOptions::cancellation_token`.
+    pub(crate) cancellation_token: Option<Arc<AtomicBool>>,
+}
+
+impl QueryPlanningParameters<'_> {
+    /// Returns an error once the planning deadline has passed or planning was cancelled, to
+    /// cooperatively abort planning.
+    pub(crate) fn check_cancellation(&self) -> Result<(), FederationError> {
+        if let Some(token) = &self.cancellation_token {
+            if token.load(Ordering::Relaxed) {
+                return Err(SingleFederationError::QueryPlanningCancelled.into());
+            }
+        }
+        match &self.deadline {
+            Some(deadline) => deadline.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The time at which planning an operation is aborted.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlanningDeadline {
+    timeout: Duration,
+    deadline: Instant,
+}
+
+impl PlanningDeadline {
+    pub(crate) fn after(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    fn check(&self) -> Result<(), FederationError> {
+        if Instant::now() >= self.deadline {
+            return Err(SingleFederationError::QueryPlanningTimeout {
+                timeout: self.timeout,
+            }
+            .into());
+        }
+        Ok(())
+    }
 }
 
 pub(crate) struct QueryPlanningTraversal<'a, 'b> {
@@ -338,6 +389,7 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
     )]
     fn find_best_plan_inner(&mut self) -> Result<Option<&BestQueryPlanInfo>, FederationError> {
         while !self.open_branches.is_empty() {
+            self.parameters.check_cancellation()?;
             snapshot!(
                 "OpenBranches",
                 snapshot_helper::open_branches_to_string(&self.open_branches),
@@ -400,6 +452,7 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
         );
 
         for option in options.iter_mut() {
+            self.parameters.check_cancellation()?;
             let followups_for_option = option.advance_with_operation_element(
                 self.parameters.supergraph_schema.clone(),
                 &operation_element,
@@ -649,6 +702,15 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
         dependency_graph: &mut FetchDependencyGraph,
     ) -> Result<QueryPlanCost, FederationError> {
         let (main, deferred) = dependency_graph.process(self.cost_processor, self.root_kind)?;
+        // The graph is optimized by `process`, so it has the fetches of the plan it results in.
+        // Plans over the limit get the worst cost: they are only chosen when no plan is within
+        // the limit, and partial plans over the limit are not extended once a plan within the
+        // limit is found.
+        if let Some(max_fetch_nodes) = self.parameters.config.debug.max_fetch_nodes {
+            if dependency_graph.fetch_count() > max_fetch_nodes as usize {
+                return Ok(QueryPlanCost::INFINITY);
+            }
+        }
         if deferred.is_empty() {
             Ok(main)
         } else {
@@ -1120,6 +1182,8 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
             statistics: self.parameters.statistics,
             override_conditions: self.parameters.override_conditions.clone(),
             fetch_id_generator: self.parameters.fetch_id_generator.clone(),
+            deadline: self.parameters.deadline,
+            cancellation_token: self.parameters.cancellation_token.clone(),
         };
         let best_plan_opt = QueryPlanningTraversal::new_inner(
             &parameters,
@@ -1150,6 +1214,7 @@ impl<'a: 'b, 'b> PlanBuilder<PlanInfo, Arc<OpPathTree>> for QueryPlanningTravers
         plan_info: &PlanInfo,
         tree: Arc<OpPathTree>,
     ) -> Result<PlanInfo, FederationError> {
+        self.parameters.check_cancellation()?;
         let mut updated_graph = plan_info.fetch_dependency_graph.clone();
         self.updated_dependency_graph(
             &mut updated_graph,
@@ -30,6 +30,7 @@ pub(crate) mod argument_composition_strategies;
 pub(crate) mod definitions;
 pub(crate) mod field_set;
 pub(crate) mod position;
+pub mod printer;
 pub(crate) mod referencer;
 pub(crate) mod subgraph_metadata;
 
@@ -0,0 +1,367 @@
+//! Canonical SDL printing for supergraphs.
+//!
+//! The apollo-compiler printer keeps definitions in insertion order and prints described
+//! arguments with trailing commas, which makes the SDL of supergraphs noisy to diff. This printer
+//! produces the same output for the same schema regardless of how it was built:
+//! - the schema definition comes first, with one directive application per line,
+//! - directive definitions and types follow, each sorted by name,
+//! - descriptions are printed as block strings indented at the level of their definition,
+//! - arguments are printed on a single line, or one per line without separators when any of
+//!   them has a description.
+
+use std::fmt;
+use std::fmt::Display;
+
+use apollo_compiler::ast::Directive;
+use apollo_compiler::ast::DirectiveDefinition;
+use apollo_compiler::ast::FieldDefinition;
+use apollo_compiler::ast::InputValueDefinition;
+use apollo_compiler::schema::ComponentName;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::Node;
+use apollo_compiler::Schema;
+use itertools::Itertools;
+
+use crate::display_helpers::write_indented_lines;
+use crate::display_helpers::State;
+
+/// Prints a schema in the canonical SDL form, see the [module documentation](self).
+pub fn print_sdl(schema: &Schema) -> String {
+    PrintSdl(schema).to_string()
+}
+
+struct PrintSdl<'a>(&'a Schema);
+
+impl Display for PrintSdl<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let schema = self.0;
+        let built_ins = Schema::new();
+        let state = &mut State::new(f);
+
+        let mut first = true;
+        let mut separate = |state: &mut State<'_, '_>| {
+            if !std::mem::take(&mut first) {
+                state.write("\n\n")?;
+            }
+            Ok::<_, fmt::Error>(())
+        };
+
+        if has_schema_definition(schema) {
+            separate(state)?;
+            write_schema_definition(state, schema)?;
+        }
+        for (name, definition) in schema
+            .directive_definitions
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        {
+            if built_ins.directive_definitions.get(name) == Some(definition) {
+                continue;
+            }
+            separate(state)?;
+            write_directive_definition(state, definition)?;
+        }
+        for (_, ty) in schema.types.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+            if ty.is_built_in() {
+                continue;
+            }
+            separate(state)?;
+            write_type(state, ty)?;
+        }
+        if !first {
+            state.write("\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the schema definition carries information that the default root type names do not.
+fn has_schema_definition(schema: &Schema) -> bool {
+    let definition = &schema.schema_definition;
+    let is_default = |root: &Option<ComponentName>, default: &str| {
+        root.as_ref().map_or(true, |name| name.as_str() == default)
+    };
+    definition.description.is_some()
+        || !definition.directives.is_empty()
+        || !is_default(&definition.query, "Query")
+        || !is_default(&definition.mutation, "Mutation")
+        || !is_default(&definition.subscription, "Subscription")
+}
+
+fn write_schema_definition(state: &mut State<'_, '_>, schema: &Schema) -> fmt::Result {
+    let definition = &schema.schema_definition;
+    write_description(state, definition.description.as_deref())?;
+    state.write("schema")?;
+    if definition.directives.is_empty() {
+        state.write(" {")?;
+    } else {
+        write_indented_lines(state, &definition.directives, |state, directive| {
+            state.write(AsRef::<Directive>::as_ref(directive))
+        })?;
+        state.write("{")?;
+    }
+    let roots = [
+        ("query", &definition.query),
+        ("mutation", &definition.mutation),
+        ("subscription", &definition.subscription),
+    ];
+    let roots = roots
+        .iter()
+        .filter_map(|(operation, root)| Some((operation, root.as_ref()?)))
+        .collect_vec();
+    write_indented_lines(state, &roots, |state, (operation, root)| {
+        state.write_fmt(format_args!("{operation}: {root}"))
+    })?;
+    state.write("}")
+}
+
+fn write_directive_definition(
+    state: &mut State<'_, '_>,
+    definition: &DirectiveDefinition,
+) -> fmt::Result {
+    write_description(state, definition.description.as_deref())?;
+    state.write_fmt(format_args!("directive @{}", definition.name))?;
+    write_arguments_definition(state, &definition.arguments)?;
+    if definition.repeatable {
+        state.write(" repeatable")?;
+    }
+    state.write_fmt(format_args!(
+        " on {}",
+        definition.locations.iter().join(" | ")
+    ))
+}
+
+fn write_type(state: &mut State<'_, '_>, ty: &ExtendedType) -> fmt::Result {
+    write_description(state, ty.description().map(|description| &**description))?;
+    match ty {
+        ExtendedType::Scalar(scalar) => {
+            state.write_fmt(format_args!("scalar {}", scalar.name))?;
+            write_directives(state, &scalar.directives)
+        }
+        ExtendedType::Object(object) => {
+            state.write_fmt(format_args!("type {}", object.name))?;
+            write_implements(state, object.implements_interfaces.iter())?;
+            write_directives(state, &object.directives)?;
+            write_fields(state, object.fields.values().map(AsRef::as_ref))
+        }
+        ExtendedType::Interface(interface) => {
+            state.write_fmt(format_args!("interface {}", interface.name))?;
+            write_implements(state, interface.implements_interfaces.iter())?;
+            write_directives(state, &interface.directives)?;
+            write_fields(state, interface.fields.values().map(AsRef::as_ref))
+        }
+        ExtendedType::Union(union_) => {
+            state.write_fmt(format_args!("union {}", union_.name))?;
+            write_directives(state, &union_.directives)?;
+            if !union_.members.is_empty() {
+                state.write_fmt(format_args!(" = {}", union_.members.iter().join(" | ")))?;
+            }
+            Ok(())
+        }
+        ExtendedType::Enum(enum_) => {
+            state.write_fmt(format_args!("enum {}", enum_.name))?;
+            write_directives(state, &enum_.directives)?;
+            let values = enum_.values.values().collect_vec();
+            write_block(state, &values, |state, value| {
+                write_description(state, value.description.as_deref())?;
+                state.write(&value.value)?;
+                write_directives(state, &value.directives)
+            })
+        }
+        ExtendedType::InputObject(input) => {
+            state.write_fmt(format_args!("input {}", input.name))?;
+            write_directives(state, &input.directives)?;
+            let fields = input.fields.values().collect_vec();
+            write_block(state, &fields, |state, field| {
+                write_input_value_definition(state, field)
+            })
+        }
+    }
+}
+
+fn write_implements<'a>(
+    state: &mut State<'_, '_>,
+    interfaces: impl ExactSizeIterator<Item = &'a ComponentName>,
+) -> fmt::Result {
+    if interfaces.len() == 0 {
+        return Ok(());
+    }
+    state.write_fmt(format_args!(" implements {}", interfaces.format(" & ")))
+}
+
+fn write_fields<'a>(
+    state: &mut State<'_, '_>,
+    fields: impl Iterator<Item = &'a FieldDefinition>,
+) -> fmt::Result {
+    let fields = fields.collect_vec();
+    write_block(state, &fields, |state, field| {
+        write_description(state, field.description.as_deref())?;
+        state.write(&field.name)?;
+        write_arguments_definition(state, &field.arguments)?;
+        state.write_fmt(format_args!(": {}", field.ty))?;
+        write_directives(state, &field.directives)
+    })
+}
+
+/// Writes ` { ... }` with one item per line, or nothing if there are no items.
+fn write_block<T>(
+    state: &mut State<'_, '_>,
+    items: &[T],
+    write_item: impl FnMut(&mut State<'_, '_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    if items.is_empty() {
+        return Ok(());
+    }
+    state.write(" {")?;
+    write_indented_lines(state, items, write_item)?;
+    state.write("}")
+}
+
+fn write_arguments_definition(
+    state: &mut State<'_, '_>,
+    arguments: &[Node<InputValueDefinition>],
+) -> fmt::Result {
+    if arguments.is_empty() {
+        return Ok(());
+    }
+    state.write("(")?;
+    if arguments
+        .iter()
+        .any(|argument| argument.description.is_some())
+    {
+        write_indented_lines(state, arguments, |state, argument| {
+            write_input_value_definition(state, argument)
+        })?;
+    } else {
+        for (index, argument) in arguments.iter().enumerate() {
+            if index > 0 {
+                state.write(", ")?;
+            }
+            write_input_value_definition(state, argument)?;
+        }
+    }
+    state.write(")")
+}
+
+fn write_input_value_definition(
+    state: &mut State<'_, '_>,
+    definition: &InputValueDefinition,
+) -> fmt::Result {
+    write_description(state, definition.description.as_deref())?;
+    state.write_fmt(format_args!("{}: {}", definition.name, definition.ty))?;
+    if let Some(default_value) = &definition.default_value {
+        state.write_fmt(format_args!(" = {default_value}"))?;
+    }
+    write_directives(state, &definition.directives)
+}
+
+fn write_directives<I: AsRef<Directive>>(
+    state: &mut State<'_, '_>,
+    directives: &[I],
+) -> fmt::Result {
+    for directive in directives {
+        state.write_fmt(format_args!(" {}", directive.as_ref()))?;
+    }
+    Ok(())
+}
+
+/// Writes a description as a block string followed by a new line. Multi-line descriptions
+/// open and close the block string on their own lines, and are indented like the definition.
+fn write_description(state: &mut State<'_, '_>, description: Option<&str>) -> fmt::Result {
+    let Some(description) = description else {
+        return Ok(());
+    };
+    let description = description.replace(r#"""""#, r#"\""""#);
+    if !description.contains('\n') && !description.ends_with('"') {
+        state.write_fmt(format_args!(r#""""{description}""""#))?;
+        return state.new_line();
+    }
+    state.write(r#"""""#)?;
+    for line in description.lines() {
+        if line.trim().is_empty() {
+            // avoid trailing whitespace on blank lines
+            state.write("\n")?;
+        } else {
+            state.new_line()?;
+            state.write(line)?;
+        }
+    }
+    state.new_line()?;
+    state.write(r#"""""#)?;
+    state.new_line()
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_compiler::Schema;
+
+    use super::print_sdl;
+
+    #[test]
+    fn prints_canonical_sdl() {
+        let schema = Schema::parse(
+            r#"
+                type Query {
+                  "Returns tea"
+                  t(
+                    """
+                    An argument that is
+                    very important
+                    """
+                    x: String!
+                    y: Int = 1
+                  ): String
+                  u(a: Int, b: [String!]): Boolean @deprecated
+                }
+
+                directive @foo(url: String) repeatable on FIELD | OBJECT | SCHEMA
+
+                """
+                An enum
+
+                with a blank line
+                """
+                enum E { B A }
+
+                schema @foo { query: Query }
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+
+        assert_eq!(
+            print_sdl(&schema),
+            r#"schema
+  @foo
+{
+  query: Query
+}
+
+directive @foo(url: String) repeatable on FIELD | OBJECT | SCHEMA
+
+"""
+An enum
+
+with a blank line
+"""
+enum E {
+  B
+  A
+}
+
+type Query {
+  """Returns tea"""
+  t(
+    """
+    An argument that is
+    very important
+    """
+    x: String!
+    y: Int = 1
+  ): String
+  u(a: Int, b: [String!]): Boolean @deprecated
+}
+"#
+        );
+    }
+}
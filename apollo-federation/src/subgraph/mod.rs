@@ -33,6 +33,7 @@ use crate::ValidFederationSubgraph;
 
 mod database;
 pub mod spec;
+mod validation;
 
 pub struct Subgraph {
     pub name: String,
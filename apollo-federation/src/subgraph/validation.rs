@@ -0,0 +1,334 @@
+//! Validation of federation directive usage in a single subgraph schema
+
+use apollo_compiler::ast::Directive;
+use apollo_compiler::collections::IndexSet;
+use apollo_compiler::executable;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::schema::FieldDefinition;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Name;
+use apollo_compiler::Schema;
+
+use crate::error::MultipleFederationErrors;
+use crate::error::SingleFederationError;
+use crate::link::federation_spec_definition::FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_FIELDS_ARGUMENT_NAME;
+use crate::link::federation_spec_definition::FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::federation_spec_definition::FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC;
+use crate::link::spec::Identity;
+use crate::link::spec::Version;
+use crate::link::Link;
+use crate::schema::field_set::parse_field_set_without_normalization;
+use crate::subgraph::ValidSubgraph;
+
+/// The names of the federation directives, as imported in the subgraph schema
+struct FederationDirectiveNames {
+    key: Name,
+    requires: Name,
+    provides: Name,
+    external: Name,
+    interface_object: Name,
+}
+
+impl FederationDirectiveNames {
+    fn new(link: Option<&Link>) -> Self {
+        let name_in_schema = |name: Name| match link {
+            Some(link) => link.directive_name_in_schema(&name),
+            None => name,
+        };
+        Self {
+            key: name_in_schema(FEDERATION_KEY_DIRECTIVE_NAME_IN_SPEC),
+            requires: name_in_schema(FEDERATION_REQUIRES_DIRECTIVE_NAME_IN_SPEC),
+            provides: name_in_schema(FEDERATION_PROVIDES_DIRECTIVE_NAME_IN_SPEC),
+            external: name_in_schema(FEDERATION_EXTERNAL_DIRECTIVE_NAME_IN_SPEC),
+            interface_object: name_in_schema(FEDERATION_INTERFACEOBJECT_DIRECTIVE_NAME_IN_SPEC),
+        }
+    }
+}
+
+impl ValidSubgraph {
+    /// Checks the usage of the federation directives (`@key`, `@requires`, `@provides`,
+    /// `@external` and `@interfaceObject`) in this subgraph, returning all the misuses found.
+    pub fn validate_federation_directives(&self) -> Result<(), MultipleFederationErrors> {
+        let schema = &self.schema;
+        let federation_link =
+            Link::for_identity(schema, &Identity::federation_identity()).map(|(link, _)| link);
+        let names = FederationDirectiveNames::new(federation_link.as_ref());
+        let mut validator = Validator {
+            schema,
+            names: &names,
+            errors: MultipleFederationErrors { errors: vec![] },
+            used_external_fields: IndexSet::default(),
+        };
+
+        for (type_name, ty) in &schema.types {
+            if ty.is_built_in() {
+                continue;
+            }
+            for key in ty.directives().get_all(&names.key) {
+                validator.validate_key(type_name, key);
+            }
+            if ty.directives().has(&names.interface_object) {
+                validator.validate_interface_object(type_name, ty, federation_link.as_ref());
+            }
+            let fields = match ty {
+                ExtendedType::Object(object) => &object.fields,
+                ExtendedType::Interface(interface) => &interface.fields,
+                _ => continue,
+            };
+            for field in fields.values() {
+                for requires in field.directives.get_all(&names.requires) {
+                    validator.validate_requires(type_name, field, requires);
+                }
+                for provides in field.directives.get_all(&names.provides) {
+                    validator.validate_provides(type_name, field, provides);
+                }
+            }
+        }
+        validator.validate_external_fields_are_used();
+
+        if validator.errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+}
+
+struct Validator<'a> {
+    schema: &'a Valid<Schema>,
+    names: &'a FederationDirectiveNames,
+    errors: MultipleFederationErrors,
+    /// `(type name, field name)` of the fields referenced by a `@key`, `@requires` or `@provides`
+    used_external_fields: IndexSet<(Name, Name)>,
+}
+
+impl Validator<'_> {
+    fn field_set<'d>(&self, directive: &'d Directive) -> Option<&'d str> {
+        directive
+            .specified_argument_by_name(&FEDERATION_FIELDS_ARGUMENT_NAME)
+            .and_then(|value| value.as_str())
+    }
+
+    /// Parses a field set and records the fields it references.
+    fn parse_field_set(
+        &mut self,
+        parent_type_name: &Name,
+        field_set: &str,
+    ) -> Result<executable::SelectionSet, String> {
+        let selection_set =
+            parse_field_set_without_normalization(self.schema, parent_type_name.clone(), field_set)
+                .map_err(|err| err.to_string())?;
+        let mut stack = vec![&selection_set];
+        while let Some(selection_set) = stack.pop() {
+            for selection in &selection_set.selections {
+                match selection {
+                    executable::Selection::Field(field) => {
+                        self.used_external_fields
+                            .insert((selection_set.ty.clone(), field.name.clone()));
+                        stack.push(&field.selection_set);
+                    }
+                    executable::Selection::InlineFragment(fragment) => {
+                        stack.push(&fragment.selection_set);
+                    }
+                    executable::Selection::FragmentSpread(_) => {}
+                }
+            }
+        }
+        Ok(selection_set)
+    }
+
+    fn is_external(&self, type_name: &Name, field_name: &Name) -> bool {
+        self.schema
+            .type_field(type_name, field_name)
+            .is_ok_and(|field| field.directives.has(&self.names.external))
+    }
+
+    /// Names of the fields directly selected by a field set that are not `@external`
+    fn non_external_fields(&self, selection_set: &executable::SelectionSet) -> Vec<String> {
+        selection_set
+            .fields()
+            .filter(|field| {
+                field.name != "__typename" && !self.is_external(&selection_set.ty, &field.name)
+            })
+            .map(|field| format!("{}.{}", selection_set.ty, field.name))
+            .collect()
+    }
+
+    fn validate_key(&mut self, type_name: &Name, key: &Directive) {
+        let Some(field_set) = self.field_set(key) else {
+            return;
+        };
+        let selection_set = match self.parse_field_set(type_name, field_set) {
+            Ok(selection_set) => selection_set,
+            Err(message) => {
+                self.errors
+                    .errors
+                    .push(SingleFederationError::KeyInvalidFields {
+                        message: format!(
+                            "On type \"{type_name}\", for @key(fields: \"{field_set}\"): {message}"
+                        ),
+                    });
+                return;
+            }
+        };
+        let mut stack = vec![&selection_set];
+        while let Some(selection_set) = stack.pop() {
+            for field in selection_set.fields() {
+                if !field.definition.arguments.is_empty() {
+                    self.errors.errors.push(SingleFederationError::KeyFieldsHasArgs {
+                        message: format!(
+                            "On type \"{type_name}\", for @key(fields: \"{field_set}\"): field {}.{} cannot be included because it has arguments",
+                            selection_set.ty, field.name
+                        ),
+                    });
+                }
+                let field_type = field.definition.ty.inner_named_type();
+                if matches!(
+                    self.schema.types.get(field_type),
+                    Some(ExtendedType::Interface(_) | ExtendedType::Union(_))
+                ) {
+                    self.errors.errors.push(SingleFederationError::KeyFieldsSelectInvalidType {
+                        message: format!(
+                            "On type \"{type_name}\", for @key(fields: \"{field_set}\"): field {}.{} is an interface or union type which is not allowed in @key",
+                            selection_set.ty, field.name
+                        ),
+                    });
+                }
+                stack.push(&field.selection_set);
+            }
+        }
+    }
+
+    fn validate_requires(
+        &mut self,
+        type_name: &Name,
+        field: &FieldDefinition,
+        requires: &Directive,
+    ) {
+        let Some(field_set) = self.field_set(requires) else {
+            return;
+        };
+        let coordinate = format!("{type_name}.{}", field.name);
+        match self.parse_field_set(type_name, field_set) {
+            Ok(selection_set) => {
+                for non_external in self.non_external_fields(&selection_set) {
+                    self.errors.errors.push(SingleFederationError::RequiresFieldsMissingExternal {
+                        message: format!(
+                            "On field \"{coordinate}\", for @requires(fields: \"{field_set}\"): field \"{non_external}\" should not be part of a @requires since it is already provided by this subgraph (it is not marked @external)"
+                        ),
+                    });
+                }
+            }
+            Err(message) => self
+                .errors
+                .errors
+                .push(SingleFederationError::RequiresInvalidFields {
+                    message: format!(
+                    "On field \"{coordinate}\", for @requires(fields: \"{field_set}\"): {message}"
+                ),
+                }),
+        }
+    }
+
+    fn validate_provides(
+        &mut self,
+        type_name: &Name,
+        field: &FieldDefinition,
+        provides: &Directive,
+    ) {
+        let Some(field_set) = self.field_set(provides) else {
+            return;
+        };
+        let coordinate = format!("{type_name}.{}", field.name);
+        let target_type = field.ty.inner_named_type();
+        if !matches!(
+            self.schema.types.get(target_type),
+            Some(ExtendedType::Object(_) | ExtendedType::Interface(_) | ExtendedType::Union(_))
+        ) {
+            self.errors.errors.push(SingleFederationError::ProvidesOnNonObjectField {
+                message: format!(
+                    "Invalid @provides directive on field \"{coordinate}\": field has type \"{}\" which is not a Composite Type",
+                    field.ty
+                ),
+            });
+            return;
+        }
+        match self.parse_field_set(target_type, field_set) {
+            Ok(selection_set) => {
+                for non_external in self.non_external_fields(&selection_set) {
+                    self.errors.errors.push(SingleFederationError::ProvidesFieldsMissingExternal {
+                        message: format!(
+                            "On field \"{coordinate}\", for @provides(fields: \"{field_set}\"): field \"{non_external}\" should not be part of a @provides since it is already provided by this subgraph (it is not marked @external)"
+                        ),
+                    });
+                }
+            }
+            Err(message) => self
+                .errors
+                .errors
+                .push(SingleFederationError::ProvidesInvalidFields {
+                    message: format!(
+                    "On field \"{coordinate}\", for @provides(fields: \"{field_set}\"): {message}"
+                ),
+                }),
+        }
+    }
+
+    fn validate_interface_object(
+        &mut self,
+        type_name: &Name,
+        ty: &ExtendedType,
+        federation_link: Option<&Link>,
+    ) {
+        let required_version = Version { major: 2, minor: 3 };
+        if federation_link.is_some_and(|link| !link.url.version.satisfies(&required_version)) {
+            self.errors.errors.push(SingleFederationError::InterfaceObjectUsageError {
+                message: format!(
+                    "Type \"{type_name}\" uses @interfaceObject, which requires federation {required_version} or later"
+                ),
+            });
+        }
+        if !ty.directives().has(&self.names.key) {
+            self.errors.errors.push(SingleFederationError::InterfaceObjectUsageError {
+                message: format!(
+                    "The @interfaceObject directive can only be applied to entity types but type \"{type_name}\" has no @key in this subgraph."
+                ),
+            });
+        }
+    }
+
+    fn validate_external_fields_are_used(&mut self) {
+        for (type_name, ty) in &self.schema.types {
+            let ExtendedType::Object(object) = ty else {
+                continue;
+            };
+            for (field_name, field) in &object.fields {
+                if !field.directives.has(&self.names.external)
+                    || self
+                        .used_external_fields
+                        .contains(&(type_name.clone(), field_name.clone()))
+                {
+                    continue;
+                }
+                // Fields implementing an interface field are needed for the type to be valid
+                let implements_interface_field =
+                    object.implements_interfaces.iter().any(|interface| {
+                        self.schema
+                            .get_interface(interface)
+                            .is_some_and(|interface| interface.fields.contains_key(field_name))
+                    });
+                if implements_interface_field {
+                    continue;
+                }
+                self.errors.errors.push(SingleFederationError::ExternalUnused {
+                    message: format!(
+                        "Field \"{type_name}.{field_name}\" is marked @external but is not used in any federation directive (@key, @provides, @requires) or to satisfy an interface; the field declaration has no use and should be removed (or the field should not be @external)."
+                    ),
+                });
+            }
+        }
+    }
+}
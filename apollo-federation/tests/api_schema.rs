@@ -1,4 +1,5 @@
 use apollo_compiler::coord;
+use apollo_compiler::name;
 use apollo_compiler::schema::ExtendedType;
 use apollo_compiler::validation::Valid;
 use apollo_compiler::Schema;
@@ -2340,6 +2341,7 @@ fn include_supergraph_directives() -> Result<(), FederationError> {
     let api_schema = graph.to_api_schema(ApiSchemaOptions {
         include_defer: true,
         include_stream: true,
+        ..Default::default()
     })?;
 
     insta::assert_snapshot!(api_schema.schema(), @r###"
@@ -2355,6 +2357,125 @@ fn include_supergraph_directives() -> Result<(), FederationError> {
     Ok(())
 }
 
+const RETAINED_DIRECTIVES_SUPERGRAPH: &str = r#"
+    directive @link(url: String!, as: String, import: [link__Import], for: link__Purpose) repeatable on SCHEMA
+
+    scalar link__Import
+
+    enum link__Purpose {
+      EXECUTION
+      SECURITY
+    }
+
+    directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+
+    directive @cache(maxAge: cache__Duration!) on FIELD_DEFINITION
+
+    scalar cache__Duration
+
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/tag/v0.3")
+      @link(url: "https://example.com/cache/v1.0", import: ["@cache"])
+    {
+      query: Query
+    }
+
+    "The root query type"
+    type Query {
+      "A product"
+      product(
+        "The product id"
+        id: ID!
+      ): Product @tag(name: "public") @cache(maxAge: "1m")
+    }
+
+    """
+    A product
+    """
+    type Product {
+      "Its name"
+      name: String @deprecated(reason: "use title")
+      title: String
+      status: Status
+    }
+
+    "Availability"
+    enum Status {
+      "In stock"
+      AVAILABLE
+      SOLD_OUT
+    }
+"#;
+
+#[test]
+fn removes_linked_directives_by_default() -> Result<(), FederationError> {
+    let graph = Supergraph::new(RETAINED_DIRECTIVES_SUPERGRAPH)?;
+    let api_schema = graph.to_api_schema(Default::default())?;
+    let schema = api_schema.schema();
+
+    assert!(!schema.directive_definitions.contains_key("tag"));
+    assert!(!schema.directive_definitions.contains_key("cache"));
+    assert!(!schema.types.contains_key("cache__Duration"));
+    let product = schema.type_field("Query", "product").unwrap();
+    assert!(product.directives.is_empty());
+    assert_eq!(product.description.as_deref(), Some("A product"));
+
+    Ok(())
+}
+
+#[test]
+fn retains_selected_directives() -> Result<(), FederationError> {
+    let graph = Supergraph::new(RETAINED_DIRECTIVES_SUPERGRAPH)?;
+    let api_schema = graph.to_api_schema(ApiSchemaOptions {
+        retain_directives: vec![name!("cache")],
+        ..Default::default()
+    })?;
+    let schema = api_schema.schema();
+
+    assert!(!schema.directive_definitions.contains_key("tag"));
+    assert!(schema.directive_definitions.contains_key("cache"));
+    // The types used by the arguments of retained directives are kept as well
+    assert!(schema.types.contains_key("cache__Duration"));
+    let product = schema.type_field("Query", "product").unwrap();
+    assert!(!product.directives.has("tag"));
+    assert_eq!(
+        product.directives.get("cache").unwrap().to_string(),
+        r#"@cache(maxAge: "1m")"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn strips_descriptions() -> Result<(), FederationError> {
+    let graph = Supergraph::new(RETAINED_DIRECTIVES_SUPERGRAPH)?;
+    let api_schema = graph.to_api_schema(ApiSchemaOptions {
+        strip_descriptions: true,
+        ..Default::default()
+    })?;
+
+    // Built-in directives like `@deprecated` are kept
+    insta::assert_snapshot!(api_schema.schema(), @r###"
+    type Query {
+      product(id: ID!): Product
+    }
+
+    type Product {
+      name: String @deprecated(reason: "use title")
+      title: String
+      status: Status
+    }
+
+    enum Status {
+      AVAILABLE
+      SOLD_OUT
+    }
+    "###);
+
+    Ok(())
+}
+
 #[test]
 fn supports_core_directive_supergraph() {
     let sdl = r#"
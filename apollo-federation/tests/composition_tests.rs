@@ -1,4 +1,5 @@
 use apollo_compiler::Schema;
+use apollo_federation::merge::Severity;
 use apollo_federation::subgraph::Subgraph;
 use apollo_federation::Supergraph;
 
@@ -198,3 +199,487 @@ fn compose_removes_federation_directives() {
             .schema()
     ));
 }
+
+#[test]
+fn compose_reports_diagnostics_with_subgraph_and_location() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            type Query {
+              t: T
+            }
+
+            "A T"
+            type T {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            "Another T"
+            type T {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+
+    let (_, hints) = Supergraph::compose_with_diagnostics(vec![&s1, &s2]).unwrap();
+    assert_eq!(hints.len(), 1);
+    let hint = &hints[0];
+    assert_eq!(hint.code, "INCONSISTENT_DESCRIPTION");
+    assert_eq!(hint.subgraph.as_deref(), Some("SubgraphB"));
+    assert!(hint.message.contains("type `T`"), "{}", hint.message);
+    assert_eq!(hint.locations.len(), 1);
+    assert_eq!(hint.locations[0].start_line, 2);
+    assert!(hint.suggestion.is_some());
+}
+
+#[test]
+fn compose_reports_invalid_subgraph_names() {
+    let s1 = Subgraph::parse_and_expand(
+        "my-subgraph",
+        "https://subgraph",
+        r#"
+            type Query {
+              t: ID
+            }
+        "#,
+    )
+    .unwrap();
+
+    let failure = Supergraph::compose(vec![&s1]).unwrap_err();
+    assert_eq!(failure.errors.len(), 1);
+    assert_eq!(failure.errors[0].code, "INVALID_SUBGRAPH_NAME");
+    assert_eq!(failure.errors[0].subgraph.as_deref(), Some("my-subgraph"));
+}
+
+#[test]
+fn compose_returns_hints_alongside_supergraph() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@external", "@override" ])
+
+            type Query {
+              products(first: Int = 10): [Product!]
+            }
+
+            type Product @key(fields: "sku") {
+              sku: String!
+              name: String
+              price: Int @override(from: "SubgraphC")
+              weight: Int @external
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@shareable" ])
+
+            type Query {
+              products(first: Int = 20): [Product!] @shareable
+            }
+
+            type Product @key(fields: "sku") {
+              sku: String!
+              name: String! @shareable
+            }
+        "#,
+    )
+    .unwrap();
+
+    let (_, hints) = Supergraph::compose_with_diagnostics(vec![&s1, &s2]).unwrap();
+    let hints: Vec<_> = hints
+        .iter()
+        .map(|hint| (hint.code.as_str(), hint.severity, hint.subgraph.as_deref()))
+        .collect();
+    assert_eq!(
+        hints,
+        [
+            ("UNUSED_EXTERNAL", Severity::Warn, Some("SubgraphA")),
+            (
+                "FROM_SUBGRAPH_DOES_NOT_EXIST",
+                Severity::Warn,
+                Some("SubgraphA")
+            ),
+            (
+                "INCONSISTENT_DEFAULT_VALUE",
+                Severity::Warn,
+                Some("SubgraphB")
+            ),
+            (
+                "INCONSISTENT_BUT_COMPATIBLE_FIELD_TYPE",
+                Severity::Info,
+                Some("SubgraphB")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn compose_reports_all_conflicts_at_once() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@shareable" ])
+
+            type Query {
+              products(status: Status): [Product] @shareable
+              node: Node
+            }
+
+            interface Node @key(fields: "id") {
+              id: ID!
+            }
+
+            type Product implements Node @key(fields: "sku") {
+              id: ID!
+              sku: String!
+              price: Int @shareable
+            }
+
+            enum Status {
+              AVAILABLE
+              SOLD_OUT
+            }
+
+            type Review {
+              body: String
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@shareable" ])
+
+            type Query {
+              products(status: Status): [Product] @shareable
+            }
+
+            type Product @key(fields: "sku") {
+              sku: String!
+              price: String @shareable
+            }
+
+            enum Status {
+              AVAILABLE
+            }
+
+            interface Review {
+              body: String
+            }
+        "#,
+    )
+    .unwrap();
+
+    let failure = Supergraph::compose(vec![&s1, &s2]).unwrap_err();
+    let errors: Vec<_> = failure
+        .errors
+        .iter()
+        .map(|error| (error.code.as_str(), error.subgraph.as_deref()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("INTERFACE_KEY_NOT_ON_IMPLEMENTATION", Some("SubgraphA")),
+            ("TYPE_KIND_MISMATCH", Some("SubgraphB")),
+            ("FIELD_TYPE_MISMATCH", Some("SubgraphB")),
+            ("ENUM_VALUE_MISMATCH", Some("SubgraphB")),
+        ]
+    );
+
+    let kind_mismatch = &failure.errors[1];
+    assert!(
+        kind_mismatch.message.contains("`Review`"),
+        "{}",
+        kind_mismatch.message
+    );
+    // Both definitions of the type are located
+    assert_eq!(kind_mismatch.locations.len(), 2);
+
+    let field_mismatch = &failure.errors[2];
+    assert!(
+        field_mismatch.message.contains("`Product.price`"),
+        "{}",
+        field_mismatch.message
+    );
+    assert_eq!(field_mismatch.locations.len(), 2);
+
+    let enum_mismatch = &failure.errors[3];
+    assert!(
+        enum_mismatch.message.contains("`SOLD_OUT`"),
+        "{}",
+        enum_mismatch.message
+    );
+}
+
+#[test]
+fn compose_accepts_output_fields_with_subtypes() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@shareable" ])
+
+            type Query {
+              media: Media @shareable
+            }
+
+            interface Media {
+              title: String
+            }
+
+            type Book implements Media {
+              title: String
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@shareable" ])
+
+            type Query {
+              media: Book @shareable
+            }
+
+            interface Media {
+              title: String
+            }
+
+            type Book implements Media {
+              title: String
+            }
+        "#,
+    )
+    .unwrap();
+
+    let (_, hints) = Supergraph::compose_with_diagnostics(vec![&s1, &s2]).unwrap();
+    let hints: Vec<_> = hints.iter().map(|hint| hint.code.as_str()).collect();
+    assert_eq!(hints, ["INCONSISTENT_BUT_COMPATIBLE_FIELD_TYPE"]);
+}
+
+#[test]
+fn compose_keeps_composed_and_common_executable_directives() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@composeDirective" ])
+              @link(url: "https://example.com/cache/v1.0", import: [ "@cached" ])
+              @composeDirective(name: "@cached")
+
+            directive @cached(ttl: Int) on OBJECT | FIELD_DEFINITION
+            directive @trace(level: Int) on FIELD | QUERY
+            directive @debug on FIELD
+
+            type Query {
+              t: T @cached(ttl: 60)
+            }
+
+            type T @key(fields: "k") @cached(ttl: 30) {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@composeDirective" ])
+              @link(url: "https://example.com/cache/v1.0", import: [ "@cached" ])
+              @composeDirective(name: "@cached")
+
+            directive @cached(ttl: Int) on FIELD_DEFINITION | OBJECT
+            directive @trace(level: Int, verbose: Boolean) on FIELD
+
+            type T @key(fields: "k") @cached(ttl: 30) {
+              k: ID
+              a: Int
+            }
+        "#,
+    )
+    .unwrap();
+
+    let (supergraph, hints) = Supergraph::compose_with_diagnostics(vec![&s1, &s2]).unwrap();
+    let schema = supergraph.schema.schema();
+
+    assert!(schema.directive_definitions.contains_key("cached"));
+    let link = schema
+        .schema_definition
+        .directives
+        .iter()
+        .find(|link| link.to_string().contains("https://example.com/cache/v1.0"))
+        .expect("composed directive spec is linked");
+    assert_eq!(
+        link.to_string(),
+        r#"@link(url: "https://example.com/cache/v1.0", import: ["@cached"])"#
+    );
+    // identical applications from both subgraphs are kept once
+    assert_eq!(schema.types["T"].directives().get_all("cached").count(), 1);
+    assert!(schema
+        .type_field("Query", "t")
+        .unwrap()
+        .directives
+        .has("cached"));
+
+    // executable directives keep the locations and arguments common to all subgraphs
+    assert_eq!(
+        schema.directive_definitions["trace"].to_string(),
+        "directive @trace(level: Int) on FIELD"
+    );
+    assert!(!schema.directive_definitions.contains_key("debug"));
+    let hints: Vec<_> = hints.iter().map(|hint| hint.code.as_str()).collect();
+    assert_eq!(hints, ["INCONSISTENT_EXECUTABLE_DIRECTIVE_PRESENCE"]);
+}
+
+#[test]
+fn compose_reports_composed_directive_conflicts() {
+    let s1 = Subgraph::parse_and_expand(
+        "SubgraphA",
+        "https://subgraphA",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@composeDirective" ])
+              @link(url: "https://example.com/cache/v1.0", import: [ "@cached" ])
+              @composeDirective(name: "@cached")
+              @composeDirective(name: "@missing")
+
+            directive @cached(ttl: Int) on OBJECT
+
+            type Query {
+              t: T
+            }
+
+            type T @key(fields: "k") @cached(ttl: 30) {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "SubgraphB",
+        "https://subgraphB",
+        r#"
+            extend schema
+              @link(url: "https://specs.apollo.dev/federation/v2.5", import: [ "@key", "@composeDirective" ])
+              @link(url: "https://example.com/cache/v1.0", import: [ "@cached" ])
+              @composeDirective(name: "@cached")
+
+            directive @cached(ttl: String) on OBJECT
+
+            type T @key(fields: "k") {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+
+    let failure = Supergraph::compose(vec![&s1, &s2]).unwrap_err();
+    let errors: Vec<_> = failure
+        .errors
+        .iter()
+        .map(|error| (error.code.as_str(), error.subgraph.as_deref()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("DIRECTIVE_COMPOSITION_ERROR", Some("SubgraphA")),
+            ("DIRECTIVE_COMPOSITION_ERROR", Some("SubgraphB")),
+        ]
+    );
+    assert!(
+        failure.errors[0].message.contains("\"@missing\""),
+        "{}",
+        failure.errors[0].message
+    );
+    // Both definitions of the directive are located
+    assert_eq!(failure.errors[1].locations.len(), 2);
+}
+
+#[test]
+fn supergraph_sdl_is_canonical() {
+    let s1 = Subgraph::parse_and_expand(
+        "Subgraph1",
+        "https://subgraph1",
+        r#"
+            type Query {
+              """
+              Returns tea
+
+              Or coffee
+              """
+              t(
+                "An argument that is very important"
+                x: String!
+              ): String
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "Subgraph2",
+        "https://subgraph2",
+        r#"
+            enum E {
+              A
+              B
+            }
+        "#,
+    )
+    .unwrap();
+
+    let sdl = Supergraph::compose(vec![&s1, &s2]).unwrap().to_sdl();
+    assert!(
+        sdl.contains(
+            r#"
+  """
+  Returns tea
+
+  Or coffee
+  """
+  t(
+    """An argument that is very important"""
+    x: String!
+  ): String @join__field(graph: SUBGRAPH1)
+"#
+        ),
+        "{sdl}"
+    );
+    let positions: Vec<_> = [
+        "directive @join__enumValue",
+        "directive @link",
+        "enum E",
+        "type Query",
+    ]
+    .iter()
+    .map(|definition| sdl.find(definition).unwrap())
+    .collect();
+    assert!(positions.is_sorted(), "{sdl}");
+
+    // printing is stable through a round trip
+    let reparsed = Schema::parse(&sdl, "supergraph.graphql").unwrap();
+    assert_eq!(
+        apollo_federation::schema::printer::print_sdl(&reparsed),
+        sdl
+    );
+}
@@ -96,7 +96,7 @@ pub(crate) fn api_schema_and_planner(
     let planner = QueryPlanner::new(&supergraph, config.clone()).unwrap();
     let api_schema_config = apollo_federation::ApiSchemaOptions {
         include_defer: config.incremental_delivery.enable_defer,
-        include_stream: false,
+        ..Default::default()
     };
     let api_schema = supergraph.to_api_schema(api_schema_config).unwrap();
     (api_schema, planner)
@@ -33,7 +33,10 @@ fn some_name() {
 
 mod context;
 mod debug_max_evaluated_plans_configuration;
+mod debug_max_fetch_nodes_configuration;
+mod debug_planning_timeout_configuration;
 mod defer;
+mod disabled_subgraphs;
 mod entities;
 mod fetch_operation_names;
 mod field_merging_with_skip_and_include;
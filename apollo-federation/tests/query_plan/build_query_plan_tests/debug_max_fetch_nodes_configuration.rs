@@ -0,0 +1,134 @@
+use apollo_compiler::name;
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::error::FederationError;
+use apollo_federation::error::SingleFederationError;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::query_plan::query_planner::QueryPlannerDebugConfig;
+
+fn config(max_fetch_nodes: u32) -> QueryPlannerConfig {
+    QueryPlannerConfig {
+        debug: QueryPlannerDebugConfig {
+            max_fetch_nodes: Some(max_fetch_nodes),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn aborts_planning_beyond_the_limit() {
+    let (api_schema, planner) = planner!(
+        config = config(1),
+        Subgraph1: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop1: String
+          }
+        "#,
+        Subgraph2: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop2: String
+          }
+        "#,
+    );
+    let document = ExecutableDocument::parse_and_validate(
+        api_schema.schema(),
+        r#"
+          query Me {
+            me {
+              prop1
+              prop2
+            }
+          }
+        "#,
+        "aborts_planning_beyond_the_limit.graphql",
+    )
+    .unwrap();
+
+    let error = planner
+        .build_query_plan(&document, Some(name!(Me)), Default::default())
+        .expect_err("should return an error");
+    assert!(matches!(
+        error,
+        FederationError::SingleFederationError(SingleFederationError::QueryPlanTooLarge {
+            max_fetch_nodes: 1
+        })
+    ));
+}
+
+#[test]
+fn plans_within_the_limit() {
+    let planner = planner!(
+        config = config(2),
+        Subgraph1: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop1: String
+          }
+        "#,
+        Subgraph2: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop2: String
+          }
+        "#,
+    );
+    assert_plan!(
+        &planner,
+        r#"
+          {
+            me {
+              prop1
+              prop2
+            }
+          }
+        "#,
+        @r###"
+          QueryPlan {
+            Sequence {
+              Fetch(service: "Subgraph1") {
+                {
+                  me {
+                    __typename
+                    id
+                    prop1
+                  }
+                }
+              },
+              Flatten(path: "me") {
+                Fetch(service: "Subgraph2") {
+                  {
+                    ... on User {
+                      __typename
+                      id
+                    }
+                  } =>
+                  {
+                    ... on User {
+                      prop2
+                    }
+                  }
+                },
+              },
+            },
+          }
+        "###
+    );
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use apollo_compiler::name;
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::error::FederationError;
+use apollo_federation::error::SingleFederationError;
+use apollo_federation::query_plan::query_planner::QueryPlanOptions;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::query_plan::query_planner::QueryPlannerDebugConfig;
+
+#[test]
+fn aborts_planning_after_the_timeout() {
+    let (api_schema, planner) = planner!(
+        config = QueryPlannerConfig {
+            debug: QueryPlannerDebugConfig {
+                planning_timeout: Some(Duration::ZERO),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Subgraph1: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop1: String
+          }
+        "#,
+        Subgraph2: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop2: String
+          }
+        "#,
+    );
+    let document = ExecutableDocument::parse_and_validate(
+        api_schema.schema(),
+        r#"
+          query Me {
+            me {
+              prop1
+              prop2
+            }
+          }
+        "#,
+        "aborts_planning_after_the_timeout.graphql",
+    )
+    .unwrap();
+
+    let error = planner
+        .build_query_plan(&document, Some(name!(Me)), Default::default())
+        .expect_err("should return an error");
+    assert!(matches!(
+        error,
+        FederationError::SingleFederationError(SingleFederationError::QueryPlanningTimeout {
+            timeout: Duration::ZERO
+        })
+    ));
+}
+
+#[test]
+fn aborts_planning_once_cancelled() {
+    let (api_schema, planner) = planner!(
+        Subgraph1: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop1: String
+          }
+        "#,
+        Subgraph2: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop2: String
+          }
+        "#,
+    );
+    let document = ExecutableDocument::parse_and_validate(
+        api_schema.schema(),
+        r#"
+          query Me {
+            me {
+              prop1
+              prop2
+            }
+          }
+        "#,
+        "aborts_planning_once_cancelled.graphql",
+    )
+    .unwrap();
+
+    let options = QueryPlanOptions {
+        cancellation_token: Some(Arc::new(AtomicBool::new(true))),
+        ..Default::default()
+    };
+    let error = planner
+        .build_query_plan(&document, Some(name!(Me)), options)
+        .expect_err("should return an error");
+    assert!(matches!(
+        error,
+        FederationError::SingleFederationError(SingleFederationError::QueryPlanningCancelled)
+    ));
+}
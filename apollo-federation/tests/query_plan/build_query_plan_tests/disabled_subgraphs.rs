@@ -0,0 +1,72 @@
+use apollo_federation::query_plan::query_planner::QueryPlanOptions;
+
+#[test]
+fn avoids_disabled_subgraphs_for_shareable_fields() {
+    let planner = planner!(
+        Subgraph1: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop1: String
+          }
+        "#,
+        Subgraph2: r#"
+          type Query {
+            me: User! @shareable
+          }
+
+          type User @key(fields: "id") {
+            id: ID!
+            prop2: String
+          }
+        "#,
+    );
+    assert_plan!(
+        &planner,
+        r#"
+          {
+            me {
+              prop1
+              prop2
+            }
+          }
+        "#,
+        QueryPlanOptions {
+            disabled_subgraphs: vec!["Subgraph1".to_string()],
+            ..Default::default()
+        },
+        @r###"
+          QueryPlan {
+            Sequence {
+              Fetch(service: "Subgraph2") {
+                {
+                  me {
+                    __typename
+                    id
+                    prop2
+                  }
+                }
+              },
+              Flatten(path: "me") {
+                Fetch(service: "Subgraph1") {
+                  {
+                    ... on User {
+                      __typename
+                      id
+                    }
+                  } =>
+                  {
+                    ... on User {
+                      prop1
+                    }
+                  }
+                },
+              },
+            },
+          }
+        "###
+    );
+}
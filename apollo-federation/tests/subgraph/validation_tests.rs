@@ -0,0 +1,89 @@
+use apollo_federation::subgraph::Subgraph;
+
+fn error_codes(schema: &str) -> Vec<String> {
+    let subgraph = Subgraph::parse_and_expand("S1", "http://s1", schema).unwrap();
+    match subgraph.validate_federation_directives() {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .errors
+            .iter()
+            .map(|error| error.code().definition().code().to_string())
+            .collect(),
+    }
+}
+
+#[test]
+fn accepts_valid_federation_directives() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external", "@requires", "@provides", "@interfaceObject" ])
+
+        type Query {
+            t: T @provides(fields: "name")
+        }
+
+        type T @key(fields: "id") {
+            id: ID!
+            name: String @external
+            weight: Int @external
+            shipping: Int @requires(fields: "weight")
+        }
+
+        type I @key(fields: "id") @interfaceObject {
+            id: ID!
+        }
+        "#;
+
+    assert_eq!(error_codes(schema), Vec::<String>::new());
+}
+
+#[test]
+fn reports_federation_directive_misuses() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.3", import: [ "@key", "@external", "@requires", "@interfaceObject" ])
+
+        type Query {
+            t: T
+        }
+
+        type T @key(fields: "unknown") {
+            id: ID!
+            weight: Int
+            unused: Int @external
+            shipping: Int @requires(fields: "weight")
+        }
+
+        type I @interfaceObject {
+            id: ID!
+        }
+        "#;
+
+    assert_eq!(
+        error_codes(schema),
+        [
+            "KEY_INVALID_FIELDS",
+            "REQUIRES_FIELDS_MISSING_EXTERNAL",
+            "INTERFACE_OBJECT_USAGE_ERROR",
+            "EXTERNAL_UNUSED",
+        ]
+    );
+}
+
+#[test]
+fn reports_interface_object_before_federation_2_3() {
+    let schema = r#"
+        extend schema
+          @link(url: "https://specs.apollo.dev/federation/v2.1", import: [ "@key", "@interfaceObject" ])
+
+        type Query {
+            i: I
+        }
+
+        type I @key(fields: "id") @interfaceObject {
+            id: ID!
+        }
+        "#;
+
+    assert_eq!(error_codes(schema), ["INTERFACE_OBJECT_USAGE_ERROR"]);
+}
@@ -0,0 +1,88 @@
+//! Compare serde_json and simd-json on the JSON documents parsed by the Router: client request
+//! bodies and subgraph responses.
+//!
+//! Run with `cargo bench --bench json_parsing --features simd-json`
+
+use std::fmt::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+use apollo_router::graphql;
+use bytes::Bytes;
+use serde_json_bytes::Value;
+
+const ITERATIONS: u32 = 100;
+
+fn main() {
+    println!("Columns:");
+    println!("* Parsed document");
+    println!("* Average time with serde_json");
+    println!("* Average time with simd-json");
+    println!();
+    for entities in [10, 1_000, 100_000] {
+        let response = subgraph_response(entities);
+        let serde_json = average(|| {
+            Value::from_bytes(response.clone()).unwrap();
+        });
+        let simd_json = average(|| {
+            let mut buffer = response.to_vec();
+            simd_json::serde::from_slice::<Value>(&mut buffer).unwrap();
+        });
+        println!(
+            "subgraph response, {entities:>6} entities {serde_json:>12.2?} {simd_json:>12.2?}"
+        );
+    }
+    for variables in [10, 1_000, 100_000] {
+        let request = client_request(variables);
+        let serde_json = average(|| {
+            serde_json::from_slice::<graphql::Request>(&request).unwrap();
+        });
+        let simd_json = average(|| {
+            let mut buffer = request.to_vec();
+            simd_json::serde::from_slice::<graphql::Request>(&mut buffer).unwrap();
+        });
+        println!(
+            "client request, {variables:>6} variables  {serde_json:>12.2?} {simd_json:>12.2?}"
+        );
+    }
+}
+
+fn average(mut parse: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        parse();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+/// An `_entities` response, as returned by subgraphs for large lists
+fn subgraph_response(entities: usize) -> Bytes {
+    let mut json = String::from(r#"{"data":{"_entities":["#);
+    for i in 0..entities {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(
+            json,
+            r#"{{"__typename":"Product","id":"{i}","name":"Product {i}","price":{i}.99,"inStock":true,"tags":["a","b","c"]}}"#
+        )
+        .unwrap();
+    }
+    json.push_str("]}}");
+    json.into()
+}
+
+/// A client request with a large list of input variables
+fn client_request(variables: usize) -> Bytes {
+    let mut json = String::from(
+        r#"{"query":"query($ids: [ID!]!) { products(ids: $ids) { id name } }","variables":{"ids":["#,
+    );
+    for i in 0..variables {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(json, r#""product-{i}""#).unwrap();
+    }
+    json.push_str("]}}");
+    json.into()
+}
@@ -0,0 +1,61 @@
+//! Measure how long the Router takes to reload an unchanged schema, when it is parsed and
+//! planned again or when the previously parsed schema and query planner are reused.
+//!
+//! Run with `cargo bench --bench schema_reload`
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use apollo_router::_private::measure_schema_reload;
+
+const BASE_SCHEMA: &str = include_str!("../src/testdata/minimal_supergraph.graphql");
+
+const CONFIGURATION: &str = "
+supergraph:
+  introspection: true
+";
+
+const ITERATIONS: u32 = 5;
+
+#[tokio::main]
+async fn main() {
+    println!("Columns:");
+    println!("* Number of object types in the schema");
+    println!("* Reload time when the schema is parsed and planned again");
+    println!("* Reload time when the previous schema and query planner are reused");
+    println!();
+    for types in [100, 1_000, 5_000] {
+        let schema = large_schema(types);
+        let reparse = average(&schema, false).await;
+        let reuse = average(&schema, true).await;
+        println!("{types:>5} {reparse:>12.2?} {reuse:>12.2?}");
+    }
+}
+
+async fn average(schema: &str, reuse_schema: bool) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        total += measure_schema_reload(schema, CONFIGURATION, reuse_schema).await;
+    }
+    total / ITERATIONS
+}
+
+/// The minimal supergraph, with object types reachable from the root query type
+fn large_schema(types: usize) -> String {
+    let mut schema = BASE_SCHEMA.replace(
+        "  me: String @join__field(graph: SUBGRAPH_A)\n",
+        "  me: String @join__field(graph: SUBGRAPH_A)\n  type0: Type0 @join__field(graph: SUBGRAPH_A)\n",
+    );
+    for i in 0..types {
+        writeln!(schema).unwrap();
+        writeln!(schema, "type Type{i} @join__type(graph: SUBGRAPH_A) {{").unwrap();
+        for field in 0..10 {
+            writeln!(schema, "  field{field}: String").unwrap();
+        }
+        if i + 1 < types {
+            writeln!(schema, "  next: Type{}", i + 1).unwrap();
+        }
+        writeln!(schema, "}}").unwrap();
+    }
+    schema
+}
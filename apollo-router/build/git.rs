@@ -0,0 +1,29 @@
+use std::error::Error;
+use std::process::Command;
+
+/// Exposes the commit the router is built from as `APOLLO_ROUTER_GIT_SHA`
+///
+/// The variable can be set when building outside of a git checkout, like from a source archive.
+pub fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=APOLLO_ROUTER_GIT_SHA");
+    if std::env::var_os("APOLLO_ROUTER_GIT_SHA").is_some() {
+        return Ok(());
+    }
+
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"])? {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+    }
+    if let Some(sha) = git(&["rev-parse", "HEAD"])? {
+        println!("cargo:rustc-env=APOLLO_ROUTER_GIT_SHA={sha}");
+    }
+    Ok(())
+}
+
+fn git(args: &[&str]) -> Result<Option<String>, Box<dyn Error>> {
+    match Command::new("git").args(args).output() {
+        Ok(output) if output.status.success() => {
+            Ok(Some(String::from_utf8(output.stdout)?.trim().to_string()))
+        }
+        _ => Ok(None),
+    }
+}
@@ -1,5 +1,7 @@
+mod git;
 mod studio;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    git::main()?;
     studio::main()
 }
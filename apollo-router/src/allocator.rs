@@ -0,0 +1,145 @@
+//! Memory instrumentation, enabled by the `memory-instrumentation` feature
+//!
+//! The global allocator is wrapped to count the bytes allocated by each thread. Measuring that
+//! count around each poll of a future estimates the memory it allocated, even when it moves
+//! between the threads of the runtime. Allocations made by the other tasks it spawns are not
+//! counted.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use pin_project_lite::pin_project;
+
+thread_local! {
+    static ALLOCATED: Cell<u64> = const { Cell::new(0) };
+}
+
+fn record_allocation(size: usize) {
+    // The thread local is unavailable while the thread is being destroyed
+    let _ =
+        ALLOCATED.try_with(|allocated| allocated.set(allocated.get().wrapping_add(size as u64)));
+}
+
+/// Number of bytes allocated by the current thread since it started
+pub(crate) fn thread_allocated() -> u64 {
+    ALLOCATED.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Global allocator counting the bytes allocated by each thread
+pub(crate) struct TrackingAllocator<A> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub(crate) const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation(new_size.saturating_sub(layout.size()));
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+pin_project! {
+    /// Future counting the bytes allocated while it is polled
+    pub(crate) struct AllocationTracked<F> {
+        #[pin]
+        inner: F,
+        allocated: u64,
+    }
+}
+
+impl<F> AllocationTracked<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            allocated: 0,
+        }
+    }
+}
+
+impl<F: Future> Future for AllocationTracked<F> {
+    /// The output of the future and an estimate of the bytes it allocated
+    type Output = (F::Output, u64);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let before = thread_allocated();
+        let poll = this.inner.poll(cx);
+        *this.allocated += thread_allocated().wrapping_sub(before);
+        poll.map(|output| (output, *this.allocated))
+    }
+}
+
+/// Heap statistics of jemalloc, in bytes
+#[cfg(target_os = "linux")]
+pub(crate) struct HeapStats {
+    /// Bytes allocated by the router
+    pub(crate) allocated: u64,
+    /// Bytes in the pages of the heap that are in use
+    pub(crate) active: u64,
+    /// Bytes of the heap mapped in physical memory
+    pub(crate) resident: u64,
+    /// Bytes of the heap that are mapped
+    pub(crate) mapped: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl HeapStats {
+    /// Reads the latest statistics. They are cached by jemalloc, and refreshed by advancing its
+    /// epoch.
+    pub(crate) fn read() -> Result<Self, tikv_jemalloc_ctl::Error> {
+        use tikv_jemalloc_ctl::epoch;
+        use tikv_jemalloc_ctl::stats;
+
+        epoch::advance()?;
+        Ok(Self {
+            allocated: stats::allocated::read()? as u64,
+            active: stats::active::read()? as u64,
+            resident: stats::resident::read()? as u64,
+            mapped: stats::mapped::read()? as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_estimates_the_allocations_of_futures() {
+        let (length, allocated) = AllocationTracked::new(async {
+            let buffer = vec![1u8; 1024 * 1024];
+            tokio::task::yield_now().await;
+            buffer.len()
+        })
+        .await;
+        assert_eq!(length, 1024 * 1024);
+        assert!(allocated >= 1024 * 1024);
+
+        let ((), allocated) = AllocationTracked::new(async {}).await;
+        assert_eq!(allocated, 0);
+    }
+}
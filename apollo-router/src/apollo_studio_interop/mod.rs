@@ -1,6 +1,8 @@
 //! Generation of usage reporting fields
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
@@ -28,6 +30,8 @@ use apollo_compiler::Node;
 use apollo_compiler::Schema;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
 
 use crate::json_ext::Object;
 use crate::json_ext::Value as JsonValue;
@@ -195,6 +199,7 @@ pub(crate) fn generate_usage_reporting(
     operation_name: &Option<String>,
     schema: &Valid<Schema>,
     normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    redacted_arguments: &[String],
 ) -> UsageReporting {
     let mut generator = UsageGenerator {
         signature_doc,
@@ -202,6 +207,7 @@ pub(crate) fn generate_usage_reporting(
         operation_name,
         schema,
         normalization_algorithm,
+        redacted_arguments,
         variables: &Object::new(),
         fragments_map: HashMap::new(),
         fields_by_type: HashMap::new(),
@@ -238,6 +244,112 @@ pub(crate) fn generate_extended_references(
     generator.generate_extended_references()
 }
 
+/// Prefix of the variables replacing extracted literals. Names starting with `__` are reserved by
+/// the GraphQL specification, so a placeholder cannot collide with a variable of the operation.
+const LITERAL_PLACEHOLDER_PREFIX: &str = "__literal_";
+
+/// Options of [`normalize_operation`]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct NormalizationOptions {
+    /// Replace string, number and boolean literals with variables, so that operations that only
+    /// differ by these literals have the same normalized text. The literals are returned separately.
+    pub(crate) extract_literals: bool,
+    /// Sort the selections of each selection set, so that operations that only differ by the order
+    /// of their fields have the same normalized text
+    pub(crate) sort_selections: bool,
+}
+
+/// An operation printed in a canonical form, used to compute cache keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct NormalizedOperation {
+    /// The fragments used by the operation sorted by name, followed by the operation, without
+    /// comments or insignificant whitespace
+    pub(crate) text: String,
+    /// The literals extracted from the text, in the order of their `$__literal_0`, `$__literal_1`…
+    /// placeholders
+    pub(crate) literals: Vec<String>,
+}
+
+impl NormalizedOperation {
+    /// Hash of the text and of the extracted literals
+    pub(crate) fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.text.as_bytes());
+        for literal in &self.literals {
+            hasher.update([0]);
+            hasher.update(literal.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Normalize an operation for cache keys.
+///
+/// This uses the same formatter as usage reporting signatures, but keeps aliases and values so
+/// that operations with different results have different normalized forms.
+pub(crate) fn normalize_operation(
+    doc: &ExecutableDocument,
+    operation_name: Option<&str>,
+    options: NormalizationOptions,
+) -> Option<NormalizedOperation> {
+    let operation = doc.operations.get(operation_name).ok()?;
+    let mut fragments = BTreeMap::new();
+    collect_fragments(doc, &operation.selection_set, &mut fragments);
+
+    let literals = RefCell::new(Vec::new());
+    let format_options = FormatOptions {
+        enhanced: true,
+        sort_selections: options.sort_selections,
+        values: if options.extract_literals {
+            ValueFormat::Extracted(&literals)
+        } else {
+            ValueFormat::Literal
+        },
+        redacted_arguments: &[],
+    };
+
+    let mut text = String::new();
+    for fragment in fragments.into_values() {
+        let formatter = SignatureFormatterWithAlgorithm {
+            formatter: &ApolloReportingSignatureFormatter::Fragment(fragment),
+            options: &format_options,
+        };
+        write!(&mut text, "{formatter}").expect("infallible");
+    }
+    let formatter = SignatureFormatterWithAlgorithm {
+        formatter: &ApolloReportingSignatureFormatter::Operation(operation),
+        options: &format_options,
+    };
+    write!(&mut text, "{formatter}").expect("infallible");
+
+    Some(NormalizedOperation {
+        text,
+        literals: literals.into_inner(),
+    })
+}
+
+fn collect_fragments<'doc>(
+    doc: &'doc ExecutableDocument,
+    selection_set: &'doc SelectionSet,
+    fragments: &mut BTreeMap<&'doc Name, &'doc Node<Fragment>>,
+) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => collect_fragments(doc, &field.selection_set, fragments),
+            Selection::InlineFragment(fragment) => {
+                collect_fragments(doc, &fragment.selection_set, fragments)
+            }
+            Selection::FragmentSpread(spread) => {
+                if let Some((name, fragment)) = doc.fragments.get_key_value(&spread.fragment_name) {
+                    if fragments.insert(name, fragment).is_none() {
+                        collect_fragments(doc, &fragment.selection_set, fragments);
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub(crate) fn extract_enums_from_response(
     query: Arc<Query>,
     schema: &Valid<Schema>,
@@ -350,6 +462,8 @@ struct UsageGenerator<'a> {
     operation_name: &'a Option<String>,
     schema: &'a Valid<Schema>,
     normalization_algorithm: &'a ApolloSignatureNormalizationAlgorithm,
+    /// Arguments whose literal values are removed from the signature
+    redacted_arguments: &'a [String],
     variables: &'a Object,
     fragments_map: HashMap<String, Node<Fragment>>,
     fields_by_type: HashMap<String, HashSet<String>>,
@@ -425,7 +539,10 @@ impl UsageGenerator<'_> {
         sorted_fragments.into_iter().for_each(|(_, f)| {
             let formatter = SignatureFormatterWithAlgorithm {
                 formatter: &ApolloReportingSignatureFormatter::Fragment(f),
-                normalization_algorithm: self.normalization_algorithm,
+                options: &FormatOptions::signature(
+                    self.normalization_algorithm,
+                    self.redacted_arguments,
+                ),
             };
             write!(&mut result, "{formatter}").expect("infallible");
         });
@@ -433,7 +550,10 @@ impl UsageGenerator<'_> {
         // Followed by the operation
         let formatter = SignatureFormatterWithAlgorithm {
             formatter: &ApolloReportingSignatureFormatter::Operation(operation),
-            normalization_algorithm: self.normalization_algorithm,
+            options: &FormatOptions::signature(
+                self.normalization_algorithm,
+                self.redacted_arguments,
+            ),
         };
         write!(&mut result, "{formatter}").expect("infallible");
 
@@ -756,42 +876,79 @@ enum ApolloReportingSignatureFormatter<'a> {
     Fragment(&'a Node<Fragment>),
     Argument(&'a Node<Argument>),
     Field(&'a Node<Field>),
+    FragmentSpread(&'a Node<FragmentSpread>),
+    InlineFragment(&'a Node<InlineFragment>),
+}
+
+/// How the formatter functions below print operations
+struct FormatOptions<'a> {
+    /// Print aliases and input object fields, always sort directives and separate arguments with commas
+    enhanced: bool,
+    /// Print fields, then named fragments, then inline fragments, each sorted by name.
+    /// Otherwise, selections are printed in the document order
+    sort_selections: bool,
+    values: ValueFormat<'a>,
+    /// Arguments whose literal values are printed as `null`
+    redacted_arguments: &'a [String],
+}
+
+enum ValueFormat<'a> {
+    /// Replace values with empty placeholders, as usage reporting signatures do
+    Redacted,
+    /// Print values as they are
+    Literal,
+    /// Replace scalar values with variables, and record them
+    Extracted(&'a RefCell<Vec<String>>),
+}
+
+impl<'a> FormatOptions<'a> {
+    fn signature(
+        normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+        redacted_arguments: &'a [String],
+    ) -> Self {
+        Self {
+            enhanced: matches!(
+                normalization_algorithm,
+                ApolloSignatureNormalizationAlgorithm::Enhanced
+            ),
+            sort_selections: true,
+            values: ValueFormat::Redacted,
+            redacted_arguments,
+        }
+    }
 }
 
 struct SignatureFormatterWithAlgorithm<'a> {
     formatter: &'a ApolloReportingSignatureFormatter<'a>,
-    normalization_algorithm: &'a ApolloSignatureNormalizationAlgorithm,
+    options: &'a FormatOptions<'a>,
 }
 
 impl fmt::Display for SignatureFormatterWithAlgorithm<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self.formatter {
             ApolloReportingSignatureFormatter::Operation(operation) => {
-                format_operation(operation, self.normalization_algorithm, f)
+                format_operation(operation, self.options, f)
             }
             ApolloReportingSignatureFormatter::Fragment(fragment) => {
-                format_fragment(fragment, self.normalization_algorithm, f)
+                format_fragment(fragment, self.options, f)
             }
             ApolloReportingSignatureFormatter::Argument(argument) => {
-                format_argument(argument, self.normalization_algorithm, f)
+                format_argument(argument, self.options, f)
+            }
+            ApolloReportingSignatureFormatter::Field(field) => format_field(field, self.options, f),
+            ApolloReportingSignatureFormatter::FragmentSpread(fragment_spread) => {
+                format_fragment_spread(fragment_spread, self.options, f)
             }
-            ApolloReportingSignatureFormatter::Field(field) => {
-                format_field(field, self.normalization_algorithm, f)
+            ApolloReportingSignatureFormatter::InlineFragment(inline_fragment) => {
+                format_inline_fragment(inline_fragment, self.options, f)
             }
         }
     }
 }
 
-fn is_enhanced(normalization_algorithm: &ApolloSignatureNormalizationAlgorithm) -> bool {
-    matches!(
-        normalization_algorithm,
-        ApolloSignatureNormalizationAlgorithm::Enhanced
-    )
-}
-
 fn format_operation(
     operation: &Node<Operation>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     let shorthand = operation.operation_type == OperationType::Query
@@ -814,43 +971,48 @@ fn format_operation(
                 if index != 0 {
                     f.write_str(",")?;
                 }
-                format_variable(variable, normalization_algorithm, f)?;
+                format_variable(variable, options, f)?;
             }
             f.write_str(")")?;
         }
 
         // In the JS implementation, only the fragment directives are sorted (this is overridden in enhanced mode)
-        format_directives(&operation.directives, false, normalization_algorithm, f)?;
+        format_directives(&operation.directives, false, options, f)?;
     }
 
-    format_selection_set(&operation.selection_set, normalization_algorithm, f)
+    format_selection_set(&operation.selection_set, options, f)
 }
 
 fn format_selection_set(
     selection_set: &SelectionSet,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
-    // print selection set sorted by name with fields followed by named fragments followed by inline fragments
-    let mut fields: Vec<&Node<Field>> = Vec::new();
-    let mut named_fragments: Vec<&Node<FragmentSpread>> = Vec::new();
-    let mut inline_fragments: Vec<&Node<InlineFragment>> = Vec::new();
-    for selection in selection_set.selections.iter() {
-        match selection {
-            Selection::Field(field) => {
-                fields.push(field);
-            }
-            Selection::FragmentSpread(fragment_spread) => {
-                named_fragments.push(fragment_spread);
-            }
-            Selection::InlineFragment(inline_fragment) => {
-                inline_fragments.push(inline_fragment);
+    if selection_set.selections.is_empty() {
+        return Ok(());
+    }
+
+    let mut selections: Vec<ApolloReportingSignatureFormatter> = Vec::new();
+    if options.sort_selections {
+        // print selection set sorted by name with fields followed by named fragments followed by inline fragments
+        let mut fields: Vec<&Node<Field>> = Vec::new();
+        let mut named_fragments: Vec<&Node<FragmentSpread>> = Vec::new();
+        let mut inline_fragments: Vec<&Node<InlineFragment>> = Vec::new();
+        for selection in selection_set.selections.iter() {
+            match selection {
+                Selection::Field(field) => {
+                    fields.push(field);
+                }
+                Selection::FragmentSpread(fragment_spread) => {
+                    named_fragments.push(fragment_spread);
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    inline_fragments.push(inline_fragment);
+                }
             }
         }
-    }
 
-    if !fields.is_empty() || !named_fragments.is_empty() || !inline_fragments.is_empty() {
-        if is_enhanced(normalization_algorithm) {
+        if options.enhanced {
             // in enhanced mode we display aliases so we show non-aliased field sorted by name first, then aliased fields sorted by alias
             fields.sort_by(|&a, &b| {
                 match (a.alias.as_ref(), b.alias.as_ref()) {
@@ -870,7 +1032,7 @@ fn format_selection_set(
         named_fragments.sort_by(|&a, &b| a.fragment_name.cmp(&b.fragment_name));
 
         // in enhanced mode we sort inline fragments
-        if is_enhanced(normalization_algorithm) {
+        if options.enhanced {
             inline_fragments.sort_by(|&a, &b| {
                 let a_name = a.type_condition.as_ref().map(|t| t.as_str()).unwrap_or("");
                 let b_name = b.type_condition.as_ref().map(|t| t.as_str()).unwrap_or("");
@@ -878,70 +1040,103 @@ fn format_selection_set(
             });
         }
 
-        f.write_str("{")?;
-
-        for (i, &field) in fields.iter().enumerate() {
-            let formatter = SignatureFormatterWithAlgorithm {
-                formatter: &ApolloReportingSignatureFormatter::Field(field),
-                normalization_algorithm,
-            };
-            let field_str = format!("{}", formatter);
-            f.write_str(&field_str)?;
-
-            // We need to insert a space if this is not the last field and it ends in an alphanumeric character.
-            let use_separator = field_str
-                .chars()
-                .last()
-                .map_or(false, |c| c.is_alphanumeric() || c == '_');
-            if i < fields.len() - 1 && use_separator {
-                f.write_str(" ")?;
-            }
-        }
+        selections.extend(
+            fields
+                .into_iter()
+                .map(ApolloReportingSignatureFormatter::Field),
+        );
+        selections.extend(
+            named_fragments
+                .into_iter()
+                .map(ApolloReportingSignatureFormatter::FragmentSpread),
+        );
+        selections.extend(
+            inline_fragments
+                .into_iter()
+                .map(ApolloReportingSignatureFormatter::InlineFragment),
+        );
+    } else {
+        selections.extend(
+            selection_set
+                .selections
+                .iter()
+                .map(|selection| match selection {
+                    Selection::Field(field) => ApolloReportingSignatureFormatter::Field(field),
+                    Selection::FragmentSpread(fragment_spread) => {
+                        ApolloReportingSignatureFormatter::FragmentSpread(fragment_spread)
+                    }
+                    Selection::InlineFragment(inline_fragment) => {
+                        ApolloReportingSignatureFormatter::InlineFragment(inline_fragment)
+                    }
+                }),
+        );
+    }
 
-        for &frag in named_fragments.iter() {
-            format_fragment_spread(frag, normalization_algorithm, f)?;
-        }
+    f.write_str("{")?;
 
-        for &frag in inline_fragments.iter() {
-            format_inline_fragment(frag, normalization_algorithm, f)?;
+    let mut previous: Option<String> = None;
+    for selection in selections.iter() {
+        let formatter = SignatureFormatterWithAlgorithm {
+            formatter: selection,
+            options,
+        };
+        let selection_str = format!("{}", formatter);
+
+        // We need to insert a space between two fields if the first one ends in an alphanumeric character.
+        let is_name_char = |c: char| c.is_alphanumeric() || c == '_';
+        let use_separator = previous
+            .as_ref()
+            .and_then(|previous| previous.chars().last())
+            .map_or(false, is_name_char)
+            && selection_str.chars().next().map_or(false, is_name_char);
+        if use_separator {
+            f.write_str(" ")?;
         }
-
-        f.write_str("}")?;
+        f.write_str(&selection_str)?;
+        previous = Some(selection_str);
     }
 
-    Ok(())
+    f.write_str("}")
 }
 
 fn format_variable(
     arg: &Node<VariableDefinition>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     write!(f, "${}:{}", arg.name, arg.ty)?;
     if let Some(value) = &arg.default_value {
         f.write_str("=")?;
-        format_value(value, normalization_algorithm, f)?;
+        format_value(value, options, f)?;
     }
 
     // The JS implementation doesn't sort directives (this is overridden in enhanced mode)
-    format_directives(&arg.directives, false, normalization_algorithm, f)
+    format_directives(&arg.directives, false, options, f)
 }
 
 fn format_argument(
     arg: &Node<Argument>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     write!(f, "{}:", arg.name)?;
-    format_value(&arg.value, normalization_algorithm, f)
+    let redacted = !matches!(*arg.value, Value::Variable(_))
+        && options
+            .redacted_arguments
+            .iter()
+            .any(|name| name == arg.name.as_str());
+    if redacted {
+        return f.write_str("null");
+    }
+    format_value(&arg.value, options, f)
 }
 
 fn format_field(
     field: &Node<Field>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
-    if is_enhanced(normalization_algorithm) {
+    if options.enhanced {
         if let Some(alias) = &field.alias {
             write!(f, "{alias}:")?;
         }
@@ -960,13 +1155,13 @@ fn format_field(
             .map(|a| {
                 let formatter = SignatureFormatterWithAlgorithm {
                     formatter: &ApolloReportingSignatureFormatter::Argument(a),
-                    normalization_algorithm,
+                    options,
                 };
                 format!("{}", formatter)
             })
             .collect();
 
-        let separator = get_arg_separator(&field.name, &arg_strings, normalization_algorithm);
+        let separator = get_arg_separator(&field.name, &arg_strings, options);
 
         for (index, arg_string) in arg_strings.iter().enumerate() {
             f.write_str(arg_string)?;
@@ -987,13 +1182,13 @@ fn format_field(
     }
 
     // In the JS implementation, only the fragment directives are sorted (this is overridden in enhanced mode)
-    format_directives(&field.directives, false, normalization_algorithm, f)?;
-    format_selection_set(&field.selection_set, normalization_algorithm, f)
+    format_directives(&field.directives, false, options, f)?;
+    format_selection_set(&field.selection_set, options, f)
 }
 
 fn format_inline_fragment(
     inline_fragment: &Node<InlineFragment>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     if let Some(type_name) = &inline_fragment.type_condition {
@@ -1002,18 +1197,13 @@ fn format_inline_fragment(
         f.write_str("...")?;
     }
 
-    format_directives(
-        &inline_fragment.directives,
-        true,
-        normalization_algorithm,
-        f,
-    )?;
-    format_selection_set(&inline_fragment.selection_set, normalization_algorithm, f)
+    format_directives(&inline_fragment.directives, true, options, f)?;
+    format_selection_set(&inline_fragment.selection_set, options, f)
 }
 
 fn format_fragment(
     fragment: &Node<Fragment>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     write!(
@@ -1022,20 +1212,20 @@ fn format_fragment(
         &fragment.name.to_string(),
         &fragment.selection_set.ty.to_string()
     )?;
-    format_directives(&fragment.directives, true, normalization_algorithm, f)?;
-    format_selection_set(&fragment.selection_set, normalization_algorithm, f)
+    format_directives(&fragment.directives, true, options, f)?;
+    format_selection_set(&fragment.selection_set, options, f)
 }
 
 fn format_directives(
     directives: &DirectiveList,
     sorted: bool,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     let mut sorted_directives = directives.clone();
 
     // In enhanced mode, we always want to sort
-    if sorted || is_enhanced(normalization_algorithm) {
+    if sorted || options.enhanced {
         sorted_directives.sort_by(|a, b| a.name.cmp(&b.name));
     }
 
@@ -1054,7 +1244,7 @@ fn format_directives(
                 }
                 let formatter = SignatureFormatterWithAlgorithm {
                     formatter: &ApolloReportingSignatureFormatter::Argument(argument),
-                    normalization_algorithm,
+                    options,
                 };
                 write!(f, "{}", formatter)?;
             }
@@ -1066,42 +1256,51 @@ fn format_directives(
     Ok(())
 }
 
-fn format_value(
-    value: &Value,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
-    f: &mut fmt::Formatter,
-) -> fmt::Result {
-    match value {
-        Value::String(_) => f.write_str("\"\""),
-        Value::Float(_) | Value::Int(_) => f.write_str("0"),
-        Value::Object(o) => {
-            if is_enhanced(normalization_algorithm) {
-                f.write_str("{")?;
-                for (index, (name, val)) in o.iter().enumerate() {
-                    if index != 0 {
-                        f.write_str(",")?;
-                    }
-                    write!(f, "{}:", name)?;
-                    format_value(val, normalization_algorithm, f)?;
+fn format_value(value: &Value, options: &FormatOptions, f: &mut fmt::Formatter) -> fmt::Result {
+    match (&options.values, value) {
+        (ValueFormat::Literal, value) => write!(f, "{value}"),
+        (ValueFormat::Redacted, Value::String(_)) => f.write_str("\"\""),
+        (ValueFormat::Redacted, Value::Float(_) | Value::Int(_)) => f.write_str("0"),
+        (ValueFormat::Redacted, Value::List(_)) => f.write_str("[]"),
+        (ValueFormat::Redacted, Value::Object(_)) if !options.enhanced => f.write_str("{}"),
+        (
+            ValueFormat::Extracted(literals),
+            Value::String(_) | Value::Float(_) | Value::Int(_) | Value::Boolean(_),
+        ) => {
+            let mut literals = literals.borrow_mut();
+            write!(f, "${LITERAL_PLACEHOLDER_PREFIX}{}", literals.len())?;
+            literals.push(value.to_string());
+            Ok(())
+        }
+        (ValueFormat::Extracted(_), Value::List(values)) => {
+            f.write_str("[")?;
+            for (index, val) in values.iter().enumerate() {
+                if index != 0 {
+                    f.write_str(",")?;
+                }
+                format_value(val, options, f)?;
+            }
+            f.write_str("]")
+        }
+        (_, Value::Object(o)) => {
+            f.write_str("{")?;
+            for (index, (name, val)) in o.iter().enumerate() {
+                if index != 0 {
+                    f.write_str(",")?;
                 }
-                f.write_str("}")
-            } else {
-                f.write_str("{}")
+                write!(f, "{}:", name)?;
+                format_value(val, options, f)?;
             }
+            f.write_str("}")
         }
-        Value::List(_) => f.write_str("[]"),
-        rest => f.write_str(&rest.to_string()),
+        (_, rest) => f.write_str(&rest.to_string()),
     }
 }
 
 // Figure out which separator to use between arguments
-fn get_arg_separator(
-    field_name: &Name,
-    arg_strings: &[String],
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
-) -> char {
+fn get_arg_separator(field_name: &Name, arg_strings: &[String], options: &FormatOptions) -> char {
     // In enhanced mode, we just always use a comma
-    if is_enhanced(normalization_algorithm) {
+    if options.enhanced {
         return ',';
     }
 
@@ -1131,16 +1330,11 @@ fn get_arg_separator(
 
 fn format_fragment_spread(
     fragment_spread: &Node<FragmentSpread>,
-    normalization_algorithm: &ApolloSignatureNormalizationAlgorithm,
+    options: &FormatOptions,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     write!(f, "...{}", fragment_spread.fragment_name)?;
-    format_directives(
-        &fragment_spread.directives,
-        true,
-        normalization_algorithm,
-        f,
-    )
+    format_directives(&fragment_spread.directives, true, options, f)
 }
 
 #[cfg(test)]
@@ -42,6 +42,7 @@ fn generate_enhanced(
         operation_name,
         schema,
         &ApolloSignatureNormalizationAlgorithm::Enhanced,
+        &[],
     )
 }
 
@@ -299,3 +300,103 @@ async fn test_enums_from_response_fragments() {
     let generated = enums_from_response(query_str, op_name, schema_str, response_str);
     assert_enums_from_response!(&generated);
 }
+
+#[test(tokio::test)]
+async fn test_normalize_operation() {
+    let schema_str = include_str!("testdata/schema_interop.graphql");
+    let schema = Schema::parse_and_validate(schema_str, "schema.graphql").unwrap();
+    let normalize = |query_str: &str, options: NormalizationOptions| {
+        let doc = ExecutableDocument::parse(&schema, query_str, "query.graphql").unwrap();
+        normalize_operation(&doc, Some("Q"), options).unwrap()
+    };
+
+    let query_str = r#"
+        query Q {
+            # comments and whitespace are removed
+            manyArgsQuery(arg2: "b", arg1: "a") {
+                ...F
+                alias: id
+            }
+        }
+
+        fragment F on EverythingResponse {
+            nullableId
+        }
+    "#;
+    let normalized = normalize(query_str, NormalizationOptions::default());
+    assert_eq!(
+        normalized.text,
+        r#"fragment F on EverythingResponse{nullableId}query Q{manyArgsQuery(arg1:"a",arg2:"b"){...F alias:id}}"#
+    );
+    assert!(normalized.literals.is_empty());
+
+    let sorted = NormalizationOptions {
+        sort_selections: true,
+        ..Default::default()
+    };
+    let reordered = r#"
+        query Q { manyArgsQuery(arg1: "a", arg2: "b") { alias: id ...F } }
+        fragment F on EverythingResponse { nullableId }
+    "#;
+    assert_eq!(normalize(query_str, sorted), normalize(reordered, sorted));
+    assert_ne!(
+        normalize(query_str, NormalizationOptions::default()),
+        normalize(reordered, NormalizationOptions::default())
+    );
+}
+
+#[test(tokio::test)]
+async fn test_normalize_operation_extracts_literals() {
+    let schema_str = include_str!("testdata/schema_interop.graphql");
+    let schema = Schema::parse_and_validate(schema_str, "schema.graphql").unwrap();
+    let options = NormalizationOptions {
+        extract_literals: true,
+        ..Default::default()
+    };
+    let normalize = |query_str: &str| {
+        let doc = ExecutableDocument::parse(&schema, query_str, "query.graphql").unwrap();
+        normalize_operation(&doc, None, options).unwrap()
+    };
+
+    let a = normalize(r#"{ manyArgsQuery(arg1: "a", arg2: "b") { id } }"#);
+    assert_eq!(
+        a.text,
+        "{manyArgsQuery(arg1:$__literal_0,arg2:$__literal_1){id}}"
+    );
+    assert_eq!(a.literals, vec![r#""a""#.to_string(), r#""b""#.to_string()]);
+
+    // The literals are not part of the text, but they are part of the hash
+    let b = normalize(r#"{ manyArgsQuery(arg1: "a", arg2: "c") { id } }"#);
+    assert_eq!(a.text, b.text);
+    assert_ne!(a.hash(), b.hash());
+
+    // A variable named like a placeholder is kept apart from the extracted literals
+    let c = normalize(r#"query($_0: String) { manyArgsQuery(arg1: $_0, arg2: "b") { id } }"#);
+    assert_eq!(
+        c.text,
+        "query($_0:String){manyArgsQuery(arg1:$_0,arg2:$__literal_0){id}}"
+    );
+}
+
+#[test(tokio::test)]
+async fn test_signature_redacts_arguments() {
+    let schema_str = include_str!("testdata/schema_interop.graphql");
+    let schema = Schema::parse_and_validate(schema_str, "schema.graphql").unwrap();
+    let query_str = r#"query Redacted($a: String) {
+        manyArgsQuery(arg1: $a, arg2: "secret") { id }
+        inputTypeQuery(input: { inputString: "secret", enumInput: SOME_VALUE_1 }) { enumResponse }
+    }"#;
+    let doc = ExecutableDocument::parse(&schema, query_str, "query.graphql").unwrap();
+
+    let generated = generate_usage_reporting(
+        &doc,
+        &doc,
+        &Some("Redacted".into()),
+        &schema,
+        &ApolloSignatureNormalizationAlgorithm::Enhanced,
+        &["arg1".to_string(), "arg2".to_string(), "input".to_string()],
+    );
+    // Variables are kept, since their values are redacted from traces by name
+    let expected_sig = "# Redacted\nquery Redacted($a:String){inputTypeQuery(input:null){enumResponse}manyArgsQuery(arg1:$a,arg2:null){id}}";
+    assert_expected_signature(&generated, expected_sig);
+}
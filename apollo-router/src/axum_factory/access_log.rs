@@ -0,0 +1,405 @@
+//! HTTP access log, written once the response body has been sent
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use axum::extract::State;
+use axum::middleware::Next;
+use axum::response::Response;
+use bytes::Bytes;
+use http::header::REFERER;
+use http::header::USER_AGENT;
+use http::HeaderMap;
+use http::Request;
+use http_body::combinators::UnsyncBoxBody;
+use http_body::Body as _;
+use http_body::SizeHint;
+use once_cell::sync::Lazy;
+
+use crate::axum_factory::utils::ConnectionInfo;
+use crate::configuration::access_log::AccessLog;
+use crate::configuration::access_log::AccessLogField;
+use crate::configuration::access_log::AccessLogFormat;
+use crate::context::OPERATION_NAME;
+use crate::tracer::TraceId;
+use crate::Context;
+
+/// Number of log lines waiting to be written before new lines are dropped
+const BUFFERED_LINES: usize = 4096;
+
+/// Writing to stdout can block when it is a slow pipe or terminal, so lines are handed over to a
+/// dedicated thread instead of being written while dropping the response body
+static WRITER: Lazy<AccessLogWriter> =
+    Lazy::new(|| AccessLogWriter::new(std::io::stdout(), BUFFERED_LINES));
+
+struct AccessLogWriter {
+    lines: mpsc::SyncSender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLogWriter {
+    fn new(mut output: impl Write + Send + 'static, buffered_lines: usize) -> Self {
+        let (lines, receiver) = mpsc::sync_channel::<String>(buffered_lines);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let dropped_lines = dropped.clone();
+        std::thread::Builder::new()
+            .name("access-log".to_string())
+            .spawn(move || {
+                for line in receiver {
+                    let _ = writeln!(output, "{line}");
+                    let dropped = dropped_lines.swap(0, Ordering::Relaxed);
+                    if dropped > 0 {
+                        tracing::warn!(
+                            "dropped {dropped} access log lines because the output could not keep up"
+                        );
+                    }
+                }
+            })
+            .expect("could not spawn the access log thread");
+        Self { lines, dropped }
+    }
+
+    /// Queues a line without blocking, dropping it if the buffer is full
+    fn write(&self, line: String) {
+        if self.lines.try_send(line).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+pub(super) async fn access_log_handler<B>(
+    State(config): State<Arc<AccessLog>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if config
+        .exclude_paths
+        .iter()
+        .any(|path| path == request.uri().path())
+    {
+        return next.run(request).await;
+    }
+
+    let start = Instant::now();
+    let mut entry = AccessLogEntry {
+        config: config.clone(),
+        timestamp: SystemTime::now(),
+        remote_addr: request
+            .extensions()
+            .get::<ConnectionInfo>()
+            .and_then(|info| info.peer_address)
+            .map(|addr| addr.ip().to_string()),
+        method: request.method().to_string(),
+        path: request
+            .uri()
+            .path_and_query()
+            .map(|path| path.to_string())
+            .unwrap_or_else(|| request.uri().path().to_string()),
+        protocol: format!("{:?}", request.version()),
+        referer: header(request.headers(), REFERER),
+        user_agent: header(request.headers(), USER_AGENT),
+        status: 0,
+        bytes: 0,
+        start,
+        operation_name: None,
+        trace_id: None,
+    };
+
+    let response = next.run(request).await;
+    entry.status = response.status().as_u16();
+    entry.operation_name = response
+        .extensions()
+        .get::<Context>()
+        .and_then(|context| context.get(OPERATION_NAME).ok().flatten());
+    entry.trace_id = response
+        .extensions()
+        .get::<TraceId>()
+        .map(|trace_id| trace_id.to_string());
+
+    response.map(|body| {
+        UnsyncBoxBody::new(AccessLogBody {
+            inner: body,
+            entry: Some(entry),
+        })
+    })
+}
+
+fn header(headers: &HeaderMap, name: http::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+struct AccessLogEntry {
+    config: Arc<AccessLog>,
+    timestamp: SystemTime,
+    remote_addr: Option<String>,
+    method: String,
+    path: String,
+    protocol: String,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    status: u16,
+    bytes: u64,
+    start: Instant,
+    operation_name: Option<String>,
+    trace_id: Option<String>,
+}
+
+impl AccessLogEntry {
+    fn value(&self, field: AccessLogField) -> serde_json::Value {
+        match field {
+            AccessLogField::RemoteAddr => self.remote_addr.clone().into(),
+            AccessLogField::Timestamp => humantime::format_rfc3339_millis(self.timestamp)
+                .to_string()
+                .into(),
+            AccessLogField::Method => self.method.clone().into(),
+            AccessLogField::Path => self.path.clone().into(),
+            AccessLogField::Protocol => self.protocol.clone().into(),
+            AccessLogField::Status => self.status.into(),
+            AccessLogField::Bytes => self.bytes.into(),
+            AccessLogField::Latency => (self.start.elapsed().as_secs_f64() * 1000.0).into(),
+            AccessLogField::Referer => self.referer.clone().into(),
+            AccessLogField::UserAgent => self.user_agent.clone().into(),
+            AccessLogField::OperationName => self.operation_name.clone().into(),
+            AccessLogField::TraceId => self.trace_id.clone().into(),
+        }
+    }
+
+    fn format(&self) -> String {
+        let config = &self.config;
+        match config.format {
+            AccessLogFormat::Json => {
+                let line: serde_json::Map<String, serde_json::Value> = config
+                    .fields
+                    .iter()
+                    .map(|field| (field_name(*field), self.value(*field)))
+                    .collect();
+                serde_json::Value::Object(line).to_string()
+            }
+            format => {
+                // %h %l %u %t "%r" %>s %b
+                let mut line = format!(
+                    "{} - - [{}] \"{} {} {}\" {} {}",
+                    self.remote_addr.as_deref().unwrap_or("-"),
+                    clf_timestamp(self.timestamp),
+                    self.method,
+                    self.path,
+                    self.protocol,
+                    self.status,
+                    self.bytes,
+                );
+                if format == AccessLogFormat::Combined {
+                    let _ = write!(
+                        line,
+                        " \"{}\" \"{}\"",
+                        self.referer.as_deref().unwrap_or("-"),
+                        self.user_agent.as_deref().unwrap_or("-"),
+                    );
+                }
+                for field in &config.fields {
+                    if format.fields().contains(field) {
+                        continue;
+                    }
+                    let value = match self.value(*field) {
+                        serde_json::Value::Null => "-".to_string(),
+                        serde_json::Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    let _ = write!(line, " {}={value:?}", field_name(*field));
+                }
+                line
+            }
+        }
+    }
+}
+
+fn field_name(field: AccessLogField) -> String {
+    serde_json::to_value(field)
+        .ok()
+        .and_then(|name| name.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Formats a timestamp as in the Common Log Format: `10/Oct/2000:13:55:36 +0000`
+fn clf_timestamp(timestamp: SystemTime) -> String {
+    let timestamp = time::OffsetDateTime::from(timestamp);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        timestamp.day(),
+        &timestamp.month().to_string()[..3],
+        timestamp.year(),
+        timestamp.hour(),
+        timestamp.minute(),
+        timestamp.second(),
+    )
+}
+
+/// Response body counting the bytes sent, that writes the access log line when dropped
+struct AccessLogBody {
+    inner: UnsyncBoxBody<Bytes, axum::Error>,
+    entry: Option<AccessLogEntry>,
+}
+
+impl http_body::Body for AccessLogBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_data(cx);
+        if let Poll::Ready(Some(Ok(data))) = &poll {
+            if let Some(entry) = self.entry.as_mut() {
+                entry.bytes += data.len() as u64;
+            }
+        }
+        poll
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for AccessLogBody {
+    fn drop(&mut self) {
+        if let Some(entry) = self.entry.take() {
+            WRITER.write(entry.format());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn entry(config: AccessLog) -> AccessLogEntry {
+        AccessLogEntry {
+            config: Arc::new(config),
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(971_186_136),
+            remote_addr: Some("127.0.0.1".to_string()),
+            method: "POST".to_string(),
+            path: "/graphql".to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            referer: None,
+            user_agent: Some("curl/8.0".to_string()),
+            status: 200,
+            bytes: 42,
+            start: Instant::now(),
+            operation_name: Some("Me".to_string()),
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn it_formats_common_log_lines() {
+        let mut config = AccessLog::default();
+        assert_eq!(
+            entry(config.clone()).format(),
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "POST /graphql HTTP/1.1" 200 42"#
+        );
+
+        config.format = AccessLogFormat::Combined;
+        config.fields.push(AccessLogField::OperationName);
+        config.fields.push(AccessLogField::TraceId);
+        assert_eq!(
+            entry(config).format(),
+            r#"127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "POST /graphql HTTP/1.1" 200 42 "-" "curl/8.0" operation_name="Me" trace_id="-""#
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_writes_lines_from_a_dedicated_thread() {
+        let output = SharedOutput::default();
+        let writer = AccessLogWriter::new(output.clone(), 16);
+        writer.write("first".to_string());
+        writer.write("second".to_string());
+        drop(writer);
+
+        // the thread exits once the writer is dropped and every queued line is written
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while output.0.lock().unwrap().as_slice() != b"first\nsecond\n" {
+            assert!(Instant::now() < deadline, "lines were not written");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn it_drops_lines_instead_of_blocking() {
+        struct BlockedOutput(mpsc::Receiver<()>);
+
+        impl Write for BlockedOutput {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let _ = self.0.recv();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (unblock, blocked) = mpsc::channel();
+        let writer = AccessLogWriter::new(BlockedOutput(blocked), 1);
+        for line in 0..10 {
+            writer.write(line.to_string());
+        }
+        assert!(writer.dropped.load(Ordering::Relaxed) >= 8);
+        drop(unblock);
+    }
+
+    #[test]
+    fn it_formats_json_log_lines() {
+        let config = AccessLog {
+            format: AccessLogFormat::Json,
+            fields: vec![
+                AccessLogField::Method,
+                AccessLogField::Status,
+                AccessLogField::OperationName,
+                AccessLogField::TraceId,
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            entry(config).format(),
+            r#"{"method":"POST","status":200,"operation_name":"Me","trace_id":null}"#
+        );
+    }
+}
@@ -5,7 +5,6 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
 use std::time::Instant;
 
 use axum::error_handling::HandleErrorLayer;
@@ -37,6 +36,7 @@ use serde_json::json;
 use tokio::net::UnixListener;
 use tokio::sync::mpsc;
 use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
 use tower::layer::layer_fn;
 use tower::service_fn;
 use tower::BoxError;
@@ -47,6 +47,9 @@ use tower_http::trace::TraceLayer;
 use tracing::instrument::WithSubscriber;
 use tracing::Instrument;
 
+use super::access_log::access_log_handler;
+use super::ip_protection::ip_protection_handler;
+use super::ip_protection::IpGuard;
 use super::listeners::ensure_endpoints_consistency;
 use super::listeners::ensure_listenaddrs_consistency;
 use super::listeners::extra_endpoints;
@@ -70,6 +73,7 @@ use crate::router_factory::Endpoint;
 use crate::router_factory::RouterFactory;
 use crate::services::http::service::BodyStream;
 use crate::services::router;
+use crate::tracer::TraceId;
 use crate::uplink::license_enforcement::LicenseState;
 use crate::uplink::license_enforcement::APOLLO_ROUTER_LICENSE_EXPIRED;
 use crate::uplink::license_enforcement::LICENSE_EXPIRED_SHORT_MESSAGE;
@@ -226,6 +230,29 @@ where
             .fold(main_endpoint.1, |acc, r| acc.merge(r));
     }
 
+    if let Some(ip_guard) = IpGuard::new(&configuration.server) {
+        main_endpoint.1 = main_endpoint.1.layer(middleware::from_fn_with_state(
+            ip_guard,
+            ip_protection_handler,
+        ));
+    }
+
+    if configuration.access_log.enabled {
+        let access_log = Arc::new(configuration.access_log.clone());
+        main_endpoint.1 = main_endpoint.1.layer(middleware::from_fn_with_state(
+            access_log.clone(),
+            access_log_handler,
+        ));
+        for (_, routers) in extra_endpoints.iter_all_mut() {
+            for router in routers.iter_mut() {
+                *router = std::mem::take(router).layer(middleware::from_fn_with_state(
+                    access_log.clone(),
+                    access_log_handler,
+                ));
+            }
+        }
+    }
+
     Ok(ListenersAndRouters {
         main: main_endpoint,
         extra: extra_endpoints,
@@ -270,7 +297,17 @@ impl HttpServerFactory for AxumHttpServerFactory {
                         .supergraph
                         .as_ref()
                         .map(|tls| tls.tls_config())
-                        .transpose()?;
+                        .transpose()?
+                        .map(|tls_config| {
+                            if configuration.server.http.http2 {
+                                tls_config
+                            } else {
+                                // only offer HTTP/1 during the ALPN negotiation
+                                let mut tls_config = (*tls_config).clone();
+                                tls_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+                                Arc::new(tls_config)
+                            }
+                        });
                     let tls_acceptor = tls_config.clone().map(TlsAcceptor::from);
 
                     match main_listener.take() {
@@ -314,8 +351,7 @@ impl HttpServerFactory for AxumHttpServerFactory {
                 .local_addr()
                 .map_err(ApolloRouterError::ServerCreationError)?;
             let mut http_config = Http::new();
-            http_config.http1_keep_alive(true);
-            http_config.http1_header_read_timeout(Duration::from_secs(10));
+            configuration.server.http.configure(&mut http_config);
 
             #[cfg(feature = "hyper_header_limits")]
             if let Some(max_headers) = configuration.limits.http1_max_request_headers {
@@ -326,12 +362,19 @@ impl HttpServerFactory for AxumHttpServerFactory {
                 http_config.max_buf_size(max_buf_size.as_u64() as usize);
             }
 
+            let write_timeout = configuration.limits.http_write_timeout;
+            let connection_age = configuration.server.http.connection_age();
+            let ip_guard = IpGuard::new(&configuration.server);
+
             let (main_server, main_shutdown_sender) = serve_router_on_listen_addr(
                 main_listener,
                 actual_main_listen_address.clone(),
                 all_routers.main.1,
                 true,
                 http_config.clone(),
+                write_timeout,
+                connection_age,
+                ip_guard,
                 all_connections_stopped_sender.clone(),
             );
 
@@ -372,6 +415,9 @@ impl HttpServerFactory for AxumHttpServerFactory {
                             router,
                             false,
                             http_config.clone(),
+                            write_timeout,
+                            connection_age,
+                            None,
                             all_connections_stopped_sender.clone(),
                         );
                         (
@@ -676,8 +722,13 @@ async fn handle_graphql(
         // we execute the request in a separate task that will run until we get the first response, which
         // means it went through the entire pipeline at least once (not looking at deferred responses or
         // subscription events). This is a bit wasteful, so to avoid unneeded subgraph calls, we insert in
-        // the context a flag to indicate that the request is canceled and subgraph calls should not be made
-        let mut cancel_handler = CancelHandler::new(&context, experimental_log_on_broken_pipe);
+        // the context a cancellation token that is triggered when the client goes away: subgraph calls
+        // are not made anymore, and the ones in flight are aborted
+        let cancellation = RequestCancellation::default();
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(cancellation.clone()));
+        let mut cancel_handler = CancelHandler::new(cancellation, experimental_log_on_broken_pipe);
         let task = service
             .oneshot(request)
             .with_current_subscriber()
@@ -719,6 +770,12 @@ async fn handle_graphql(
                 }
             };
 
+            // Used by the access log, once the response is sent
+            parts.extensions.insert(context);
+            if let Some(trace_id) = TraceId::current() {
+                parts.extensions.insert(trace_id);
+            }
+
             http::Response::from_parts(parts, body).into_response()
         }
     }
@@ -745,17 +802,17 @@ where
     (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(response))).into_response()
 }
 
-struct CancelHandler<'a> {
-    context: &'a Context,
+struct CancelHandler {
+    cancellation: RequestCancellation,
     got_first_response: bool,
     experimental_log_on_broken_pipe: bool,
     span: tracing::Span,
 }
 
-impl<'a> CancelHandler<'a> {
-    fn new(context: &'a Context, experimental_log_on_broken_pipe: bool) -> Self {
+impl CancelHandler {
+    fn new(cancellation: RequestCancellation, experimental_log_on_broken_pipe: bool) -> Self {
         CancelHandler {
-            context,
+            cancellation,
             got_first_response: false,
             experimental_log_on_broken_pipe,
             span: tracing::Span::current(),
@@ -767,21 +824,48 @@ impl<'a> CancelHandler<'a> {
     }
 }
 
-impl Drop for CancelHandler<'_> {
+impl Drop for CancelHandler {
     fn drop(&mut self) {
         if !self.got_first_response {
             if self.experimental_log_on_broken_pipe {
                 self.span
                     .in_scope(|| tracing::error!("broken pipe: the client closed the connection"));
             }
-            self.context
-                .extensions()
-                .with_lock(|mut lock| lock.insert(CanceledRequest));
+            self.cancellation.0.cancel();
         }
     }
 }
 
-pub(crate) struct CanceledRequest;
+/// Cancelled when the client closes the connection before the first response is ready.
+///
+/// It is stored in the context extensions of the router request.
+#[derive(Clone, Default)]
+pub(crate) struct RequestCancellation(CancellationToken);
+
+impl RequestCancellation {
+    pub(crate) fn is_cancelled(context: &Context) -> bool {
+        context.extensions().with_lock(|lock| {
+            lock.get::<RequestCancellation>()
+                .is_some_and(|cancellation| cancellation.0.is_cancelled())
+        })
+    }
+
+    /// Runs `future` until the client closes the connection, in which case it is dropped
+    pub(crate) async fn run<F: Future>(context: &Context, future: F) -> Option<F::Output> {
+        let token = context.extensions().with_lock(|lock| {
+            lock.get::<RequestCancellation>()
+                .map(|cancellation| cancellation.0.clone())
+        });
+        match token {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => None,
+                output = future => Some(output),
+            },
+            None => Some(future.await),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -888,4 +972,24 @@ mod tests {
         ))
         .await
     }
+
+    #[tokio::test]
+    async fn request_cancellation_stops_pending_work() {
+        let context = Context::new();
+        assert!(!RequestCancellation::is_cancelled(&context));
+        assert_eq!(
+            RequestCancellation::run(&context, async { 1 }).await,
+            Some(1)
+        );
+
+        let cancellation = RequestCancellation::default();
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(cancellation.clone()));
+        drop(CancelHandler::new(cancellation, false));
+
+        assert!(RequestCancellation::is_cancelled(&context));
+        let pending = std::future::pending::<()>();
+        assert_eq!(RequestCancellation::run(&context, pending).await, None);
+    }
 }
@@ -0,0 +1,437 @@
+//! Protections applied to the IP address of each client, before their requests are parsed
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use axum::extract::State;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use http::header::CONTENT_TYPE;
+use http::header::FORWARDED;
+use http::HeaderMap;
+use http::Request;
+use http::StatusCode;
+use mime::APPLICATION_JSON;
+
+use crate::axum_factory::utils::ConnectionInfo;
+use crate::configuration::server::ForwardedHeader;
+use crate::configuration::server::IpNetwork;
+use crate::configuration::server::IpProtection;
+use crate::configuration::server::Server;
+use crate::graphql;
+use crate::plugins::traffic_shaping::rate::ClientRateLimit;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Applies the IP protections of the configuration to connections and requests
+pub(crate) struct IpGuard {
+    trusted_proxies: Vec<IpNetwork>,
+    forwarded_header: ForwardedHeader,
+    protection: IpProtection,
+    connections: Mutex<HashMap<IpAddr, usize>>,
+    rate_limit: Option<ClientRateLimit<IpAddr>>,
+}
+
+impl IpGuard {
+    /// Returns `None` if no protection is configured
+    pub(crate) fn new(server: &Server) -> Option<Arc<Self>> {
+        if !server.ip_protection.is_enabled() {
+            return None;
+        }
+        Some(Arc::new(Self {
+            trusted_proxies: server.client_ip.trusted_proxies.clone(),
+            forwarded_header: server.client_ip.forwarded_header,
+            protection: server.ip_protection.clone(),
+            connections: Default::default(),
+            rate_limit: server
+                .ip_protection
+                .max_requests_per_ip
+                .as_ref()
+                .map(|rate_limit| ClientRateLimit::new(rate_limit.capacity, rate_limit.interval)),
+        }))
+    }
+
+    /// The address the limits of a client are counted against.
+    ///
+    /// IPv6 clients are usually given a whole /64 network, so all the addresses of a /64 share
+    /// the same limits. Otherwise a single client could use as many addresses as it wants.
+    fn limited_address(ip: IpAddr) -> IpAddr {
+        match ip.to_canonical() {
+            IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !(u128::MAX >> 64))),
+            ip => ip,
+        }
+    }
+
+    fn is_allowed(&self, ip: IpAddr) -> bool {
+        (self.protection.allow.is_empty()
+            || self
+                .protection
+                .allow
+                .iter()
+                .any(|network| network.contains(ip)))
+            && !self
+                .protection
+                .deny
+                .iter()
+                .any(|network| network.contains(ip))
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|network| network.contains(ip))
+    }
+
+    /// Checks a new connection, returning `None` if it must be closed.
+    ///
+    /// Connections from trusted proxies are accepted, as the IP address of their clients is only
+    /// known once their requests are received.
+    pub(crate) fn accept_connection(
+        self: &Arc<Self>,
+        peer_address: Option<SocketAddr>,
+    ) -> Option<ConnectionGuard> {
+        let ip = match peer_address.map(|address| address.ip()) {
+            Some(ip) if !self.is_trusted_proxy(ip) => ip,
+            _ => {
+                return Some(ConnectionGuard {
+                    ip_guard: self.clone(),
+                    ip: None,
+                })
+            }
+        };
+        if !self.is_allowed(ip) {
+            rejected("ip_denied");
+            return None;
+        }
+        if let Some(max_connections) = self.protection.max_connections_per_ip {
+            let mut connections = self.connections.lock().unwrap();
+            let count = connections.entry(Self::limited_address(ip)).or_default();
+            if *count >= max_connections.get() {
+                rejected("max_connections_per_ip");
+                return None;
+            }
+            *count += 1;
+        }
+        Some(ConnectionGuard {
+            ip_guard: self.clone(),
+            ip: self
+                .protection
+                .max_connections_per_ip
+                .map(|_| Self::limited_address(ip)),
+        })
+    }
+
+    /// Resolves the IP address of the client sending a request through the connection from
+    /// the peer address.
+    ///
+    /// The forwarded addresses are read from right to left, as each proxy appends the address
+    /// it received the request from: the first one that is not a trusted proxy is the client.
+    /// The addresses on its left were sent by the client itself, and cannot be trusted.
+    pub(crate) fn client_ip(&self, peer_ip: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client_ip = peer_ip;
+        if !self.is_trusted_proxy(peer_ip) {
+            return client_ip;
+        }
+        for forwarded_ip in forwarded_for(headers, self.forwarded_header)
+            .into_iter()
+            .rev()
+        {
+            match forwarded_ip {
+                Some(forwarded_ip) => {
+                    client_ip = forwarded_ip;
+                    if !self.is_trusted_proxy(forwarded_ip) {
+                        break;
+                    }
+                }
+                // Obfuscated or unknown addresses end the chain of trusted proxies
+                None => break,
+            }
+        }
+        client_ip
+    }
+}
+
+/// Counts an open connection against the limit of its IP address, until it is dropped
+pub(crate) struct ConnectionGuard {
+    ip_guard: Arc<IpGuard>,
+    /// The limited address the connection is counted against
+    ip: Option<IpAddr>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Some(ip) = self.ip {
+            let mut connections = self.ip_guard.connections.lock().unwrap();
+            if let Some(count) = connections.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    connections.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// The addresses of the header set by the trusted proxies, in the order they were added: the
+/// `for` parameters of the `Forwarded` header, or the `X-Forwarded-For` header. Addresses that
+/// cannot be parsed, such as obfuscated identifiers, are `None`.
+fn forwarded_for(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    match header {
+        ForwardedHeader::Forwarded => headers
+            .get_all(FORWARDED)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (name, value) = pair.trim().split_once('=')?;
+                    name.eq_ignore_ascii_case("for").then(|| parse_node(value))
+                })
+            })
+            .collect(),
+        ForwardedHeader::XForwardedFor => headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(parse_node)
+            .collect(),
+    }
+}
+
+/// Parses an address, with an optional port, such as `192.0.2.60`, `"192.0.2.60:4711"` or
+/// `"[2001:db8:cafe::17]:4711"`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(node) = node.strip_prefix('[') {
+        return node.split_once(']')?.0.parse().ok();
+    }
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|address| address.ip()))
+}
+
+fn rejected(reason: &'static str) {
+    u64_counter!(
+        "apollo.router.ip_protection.rejected",
+        "Number of connections and requests rejected by the IP protections",
+        1,
+        reason = reason
+    );
+}
+
+fn error_response(status: StatusCode, error: graphql::Error) -> Response {
+    let body = serde_json::to_string(&graphql::Response::builder().error(error).build())
+        .expect("responses must be serializable");
+    (
+        status,
+        [(CONTENT_TYPE, APPLICATION_JSON.essence_str())],
+        body,
+    )
+        .into_response()
+}
+
+pub(super) async fn ip_protection_handler<B>(
+    State(ip_guard): State<Arc<IpGuard>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // Unix sockets have no peer IP address
+    let Some(peer_ip) = request
+        .extensions()
+        .get::<ConnectionInfo>()
+        .and_then(|info| info.peer_address)
+        .map(|address| address.ip())
+    else {
+        return next.run(request).await;
+    };
+
+    let client_ip = ip_guard.client_ip(peer_ip, request.headers());
+    if !ip_guard.is_allowed(client_ip) {
+        rejected("ip_denied");
+        return error_response(
+            StatusCode::FORBIDDEN,
+            graphql::Error::builder()
+                .message("requests from this IP address are not allowed")
+                .extension_code("IP_ADDRESS_DENIED")
+                .build(),
+        );
+    }
+    if let Some(rate_limit) = &ip_guard.rate_limit {
+        if let Err(rate_limited) = rate_limit.try_acquire(&IpGuard::limited_address(client_ip)) {
+            rejected("max_requests_per_ip");
+            return error_response(StatusCode::TOO_MANY_REQUESTS, rate_limited.into());
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn ip_guard(yaml: &str) -> Arc<IpGuard> {
+        let server: Server = serde_yaml::from_str(yaml).unwrap();
+        IpGuard::new(&server).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn it_filters_ip_addresses() {
+        let ip_guard = ip_guard(
+            r#"
+ip_protection:
+  allow: ["10.0.0.0/8"]
+  deny: ["10.0.0.13"]
+"#,
+        );
+        assert!(ip_guard.is_allowed(ip("10.1.2.3")));
+        assert!(!ip_guard.is_allowed(ip("10.0.0.13")));
+        assert!(!ip_guard.is_allowed(ip("192.0.2.1")));
+    }
+
+    #[test]
+    fn it_limits_connections_per_ip() {
+        let ip_guard = ip_guard(
+            r#"
+client_ip:
+  trusted_proxies: ["192.0.2.0/24"]
+ip_protection:
+  max_connections_per_ip: 2
+"#,
+        );
+        assert_eq!(
+            ip_guard.protection.max_connections_per_ip,
+            NonZeroUsize::new(2)
+        );
+        let client = Some("203.0.113.7:4000".parse().unwrap());
+        let first = ip_guard.accept_connection(client).unwrap();
+        let _second = ip_guard.accept_connection(client).unwrap();
+        assert!(ip_guard.accept_connection(client).is_none());
+        drop(first);
+        assert!(ip_guard.accept_connection(client).is_some());
+
+        // the clients of trusted proxies are only known once their requests are received
+        let proxy = Some("192.0.2.1:4000".parse().unwrap());
+        let _proxied: Vec<_> = (0..3)
+            .map(|_| ip_guard.accept_connection(proxy).unwrap())
+            .collect();
+    }
+
+    #[test]
+    fn it_limits_ipv6_clients_by_network() {
+        let ip_guard = ip_guard(
+            r#"
+ip_protection:
+  max_connections_per_ip: 1
+  max_requests_per_ip:
+    capacity: 1
+    interval: 1m
+"#,
+        );
+        let first = Some("[2001:db8:cafe:1::17]:4000".parse().unwrap());
+        let same_network = Some("[2001:db8:cafe:1::18]:4000".parse().unwrap());
+        let other_network = Some("[2001:db8:cafe:2::17]:4000".parse().unwrap());
+        let _connection = ip_guard.accept_connection(first).unwrap();
+        assert!(ip_guard.accept_connection(same_network).is_none());
+        assert!(ip_guard.accept_connection(other_network).is_some());
+
+        let rate_limit = ip_guard.rate_limit.as_ref().unwrap();
+        assert!(rate_limit
+            .try_acquire(&IpGuard::limited_address(ip("2001:db8:cafe:1::17")))
+            .is_ok());
+        assert!(rate_limit
+            .try_acquire(&IpGuard::limited_address(ip("2001:db8:cafe:1::18")))
+            .is_err());
+        // IPv4 addresses are limited one by one, including when mapped to IPv6
+        assert!(rate_limit
+            .try_acquire(&IpGuard::limited_address(ip("203.0.113.7")))
+            .is_ok());
+        assert!(rate_limit
+            .try_acquire(&IpGuard::limited_address(ip("::ffff:203.0.113.7")))
+            .is_err());
+        assert!(rate_limit
+            .try_acquire(&IpGuard::limited_address(ip("203.0.113.8")))
+            .is_ok());
+    }
+
+    #[test]
+    fn it_resolves_client_ips_from_trusted_proxies() {
+        let ip_guard = ip_guard(
+            r#"
+client_ip:
+  trusted_proxies: ["10.0.0.0/8"]
+ip_protection:
+  deny: ["203.0.113.66"]
+"#,
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("198.51.100.1, 203.0.113.7, 10.0.0.2"),
+        );
+        // the leftmost address was sent by the client, and isn't trusted
+        assert_eq!(
+            ip_guard.client_ip(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+        // the headers of untrusted peers are ignored
+        assert_eq!(
+            ip_guard.client_ip(ip("192.0.2.1"), &headers),
+            ip("192.0.2.1")
+        );
+
+        // the proxies only set `X-Forwarded-For`, so a `Forwarded` header comes from the client
+        headers.insert(FORWARDED, HeaderValue::from_static("for=192.0.2.99"));
+        assert_eq!(
+            ip_guard.client_ip(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn it_resolves_client_ips_from_the_forwarded_header() {
+        let ip_guard = ip_guard(
+            r#"
+client_ip:
+  trusted_proxies: ["10.0.0.0/8"]
+  forwarded_header: forwarded
+ip_protection:
+  deny: ["203.0.113.66"]
+"#,
+        );
+        let mut headers = HeaderMap::new();
+        // the proxies only set `Forwarded`, so `X-Forwarded-For` comes from the client
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_static("192.0.2.99"));
+        assert_eq!(ip_guard.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.1"));
+
+        headers.insert(
+            FORWARDED,
+            HeaderValue::from_static(
+                r#"for="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.2;by=10.0.0.1"#,
+            ),
+        );
+        assert_eq!(
+            ip_guard.client_ip(ip("10.0.0.1"), &headers),
+            ip("2001:db8:cafe::17")
+        );
+
+        headers.insert(
+            FORWARDED,
+            HeaderValue::from_static("for=203.0.113.66, for=_hidden, for=10.0.0.2"),
+        );
+        assert_eq!(ip_guard.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.2"));
+    }
+}
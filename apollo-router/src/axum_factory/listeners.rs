@@ -20,9 +20,12 @@ use tokio::sync::mpsc;
 use tokio::sync::Notify;
 use tower_service::Service;
 
+use crate::axum_factory::ip_protection::IpGuard;
 use crate::axum_factory::utils::ConnectionInfo;
 use crate::axum_factory::utils::InjectConnectionInfo;
+use crate::axum_factory::write_timeout::WriteTimeout;
 use crate::axum_factory::ENDPOINT_CALLBACK;
+use crate::configuration::server::ConnectionAge;
 use crate::configuration::Configuration;
 use crate::http_server_factory::Listener;
 use crate::http_server_factory::NetworkStream;
@@ -197,12 +200,16 @@ pub(super) async fn get_extra_listeners(
     Ok(listeners_and_routers)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn serve_router_on_listen_addr(
     mut listener: Listener,
     address: ListenAddr,
     router: axum::Router,
     main_graphql_port: bool,
     http_config: Http,
+    write_timeout: Option<Duration>,
+    connection_age: ConnectionAge,
+    ip_guard: Option<Arc<IpGuard>>,
     all_connections_stopped_sender: mpsc::Sender<()>,
 ) -> (impl Future<Output = Listener>, oneshot::Sender<()>) {
     let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
@@ -230,6 +237,16 @@ pub(super) fn serve_router_on_listen_addr(
 
                     match res {
                         Ok(res) => {
+                            // connections from denied IP addresses, or above the limit of their
+                            // IP address, are closed right away, before their TLS handshake
+                            let connection_guard = match ip_guard
+                                .as_ref()
+                                .map(|ip_guard| ip_guard.accept_connection(res.peer_addr()))
+                            {
+                                Some(None) => continue,
+                                connection_guard => connection_guard.flatten(),
+                            };
+
                             if MAX_FILE_HANDLES_WARN.load(Ordering::SeqCst) {
                                 tracing::info!("can accept connections again");
                                 MAX_FILE_HANDLES_WARN.store(false, Ordering::SeqCst);
@@ -248,9 +265,15 @@ pub(super) fn serve_router_on_listen_addr(
                             tokio::task::spawn(async move {
                                 // this sender must be moved into the session to track that it is still running
                                 let _connection_stop_signal = connection_stop_signal;
+                                // and this guard to count the connection against the limit of its IP address
+                                let _connection_guard = connection_guard;
 
-                                match res {
-                                    NetworkStream::Tcp(stream) => {
+                                match res.handshake().await {
+                                    // the client was already told why the TLS handshake failed
+                                    Err(error) => {
+                                        tracing::debug!("TLS handshake failed: {error}");
+                                    }
+                                    Ok(NetworkStream::Tcp(stream)) => {
                                         let received_first_request = Arc::new(AtomicBool::new(false));
                                         let app = InjectConnectionInfo::new(app, ConnectionInfo {
                                             peer_address: stream.peer_addr().ok(),
@@ -264,6 +287,7 @@ pub(super) fn serve_router_on_listen_addr(
                                                 "this should not fail unless the socket is invalid",
                                             );
 
+                                        let stream = WriteTimeout::new(stream, write_timeout);
                                         let connection = http_config.serve_connection(stream, app);
                                         tokio::pin!(connection);
                                         tokio::select! {
@@ -284,12 +308,25 @@ pub(super) fn serve_router_on_listen_addr(
                                                     let _= connection.await;
                                                 }
                                             }
+                                            // the connection reached its maximum age, so we ask the client to
+                                            // reconnect, and give the requests in flight some time to complete
+                                            _ = connection_age.expired() => {
+                                                connection.as_mut().graceful_shutdown();
+
+                                                // as above, an idle connection that never received a request
+                                                // would never finish its graceful shutdown, so it is closed
+                                                // right away
+                                                if received_first_request.load(Ordering::Relaxed) {
+                                                    connection_age.drain(connection).await;
+                                                }
+                                            }
                                         }
                                     }
                                     #[cfg(unix)]
-                                    NetworkStream::Unix(stream) => {
+                                    Ok(NetworkStream::Unix(stream)) => {
                                         let received_first_request = Arc::new(AtomicBool::new(false));
                                         let app = IdleConnectionChecker::new(received_first_request.clone(), app);
+                                        let stream = WriteTimeout::new(stream, write_timeout);
                                         let connection = http_config.serve_connection(stream, app);
 
                                         tokio::pin!(connection);
@@ -311,10 +348,26 @@ pub(super) fn serve_router_on_listen_addr(
                                                     let _= connection.await;
                                                 }
                                             }
+                                            // the connection reached its maximum age, so we ask the client to
+                                            // reconnect, and give the requests in flight some time to complete
+                                            _ = connection_age.expired() => {
+                                                connection.as_mut().graceful_shutdown();
+
+                                                // as above, an idle connection that never received a request
+                                                // would never finish its graceful shutdown, so it is closed
+                                                // right away
+                                                if received_first_request.load(Ordering::Relaxed) {
+                                                    connection_age.drain(connection).await;
+                                                }
+                                            }
                                         }
                                     },
-                                    NetworkStream::Tls(stream) => {
+                                    Ok(NetworkStream::Tls(stream)) => {
                                         let received_first_request = Arc::new(AtomicBool::new(false));
+                                        let app = InjectConnectionInfo::new(app, ConnectionInfo {
+                                            peer_address: stream.get_ref().0.peer_addr().ok(),
+                                            server_address: stream.get_ref().0.local_addr().ok(),
+                                        });
                                         let app = IdleConnectionChecker::new(received_first_request.clone(), app);
 
                                         stream.get_ref().0
@@ -326,9 +379,11 @@ pub(super) fn serve_router_on_listen_addr(
                                             let protocol = stream.get_ref().1.alpn_protocol();
                                             let http2 = protocol == Some(&b"h2"[..]);
 
-                                        let connection = http_config
-                                            .http2_only(http2)
-                                            .serve_connection(stream, app);
+                                        let stream = WriteTimeout::new(stream, write_timeout);
+                                        if http2 {
+                                            http_config.http2_only(true);
+                                        }
+                                        let connection = http_config.serve_connection(stream, app);
 
                                         tokio::pin!(connection);
                                         tokio::select! {
@@ -349,6 +404,18 @@ pub(super) fn serve_router_on_listen_addr(
                                                     let _= connection.await;
                                                 }
                                             }
+                                            // the connection reached its maximum age, so we ask the client to
+                                            // reconnect, and give the requests in flight some time to complete
+                                            _ = connection_age.expired() => {
+                                                connection.as_mut().graceful_shutdown();
+
+                                                // as above, an idle connection that never received a request
+                                                // would never finish its graceful shutdown, so it is closed
+                                                // right away
+                                                if received_first_request.load(Ordering::Relaxed) {
+                                                    connection_age.drain(connection).await;
+                                                }
+                                            }
                                         }
                                     }
                                 }
@@ -1,10 +1,13 @@
 //! axum factory is useful to create an [`AxumHttpServerFactory`] which implements [`crate::http_server_factory::HttpServerFactory`]
+mod access_log;
 mod axum_http_server_factory;
 pub(crate) mod compression;
+mod ip_protection;
 mod listeners;
 #[cfg(test)]
 pub(crate) mod tests;
 pub(crate) mod utils;
+mod write_timeout;
 
 use std::sync::Arc;
 use std::sync::OnceLock;
@@ -12,7 +15,7 @@ use std::sync::OnceLock;
 use axum::Router;
 pub(crate) use axum_http_server_factory::span_mode;
 pub(crate) use axum_http_server_factory::AxumHttpServerFactory;
-pub(crate) use axum_http_server_factory::CanceledRequest;
+pub(crate) use axum_http_server_factory::RequestCancellation;
 pub(crate) use listeners::ListenAddrAndRouter;
 
 static ENDPOINT_CALLBACK: OnceLock<Arc<dyn Fn(Router) -> Router + Send + Sync>> = OnceLock::new();
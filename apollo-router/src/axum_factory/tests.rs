@@ -1212,6 +1212,56 @@ async fn it_displays_homepage() {
     server.shutdown().await.unwrap();
 }
 
+#[test(tokio::test)]
+async fn it_displays_custom_homepage() {
+    let homepage = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(homepage.path(), "<html><body>custom homepage</body></html>").unwrap();
+    let conf = Arc::new(
+        Configuration::fake_builder()
+            .homepage(
+                crate::configuration::Homepage::fake_builder()
+                    .enabled(true)
+                    .path(homepage.path().to_path_buf())
+                    .build(),
+            )
+            .build()
+            .unwrap(),
+    );
+
+    let router_service = router::service::from_supergraph_mock_callback_and_configuration(
+        |req| {
+            Ok(SupergraphResponse::new_from_graphql_response(
+                graphql::Response::builder()
+                    .data(json!({"response": "test"}))
+                    .build(),
+                req.context,
+            ))
+        },
+        conf.clone(),
+    )
+    .await;
+
+    let (server, client) = init_with_config(router_service, conf, MultiMap::new())
+        .await
+        .unwrap();
+    let response = client
+        .get(format!(
+            "{}/",
+            server.graphql_listen_address().as_ref().unwrap()
+        ))
+        .header(ACCEPT, "text/html")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "<html><body>custom homepage</body></html>"
+    );
+    server.shutdown().await.unwrap();
+}
+
 #[test(tokio::test)]
 async fn it_doesnt_display_disabled_homepage() {
     let conf = Arc::new(
@@ -2351,3 +2401,96 @@ async fn test_supergraph_timeout() {
         })
     );
 }
+
+#[tokio::test]
+async fn it_closes_connections_reaching_their_max_age() {
+    let conf = Arc::new(
+        Configuration::fake_builder()
+            .server(crate::configuration::server::Server {
+                http: crate::configuration::server::ServerHttp {
+                    max_connection_age: Some(Duration::from_millis(300)),
+                    ..Default::default()
+                },
+            })
+            .build()
+            .unwrap(),
+    );
+    let router_service = router::service::from_supergraph_mock_callback_and_configuration(
+        move |req| {
+            Ok(SupergraphResponse::new_from_graphql_response(
+                graphql::Response::builder()
+                    .data(json!({"me": {"name": "Ada"}}))
+                    .build(),
+                req.context,
+            ))
+        },
+        conf.clone(),
+    )
+    .await;
+    let (server, _client) = init_with_config(router_service, conf, MultiMap::new())
+        .await
+        .unwrap();
+    let address = match server.graphql_listen_address().as_ref().unwrap() {
+        ListenAddr::SocketAddr(address) => *address,
+        #[cfg(unix)]
+        ListenAddr::UnixSocket(_) => panic!("expected a TCP listener"),
+    };
+
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            b"GET /?query=%7Bme%7Bname%7D%7D HTTP/1.1\r\nhost: localhost\r\naccept: application/json\r\napollo-require-preflight: true\r\n\r\n",
+        )
+        .await
+        .unwrap();
+
+    // the connection is kept alive after the response, until it reaches its maximum age
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received))
+        .await
+        .expect("the connection should be closed once it reached its maximum age")
+        .unwrap();
+    assert!(String::from_utf8_lossy(&received).starts_with("HTTP/1.1 200 OK"));
+}
+
+#[tokio::test]
+async fn it_closes_idle_connections_reaching_their_max_age() {
+    let conf = Arc::new(
+        Configuration::fake_builder()
+            .server(crate::configuration::server::Server {
+                http: crate::configuration::server::ServerHttp {
+                    max_connection_age: Some(Duration::from_millis(300)),
+                    ..Default::default()
+                },
+            })
+            .build()
+            .unwrap(),
+    );
+    let router_service = router::service::from_supergraph_mock_callback_and_configuration(
+        move |req| {
+            Ok(SupergraphResponse::new_from_graphql_response(
+                graphql::Response::builder().build(),
+                req.context,
+            ))
+        },
+        conf.clone(),
+    )
+    .await;
+    let (server, _client) = init_with_config(router_service, conf, MultiMap::new())
+        .await
+        .unwrap();
+    let address = match server.graphql_listen_address().as_ref().unwrap() {
+        ListenAddr::SocketAddr(address) => *address,
+        #[cfg(unix)]
+        ListenAddr::UnixSocket(_) => panic!("expected a TCP listener"),
+    };
+
+    // no request is sent, so hyper's graceful shutdown alone would never close the connection
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received))
+        .await
+        .expect("the idle connection should be closed once it reached its maximum age")
+        .unwrap();
+    assert!(received.is_empty());
+}
@@ -0,0 +1,147 @@
+//! Protection against slow-reading clients
+//!
+//! A client that stops reading its responses keeps the connection and the buffered response
+//! data alive. Connections wrapped in [`WriteTimeout`] fail when no data could be written to
+//! them for the configured duration, which closes them.
+
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures::Future;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::ReadBuf;
+use tokio::time::Sleep;
+
+pub(crate) struct WriteTimeout<S> {
+    inner: S,
+    timeout: Option<Duration>,
+    /// Started when a write cannot make progress, reset when it does
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> WriteTimeout<S> {
+    pub(crate) fn new(inner: S, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout,
+            sleep: None,
+        }
+    }
+
+    fn check<T>(&mut self, cx: &mut Context<'_>, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if poll.is_ready() {
+            self.sleep = None;
+            return poll;
+        }
+        let Some(timeout) = self.timeout else {
+            return poll;
+        };
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+        if sleep.as_mut().poll(cx).is_ready() {
+            tracing::debug!("closing a connection that did not accept data for {timeout:?}");
+            u64_counter!(
+                "apollo.router.http.write_timeouts",
+                "Number of client connections closed because they did not accept response data",
+                1
+            );
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the client did not accept data in time",
+            )));
+        }
+        poll
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for WriteTimeout<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for WriteTimeout<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.check(cx, poll)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+        self.check(cx, poll)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.inner).poll_flush(cx);
+        self.check(cx, poll)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+
+    #[tokio::test]
+    async fn slow_readers_time_out() {
+        async {
+            let (client, server) = tokio::io::duplex(16);
+            let mut server = WriteTimeout::new(server, Some(Duration::from_millis(50)));
+
+            // The client never reads, so the write blocks once the buffer is full
+            let error = server.write_all(&[0; 64]).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+            assert_counter!("apollo.router.http.write_timeouts", 1);
+            drop(client);
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn writes_that_make_progress_do_not_time_out() {
+        let (mut client, server) = tokio::io::duplex(16);
+        let mut server = WriteTimeout::new(server, Some(Duration::from_millis(50)));
+
+        let reader = tokio::spawn(async move {
+            let mut received = Vec::new();
+            let mut buf = [0; 8];
+            while received.len() < 64 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let read = client.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..read]);
+            }
+            received.len()
+        });
+        server.write_all(&[0; 64]).await.unwrap();
+        assert_eq!(reader.await.unwrap(), 64);
+    }
+}
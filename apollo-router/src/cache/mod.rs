@@ -138,6 +138,10 @@ where
         self.storage.in_memory_cache()
     }
 
+    pub(crate) async fn clear_in_memory(&self) {
+        self.storage.clear_in_memory().await
+    }
+
     pub(crate) fn activate(&self) {
         self.storage.activate()
     }
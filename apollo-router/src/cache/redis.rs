@@ -5,6 +5,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use fred::interfaces::EventInterface;
+use fred::interfaces::LuaInterface;
 #[cfg(test)]
 use fred::mocks::Mocks;
 use fred::prelude::ClientLike;
@@ -41,6 +42,15 @@ const SUPPORTED_REDIS_SCHEMES: [&str; 6] = [
     "rediss-sentinel",
 ];
 
+/// Increments the counter at `KEYS[1]`, and sets its TTL to `ARGV[1]` milliseconds if it has none.
+const INCREMENT_SCRIPT: &str = r#"
+local count = redis.call('INCR', KEYS[1])
+if redis.call('PTTL', KEYS[1]) == -1 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+end
+return count
+"#;
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct RedisKey<K>(pub(crate) K)
 where
@@ -555,6 +565,22 @@ impl RedisCacheStorage {
         tracing::trace!("insert result {:?}", r);
     }
 
+    /// Increments a counter and returns its new value. The TTL is set when the counter is created.
+    pub(crate) async fn increment<K: KeyType>(
+        &self,
+        key: RedisKey<K>,
+        ttl: Duration,
+    ) -> Result<u64, RedisError> {
+        let key = self.make_key(key);
+        // The TTL is rounded up to the next millisecond, so that a counter never expires early
+        let ttl_ms = ttl.as_nanos().div_ceil(1_000_000).max(1) as i64;
+        // The counter is incremented and given its TTL in a single script, so that a counter
+        // recreated after it expired always gets a TTL as well
+        let count: u64 = self.inner.eval(INCREMENT_SCRIPT, key, vec![ttl_ms]).await?;
+        tracing::trace!("increment result {count}");
+        Ok(count)
+    }
+
     pub(crate) async fn delete<K: KeyType>(&self, keys: Vec<RedisKey<K>>) -> Option<u32> {
         let mut h: HashMap<u16, Vec<String>> = HashMap::new();
         for key in keys.into_iter() {
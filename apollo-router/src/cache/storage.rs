@@ -305,6 +305,13 @@ where
         self.inner.clone()
     }
 
+    /// Removes all the entries of the in-memory cache. Redis is left untouched.
+    pub(crate) async fn clear_in_memory(&self) {
+        self.inner.lock().await.clear();
+        self.cache_size.store(0, Ordering::SeqCst);
+        self.cache_estimated_storage.store(0, Ordering::SeqCst);
+    }
+
     #[cfg(test)]
     pub(crate) async fn len(&self) -> usize {
         self.inner.lock().await.len()
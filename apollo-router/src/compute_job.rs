@@ -17,7 +17,7 @@ const QUEUE_SOFT_CAPACITY_PER_THREAD: usize = 20;
 /// Let this thread pool use all available resources if it can.
 /// In the worst case, we’ll have moderate context switching cost
 /// as the kernel’s scheduler distributes time to it or Tokio or other threads.
-fn thread_pool_size() -> usize {
+pub(crate) fn thread_pool_size() -> usize {
     std::thread::available_parallelism()
         .expect("available_parallelism() failed")
         .get()
@@ -0,0 +1,117 @@
+//! HTTP access log configuration
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Access log configuration.
+///
+/// Writes a line to the standard output for each HTTP request handled by the router.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct AccessLog {
+    /// Set to true to log the HTTP requests.
+    ///
+    /// Defaults to false
+    pub(crate) enabled: bool,
+
+    /// The format of the lines.
+    ///
+    /// Defaults to `common`
+    pub(crate) format: AccessLogFormat,
+
+    /// The fields of the lines in the `json` format. With the `common` and `combined` formats,
+    /// the fields that are not part of the format are appended to the line as `name=value`.
+    ///
+    /// Defaults to the fields of the `common` format
+    pub(crate) fields: Vec<AccessLogField>,
+
+    /// Paths of the requests that are not logged, such as the health check or metrics paths.
+    pub(crate) exclude_paths: Vec<String>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: AccessLogFormat::default(),
+            fields: AccessLogFormat::Common.fields().to_vec(),
+            exclude_paths: Vec::new(),
+        }
+    }
+}
+
+/// Format of the access log lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum AccessLogFormat {
+    /// The Common Log Format
+    #[default]
+    Common,
+    /// The Combined Log Format: the Common Log Format, followed by the referer and user agent
+    Combined,
+    /// A JSON object per line
+    Json,
+}
+
+impl AccessLogFormat {
+    /// The fields written by this format, before the additional ones.
+    pub(crate) fn fields(&self) -> &'static [AccessLogField] {
+        const COMMON: &[AccessLogField] = &[
+            AccessLogField::RemoteAddr,
+            AccessLogField::Timestamp,
+            AccessLogField::Method,
+            AccessLogField::Path,
+            AccessLogField::Protocol,
+            AccessLogField::Status,
+            AccessLogField::Bytes,
+        ];
+        const COMBINED: &[AccessLogField] = &[
+            AccessLogField::RemoteAddr,
+            AccessLogField::Timestamp,
+            AccessLogField::Method,
+            AccessLogField::Path,
+            AccessLogField::Protocol,
+            AccessLogField::Status,
+            AccessLogField::Bytes,
+            AccessLogField::Referer,
+            AccessLogField::UserAgent,
+        ];
+        match self {
+            AccessLogFormat::Common => COMMON,
+            AccessLogFormat::Combined => COMBINED,
+            AccessLogFormat::Json => &[],
+        }
+    }
+}
+
+/// A field of the access log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum AccessLogField {
+    /// The address of the client
+    RemoteAddr,
+    /// The time at which the request was received
+    Timestamp,
+    /// The HTTP method
+    Method,
+    /// The path and query of the request
+    Path,
+    /// The HTTP version
+    Protocol,
+    /// The HTTP status code of the response
+    Status,
+    /// The size of the response body, in bytes
+    Bytes,
+    /// The time between receiving the request and sending the end of the response, in milliseconds
+    Latency,
+    /// The `referer` header of the request
+    Referer,
+    /// The `user-agent` header of the request
+    UserAgent,
+    /// The name of the GraphQL operation
+    OperationName,
+    /// The trace id of the request
+    TraceId,
+}
@@ -0,0 +1,48 @@
+//! Admin API configuration
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::ListenAddr;
+
+/// Admin API configuration.
+///
+/// Exposes runtime controls on a separate listener, authenticated with an API key.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct Admin {
+    /// Set to true to enable the admin API.
+    ///
+    /// Defaults to false
+    pub(crate) enabled: bool,
+
+    /// The socket address and port to listen on. It must differ from the supergraph listener.
+    ///
+    /// Defaults to 127.0.0.1:8089
+    pub(crate) listen: ListenAddr,
+
+    /// The path prefix of the admin API.
+    ///
+    /// Defaults to /admin
+    pub(crate) path: String,
+
+    /// The API key expected in the `Authorization: Bearer <key>` header. It is required when the
+    /// admin API is enabled.
+    pub(crate) api_key: Option<String>,
+}
+
+impl Default for Admin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from_str("127.0.0.1:8089").unwrap().into(),
+            path: "/admin".to_string(),
+            api_key: None,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Server certificates reloaded from their files when they change
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use futures::prelude::*;
+use futures::stream;
+use rustls::server::ClientHello;
+use rustls::server::ResolvesServerCert;
+use rustls::sign::CertifiedKey;
+use rustls::Certificate;
+use rustls::PrivateKey;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tower::BoxError;
+
+use super::load_certs;
+use super::load_key;
+use crate::files;
+
+/// Files the server certificate is reloaded from when they change, without restarting the router
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TlsReload {
+    /// path of the server certificate in PEM format
+    pub(crate) certificate: PathBuf,
+    /// path of the server key in PEM format
+    pub(crate) key: PathBuf,
+    /// path of the list of certificate authorities in PEM format
+    #[serde(default)]
+    pub(crate) certificate_chain: Option<PathBuf>,
+}
+
+/// Builds the certificate sent to clients, with the OCSP response stapled to it
+pub(crate) fn certified_key(
+    certificates: Vec<Certificate>,
+    key: &PrivateKey,
+    ocsp_response: Option<Vec<u8>>,
+) -> Result<CertifiedKey, rustls::Error> {
+    let key = rustls::sign::any_supported_type(key)
+        .map_err(|_| rustls::Error::General("invalid private key".to_string()))?;
+    let mut certified_key = CertifiedKey::new(certificates, key);
+    certified_key.ocsp = ocsp_response.filter(|response| !response.is_empty());
+    Ok(certified_key)
+}
+
+/// Reads the certificate, its chain and key, and the OCSP response from their files
+pub(crate) fn load(
+    reload: &TlsReload,
+    ocsp_response: Option<&Path>,
+) -> Result<CertifiedKey, BoxError> {
+    let mut certificates = load_certs(&std::fs::read_to_string(&reload.certificate)?)?;
+    if certificates.len() != 1 {
+        return Err(format!(
+            "expected exactly one certificate in {}",
+            reload.certificate.display()
+        )
+        .into());
+    }
+    if let Some(certificate_chain) = &reload.certificate_chain {
+        certificates.extend(load_certs(&std::fs::read_to_string(certificate_chain)?)?);
+    }
+    let key = load_key(&std::fs::read_to_string(&reload.key)?)?;
+    let ocsp_response = ocsp_response.map(std::fs::read).transpose()?;
+    Ok(certified_key(certificates, &key, ocsp_response)?)
+}
+
+/// Sends the latest certificate read from the watched files.
+///
+/// The files are watched until the resolver is dropped, which happens when the server is
+/// reconfigured.
+pub(crate) struct ReloadingCertResolver {
+    certified_key: Arc<ArcSwap<CertifiedKey>>,
+    _stop_watching: oneshot::Sender<()>,
+}
+
+impl ReloadingCertResolver {
+    /// Starts watching the files, serving the given certificate until one of them changes
+    pub(crate) fn new(
+        reload: &TlsReload,
+        ocsp_response: Option<&Path>,
+        certified_key: CertifiedKey,
+    ) -> Self {
+        let certified_key = Arc::new(ArcSwap::from_pointee(certified_key));
+        let (stop_watching, stopped) = oneshot::channel::<()>();
+
+        let mut paths = vec![reload.certificate.clone(), reload.key.clone()];
+        paths.extend(reload.certificate_chain.clone());
+        paths.extend(ocsp_response.map(Path::to_path_buf));
+        // The first event of a watch is sent when it starts, not when the file changes
+        let mut changes =
+            stream::select_all(paths.iter().map(|path| files::watch(path).skip(1).boxed()))
+                .take_until(stopped);
+
+        let reload = reload.clone();
+        let ocsp_response = ocsp_response.map(Path::to_path_buf);
+        let reloaded_key = certified_key.clone();
+        tokio::spawn(async move {
+            while changes.next().await.is_some() {
+                match load(&reload, ocsp_response.as_deref()) {
+                    Ok(certified_key) => {
+                        reloaded_key.store(Arc::new(certified_key));
+                        tracing::info!("reloaded the TLS server certificate");
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            "could not reload the TLS server certificate, the previous one is still used: {err}"
+                        );
+                    }
+                }
+            }
+        });
+
+        Self {
+            certified_key,
+            _stop_watching: stop_watching,
+        }
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key.load_full())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const CERTIFICATE: &str = include_str!("testdata/server.crt");
+    const KEY: &str = include_str!("testdata/server.key");
+
+    #[tokio::test]
+    async fn it_reloads_changed_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let reload = TlsReload {
+            certificate: dir.path().join("server.crt"),
+            key: dir.path().join("server.key"),
+            certificate_chain: Some(dir.path().join("chain.crt")),
+        };
+        let ocsp_response = dir.path().join("ocsp.der");
+        std::fs::write(&reload.certificate, CERTIFICATE).unwrap();
+        std::fs::write(&reload.key, KEY).unwrap();
+        std::fs::write(reload.certificate_chain.as_ref().unwrap(), "").unwrap();
+        std::fs::write(&ocsp_response, b"response").unwrap();
+
+        let initial = load(&reload, Some(&ocsp_response)).unwrap();
+        assert_eq!(initial.cert.len(), 1);
+        assert_eq!(initial.ocsp.as_deref(), Some(&b"response"[..]));
+        let resolver = ReloadingCertResolver::new(&reload, Some(&ocsp_response), initial);
+
+        std::fs::write(reload.certificate_chain.as_ref().unwrap(), CERTIFICATE).unwrap();
+        std::fs::write(&ocsp_response, b"renewed response").unwrap();
+        for _ in 0..50 {
+            let current = resolver.certified_key.load();
+            if current.cert.len() == 2 && current.ocsp.as_deref() == Some(&b"renewed response"[..])
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("the certificate was not reloaded");
+    }
+
+    #[tokio::test]
+    async fn it_keeps_the_certificate_when_reloading_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let reload = TlsReload {
+            certificate: dir.path().join("server.crt"),
+            key: dir.path().join("server.key"),
+            certificate_chain: None,
+        };
+        std::fs::write(&reload.certificate, CERTIFICATE).unwrap();
+        std::fs::write(&reload.key, KEY).unwrap();
+
+        let resolver = ReloadingCertResolver::new(&reload, None, load(&reload, None).unwrap());
+        std::fs::write(&reload.key, "not a key").unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(resolver.certified_key.load().cert.len(), 1);
+    }
+}
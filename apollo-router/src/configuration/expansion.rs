@@ -1,14 +1,17 @@
 //! Environment variable expansion in the configuration file
 
+use std::cell::Cell;
 use std::env;
 use std::env::VarError;
 use std::fs;
 use std::str::FromStr;
+use std::time::Duration;
 
 use proteus::Parser;
 use proteus::TransformBuilder;
 use serde_json::Value;
 
+use super::secrets;
 use super::ConfigurationError;
 use crate::executable::APOLLO_ROUTER_DEV_ENV;
 
@@ -73,7 +76,7 @@ impl Expansion {
 
         let supported_expansion_modes = match env::var("APOLLO_ROUTER_CONFIG_SUPPORTED_MODES") {
             Ok(v) => v,
-            Err(VarError::NotPresent) => "env,file".to_string(),
+            Err(VarError::NotPresent) => "env,file,secret".to_string(),
             Err(VarError::NotUnicode(_)) => Err(ConfigurationError::InvalidExpansionModeConfig)?,
         };
         let supported_modes = supported_expansion_modes
@@ -172,7 +175,10 @@ fn dev_mode_defaults() -> Vec<Override> {
 }
 
 impl Expansion {
-    fn context_fn(&self) -> impl Fn(&str) -> Result<Option<String>, ConfigurationError> + '_ {
+    fn context_fn<'a>(
+        &'a self,
+        secrets_ttl: &'a Cell<Option<Duration>>,
+    ) -> impl Fn(&str) -> Result<Option<String>, ConfigurationError> + 'a {
         move |key: &str| {
             if !self
                 .supported_modes
@@ -200,6 +206,18 @@ impl Expansion {
                     }
                 });
             }
+            if let Some(reference) = key.strip_prefix("secret.") {
+                let secret = secrets::resolve(reference).map_err(|cause| {
+                    ConfigurationError::CannotExpandVariable {
+                        key: key.to_string(),
+                        cause: format!("{cause}"),
+                    }
+                })?;
+                if let Some(ttl) = secret.ttl {
+                    secrets_ttl.set(Some(secrets_ttl.get().map_or(ttl, |min| min.min(ttl))));
+                }
+                return Ok(Some(secret.value));
+            }
             Err(ConfigurationError::InvalidExpansionModeConfig)
         }
     }
@@ -220,10 +238,21 @@ impl Expansion {
         &self,
         configuration: &serde_json::Value,
     ) -> Result<serde_json::Value, ConfigurationError> {
+        self.expand_with_secrets_ttl(configuration)
+            .map(|(configuration, _)| configuration)
+    }
+
+    /// Expands the configuration, and returns the shortest time to live of the secrets it
+    /// references, if any of them expires
+    pub(crate) fn expand_with_secrets_ttl(
+        &self,
+        configuration: &serde_json::Value,
+    ) -> Result<(serde_json::Value, Option<Duration>), ConfigurationError> {
         let mut configuration = configuration.clone();
+        let secrets_ttl = Cell::new(None);
         self.defaults(&mut configuration)?;
-        self.visit(&mut configuration)?;
-        Ok(configuration)
+        self.visit(&mut configuration, &secrets_ttl)?;
+        Ok((configuration, secrets_ttl.get()))
     }
 
     fn defaults(&self, config: &mut Value) -> Result<(), ConfigurationError> {
@@ -251,24 +280,28 @@ impl Expansion {
         Ok(())
     }
 
-    fn visit(&self, value: &mut Value) -> Result<(), ConfigurationError> {
+    fn visit(
+        &self,
+        value: &mut Value,
+        secrets_ttl: &Cell<Option<Duration>>,
+    ) -> Result<(), ConfigurationError> {
         let mut expanded: Option<String> = None;
         match value {
             Value::String(value) => {
-                let new_value =
-                    shellexpand::env_with_context(value, self.context_fn()).map_err(|e| e.cause)?;
+                let new_value = shellexpand::env_with_context(value, self.context_fn(secrets_ttl))
+                    .map_err(|e| e.cause)?;
                 if &new_value != value {
                     expanded = Some(new_value.to_string());
                 }
             }
             Value::Array(a) => {
                 for v in a {
-                    self.visit(v)?
+                    self.visit(v, secrets_ttl)?
                 }
             }
             Value::Object(o) => {
                 for v in o.values_mut() {
-                    self.visit(v)?
+                    self.visit(v, secrets_ttl)?
                 }
             }
             _ => {}
@@ -292,14 +325,20 @@ pub(crate) fn coerce(expanded: &str) -> Value {
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use insta::assert_yaml_snapshot;
     use serde_json::json;
     use serde_json::Value;
+    use tower::BoxError;
 
     use crate::configuration::expansion::dev_mode_defaults;
     use crate::configuration::expansion::Override;
     use crate::configuration::expansion::ValueType;
     use crate::configuration::Expansion;
+    use crate::register_secret_resolver;
+    use crate::Secret;
+    use crate::SecretResolver;
 
     #[test]
     fn test_override_precedence() {
@@ -477,6 +516,40 @@ mod test {
         })
     }
 
+    #[test]
+    fn test_secrets() {
+        struct Rotating;
+        impl SecretResolver for Rotating {
+            fn resolve(&self, key: &str) -> Result<Secret, BoxError> {
+                let ttl = if key == "short" { 30 } else { 300 };
+                Ok(Secret::with_ttl(
+                    format!("{key}-value"),
+                    Duration::from_secs(ttl),
+                ))
+            }
+        }
+        register_secret_resolver("test_expansion", Rotating);
+        std::env::set_var("TEST_SECRET_EXPANSION_VAR", "from-env");
+
+        let expansion = Expansion::builder().supported_mode("secret").build();
+        let value = json!({
+            "env": "${secret.env.TEST_SECRET_EXPANSION_VAR}",
+            "long": "${secret.test_expansion.long}",
+            "short": "Bearer ${secret.test_expansion.short}"
+        });
+        let (value, secrets_ttl) = expansion
+            .expand_with_secrets_ttl(&value)
+            .expect("expansion must succeed");
+        assert_eq!(
+            value,
+            json!({"env": "from-env", "long": "long-value", "short": "Bearer short-value"})
+        );
+        assert_eq!(secrets_ttl, Some(Duration::from_secs(30)));
+
+        let value = json!({"unknown": "${secret.unregistered.key}"});
+        assert!(expansion.expand(&value).is_err());
+    }
+
     #[test]
     fn test_dev_mode() {
         let expansion = Expansion::builder()
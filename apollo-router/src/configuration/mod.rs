@@ -7,6 +7,7 @@ use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::num::NonZeroU32;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -16,6 +17,7 @@ use displaydoc::Display;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 pub(crate) use persisted_queries::PersistedQueries;
+pub(crate) use persisted_queries::PersistedQueriesManifestGeneration;
 pub(crate) use persisted_queries::PersistedQueriesPrewarmQueryPlanCache;
 #[cfg(test)]
 pub(crate) use persisted_queries::PersistedQueriesSafelist;
@@ -38,12 +40,18 @@ use serde_json::Map;
 use serde_json::Value;
 use thiserror::Error;
 
+use self::access_log::AccessLog;
+use self::admin::Admin;
+use self::certificates::ReloadingCertResolver;
+use self::certificates::TlsReload;
 use self::cors::Cors;
 use self::expansion::Expansion;
 pub(crate) use self::experimental::Discussed;
 pub(crate) use self::schema::generate_config_schema;
 pub(crate) use self::schema::generate_upgrade;
+use self::server::Server;
 use self::subgraph::SubgraphConfiguration;
+use self::tenants::Tenant;
 use crate::cache::DEFAULT_CACHE_CAPACITY;
 use crate::configuration::schema::Mode;
 use crate::graphql;
@@ -53,17 +61,24 @@ use crate::plugins::limits;
 use crate::plugins::subscription::SubscriptionConfig;
 use crate::plugins::subscription::APOLLO_SUBSCRIPTION_PLUGIN;
 use crate::plugins::subscription::APOLLO_SUBSCRIPTION_PLUGIN_NAME;
+use crate::services::router::error_detail::ErrorDetailLevel;
 use crate::uplink::UplinkConfig;
 use crate::ApolloRouterError;
 
+pub(crate) mod access_log;
+pub(crate) mod admin;
+pub(crate) mod certificates;
 pub(crate) mod cors;
 pub(crate) mod expansion;
 mod experimental;
 pub(crate) mod metrics;
 mod persisted_queries;
 mod schema;
+pub(crate) mod secrets;
+pub(crate) mod server;
 pub(crate) mod shared;
 pub(crate) mod subgraph;
+pub(crate) mod tenants;
 #[cfg(test)]
 mod tests;
 mod upgrade;
@@ -90,8 +105,12 @@ pub enum ConfigurationError {
     },
     /// unknown plugin {0}
     PluginUnknown(String),
+    /// plugin {0} is registered more than once
+    PluginDuplicate(String),
     /// plugin {plugin} could not be configured: {error}
     PluginConfiguration { plugin: String, error: String },
+    /// plugin {plugin} could not be started: {error}
+    PluginStartup { plugin: String, error: String },
     /// {message}: {error}
     InvalidConfiguration {
         message: &'static str,
@@ -100,7 +119,7 @@ pub enum ConfigurationError {
     /// could not deserialize configuration: {0}
     DeserializeConfigError(serde_json::Error),
 
-    /// APOLLO_ROUTER_CONFIG_SUPPORTED_MODES must be of the format env,file,... Possible modes are 'env' and 'file'.
+    /// APOLLO_ROUTER_CONFIG_SUPPORTED_MODES must be of the format env,file,... Possible modes are 'env', 'file' and 'secret'.
     InvalidExpansionModeConfig,
 
     /// could not migrate configuration: {error}.
@@ -122,6 +141,10 @@ pub struct Configuration {
     #[serde(skip)]
     pub(crate) validated_yaml: Option<Value>,
 
+    /// The shortest time to live of the secrets referenced by the configuration file
+    #[serde(skip)]
+    pub(crate) secrets_ttl: Option<Duration>,
+
     /// Health check configuration
     #[serde(default)]
     pub(crate) health_check: HealthCheck,
@@ -145,6 +168,18 @@ pub struct Configuration {
     #[serde(default)]
     pub(crate) tls: Tls,
 
+    /// HTTP access log configuration
+    #[serde(default)]
+    pub(crate) access_log: AccessLog,
+
+    /// Admin API configuration
+    #[serde(default)]
+    pub(crate) admin: Admin,
+
+    /// Client-facing HTTP server configuration
+    #[serde(default)]
+    pub(crate) server: Server,
+
     /// Configures automatic persisted queries
     #[serde(default)]
     pub(crate) apq: Apq,
@@ -185,6 +220,10 @@ pub struct Configuration {
     /// Type conditioned fetching configuration.
     #[serde(default)]
     pub(crate) experimental_type_conditioned_fetching: bool,
+
+    /// Additional graphs served by the router, each with its own schema and configuration.
+    #[serde(default)]
+    pub(crate) experimental_tenants: Vec<Tenant>,
 }
 
 impl PartialEq for Configuration {
@@ -208,6 +247,9 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             homepage: Homepage,
             supergraph: Supergraph,
             cors: Cors,
+            access_log: AccessLog,
+            admin: Admin,
+            server: Server,
             plugins: UserPlugins,
             #[serde(flatten)]
             apollo_plugins: ApolloPlugins,
@@ -218,6 +260,7 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             experimental_chaos: Chaos,
             batching: Batching,
             experimental_type_conditioned_fetching: bool,
+            experimental_tenants: Vec<Tenant>,
         }
         let mut ad_hoc: AdHocConfiguration = serde::Deserialize::deserialize(deserializer)?;
 
@@ -238,6 +281,9 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             homepage: ad_hoc.homepage,
             supergraph: ad_hoc.supergraph,
             cors: ad_hoc.cors,
+            access_log: ad_hoc.access_log,
+            admin: ad_hoc.admin,
+            server: ad_hoc.server,
             tls: ad_hoc.tls,
             apq: ad_hoc.apq,
             persisted_queries: ad_hoc.persisted_queries,
@@ -247,11 +293,13 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             plugins: ad_hoc.plugins,
             apollo_plugins: ad_hoc.apollo_plugins,
             batching: ad_hoc.batching,
+            experimental_tenants: ad_hoc.experimental_tenants,
 
             // serde(skip)
             notify,
             uplink: None,
             validated_yaml: None,
+            secrets_ttl: None,
         }
         .validate()
         .map_err(|e| serde::de::Error::custom(e.to_string()))
@@ -280,6 +328,8 @@ impl Configuration {
         sandbox: Option<Sandbox>,
         homepage: Option<Homepage>,
         cors: Option<Cors>,
+        access_log: Option<AccessLog>,
+        server: Option<Server>,
         plugins: Map<String, Value>,
         apollo_plugins: Map<String, Value>,
         tls: Option<Tls>,
@@ -295,11 +345,15 @@ impl Configuration {
 
         let conf = Self {
             validated_yaml: Default::default(),
+            secrets_ttl: None,
             supergraph: supergraph.unwrap_or_default(),
             health_check: health_check.unwrap_or_default(),
             sandbox: sandbox.unwrap_or_default(),
             homepage: homepage.unwrap_or_default(),
             cors: cors.unwrap_or_default(),
+            access_log: access_log.unwrap_or_default(),
+            admin: Default::default(),
+            server: server.unwrap_or_default(),
             apq: apq.unwrap_or_default(),
             persisted_queries: persisted_query.unwrap_or_default(),
             limits: operation_limits.unwrap_or_default(),
@@ -315,6 +369,7 @@ impl Configuration {
             batching: batching.unwrap_or_default(),
             experimental_type_conditioned_fetching: experimental_type_conditioned_fetching
                 .unwrap_or_default(),
+            experimental_tenants: Default::default(),
             notify,
         };
 
@@ -380,6 +435,8 @@ impl Configuration {
             debug: QueryPlannerDebugConfig {
                 max_evaluated_plans,
                 paths_limit: self.supergraph.query_planning.experimental_paths_limit,
+                max_fetch_nodes: self.supergraph.query_planning.experimental_max_fetch_nodes,
+                planning_timeout: self.supergraph.query_planning.experimental_planning_timeout,
             },
         }
     }
@@ -403,6 +460,8 @@ impl Configuration {
         sandbox: Option<Sandbox>,
         homepage: Option<Homepage>,
         cors: Option<Cors>,
+        access_log: Option<AccessLog>,
+        server: Option<Server>,
         plugins: Map<String, Value>,
         apollo_plugins: Map<String, Value>,
         tls: Option<Tls>,
@@ -417,11 +476,15 @@ impl Configuration {
     ) -> Result<Self, ConfigurationError> {
         let configuration = Self {
             validated_yaml: Default::default(),
+            secrets_ttl: None,
             supergraph: supergraph.unwrap_or_else(|| Supergraph::fake_builder().build()),
             health_check: health_check.unwrap_or_else(|| HealthCheck::fake_builder().build()),
             sandbox: sandbox.unwrap_or_else(|| Sandbox::fake_builder().build()),
             homepage: homepage.unwrap_or_else(|| Homepage::fake_builder().build()),
             cors: cors.unwrap_or_default(),
+            access_log: access_log.unwrap_or_default(),
+            admin: Default::default(),
+            server: server.unwrap_or_default(),
             limits: operation_limits.unwrap_or_default(),
             experimental_chaos: chaos.unwrap_or_default(),
             plugins: UserPlugins {
@@ -438,6 +501,7 @@ impl Configuration {
             experimental_type_conditioned_fetching: experimental_type_conditioned_fetching
                 .unwrap_or_default(),
             batching: batching.unwrap_or_default(),
+            experimental_tenants: Default::default(),
         };
 
         configuration.validate()
@@ -502,6 +566,48 @@ impl Configuration {
             );
         }
 
+        if self.admin.enabled {
+            if self.admin.api_key.as_deref().unwrap_or_default().is_empty() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'admin' configuration",
+                    error: "an 'api_key' is required to enable the admin API".to_string(),
+                });
+            }
+            if self.admin.listen == self.supergraph.listen {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'admin' configuration",
+                    error: "the admin API must listen on a different address than the supergraph"
+                        .to_string(),
+                });
+            }
+            if !self.admin.path.starts_with('/') || self.admin.path.ends_with('/') {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'admin' configuration",
+                    error: format!(
+                        "'{}' is invalid, the path must start with '/' and not end with '/'",
+                        self.admin.path
+                    ),
+                });
+            }
+        }
+
+        // Tenants.
+        let mut tenant_names = std::collections::HashSet::new();
+        for tenant in &self.experimental_tenants {
+            if !tenant_names.insert(tenant.name.as_str()) {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'experimental_tenants' configuration",
+                    error: format!("the tenant name '{}' is used more than once", tenant.name),
+                });
+            }
+            if let Err(error) = tenant.matcher.validate() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'experimental_tenants' configuration",
+                    error: format!("tenant '{}': {error}", tenant.name),
+                });
+            }
+        }
+
         // PQs.
         if self.persisted_queries.enabled {
             if self.persisted_queries.safelist.enabled && self.apq.enabled {
@@ -656,6 +762,36 @@ pub(crate) struct Supergraph {
     /// Log a message if the client closes the connection before the response is sent.
     /// Default: false.
     pub(crate) experimental_log_on_broken_pipe: bool,
+
+    /// Reject schema updates containing breaking changes to the API schema, keeping the
+    /// previous schema. Breaking changes are logged in any case.
+    /// Default: false.
+    pub(crate) experimental_reject_breaking_schema_changes: bool,
+
+    /// Merge the entity fetches of a query plan that target the same subgraph
+    pub(crate) experimental_entity_batching: EntityBatching,
+
+    /// Execute the mutation fetches grouped in a parallel node of a query plan concurrently,
+    /// instead of one after the other. Only enable it if the mutations sent to different
+    /// subgraphs do not depend on each other.
+    /// Default: false.
+    pub(crate) experimental_parallel_mutations: bool,
+
+    /// Validate the responses against the API schema
+    /// Default: disabled.
+    pub(crate) experimental_response_validation: ResponseValidation,
+
+    /// Serve a contract variant of the supergraph, filtered with its `@tag` directives
+    pub(crate) experimental_contract: Contract,
+
+    /// How much of the errors is sent to clients: `full`, `codes_only` or `minimal`
+    /// Default: full
+    pub(crate) error_detail_level: ErrorDetailLevel,
+
+    /// Requests with this value in the `apollo-error-detail-override` header receive errors
+    /// with the `full` detail level, whatever the configured level is.
+    /// Default: none, the level cannot be overridden
+    pub(crate) error_detail_override_secret: Option<String>,
 }
 
 const fn default_generate_query_fragments() -> bool {
@@ -685,6 +821,13 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        experimental_reject_breaking_schema_changes: Option<bool>,
+        experimental_entity_batching: Option<EntityBatching>,
+        experimental_parallel_mutations: Option<bool>,
+        experimental_response_validation: Option<ResponseValidation>,
+        experimental_contract: Option<Contract>,
+        error_detail_level: Option<ErrorDetailLevel>,
+        error_detail_override_secret: Option<String>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(default_graphql_listen),
@@ -696,6 +839,14 @@ impl Supergraph {
                 .unwrap_or_else(default_generate_query_fragments),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            experimental_reject_breaking_schema_changes:
+                experimental_reject_breaking_schema_changes.unwrap_or_default(),
+            experimental_entity_batching: experimental_entity_batching.unwrap_or_default(),
+            experimental_parallel_mutations: experimental_parallel_mutations.unwrap_or_default(),
+            experimental_response_validation: experimental_response_validation.unwrap_or_default(),
+            experimental_contract: experimental_contract.unwrap_or_default(),
+            error_detail_level: error_detail_level.unwrap_or_default(),
+            error_detail_override_secret,
         }
     }
 }
@@ -714,6 +865,13 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        experimental_reject_breaking_schema_changes: Option<bool>,
+        experimental_entity_batching: Option<EntityBatching>,
+        experimental_parallel_mutations: Option<bool>,
+        experimental_response_validation: Option<ResponseValidation>,
+        experimental_contract: Option<Contract>,
+        error_detail_level: Option<ErrorDetailLevel>,
+        error_detail_override_secret: Option<String>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(test_listen),
@@ -725,6 +883,14 @@ impl Supergraph {
                 .unwrap_or_else(default_generate_query_fragments),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            experimental_reject_breaking_schema_changes:
+                experimental_reject_breaking_schema_changes.unwrap_or_default(),
+            experimental_entity_batching: experimental_entity_batching.unwrap_or_default(),
+            experimental_parallel_mutations: experimental_parallel_mutations.unwrap_or_default(),
+            experimental_response_validation: experimental_response_validation.unwrap_or_default(),
+            experimental_contract: experimental_contract.unwrap_or_default(),
+            error_detail_level: error_detail_level.unwrap_or_default(),
+            error_detail_override_secret,
         }
     }
 }
@@ -752,6 +918,78 @@ impl Supergraph {
     }
 }
 
+/// Entity fetch batching configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct EntityBatching {
+    /// Merge the entity fetches that run in parallel in a query plan, and target the same
+    /// subgraph with the same selection, into a single fetch (disabled by default)
+    pub(crate) enabled: bool,
+
+    /// Subgraph options for entity batching
+    pub(crate) subgraph: SubgraphConfiguration<SubgraphEntityBatching>,
+}
+
+/// Subgraph level entity batching configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct SubgraphEntityBatching {
+    /// Set to false to send the entity fetches to this subgraph separately (enabled by default)
+    pub(crate) enabled: bool,
+
+    /// Maximum number of representations in a merged fetch. A fetch is not merged into a
+    /// batch that would exceed this size
+    pub(crate) max_batch_size: Option<NonZeroUsize>,
+}
+
+impl Default for SubgraphEntityBatching {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_batch_size: None,
+        }
+    }
+}
+
+impl EntityBatching {
+    /// The entity batching configuration of a subgraph, if its fetches can be merged
+    pub(crate) fn for_subgraph(&self, service_name: &str) -> Option<&SubgraphEntityBatching> {
+        let config = self.subgraph.get(service_name);
+        (self.enabled && config.enabled).then_some(config)
+    }
+}
+
+/// Validation of the responses against the API schema
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ResponseValidation {
+    /// The responses are not validated
+    #[default]
+    Disabled,
+    /// The values that do not match the schema are logged and counted
+    Log,
+    /// The values that do not match the schema are also reported as errors in the response
+    Strict,
+}
+
+/// Contract variant configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Contract {
+    /// Only keep the fields tagged with one of these tags, or belonging to a type tagged with one
+    /// of them. All the fields are kept if empty
+    pub(crate) include_tags: Vec<String>,
+
+    /// Remove the types, fields, arguments and enum values tagged with one of these tags
+    pub(crate) exclude_tags: Vec<String>,
+}
+
+impl Contract {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.include_tags.is_empty() || !self.exclude_tags.is_empty()
+    }
+}
+
 /// Router level (APQ) configuration
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Default)]
 #[serde(deny_unknown_fields)]
@@ -818,6 +1056,17 @@ pub(crate) struct QueryPlanning {
     /// the in memory cache
     pub(crate) warmed_up_queries: Option<usize>,
 
+    /// Path to a JSON file listing operations to plan when the router starts and when
+    /// the schema or configuration is reloaded, before the new schema serves traffic.
+    /// The file contains an array of objects with a `query` and an optional `operationName`
+    pub(crate) experimental_warm_up_manifest: Option<PathBuf>,
+
+    /// Limits the memory used by the cache of parsed and validated operations, measured as the
+    /// total length in bytes of their query strings. The least recently used operations are
+    /// evicted beyond this limit. The number of cached operations is also bounded by
+    /// `cache.in_memory.limit`
+    pub(crate) experimental_parsed_operations_max_bytes: Option<usize>,
+
     /// Sets a limit to the number of generated query plans.
     /// The planning process generates many different query plans as it
     /// explores the graph, and the list can grow large. By using this
@@ -841,9 +1090,30 @@ pub(crate) struct QueryPlanning {
     /// The default value is None, which specifies no limit.
     pub(crate) experimental_paths_limit: Option<u32>,
 
+    /// Limits the number of subgraph fetches of query plans. Operations that would need more
+    /// fetches are rejected with a `QUERY_PLAN_TOO_LARGE` error. While planning, the candidate
+    /// plans over the limit are discarded, and the chosen plan is checked once optimized.
+    ///
+    /// The default value is None, which specifies no limit.
+    pub(crate) experimental_max_fetch_nodes: Option<u32>,
+
+    /// Limits the time spent planning an operation. The query planner checks this deadline
+    /// periodically, and aborts planning once it has passed. The operation is rejected with a
+    /// `QUERY_PLANNING_TIMEOUT` error.
+    ///
+    /// The default value is None, which specifies no limit.
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) experimental_planning_timeout: Option<Duration>,
+
     /// If cache warm up is configured, this will allow the router to keep a query plan created with
     /// the old schema, if it determines that the schema update does not affect the corresponding query
     pub(crate) experimental_reuse_query_plans: bool,
+
+    /// Names of subgraphs to treat as temporarily unavailable. Query plans avoid fetching from
+    /// these subgraphs when another subgraph can resolve the same fields. Fields that only a
+    /// disabled subgraph can resolve are still fetched from it.
+    pub(crate) experimental_disabled_subgraphs: Vec<String>,
 }
 
 /// Cache configuration
@@ -1032,30 +1302,92 @@ pub(crate) struct Tls {
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct TlsSupergraph {
-    /// server certificate in PEM format
-    #[serde(deserialize_with = "deserialize_certificate", skip_serializing)]
-    #[schemars(with = "String")]
-    pub(crate) certificate: Certificate,
-    /// server key in PEM format
-    #[serde(deserialize_with = "deserialize_key", skip_serializing)]
-    #[schemars(with = "String")]
-    pub(crate) key: PrivateKey,
+    /// server certificate in PEM format, required unless `reload` is set
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_certificate",
+        skip_serializing
+    )]
+    #[schemars(with = "Option<String>")]
+    pub(crate) certificate: Option<Certificate>,
+    /// server key in PEM format, required unless `reload` is set
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_key",
+        skip_serializing
+    )]
+    #[schemars(with = "Option<String>")]
+    pub(crate) key: Option<PrivateKey>,
     /// list of certificate authorities in PEM format
-    #[serde(deserialize_with = "deserialize_certificate_chain", skip_serializing)]
-    #[schemars(with = "String")]
+    #[serde(
+        default,
+        deserialize_with = "deserialize_certificate_chain",
+        skip_serializing
+    )]
+    #[schemars(with = "Option<String>")]
     pub(crate) certificate_chain: Vec<Certificate>,
+    /// path of a DER encoded OCSP response, stapled to the TLS handshake. The router does not
+    /// fetch or renew it: the file must be kept up to date by an external tool
+    #[serde(default)]
+    pub(crate) ocsp_response: Option<PathBuf>,
+    /// files the certificate, key, and certificate chain are read from, at startup and again
+    /// when they change, along with the OCSP response. When set, the inline `certificate`,
+    /// `key` and `certificate_chain` must not be
+    #[serde(default)]
+    pub(crate) reload: Option<TlsReload>,
 }
 
 impl TlsSupergraph {
     pub(crate) fn tls_config(&self) -> Result<Arc<rustls::ServerConfig>, ApolloRouterError> {
-        let mut certificates = vec![self.certificate.clone()];
-        certificates.extend(self.certificate_chain.iter().cloned());
-
-        let mut config = ServerConfig::builder()
+        let builder = ServerConfig::builder()
             .with_safe_defaults()
-            .with_no_client_auth()
-            .with_single_cert(certificates, self.key.clone())
-            .map_err(ApolloRouterError::Rustls)?;
+            .with_no_client_auth();
+        let mut config = match (&self.reload, &self.certificate, &self.key) {
+            (Some(reload), None, None) if self.certificate_chain.is_empty() => {
+                // The initial certificate is read from the same files as the reloaded ones, so
+                // that they can't diverge
+                let certified_key = certificates::load(reload, self.ocsp_response.as_deref())
+                    .map_err(|err| {
+                        ApolloRouterError::Rustls(rustls::Error::General(format!(
+                            "could not load the TLS server certificate: {err}"
+                        )))
+                    })?;
+                builder.with_cert_resolver(Arc::new(ReloadingCertResolver::new(
+                    reload,
+                    self.ocsp_response.as_deref(),
+                    certified_key,
+                )))
+            }
+            (Some(_), _, _) => {
+                return Err(ApolloRouterError::Rustls(rustls::Error::General(
+                    "the certificate, key and certificate chain are read from the `reload` files and must not be set inline"
+                        .to_string(),
+                )))
+            }
+            (None, Some(certificate), Some(key)) => {
+                let mut certificates = vec![certificate.clone()];
+                certificates.extend(self.certificate_chain.iter().cloned());
+                let ocsp_response = self
+                    .ocsp_response
+                    .as_deref()
+                    .map(std::fs::read)
+                    .transpose()
+                    .map_err(ApolloRouterError::ServerCreationError)?;
+                builder
+                    .with_single_cert_with_ocsp_and_sct(
+                        certificates,
+                        key.clone(),
+                        ocsp_response.unwrap_or_default(),
+                        Vec::new(),
+                    )
+                    .map_err(ApolloRouterError::Rustls)?
+            }
+            (None, _, _) => {
+                return Err(ApolloRouterError::Rustls(rustls::Error::General(
+                    "a certificate and a key are required".to_string(),
+                )))
+            }
+        };
         config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
         Ok(Arc::new(config))
@@ -1081,6 +1413,22 @@ where
         })
 }
 
+fn deserialize_optional_certificate<'de, D>(
+    deserializer: D,
+) -> Result<Option<Certificate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_certificate(deserializer).map(Some)
+}
+
+fn deserialize_optional_key<'de, D>(deserializer: D) -> Result<Option<PrivateKey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_key(deserializer).map(Some)
+}
+
 fn deserialize_certificate_chain<'de, D>(deserializer: D) -> Result<Vec<Certificate>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1237,6 +1585,8 @@ pub(crate) struct Homepage {
     /// Graph reference
     /// This will allow you to redirect from the Apollo Router landing page back to Apollo Studio Explorer
     pub(crate) graph_ref: Option<String>,
+    /// Path to an HTML file served instead of the default homepage
+    pub(crate) path: Option<PathBuf>,
 }
 
 fn default_homepage() -> bool {
@@ -1250,6 +1600,7 @@ impl Homepage {
         Self {
             enabled: enabled.unwrap_or_else(default_homepage),
             graph_ref: None,
+            path: None,
         }
     }
 }
@@ -1258,10 +1609,11 @@ impl Homepage {
 #[buildstructor::buildstructor]
 impl Homepage {
     #[builder]
-    pub(crate) fn fake_new(enabled: Option<bool>) -> Self {
+    pub(crate) fn fake_new(enabled: Option<bool>, path: Option<PathBuf>) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_homepage),
             graph_ref: None,
+            path,
         }
     }
 }
@@ -1462,10 +1814,24 @@ pub(crate) struct Batching {
     pub(crate) enabled: bool,
 
     /// Batching mode
+    #[serde(default)]
     pub(crate) mode: BatchingMode,
 
     /// Subgraph options for batching
     pub(crate) subgraph: Option<SubgraphConfiguration<CommonBatchingConfig>>,
+
+    /// Maximum number of operations in a client batch. Larger batches are rejected
+    #[serde(default)]
+    pub(crate) maximum_size: Option<NonZeroUsize>,
+
+    /// Maximum number of operations of a client batch executed at the same time (unlimited by
+    /// default). Ignored when subgraph batching is enabled
+    #[serde(default)]
+    pub(crate) maximum_concurrency: Option<NonZeroUsize>,
+
+    /// Batching of the requests sent to a subgraph by different client requests
+    #[serde(default)]
+    pub(crate) experimental_cross_request: SubgraphConfiguration<CrossRequestBatching>,
 }
 
 /// Common options for configuring subgraph batching
@@ -1475,6 +1841,37 @@ pub(crate) struct CommonBatchingConfig {
     pub(crate) enabled: bool,
 }
 
+/// Subgraph level cross-request batching configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct CrossRequestBatching {
+    /// Coalesce the requests sent to this subgraph by different client requests into array
+    /// batches (disabled by default)
+    pub(crate) enabled: bool,
+
+    /// Maximum number of requests in a batch. A full batch is sent right away
+    pub(crate) max_size: NonZeroUsize,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String", default = "default_cross_request_max_wait")]
+    /// Maximum time a request waits for other requests before its batch is sent (default: 5ms)
+    pub(crate) max_wait: Duration,
+}
+
+fn default_cross_request_max_wait() -> Duration {
+    Duration::from_millis(5)
+}
+
+impl Default for CrossRequestBatching {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: NonZeroUsize::new(10).expect("it is not zero"),
+            max_wait: default_cross_request_max_wait(),
+        }
+    }
+}
+
 impl Batching {
     // Check if we should enable batching for a particular subgraph (service_name)
     pub(crate) fn batch_include(&self, service_name: &str) -> bool {
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -20,6 +23,9 @@ pub struct PersistedQueries {
 
     /// Enables using a local copy of the persisted query manifest to safelist operations
     pub experimental_local_manifests: Option<Vec<String>>,
+
+    /// Experimental feature to generate a persisted query manifest from the operations executed by the router
+    pub experimental_manifest_generation: PersistedQueriesManifestGeneration,
 }
 
 #[cfg(test)]
@@ -32,6 +38,7 @@ impl PersistedQueries {
         safelist: Option<PersistedQueriesSafelist>,
         experimental_prewarm_query_plan_cache: Option<PersistedQueriesPrewarmQueryPlanCache>,
         experimental_local_manifests: Option<Vec<String>>,
+        experimental_manifest_generation: Option<PersistedQueriesManifestGeneration>,
     ) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_pq),
@@ -40,6 +47,7 @@ impl PersistedQueries {
             experimental_prewarm_query_plan_cache: experimental_prewarm_query_plan_cache
                 .unwrap_or_default(),
             experimental_local_manifests,
+            experimental_manifest_generation: experimental_manifest_generation.unwrap_or_default(),
         }
     }
 }
@@ -78,6 +86,30 @@ pub struct PersistedQueriesPrewarmQueryPlanCache {
     pub on_reload: bool,
 }
 
+/// Persisted Queries (PQ) manifest generation configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub struct PersistedQueriesManifestGeneration {
+    /// Records the freeform operations executed by the router into a persisted query manifest (disabled by default)
+    pub enabled: bool,
+
+    /// Path of the generated manifest file (default: persisted-query-manifest.json)
+    pub path: PathBuf,
+
+    /// Operations that were not executed during this window are left out of the manifest (default: 24h)
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub window: Duration,
+
+    /// Interval between two writes of the manifest file (default: 1m)
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub export_interval: Duration,
+
+    /// Maximum number of operations kept in the manifest (default: 10000)
+    pub max_operations: usize,
+}
+
 impl Default for PersistedQueries {
     fn default() -> Self {
         Self {
@@ -86,6 +118,7 @@ impl Default for PersistedQueries {
             log_unknown: default_log_unknown(),
             experimental_prewarm_query_plan_cache: PersistedQueriesPrewarmQueryPlanCache::default(),
             experimental_local_manifests: None,
+            experimental_manifest_generation: PersistedQueriesManifestGeneration::default(),
         }
     }
 }
@@ -108,6 +141,18 @@ impl Default for PersistedQueriesPrewarmQueryPlanCache {
     }
 }
 
+impl Default for PersistedQueriesManifestGeneration {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("persisted-query-manifest.json"),
+            window: Duration::from_secs(24 * 60 * 60),
+            export_interval: Duration::from_secs(60),
+            max_operations: 10_000,
+        }
+    }
+}
+
 const fn default_pq() -> bool {
     false
 }
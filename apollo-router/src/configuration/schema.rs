@@ -143,7 +143,7 @@ pub(crate) fn validate_yaml_configuration(
         }
     }
 
-    let expanded_yaml = expansion.expand(&yaml)?;
+    let (expanded_yaml, secrets_ttl) = expansion.expand_with_secrets_ttl(&yaml)?;
     let parsed_yaml = super::yaml::parse(raw_yaml)?;
     if let Err(errors_it) = schema.validate(&expanded_yaml) {
         // Validation failed, translate the errors into something nice for the user
@@ -305,6 +305,7 @@ pub(crate) fn validate_yaml_configuration(
         });
     }
     config.validated_yaml = Some(expanded_yaml);
+    config.secrets_ttl = secrets_ttl;
     Ok(config)
 }
 
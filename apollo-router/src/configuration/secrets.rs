@@ -0,0 +1,171 @@
+//! Secrets referenced in the configuration file
+//!
+//! A value such as `${secret.vault.kv/router#token}` is expanded by the secret resolver
+//! registered with the name `vault`, with the key `kv/router#token`. The `env` and `file`
+//! resolvers are built in, other resolvers are registered with [`register_secret_resolver`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use tower::BoxError;
+
+static SECRET_RESOLVERS: Lazy<RwLock<HashMap<String, Arc<dyn SecretResolver>>>> = Lazy::new(|| {
+    let mut resolvers: HashMap<String, Arc<dyn SecretResolver>> = HashMap::new();
+    resolvers.insert("env".to_string(), Arc::new(EnvSecretResolver));
+    resolvers.insert("file".to_string(), Arc::new(FileSecretResolver));
+    RwLock::new(resolvers)
+});
+
+/// The value of a secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Secret {
+    /// The value the secret is expanded to
+    pub value: String,
+    /// How long the value is valid for. The configuration is read again once this duration has
+    /// elapsed, and the router reloaded if any of its secrets changed.
+    pub ttl: Option<Duration>,
+}
+
+impl Secret {
+    /// A secret that does not expire
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            ttl: None,
+        }
+    }
+
+    /// A secret that must be resolved again after the given duration
+    pub fn with_ttl(value: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            value: value.into(),
+            ttl: Some(ttl),
+        }
+    }
+}
+
+/// Resolves the secrets referenced in the configuration file, such as the credentials of a
+/// secret manager like Vault or AWS Secrets Manager.
+///
+/// The configuration is loaded synchronously, so resolvers are called on a dedicated thread,
+/// outside of the async runtime of the router: they can do blocking I/O, or drive the futures of
+/// an async client with their own runtime.
+pub trait SecretResolver: Send + Sync + 'static {
+    /// Returns the secret with the given key
+    fn resolve(&self, key: &str) -> Result<Secret, BoxError>;
+}
+
+/// Registers a secret resolver, which expands the `${secret.<name>.<key>}` values of the
+/// configuration. It must be registered before the router starts.
+///
+/// A resolver registered with the name of a built-in resolver, `env` or `file`, replaces it.
+pub fn register_secret_resolver(name: impl Into<String>, resolver: impl SecretResolver) {
+    SECRET_RESOLVERS
+        .write()
+        .insert(name.into(), Arc::new(resolver));
+}
+
+/// Resolves a secret reference, in the `<name>.<key>` format
+pub(crate) fn resolve(reference: &str) -> Result<Secret, BoxError> {
+    let (name, key) = reference
+        .split_once('.')
+        .ok_or("secrets must be referenced as 'secret.<resolver>.<key>'")?;
+    let resolver = SECRET_RESOLVERS
+        .read()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("no secret resolver is registered with the name '{name}'"))?;
+    // The configuration may be loaded from a task of the async runtime, where blocking or
+    // starting another runtime would stall or panic
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| resolver.resolve(key))
+            .join()
+            .unwrap_or_else(|_| Err(format!("the secret resolver '{name}' panicked").into()))
+    })
+}
+
+/// Resolves secrets from environment variables
+struct EnvSecretResolver;
+
+impl SecretResolver for EnvSecretResolver {
+    fn resolve(&self, key: &str) -> Result<Secret, BoxError> {
+        Ok(Secret::new(std::env::var(key)?))
+    }
+}
+
+/// Resolves secrets from the content of files, such as the secrets mounted in a container
+struct FileSecretResolver;
+
+impl SecretResolver for FileSecretResolver {
+    fn resolve(&self, key: &str) -> Result<Secret, BoxError> {
+        let content = std::fs::read_to_string(key)?;
+        // Secret files usually end with a new line that isn't part of the secret
+        Ok(Secret::new(content.trim_end_matches(['\n', '\r'])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    struct Rotating;
+
+    impl SecretResolver for Rotating {
+        fn resolve(&self, key: &str) -> Result<Secret, BoxError> {
+            Ok(Secret::with_ttl(
+                format!("rotated-{key}"),
+                Duration::from_secs(60),
+            ))
+        }
+    }
+
+    #[test]
+    fn it_resolves_builtin_secrets() {
+        std::env::set_var("TEST_SECRET_RESOLVER_VAR", "from-env");
+        assert_eq!(
+            resolve("env.TEST_SECRET_RESOLVER_VAR").unwrap(),
+            Secret::new("from-env")
+        );
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "from-file").unwrap();
+        let reference = format!("file.{}", file.path().display());
+        assert_eq!(resolve(&reference).unwrap(), Secret::new("from-file"));
+    }
+
+    struct Async;
+
+    impl SecretResolver for Async {
+        fn resolve(&self, key: &str) -> Result<Secret, BoxError> {
+            let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+            let value = runtime.block_on(async { format!("async-{key}") });
+            Ok(Secret::new(value))
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn it_resolves_secrets_outside_of_the_runtime() {
+        register_secret_resolver("test_async", Async);
+        assert_eq!(
+            resolve("test_async.token").unwrap(),
+            Secret::new("async-token")
+        );
+    }
+
+    #[test]
+    fn it_resolves_registered_secrets() {
+        register_secret_resolver("test_rotating", Rotating);
+        assert_eq!(
+            resolve("test_rotating.db/password").unwrap(),
+            Secret::with_ttl("rotated-db/password", Duration::from_secs(60))
+        );
+        assert!(resolve("unregistered.key").is_err());
+        assert!(resolve("no_key").is_err());
+    }
+}
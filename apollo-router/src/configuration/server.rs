@@ -0,0 +1,361 @@
+//! Client-facing HTTP server configuration
+
+use std::fmt;
+use std::future::Future;
+use std::net::IpAddr;
+use std::num::NonZeroU64;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bytesize::ByteSize;
+use hyper::server::conn::Http;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Client-facing HTTP server configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct Server {
+    /// HTTP connection settings of the supergraph and other endpoints.
+    pub(crate) http: ServerHttp,
+
+    /// Resolution of the IP address of the clients.
+    pub(crate) client_ip: ClientIp,
+
+    /// Protections applied to the IP address of each client on the supergraph endpoint, before
+    /// their requests are parsed.
+    pub(crate) ip_protection: IpProtection,
+}
+
+/// HTTP connection settings.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct ServerHttp {
+    /// Set to false to only accept HTTP/1 connections.
+    ///
+    /// Defaults to true
+    pub(crate) http2: bool,
+
+    /// Maximum number of concurrent requests on an HTTP/2 connection.
+    ///
+    /// Defaults to no limit
+    pub(crate) http2_max_concurrent_streams: Option<u32>,
+
+    /// Maximum size of the headers of an HTTP/2 request.
+    ///
+    /// Defaults to 16MB
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) http2_max_header_list_size: Option<ByteSize>,
+
+    /// How long to wait for the headers of an HTTP/1 request before closing the connection.
+    ///
+    /// Defaults to 10s
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub(crate) header_read_timeout: Duration,
+
+    /// Set to false to close HTTP/1 connections after each response.
+    ///
+    /// Defaults to true
+    pub(crate) keep_alive: bool,
+
+    /// Interval of the pings sent on idle HTTP/2 connections to check that the client is
+    /// still there.
+    ///
+    /// Defaults to no pings
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) http2_keep_alive_interval: Option<Duration>,
+
+    /// How long to wait for the acknowledgement of a ping before closing the HTTP/2 connection.
+    ///
+    /// Defaults to 20s
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub(crate) http2_keep_alive_timeout: Duration,
+
+    /// Age after which connections are gracefully closed, so that clients reconnect and are
+    /// balanced again by the load balancers in front of the router. HTTP/2 connections receive
+    /// a GOAWAY frame, HTTP/1 connections are closed after their current response.
+    ///
+    /// Defaults to no maximum age
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) max_connection_age: Option<Duration>,
+
+    /// How long the requests in flight on a connection that reached its maximum age are given
+    /// to complete, before the connection is closed.
+    ///
+    /// Defaults to no limit
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) max_connection_age_grace: Option<Duration>,
+}
+
+impl Default for ServerHttp {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            http2_max_concurrent_streams: None,
+            http2_max_header_list_size: None,
+            header_read_timeout: Duration::from_secs(10),
+            keep_alive: true,
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: Duration::from_secs(20),
+            max_connection_age: None,
+            max_connection_age_grace: None,
+        }
+    }
+}
+
+impl ServerHttp {
+    /// Applies the connection settings to the HTTP server
+    pub(crate) fn configure(&self, http_config: &mut Http) {
+        http_config.http1_keep_alive(self.keep_alive);
+        http_config.http1_header_read_timeout(self.header_read_timeout);
+        if !self.http2 {
+            http_config.http1_only(true);
+        }
+        http_config.http2_max_concurrent_streams(self.http2_max_concurrent_streams);
+        if let Some(max_header_list_size) = self.http2_max_header_list_size {
+            http_config.http2_max_header_list_size(
+                u32::try_from(max_header_list_size.as_u64()).unwrap_or(u32::MAX),
+            );
+        }
+        http_config.http2_keep_alive_interval(self.http2_keep_alive_interval);
+        http_config.http2_keep_alive_timeout(self.http2_keep_alive_timeout);
+    }
+
+    pub(crate) fn connection_age(&self) -> ConnectionAge {
+        ConnectionAge {
+            max: self.max_connection_age,
+            grace: self.max_connection_age_grace,
+        }
+    }
+}
+
+/// Graceful closing of the connections that reached their maximum age
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnectionAge {
+    max: Option<Duration>,
+    grace: Option<Duration>,
+}
+
+impl ConnectionAge {
+    /// Completes when a connection accepted now reaches its maximum age, never if there is none
+    pub(crate) async fn expired(self) {
+        match self.max {
+            Some(max) => tokio::time::sleep(max).await,
+            None => futures::future::pending().await,
+        }
+    }
+
+    /// Waits for a connection that reached its maximum age to complete its requests in flight,
+    /// at most for the grace period
+    pub(crate) async fn drain<F: Future>(self, connection: F) {
+        match self.grace {
+            Some(grace) => {
+                let _ = tokio::time::timeout(grace, connection).await;
+            }
+            None => {
+                connection.await;
+            }
+        }
+    }
+}
+
+/// Resolution of the IP address of the clients.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct ClientIp {
+    /// Proxies and load balancers in front of the router, in CIDR notation. The IP address of
+    /// the clients connecting through them is read from the `forwarded_header` header.
+    ///
+    /// Defaults to none: the IP address of a client is the one it connects from
+    #[schemars(with = "Vec<String>")]
+    pub(crate) trusted_proxies: Vec<IpNetwork>,
+
+    /// The header the trusted proxies add the address of their clients to. The other header is
+    /// ignored, as clients can set it to any address.
+    ///
+    /// Defaults to `x_forwarded_for`
+    pub(crate) forwarded_header: ForwardedHeader,
+}
+
+/// Header carrying the addresses of the clients of proxies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ForwardedHeader {
+    /// The `X-Forwarded-For` header
+    #[default]
+    XForwardedFor,
+    /// The standard `Forwarded` header, from its `for` parameters
+    Forwarded,
+}
+
+/// Protections applied to the IP address of each client.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub(crate) struct IpProtection {
+    /// IP addresses allowed to connect, in CIDR notation.
+    ///
+    /// Defaults to all IP addresses
+    #[schemars(with = "Vec<String>")]
+    pub(crate) allow: Vec<IpNetwork>,
+
+    /// IP addresses denied, in CIDR notation, even if they are part of the allowed ones.
+    #[schemars(with = "Vec<String>")]
+    pub(crate) deny: Vec<IpNetwork>,
+
+    /// Maximum number of open connections from the same IP address.
+    ///
+    /// Defaults to no limit
+    pub(crate) max_connections_per_ip: Option<NonZeroUsize>,
+
+    /// Rate limit of the requests from the same IP address.
+    ///
+    /// Defaults to no limit
+    pub(crate) max_requests_per_ip: Option<IpRateLimit>,
+}
+
+impl IpProtection {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.allow.is_empty()
+            || !self.deny.is_empty()
+            || self.max_connections_per_ip.is_some()
+            || self.max_requests_per_ip.is_some()
+    }
+}
+
+/// Rate limit of the requests from the same IP address.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct IpRateLimit {
+    /// Number of requests allowed
+    pub(crate) capacity: NonZeroU64,
+    /// Per interval
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub(crate) interval: Duration,
+}
+
+/// A range of IP addresses in CIDR notation, such as `10.0.0.0/8`, or a single IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let address = IpAddr::from_str(address)
+            .map_err(|_| format!("invalid IP address in '{s}'"))?
+            .to_canonical();
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                .ok_or_else(|| format!("invalid prefix length in '{s}'"))?,
+            None => max_prefix_len,
+        };
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_networks_contain_their_addresses() {
+        let network: IpNetwork = "10.0.0.0/8".parse().unwrap();
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+        assert!(!network.contains("::1".parse().unwrap()));
+
+        let network: IpNetwork = "2001:db8::/32".parse().unwrap();
+        assert!(network.contains("2001:db8::1".parse().unwrap()));
+        assert!(!network.contains("2001:db9::1".parse().unwrap()));
+
+        let network: IpNetwork = "192.168.1.1".parse().unwrap();
+        assert_eq!(network.to_string(), "192.168.1.1/32");
+        assert!(network.contains("192.168.1.1".parse().unwrap()));
+        assert!(!network.contains("192.168.1.2".parse().unwrap()));
+
+        let network: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(network.contains("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn invalid_ip_networks_are_rejected() {
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("not an address".parse::<IpNetwork>().is_err());
+        assert!("10.0.0.0/".parse::<IpNetwork>().is_err());
+    }
+}
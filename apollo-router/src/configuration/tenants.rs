@@ -0,0 +1,162 @@
+//! Multi-tenant configuration
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use http::header::HOST;
+use http::uri::Authority;
+use http::HeaderName;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A graph served by the router next to the main supergraph.
+///
+/// Each tenant has its own schema, configuration, query plan cache and usage reporting graph ref.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Tenant {
+    /// The name of the tenant. It is stored in the context of its requests under the
+    /// `apollo::tenant::name` key.
+    pub(crate) name: String,
+
+    /// Selects the requests sent to this tenant.
+    #[serde(rename = "match")]
+    pub(crate) matcher: TenantMatch,
+
+    /// Path to the supergraph schema of the tenant.
+    pub(crate) supergraph_path: PathBuf,
+
+    /// Path to the router configuration of the tenant.
+    ///
+    /// Defaults to the main configuration, without its tenants
+    #[serde(default)]
+    pub(crate) configuration_path: Option<PathBuf>,
+
+    /// The graph ref the usage of the tenant is reported to.
+    ///
+    /// Defaults to the graph ref of the router
+    #[serde(default)]
+    pub(crate) graph_ref: Option<String>,
+
+    /// The API key of the graph the usage of the tenant is reported to.
+    ///
+    /// Defaults to the API key of the router
+    #[serde(default)]
+    pub(crate) apollo_key: Option<String>,
+}
+
+/// How requests are matched to a tenant.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum TenantMatch {
+    /// Requests sent to this host name, from the `Host` header or the URI authority
+    Host(String),
+    /// Requests whose path starts with this prefix, compared segment by segment
+    PathPrefix(String),
+    /// Requests with this header value
+    Header {
+        /// The header name
+        name: String,
+        /// The header value
+        value: String,
+    },
+}
+
+impl TenantMatch {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self {
+            TenantMatch::Host(host) => {
+                if host.is_empty() {
+                    return Err("the host cannot be empty".to_string());
+                }
+            }
+            TenantMatch::PathPrefix(prefix) => {
+                if !prefix.starts_with('/') {
+                    return Err(format!("'{prefix}' is invalid, it must start with '/'"));
+                }
+            }
+            TenantMatch::Header { name, .. } => {
+                HeaderName::from_str(name).map_err(|e| format!("invalid header name: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn matches<B>(&self, request: &http::Request<B>) -> bool {
+        match self {
+            TenantMatch::Host(host) => request_host(request)
+                .is_some_and(|authority| authority.host().eq_ignore_ascii_case(host)),
+            TenantMatch::PathPrefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                match request.uri().path().strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+            TenantMatch::Header { name, value } => request
+                .headers()
+                .get_all(name.as_str())
+                .iter()
+                .any(|header| header.as_bytes() == value.as_bytes()),
+        }
+    }
+}
+
+fn request_host<B>(request: &http::Request<B>) -> Option<Authority> {
+    if let Some(authority) = request.uri().authority() {
+        return Some(authority.clone());
+    }
+    let host = request.headers().get(HOST)?.to_str().ok()?;
+    Authority::from_str(host).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, headers: &[(&str, &str)]) -> http::Request<()> {
+        let mut builder = http::Request::builder().uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn it_matches_requests_to_tenants() {
+        let host = TenantMatch::Host("acme.example.com".to_string());
+        assert!(host.matches(&request("/", &[("host", "ACME.example.com:4000")])));
+        assert!(host.matches(&request("http://acme.example.com/graphql", &[])));
+        assert!(!host.matches(&request("/", &[("host", "other.example.com")])));
+        assert!(!host.matches(&request("/", &[])));
+
+        let prefix = TenantMatch::PathPrefix("/acme/".to_string());
+        assert!(prefix.matches(&request("/acme", &[])));
+        assert!(prefix.matches(&request("/acme/graphql?query=1", &[])));
+        assert!(!prefix.matches(&request("/acmecorp/graphql", &[])));
+        assert!(!prefix.matches(&request("/", &[])));
+
+        let header = TenantMatch::Header {
+            name: "X-Tenant".to_string(),
+            value: "acme".to_string(),
+        };
+        assert!(header.matches(&request("/", &[("x-tenant", "acme")])));
+        assert!(!header.matches(&request("/", &[("x-tenant", "other")])));
+        assert!(!header.matches(&request("/", &[])));
+    }
+
+    #[test]
+    fn it_validates_matchers() {
+        assert!(TenantMatch::PathPrefix("acme".to_string())
+            .validate()
+            .is_err());
+        assert!(TenantMatch::Host(String::new()).validate().is_err());
+        assert!(TenantMatch::Header {
+            name: "x tenant".to_string(),
+            value: "acme".to_string(),
+        }
+        .validate()
+        .is_err());
+    }
+}
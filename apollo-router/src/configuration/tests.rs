@@ -107,6 +107,77 @@ fn bad_graphql_path_configuration_without_slash() {
     assert_eq!(error.to_string(), String::from("invalid 'server.graphql_path' configuration: 'test' is invalid, it must be an absolute path and start with '/', you should try with '/test'"));
 }
 
+#[test]
+fn bad_tenants_configuration() {
+    let error = Configuration::from_str(
+        r#"
+experimental_tenants:
+  - name: acme
+    match:
+      path_prefix: /acme
+    supergraph_path: acme.graphql
+  - name: acme
+    match:
+      host: acme.example.com
+    supergraph_path: acme.graphql
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("the tenant name 'acme' is used more than once"));
+
+    let error = Configuration::from_str(
+        r#"
+experimental_tenants:
+  - name: acme
+    match:
+      path_prefix: acme
+    supergraph_path: acme.graphql
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("tenant 'acme': 'acme' is invalid, it must start with '/'"));
+}
+
+#[test]
+fn bad_admin_configuration() {
+    let error = Configuration::from_str(
+        r#"
+admin:
+  enabled: true
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("an 'api_key' is required to enable the admin API"));
+
+    let error = Configuration::from_str(
+        r#"
+admin:
+  enabled: true
+  listen: 127.0.0.1:4000
+  api_key: secret
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("the admin API must listen on a different address than the supergraph"));
+
+    assert!(Configuration::from_str(
+        r#"
+admin:
+  enabled: true
+  api_key: secret
+"#,
+    )
+    .is_ok());
+}
+
 #[test]
 fn bad_graphql_path_configuration_with_wildcard_as_prefix() {
     let error = Configuration::fake_builder()
@@ -750,6 +821,52 @@ tls:
     cfg.tls.supergraph.unwrap().tls_config().unwrap();
 }
 
+#[tokio::test]
+async fn load_tls_from_reload_files() {
+    let mut testdata = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    testdata.push("src");
+    testdata.push("configuration");
+    testdata.push("testdata");
+    let cert_path = testdata.join("server.crt");
+    let cert_path = cert_path.to_string_lossy();
+    let key_path = testdata.join("server.key");
+    let key_path = key_path.to_string_lossy();
+
+    let cfg = validate_yaml_configuration(
+        &format!(
+            r#"
+tls:
+  supergraph:
+    reload:
+      certificate: {cert_path}
+      key: {key_path}
+"#,
+        ),
+        Expansion::default().unwrap(),
+        Mode::NoUpgrade,
+    )
+    .expect("should not have resulted in an error");
+    cfg.tls.supergraph.unwrap().tls_config().unwrap();
+
+    let cfg = validate_yaml_configuration(
+        &format!(
+            r#"
+tls:
+  supergraph:
+    certificate: ${{file.{cert_path}}}
+    key: ${{file.{key_path}}}
+    reload:
+      certificate: {cert_path}
+      key: {key_path}
+"#,
+        ),
+        Expansion::builder().supported_mode("file").build(),
+        Mode::NoUpgrade,
+    )
+    .expect("should not have resulted in an error");
+    assert!(cfg.tls.supergraph.unwrap().tls_config().is_err());
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 struct TestSubgraphOverride {
     value: Option<u8>,
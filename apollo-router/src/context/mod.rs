@@ -30,6 +30,8 @@ pub(crate) const OPERATION_NAME: &str = "operation_name";
 pub(crate) const OPERATION_KIND: &str = "operation_kind";
 /// The key to know if the response body contains at least 1 GraphQL error
 pub(crate) const CONTAINS_GRAPHQL_ERROR: &str = "apollo::telemetry::contains_graphql_error";
+/// The key of the name of the tenant serving the request
+pub(crate) const TENANT_NAME: &str = "apollo::tenant::name";
 
 /// Holds [`Context`] entries.
 pub(crate) type Entries = Arc<DashMap<String, Value>>;
@@ -206,7 +208,10 @@ impl Context {
     /// The resolving function must yield a value to be used in the context. It
     /// is provided with the current value to use in evaluating which value to
     /// yield.
-    pub(crate) fn upsert_json_value<K>(&self, key: K, upsert: impl FnOnce(Value) -> Value)
+    ///
+    /// If no value is present for the key, the resolving function is provided
+    /// with [`Value::Null`].
+    pub fn upsert_json_value<K>(&self, key: K, upsert: impl FnOnce(Value) -> Value)
     where
         K: Into<String>,
     {
@@ -215,6 +220,18 @@ impl Context {
         self.entries.alter(&key, |_, v| upsert(v));
     }
 
+    /// Remove the entry of the provided key from the context, whether it was inserted with
+    /// [`Context::insert`] or with [`Context::insert_json_value`].
+    ///
+    /// Semantics: the result is the removed value in its JSON representation, or `None` if the
+    /// context had no entry for the key.
+    pub fn remove_json_value<K>(&self, key: K) -> Option<Value>
+    where
+        K: Into<String>,
+    {
+        self.entries.remove(&key.into()).map(|(_, v)| v)
+    }
+
     /// Convert the context into an iterator.
     pub(crate) fn try_into_iter(
         self,
@@ -378,6 +395,34 @@ mod test {
         assert_eq!(c.get("not_present").unwrap(), Some(1));
     }
 
+    #[test]
+    fn test_context_json_values() {
+        let c = Context::new();
+        assert_eq!(
+            c.insert_json_value("json", serde_json_bytes::json!(1)),
+            None
+        );
+        assert_eq!(c.get_json_value("json"), Some(serde_json_bytes::json!(1)));
+        c.upsert_json_value("json", |v| serde_json_bytes::json!([v, 2]));
+        c.upsert_json_value("absent", |v| {
+            assert_eq!(v, serde_json_bytes::Value::Null);
+            serde_json_bytes::json!("value")
+        });
+        assert_eq!(
+            c.get_json_value("json"),
+            Some(serde_json_bytes::json!([1, 2]))
+        );
+        assert_eq!(
+            c.get::<_, String>("absent").unwrap(),
+            Some("value".to_string())
+        );
+        assert_eq!(
+            c.remove_json_value("json"),
+            Some(serde_json_bytes::json!([1, 2]))
+        );
+        assert!(!c.contains_key("json"));
+    }
+
     #[test]
     fn test_context_marshall_errors() {
         let c = Context::new();
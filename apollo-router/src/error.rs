@@ -91,6 +91,15 @@ pub(crate) enum FetchError {
         /// The reason the fetch failed.
         reason: String,
     },
+    /// response from '{service}' was too large: {reason}
+    SubrequestResponseTooLarge {
+        /// The service whose response was too large.
+        service: String,
+
+        /// The limit that was exceeded.
+        reason: String,
+    },
+
     /// Websocket fetch failed from '{service}': {reason}
     ///
     /// note that this relates to a transport error and not a GraphQL error
@@ -141,6 +150,7 @@ impl FetchError {
                 }
                 FetchError::SubrequestMalformedResponse { service, .. }
                 | FetchError::SubrequestUnexpectedPatchResponse { service }
+                | FetchError::SubrequestResponseTooLarge { service, .. }
                 | FetchError::SubrequestWsError { service, .. } => {
                     extensions
                         .entry("service")
@@ -182,6 +192,7 @@ impl ErrorExtension for FetchError {
                 "SUBREQUEST_UNEXPECTED_PATCH_RESPONSE"
             }
             FetchError::SubrequestHttpError { .. } => "SUBREQUEST_HTTP_ERROR",
+            FetchError::SubrequestResponseTooLarge { .. } => "SUBREQUEST_RESPONSE_TOO_LARGE",
             FetchError::SubrequestWsError { .. } => "SUBREQUEST_WEBSOCKET_ERROR",
             FetchError::ExecutionPathNotFound { .. } => "EXECUTION_PATH_NOT_FOUND",
             FetchError::MalformedRequest { .. } => "MALFORMED_REQUEST",
@@ -309,6 +320,10 @@ pub(crate) enum FederationErrorBridge {
     /// {0}
     OperationNameNotProvided(String),
     /// {0}
+    QueryPlanTooLarge(String),
+    /// {0}
+    QueryPlanningTimeout(String),
+    /// {0}
     Other(String),
 }
 
@@ -321,6 +336,12 @@ impl From<FederationError> for FederationErrorBridge {
             err @ FederationError::SingleFederationError(
                 apollo_federation::error::SingleFederationError::OperationNameNotProvided,
             ) => Self::OperationNameNotProvided(err.to_string()),
+            err @ FederationError::SingleFederationError(
+                apollo_federation::error::SingleFederationError::QueryPlanTooLarge { .. },
+            ) => Self::QueryPlanTooLarge(err.to_string()),
+            err @ FederationError::SingleFederationError(
+                apollo_federation::error::SingleFederationError::QueryPlanningTimeout { .. },
+            ) => Self::QueryPlanningTimeout(err.to_string()),
             err => Self::Other(err.to_string()),
         }
     }
@@ -337,6 +358,14 @@ impl IntoGraphQLErrors for FederationErrorBridge {
                 .message(msg)
                 .extension_code("GRAPHQL_VALIDATION_FAILED")
                 .build()]),
+            FederationErrorBridge::QueryPlanTooLarge(msg) => Ok(vec![Error::builder()
+                .message(msg)
+                .extension_code("QUERY_PLAN_TOO_LARGE")
+                .build()]),
+            FederationErrorBridge::QueryPlanningTimeout(msg) => Ok(vec![Error::builder()
+                .message(msg)
+                .extension_code("QUERY_PLANNING_TIMEOUT")
+                .build()]),
             // All other errors will be pushed on and be treated as internal server errors
             err => Err(err),
         }
@@ -6,6 +6,7 @@ use std::net::SocketAddr;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
@@ -37,16 +38,36 @@ use crate::router::SchemaSource;
 use crate::router::ShutdownSource;
 use crate::uplink::Endpoints;
 use crate::uplink::UplinkConfig;
+use crate::Configuration;
 use crate::LicenseSource;
 
 #[cfg(all(
     feature = "global-allocator",
     not(feature = "dhat-heap"),
+    not(feature = "memory-instrumentation"),
     target_os = "linux"
 ))]
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+#[cfg(all(
+    feature = "memory-instrumentation",
+    not(feature = "dhat-heap"),
+    target_os = "linux"
+))]
+#[global_allocator]
+static ALLOC: crate::allocator::TrackingAllocator<tikv_jemallocator::Jemalloc> =
+    crate::allocator::TrackingAllocator::new(tikv_jemallocator::Jemalloc);
+
+#[cfg(all(
+    feature = "memory-instrumentation",
+    not(feature = "dhat-heap"),
+    not(target_os = "linux")
+))]
+#[global_allocator]
+static ALLOC: crate::allocator::TrackingAllocator<std::alloc::System> =
+    crate::allocator::TrackingAllocator::new(std::alloc::System);
+
 // Note: the dhat-heap and dhat-ad-hoc features should not be both enabled. We name our functions
 // and variables identically to prevent this from happening.
 
@@ -140,6 +161,12 @@ enum ConfigSubcommand {
         #[clap(action = ArgAction::SetTrue, long)]
         diff: bool,
     },
+    /// Validate a configuration file against the configuration schema.
+    Validate {
+        /// The location of the config to validate.
+        #[clap(value_parser, env = "APOLLO_ROUTER_CONFIG_PATH")]
+        config_path: PathBuf,
+    },
     /// List all the available experimental configurations with related GitHub discussion
     Experimental,
     /// List all the available preview configurations with related GitHub discussion
@@ -197,6 +224,15 @@ pub struct Opt {
     )]
     supergraph_path: Option<PathBuf>,
 
+    /// Directory of subgraph schemas to compose into the supergraph, with a `supergraph.yaml` routing config. The subgraphs are composed again when they change if hot reload is enabled.
+    #[clap(
+        long = "subgraphs",
+        value_parser,
+        env = "APOLLO_ROUTER_SUBGRAPHS_PATH",
+        conflicts_with_all = ["supergraph_path", "supergraph_urls"]
+    )]
+    subgraphs_path: Option<PathBuf>,
+
     /// Locations (comma separated) to fetch the supergraph from. These will be queried in order.
     #[clap(env = "APOLLO_ROUTER_SUPERGRAPH_URLS", value_delimiter = ',')]
     supergraph_urls: Option<Vec<Url>>,
@@ -262,22 +298,24 @@ pub struct Opt {
 fn add_log_filter(raw: &str) -> Result<String, String> {
     match std::env::var("RUST_LOG") {
         Ok(filter) => Ok(filter),
-        Err(_e) => {
-            // Directives are case-insensitive. Convert to lowercase before processing.
-            let lowered = raw.to_lowercase();
-            // Find "global" directives and limit them to apollo_router
-            let rgx =
-                Regex::new(r"(^|,)(off|error|warn|info|debug|trace)").expect("regex must be valid");
-            let res = rgx.replace_all(&lowered, |caps: &Captures| {
-                // The default level is info, then other ones can override the default one
-                // If the pattern matches, we must have caps 1 and 2
-                format!("{}apollo_router={}", &caps[1], &caps[2])
-            });
-            Ok(format!("info,{res}"))
-        }
+        Err(_e) => Ok(router_log_filter(raw)),
     }
 }
 
+/// Limits the "global" directives of a log level to the router.
+pub(crate) fn router_log_filter(raw: &str) -> String {
+    // Directives are case-insensitive. Convert to lowercase before processing.
+    let lowered = raw.to_lowercase();
+    // Find "global" directives and limit them to apollo_router
+    let rgx = Regex::new(r"(^|,)(off|error|warn|info|debug|trace)").expect("regex must be valid");
+    let res = rgx.replace_all(&lowered, |caps: &Captures| {
+        // The default level is info, then other ones can override the default one
+        // If the pattern matches, we must have caps 1 and 2
+        format!("{}apollo_router={}", &caps[1], &caps[2])
+    });
+    format!("info,{res}")
+}
+
 impl Opt {
     pub(crate) fn uplink_config(&self) -> Result<UplinkConfig, anyhow::Error> {
         Ok(UplinkConfig {
@@ -440,6 +478,20 @@ impl Executable {
                 println!("{output}");
                 Ok(())
             }
+            Some(Commands::Config(ConfigSubcommandArgs {
+                command: ConfigSubcommand::Validate { config_path },
+            })) => {
+                let config_string = std::fs::read_to_string(config_path)?;
+                Configuration::from_str(&config_string).map_err(|e| {
+                    anyhow!(
+                        "{} is not a valid configuration: {}",
+                        config_path.display(),
+                        e
+                    )
+                })?;
+                println!("{} is a valid configuration", config_path.display());
+                Ok(())
+            }
             Some(Commands::Config(ConfigSubcommandArgs {
                 command: ConfigSubcommand::Experimental,
             })) => {
@@ -525,6 +577,29 @@ impl Executable {
         #[cfg(not(unix))]
         let akp: &Option<PathBuf> = &None;
 
+        let schema = match (schema, &opt.subgraphs_path) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "--subgraphs and APOLLO_ROUTER_SUBGRAPHS_PATH cannot be used when a custom schema source is in use"
+                ))
+            }
+            (None, Some(subgraphs_path)) => {
+                tracing::info!("{apollo_router_msg}");
+                tracing::info!("{apollo_telemetry_msg}");
+
+                let subgraphs_path = if subgraphs_path.is_relative() {
+                    current_directory.join(subgraphs_path)
+                } else {
+                    subgraphs_path.clone()
+                };
+                Some(SchemaSource::Subgraphs {
+                    path: subgraphs_path,
+                    watch: opt.hot_reload,
+                })
+            }
+            (schema, None) => schema,
+        };
+
         let schema_source = match (schema, &opt.supergraph_path, &opt.supergraph_urls, &opt.apollo_key, akp) {
             (Some(_), Some(_), _, _, _) | (Some(_), _, Some(_), _, _) => {
                 return Err(anyhow!(
@@ -634,6 +709,10 @@ impl Executable {
 
       $ ./router --supergraph <file_path>
 
+  * Compose a directory of subgraph schemas with the '--subgraphs' option:
+
+      $ ./router --dev --subgraphs <directory>
+
   * Fetch a registered schema from GraphOS by setting
     these environment variables:
 
@@ -744,11 +823,19 @@ fn setup_panic_handler() {
         tracing::warn!("RUST_BACKTRACE={} detected. This is useful for diagnostics but will have a performance impact and may leak sensitive information", backtrace_env.as_ref().unwrap());
     }
     std::panic::set_hook(Box::new(move |e| {
+        // The id of the request a caught panic happened in, also sent to the client
+        let request_id = crate::layers::catch_panic::panicking_request_id();
+        let request_id = request_id.as_deref();
         if show_backtraces {
             let backtrace = std::backtrace::Backtrace::capture();
-            tracing::error!("{}\n{}", e, backtrace)
+            tracing::error!(request_id, "{}\n{}", e, backtrace)
         } else {
-            tracing::error!("{}", e)
+            tracing::error!(request_id, "{}", e)
+        }
+
+        // Panics while processing a request are caught and turned into an error response
+        if crate::layers::catch_panic::is_catching() {
+            return;
         }
 
         // Once we've panic'ed the behaviour of the router is non-deterministic
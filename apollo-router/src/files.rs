@@ -23,6 +23,9 @@ const DEFAULT_WATCH_DURATION: Duration = Duration::from_millis(100);
 /// Creates a stream events whenever the file at the path has changes. The stream never terminates
 /// and must be dropped to finish watching.
 ///
+/// A file that does not exist yet is watched through its directory, so that an event is sent
+/// once it is created.
+///
 /// # Arguments
 ///
 /// * `path`: The file to watch
@@ -34,10 +37,21 @@ pub(crate) fn watch(path: &Path) -> impl Stream<Item = ()> {
 }
 
 fn watch_with_duration(path: &Path, duration: Duration) -> impl Stream<Item = ()> {
-    // Due to the vagaries of file watching across multiple platforms, instead of watching the
-    // supplied path (file), we are going to watch the parent (directory) of the path.
+    // A path that does not exist cannot be watched, so the parent (directory) of the path is
+    // watched until the file is created.
     let config_file_path = PathBuf::from(path);
     let watched_path = config_file_path.clone();
+    let watch_target = if config_file_path.exists() {
+        config_file_path.clone()
+    } else {
+        match config_file_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    };
+    // Paths of events reported for the entries of a directory are relative to the directory,
+    // so the file is recognized by its name
+    let watches_directory = watch_target != config_file_path;
 
     let (watch_sender, watch_receiver) = mpsc::channel(1);
     let watch_receiver_stream = tokio_stream::wrappers::ReceiverStream::new(watch_receiver);
@@ -52,13 +66,21 @@ fn watch_with_duration(path: &Path, duration: Duration) -> impl Stream<Item = ()
     let mut watcher = PollWatcher::new(
         move |res: Result<notify::Event, notify::Error>| match res {
             Ok(event) => {
-                // The two kinds of events of interest to use are writes to the metadata of a
-                // watched file and changes to the data of a watched file
+                // The kinds of events of interest to use are writes to the metadata of a
+                // watched file, changes to the data of a watched file, and its creation
+                let is_watched_path = |path: &PathBuf| {
+                    if watches_directory {
+                        path.file_name() == watched_path.file_name()
+                    } else {
+                        *path == watched_path
+                    }
+                };
                 if matches!(
                     event.kind,
                     EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime))
                         | EventKind::Modify(ModifyKind::Data(DataChange::Any))
-                ) && event.paths.contains(&watched_path)
+                        | EventKind::Create(_)
+                ) && event.paths.iter().any(is_watched_path)
                 {
                     loop {
                         match watch_sender.try_send(()) {
@@ -84,8 +106,8 @@ fn watch_with_duration(path: &Path, duration: Duration) -> impl Stream<Item = ()
     )
     .unwrap_or_else(|_| panic!("could not create watch on: {config_file_path:?}"));
     watcher
-        .watch(&config_file_path, RecursiveMode::NonRecursive)
-        .unwrap_or_else(|_| panic!("could not watch: {config_file_path:?}"));
+        .watch(&watch_target, RecursiveMode::NonRecursive)
+        .unwrap_or_else(|_| panic!("could not watch: {watch_target:?}"));
     // Tell watchers once they should read the file once,
     // then listen to fs events.
     stream::once(future::ready(()))
@@ -128,6 +150,21 @@ pub(crate) mod tests {
         assert!(futures::poll!(watch.next()).is_ready())
     }
 
+    #[test(tokio::test)]
+    async fn missing_file_watch() {
+        let path = temp_dir().join(format!("{}", uuid::Uuid::new_v4()));
+        let mut watch = watch_with_duration(&path, Duration::from_millis(100));
+        assert!(futures::poll!(watch.next()).is_ready());
+        assert!(futures::poll!(watch.next()).is_pending());
+
+        // The creation of the file is noticed, then its changes
+        let mut file = std::fs::File::create(&path).unwrap();
+        write_and_flush(&mut file, "Some data 1").await;
+        assert!(futures::poll!(watch.next()).is_ready());
+        write_and_flush(&mut file, "Some data 2").await;
+        assert!(futures::poll!(watch.next()).is_ready())
+    }
+
     pub(crate) fn create_temp_file() -> (PathBuf, File) {
         let path = temp_dir().join(format!("{}", uuid::Uuid::new_v4()));
         let file = std::fs::File::create(&path).unwrap();
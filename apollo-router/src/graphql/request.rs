@@ -154,8 +154,21 @@ impl Request {
         }
     }
 
-    /// Deserialize as JSON from `&Bytes`, avoiding string copies where possible
+    /// Deserialize as JSON from `&Bytes`, avoiding string copies where possible.
+    #[cfg(not(feature = "simd-json"))]
     pub fn deserialize_from_bytes(data: &Bytes) -> Result<Self, serde_json::Error> {
+        Self::deserialize_from_bytes_with_serde_json(data)
+    }
+
+    /// Deserialize as JSON from `&Bytes` with simd-json, from a copy of the bytes as simd-json
+    /// parses in place.
+    #[cfg(feature = "simd-json")]
+    pub fn deserialize_from_bytes(data: &Bytes) -> Result<Self, serde_json::Error> {
+        crate::json_ext::simd_from_vec(data.to_vec())
+    }
+
+    #[cfg_attr(feature = "simd-json", allow(dead_code))]
+    fn deserialize_from_bytes_with_serde_json(data: &Bytes) -> Result<Self, serde_json::Error> {
         let seed = RequestFromBytesSeed(data);
         let mut de = serde_json::Deserializer::from_slice(data);
         seed.deserialize(&mut de)
@@ -180,6 +193,9 @@ impl Request {
     /// An error will be produced in the event that the bytes array cannot be
     /// turned into a valid GraphQL `Request`.
     pub(crate) fn batch_from_bytes(bytes: &[u8]) -> Result<Vec<Request>, serde_json::Error> {
+        #[cfg(feature = "simd-json")]
+        let value: serde_json::Value = crate::json_ext::simd_from_vec(bytes.to_vec())?;
+        #[cfg(not(feature = "simd-json"))]
         let value: serde_json::Value =
             serde_json::from_slice(bytes).map_err(serde_json::Error::custom)?;
 
@@ -404,6 +420,42 @@ mod tests {
 
     use super::*;
 
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn simd_json_deserializes_requests_like_serde_json() {
+        for json in [
+            r#"{"query":"{ a }","operationName":"A","variables":{"a":1},"extensions":{"b":2}}"#,
+            // duplicate fields are rejected
+            r#"{"query":"{ a }","query":"{ b }"}"#,
+            r#"{"query":"{ a }","variables":{"a":1},"variables":{"a":2}}"#,
+            // null is accepted instead of the variables and extensions objects
+            r#"{"query":"{ a }","variables":null,"extensions":null,"operationName":null}"#,
+            r#"{"query":"{ a }","variables":{"a":null}}"#,
+            r#"{"query":"{ a }","variables":{"big":12345678901234567890123,"max":18446744073709551615,"min":-9223372036854775808,"float":1.5e300}}"#,
+            r#"{"query":"{ a }","variables":"not an object"}"#,
+            r#"{"query":"{ a }","unknown":[1,2]}"#,
+            r#"{"query":"#,
+        ] {
+            let bytes = Bytes::from_static(json.as_bytes());
+            let simd_json = Request::deserialize_from_bytes(&bytes);
+            let serde_json = Request::deserialize_from_bytes_with_serde_json(&bytes);
+            match (simd_json, serde_json) {
+                (Ok(simd_json), Ok(serde_json)) => assert_eq!(simd_json, serde_json, "{json}"),
+                (Err(_), Err(_)) => {}
+                (simd_json, serde_json) => {
+                    panic!("{json}: simd-json {simd_json:?}, serde_json {serde_json:?}")
+                }
+            }
+        }
+
+        let batch = br#"[{"query":"{ a }","variables":null},{"query":"{ b }","variables":{"big":12345678901234567890123}}]"#;
+        assert_eq!(
+            Request::batch_from_bytes(batch).unwrap(),
+            serde_json::from_slice::<Vec<Request>>(batch).unwrap()
+        );
+        assert!(Request::batch_from_bytes(br#"[{"query":"{ a }","query":"{ b }"}]"#).is_err());
+    }
+
     #[test]
     fn test_request() {
         let data = json!(
@@ -10,6 +10,7 @@ use serde_json_bytes::Map;
 use crate::error::Error;
 use crate::error::FetchError;
 use crate::graphql::IntoGraphQLErrors;
+use crate::json_ext;
 use crate::json_ext::Object;
 use crate::json_ext::Path;
 use crate::json_ext::Value;
@@ -96,13 +97,26 @@ impl Response {
 
     /// Create a [`Response`] from the supplied [`Bytes`].
     ///
+    /// The strings of the response are not copied: they reference the supplied bytes, unless
+    /// the router is built with the `simd-json` feature. simd-json copies them, and parses in
+    /// place in a copy of the bytes if they are shared.
+    ///
     /// This will return an error (identifying the faulty service) if the input is invalid.
     pub(crate) fn from_bytes(service_name: &str, b: Bytes) -> Result<Response, FetchError> {
-        let value =
-            Value::from_bytes(b).map_err(|error| FetchError::SubrequestMalformedResponse {
+        let value = json_ext::value_from_bytes(b).map_err(|error| {
+            FetchError::SubrequestMalformedResponse {
                 service: service_name.to_string(),
                 reason: error.to_string(),
-            })?;
+            }
+        })?;
+        Response::from_value(service_name, value)
+    }
+
+    /// Create a [`Response`] from an already parsed JSON value, such as one of the responses of
+    /// a batch.
+    ///
+    /// This will return an error (identifying the faulty service) if the input is invalid.
+    pub(crate) fn from_value(service_name: &str, value: Value) -> Result<Response, FetchError> {
         let object =
             ensure_object!(value).map_err(|error| FetchError::SubrequestMalformedResponse {
                 service: service_name.to_string(),
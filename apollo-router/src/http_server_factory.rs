@@ -202,6 +202,44 @@ pub(crate) enum NetworkStream {
     Tls(tokio_rustls::server::TlsStream<tokio::net::TcpStream>),
 }
 
+impl NetworkStream {
+    /// The address of the client, unknown for Unix sockets
+    pub(crate) fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            NetworkStream::Tcp(stream) => stream.peer_addr().ok(),
+            #[cfg(unix)]
+            NetworkStream::Unix(_) => None,
+            NetworkStream::Tls(stream) => stream.get_ref().0.peer_addr().ok(),
+        }
+    }
+}
+
+/// A connection accepted by a listener, before its TLS handshake
+pub(crate) struct AcceptedConnection {
+    stream: NetworkStream,
+    tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+}
+
+impl AcceptedConnection {
+    /// The address of the client, unknown for Unix sockets
+    pub(crate) fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Runs the TLS handshake if the listener uses TLS.
+    ///
+    /// It is not run by [`Listener::accept`], so that connections can be checked before it, and
+    /// so that a slow client does not delay the next connections.
+    pub(crate) async fn handshake(self) -> std::io::Result<NetworkStream> {
+        match (self.stream, self.tls_acceptor) {
+            (NetworkStream::Tcp(stream), Some(acceptor)) => {
+                Ok(NetworkStream::Tls(acceptor.accept(stream).await?))
+            }
+            (stream, _) => Ok(stream),
+        }
+    }
+}
+
 impl Listener {
     pub(crate) async fn new_from_socket_addr(
         address: SocketAddr,
@@ -241,23 +279,20 @@ impl Listener {
         }
     }
 
-    pub(crate) async fn accept(&mut self) -> std::io::Result<NetworkStream> {
-        match self {
-            Listener::Tcp(listener) => listener
-                .accept()
-                .await
-                .map(|(stream, _)| NetworkStream::Tcp(stream)),
+    pub(crate) async fn accept(&mut self) -> std::io::Result<AcceptedConnection> {
+        let (stream, tls_acceptor) = match self {
+            Listener::Tcp(listener) => (NetworkStream::Tcp(listener.accept().await?.0), None),
             #[cfg(unix)]
-            Listener::Unix(listener) => listener
-                .accept()
-                .await
-                .map(|(stream, _)| NetworkStream::Unix(stream)),
-            Listener::Tls { listener, acceptor } => {
-                let (stream, _) = listener.accept().await?;
-
-                Ok(NetworkStream::Tls(acceptor.accept(stream).await?))
-            }
-        }
+            Listener::Unix(listener) => (NetworkStream::Unix(listener.accept().await?.0), None),
+            Listener::Tls { listener, acceptor } => (
+                NetworkStream::Tcp(listener.accept().await?.0),
+                Some(acceptor.clone()),
+            ),
+        };
+        Ok(AcceptedConnection {
+            stream,
+            tls_acceptor,
+        })
     }
 }
 
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::num::NonZeroUsize;
 use std::ops::ControlFlow;
 use std::sync::Arc;
@@ -20,10 +21,30 @@ const DEFAULT_INTROSPECTION_CACHE_CAPACITY: NonZeroUsize =
 pub(crate) enum IntrospectionCache {
     Disabled,
     Enabled {
-        storage: Arc<CacheStorage<String, graphql::Response>>,
+        storage: Arc<CacheStorage<IntrospectionCacheKey, graphql::Response>>,
     },
 }
 
+/// Introspection responses only depend on the API schema and on the selected operation
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct IntrospectionCacheKey {
+    schema_id: Arc<String>,
+    operation_name: Option<String>,
+    query: String,
+}
+
+impl fmt::Display for IntrospectionCacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "introspection:{}:opname:{}:{}",
+            self.schema_id,
+            self.operation_name.as_deref().unwrap_or("-"),
+            self.query
+        )
+    }
+}
+
 impl IntrospectionCache {
     pub(crate) fn new(configuration: &Configuration) -> Self {
         if configuration.supergraph.introspection {
@@ -127,11 +148,14 @@ impl IntrospectionCache {
                 return graphql::Response::builder().error(error).build();
             }
         };
-        let query = key.filtered_query.clone();
         // TODO:  when adding support for variables in introspection queries,
         // variable values should become part of the cache key.
         // https://github.com/apollographql/router/issues/3831
-        let cache_key = query;
+        let cache_key = IntrospectionCacheKey {
+            schema_id: schema.schema_id.clone(),
+            operation_name: key.operation_name.clone(),
+            query: key.filtered_query.clone(),
+        };
         if let Some(response) = storage.get(&cache_key, |_| unreachable!()).await {
             return response;
         }
@@ -5,6 +5,7 @@
 use std::cmp::min;
 use std::fmt;
 
+use bytes::Bytes;
 use num_traits::ToPrimitive;
 use once_cell::sync::Lazy;
 use regex::Captures;
@@ -23,6 +24,29 @@ use crate::spec::TYPENAME;
 /// A JSON object.
 pub(crate) type Object = Map<ByteString, Value>;
 
+/// Parses a JSON value. Its strings reference the bytes instead of being copied.
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn value_from_bytes(bytes: Bytes) -> Result<Value, serde_json::Error> {
+    Value::from_bytes(bytes)
+}
+
+/// Parses a JSON value with simd-json. Its strings are copied, and so are the bytes if they are
+/// shared, as simd-json parses in place.
+#[cfg(feature = "simd-json")]
+pub(crate) fn value_from_bytes(bytes: Bytes) -> Result<Value, serde_json::Error> {
+    simd_from_vec(bytes.into())
+}
+
+/// Deserializes a JSON document with simd-json, which parses it in place in the buffer.
+///
+/// Its errors are returned as serde_json errors, without parsing the document again.
+#[cfg(feature = "simd-json")]
+pub(crate) fn simd_from_vec<T: serde::de::DeserializeOwned>(
+    mut buffer: Vec<u8>,
+) -> Result<T, serde_json::Error> {
+    simd_json::serde::from_slice(&mut buffer).map_err(serde::de::Error::custom)
+}
+
 const FRAGMENT_PREFIX: &str = "... on ";
 
 static TYPE_CONDITIONS_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -1152,6 +1176,29 @@ mod tests {
 
     use super::*;
 
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn simd_json_parses_values_like_serde_json() {
+        for json in [
+            // the last duplicate field wins
+            r#"{"a":1,"b":2,"a":3}"#,
+            r#"{"data":null,"errors":[{"message":"error","path":null}]}"#,
+            // numbers that fit in no integer type are parsed as floats
+            r#"{"big":12345678901234567890123,"max":18446744073709551615,"min":-9223372036854775808,"float":1.5e300,"precise":0.1}"#,
+            r#"{"escaped":"\u00e9\n\"","unicode":"é"}"#,
+        ] {
+            assert_eq!(
+                simd_from_vec::<Value>(json.as_bytes().to_vec()).unwrap(),
+                Value::from_bytes(Bytes::from_static(json.as_bytes())).unwrap(),
+                "{json}"
+            );
+        }
+        for json in ["", "{", r#"{"a":}"#, "[1,]"] {
+            assert!(simd_from_vec::<Value>(json.as_bytes().to_vec()).is_err());
+            assert!(Value::from_bytes(Bytes::from_static(json.as_bytes())).is_err());
+        }
+    }
+
     macro_rules! assert_is_subset {
         ($a:expr, $b:expr $(,)?) => {
             assert!($a.is_subset(&$b));
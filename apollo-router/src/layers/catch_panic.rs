@@ -0,0 +1,323 @@
+//! Catch-unwind boundary.
+//!
+//! A panic in the wrapped service, when it is called or while its response future is polled, is
+//! caught and returned as an [`InternalPanic`] error instead of unwinding through the connection
+//! task or the worker task of a buffered service. A panic while a response body is streamed, with
+//! [`CatchPanicStream`], ends the body with an error.
+//!
+//! The state shared with other requests is not rolled back: a `std::sync::Mutex` locked while
+//! panicking stays poisoned, and the code locking it with `.lock().unwrap()` will then panic for
+//! every request. Those panics are caught too, and counted by the `apollo.router.panics` metric.
+//!
+//! See [`Layer`] and [`Service`] for more details.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::Stream;
+use pin_project_lite::pin_project;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+
+use crate::services::router;
+
+thread_local! {
+    /// Number of catch-unwind boundaries the current thread is running in
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+    /// Id of the request processed by the innermost boundary
+    static REQUEST_ID: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+/// Returns true if a panic on the current thread will be caught by a boundary, so that the
+/// panic hook does not need to terminate the process
+pub(crate) fn is_catching() -> bool {
+    CATCHING.with(|catching| catching.get() > 0)
+}
+
+/// Returns the id of the request processed when the current thread panicked, so that the panic
+/// hook can log it. It is the `requestId` extension of the error sent to the client.
+pub(crate) fn panicking_request_id() -> Option<Arc<str>> {
+    REQUEST_ID.with(|request_id| request_id.borrow().clone())
+}
+
+fn catch<R>(request_id: Option<&Arc<str>>, f: impl FnOnce() -> R) -> Result<R, InternalPanic> {
+    CATCHING.with(|catching| catching.set(catching.get() + 1));
+    let outer_request_id = REQUEST_ID.with(|current| current.replace(request_id.cloned()));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+    REQUEST_ID.with(|current| *current.borrow_mut() = outer_request_id);
+    CATCHING.with(|catching| catching.set(catching.get() - 1));
+    result.map_err(InternalPanic::new)
+}
+
+/// Requests that can be identified in the logs of the panic hook
+pub(crate) trait RequestId {
+    fn request_id(&self) -> Option<Arc<str>>;
+}
+
+impl RequestId for router::Request {
+    fn request_id(&self) -> Option<Arc<str>> {
+        Some(self.context.id.as_str().into())
+    }
+}
+
+/// A panic caught while processing a request
+#[derive(Debug)]
+pub(crate) struct InternalPanic {
+    message: String,
+}
+
+impl InternalPanic {
+    fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+        u64_counter!(
+            "apollo.router.panics",
+            "Number of panics caught while processing requests",
+            1
+        );
+        Self { message }
+    }
+}
+
+impl fmt::Display for InternalPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panic while processing the request: {}", self.message)
+    }
+}
+
+impl std::error::Error for InternalPanic {}
+
+/// [`Layer`] catching the panics of a service
+#[derive(Clone, Default)]
+pub(crate) struct CatchPanicLayer;
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanicService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanicService { inner }
+    }
+}
+
+/// [`Service`] returning an [`InternalPanic`] error when the inner service panics
+#[derive(Clone)]
+pub(crate) struct CatchPanicService<S> {
+    inner: S,
+}
+
+impl<S, Request> Service<Request> for CatchPanicService<S>
+where
+    S: Service<Request>,
+    S::Error: Into<BoxError>,
+    Request: RequestId,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = CatchPanicFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let request_id = req.request_id();
+        match catch(request_id.as_ref(), || self.inner.call(req)) {
+            Ok(inner) => CatchPanicFuture {
+                inner: Some(inner),
+                request_id,
+                panic: None,
+            },
+            Err(panic) => CatchPanicFuture {
+                inner: None,
+                request_id,
+                panic: Some(panic),
+            },
+        }
+    }
+}
+
+pin_project! {
+    /// Response future of [`CatchPanicService`]
+    pub(crate) struct CatchPanicFuture<F> {
+        #[pin]
+        inner: Option<F>,
+        request_id: Option<Arc<str>>,
+        panic: Option<InternalPanic>,
+    }
+}
+
+impl<F, T, E> Future for CatchPanicFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<BoxError>,
+{
+    type Output = Result<T, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        if let Some(panic) = this.panic.take() {
+            return Poll::Ready(Err(panic.into()));
+        }
+        let Some(inner) = this.inner.as_mut().as_pin_mut() else {
+            panic!("CatchPanicFuture polled after completion");
+        };
+        match catch(this.request_id.as_ref(), || inner.poll(cx)) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(result)) => {
+                this.inner.set(None);
+                Poll::Ready(result.map_err(Into::into))
+            }
+            Err(panic) => {
+                // The inner future must not be polled again after a panic
+                this.inner.set(None);
+                Poll::Ready(Err(panic.into()))
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Stream of which panics while polling end the stream with an [`InternalPanic`] error.
+    ///
+    /// It wraps response bodies, which are polled after the response future completed. As the
+    /// response was already sent, the client sees the body being cut short.
+    pub(crate) struct CatchPanicStream<S> {
+        #[pin]
+        inner: Option<S>,
+        request_id: Option<Arc<str>>,
+    }
+}
+
+impl<S> CatchPanicStream<S> {
+    pub(crate) fn new(inner: S, request_id: Option<Arc<str>>) -> Self {
+        Self {
+            inner: Some(inner),
+            request_id,
+        }
+    }
+}
+
+impl<S, T, E> Stream for CatchPanicStream<S>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: Into<BoxError>,
+{
+    type Item = Result<T, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        let Some(inner) = this.inner.as_mut().as_pin_mut() else {
+            return Poll::Ready(None);
+        };
+        match catch(this.request_id.as_ref(), || inner.poll_next(cx)) {
+            Ok(poll) => poll.map(|item| item.map(|item| item.map_err(Into::into))),
+            Err(panic) => {
+                // The inner stream must not be polled again after a panic
+                this.inner.set(None);
+                Poll::Ready(Some(Err(panic.into())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use tower::ServiceBuilder;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::layers::ServiceBuilderExt;
+
+    impl RequestId for &'static str {
+        fn request_id(&self) -> Option<Arc<str>> {
+            Some((*self).into())
+        }
+    }
+
+    impl RequestId for () {
+        fn request_id(&self) -> Option<Arc<str>> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn it_catches_panics_in_calls_and_futures() {
+        let service = ServiceBuilder::new().layer(CatchPanicLayer).service_fn(
+            |request: &'static str| async move {
+                if request == "panic in future" {
+                    panic!("{request}");
+                }
+                Ok::<_, BoxError>(request)
+            },
+        );
+        assert_eq!(service.clone().oneshot("ok").await.unwrap(), "ok");
+        let error = service.oneshot("panic in future").await.unwrap_err();
+        assert!(error.is::<InternalPanic>());
+        assert_eq!(
+            error.to_string(),
+            "panic while processing the request: panic in future"
+        );
+
+        let service = CatchPanicLayer.layer(tower::service_fn(
+            |_: ()| -> futures::future::Ready<Result<(), BoxError>> { panic!("panic in call") },
+        ));
+        let error = service.oneshot(()).await.unwrap_err();
+        assert!(error.is::<InternalPanic>());
+        assert!(!is_catching());
+    }
+
+    #[tokio::test]
+    async fn buffered_workers_survive_panics() {
+        let mut service = ServiceBuilder::new()
+            .buffered()
+            .layer(CatchPanicLayer)
+            .service_fn(|request: &'static str| {
+                if request == "panic" {
+                    panic!("panic in the worker");
+                }
+                futures::future::ready(Ok::<_, BoxError>(request))
+            });
+        let error = (&mut service).oneshot("panic").await.unwrap_err();
+        assert!(error.to_string().contains("panic in the worker"));
+        assert_eq!(service.oneshot("ok").await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn it_catches_panics_in_streams() {
+        let request_id: Arc<str> = "request".into();
+        let stream = futures::stream::iter(["first", "panic", "last"]).map(|item| {
+            if item == "panic" {
+                panic!("panic in {:?}", panicking_request_id());
+            }
+            Ok::<_, BoxError>(item)
+        });
+        let items: Vec<_> = CatchPanicStream::new(stream, Some(request_id))
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &"first");
+        let error = items[1].as_ref().unwrap_err();
+        assert!(error.is::<InternalPanic>());
+        assert_eq!(
+            error.to_string(),
+            r#"panic while processing the request: panic in Some("request")"#
+        );
+        assert!(!is_catching());
+        assert!(panicking_request_id().is_none());
+    }
+}
@@ -23,6 +23,7 @@ use crate::services::supergraph;
 use crate::Context;
 
 pub mod async_checkpoint;
+pub(crate) mod catch_panic;
 pub mod instrument;
 pub mod map_first_graphql_response;
 pub mod map_future_with_request_data;
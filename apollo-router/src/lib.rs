@@ -51,6 +51,8 @@ pub mod plugin;
 pub(crate) mod metrics;
 
 mod ageing_priority_queue;
+#[cfg(feature = "memory-instrumentation")]
+mod allocator;
 mod apollo_studio_interop;
 pub(crate) mod axum_factory;
 mod batching;
@@ -82,6 +84,9 @@ pub mod tracer;
 mod uplink;
 
 pub use crate::axum_factory::unsupported_set_axum_router_callback;
+pub use crate::configuration::secrets::register_secret_resolver;
+pub use crate::configuration::secrets::Secret;
+pub use crate::configuration::secrets::SecretResolver;
 pub use crate::configuration::Configuration;
 pub use crate::configuration::ListenAddr;
 pub use crate::context::extensions::sync::ExtensionsMutex;
@@ -114,4 +119,5 @@ pub mod _private {
     pub use crate::plugin::PLUGINS;
     // For tests
     pub use crate::router_factory::create_test_service_factory_from_yaml;
+    pub use crate::router_factory::measure_schema_reload;
 }
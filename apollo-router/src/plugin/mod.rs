@@ -39,10 +39,13 @@ use schemars::JsonSchema;
 use tower::buffer::future::ResponseFuture;
 use tower::buffer::Buffer;
 use tower::BoxError;
+use tower::Layer;
 use tower::Service;
 use tower::ServiceBuilder;
 
+use crate::configuration::ConfigurationError;
 use crate::graphql;
+use crate::layers::catch_panic::CatchPanicLayer;
 use crate::layers::ServiceBuilderExt;
 use crate::notification::Notify;
 use crate::query_planner::fetch::SubgraphSchemas;
@@ -81,6 +84,10 @@ pub struct PluginInit<T> {
     pub(crate) launch_id: Option<Arc<String>>,
 
     pub(crate) notify: Notify<String, graphql::Response>,
+
+    /// The whole router configuration, for plugins that depend on the configuration of other
+    /// plugins
+    pub(crate) full_config: Option<serde_json::Value>,
 }
 
 impl<T> PluginInit<T>
@@ -179,6 +186,7 @@ where
         subgraph_schemas: Option<Arc<SubgraphSchemas>>,
         launch_id: Option<Option<Arc<String>>>,
         notify: Notify<String, graphql::Response>,
+        full_config: Option<serde_json::Value>,
     ) -> Self {
         PluginInit {
             config,
@@ -188,6 +196,7 @@ where
             subgraph_schemas: subgraph_schemas.unwrap_or_default(),
             launch_id: launch_id.flatten(),
             notify,
+            full_config,
         }
     }
 
@@ -204,6 +213,7 @@ where
         subgraph_schemas: Option<Arc<SubgraphSchemas>>,
         launch_id: Option<Arc<String>>,
         notify: Notify<String, graphql::Response>,
+        full_config: Option<serde_json::Value>,
     ) -> Result<Self, BoxError> {
         let config: T = serde_json::from_value(config)?;
         Ok(PluginInit {
@@ -214,6 +224,7 @@ where
             subgraph_schemas: subgraph_schemas.unwrap_or_default(),
             launch_id,
             notify,
+            full_config,
         })
     }
 
@@ -227,6 +238,7 @@ where
         subgraph_schemas: Option<Arc<SubgraphSchemas>>,
         launch_id: Option<Arc<String>>,
         notify: Option<Notify<String, graphql::Response>>,
+        full_config: Option<serde_json::Value>,
     ) -> Self {
         PluginInit {
             config,
@@ -237,6 +249,7 @@ where
             subgraph_schemas: subgraph_schemas.unwrap_or_default(),
             launch_id,
             notify: notify.unwrap_or_else(Notify::for_tests),
+            full_config,
         }
     }
 }
@@ -254,6 +267,7 @@ impl PluginInit<serde_json::Value> {
             .supergraph_sdl(self.supergraph_sdl)
             .subgraph_schemas(self.subgraph_schemas)
             .notify(self.notify.clone())
+            .and_full_config(self.full_config)
             .build()
     }
 }
@@ -357,6 +371,21 @@ pub(crate) fn plugins() -> impl Iterator<Item = &'static Lazy<PluginFactory>> {
     PLUGINS.iter()
 }
 
+/// Find the plugin factory registered under a name.
+///
+/// Plugins register themselves at link time so nothing prevents two of them from claiming the
+/// same name, in which case we cannot tell which one the configuration refers to.
+pub(crate) fn plugin_factory(
+    name: &str,
+) -> Result<Option<&'static PluginFactory>, ConfigurationError> {
+    let mut factories = plugins().filter(|factory| factory.name == name);
+    match (factories.next(), factories.next()) {
+        (Some(factory), None) => Ok(Some(&**factory)),
+        (None, _) => Ok(None),
+        (Some(_), Some(_)) => Err(ConfigurationError::PluginDuplicate(name.to_string())),
+    }
+}
+
 /// All router plugins must implement the Plugin trait.
 ///
 /// This trait defines lifecycle hooks that enable hooking into Apollo Router services.
@@ -428,6 +457,28 @@ pub trait Plugin: Send + Sync + 'static {
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
         MultiMap::new()
     }
+
+    /// This is invoked once every plugin of the router has been created, before the router starts
+    /// serving traffic. Use it to open connections or fetch remote resources the plugin needs.
+    ///
+    /// Plugins are started one at a time, in the order in which they are activated, so a plugin
+    /// can rely on every plugin that precedes it having already started. The `config` parameter
+    /// is the raw configuration the plugin was created from.
+    ///
+    /// If startup fails, the router is not created and the plugins that already started are
+    /// shut down in reverse order.
+    async fn startup(&mut self, _config: &serde_json::Value) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    /// This is invoked once when a started plugin is discarded: either before going live, because
+    /// another plugin of the same router failed to start or be configured, or after the router it
+    /// belongs to was replaced by a reload or stopped, once the last request it was handling
+    /// completed.
+    ///
+    /// Before going live, plugins are shut down in the reverse order of startup. Plugins of a
+    /// discarded router are shut down concurrently, in a background task.
+    async fn shutdown(&mut self) {}
 }
 
 /// Plugin trait for unstable features
@@ -503,6 +554,28 @@ pub trait PluginUnstable: Send + Sync + 'static {
         MultiMap::new()
     }
 
+    /// This is invoked once every plugin of the router has been created, before the router starts
+    /// serving traffic. Use it to open connections or fetch remote resources the plugin needs.
+    ///
+    /// Plugins are started one at a time, in the order in which they are activated, so a plugin
+    /// can rely on every plugin that precedes it having already started. The `config` parameter
+    /// is the raw configuration the plugin was created from.
+    ///
+    /// If startup fails, the router is not created and the plugins that already started are
+    /// shut down in reverse order.
+    async fn startup(&mut self, _config: &serde_json::Value) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    /// This is invoked once when a started plugin is discarded: either before going live, because
+    /// another plugin of the same router failed to start or be configured, or after the router it
+    /// belongs to was replaced by a reload or stopped, once the last request it was handling
+    /// completed.
+    ///
+    /// Before going live, plugins are shut down in the reverse order of startup. Plugins of a
+    /// discarded router are shut down concurrently, in a background task.
+    async fn shutdown(&mut self) {}
+
     /// test
     fn unstable_method(&self);
 }
@@ -553,6 +626,14 @@ where
         Plugin::web_endpoints(self)
     }
 
+    async fn startup(&mut self, config: &serde_json::Value) -> Result<(), BoxError> {
+        Plugin::startup(self, config).await
+    }
+
+    async fn shutdown(&mut self) {
+        Plugin::shutdown(self).await
+    }
+
     fn unstable_method(&self) {
         todo!()
     }
@@ -641,6 +722,28 @@ pub(crate) trait PluginPrivate: Send + Sync + 'static {
         MultiMap::new()
     }
 
+    /// This is invoked once every plugin of the router has been created, before the router starts
+    /// serving traffic. Use it to open connections or fetch remote resources the plugin needs.
+    ///
+    /// Plugins are started one at a time, in the order in which they are activated, so a plugin
+    /// can rely on every plugin that precedes it having already started. The `config` parameter
+    /// is the raw configuration the plugin was created from.
+    ///
+    /// If startup fails, the router is not created and the plugins that already started are
+    /// shut down in reverse order.
+    async fn startup(&mut self, _config: &serde_json::Value) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    /// This is invoked once when a started plugin is discarded: either before going live, because
+    /// another plugin of the same router failed to start or be configured, or after the router it
+    /// belongs to was replaced by a reload or stopped, once the last request it was handling
+    /// completed.
+    ///
+    /// Before going live, plugins are shut down in the reverse order of startup. Plugins of a
+    /// discarded router are shut down concurrently, in a background task.
+    async fn shutdown(&mut self) {}
+
     /// The point of no return this plugin is about to go live
     fn activate(&self) {}
 }
@@ -691,6 +794,14 @@ where
         PluginUnstable::web_endpoints(self)
     }
 
+    async fn startup(&mut self, config: &serde_json::Value) -> Result<(), BoxError> {
+        PluginUnstable::startup(self, config).await
+    }
+
+    async fn shutdown(&mut self) {
+        PluginUnstable::shutdown(self).await
+    }
+
     fn activate(&self) {}
 }
 
@@ -751,6 +862,12 @@ pub(crate) trait DynPlugin: Send + Sync + 'static {
 
     /// The point of no return, this plugin is about to go live
     fn activate(&self) {}
+
+    /// Start the plugin before the router it belongs to serves traffic.
+    async fn startup(&mut self, config: &serde_json::Value) -> Result<(), BoxError>;
+
+    /// Shut down a started plugin that is discarded.
+    async fn shutdown(&mut self);
 }
 
 #[async_trait]
@@ -805,6 +922,14 @@ where
     fn activate(&self) {
         self.activate()
     }
+
+    async fn startup(&mut self, config: &serde_json::Value) -> Result<(), BoxError> {
+        PluginPrivate::startup(self, config).await
+    }
+
+    async fn shutdown(&mut self) {
+        PluginPrivate::shutdown(self).await
+    }
 }
 
 impl<T> From<T> for Box<dyn DynPlugin>
@@ -896,7 +1021,9 @@ pub(crate) struct Handler {
 impl Handler {
     pub(crate) fn new(service: router::BoxService) -> Self {
         Self {
-            service: ServiceBuilder::new().buffered().service(service),
+            service: ServiceBuilder::new()
+                .buffered()
+                .service(router::BoxService::new(CatchPanicLayer.layer(service))),
         }
     }
 }
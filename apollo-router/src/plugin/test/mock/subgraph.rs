@@ -5,17 +5,21 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::Duration;
 
 use apollo_compiler::ast::Definition;
 use apollo_compiler::ast::Document;
-use futures::future;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use http::HeaderMap;
 use http::HeaderName;
 use http::HeaderValue;
 use http::StatusCode;
+use parking_lot::Mutex;
 use tower::BoxError;
 use tower::Service;
 
+use crate::error::FetchError;
 use crate::graphql;
 use crate::graphql::Request;
 use crate::graphql::Response;
@@ -25,16 +29,21 @@ use crate::services::SubgraphRequest;
 use crate::services::SubgraphResponse;
 
 type MockResponses = HashMap<Request, Response>;
+type MockFailures = HashMap<Request, String>;
 
 #[derive(Default, Clone)]
 pub struct MockSubgraph {
     // using an arc to improve efficiency when service is cloned
     mocks: Arc<MockResponses>,
+    failures: Arc<MockFailures>,
     extensions: Option<Object>,
     subscription_stream: Option<Handle<String, graphql::Response>>,
     map_request_fn:
         Option<Arc<dyn (Fn(SubgraphRequest) -> SubgraphRequest) + Send + Sync + 'static>>,
     headers: HeaderMap,
+    delay: Option<Duration>,
+    // shared by the clones of the mock, so that tests can keep one to inspect the calls
+    received_requests: Arc<Mutex<Vec<Request>>>,
 }
 
 impl MockSubgraph {
@@ -49,10 +58,7 @@ impl MockSubgraph {
                     })
                     .collect(),
             ),
-            extensions: None,
-            subscription_stream: None,
-            map_request_fn: None,
-            headers: HeaderMap::new(),
+            ..Default::default()
         }
     }
 
@@ -81,15 +87,27 @@ impl MockSubgraph {
         self.map_request_fn = Some(Arc::new(map_request_fn));
         self
     }
+
+    /// The requests received by this subgraph and its clones, in the order they were received
+    pub fn received_requests(&self) -> Vec<Request> {
+        self.received_requests.lock().clone()
+    }
+
+    /// The number of requests received by this subgraph and its clones
+    pub fn call_count(&self) -> usize {
+        self.received_requests.lock().len()
+    }
 }
 
 /// Builder for `MockSubgraph`
 #[derive(Default, Clone)]
 pub struct MockSubgraphBuilder {
     mocks: MockResponses,
+    failures: MockFailures,
     extensions: Option<Object>,
     subscription_stream: Option<Handle<String, graphql::Response>>,
     headers: HeaderMap,
+    delay: Option<Duration>,
 }
 impl MockSubgraphBuilder {
     pub fn with_extensions(mut self, extensions: Object) -> Self {
@@ -108,6 +126,22 @@ impl MockSubgraphBuilder {
         self
     }
 
+    /// makes a request fail as if the subgraph could not be reached
+    ///
+    /// the request must deserialize to `crate::graphql::Request`
+    pub fn with_failure(mut self, request: serde_json::Value, reason: &str) -> Self {
+        let mut request = serde_json::from_value(request).unwrap();
+        normalize(&mut request);
+        self.failures.insert(request, reason.to_string());
+        self
+    }
+
+    /// delays every response, including failures and missing mocks
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
     pub fn with_subscription_stream(
         mut self,
         subscription_stream: Handle<String, graphql::Response>,
@@ -124,10 +158,13 @@ impl MockSubgraphBuilder {
     pub fn build(self) -> MockSubgraph {
         MockSubgraph {
             mocks: Arc::new(self.mocks),
+            failures: Arc::new(self.failures),
             extensions: self.extensions,
             subscription_stream: self.subscription_stream,
             map_request_fn: None,
             headers: self.headers,
+            delay: self.delay,
+            received_requests: Default::default(),
         }
     }
 }
@@ -153,7 +190,7 @@ impl Service<SubgraphRequest> for MockSubgraph {
 
     type Error = BoxError;
 
-    type Future = future::Ready<Result<Self::Response, Self::Error>>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
@@ -198,9 +235,17 @@ impl Service<SubgraphRequest> for MockSubgraph {
             }
         }
 
+        self.received_requests.lock().push(body.clone());
         normalize(body);
 
-        let response = if let Some(response) = self.mocks.get(body) {
+        let response = if let Some(reason) = self.failures.get(body) {
+            Err(FetchError::SubrequestHttpError {
+                status_code: None,
+                service: req.subgraph_name.unwrap_or_default(),
+                reason: reason.clone(),
+            }
+            .into())
+        } else if let Some(response) = self.mocks.get(body) {
             // Build an http Response
             let mut http_response_builder = http::Response::builder().status(StatusCode::OK);
             if let Some(headers) = http_response_builder.headers_mut() {
@@ -209,12 +254,12 @@ impl Service<SubgraphRequest> for MockSubgraph {
             let http_response = http_response_builder
                 .body(response.clone())
                 .expect("Response is serializable; qed");
-            SubgraphResponse::new_from_response(
+            Ok(SubgraphResponse::new_from_response(
                 http_response,
                 req.context,
                 "test".to_string(),
                 req.id,
-            )
+            ))
         } else {
             let error = crate::error::Error::builder()
                 .message(format!(
@@ -224,12 +269,19 @@ impl Service<SubgraphRequest> for MockSubgraph {
                 .extension_code("FETCH_ERROR".to_string())
                 .extensions(self.extensions.clone().unwrap_or_default())
                 .build();
-            SubgraphResponse::fake_builder()
+            Ok(SubgraphResponse::fake_builder()
                 .error(error)
                 .context(req.context)
                 .id(req.id)
-                .build()
+                .build())
         };
-        future::ok(response)
+        let delay = self.delay;
+        async move {
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
+            }
+            response
+        }
+        .boxed()
     }
 }
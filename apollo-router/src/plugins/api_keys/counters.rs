@@ -0,0 +1,100 @@
+//! Request counters of the API keys
+//!
+//! Counters are grouped in fixed windows: a rate limit window lasts for the interval of the rate
+//! limit, and a quota window is a UTC day. They are stored in Redis when it is configured, so that
+//! all the router instances share them, and in the memory of each router instance otherwise.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tower::BoxError;
+
+use crate::cache::redis::RedisCacheStorage;
+use crate::cache::redis::RedisKey;
+
+/// A fixed time window, relative to the UNIX epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Window {
+    index: u128,
+    length: Duration,
+}
+
+impl Window {
+    pub(crate) const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// The window of `length` containing `now`, the time elapsed since the UNIX epoch
+    pub(crate) fn current(length: Duration, now: Duration) -> Self {
+        Self {
+            index: now.as_millis() / length.as_millis().max(1),
+            length,
+        }
+    }
+
+    /// The time left until the end of the window
+    pub(crate) fn remaining(&self, now: Duration) -> Duration {
+        let end = (self.index + 1) * self.length.as_millis().max(1);
+        Duration::from_millis(end.saturating_sub(now.as_millis()) as u64)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum Counters {
+    Memory(Arc<Mutex<HashMap<String, (Window, u64)>>>),
+    Redis(RedisCacheStorage),
+}
+
+impl Counters {
+    pub(crate) fn in_memory() -> Self {
+        Counters::Memory(Default::default())
+    }
+
+    /// Counts a request in the current window of a counter, and returns the number of requests
+    /// counted in that window
+    pub(crate) async fn increment(&self, name: &str, window: Window) -> Result<u64, BoxError> {
+        match self {
+            Counters::Memory(counters) => {
+                let mut counters = counters.lock().expect("lock poisoned");
+                let (counter_window, count) =
+                    counters.entry(name.to_string()).or_insert((window, 0));
+                if *counter_window != window {
+                    *counter_window = window;
+                    *count = 0;
+                }
+                *count += 1;
+                Ok(*count)
+            }
+            Counters::Redis(storage) => Ok(storage
+                .increment(
+                    RedisKey(format!("api_key:{name}:{}", window.index)),
+                    window.length,
+                )
+                .await?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn counters_are_reset_in_each_window() {
+        let counters = Counters::in_memory();
+        let second = Duration::from_secs(1);
+        let first = Window::current(second, Duration::from_millis(1200));
+        assert_eq!(
+            first.remaining(Duration::from_millis(1200)),
+            Duration::from_millis(800)
+        );
+
+        assert_eq!(counters.increment("a", first).await.unwrap(), 1);
+        assert_eq!(counters.increment("a", first).await.unwrap(), 2);
+        assert_eq!(counters.increment("b", first).await.unwrap(), 1);
+
+        let next = Window::current(second, Duration::from_millis(2000));
+        assert_ne!(first, next);
+        assert_eq!(counters.increment("a", next).await.unwrap(), 1);
+    }
+}
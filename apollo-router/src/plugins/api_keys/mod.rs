@@ -0,0 +1,641 @@
+//! API key authentication
+//!
+//! Requests are authenticated with an API key sent in a header. Each key identifies a client,
+//! whose name and version are used by telemetry and usage reporting, and can have its own rate
+//! limit and daily quota. Keys are listed in the configuration, or in a file that is reloaded
+//! when it changes.
+
+mod counters;
+
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use arc_swap::ArcSwap;
+use futures::FutureExt;
+use futures::StreamExt;
+use http::header;
+use http::HeaderName;
+use http::HeaderValue;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::json;
+use sha2::Digest;
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use self::counters::Counters;
+use self::counters::Window;
+use crate::cache::redis::RedisCacheStorage;
+use crate::configuration::RedisCache;
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::authentication::constant_time_eq;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::plugins::telemetry::CLIENT_VERSION;
+use crate::register_plugin;
+use crate::services::router;
+use crate::services::APPLICATION_JSON_HEADER_VALUE;
+use crate::Context;
+
+/// API key authentication configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ApiKeysConfig {
+    /// Set to true to reject the requests without a valid API key
+    #[serde(default)]
+    enabled: bool,
+
+    /// The header the API key is read from (default: x-api-key)
+    #[serde(default = "default_header_name")]
+    header_name: String,
+
+    /// The API keys
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+
+    /// Path to a YAML file listing more API keys under a `keys` entry. The file is reloaded when
+    /// it changes
+    keys_path: Option<PathBuf>,
+
+    /// Redis instance the rate limits and quotas are tracked in, to share them between router
+    /// instances. Without it, each router instance tracks them in memory
+    redis: Option<RedisCache>,
+}
+
+fn default_header_name() -> String {
+    "x-api-key".to_string()
+}
+
+/// An API key and the client it identifies
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ApiKey {
+    /// The API key
+    key: String,
+
+    /// The name of the client, used by telemetry and usage reporting
+    client_name: String,
+
+    /// The version of the client
+    client_version: Option<String>,
+
+    /// Maximum number of requests with this key per interval
+    rate_limit: Option<ApiKeyRateLimit>,
+
+    /// Maximum number of requests with this key per UTC day
+    daily_quota: Option<NonZeroU64>,
+}
+
+/// Rate limit of an API key
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ApiKeyRateLimit {
+    /// Number of requests allowed
+    capacity: NonZeroU64,
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// Per interval
+    interval: Duration,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile {
+    keys: Vec<ApiKey>,
+}
+
+/// API keys indexed by the SHA-256 digest of the key, so that looking them up does not take a time
+/// that depends on the key
+type KeyMap = HashMap<[u8; 32], Arc<ApiKey>>;
+
+fn key_digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+struct ApiKeys {
+    authenticator: Option<Arc<Authenticator>>,
+    _drop_signal: Option<oneshot::Sender<()>>,
+}
+
+struct Authenticator {
+    header_name: HeaderName,
+    keys: ArcSwap<KeyMap>,
+    counters: Counters,
+}
+
+#[derive(Debug, Error)]
+enum Rejection {
+    #[error("missing API key in the '{0}' header")]
+    MissingKey(HeaderName),
+    #[error("invalid API key")]
+    InvalidKey,
+    #[error("rate limit exceeded for client '{client_name}'")]
+    RateLimited {
+        client_name: String,
+        limit: u64,
+        retry_after: Duration,
+    },
+    #[error("daily quota exceeded for client '{client_name}'")]
+    QuotaExceeded {
+        client_name: String,
+        limit: u64,
+        retry_after: Duration,
+    },
+}
+
+#[async_trait::async_trait]
+impl Plugin for ApiKeys {
+    type Config = ApiKeysConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let config = init.config;
+        if !config.enabled {
+            return Ok(ApiKeys {
+                authenticator: None,
+                _drop_signal: None,
+            });
+        }
+
+        let header_name = HeaderName::from_str(&config.header_name)?;
+        let file_keys = match &config.keys_path {
+            Some(path) => read_keys_file(path).await?,
+            None => Vec::new(),
+        };
+        let keys = key_map(&config.keys, file_keys)?;
+
+        let counters = match config.redis {
+            Some(redis) => {
+                let required_to_start = redis.required_to_start;
+                match RedisCacheStorage::new(redis).await {
+                    Ok(storage) => Counters::Redis(storage),
+                    Err(e) => {
+                        tracing::error!(
+                            e,
+                            "could not open connection to Redis for API key quotas, tracking them in memory"
+                        );
+                        if required_to_start {
+                            return Err(e);
+                        }
+                        Counters::in_memory()
+                    }
+                }
+            }
+            None => Counters::in_memory(),
+        };
+
+        let authenticator = Arc::new(Authenticator {
+            header_name,
+            keys: ArcSwap::from_pointee(keys),
+            counters,
+        });
+
+        let drop_signal = config.keys_path.map(|path| {
+            let (drop_signal, drop_receiver) = oneshot::channel();
+            tokio::task::spawn(watch_keys_file(
+                path,
+                config.keys,
+                authenticator.clone(),
+                drop_receiver,
+            ));
+            drop_signal
+        });
+
+        Ok(ApiKeys {
+            authenticator: Some(authenticator),
+            _drop_signal: drop_signal,
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        let Some(authenticator) = self.authenticator.clone() else {
+            return service;
+        };
+
+        ServiceBuilder::new()
+            .oneshot_checkpoint_async(move |request: router::Request| {
+                let authenticator = authenticator.clone();
+                async move {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default();
+                    Ok(authenticator.authenticate(request, now).await)
+                }
+                .boxed()
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+impl Authenticator {
+    async fn authenticate(
+        &self,
+        request: router::Request,
+        now: Duration,
+    ) -> ControlFlow<router::Response, router::Request> {
+        let api_key = match request.router_request.headers().get(&self.header_name) {
+            None => {
+                return rejection_response(
+                    request.context,
+                    Rejection::MissingKey(self.header_name.clone()),
+                )
+            }
+            Some(key) => match key.to_str().ok().and_then(|key| self.find(key)) {
+                Some(api_key) => api_key,
+                None => return rejection_response(request.context, Rejection::InvalidKey),
+            },
+        };
+
+        let _ = request
+            .context
+            .insert(CLIENT_NAME, api_key.client_name.clone());
+        if let Some(client_version) = &api_key.client_version {
+            let _ = request
+                .context
+                .insert(CLIENT_VERSION, client_version.clone());
+        }
+
+        // Counters are named after a hash of the key, so that keys are not stored in Redis
+        let counter = format!("{:x}", Sha256::digest(api_key.key.as_bytes()));
+        if let Some(rate_limit) = &api_key.rate_limit {
+            let window = Window::current(rate_limit.interval, now);
+            let limit = rate_limit.capacity.get();
+            if self.count(&format!("rate:{counter}"), window).await > limit {
+                return rejection_response(
+                    request.context,
+                    Rejection::RateLimited {
+                        client_name: api_key.client_name.clone(),
+                        limit,
+                        retry_after: window.remaining(now),
+                    },
+                );
+            }
+        }
+        if let Some(daily_quota) = api_key.daily_quota {
+            let window = Window::current(Window::DAY, now);
+            let limit = daily_quota.get();
+            if self.count(&format!("quota:{counter}"), window).await > limit {
+                return rejection_response(
+                    request.context,
+                    Rejection::QuotaExceeded {
+                        client_name: api_key.client_name.clone(),
+                        limit,
+                        retry_after: window.remaining(now),
+                    },
+                );
+            }
+        }
+
+        ControlFlow::Continue(request)
+    }
+
+    /// Finds an API key, comparing it in constant time
+    fn find(&self, key: &str) -> Option<Arc<ApiKey>> {
+        self.keys
+            .load()
+            .get(&key_digest(key))
+            .filter(|api_key| constant_time_eq(api_key.key.as_bytes(), key.as_bytes()))
+            .cloned()
+    }
+
+    /// Counts the request in a counter. Requests are let through if the counter is unavailable
+    async fn count(&self, name: &str, window: Window) -> u64 {
+        self.counters
+            .increment(name, window)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!(error = %e, "could not count the request of an API key");
+                0
+            })
+    }
+}
+
+impl Rejection {
+    fn reason(&self) -> &'static str {
+        match self {
+            Rejection::MissingKey(_) => "missing_key",
+            Rejection::InvalidKey => "invalid_key",
+            Rejection::RateLimited { .. } => "rate_limit",
+            Rejection::QuotaExceeded { .. } => "quota",
+        }
+    }
+}
+
+fn rejection_response(
+    context: Context,
+    rejection: Rejection,
+) -> ControlFlow<router::Response, router::Request> {
+    u64_counter!(
+        "apollo.router.operations.authentication.api_key.rejected",
+        "Number of requests rejected by the API key authentication",
+        1,
+        reason = rejection.reason()
+    );
+    tracing::info!(message = %rejection, "api key authentication failure");
+
+    let (error, status_code, retry_after) = match &rejection {
+        Rejection::MissingKey(_) | Rejection::InvalidKey => (
+            graphql::Error::builder()
+                .message(rejection.to_string())
+                .extension_code("INVALID_API_KEY")
+                .build(),
+            StatusCode::UNAUTHORIZED,
+            None,
+        ),
+        Rejection::RateLimited {
+            client_name,
+            limit,
+            retry_after,
+        }
+        | Rejection::QuotaExceeded {
+            client_name,
+            limit,
+            retry_after,
+        } => {
+            let code = if matches!(rejection, Rejection::RateLimited { .. }) {
+                "API_KEY_RATE_LIMITED"
+            } else {
+                "API_KEY_QUOTA_EXCEEDED"
+            };
+            // Rounded up, so that clients do not retry before the end of the window
+            let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            (
+                graphql::Error::builder()
+                    .message(rejection.to_string())
+                    .extension_code(code)
+                    .extension("clientName", client_name.clone())
+                    .extension("limit", json!(limit))
+                    .extension("retryAfter", json!(retry_after))
+                    .build(),
+                StatusCode::TOO_MANY_REQUESTS,
+                Some(retry_after),
+            )
+        }
+    };
+    let mut response = router::Response::infallible_builder()
+        .error(error)
+        .status_code(status_code)
+        .header(header::CONTENT_TYPE, APPLICATION_JSON_HEADER_VALUE.clone())
+        .context(context)
+        .build();
+    if let Some(retry_after) = retry_after {
+        response
+            .response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    }
+    ControlFlow::Break(response)
+}
+
+async fn read_keys_file(path: &Path) -> Result<Vec<ApiKey>, BoxError> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("could not read the API keys file '{}': {e}", path.display()))?;
+    let file: ApiKeysFile = serde_yaml::from_str(&contents).map_err(|e| {
+        format!(
+            "could not parse the API keys file '{}': {e}",
+            path.display()
+        )
+    })?;
+    Ok(file.keys)
+}
+
+fn key_map(config_keys: &[ApiKey], file_keys: Vec<ApiKey>) -> Result<KeyMap, BoxError> {
+    let mut keys = KeyMap::new();
+    for api_key in config_keys.iter().cloned().chain(file_keys) {
+        if api_key.key.is_empty() {
+            return Err(format!(
+                "the API key of client '{}' cannot be empty",
+                api_key.client_name
+            )
+            .into());
+        }
+        let client_name = api_key.client_name.clone();
+        if keys
+            .insert(key_digest(&api_key.key), Arc::new(api_key))
+            .is_some()
+        {
+            return Err(
+                format!("an API key of client '{client_name}' is used more than once").into(),
+            );
+        }
+    }
+    Ok(keys)
+}
+
+async fn watch_keys_file(
+    path: PathBuf,
+    config_keys: Vec<ApiKey>,
+    authenticator: Arc<Authenticator>,
+    mut drop_receiver: oneshot::Receiver<()>,
+) {
+    let mut changes = crate::files::watch(&path).boxed();
+    loop {
+        tokio::select! {
+            // the plugin was dropped, we must shut down the task
+            _ = &mut drop_receiver => return,
+            change = changes.next() => {
+                if change.is_none() {
+                    return;
+                }
+                let keys = read_keys_file(&path)
+                    .await
+                    .and_then(|file_keys| key_map(&config_keys, file_keys));
+                match keys {
+                    Ok(keys) => {
+                        authenticator.keys.store(Arc::new(keys));
+                        tracing::info!("reloaded the API keys from '{}'", path.display());
+                    }
+                    Err(e) => tracing::error!(
+                        "could not reload the API keys, keeping the previous ones: {e}"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+register_plugin!("apollo", "api_keys", ApiKeys);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::Service;
+
+    use super::*;
+    use crate::files::tests::create_temp_file;
+    use crate::files::tests::write_and_flush;
+    use crate::plugin::test::MockRouterService;
+    use crate::services::router::body::get_body_bytes;
+
+    async fn api_keys(config: serde_json::Value) -> ApiKeys {
+        ApiKeys::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Default::default(),
+        ))
+        .await
+        .unwrap()
+    }
+
+    fn request(api_key: Option<&str>) -> router::Request {
+        let mut builder = router::Request::fake_builder();
+        if let Some(api_key) = api_key {
+            builder = builder.header("x-api-key", api_key);
+        }
+        builder.build().unwrap()
+    }
+
+    async fn error(response: router::Response) -> serde_json::Value {
+        let body = get_body_bytes(response.response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        body["errors"][0].clone()
+    }
+
+    #[tokio::test]
+    async fn it_identifies_clients_by_api_key() {
+        let mut mock = MockRouterService::new();
+        mock.expect_call().times(1).returning(|req| {
+            assert_eq!(
+                req.context.get::<_, String>(CLIENT_NAME).unwrap().unwrap(),
+                "mobile"
+            );
+            assert_eq!(
+                req.context
+                    .get::<_, String>(CLIENT_VERSION)
+                    .unwrap()
+                    .unwrap(),
+                "2.1"
+            );
+            Ok(router::Response::fake_builder()
+                .context(req.context)
+                .build()
+                .unwrap())
+        });
+        let mut service = api_keys(json!({
+            "enabled": true,
+            "keys": [{ "key": "abc", "client_name": "mobile", "client_version": "2.1" }]
+        }))
+        .await
+        .router_service(mock.boxed());
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request(Some("abc")))
+            .await
+            .unwrap();
+        assert_eq!(response.response.status(), StatusCode::OK);
+
+        for api_key in [None, Some("unknown")] {
+            let response = service
+                .ready()
+                .await
+                .unwrap()
+                .call(request(api_key))
+                .await
+                .unwrap();
+            assert_eq!(response.response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                error(response).await["extensions"]["code"],
+                "INVALID_API_KEY"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_rejects_clients_over_their_limits() {
+        let plugin = api_keys(json!({
+            "enabled": true,
+            "keys": [
+                {
+                    "key": "abc",
+                    "client_name": "mobile",
+                    "rate_limit": { "capacity": 2, "interval": "1s" }
+                },
+                { "key": "def", "client_name": "web", "daily_quota": 1 }
+            ]
+        }))
+        .await;
+        let authenticator = plugin.authenticator.unwrap();
+        let now = Duration::from_millis(10_500);
+
+        for _ in 0..2 {
+            assert!(authenticator
+                .authenticate(request(Some("abc")), now)
+                .await
+                .is_continue());
+        }
+        let ControlFlow::Break(response) =
+            authenticator.authenticate(request(Some("abc")), now).await
+        else {
+            panic!("the request must be rate limited");
+        };
+        assert_eq!(response.response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.response.headers()[header::RETRY_AFTER], "1");
+        let error = error(response).await;
+        assert_eq!(error["extensions"]["code"], "API_KEY_RATE_LIMITED");
+        assert_eq!(error["extensions"]["clientName"], "mobile");
+        assert_eq!(error["extensions"]["limit"], 2);
+        // the next window starts with a new count
+        assert!(authenticator
+            .authenticate(request(Some("abc")), now + Duration::from_secs(1))
+            .await
+            .is_continue());
+
+        assert!(authenticator
+            .authenticate(request(Some("def")), now)
+            .await
+            .is_continue());
+        let ControlFlow::Break(response) =
+            authenticator.authenticate(request(Some("def")), now).await
+        else {
+            panic!("the request must be over quota");
+        };
+        assert_eq!(response.response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let error = error(response).await;
+        assert_eq!(error["extensions"]["code"], "API_KEY_QUOTA_EXCEEDED");
+        assert_eq!(error["extensions"]["retryAfter"], 86_390);
+    }
+
+    #[tokio::test]
+    async fn it_reloads_the_keys_file() {
+        let (path, mut file) = create_temp_file();
+        write_and_flush(&mut file, "keys:\n  - key: abc\n    client_name: mobile\n").await;
+        let plugin = api_keys(json!({ "enabled": true, "keys_path": path })).await;
+        let authenticator = plugin.authenticator.clone().unwrap();
+        assert!(authenticator.find("abc").is_some());
+
+        write_and_flush(&mut file, "keys:\n  - key: def\n    client_name: web\n").await;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while authenticator.find("def").is_none() {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("the keys file must be reloaded");
+        assert!(authenticator.find("abc").is_none());
+    }
+
+    #[test]
+    fn it_rejects_duplicate_keys() {
+        let key: ApiKey =
+            serde_json::from_value(json!({ "key": "abc", "client_name": "mobile" })).unwrap();
+        assert!(key_map(&[key.clone()], vec![key]).is_err());
+    }
+}
@@ -0,0 +1,617 @@
+//! Audit logging
+//!
+//! Emits one structured JSON event per client request, once its response is complete or was
+//! abandoned, to a configurable sink. Events gather what every stage of the pipeline learned about the request:
+//! the operation, the client, the authorization decisions, the subgraphs contacted and the
+//! outcome of the response.
+//!
+//! Events never contain variable values. The operation is identified by its usage reporting
+//! signature, whose literals are removed with the `telemetry.apollo.redaction` options, like in
+//! the reports sent to Apollo.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::stream::once;
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt as TowerServiceExt;
+
+use self::sink::AuditSink;
+use crate::apollo_studio_interop::UsageReporting;
+use crate::context::OPERATION_KIND;
+use crate::context::OPERATION_NAME;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::plugins::authorization::AUTHENTICATED_KEY;
+use crate::plugins::authorization::REQUIRED_POLICIES_KEY;
+use crate::plugins::authorization::REQUIRED_SCOPES_KEY;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::plugins::telemetry::CLIENT_VERSION;
+use crate::register_plugin;
+use crate::services::execution;
+use crate::services::router;
+use crate::services::router::body::RouterBody;
+use crate::services::subgraph;
+use crate::services::supergraph;
+use crate::Context;
+
+mod sink;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Audit logging configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct AuditConfig {
+    /// Audit logging is disabled by default
+    enabled: bool,
+    /// Where the audit events are written (default: stdout)
+    #[serde(default)]
+    sink: AuditSink,
+    /// Fields of the events whose values are replaced with `[REDACTED]`, as dot separated paths
+    /// such as `client.subject`
+    #[serde(default)]
+    redacted_fields: Vec<String>,
+    /// Number of events that can wait to be written before new events are dropped (default: 1024)
+    #[serde(default = "default_buffer_size")]
+    buffer_size: usize,
+}
+
+fn default_buffer_size() -> usize {
+    1024
+}
+
+/// Structured audit event of a client request
+#[derive(Debug, Default, Clone, Serialize)]
+struct AuditEvent {
+    timestamp: String,
+    operation_name: Option<String>,
+    operation_kind: Option<String>,
+    /// The usage reporting signature of the operation, without its literal values
+    operation_signature: Option<String>,
+    client: ClientIdentity,
+    authorization: AuthorizationDecisions,
+    /// Names of the subgraphs that were sent a request, in alphabetical order
+    subgraphs: BTreeSet<String>,
+    /// HTTP status of the response, if one was produced
+    status: Option<u16>,
+    outcome: Outcome,
+    /// Extension codes of the GraphQL errors of the response, in order of appearance
+    error_codes: Vec<String>,
+}
+
+/// How the handling of a request ended
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    /// The response was entirely sent
+    Completed,
+    /// The request or its response were dropped before completion, for instance because the
+    /// client went away
+    #[default]
+    Cancelled,
+    /// The pipeline returned an error instead of a response
+    Failed,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+struct ClientIdentity {
+    name: Option<String>,
+    version: Option<String>,
+    /// The `sub` claim of the authenticated JWT
+    subject: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+struct AuthorizationDecisions {
+    requires_authentication: bool,
+    required_scopes: Vec<String>,
+    /// Required policies, with the decision taken for each of them, if any
+    required_policies: HashMap<String, Option<bool>>,
+    /// Paths removed from the query because the client was not authorized to access them
+    filtered_paths: Vec<String>,
+}
+
+impl AuditEvent {
+    fn complete_from_context(&mut self, context: &Context) {
+        self.timestamp = humantime::format_rfc3339_millis(std::time::SystemTime::now()).to_string();
+        self.operation_name = context.get(OPERATION_NAME).ok().flatten();
+        self.operation_kind = context.get(OPERATION_KIND).ok().flatten();
+        self.operation_signature = context.extensions().with_lock(|lock| {
+            lock.get::<Arc<UsageReporting>>()
+                .map(|usage_reporting| usage_reporting.stats_report_key.clone())
+        });
+        self.client.name = context.get(CLIENT_NAME).ok().flatten();
+        self.client.version = context.get(CLIENT_VERSION).ok().flatten();
+        self.client.subject = context
+            .get::<_, serde_json::Value>(APOLLO_AUTHENTICATION_JWT_CLAIMS)
+            .ok()
+            .flatten()
+            .and_then(|claims| claims.get("sub")?.as_str().map(str::to_string));
+        self.authorization.requires_authentication = context.contains_key(AUTHENTICATED_KEY);
+        self.authorization.required_scopes = context
+            .get(REQUIRED_SCOPES_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        self.authorization.required_policies = context
+            .get(REQUIRED_POLICIES_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+    }
+}
+
+/// Emits the audit event of a request when dropped, so that requests whose response is never
+/// completed are audited too
+struct PendingEvent {
+    context: Context,
+    sender: mpsc::Sender<serde_json::Value>,
+    redacted_fields: Arc<Vec<Vec<String>>>,
+    status: Option<u16>,
+    outcome: Outcome,
+}
+
+impl Drop for PendingEvent {
+    fn drop(&mut self) {
+        let event = self
+            .context
+            .extensions()
+            .with_lock(|mut lock| lock.remove::<AuditEvent>());
+
+        if let Some(mut event) = event {
+            event.status = self.status;
+            event.outcome = self.outcome;
+            event.complete_from_context(&self.context);
+            emit(&self.sender, event, &self.redacted_fields);
+        }
+    }
+}
+
+struct Audit {
+    enabled: bool,
+    redacted_fields: Arc<Vec<Vec<String>>>,
+    sender: Option<mpsc::Sender<serde_json::Value>>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Audit {
+    type Config = AuditConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let config = init.config;
+        let sender = if config.enabled {
+            Some(sink::spawn(&config.sink, config.buffer_size.max(1)).await?)
+        } else {
+            None
+        };
+
+        Ok(Audit {
+            enabled: config.enabled,
+            redacted_fields: Arc::new(
+                config
+                    .redacted_fields
+                    .iter()
+                    .map(|path| path.split('.').map(str::to_string).collect())
+                    .collect(),
+            ),
+            sender,
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        let Some(sender) = self.sender.clone() else {
+            return service;
+        };
+        let redacted_fields = self.redacted_fields.clone();
+
+        ServiceBuilder::new()
+            .map_future_with_request_data(
+                |req: &router::Request| {
+                    req.context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(AuditEvent::default()));
+                    req.context.clone()
+                },
+                move |context: Context, future: BoxFuture<'static, router::ServiceResult>| {
+                    let mut pending = PendingEvent {
+                        context,
+                        sender: sender.clone(),
+                        redacted_fields: redacted_fields.clone(),
+                        status: None,
+                        outcome: Outcome::Cancelled,
+                    };
+
+                    async move {
+                        let res = match future.await {
+                            Ok(res) => res,
+                            Err(err) => {
+                                pending.outcome = Outcome::Failed;
+                                return Err(err);
+                            }
+                        };
+                        let (parts, stream) = res.response.into_parts();
+                        pending.status = Some(parts.status.as_u16());
+
+                        // The event is emitted once the body was entirely sent, or when the
+                        // body is dropped before that
+                        let after_complete = once(async move {
+                            pending.outcome = Outcome::Completed;
+                            drop(pending);
+                            None
+                        })
+                        .filter_map(|a| async move { a });
+
+                        let stream = stream.chain(after_complete);
+
+                        Ok(router::Response {
+                            context: res.context,
+                            response: http::Response::from_parts(
+                                parts,
+                                RouterBody::wrap_stream(stream).into_inner(),
+                            ),
+                        })
+                    }
+                },
+            )
+            .service(service)
+            .boxed()
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.enabled {
+            return service;
+        }
+
+        ServiceBuilder::new()
+            .map_response(|res: supergraph::Response| {
+                let context = res.context.clone();
+                res.map_stream(move |chunk| {
+                    if !chunk.errors.is_empty() {
+                        context.extensions().with_lock(|mut lock| {
+                            if let Some(event) = lock.get_mut::<AuditEvent>() {
+                                event
+                                    .error_codes
+                                    .extend(chunk.errors.iter().filter_map(|error| {
+                                        Some(error.extensions.get("code")?.as_str()?.to_string())
+                                    }));
+                            }
+                        });
+                    }
+                    chunk
+                })
+            })
+            .service(service)
+            .boxed()
+    }
+
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        if !self.enabled {
+            return service;
+        }
+
+        ServiceBuilder::new()
+            .map_request(|req: execution::Request| {
+                let filtered_paths = &req.query_plan.query.unauthorized.paths;
+                if !filtered_paths.is_empty() {
+                    req.context.extensions().with_lock(|mut lock| {
+                        if let Some(event) = lock.get_mut::<AuditEvent>() {
+                            event.authorization.filtered_paths =
+                                filtered_paths.iter().map(|path| path.to_string()).collect();
+                        }
+                    });
+                }
+                req
+            })
+            .service(service)
+            .boxed()
+    }
+
+    fn subgraph_service(
+        &self,
+        subgraph_name: &str,
+        service: subgraph::BoxService,
+    ) -> subgraph::BoxService {
+        if !self.enabled {
+            return service;
+        }
+
+        let subgraph_name = subgraph_name.to_string();
+        ServiceBuilder::new()
+            .map_request(move |req: subgraph::Request| {
+                req.context.extensions().with_lock(|mut lock| {
+                    if let Some(event) = lock.get_mut::<AuditEvent>() {
+                        event.subgraphs.insert(subgraph_name.clone());
+                    }
+                });
+                req
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Queues the event to be written, without waiting on the sink
+fn emit(
+    sender: &mpsc::Sender<serde_json::Value>,
+    event: AuditEvent,
+    redacted_fields: &[Vec<String>],
+) {
+    let mut event = match serde_json::to_value(event) {
+        Ok(event) => event,
+        Err(err) => {
+            tracing::error!("cannot serialize audit event: {err}");
+            return;
+        }
+    };
+    for path in redacted_fields {
+        redact(&mut event, path);
+    }
+    match sender.try_send(event) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            tracing::warn!("the audit log buffer is full, dropping an audit event");
+        }
+        Err(TrySendError::Closed(_)) => {
+            tracing::error!("the audit log sink is closed, dropping an audit event");
+        }
+    }
+}
+
+fn redact(value: &mut serde_json::Value, path: &[String]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = value;
+    for key in parents {
+        match current.get_mut(key.as_str()) {
+            Some(next) => current = next,
+            None => return,
+        }
+    }
+    if let Some(field) = current.get_mut(last.as_str()) {
+        if !field.is_null() {
+            *field = serde_json::Value::String(REDACTED.to_string());
+        }
+    }
+}
+
+register_plugin!("experimental", "audit", Audit);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tower::service_fn;
+
+    use super::*;
+    use crate::graphql;
+    use crate::services::router::body::get_body_bytes;
+
+    #[test]
+    fn it_redacts_fields() {
+        let mut event = json!({
+            "operation_name": "Me",
+            "client": { "name": "web", "subject": "user-1", "version": null },
+        });
+        redact(&mut event, &["client".to_string(), "subject".to_string()]);
+        redact(&mut event, &["client".to_string(), "version".to_string()]);
+        redact(&mut event, &["unknown".to_string(), "field".to_string()]);
+        assert_eq!(
+            event,
+            json!({
+                "operation_name": "Me",
+                "client": { "name": "web", "subject": "[REDACTED]", "version": null },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_emits_an_event_per_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let plugin = Arc::new(
+            Audit::new(
+                PluginInit::fake_builder()
+                    .config(
+                        serde_json::from_value(json!({
+                            "enabled": true,
+                            "sink": { "file": { "path": path } },
+                            "redacted_fields": ["client.name"]
+                        }))
+                        .unwrap(),
+                    )
+                    .build(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let inner = plugin.clone();
+        let router = plugin.router_service(
+            service_fn(move |req: router::Request| {
+                let plugin = inner.clone();
+                async move {
+                    let _ = req.context.insert(OPERATION_NAME, "Me".to_string());
+                    let _ = req.context.insert(CLIENT_NAME, "web".to_string());
+                    req.context.extensions().with_lock(|mut lock| {
+                        lock.insert(Arc::new(UsageReporting {
+                            stats_report_key: "# Me\nquery Me{me(id:null){name}}".to_string(),
+                            referenced_fields_by_type: Default::default(),
+                        }))
+                    });
+
+                    let subgraph = plugin.subgraph_service(
+                        "accounts",
+                        service_fn(|req: subgraph::Request| async move {
+                            Ok::<_, BoxError>(
+                                subgraph::Response::fake_builder()
+                                    .context(req.context)
+                                    .build(),
+                            )
+                        })
+                        .boxed(),
+                    );
+                    subgraph
+                        .oneshot(
+                            subgraph::Request::fake_builder()
+                                .context(req.context.clone())
+                                .build(),
+                        )
+                        .await?;
+
+                    let supergraph = plugin.supergraph_service(
+                        service_fn(|req: supergraph::Request| async move {
+                            supergraph::Response::fake_builder()
+                                .error(
+                                    graphql::Error::builder()
+                                        .message("forbidden")
+                                        .extension_code("FORBIDDEN")
+                                        .build(),
+                                )
+                                .context(req.context)
+                                .build()
+                        })
+                        .boxed(),
+                    );
+                    let mut response = supergraph
+                        .oneshot(
+                            supergraph::Request::fake_builder()
+                                .context(req.context.clone())
+                                .build()?,
+                        )
+                        .await?;
+                    let body = response.next_response().await.unwrap();
+
+                    router::Response::fake_builder()
+                        .errors(body.errors)
+                        .context(req.context)
+                        .build()
+                }
+            })
+            .boxed(),
+        );
+
+        let response = router
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        get_body_bytes(response.response.into_body()).await.unwrap();
+
+        let mut contents = String::new();
+        for _ in 0..50 {
+            contents = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+            if !contents.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let event: serde_json::Value = serde_json::from_str(contents.trim()).unwrap();
+        assert_eq!(event["operation_name"], "Me");
+        assert_eq!(
+            event["operation_signature"],
+            "# Me\nquery Me{me(id:null){name}}"
+        );
+        assert_eq!(event["client"]["name"], REDACTED);
+        assert_eq!(event["subgraphs"], json!(["accounts"]));
+        assert_eq!(event["status"], 200);
+        assert_eq!(event["outcome"], "completed");
+        assert_eq!(event["error_codes"], json!(["FORBIDDEN"]));
+    }
+
+    async fn audited_router(
+        path: &std::path::Path,
+        service: router::BoxService,
+    ) -> router::BoxService {
+        let plugin = Audit::new(
+            PluginInit::fake_builder()
+                .config(
+                    serde_json::from_value(json!({
+                        "enabled": true,
+                        "sink": { "file": { "path": path } }
+                    }))
+                    .unwrap(),
+                )
+                .build(),
+        )
+        .await
+        .unwrap();
+        plugin.router_service(service)
+    }
+
+    async fn read_event(path: &std::path::Path) -> serde_json::Value {
+        let mut contents = String::new();
+        for _ in 0..50 {
+            contents = tokio::fs::read_to_string(path).await.unwrap_or_default();
+            if !contents.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        serde_json::from_str(contents.trim()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_emits_an_event_for_abandoned_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let router = audited_router(
+            &path,
+            service_fn(|req: router::Request| async move {
+                router::Response::fake_builder()
+                    .status_code(http::StatusCode::OK)
+                    .context(req.context)
+                    .build()
+            })
+            .boxed(),
+        )
+        .await;
+
+        // the client goes away before reading the body
+        let response = router
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        drop(response);
+
+        let event = read_event(&path).await;
+        assert_eq!(event["status"], 200);
+        assert_eq!(event["outcome"], "cancelled");
+    }
+
+    #[tokio::test]
+    async fn it_emits_an_event_for_failed_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let router = audited_router(
+            &path,
+            service_fn(|_req: router::Request| async move {
+                Err::<router::Response, _>(BoxError::from("pipeline failure"))
+            })
+            .boxed(),
+        )
+        .await;
+
+        assert!(router
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .is_err());
+
+        let event = read_event(&path).await;
+        assert_eq!(event["status"], serde_json::Value::Null);
+        assert_eq!(event["outcome"], "failed");
+    }
+}
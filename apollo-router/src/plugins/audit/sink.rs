@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tower::BoxError;
+use url::Url;
+
+/// Destination of the audit events
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum AuditSink {
+    /// Write one JSON event per line to the standard output
+    #[default]
+    Stdout,
+    /// Append one JSON event per line to a file
+    File {
+        /// The path of the file. It is created if it does not exist
+        path: PathBuf,
+    },
+    /// Send each event as a JSON `POST` request to an HTTP endpoint
+    Http {
+        /// The URL of the endpoint
+        endpoint: Url,
+        /// Headers added to each request, such as credentials for the endpoint
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+enum Writer {
+    Stream(Box<dyn AsyncWrite + Send + Unpin>),
+    Http {
+        client: reqwest::Client,
+        endpoint: Url,
+        headers: http::HeaderMap,
+    },
+}
+
+impl Writer {
+    async fn new(sink: &AuditSink) -> Result<Self, BoxError> {
+        Ok(match sink {
+            AuditSink::Stdout => Writer::Stream(Box::new(tokio::io::stdout())),
+            AuditSink::File { path } => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|err| format!("cannot open audit log file {path:?}: {err}"))?;
+                Writer::Stream(Box::new(file))
+            }
+            AuditSink::Http { endpoint, headers } => {
+                let headers = headers
+                    .iter()
+                    .map(|(name, value)| {
+                        Ok((
+                            http::HeaderName::try_from(name.as_str())?,
+                            http::HeaderValue::try_from(value.as_str())?,
+                        ))
+                    })
+                    .collect::<Result<http::HeaderMap, BoxError>>()?;
+                Writer::Http {
+                    client: reqwest::Client::new(),
+                    endpoint: endpoint.clone(),
+                    headers,
+                }
+            }
+        })
+    }
+
+    async fn write(&mut self, event: &serde_json::Value) -> Result<(), BoxError> {
+        match self {
+            Writer::Stream(stream) => {
+                let mut line = serde_json::to_vec(event)?;
+                line.push(b'\n');
+                stream.write_all(&line).await?;
+                stream.flush().await?;
+            }
+            Writer::Http {
+                client,
+                endpoint,
+                headers,
+            } => {
+                client
+                    .post(endpoint.clone())
+                    .headers(headers.clone())
+                    .json(event)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Opens the sink and spawns the task writing the events sent to the returned channel.
+///
+/// The task stops once every sender has been dropped.
+pub(super) async fn spawn(
+    sink: &AuditSink,
+    capacity: usize,
+) -> Result<mpsc::Sender<serde_json::Value>, BoxError> {
+    let mut writer = Writer::new(sink).await?;
+    let (sender, mut receiver) = mpsc::channel::<serde_json::Value>(capacity);
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            if let Err(err) = writer.write(&event).await {
+                tracing::error!("cannot write audit event: {err}");
+            }
+        }
+    });
+    Ok(sender)
+}
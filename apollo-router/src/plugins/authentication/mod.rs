@@ -858,6 +858,11 @@ fn decode_jwt(
     }
 }
 
+/// Compares secrets in a time that does not depend on their contents
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 pub(crate) fn jwt_expires_in(context: &Context) -> Duration {
     let claims = context
         .get(APOLLO_AUTHENTICATION_JWT_CLAIMS)
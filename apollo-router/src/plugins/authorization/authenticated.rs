@@ -1669,7 +1669,9 @@ mod tests {
             lock.insert(ClientRequestAccepts {
                 multipart_defer: true,
                 multipart_subscription: true,
+                event_stream: true,
                 json: true,
+                graphql_response_json: false,
                 wildcard: true,
             })
         });
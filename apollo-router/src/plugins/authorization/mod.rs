@@ -52,9 +52,9 @@ pub(crate) mod authenticated;
 pub(crate) mod policy;
 pub(crate) mod scopes;
 
-const AUTHENTICATED_KEY: &str = "apollo_authorization::authenticated::required";
-const REQUIRED_SCOPES_KEY: &str = "apollo_authorization::scopes::required";
-const REQUIRED_POLICIES_KEY: &str = "apollo_authorization::policies::required";
+pub(crate) const AUTHENTICATED_KEY: &str = "apollo_authorization::authenticated::required";
+pub(crate) const REQUIRED_SCOPES_KEY: &str = "apollo_authorization::scopes::required";
+pub(crate) const REQUIRED_POLICIES_KEY: &str = "apollo_authorization::policies::required";
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct CacheKeyMetadata {
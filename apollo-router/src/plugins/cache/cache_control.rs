@@ -249,6 +249,27 @@ impl CacheControl {
         Ok(s)
     }
 
+    /// Policy resulting from the cache hints of an operation
+    pub(super) fn from_hint(max_age: u32, private: bool) -> Self {
+        if max_age == 0 {
+            return Self::no_store();
+        }
+        CacheControl {
+            max_age: Some(max_age),
+            private,
+            public: !private,
+            ..Default::default()
+        }
+    }
+
+    /// Policy limiting the maximum age of this one, used to cache errors for a short time
+    pub(super) fn with_max_age(&self, max_age: u32) -> Self {
+        self.merge(&CacheControl {
+            max_age: Some(max_age),
+            ..Default::default()
+        })
+    }
+
     pub(super) fn no_store() -> Self {
         CacheControl {
             no_store: true,
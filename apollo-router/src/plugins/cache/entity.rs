@@ -65,6 +65,8 @@ pub(crate) const REPRESENTATIONS: &str = "representations";
 pub(crate) const CONTEXT_CACHE_KEY: &str = "apollo_entity_cache::key";
 /// Context key to enable support of surrogate cache key
 pub(crate) const CONTEXT_CACHE_KEYS: &str = "apollo::entity_cache::cached_keys_status";
+/// Codes of authentication and authorization errors, which are never cached
+const AUTH_ERROR_CODES: &[&str] = &["UNAUTHENTICATED", "FORBIDDEN", "UNAUTHORIZED_FIELD_OR_TYPE"];
 
 register_plugin!("apollo", "preview_entity_cache", EntityCache);
 
@@ -133,6 +135,9 @@ pub(crate) struct Subgraph {
 
     /// Invalidation configuration
     pub(crate) invalidation: Option<SubgraphInvalidationConfig>,
+
+    /// Caching of responses containing errors
+    pub(crate) errors: Option<ErrorCaching>,
 }
 
 impl Default for Subgraph {
@@ -143,7 +148,70 @@ impl Default for Subgraph {
             ttl: Default::default(),
             private_id: Default::default(),
             invalidation: Default::default(),
+            errors: Default::default(),
+        }
+    }
+}
+
+/// Caching of responses containing errors, like entities not found
+#[derive(Clone, Debug, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) struct ErrorCaching {
+    /// Codes (from the `code` extension) of the errors that can be cached. A response or entity is only cached if all of its errors have one of these codes. Authentication and authorization errors are never cached
+    #[serde(default)]
+    pub(crate) codes: Vec<String>,
+
+    /// expiration of cached errors, taking precedence over longer expirations from the `Cache-Control` header
+    pub(crate) ttl: Ttl,
+}
+
+impl ErrorCaching {
+    fn validate(&self, subgraph: &str) -> Result<(), BoxError> {
+        if let Some(code) = self
+            .codes
+            .iter()
+            .find(|code| AUTH_ERROR_CODES.contains(&code.as_str()))
+        {
+            return Err(
+                format!("the {code} error code cannot be cached for subgraph {subgraph}").into(),
+            );
+        }
+        if self.ttl.0.as_secs() == 0 {
+            return Err(format!(
+                "the TTL of cached errors must be at least one second for subgraph {subgraph}"
+            )
+            .into());
         }
+        Ok(())
+    }
+
+    /// Returns true if all the errors have one of the configured codes
+    fn can_cache<'a>(&self, mut errors: impl Iterator<Item = &'a Error>) -> bool {
+        errors.all(|error| {
+            error
+                .extensions
+                .get("code")
+                .and_then(|code| code.as_str())
+                .map(|code| {
+                    !AUTH_ERROR_CODES.contains(&code) && self.codes.iter().any(|c| c == code)
+                })
+                .unwrap_or(false)
+        })
+    }
+
+    /// Policy of the cached errors: they are cached even if the subgraph response has no
+    /// `Cache-Control` header, but only for the configured TTL
+    fn cache_control(
+        &self,
+        headers: &http::HeaderMap,
+        default_ttl: Option<Duration>,
+    ) -> Result<CacheControl, BoxError> {
+        let cache_control = if headers.contains_key(CACHE_CONTROL) {
+            CacheControl::new(headers, default_ttl)?
+        } else {
+            CacheControl::default()
+        };
+        Ok(cache_control.with_max_age(self.ttl.0.as_secs() as u32))
     }
 }
 
@@ -265,6 +333,15 @@ impl Plugin for EntityCache {
                 .into());
         }
 
+        if let Some(errors) = &init.config.subgraph.all.errors {
+            errors.validate("all")?;
+        }
+        for (subgraph, config) in &init.config.subgraph.subgraphs {
+            if let Some(errors) = &config.errors {
+                errors.validate(subgraph)?;
+            }
+        }
+
         if init
             .config
             .subgraph
@@ -365,6 +442,7 @@ impl Plugin for EntityCache {
         let subgraph_enabled =
             self.enabled && (self.subgraphs.all.enabled || self.subgraphs.get(name).enabled);
         let private_id = self.subgraphs.get(name).private_id.clone();
+        let error_caching = self.subgraphs.get(name).errors.clone();
 
         let name = name.to_string();
 
@@ -398,6 +476,7 @@ impl Plugin for EntityCache {
                     subgraph_ttl,
                     private_queries,
                     private_id,
+                    error_caching,
                     invalidation: self.invalidation.clone(),
                     expose_keys_in_context: self.expose_keys_in_context,
                 })));
@@ -507,6 +586,7 @@ struct InnerCacheService {
     subgraph_ttl: Option<Duration>,
     private_queries: Arc<RwLock<HashSet<String>>>,
     private_id: Option<String>,
+    error_caching: Option<ErrorCaching>,
     expose_keys_in_context: bool,
     invalidation: Invalidation,
 }
@@ -606,14 +686,34 @@ impl InnerCacheService {
 
                         let mut response = self.service.call(request).await?;
 
-                        let cache_control =
-                            if response.response.headers().contains_key(CACHE_CONTROL) {
-                                CacheControl::new(response.response.headers(), self.storage.ttl)?
-                            } else {
-                                let mut c = CacheControl::default();
-                                c.no_store = true;
+                        let errors = &response.response.body().errors;
+                        let cache_control = match &self.error_caching {
+                            Some(error_caching)
+                                if !errors.is_empty() && error_caching.can_cache(errors.iter()) =>
+                            {
+                                let c = error_caching
+                                    .cache_control(response.response.headers(), self.storage.ttl)?;
+                                // the errors are cached for a shorter time than announced by the subgraph
+                                c.to_headers(response.response.headers_mut())?;
                                 c
-                            };
+                            }
+                            _ => {
+                                let mut c =
+                                    if response.response.headers().contains_key(CACHE_CONTROL) {
+                                        CacheControl::new(
+                                            response.response.headers(),
+                                            self.storage.ttl,
+                                        )?
+                                    } else {
+                                        CacheControl::no_store()
+                                    };
+                                // errors are only stored if they were configured as cacheable
+                                if !errors.is_empty() {
+                                    c.no_store = true;
+                                }
+                                c
+                            }
+                        };
 
                         if cache_control.private() {
                             // we did not know in advance that this was a query with a private scope, so we update the cache key
@@ -795,11 +895,22 @@ impl InnerCacheService {
                         .await;
                     }
 
+                    let error_cache_control = self
+                        .error_caching
+                        .as_ref()
+                        .map(|error_caching| {
+                            error_caching
+                                .cache_control(response.response.headers(), self.storage.ttl)
+                                .map(|control| (error_caching, control))
+                        })
+                        .transpose()?;
+
                     cache_store_entities_from_response(
                         self.storage,
                         self.subgraph_ttl,
                         &mut response,
                         cache_control.clone(),
+                        error_cache_control,
                         cache_result.0,
                         is_known_private,
                         private_id,
@@ -906,6 +1017,7 @@ async fn cache_lookup_root(
 
                 let mut response = subgraph::Response::builder()
                     .data(value.0.data)
+                    .errors(value.0.errors)
                     .extensions(Object::new())
                     .context(request.context)
                     .and_subgraph_name(request.subgraph_name.clone())
@@ -1024,16 +1136,22 @@ async fn cache_lookup_entities(
             EntityCacheResults(cache_result, cache_control),
         )))
     } else {
-        let entities = cache_result
+        let mut entities = Vec::with_capacity(cache_result.len());
+        let mut errors = Vec::new();
+        for (entity_idx, entry) in cache_result
             .into_iter()
             .filter_map(|res| res.cache_entry)
-            .map(|entry| entry.data)
-            .collect::<Vec<_>>();
+            .enumerate()
+        {
+            errors.extend(entity_errors_from_cache(entry.errors, entity_idx));
+            entities.push(entry.data);
+        }
         let mut data = Object::default();
         data.insert(ENTITIES, entities.into());
 
         let mut response = subgraph::Response::builder()
             .data(data)
+            .errors(errors)
             .extensions(Object::new())
             .and_subgraph_name(request.subgraph_name)
             .context(request.context)
@@ -1047,7 +1165,7 @@ async fn cache_lookup_entities(
     }
 }
 
-fn update_cache_control(context: &Context, cache_control: &CacheControl) {
+pub(super) fn update_cache_control(context: &Context, cache_control: &CacheControl) {
     context.extensions().with_lock(|mut lock| {
         if let Some(c) = lock.get_mut::<CacheControl>() {
             *c = c.merge(cache_control);
@@ -1062,6 +1180,9 @@ fn update_cache_control(context: &Context, cache_control: &CacheControl) {
 struct CacheEntry {
     control: CacheControl,
     data: Value,
+    /// Errors cached along with the data, with their path relative to the subgraph response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<Error>,
 }
 
 impl ValueType for CacheEntry {
@@ -1084,9 +1205,11 @@ async fn cache_store_root_from_response(
             .map(|secs| Duration::from_secs(secs as u64))
             .or(subgraph_ttl);
 
-        if response.response.body().errors.is_empty() && cache_control.should_store() {
+        // responses with errors only get there if the errors can be cached
+        if cache_control.should_store() {
             let span = tracing::info_span!("cache.entity.store");
             let data = data.clone();
+            let errors = response.response.body().errors.clone();
             if expose_keys_in_context {
                 let response_id = response.id.clone();
                 let cache_control_header = cache_control.to_cache_control_header()?;
@@ -1125,6 +1248,7 @@ async fn cache_store_root_from_response(
                         RedisValue(CacheEntry {
                             control: cache_control,
                             data,
+                            errors,
                         }),
                         ttl,
                     )
@@ -1137,11 +1261,13 @@ async fn cache_store_root_from_response(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cache_store_entities_from_response(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
     response: &mut subgraph::Response,
     cache_control: CacheControl,
+    error_cache_control: Option<(&ErrorCaching, CacheControl)>,
     mut result_from_cache: Vec<IntermediateResult>,
     is_known_private: bool,
     private_id: Option<String>,
@@ -1172,6 +1298,7 @@ async fn cache_store_entities_from_response(
             cache,
             subgraph_ttl,
             cache_control,
+            error_cache_control,
             &mut result_from_cache,
             update_key_private,
             should_cache_private,
@@ -1446,6 +1573,7 @@ async fn insert_entities_in_result(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
     cache_control: CacheControl,
+    error_cache_control: Option<(&ErrorCaching, CacheControl)>,
     result: &mut Vec<IntermediateResult>,
     update_key_private: Option<String>,
     should_cache_private: bool,
@@ -1460,6 +1588,7 @@ async fn insert_entities_in_result(
 
     let mut inserted_types: HashMap<String, usize> = HashMap::new();
     let mut to_insert: Vec<_> = Vec::new();
+    let mut errors_to_insert: Vec<_> = Vec::new();
     let mut entities_it = entities.drain(..).enumerate();
 
     // insert requested entities and cached entities in the same order as
@@ -1475,6 +1604,7 @@ async fn insert_entities_in_result(
     {
         match cache_entry {
             Some(v) => {
+                new_errors.extend(entity_errors_from_cache(v.errors, new_entity_idx));
                 new_entities.push(v.data);
             }
             None => {
@@ -1491,7 +1621,7 @@ async fn insert_entities_in_result(
                     key = format!("{key}:{id}");
                 }
 
-                let mut has_errors = false;
+                let mut entity_errors = Vec::new();
                 for error in errors.iter().filter(|e| {
                     e.path
                         .as_ref()
@@ -1509,19 +1639,36 @@ async fn insert_entities_in_result(
                         path.0[1] = PathElement::Index(new_entity_idx);
                     }
 
-                    new_errors.push(e);
-                    has_errors = true;
+                    entity_errors.push(e);
                 }
 
-                if !has_errors && cache_control.should_store() && should_cache_private {
-                    to_insert.push((
-                        RedisKey(key),
-                        RedisValue(CacheEntry {
-                            control: cache_control.clone(),
-                            data: value.clone(),
-                        }),
-                    ));
+                if entity_errors.is_empty() {
+                    if cache_control.should_store() && should_cache_private {
+                        to_insert.push((
+                            RedisKey(key),
+                            RedisValue(CacheEntry {
+                                control: cache_control.clone(),
+                                data: value.clone(),
+                                errors: Vec::new(),
+                            }),
+                        ));
+                    }
+                } else if let Some((error_caching, error_cache_control)) = &error_cache_control {
+                    if error_caching.can_cache(entity_errors.iter())
+                        && error_cache_control.should_store()
+                        && should_cache_private
+                    {
+                        errors_to_insert.push((
+                            RedisKey(key),
+                            RedisValue(CacheEntry {
+                                control: error_cache_control.clone(),
+                                data: value.clone(),
+                                errors: entity_errors.clone(),
+                            }),
+                        ));
+                    }
                 }
+                new_errors.extend(entity_errors);
 
                 new_entities.push(value);
             }
@@ -1530,6 +1677,7 @@ async fn insert_entities_in_result(
 
     if !to_insert.is_empty() {
         let span = tracing::info_span!("cache_store");
+        let cache = cache.clone();
 
         tokio::spawn(async move {
             cache
@@ -1539,6 +1687,20 @@ async fn insert_entities_in_result(
         });
     }
 
+    if !errors_to_insert.is_empty() {
+        let span = tracing::info_span!("cache_store");
+        let errors_ttl = error_cache_control
+            .and_then(|(_, control)| control.ttl())
+            .map(|secs| Duration::from_secs(secs as u64));
+
+        tokio::spawn(async move {
+            cache
+                .insert_multiple(&errors_to_insert, errors_ttl)
+                .instrument(span)
+                .await;
+        });
+    }
+
     for (ty, nb) in inserted_types {
         tracing::event!(Level::TRACE, entity_type = ty.as_str(), cache_insert = nb,);
     }
@@ -1556,6 +1718,7 @@ fn assemble_response_from_errors(
     for (new_entity_idx, IntermediateResult { cache_entry, .. }) in result.drain(..).enumerate() {
         match cache_entry {
             Some(v) => {
+                new_errors.extend(entity_errors_from_cache(v.errors, new_entity_idx));
                 new_entities.push(v.data);
             }
             None => {
@@ -1574,6 +1737,16 @@ fn assemble_response_from_errors(
     (new_entities, new_errors)
 }
 
+/// Errors cached with an entity, moved to the entity index in the new response
+fn entity_errors_from_cache(errors: Vec<Error>, entity_idx: usize) -> impl Iterator<Item = Error> {
+    errors.into_iter().map(move |mut error| {
+        if let Some(path) = error.path.as_mut() {
+            path.0[1] = PathElement::Index(entity_idx);
+        }
+        error
+    })
+}
+
 pub(crate) type CacheKeysContext = HashMap<SubgraphRequestId, Vec<CacheKeyContext>>;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
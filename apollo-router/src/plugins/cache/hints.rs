@@ -0,0 +1,502 @@
+//! Calculation of the `Cache-Control` header of responses from the cache hints of the schema
+//!
+//! Each field of an operation constrains the cache policy of the response, through the
+//! `@cacheControl` directives of the supergraph schema or the hints of the configuration. The
+//! policy of the operation is then intersected with the `Cache-Control` headers of the subgraph
+//! responses.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use apollo_compiler::ast::Directive;
+use apollo_compiler::ast::FieldDefinition;
+use apollo_compiler::executable::Operation;
+use apollo_compiler::executable::OperationType;
+use apollo_compiler::executable::Selection;
+use apollo_compiler::executable::SelectionSet;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Name;
+use apollo_compiler::Schema;
+use http::header::CACHE_CONTROL;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use super::cache_control::CacheControl;
+use super::entity::update_cache_control;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::services::subgraph;
+use crate::services::supergraph;
+
+const CACHE_CONTROL_DIRECTIVE_NAME: &str = "cacheControl";
+
+register_plugin!("apollo", "experimental_cache_control", CacheHints);
+
+#[derive(Clone)]
+pub(crate) struct CacheHints {
+    schema: Arc<Valid<Schema>>,
+    config: Arc<CacheControlConfig>,
+}
+
+/// Configuration of the `Cache-Control` header calculation
+#[derive(Clone, Debug, Default, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+pub(crate) struct CacheControlConfig {
+    /// Set the `Cache-Control` header of the responses from the cache hints of the schema
+    enabled: bool,
+
+    /// Maximum age of the root fields and the fields returning a composite type without cache
+    /// hint. The responses are not cached by default
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String", default)]
+    default_max_age: Duration,
+
+    /// Cache hints per type name, taking precedence over the `@cacheControl` directives of the
+    /// schema
+    types: HashMap<String, TypeCacheHint>,
+}
+
+/// Cache hint of a type and its fields
+#[derive(Clone, Debug, Default, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+struct TypeCacheHint {
+    /// Maximum age of the fields returning this type
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "Option<String>", default)]
+    max_age: Option<Duration>,
+
+    /// Scope of the fields returning this type
+    scope: Option<CacheScope>,
+
+    /// Cache hints per field name
+    fields: HashMap<String, FieldCacheHint>,
+}
+
+/// Cache hint of a field
+#[derive(Clone, Debug, Default, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+struct FieldCacheHint {
+    /// Maximum age of the field
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "Option<String>", default)]
+    max_age: Option<Duration>,
+
+    /// Scope of the field
+    scope: Option<CacheScope>,
+
+    /// Use the maximum age of the parent field instead of the one of the returned type
+    inherit_max_age: bool,
+}
+
+/// Scope of a cache hint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CacheScope {
+    /// The response can be stored by shared caches
+    Public,
+    /// The response is specific to a user, and can only be stored by the client
+    Private,
+}
+
+/// Cache policy of the operation, before the subgraph responses are received
+#[derive(Clone, Debug)]
+struct OperationCachePolicy(CacheControl);
+
+#[async_trait::async_trait]
+impl Plugin for CacheHints {
+    type Config = CacheControlConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(CacheHints {
+            schema: init.supergraph_schema.clone(),
+            config: Arc::new(init.config),
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.config.enabled {
+            return service;
+        }
+
+        let this = self.clone();
+        ServiceBuilder::new()
+            .map_request(move |request: supergraph::Request| {
+                let document = request
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<ParsedDocument>().cloned());
+                if let Some(document) = document {
+                    let policy = this.operation_policy(&document.executable, &document.operation);
+                    request
+                        .context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(OperationCachePolicy(policy)));
+                }
+                request
+            })
+            .map_first_graphql_response(|context, mut parts, response| {
+                let operation_policy = context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<OperationCachePolicy>().cloned());
+                if let Some(OperationCachePolicy(operation_policy)) = operation_policy {
+                    let subgraph_policy = context
+                        .extensions()
+                        .with_lock(|lock| lock.get::<CacheControl>().cloned());
+                    let mut policy = match subgraph_policy {
+                        Some(subgraph_policy) => operation_policy.merge(&subgraph_policy),
+                        None => operation_policy,
+                    };
+                    // no-store takes precedence over every other directive, and responses
+                    // with errors are never cached
+                    if !policy.should_store() || !response.errors.is_empty() {
+                        policy = CacheControl::no_store();
+                    }
+
+                    let _ = policy.to_headers(&mut parts.headers);
+                    context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(policy));
+                }
+                (parts, response)
+            })
+            .service(service)
+            .boxed()
+    }
+
+    fn subgraph_service(&self, _name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        if !self.config.enabled {
+            return service;
+        }
+
+        ServiceBuilder::new()
+            .map_response(|response: subgraph::Response| {
+                // subgraphs without Cache-Control header do not constrain the response policy
+                if response.response.headers().contains_key(CACHE_CONTROL) {
+                    update_cache_control(
+                        &response.context,
+                        &CacheControl::new(response.response.headers(), None)
+                            .unwrap_or_else(|_| CacheControl::no_store()),
+                    );
+                }
+                response
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+impl CacheHints {
+    /// Cache policy resulting from the cache hints of the fields of an operation
+    fn operation_policy(
+        &self,
+        document: &ExecutableDocument,
+        operation: &Operation,
+    ) -> CacheControl {
+        if operation.operation_type != OperationType::Query {
+            return CacheControl::no_store();
+        }
+
+        let mut builder = PolicyBuilder {
+            hints: self,
+            document,
+            visited_fragments: HashSet::new(),
+            max_age: None,
+            private: false,
+        };
+        builder.selection_set(&operation.selection_set, true);
+
+        let default_max_age = self.config.default_max_age.as_secs() as u32;
+        CacheControl::from_hint(builder.max_age.unwrap_or(default_max_age), builder.private)
+    }
+
+    fn field_hint(&self, parent_type: &str, field: &FieldDefinition) -> Hint {
+        let configured = self
+            .config
+            .types
+            .get(parent_type)
+            .and_then(|ty| ty.fields.get(field.name.as_str()))
+            .map(|hint| Hint {
+                max_age: hint.max_age.map(|max_age| max_age.as_secs() as u32),
+                scope: hint.scope,
+                inherit_max_age: hint.inherit_max_age,
+            })
+            .unwrap_or_default();
+        let directive = field
+            .directives
+            .get(CACHE_CONTROL_DIRECTIVE_NAME)
+            .map(|directive| Hint::from_directive(directive))
+            .unwrap_or_default();
+        configured.or(directive)
+    }
+
+    fn type_hint(&self, name: &str) -> Hint {
+        let configured = self
+            .config
+            .types
+            .get(name)
+            .map(|hint| Hint {
+                max_age: hint.max_age.map(|max_age| max_age.as_secs() as u32),
+                scope: hint.scope,
+                inherit_max_age: false,
+            })
+            .unwrap_or_default();
+        let directive = self
+            .schema
+            .types
+            .get(name)
+            .and_then(|ty| ty.directives().get(CACHE_CONTROL_DIRECTIVE_NAME))
+            .map(|directive| Hint::from_directive(directive))
+            .unwrap_or_default();
+        configured.or(directive)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Hint {
+    max_age: Option<u32>,
+    scope: Option<CacheScope>,
+    inherit_max_age: bool,
+}
+
+impl Hint {
+    /// `@cacheControl(maxAge: Int, scope: CacheControlScope, inheritMaxAge: Boolean)`
+    fn from_directive(directive: &Directive) -> Self {
+        Hint {
+            max_age: directive
+                .specified_argument_by_name("maxAge")
+                .and_then(|value| value.to_i32())
+                .map(|max_age| max_age.max(0) as u32),
+            scope: directive
+                .specified_argument_by_name("scope")
+                .and_then(|value| value.as_enum())
+                .map(|scope| {
+                    if scope.as_str() == "PRIVATE" {
+                        CacheScope::Private
+                    } else {
+                        CacheScope::Public
+                    }
+                }),
+            inherit_max_age: directive
+                .specified_argument_by_name("inheritMaxAge")
+                .and_then(|value| value.to_bool())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Fills the missing parts of this hint with another one
+    fn or(self, other: Hint) -> Hint {
+        Hint {
+            max_age: self.max_age.or(other.max_age),
+            scope: self.scope.or(other.scope),
+            inherit_max_age: self.inherit_max_age || other.inherit_max_age,
+        }
+    }
+}
+
+struct PolicyBuilder<'a> {
+    hints: &'a CacheHints,
+    document: &'a ExecutableDocument,
+    visited_fragments: HashSet<(&'a Name, bool)>,
+    max_age: Option<u32>,
+    private: bool,
+}
+
+impl<'a> PolicyBuilder<'a> {
+    fn selection_set(&mut self, selection_set: &'a SelectionSet, is_root: bool) {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    if field.name.as_str().starts_with("__") {
+                        continue;
+                    }
+                    self.field(&selection_set.ty, &field.definition, is_root);
+                    self.selection_set(&field.selection_set, false);
+                }
+                Selection::FragmentSpread(spread) => {
+                    // the hints of a fragment do not depend on where it is used
+                    if !self
+                        .visited_fragments
+                        .insert((&spread.fragment_name, is_root))
+                    {
+                        continue;
+                    }
+                    if let Some(fragment) = self.document.fragments.get(&spread.fragment_name) {
+                        self.selection_set(&fragment.selection_set, is_root);
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    self.selection_set(&inline.selection_set, is_root)
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, parent_type: &str, definition: &FieldDefinition, is_root: bool) {
+        let field_type = definition.ty.inner_named_type();
+        let is_composite = matches!(
+            self.hints.schema.types.get(field_type),
+            Some(ExtendedType::Object(_) | ExtendedType::Interface(_) | ExtendedType::Union(_))
+        );
+
+        let mut hint = self.hints.field_hint(parent_type, definition);
+        if is_composite {
+            hint = hint.or(self.hints.type_hint(field_type));
+        }
+
+        // Scalar fields inherit the maximum age of their parent, while root fields and fields
+        // returning composite types get the default one
+        let max_age = if hint.inherit_max_age {
+            None
+        } else {
+            hint.max_age.or_else(|| {
+                (is_composite || is_root)
+                    .then(|| self.hints.config.default_max_age.as_secs() as u32)
+            })
+        };
+        if let Some(max_age) = max_age {
+            self.max_age = Some(self.max_age.map_or(max_age, |current| current.min(max_age)));
+        }
+        if hint.scope == Some(CacheScope::Private) {
+            self.private = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        enum CacheControlScope {
+          PUBLIC
+          PRIVATE
+        }
+
+        directive @cacheControl(
+          maxAge: Int
+          scope: CacheControlScope
+          inheritMaxAge: Boolean
+        ) on FIELD_DEFINITION | OBJECT | INTERFACE | UNION
+
+        type Query {
+          products: [Product] @cacheControl(maxAge: 60)
+          me: User
+          topProduct: Product
+          version: String @cacheControl(maxAge: 3600)
+        }
+
+        type Mutation {
+          buy(upc: String!): Boolean
+        }
+
+        type Product @cacheControl(maxAge: 300) {
+          upc: String
+          name: String
+          reviews: [Review]
+          related: Product @cacheControl(inheritMaxAge: true)
+        }
+
+        type Review @cacheControl(maxAge: 30) {
+          body: String
+        }
+
+        type User @cacheControl(maxAge: 10, scope: PRIVATE) {
+          name: String
+        }
+    "#;
+
+    fn policy(config: CacheControlConfig, query: &str) -> String {
+        let schema = Arc::new(Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap());
+        let document =
+            ExecutableDocument::parse_and_validate(&schema, query, "query.graphql").unwrap();
+        let operation = document.operations.get(None).unwrap();
+        let hints = CacheHints {
+            schema,
+            config: Arc::new(CacheControlConfig {
+                enabled: true,
+                ..config
+            }),
+        };
+        hints
+            .operation_policy(&document, operation)
+            .to_cache_control_header()
+            .unwrap()
+    }
+
+    #[test]
+    fn minimum_max_age_of_the_fields() {
+        let config = CacheControlConfig::default();
+        assert_eq!(
+            policy(config.clone(), "{ products { upc name } }"),
+            "max-age=60,public"
+        );
+        assert_eq!(
+            policy(config.clone(), "{ products { upc reviews { body } } }"),
+            "max-age=30,public"
+        );
+        assert_eq!(
+            policy(config.clone(), "{ products { related { upc } } version }"),
+            "max-age=60,public"
+        );
+        assert_eq!(
+            policy(config.clone(), "{ topProduct { upc } }"),
+            "max-age=300,public"
+        );
+        assert_eq!(
+            policy(config, "{ ...Fields } fragment Fields on Query { version }"),
+            "max-age=3600,public"
+        );
+    }
+
+    #[test]
+    fn private_scope_and_no_store() {
+        let config = CacheControlConfig::default();
+        assert_eq!(
+            policy(config.clone(), "{ me { name } version }"),
+            "max-age=10,private"
+        );
+        assert_eq!(
+            policy(config.clone(), "mutation { buy(upc: \"1\") }"),
+            "no-store"
+        );
+
+        // a field without hint returning a composite type uses the default maximum age
+        let config = CacheControlConfig {
+            types: [(
+                "Product".to_string(),
+                TypeCacheHint {
+                    max_age: Some(Duration::from_secs(120)),
+                    fields: [(
+                        "related".to_string(),
+                        FieldCacheHint {
+                            max_age: Some(Duration::ZERO),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy(config.clone(), "{ topProduct { upc } }"),
+            "max-age=120,public"
+        );
+        assert_eq!(
+            policy(config, "{ topProduct { related { upc } } }"),
+            "no-store"
+        );
+    }
+}
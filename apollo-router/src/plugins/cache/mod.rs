@@ -1,5 +1,6 @@
 pub(crate) mod cache_control;
 pub(crate) mod entity;
+pub(crate) mod hints;
 pub(crate) mod invalidation;
 pub(crate) mod invalidation_endpoint;
 pub(crate) mod metrics;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use fred::error::RedisErrorKind;
@@ -18,7 +19,9 @@ use crate::plugin::test::MockSubgraph;
 use crate::plugin::test::MockSubgraphService;
 use crate::plugins::cache::entity::CacheKeyContext;
 use crate::plugins::cache::entity::CacheKeysContext;
+use crate::plugins::cache::entity::ErrorCaching;
 use crate::plugins::cache::entity::Subgraph;
+use crate::plugins::cache::entity::Ttl;
 use crate::plugins::cache::entity::CONTEXT_CACHE_KEYS;
 use crate::services::subgraph;
 use crate::services::supergraph;
@@ -852,6 +855,112 @@ async fn missing_entities() {
     insta::assert_json_snapshot!(response);
 }
 
+#[tokio::test]
+async fn cached_errors() {
+    let query = "query { currentUser { activeOrganization { id } } }";
+
+    let subgraphs = MockedSubgraphs(
+        [(
+            "user",
+            MockSubgraph::builder()
+                .with_json(
+                    serde_json::json! {{"query":"{currentUser{activeOrganization{id}}}"}},
+                    serde_json::json! {{
+                        "data": { "currentUser": null },
+                        "errors": [{
+                            "message": "User not found",
+                            "path": ["currentUser"],
+                            "extensions": { "code": "NOT_FOUND" }
+                        }]
+                    }},
+                )
+                .with_header(
+                    CACHE_CONTROL,
+                    HeaderValue::from_static("public, max-age=3600"),
+                )
+                .build(),
+        )]
+        .into_iter()
+        .collect(),
+    );
+
+    let redis_cache = RedisCacheStorage::from_mocks(Arc::new(MockStore::new()))
+        .await
+        .unwrap();
+    let map = [(
+        "user".to_string(),
+        Subgraph {
+            errors: Some(ErrorCaching {
+                codes: vec!["NOT_FOUND".to_string()],
+                ttl: Ttl(Duration::from_secs(5)),
+            }),
+            ..Default::default()
+        },
+    )]
+    .into_iter()
+    .collect();
+    let entity_cache = EntityCache::with_mocks(redis_cache.clone(), map)
+        .await
+        .unwrap();
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .extra_plugin(subgraphs)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(Context::new())
+        .build()
+        .unwrap();
+    let mut response = service.oneshot(request).await.unwrap();
+    // the maximum age of the subgraph response is limited by the TTL of cached errors
+    assert_eq!(
+        response.response.headers().get(CACHE_CONTROL),
+        Some(&HeaderValue::from_static("max-age=5,public"))
+    );
+    let response = response.next_response().await.unwrap();
+    assert_eq!(response.errors.len(), 1);
+
+    // Now testing without any mock subgraphs, the error should come from the cache
+    let entity_cache = EntityCache::with_mocks(redis_cache.clone(), HashMap::new())
+        .await
+        .unwrap();
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(Context::new())
+        .build()
+        .unwrap();
+    let mut response = service.oneshot(request).await.unwrap();
+    let response = response.next_response().await.unwrap();
+
+    assert_eq!(
+        response.data,
+        Some(serde_json_bytes::json!({ "currentUser": null }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, "User not found");
+    assert_eq!(
+        response.errors[0].extensions.get("code"),
+        Some(&serde_json_bytes::Value::from("NOT_FOUND"))
+    );
+}
+
 /*FIXME: reactivate test if we manage to make fred return the response to SCAN in mocks
 #[tokio::test(flavor = "multi_thread")]
 async fn invalidate() {
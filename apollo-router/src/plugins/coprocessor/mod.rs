@@ -61,6 +61,8 @@ mod test;
 
 mod execution;
 mod supergraph;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 pub(crate) const EXTERNAL_SPAN_NAME: &str = "external_plugin";
 const POOL_IDLE_TIMEOUT_DURATION: Option<Duration> = Some(Duration::from_secs(5));
@@ -290,6 +292,8 @@ pub(super) struct SubgraphRequestConf {
     pub(super) service_name: bool,
     /// Send the subgraph request id
     pub(super) subgraph_request_id: bool,
+    /// How to proceed when the coprocessor call fails
+    pub(super) on_error: FailureMode,
 }
 
 /// What information is passed to a subgraph request/response stage
@@ -311,6 +315,21 @@ pub(super) struct SubgraphResponseConf {
     pub(super) status_code: bool,
     /// Send the subgraph request id
     pub(super) subgraph_request_id: bool,
+    /// How to proceed when the coprocessor call fails
+    pub(super) on_error: FailureMode,
+}
+
+/// How a subgraph stage proceeds when the coprocessor cannot be reached, times out or returns an
+/// invalid payload. The router, supergraph and execution stages always fail closed.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum FailureMode {
+    /// Fail the request
+    #[default]
+    FailClosed,
+    /// Ignore the failure and continue with the request or response as it was sent to the
+    /// coprocessor
+    FailOpen,
 }
 
 /// Configures the externalization plugin
@@ -531,6 +550,8 @@ impl SubgraphStage {
 
                 async move {
                     let mut succeeded = true;
+                    let original_request =
+                        (request_config.on_error == FailureMode::FailOpen).then(|| request.clone());
                     let result = process_subgraph_request_stage(
                         http_client,
                         coprocessor_url,
@@ -553,7 +574,10 @@ impl SubgraphStage {
                         "coprocessor.stage" = PipelineStep::SubgraphRequest,
                         "coprocessor.succeeded" = succeeded
                     );
-                    result
+                    match (result, original_request) {
+                        (Err(_), Some(request)) => Ok(ControlFlow::Continue(request)),
+                        (result, _) => result,
+                    }
                 }
             })
         });
@@ -571,6 +595,8 @@ impl SubgraphStage {
                     let response: subgraph::Response = fut.await?;
 
                     let mut succeeded = true;
+                    let original_response = (response_config.on_error == FailureMode::FailOpen)
+                        .then(|| clone_subgraph_response(&response));
                     let result = process_subgraph_response_stage(
                         http_client,
                         coprocessor_url,
@@ -593,7 +619,10 @@ impl SubgraphStage {
                         "coprocessor.stage" = PipelineStep::SubgraphResponse,
                         "coprocessor.succeeded" = succeeded
                     );
-                    result
+                    match (result, original_response) {
+                        (Err(_), Some(response)) => Ok(response),
+                        (result, _) => result,
+                    }
                 }
             })
         });
@@ -1231,6 +1260,25 @@ where
     Ok(response)
 }
 
+/// Keep a copy of a subgraph response so it can be used if the coprocessor call fails.
+///
+/// `http::Response` can't be cloned so we rebuild it, extensions are not preserved.
+fn clone_subgraph_response(response: &subgraph::Response) -> subgraph::Response {
+    let mut http_response = http::Response::builder()
+        .status(response.response.status())
+        .version(response.response.version())
+        .body(response.response.body().clone())
+        .expect("status and version come from a valid response; qed");
+    *http_response.headers_mut() = response.response.headers().clone();
+
+    subgraph::Response {
+        response: http_response,
+        subgraph_name: response.subgraph_name.clone(),
+        context: response.context.clone(),
+        id: response.id.clone(),
+    }
+}
+
 // -----------------------------------------------------------------------------------------
 
 fn validate_coprocessor_output<T>(
@@ -24,6 +24,9 @@ mod tests {
     use crate::plugin::test::MockRouterService;
     use crate::plugin::test::MockSubgraphService;
     use crate::plugin::test::MockSupergraphService;
+    use crate::plugins::coprocessor::execution::ExecutionRequestConf;
+    use crate::plugins::coprocessor::execution::ExecutionResponseConf;
+    use crate::plugins::coprocessor::supergraph::SupergraphRequestConf;
     use crate::plugins::coprocessor::supergraph::SupergraphResponseConf;
     use crate::plugins::coprocessor::supergraph::SupergraphStage;
     use crate::plugins::telemetry::config_new::conditions::SelectorOrValue;
@@ -71,6 +74,30 @@ mod tests {
             .is_err());
     }
 
+    #[test]
+    fn on_error_is_only_supported_by_subgraph_stages() {
+        let on_error = json!({ "on_error": "fail_open" });
+        assert_eq!(
+            FailureMode::FailOpen,
+            serde_json::from_value::<SubgraphRequestConf>(on_error.clone())
+                .unwrap()
+                .on_error
+        );
+        assert_eq!(
+            FailureMode::FailOpen,
+            serde_json::from_value::<SubgraphResponseConf>(on_error.clone())
+                .unwrap()
+                .on_error
+        );
+        // The router, supergraph and execution stages always fail closed
+        assert!(serde_json::from_value::<RouterRequestConf>(on_error.clone()).is_err());
+        assert!(serde_json::from_value::<RouterResponseConf>(on_error.clone()).is_err());
+        assert!(serde_json::from_value::<SupergraphRequestConf>(on_error.clone()).is_err());
+        assert!(serde_json::from_value::<SupergraphResponseConf>(on_error.clone()).is_err());
+        assert!(serde_json::from_value::<ExecutionRequestConf>(on_error.clone()).is_err());
+        assert!(serde_json::from_value::<ExecutionResponseConf>(on_error).is_err());
+    }
+
     #[tokio::test]
     async fn external_plugin_with_stages_wont_load_without_graph_ref() {
         let config = json!({
@@ -334,6 +361,135 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn coprocessor_subgraph_request_failure_fails_closed_by_default() {
+        let subgraph_stage = SubgraphStage {
+            request: SubgraphRequestConf {
+                condition: Default::default(),
+                body: true,
+                ..Default::default()
+            },
+            response: Default::default(),
+        };
+
+        let mock_subgraph_service = MockSubgraphService::new();
+
+        let mock_http_client = mock_with_callback(move |_: http::Request<RouterBody>| {
+            Box::pin(async { Err(BoxError::from("coprocessor is unavailable")) })
+        });
+
+        let service = subgraph_stage.as_service(
+            mock_http_client,
+            mock_subgraph_service.boxed(),
+            "http://test".to_string(),
+            "my_subgraph_service_name".to_string(),
+        );
+
+        let request = subgraph::Request::fake_builder().build();
+
+        assert_eq!(
+            "coprocessor is unavailable",
+            service.oneshot(request).await.unwrap_err().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn coprocessor_subgraph_request_failure_can_fail_open() {
+        let subgraph_stage = SubgraphStage {
+            request: SubgraphRequestConf {
+                condition: Default::default(),
+                body: true,
+                on_error: FailureMode::FailOpen,
+                ..Default::default()
+            },
+            response: Default::default(),
+        };
+
+        let mut mock_subgraph_service = MockSubgraphService::new();
+        mock_subgraph_service
+            .expect_call()
+            .returning(|req: subgraph::Request| {
+                Ok(subgraph::Response::builder()
+                    .data(json!({ "test": 1234_u32 }))
+                    .errors(Vec::new())
+                    .extensions(crate::json_ext::Object::new())
+                    .context(req.context)
+                    .id(req.id)
+                    .build())
+            });
+
+        let mock_http_client = mock_with_callback(move |_: http::Request<RouterBody>| {
+            Box::pin(async { Err(BoxError::from("coprocessor is unavailable")) })
+        });
+
+        let service = subgraph_stage.as_service(
+            mock_http_client,
+            mock_subgraph_service.boxed(),
+            "http://test".to_string(),
+            "my_subgraph_service_name".to_string(),
+        );
+
+        let request = subgraph::Request::fake_builder().build();
+
+        assert_eq!(
+            serde_json_bytes::json!({ "test": 1234_u32 }),
+            service
+                .oneshot(request)
+                .await
+                .unwrap()
+                .response
+                .into_body()
+                .data
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn coprocessor_subgraph_response_failure_can_fail_open() {
+        let subgraph_stage = SubgraphStage {
+            request: Default::default(),
+            response: SubgraphResponseConf {
+                condition: Default::default(),
+                body: true,
+                on_error: FailureMode::FailOpen,
+                ..Default::default()
+            },
+        };
+
+        let mut mock_subgraph_service = MockSubgraphService::new();
+        mock_subgraph_service
+            .expect_call()
+            .returning(|req: subgraph::Request| {
+                Ok(subgraph::Response::builder()
+                    .data(json!({ "test": 1234_u32 }))
+                    .errors(Vec::new())
+                    .extensions(crate::json_ext::Object::new())
+                    .context(req.context)
+                    .id(req.id)
+                    .build())
+            });
+
+        let mock_http_client = mock_with_callback(move |_: http::Request<RouterBody>| {
+            Box::pin(async { Err(BoxError::from("coprocessor is unavailable")) })
+        });
+
+        let service = subgraph_stage.as_service(
+            mock_http_client,
+            mock_subgraph_service.boxed(),
+            "http://test".to_string(),
+            "my_subgraph_service_name".to_string(),
+        );
+
+        let request = subgraph::Request::fake_builder().build();
+
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(StatusCode::OK, response.response.status());
+        assert_eq!(
+            serde_json_bytes::json!({ "test": 1234_u32 }),
+            response.response.into_body().data.unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn external_plugin_subgraph_request() {
         let subgraph_stage = SubgraphStage {
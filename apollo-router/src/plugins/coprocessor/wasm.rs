@@ -0,0 +1,432 @@
+//! WebAssembly coprocessor plugin
+//!
+//! Runs a WebAssembly module in process and exchanges the same payloads with it as the HTTP
+//! coprocessor does with an external service.
+//!
+//! The module must not import anything and must export:
+//!  - `memory`: its linear memory,
+//!  - `alloc(len: i32) -> i32`: allocates `len` bytes and returns a pointer to them,
+//!  - `handle(ptr: i32, len: i32) -> i64`: processes the JSON payload stored at `ptr` and returns
+//!    the JSON reply, packed as the pointer in the 32 high bits and the length in the 32 low bits.
+//!
+//! Each call is made on a fresh instance so that no state leaks between requests, and is bounded
+//! by the configured fuel and memory limits.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use futures::future::BoxFuture;
+use notify::event::DataChange;
+use notify::event::MetadataKind;
+use notify::event::ModifyKind;
+use notify::EventKind;
+use notify::PollWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::Service;
+use wasmtime::Engine;
+use wasmtime::Instance;
+use wasmtime::Module;
+use wasmtime::Store;
+use wasmtime::StoreLimits;
+use wasmtime::StoreLimitsBuilder;
+
+use super::execution::ExecutionStage;
+use super::supergraph::SupergraphStage;
+use super::RouterStage;
+use super::SubgraphStages;
+use crate::plugin::PluginInit;
+use crate::plugin::PluginPrivate;
+use crate::register_private_plugin;
+use crate::services;
+use crate::services::router;
+use crate::services::router::body::get_body_bytes;
+use crate::services::router::body::RouterBody;
+use crate::services::subgraph;
+
+/// Stands in for the coprocessor URL, payloads are never sent over the network
+const WASM_MODULE_URI: &str = "wasm://module";
+const DEFAULT_FUEL: u64 = 10_000_000;
+const DEFAULT_MAX_MEMORY: usize = 16 * 1024 * 1024;
+const DEFAULT_MAX_OUTPUT: usize = 1024 * 1024;
+
+/// Configures the WebAssembly coprocessor plugin
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct Conf {
+    /// The path to the WebAssembly module. The module is reloaded when the file changes
+    module: PathBuf,
+    /// The fuel available to a single call into the module. Calls running out of fuel fail
+    #[serde(default = "default_fuel")]
+    fuel: u64,
+    /// The maximum size, in bytes, of the linear memory of the module
+    #[serde(default = "default_max_memory")]
+    max_memory: usize,
+    /// The maximum size, in bytes, of the reply of the module. Calls replying with more fail
+    #[serde(default = "default_max_output")]
+    max_output: usize,
+    /// The router stage request/response configuration
+    #[serde(default)]
+    router: RouterStage,
+    /// The supergraph stage request/response configuration
+    #[serde(default)]
+    supergraph: SupergraphStage,
+    /// The execution stage request/response configuration
+    #[serde(default)]
+    execution: ExecutionStage,
+    /// The subgraph stage request/response configuration
+    #[serde(default)]
+    subgraph: SubgraphStages,
+}
+
+fn default_fuel() -> u64 {
+    DEFAULT_FUEL
+}
+
+fn default_max_memory() -> usize {
+    DEFAULT_MAX_MEMORY
+}
+
+fn default_max_output() -> usize {
+    DEFAULT_MAX_OUTPUT
+}
+
+/// A compiled WebAssembly module and the limits applied to each call into it
+struct WasmModule {
+    engine: Engine,
+    module: Module,
+    fuel: u64,
+    max_memory: usize,
+    max_output: usize,
+}
+
+impl WasmModule {
+    fn try_new(
+        path: &Path,
+        fuel: u64,
+        max_memory: usize,
+        max_output: usize,
+    ) -> Result<Self, BoxError> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)
+            .map_err(|err| format!("in WebAssembly module {}: {}", path.display(), err))?;
+
+        Ok(Self {
+            engine,
+            module,
+            fuel,
+            max_memory,
+            max_output,
+        })
+    }
+
+    fn call(&self, input: &[u8]) -> Result<Vec<u8>, BoxError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel)?;
+
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or("WebAssembly module does not export its memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let handle = instance.get_typed_func::<(i32, i32), i64>(&mut store, "handle")?;
+
+        let input_len = i32::try_from(input.len())?;
+        let input_ptr = alloc.call(&mut store, input_len)?;
+        memory.write(&mut store, input_ptr as u32 as usize, input)?;
+
+        let output = handle.call(&mut store, (input_ptr, input_len))? as u64;
+        let output_ptr = (output >> 32) as usize;
+        let output_len = (output & 0xFFFF_FFFF) as usize;
+        // The reply is untrusted, check it before allocating a buffer for it
+        if output_len > self.max_output {
+            return Err(format!(
+                "WebAssembly module reply of {output_len} bytes exceeds the {} bytes limit",
+                self.max_output
+            )
+            .into());
+        }
+        if output_ptr
+            .checked_add(output_len)
+            .map_or(true, |end| end > memory.data_size(&store))
+        {
+            return Err("WebAssembly module reply is out of the bounds of its memory".into());
+        }
+        let mut buffer = vec![0; output_len];
+        memory.read(&store, output_ptr, &mut buffer)?;
+
+        Ok(buffer)
+    }
+}
+
+/// Exposes a WebAssembly module as the HTTP client used by the coprocessor stages.
+///
+/// Calls into the module are CPU bound, they run on the blocking thread pool.
+#[derive(Clone)]
+struct WasmClient {
+    module: Arc<ArcSwap<WasmModule>>,
+}
+
+impl Service<http::Request<RouterBody>> for WasmClient {
+    type Response = http::Response<RouterBody>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<RouterBody>) -> Self::Future {
+        let module = self.module.load_full();
+        Box::pin(async move {
+            let input = get_body_bytes(request.into_body()).await?;
+            let output = tokio::task::spawn_blocking(move || module.call(&input)).await??;
+            Ok(http::Response::builder().body(RouterBody::from(output))?)
+        })
+    }
+}
+
+struct Wasm {
+    client: WasmClient,
+    configuration: Conf,
+    sdl: Arc<String>,
+    park_flag: Arc<AtomicBool>,
+    watcher_handle: Option<std::thread::JoinHandle<()>>,
+}
+
+#[async_trait::async_trait]
+impl PluginPrivate for Wasm {
+    type Config = Conf;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let configuration = init.config;
+        let module = Arc::new(ArcSwap::from_pointee(WasmModule::try_new(
+            &configuration.module,
+            configuration.fuel,
+            configuration.max_memory,
+            configuration.max_output,
+        )?));
+
+        let watched_path = configuration.module.clone();
+        let watched_module = module.clone();
+        let fuel = configuration.fuel;
+        let max_memory = configuration.max_memory;
+        let max_output = configuration.max_output;
+
+        let park_flag = Arc::new(AtomicBool::new(false));
+        let watching_flag = park_flag.clone();
+
+        let watcher_handle = std::thread::spawn(move || {
+            let reloaded_path = watched_path.clone();
+            let config = notify::Config::default()
+                .with_poll_interval(Duration::from_secs(3))
+                .with_compare_contents(true);
+            let mut watcher = PollWatcher::new(
+                move |res: Result<notify::Event, notify::Error>| match res {
+                    Ok(event) => {
+                        if matches!(
+                            event.kind,
+                            EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime))
+                                | EventKind::Modify(ModifyKind::Data(DataChange::Any))
+                                | EventKind::Create(_)
+                        ) {
+                            match WasmModule::try_new(&reloaded_path, fuel, max_memory, max_output)
+                            {
+                                Ok(module) => {
+                                    tracing::info!("updating WebAssembly module");
+                                    watched_module.store(Arc::new(module))
+                                }
+                                Err(e) => {
+                                    tracing::warn!("could not reload WebAssembly module: {}", e);
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("WebAssembly module watching event error: {:?}", e),
+                },
+                config,
+            )
+            .unwrap_or_else(|_| panic!("could not create watch on: {watched_path:?}"));
+            watcher
+                .watch(&watched_path, RecursiveMode::NonRecursive)
+                .unwrap_or_else(|_| panic!("could not watch: {watched_path:?}"));
+            // Park the thread until this plugin is dropped (see Drop impl)
+            while !watching_flag.load(Ordering::Acquire) {
+                std::thread::park();
+            }
+        });
+
+        Ok(Self {
+            client: WasmClient { module },
+            configuration,
+            sdl: init.supergraph_sdl,
+            park_flag,
+            watcher_handle: Some(watcher_handle),
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        self.configuration.router.as_service(
+            self.client.clone(),
+            service,
+            WASM_MODULE_URI.to_string(),
+            self.sdl.clone(),
+        )
+    }
+
+    fn supergraph_service(
+        &self,
+        service: services::supergraph::BoxService,
+    ) -> services::supergraph::BoxService {
+        self.configuration.supergraph.as_service(
+            self.client.clone(),
+            service,
+            WASM_MODULE_URI.to_string(),
+            self.sdl.clone(),
+        )
+    }
+
+    fn execution_service(
+        &self,
+        service: services::execution::BoxService,
+    ) -> services::execution::BoxService {
+        self.configuration.execution.as_service(
+            self.client.clone(),
+            service,
+            WASM_MODULE_URI.to_string(),
+            self.sdl.clone(),
+        )
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        self.configuration.subgraph.all.as_service(
+            self.client.clone(),
+            service,
+            WASM_MODULE_URI.to_string(),
+            name.to_string(),
+        )
+    }
+}
+
+impl Drop for Wasm {
+    fn drop(&mut self) {
+        if let Some(wh) = self.watcher_handle.take() {
+            self.park_flag.store(true, Ordering::Release);
+            wh.thread().unpark();
+            wh.join()
+                .expect("WebAssembly module watcher thread terminating");
+        }
+    }
+}
+
+register_private_plugin!("apollo", "wasm", Wasm);
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    // Echoes its input back
+    const ECHO_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32)
+            i32.const 1024)
+          (func (export "handle") (param $ptr i32) (param $len i32) (result i64)
+            local.get $ptr
+            i64.extend_i32_u
+            i64.const 32
+            i64.shl
+            local.get $len
+            i64.extend_i32_u
+            i64.or))
+    "#;
+
+    // Never returns
+    const SPIN_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32)
+            i32.const 1024)
+          (func (export "handle") (param i32 i32) (result i64)
+            (loop $spin
+              br $spin)
+            i64.const 0))
+    "#;
+
+    // Replies with the whole address space
+    const OVERSIZED_REPLY_MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32)
+            i32.const 1024)
+          (func (export "handle") (param i32 i32) (result i64)
+            i64.const 0xFFFFFFFF))
+    "#;
+
+    fn module_file(source: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new().suffix(".wat").tempfile().unwrap();
+        file.write_all(source.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn it_calls_the_module() {
+        let file = module_file(ECHO_MODULE);
+        let module = WasmModule::try_new(
+            file.path(),
+            DEFAULT_FUEL,
+            DEFAULT_MAX_MEMORY,
+            DEFAULT_MAX_OUTPUT,
+        )
+        .unwrap();
+
+        let payload = br#"{"version":1,"stage":"SubgraphRequest","control":"continue"}"#;
+        assert_eq!(module.call(payload).unwrap(), payload.to_vec());
+    }
+
+    #[test]
+    fn it_stops_modules_running_out_of_fuel() {
+        let file = module_file(SPIN_MODULE);
+        let module =
+            WasmModule::try_new(file.path(), 1_000, DEFAULT_MAX_MEMORY, DEFAULT_MAX_OUTPUT)
+                .unwrap();
+
+        assert!(module.call(b"{}").is_err());
+    }
+    #[test]
+    fn it_rejects_replies_over_the_output_limit() {
+        let file = module_file(ECHO_MODULE);
+        let module = WasmModule::try_new(file.path(), DEFAULT_FUEL, DEFAULT_MAX_MEMORY, 8).unwrap();
+
+        assert!(module.call(b"{}").is_ok());
+        let err = module.call(b"{\"version\":1}").unwrap_err();
+        assert!(err.to_string().contains("exceeds the 8 bytes limit"));
+    }
+
+    #[test]
+    fn it_rejects_replies_out_of_the_module_memory() {
+        let file = module_file(OVERSIZED_REPLY_MODULE);
+        let module =
+            WasmModule::try_new(file.path(), DEFAULT_FUEL, DEFAULT_MAX_MEMORY, usize::MAX).unwrap();
+
+        let err = module.call(b"{}").unwrap_err();
+        assert!(err.to_string().contains("out of the bounds of its memory"));
+    }
+}
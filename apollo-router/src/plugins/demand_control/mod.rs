@@ -16,6 +16,8 @@ use displaydoc::Display;
 use futures::future::Either;
 use futures::stream;
 use futures::StreamExt;
+use http::HeaderMap;
+use http::HeaderValue;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -38,6 +40,7 @@ use crate::register_plugin;
 use crate::services::execution;
 use crate::services::execution::BoxService;
 use crate::services::subgraph;
+use crate::spec::operation_limits::OperationLimits;
 use crate::Context;
 
 pub(crate) mod cost_calculator;
@@ -49,6 +52,9 @@ pub(crate) static COST_DELTA_KEY: &str = "cost.delta";
 pub(crate) static COST_RESULT_KEY: &str = "cost.result";
 pub(crate) static COST_STRATEGY_KEY: &str = "cost.strategy";
 
+const OPERATION_METRICS_HEADER_NAME: &str = "Apollo-Expose-Operation-Metrics";
+const OPERATION_METRICS_EXTENSION: &str = "operationMetrics";
+
 /// Algorithm for calculating the cost of an incoming query.
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
@@ -87,6 +93,32 @@ pub(crate) enum Mode {
     Enforce,
 }
 
+/// When to add the metrics of an operation to its response
+#[derive(Copy, Clone, Debug, Default, Deserialize, JsonSchema, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ExposeOperationMetrics {
+    /// Never add the metrics.
+    #[default]
+    Never,
+    /// Add the metrics when the request has the `Apollo-Expose-Operation-Metrics: true` header.
+    OnRequest,
+    /// Add the metrics to every response.
+    Always,
+}
+
+impl ExposeOperationMetrics {
+    fn is_enabled_for(self, headers: &HeaderMap) -> bool {
+        match self {
+            ExposeOperationMetrics::Never => false,
+            ExposeOperationMetrics::OnRequest => {
+                headers.get(OPERATION_METRICS_HEADER_NAME)
+                    == Some(&HeaderValue::from_static("true"))
+            }
+            ExposeOperationMetrics::Always => true,
+        }
+    }
+}
+
 /// Demand control configuration
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -99,6 +131,21 @@ pub(crate) struct DemandControlConfig {
     mode: Mode,
     /// The strategy used to reject requests.
     strategy: StrategyConfig,
+    /// Add the cost and the shape (depth, height, root fields and aliases) of the operation to
+    /// the `operationMetrics` extension of the response, so that clients can see how close an
+    /// operation is to the configured maximum, or why it was rejected.
+    #[serde(default)]
+    expose_operation_metrics: ExposeOperationMetrics,
+}
+
+impl DemandControlConfig {
+    fn max_cost(&self) -> Option<f64> {
+        match self.strategy {
+            StrategyConfig::StaticEstimated { max, .. } => Some(max),
+            #[cfg(test)]
+            StrategyConfig::Test { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Display, Error)]
@@ -300,6 +347,39 @@ impl DemandControl {
             "demand_control.result" = result
         );
     }
+
+    /// Metrics of the operation, added to the response extensions on request
+    fn operation_metrics(context: &Context, max_cost: Option<f64>) -> Object {
+        let mut metrics = Object::new();
+        let mut insert = |key: &'static str, value: Option<serde_json_bytes::Value>| {
+            if let Some(value) = value {
+                metrics.insert(key, value);
+            }
+        };
+        insert(
+            "estimatedCost",
+            context.get_estimated_cost().ok().flatten().map(Into::into),
+        );
+        insert(
+            "actualCost",
+            context.get_actual_cost().ok().flatten().map(Into::into),
+        );
+        insert("maxCost", max_cost.map(Into::into));
+        insert(
+            "result",
+            context.get_cost_result().ok().flatten().map(Into::into),
+        );
+        if let Some(limits) = context
+            .extensions()
+            .with_lock(|lock| lock.get::<OperationLimits<u32>>().cloned())
+        {
+            insert("depth", Some(limits.depth.into()));
+            insert("height", Some(limits.height.into()));
+            insert("rootFields", Some(limits.root_fields.into()));
+            insert("aliases", Some(limits.aliases.into()));
+        }
+        metrics
+    }
 }
 
 #[async_trait::async_trait]
@@ -332,7 +412,39 @@ impl Plugin for DemandControl {
             service
         } else {
             let strategy = self.strategy_factory.create();
+            let expose_operation_metrics = self.config.expose_operation_metrics;
+            let max_cost = self.config.max_cost();
             ServiceBuilder::new()
+                .map_future_with_request_data(
+                    move |req: &execution::Request| {
+                        expose_operation_metrics.is_enabled_for(req.supergraph_request.headers())
+                    },
+                    move |expose: bool, fut| async move {
+                        let mut resp: execution::Response = fut.await?;
+                        if expose {
+                            let context = resp.context.clone();
+                            // The metrics are only added to the primary response. They are read when it is
+                            // emitted, so that its actual cost has already been computed.
+                            resp.response = resp.response.map(move |stream| {
+                                stream
+                                    .enumerate()
+                                    .map(move |(index, mut resp)| {
+                                        if index == 0 {
+                                            resp.extensions.insert(
+                                                OPERATION_METRICS_EXTENSION,
+                                                serde_json_bytes::Value::Object(
+                                                    Self::operation_metrics(&context, max_cost),
+                                                ),
+                                            );
+                                        }
+                                        resp
+                                    })
+                                    .boxed()
+                            });
+                        }
+                        Ok::<_, BoxError>(resp)
+                    },
+                )
                 .checkpoint(move |req: execution::Request| {
                     req.context
                         .insert_demand_control_context(DemandControlContext {
@@ -501,6 +613,7 @@ mod test {
     use crate::services::layers::query_analysis::ParsedDocument;
     use crate::services::layers::query_analysis::ParsedDocumentInner;
     use crate::services::subgraph;
+    use crate::spec::operation_limits::OperationLimits;
     use crate::Context;
 
     #[tokio::test]
@@ -617,24 +730,74 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn test_expose_operation_metrics() {
+        let request = |expose: bool| {
+            let ctx = context();
+            ctx.extensions().with_lock(|mut lock| {
+                lock.insert(OperationLimits::<u32> {
+                    depth: 3,
+                    height: 5,
+                    root_fields: 1,
+                    aliases: 0,
+                })
+            });
+            let mut supergraph_request = http::Request::new(graphql::Request::default());
+            if expose {
+                supergraph_request.headers_mut().insert(
+                    "apollo-expose-operation-metrics",
+                    http::HeaderValue::from_static("true"),
+                );
+            }
+            execution::Request::fake_builder()
+                .context(ctx)
+                .supergraph_request(supergraph_request)
+                .build()
+        };
+        let config = include_str!("fixtures/expose_operation_metrics.router.yaml");
+
+        let body = test_on_execution_with_request(config, request(true)).await;
+        assert_eq!(
+            body[0].extensions.get("operationMetrics"),
+            Some(&serde_json_bytes::json!({
+                "result": "COST_ESTIMATED_TOO_EXPENSIVE",
+                "depth": 3,
+                "height": 5,
+                "rootFields": 1,
+                "aliases": 0
+            }))
+        );
+
+        let body = test_on_execution_with_request(config, request(false)).await;
+        assert!(!body[0].extensions.contains_key("operationMetrics"));
+    }
+
     async fn test_on_execution(config: &'static str) -> Vec<Response> {
+        test_on_execution_with_request(
+            config,
+            execution::Request::fake_builder()
+                .context(context())
+                .build(),
+        )
+        .await
+    }
+
+    async fn test_on_execution_with_request(
+        config: &'static str,
+        request: execution::Request,
+    ) -> Vec<Response> {
         let plugin = PluginTestHarness::<DemandControl>::builder()
             .config(config)
             .build()
             .await;
 
-        let ctx = context();
-
         let resp = plugin
-            .call_execution(
-                execution::Request::fake_builder().context(ctx).build(),
-                |req| {
-                    execution::Response::fake_builder()
-                        .context(req.context)
-                        .build()
-                        .unwrap()
-                },
-            )
+            .call_execution(request, |req| {
+                execution::Response::fake_builder()
+                    .context(req.context)
+                    .build()
+                    .unwrap()
+            })
             .await
             .unwrap();
 
@@ -1,3 +1,5 @@
+use std::ops::ControlFlow;
+
 use futures::future::ready;
 use futures::stream::once;
 use futures::StreamExt;
@@ -7,20 +9,27 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_json_bytes::json;
 use tower::BoxError;
+use tower::ServiceBuilder;
 use tower::ServiceExt as TowerServiceExt;
 
+use crate::layers::ServiceBuilderExt;
 use crate::layers::ServiceExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::query_planner::FETCH_TIMINGS_CONTEXT_KEY;
 use crate::register_plugin;
 use crate::services::execution;
 use crate::services::supergraph;
 
 const EXPOSE_QUERY_PLAN_HEADER_NAME: &str = "Apollo-Expose-Query-Plan";
+const EXPOSE_FETCH_TIMINGS_HEADER_NAME: &str = "Apollo-Expose-Fetch-Timings";
 const ENABLE_EXPOSE_QUERY_PLAN_ENV: &str = "APOLLO_EXPOSE_QUERY_PLAN";
 const QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.plan";
 const FORMATTED_QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.formatted_plan";
 const ENABLED_CONTEXT_KEY: &str = "experimental::expose_query_plan.enabled";
+/// Header value to get the query plan without executing the operation
+const DRY_RUN_HEADER_VALUE: &str = "dry-run";
+const DRY_RUN_CONTEXT_KEY: &str = "experimental::expose_query_plan.dry_run";
 
 #[derive(Debug, Clone)]
 struct ExposeQueryPlan {
@@ -47,8 +56,8 @@ impl Plugin for ExposeQueryPlan {
     }
 
     fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
-        service
-            .map_request(move |req: execution::Request| {
+        ServiceBuilder::new()
+            .checkpoint(move |req: execution::Request| {
                 if req
                     .context
                     .get::<_, bool>(ENABLED_CONTEXT_KEY)
@@ -67,8 +76,23 @@ impl Plugin for ExposeQueryPlan {
                         .unwrap();
                 }
 
-                req
+                // In dry run mode, the query plan is returned without being executed
+                if req
+                    .context
+                    .get::<_, bool>(DRY_RUN_CONTEXT_KEY)
+                    .ok()
+                    .flatten()
+                    .is_some()
+                {
+                    let res = execution::Response::builder()
+                        .context(req.context)
+                        .build()?;
+                    return Ok(ControlFlow::Break(res));
+                }
+
+                Ok(ControlFlow::Continue(req))
             })
+            .service(service)
             .boxed()
     }
 
@@ -76,18 +100,27 @@ impl Plugin for ExposeQueryPlan {
         let conf_enabled = self.enabled;
         service
             .map_future_with_request_data(move |req: &supergraph::Request| {
-                let is_enabled = conf_enabled && req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME) == Some(&HeaderValue::from_static("true"));
+                let expose_query_plan = req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME);
+                let is_dry_run = conf_enabled && expose_query_plan == Some(&HeaderValue::from_static(DRY_RUN_HEADER_VALUE));
+                let is_enabled = is_dry_run || (conf_enabled && expose_query_plan == Some(&HeaderValue::from_static("true")));
                 if is_enabled {
                     req.context.insert(ENABLED_CONTEXT_KEY, true).unwrap();
                 }
+                if is_dry_run {
+                    req.context.insert(DRY_RUN_CONTEXT_KEY, true).unwrap();
+                }
+                let timings_enabled = conf_enabled && req.supergraph_request.headers().get(EXPOSE_FETCH_TIMINGS_HEADER_NAME) == Some(&HeaderValue::from_static("true"));
+                if timings_enabled {
+                    req.context.insert_json_value(FETCH_TIMINGS_CONTEXT_KEY, json!([]));
+                }
 
-                is_enabled
-            }, move | is_enabled: bool, f| async move {
+                (is_enabled, timings_enabled)
+            }, move | (is_enabled, timings_enabled): (bool, bool), f| async move {
                 let mut res: supergraph::ServiceResult = f.await;
 
                 res = match res {
                     Ok(mut res) => {
-                        if is_enabled {
+                        if is_enabled || timings_enabled {
                             let (parts, stream) = res.response.into_parts();
                             let (mut first, rest) = stream.into_future().await;
 
@@ -99,6 +132,11 @@ impl Plugin for ExposeQueryPlan {
                                         .extensions
                                         .insert("apolloQueryPlan", json!({ "object": { "kind": "QueryPlan", "node": plan }, "text": res.context.get_json_value(FORMATTED_QUERY_PLAN_CONTEXT_KEY) }));
                                 }
+                                if let Some(timings) =
+                                    res.context.get_json_value(FETCH_TIMINGS_CONTEXT_KEY)
+                                {
+                                    first.extensions.insert("apolloFetchTimings", timings);
+                                }
                             }
                             res.response = http::Response::from_parts(
                                 parts,
@@ -248,6 +286,97 @@ mod tests {
         insta::assert_json_snapshot!(serde_json::to_value(response).unwrap());
     }
 
+    #[tokio::test]
+    async fn it_exposes_fetch_timings() {
+        let mut supergraph_service = build_mock_supergraph(serde_json::json! {{
+            "plugins": {
+                "experimental.expose_query_plan": true
+            },
+            "supergraph": {
+                // TODO(@goto-bus-stop): need to update the mocks and remove this, #6013
+                "generate_query_fragments": false,
+            }
+        }})
+        .await;
+        let request = supergraph::Request::fake_builder()
+            .query(VALID_QUERY.to_string())
+            .variable("first", 2usize)
+            .header(EXPOSE_FETCH_TIMINGS_HEADER_NAME, "true")
+            .build()
+            .expect("expecting valid request");
+        let response = supergraph_service
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap();
+
+        // the query plan is only exposed if requested
+        assert!(response.extensions.get("apolloQueryPlan").is_none());
+        let timings = response
+            .extensions
+            .get("apolloFetchTimings")
+            .and_then(|timings| timings.as_array())
+            .expect("fetch timings should be exposed");
+        let mut services: Vec<_> = timings
+            .iter()
+            .map(|timing| {
+                assert!(timing.get("startOffsetNanos").unwrap().is_u64());
+                assert!(timing.get("durationNanos").unwrap().is_u64());
+                timing.get("service").unwrap().as_str().unwrap()
+            })
+            .collect();
+        services.sort();
+        assert_eq!(services, ["accounts", "products", "products", "reviews"]);
+    }
+
+    #[tokio::test]
+    async fn it_exposes_query_plan_without_executing_in_dry_run_mode() {
+        let mut supergraph_service = build_mock_supergraph(serde_json::json! {{
+            "plugins": {
+                "experimental.expose_query_plan": true
+            },
+            "supergraph": {
+                // TODO(@goto-bus-stop): need to update the mocks and remove this, #6013
+                "generate_query_fragments": false,
+            }
+        }})
+        .await;
+        // The subgraph mocks would answer with errors to the requests of this operation
+        let request = supergraph::Request::fake_builder()
+            .query("{ me { name } }".to_string())
+            .header(EXPOSE_QUERY_PLAN_HEADER_NAME, DRY_RUN_HEADER_VALUE)
+            .build()
+            .expect("expecting valid request");
+        let response = supergraph_service
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap();
+
+        assert!(matches!(response.data, None | Some(Value::Null)));
+        assert!(response.errors.is_empty());
+        let plan = response
+            .extensions
+            .get("apolloQueryPlan")
+            .expect("query plan should be exposed");
+        assert_eq!(plan["object"]["node"]["kind"], "Fetch");
+        assert_eq!(plan["object"]["node"]["serviceName"], "accounts");
+        assert!(plan["text"]
+            .as_str()
+            .unwrap()
+            .contains("Fetch(service: \"accounts\")"));
+    }
+
     #[tokio::test]
     async fn it_doesnt_expose_query_plan() {
         let supergraph = build_mock_supergraph(serde_json::json! {{
@@ -347,6 +347,11 @@ async fn subgraph_layer(mut req: subgraph::Request) -> subgraph::Request {
 static APOLLO_REQUIRE_PREFLIGHT: HeaderName = HeaderName::from_static("apollo-require-preflight");
 static TRUE: http::HeaderValue = HeaderValue::from_static("true");
 
+/// Whether the files of a client request are sent with this subgraph request
+pub(crate) fn has_file_uploads<B>(req: &http::Request<B>) -> bool {
+    req.extensions().get::<MultipartFormData>().is_some()
+}
+
 pub(crate) async fn http_request_wrapper(
     mut req: http::Request<RouterBody>,
 ) -> http::Request<RouterBody> {
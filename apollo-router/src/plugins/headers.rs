@@ -1,10 +1,13 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 
 use access_json::JSONQuery;
+use bytesize::ByteSize;
 use http::header::HeaderName;
 use http::header::ACCEPT;
 use http::header::ACCEPT_ENCODING;
@@ -19,7 +22,9 @@ use http::header::TE;
 use http::header::TRAILER;
 use http::header::TRANSFER_ENCODING;
 use http::header::UPGRADE;
+use http::HeaderMap;
 use http::HeaderValue;
+use http::StatusCode;
 use regex::Regex;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -30,6 +35,8 @@ use tower::ServiceBuilder;
 use tower::ServiceExt;
 use tower_service::Service;
 
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
 use crate::plugin::serde::deserialize_header_name;
 use crate::plugin::serde::deserialize_header_value;
 use crate::plugin::serde::deserialize_json_query;
@@ -39,6 +46,7 @@ use crate::plugin::serde::deserialize_regex;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
 use crate::register_plugin;
+use crate::services::router;
 use crate::services::subgraph;
 use crate::services::SubgraphRequest;
 
@@ -188,12 +196,124 @@ struct Config {
     all: Option<HeadersLocation>,
     /// Rules to specific subgraphs
     subgraphs: HashMap<String, HeadersLocation>,
+    /// Limits and normalization of the headers of client requests, applied before the rules
+    client_request: ClientRequestHeaders,
+}
+
+/// Limits and normalization of the headers of client requests
+#[derive(Clone, JsonSchema, Default, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+struct ClientRequestHeaders {
+    /// Maximum number of headers of a request, counting each value of a repeated header.
+    /// Requests with more headers are rejected with a 431 status code.
+    max_count: Option<NonZeroUsize>,
+    /// Maximum total size of the names and values of the headers of a request.
+    /// Requests with larger headers are rejected with a 431 status code.
+    #[schemars(with = "Option<String>", default)]
+    max_size: Option<ByteSize>,
+    /// Maximum size of a header value.
+    /// Requests with a larger header value are rejected with a 431 status code.
+    #[schemars(with = "Option<String>", default)]
+    max_value_size: Option<ByteSize>,
+    /// Remove the hop-by-hop headers, which are meant for the connection between the client and
+    /// the router, and the duplicate values of repeated headers. Header names are always
+    /// lowercase.
+    normalize: bool,
+}
+
+impl ClientRequestHeaders {
+    fn is_enabled(&self) -> bool {
+        self.max_count.is_some()
+            || self.max_size.is_some()
+            || self.max_value_size.is_some()
+            || self.normalize
+    }
+
+    /// Returns an error message if the headers exceed a limit
+    fn check(&self, headers: &HeaderMap) -> Result<(), String> {
+        if let Some(max_count) = self.max_count {
+            if headers.len() > max_count.get() {
+                return Err(format!(
+                    "the request has {} headers, more than the maximum of {max_count}",
+                    headers.len()
+                ));
+            }
+        }
+        if let Some(max_value_size) = self.max_value_size {
+            if let Some((name, _)) = headers
+                .iter()
+                .find(|(_, value)| value.len() as u64 > max_value_size.as_u64())
+            {
+                return Err(format!(
+                    "the value of the '{name}' header is larger than the maximum of {max_value_size}"
+                ));
+            }
+        }
+        if let Some(max_size) = self.max_size {
+            let size: usize = headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum();
+            if size as u64 > max_size.as_u64() {
+                return Err(format!(
+                    "the request headers are larger than the maximum of {max_size}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Headers from https://datatracker.ietf.org/doc/html/rfc2616#section-13.5.1
+static HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    PROXY_AUTHENTICATE,
+    PROXY_AUTHORIZATION,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    UPGRADE,
+];
+
+/// Removes the hop-by-hop headers, including the ones listed in the `connection` header, and
+/// the duplicate values of repeated headers
+fn normalize_headers(headers: &mut HeaderMap) {
+    let connection_headers: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in HOP_BY_HOP_HEADERS.iter().chain(&connection_headers) {
+        headers.remove(name);
+    }
+
+    let names: Vec<HeaderName> = headers.keys().cloned().collect();
+    for name in names {
+        let mut values: Vec<HeaderValue> = Vec::new();
+        let mut count = 0;
+        for value in headers.get_all(&name) {
+            count += 1;
+            if !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+        if values.len() < count {
+            headers.remove(&name);
+            for value in values {
+                headers.append(name.clone(), value);
+            }
+        }
+    }
 }
 
 struct Headers {
     all_operations: Arc<Vec<Operation>>,
     subgraph_operations: HashMap<String, Arc<Vec<Operation>>>,
     reserved_headers: Arc<HashSet<&'static HeaderName>>,
+    client_request: ClientRequestHeaders,
 }
 
 #[async_trait::async_trait]
@@ -222,9 +342,44 @@ impl Plugin for Headers {
             all_operations: Arc::new(operations),
             subgraph_operations,
             reserved_headers: Arc::new(RESERVED_HEADERS.iter().collect()),
+            client_request: init.config.client_request,
         })
     }
 
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        if !self.client_request.is_enabled() {
+            return service;
+        }
+        let client_request = self.client_request.clone();
+        ServiceBuilder::new()
+            .checkpoint(move |mut req: router::Request| {
+                if let Err(message) = client_request.check(req.router_request.headers()) {
+                    u64_counter!(
+                        "apollo.router.headers.rejected",
+                        "Number of requests rejected because their headers exceed a limit",
+                        1
+                    );
+                    let res = router::Response::error_builder()
+                        .status_code(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+                        .error(
+                            graphql::Error::builder()
+                                .message(message)
+                                .extension_code("REQUEST_HEADERS_TOO_LARGE")
+                                .build(),
+                        )
+                        .context(req.context)
+                        .build()?;
+                    return Ok(ControlFlow::Break(res));
+                }
+                if client_request.normalize {
+                    normalize_headers(req.router_request.headers_mut());
+                }
+                Ok(ControlFlow::Continue(req))
+            })
+            .service(service)
+            .boxed()
+    }
+
     fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
         ServiceBuilder::new()
             .layer(HeadersLayer::new(
@@ -460,6 +615,7 @@ mod test {
 
     use super::*;
     use crate::graphql::Request;
+    use crate::plugin::test::MockRouterService;
     use crate::plugin::test::MockSubgraphService;
     use crate::plugins::headers::Config;
     use crate::plugins::headers::HeadersLayer;
@@ -1050,6 +1206,94 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_client_request_limits() {
+        let mut headers = HeaderMap::new();
+        headers.insert("aa", HeaderValue::from_static("vaa"));
+        headers.append("ab", HeaderValue::from_static("vab"));
+        headers.append("ab", HeaderValue::from_static("vab2"));
+
+        let limits = ClientRequestHeaders {
+            max_count: NonZeroUsize::new(3),
+            max_size: Some(ByteSize::b(16)),
+            max_value_size: Some(ByteSize::b(4)),
+            normalize: false,
+        };
+        assert!(limits.check(&headers).is_ok());
+
+        headers.insert("ac", HeaderValue::from_static("v"));
+        assert!(limits.check(&headers).unwrap_err().contains("4 headers"));
+        headers.remove("ac");
+
+        headers.insert("aa", HeaderValue::from_static("vaaaa"));
+        assert!(limits.check(&headers).unwrap_err().contains("'aa' header"));
+
+        headers.insert("aa", HeaderValue::from_static("vaaa"));
+        assert!(limits
+            .check(&headers)
+            .unwrap_err()
+            .contains("headers are larger"));
+    }
+
+    #[test]
+    fn test_normalize_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive, x-hop"));
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert("x-hop", HeaderValue::from_static("vhop"));
+        headers.insert(PROXY_AUTHORIZATION, HeaderValue::from_static("secret"));
+        headers.append("da", HeaderValue::from_static("vda"));
+        headers.append("da", HeaderValue::from_static("vda"));
+        headers.append("da", HeaderValue::from_static("vda2"));
+        headers.insert("db", HeaderValue::from_static("vdb"));
+
+        normalize_headers(&mut headers);
+        let headers: Vec<_> = headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+            .collect();
+        assert_eq!(headers, vec![("da", "vda"), ("da", "vda2"), ("db", "vdb")]);
+    }
+
+    #[tokio::test]
+    async fn test_client_request_rejected() -> Result<(), BoxError> {
+        let plugin = Headers {
+            all_operations: Default::default(),
+            subgraph_operations: Default::default(),
+            reserved_headers: Arc::new(RESERVED_HEADERS.iter().collect()),
+            client_request: ClientRequestHeaders {
+                max_count: NonZeroUsize::new(1),
+                ..Default::default()
+            },
+        };
+        let mut mock = MockRouterService::new();
+        mock.expect_call()
+            .times(1)
+            .returning(|req: router::Request| {
+                router::Response::fake_builder()
+                    .context(req.context)
+                    .build()
+            });
+        let mut service = plugin.router_service(mock.boxed());
+
+        let request = router::Request::fake_builder()
+            .header("aa", "vaa")
+            .build()?;
+        let response = service.ready().await?.call(request).await?;
+        assert_eq!(response.response.status(), StatusCode::OK);
+
+        let request = router::Request::fake_builder()
+            .header("aa", "vaa")
+            .header("ab", "vab")
+            .build()?;
+        let response = service.ready().await?.call(request).await?;
+        assert_eq!(
+            response.response.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+        Ok(())
+    }
+
     impl SubgraphRequest {
         fn assert_headers(&self, headers: Vec<(&'static str, &'static str)>) -> bool {
             let mut headers = headers.clone();
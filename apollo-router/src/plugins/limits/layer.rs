@@ -6,16 +6,24 @@ use std::task::Poll;
 
 use displaydoc::Display;
 use futures::FutureExt;
+use once_cell::sync::OnceCell;
 use pin_project_lite::pin_project;
 use tokio::sync::AcquireError;
 use tokio::sync::OwnedSemaphorePermit;
 use tower::Layer;
 use tower_service::Service;
 
-#[derive(thiserror::Error, Debug, Display)]
+use crate::plugins::limits::scanner::FieldLimits;
+use crate::plugins::limits::scanner::RequestScanner;
+
+#[derive(thiserror::Error, Debug, Display, Clone, Copy)]
 pub(super) enum BodyLimitError {
     /// Request body payload too large
     PayloadTooLarge,
+    /// Request query too large
+    QueryTooLarge,
+    /// Request variables too large
+    VariablesTooLarge,
 }
 
 struct BodyLimitControlInner {
@@ -77,14 +85,23 @@ impl BodyLimitControl {
 pub(crate) struct RequestBodyLimitLayer<Body> {
     _phantom: std::marker::PhantomData<Body>,
     control: BodyLimitControl,
+    field_limits: FieldLimits,
 }
 impl<Body> RequestBodyLimitLayer<Body> {
     pub(crate) fn new(control: BodyLimitControl) -> Self {
         Self {
             _phantom: Default::default(),
             control,
+            field_limits: FieldLimits::default(),
         }
     }
+
+    /// Also limits the size of the `query` and `variables` of the GraphQL requests, while the
+    /// body is read
+    pub(super) fn field_limits(mut self, field_limits: FieldLimits) -> Self {
+        self.field_limits = field_limits;
+        self
+    }
 }
 
 impl<Body, S> Layer<S> for RequestBodyLimitLayer<Body>
@@ -95,7 +112,7 @@ where
     type Service = RequestBodyLimit<Body, S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        RequestBodyLimit::new(inner, self.control.clone())
+        RequestBodyLimit::new(inner, self.control.clone(), self.field_limits)
     }
 }
 
@@ -103,6 +120,7 @@ pub(crate) struct RequestBodyLimit<Body, S> {
     _phantom: std::marker::PhantomData<Body>,
     inner: S,
     control: BodyLimitControl,
+    field_limits: FieldLimits,
 }
 
 impl<Body, S> RequestBodyLimit<Body, S>
@@ -110,11 +128,12 @@ where
     S: Service<http::request::Request<super::limited::Limited<Body>>>,
     Body: http_body::Body,
 {
-    fn new(inner: S, control: BodyLimitControl) -> Self {
+    fn new(inner: S, control: BodyLimitControl, field_limits: FieldLimits) -> Self {
         Self {
             _phantom: Default::default(),
             inner,
             control,
+            field_limits,
         }
     }
 }
@@ -162,14 +181,22 @@ where
             .try_acquire_owned()
             .expect("abort lock is new, qed");
 
-        let f =
-            self.inner.call(req.map(|body| {
-                super::limited::Limited::new(body, self.control.clone(), owned_permit)
-            }));
+        // The limit that was hit, if it is not the size of the body
+        let exceeded = Arc::new(OnceCell::new());
+        let scanner = self
+            .field_limits
+            .is_enabled()
+            .then(|| RequestScanner::new(self.field_limits));
+
+        let f = self.inner.call(req.map(|body| {
+            super::limited::Limited::new(body, self.control.clone(), owned_permit)
+                .scanning(scanner, exceeded.clone())
+        }));
 
         ResponseFuture::Continue {
             inner: f,
             abort: abort.acquire_owned().boxed(),
+            exceeded,
         }
     }
 }
@@ -184,6 +211,8 @@ pin_project! {
 
             #[pin]
             abort: futures::future::BoxFuture<'static, Result<OwnedSemaphorePermit, AcquireError>>,
+
+            exceeded: Arc<OnceCell<BodyLimitError>>,
         }
     }
 }
@@ -202,15 +231,21 @@ where
             // Content-length header exceeded, eager reject
             ResponseFutureProj::Reject => Poll::Ready(Err(BodyLimitError::PayloadTooLarge.into())),
             // Continue processing the request
-            ResponseFutureProj::Continue { inner, abort, .. } => {
+            ResponseFutureProj::Continue {
+                inner,
+                abort,
+                exceeded,
+            } => {
                 match inner.poll(cx) {
                     Poll::Ready(r) => Poll::Ready(r),
                     Poll::Pending => {
                         // Check to see if the stream limit has been hit
                         match abort.poll(cx) {
-                            Poll::Ready(_) => {
-                                Poll::Ready(Err(BodyLimitError::PayloadTooLarge.into()))
-                            }
+                            Poll::Ready(_) => Poll::Ready(Err(exceeded
+                                .get()
+                                .copied()
+                                .unwrap_or(BodyLimitError::PayloadTooLarge)
+                                .into())),
                             Poll::Pending => Poll::Pending,
                         }
                     }
@@ -1,14 +1,18 @@
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 
 use bytes::Buf;
 use http::HeaderMap;
 use http_body::SizeHint;
+use once_cell::sync::OnceCell;
 use pin_project_lite::pin_project;
 use tokio::sync::OwnedSemaphorePermit;
 
 use crate::plugins::limits::layer::BodyLimitControl;
+use crate::plugins::limits::layer::BodyLimitError;
+use crate::plugins::limits::scanner::RequestScanner;
 
 pin_project! {
     /// An implementation of http_body::Body that limits the number of bytes read from the inner body.
@@ -20,6 +24,8 @@ pin_project! {
         #[pin]
         permit: ForgetfulPermit,
         control: BodyLimitControl,
+        scanner: Option<RequestScanner>,
+        exceeded: Arc<OnceCell<BodyLimitError>>,
     }
 }
 
@@ -36,8 +42,22 @@ where
             inner,
             control,
             permit: permit.into(),
+            scanner: None,
+            exceeded: Default::default(),
         }
     }
+
+    /// Scans the body to reject the requests exceeding the field limits. The limit that was
+    /// exceeded is recorded for the containing layer.
+    pub(super) fn scanning(
+        mut self,
+        scanner: Option<RequestScanner>,
+        exceeded: Arc<OnceCell<BodyLimitError>>,
+    ) -> Self {
+        self.scanner = scanner;
+        self.exceeded = exceeded;
+        self
+    }
 }
 
 struct ForgetfulPermit(Option<OwnedSemaphorePermit>);
@@ -89,6 +109,16 @@ where
                     this.permit.release();
                     return Poll::Pending;
                 } else {
+                    // The data of request bodies is contiguous
+                    if let Some(Err(error)) = this
+                        .scanner
+                        .as_mut()
+                        .map(|scanner| scanner.scan(data.chunk()))
+                    {
+                        let _ = this.exceeded.set(error);
+                        this.permit.release();
+                        return Poll::Pending;
+                    }
                     this.control.increment(data.remaining());
                     Some(Ok(data))
                 }
@@ -1,10 +1,14 @@
 mod layer;
 mod limited;
+pub(crate) mod response_size;
+mod scanner;
 
 use std::error::Error;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use bytesize::ByteSize;
+use futures::StreamExt;
 use http::StatusCode;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -20,6 +24,9 @@ use crate::plugin::PluginInit;
 use crate::plugins::limits::layer::BodyLimitControl;
 use crate::plugins::limits::layer::BodyLimitError;
 use crate::plugins::limits::layer::RequestBodyLimitLayer;
+use crate::plugins::limits::response_size::ResponseSizeLimit;
+use crate::plugins::limits::response_size::ResponseSizeLimitError;
+use crate::plugins::limits::scanner::FieldLimits;
 use crate::services::router;
 use crate::services::router::BoxService;
 use crate::Context;
@@ -103,6 +110,18 @@ pub(crate) struct Config {
     /// to protect against running out of memory. Default: 2000000 (2 MB)
     pub(crate) http_max_request_bytes: usize,
 
+    /// Limit the size of the `query` string of incoming GraphQL requests, in bytes of the
+    /// request body, including those of each request of a batch.
+    /// Requests exceeding it are rejected with a HTTP 413 Payload Too Large response as soon as
+    /// the limit is reached, without reading the rest of the body. Default: no limit
+    pub(crate) http_max_query_bytes: Option<usize>,
+
+    /// Limit the size of the `variables` of incoming GraphQL requests, in bytes of the
+    /// request body, including those of each request of a batch.
+    /// Requests exceeding it are rejected with a HTTP 413 Payload Too Large response as soon as
+    /// the limit is reached, without reading the rest of the body. Default: no limit
+    pub(crate) http_max_variables_bytes: Option<usize>,
+
     /// Limit the maximum number of headers of incoming HTTP1 requests. Default is 100.
     ///
     /// If router receives more headers than the buffer size, it responds to the client with
@@ -115,6 +134,26 @@ pub(crate) struct Config {
     /// Default is ~400kib.
     #[schemars(with = "Option<String>", default)]
     pub(crate) http1_max_request_buf_size: Option<ByteSize>,
+
+    /// Limit the size of each subgraph response body read from the network,
+    /// to protect against running out of memory. Default: no limit
+    pub(crate) http_max_subgraph_response_bytes: Option<usize>,
+
+    /// Limit the total size of the subgraph response bodies read for a client request,
+    /// to protect against running out of memory while assembling the response. The response
+    /// sent to the client is limited by `http_max_response_bytes`. Default: no limit
+    pub(crate) http_max_total_subgraph_response_bytes: Option<usize>,
+
+    /// Limit the size of the serialized response sent to the client. Responses exceeding it are
+    /// replaced with a GraphQL error, and streamed responses are aborted once they exceed it.
+    /// Default: no limit
+    pub(crate) http_max_response_bytes: Option<usize>,
+
+    /// Close client connections that do not accept response data for this long,
+    /// to protect against slow-reading clients. Default: no timeout
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) http_write_timeout: Option<Duration>,
 }
 
 impl Default for Config {
@@ -127,8 +166,14 @@ impl Default for Config {
             max_aliases: None,
             warn_only: false,
             http_max_request_bytes: 2_000_000,
+            http_max_query_bytes: None,
+            http_max_variables_bytes: None,
             http1_max_request_headers: None,
             http1_max_request_buf_size: None,
+            http_max_subgraph_response_bytes: None,
+            http_max_total_subgraph_response_bytes: None,
+            http_max_response_bytes: None,
+            http_write_timeout: None,
             parser_max_tokens: 15_000,
 
             // This is `apollo-parser`’s default, which protects against stack overflow
@@ -159,22 +204,42 @@ impl Plugin for LimitsPlugin {
     fn router_service(&self, service: BoxService) -> BoxService {
         let control = BodyLimitControl::new(self.config.http_max_request_bytes);
         let control_for_context = control.clone();
+        let subgraph_response_limit = self.config.http_max_subgraph_response_bytes;
+        let total_subgraph_response_limit = self.config.http_max_total_subgraph_response_bytes;
+        let response_limit = self.config.http_max_response_bytes;
+        let field_limits = FieldLimits {
+            max_query_bytes: self.config.http_max_query_bytes,
+            max_variables_bytes: self.config.http_max_variables_bytes,
+        };
         ServiceBuilder::new()
             .map_request(move |r: router::Request| {
                 let control_for_context = control_for_context.clone();
-                r.context
-                    .extensions()
-                    .with_lock(|mut lock| lock.insert(control_for_context));
+                r.context.extensions().with_lock(|mut lock| {
+                    lock.insert(control_for_context);
+                    if subgraph_response_limit.is_some() || total_subgraph_response_limit.is_some()
+                    {
+                        lock.insert(ResponseSizeLimit::new(
+                            subgraph_response_limit,
+                            total_subgraph_response_limit,
+                        ));
+                    }
+                });
                 r
             })
             .map_future_with_request_data(
                 |r: &router::Request| r.context.clone(),
-                |ctx, f| async { Self::map_error_to_graphql(f.await, ctx) },
+                move |ctx, f| async move {
+                    let response = Self::map_error_to_graphql(f.await, ctx);
+                    match response_limit {
+                        Some(limit) => response.map(|r| Self::limit_response_size(r, limit)),
+                        None => response,
+                    }
+                },
             )
             // Here we need to convert to and from the underlying http request types so that we can use existing middleware.
             .map_request(Into::into)
             .map_response(Into::into)
-            .layer(RequestBodyLimitLayer::new(control))
+            .layer(RequestBodyLimitLayer::new(control).field_limits(field_limits))
             .map_request(Into::into)
             .map_response(Into::into)
             .service(service)
@@ -194,7 +259,7 @@ impl LimitsPlugin {
         match resp {
             Ok(r) => {
                 if r.response.status() == StatusCode::PAYLOAD_TOO_LARGE {
-                    Self::increment_legacy_metric();
+                    Self::increment_legacy_metric(BodyLimitError::PayloadTooLarge);
                     Ok(BodyLimitError::PayloadTooLarge.into_response(ctx))
                 } else {
                     Ok(r)
@@ -209,16 +274,52 @@ impl LimitsPlugin {
 
                 match root_cause.downcast_ref::<BodyLimitError>() {
                     None => Err(e),
-                    Some(_) => {
-                        Self::increment_legacy_metric();
-                        Ok(BodyLimitError::PayloadTooLarge.into_response(ctx))
+                    Some(error) => {
+                        Self::increment_legacy_metric(*error);
+                        Ok(error.into_response(ctx))
                     }
                 }
             }
         }
     }
 
-    fn increment_legacy_metric() {
+    fn limit_response_size(response: router::Response, limit: usize) -> router::Response {
+        let router::Response { response, context } = response;
+        let (parts, body) = response.into_parts();
+
+        // Bodies of a known size were serialized at once, they can still be replaced
+        if let Some(size) = http_body::Body::size_hint(&body).exact() {
+            if size > limit as u64 {
+                tracing::warn!(size, limit, "response exceeded the size limit");
+                return ResponseSizeLimitError::ResponseTooLarge(limit).into_response(context);
+            }
+            return router::Response {
+                response: http::Response::from_parts(parts, body),
+                context,
+            };
+        }
+
+        // Streamed bodies, such as deferred responses and subscriptions, already sent their
+        // headers, they are aborted once they exceed the limit
+        let mut sent = 0;
+        let body = router::Body::wrap_stream(body.map(move |chunk| {
+            let chunk = chunk?;
+            sent += chunk.len();
+            if sent > limit {
+                tracing::warn!(limit, "streamed response exceeded the size limit");
+                return Err(BoxError::from(ResponseSizeLimitError::ResponseTooLarge(
+                    limit,
+                )));
+            }
+            Ok(chunk)
+        }));
+        router::Response {
+            response: http::Response::from_parts(parts, body),
+            context,
+        }
+    }
+
+    fn increment_legacy_metric(error: BodyLimitError) {
         // Remove this eventually
         // This is already handled by the telemetry plugin via the http.server.request metric.
         u64_counter!(
@@ -226,27 +327,41 @@ impl LimitsPlugin {
             "Total number of HTTP requests made.",
             1,
             status = StatusCode::PAYLOAD_TOO_LARGE.as_u16() as i64,
-            error = BodyLimitError::PayloadTooLarge.to_string()
+            error = error.to_string()
         );
     }
 }
 
 impl BodyLimitError {
     fn into_response(self, ctx: Context) -> router::Response {
-        match self {
-            BodyLimitError::PayloadTooLarge => router::Response::error_builder()
-                .error(
-                    graphql::Error::builder()
-                        .message(self.to_string())
-                        .extension_code("INVALID_GRAPHQL_REQUEST")
-                        .extension("details", self.to_string())
-                        .build(),
-                )
-                .status_code(StatusCode::PAYLOAD_TOO_LARGE)
-                .context(ctx)
-                .build()
-                .unwrap(),
-        }
+        router::Response::error_builder()
+            .error(
+                graphql::Error::builder()
+                    .message(self.to_string())
+                    .extension_code("INVALID_GRAPHQL_REQUEST")
+                    .extension("details", self.to_string())
+                    .build(),
+            )
+            .status_code(StatusCode::PAYLOAD_TOO_LARGE)
+            .context(ctx)
+            .build()
+            .unwrap()
+    }
+}
+
+impl ResponseSizeLimitError {
+    fn into_response(self, ctx: Context) -> router::Response {
+        router::Response::error_builder()
+            .error(
+                graphql::Error::builder()
+                    .message(self.to_string())
+                    .extension_code("RESPONSE_TOO_LARGE")
+                    .build(),
+            )
+            .status_code(StatusCode::INTERNAL_SERVER_ERROR)
+            .context(ctx)
+            .build()
+            .unwrap()
     }
 }
 
@@ -439,6 +554,78 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_query_limit_exceeded() {
+        let plugin: PluginTestHarness<LimitsPlugin> = PluginTestHarness::new(
+            Some(include_str!("fixtures/field_limits.router.yaml")),
+            None,
+        )
+        .await;
+        let resp = plugin
+            .call_router(
+                router::Request::fake_builder()
+                    .body(r#"{"query": "{ me { name id } }", "variables": {}}"#)
+                    .build()
+                    .unwrap(),
+                |r| async {
+                    let body = r.router_request.into_body();
+                    let _ = get_body_bytes(body).await?;
+                    panic!("should have failed to read stream")
+                },
+            )
+            .await;
+        let resp = resp.unwrap();
+        assert_eq!(resp.response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            String::from_utf8(
+                get_body_bytes(resp.response.into_body())
+                    .await
+                    .unwrap()
+                    .to_vec()
+            )
+            .unwrap(),
+            "{\"errors\":[{\"message\":\"Request query too large\",\"extensions\":{\"details\":\"Request query too large\",\"code\":\"INVALID_GRAPHQL_REQUEST\"}}]}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_limit_exceeded() {
+        let plugin: PluginTestHarness<LimitsPlugin> =
+            PluginTestHarness::new(Some("limits:\n  http_max_response_bytes: 10"), None).await;
+        let resp = plugin
+            .call_router(
+                router::Request::fake_builder().body("").build().unwrap(),
+                |_| async {
+                    Ok(router::Response::fake_builder()
+                        .data(serde_json_bytes::json!({"field": "a value over the limit"}))
+                        .build()
+                        .unwrap())
+                },
+            )
+            .await;
+        let resp = resp.unwrap();
+        assert_eq!(resp.response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            String::from_utf8(
+                get_body_bytes(resp.response.into_body())
+                    .await
+                    .unwrap()
+                    .to_vec()
+            )
+            .unwrap(),
+            "{\"errors\":[{\"message\":\"response exceeded the limit of 10 bytes\",\"extensions\":{\"code\":\"RESPONSE_TOO_LARGE\"}}]}"
+        );
+
+        // Responses within the limit are sent as they are
+        let resp = plugin
+            .call_router(
+                router::Request::fake_builder().body("").build().unwrap(),
+                |_| async { Ok(router::Response::fake_builder().build().unwrap()) },
+            )
+            .await;
+        assert_eq!(resp.unwrap().response.status(), StatusCode::OK);
+    }
+
     async fn plugin() -> PluginTestHarness<LimitsPlugin> {
         let plugin: PluginTestHarness<LimitsPlugin> = PluginTestHarness::new(
             Some(include_str!("fixtures/content_length_limit.router.yaml")),
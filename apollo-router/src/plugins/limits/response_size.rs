@@ -0,0 +1,77 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use displaydoc::Display;
+
+#[derive(thiserror::Error, Debug, Display, PartialEq)]
+pub(crate) enum ResponseSizeLimitError {
+    /// subgraph response body exceeded the limit of {0} bytes
+    SubgraphResponseTooLarge(usize),
+    /// subgraph responses of the request exceeded the limit of {0} bytes
+    TotalSubgraphResponsesTooLarge(usize),
+    /// response exceeded the limit of {0} bytes
+    ResponseTooLarge(usize),
+}
+
+/// Limits on the size of the subgraph responses read for a client request.
+///
+/// It is stored in the context extensions and checked while the subgraph response bodies are
+/// read, so that oversized payloads are never fully buffered.
+#[derive(Clone, Debug)]
+pub(crate) struct ResponseSizeLimit {
+    subgraph_max_bytes: Option<usize>,
+    total_max_bytes: Option<usize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl ResponseSizeLimit {
+    pub(crate) fn new(subgraph_max_bytes: Option<usize>, total_max_bytes: Option<usize>) -> Self {
+        Self {
+            subgraph_max_bytes,
+            total_max_bytes,
+            total: Default::default(),
+        }
+    }
+
+    /// Accounts for a chunk of a subgraph response body, of which `read` bytes were already read
+    pub(crate) fn consume(&self, read: usize, chunk: usize) -> Result<(), ResponseSizeLimitError> {
+        if let Some(limit) = self.subgraph_max_bytes {
+            if read + chunk > limit {
+                return Err(ResponseSizeLimitError::SubgraphResponseTooLarge(limit));
+            }
+        }
+        let total = self.total.fetch_add(chunk, Ordering::SeqCst) + chunk;
+        if let Some(limit) = self.total_max_bytes {
+            if total > limit {
+                return Err(ResponseSizeLimitError::TotalSubgraphResponsesTooLarge(
+                    limit,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_response_size_limits() {
+        let limit = ResponseSizeLimit::new(Some(10), Some(15));
+        assert_eq!(limit.consume(0, 8), Ok(()));
+        assert_eq!(
+            limit.consume(8, 3),
+            Err(ResponseSizeLimitError::SubgraphResponseTooLarge(10))
+        );
+
+        // The limit is shared between the subgraph responses of a request
+        let other_subgraph = limit.clone();
+        assert_eq!(other_subgraph.consume(0, 2), Ok(()));
+        assert_eq!(
+            other_subgraph.consume(2, 3),
+            Err(ResponseSizeLimitError::TotalSubgraphResponsesTooLarge(15))
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Incremental scanning of GraphQL request bodies, to reject oversized `query` strings and
+//! `variables` while the body is received, before it is fully buffered and parsed.
+
+use crate::plugins::limits::layer::BodyLimitError;
+
+/// Limits on the size of the fields of GraphQL requests, in bytes of the request body
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct FieldLimits {
+    pub(super) max_query_bytes: Option<usize>,
+    pub(super) max_variables_bytes: Option<usize>,
+}
+
+impl FieldLimits {
+    pub(super) fn is_enabled(&self) -> bool {
+        self.max_query_bytes.is_some() || self.max_variables_bytes.is_some()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Query,
+    Variables,
+    /// A key with escape sequences, which might be either once unescaped
+    Escaped,
+}
+
+/// Measures the `query` and `variables` of a JSON request body, or of each request of a batch,
+/// chunk by chunk.
+///
+/// This is not a JSON parser: invalid documents are rejected later by the parser, and bodies
+/// that are not JSON objects or arrays, such as multipart requests, are not scanned.
+pub(super) struct RequestScanner {
+    limits: FieldLimits,
+    /// Number of objects and arrays the scanner is in
+    depth: usize,
+    /// Depth inside the objects of the requests: 1, or 2 for a batch. `None` before the first
+    /// byte of the document
+    request_depth: Option<usize>,
+    /// Set when the document is not scanned
+    disabled: bool,
+    in_string: bool,
+    escaped: bool,
+    expecting_key: bool,
+    /// The key of the request field being read, truncated
+    key: Option<Vec<u8>>,
+    /// The field whose value is being read, and its size so far
+    measured: Option<(Field, usize)>,
+}
+
+const MAX_KEY_LENGTH: usize = "variables".len() + 1;
+
+impl RequestScanner {
+    pub(super) fn new(limits: FieldLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            request_depth: None,
+            disabled: false,
+            in_string: false,
+            escaped: false,
+            expecting_key: false,
+            key: None,
+            measured: None,
+        }
+    }
+
+    /// Scans the next chunk of the body, returning an error as soon as a limit is exceeded
+    pub(super) fn scan(&mut self, chunk: &[u8]) -> Result<(), BodyLimitError> {
+        if self.disabled {
+            return Ok(());
+        }
+        for &byte in chunk {
+            if self.request_depth.is_none() {
+                match byte {
+                    b'{' => self.request_depth = Some(1),
+                    b'[' => self.request_depth = Some(2),
+                    _ if byte.is_ascii_whitespace() => continue,
+                    _ => {
+                        self.disabled = true;
+                        return Ok(());
+                    }
+                }
+            }
+            if self.scan_byte(byte) {
+                self.count()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the state with the next byte, returning true if it is part of a measured value
+    fn scan_byte(&mut self, byte: u8) -> bool {
+        let at_request_level = Some(self.depth) == self.request_depth;
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+                return true;
+            }
+            if let Some(key) = &mut self.key {
+                if key.len() < MAX_KEY_LENGTH {
+                    key.push(byte);
+                }
+            }
+            return true;
+        }
+        match byte {
+            b'"' => {
+                self.in_string = true;
+                if at_request_level && self.expecting_key {
+                    self.key = Some(Vec::new());
+                }
+            }
+            b'{' | b'[' => {
+                self.depth += 1;
+                if byte == b'{' && Some(self.depth) == self.request_depth {
+                    self.expecting_key = true;
+                    return false;
+                }
+            }
+            b'}' | b']' => {
+                self.depth = self.depth.saturating_sub(1);
+                if at_request_level {
+                    self.measured = None;
+                    return false;
+                }
+            }
+            b',' if at_request_level => {
+                self.measured = None;
+                self.expecting_key = true;
+                return false;
+            }
+            b':' if at_request_level && self.expecting_key => {
+                self.expecting_key = false;
+                self.measured = match self.key.take().as_deref() {
+                    Some(b"query") => Some((Field::Query, 0)),
+                    Some(b"variables") => Some((Field::Variables, 0)),
+                    // "query" is also the query once unescaped
+                    Some(key) if key.contains(&b'\\') => Some((Field::Escaped, 0)),
+                    _ => None,
+                };
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn count(&mut self) -> Result<(), BodyLimitError> {
+        let Some((field, size)) = &mut self.measured else {
+            return Ok(());
+        };
+        *size += 1;
+        let exceeds = |limit: Option<usize>| limit.is_some_and(|limit| *size > limit);
+        match field {
+            Field::Query if exceeds(self.limits.max_query_bytes) => {
+                Err(BodyLimitError::QueryTooLarge)
+            }
+            Field::Variables if exceeds(self.limits.max_variables_bytes) => {
+                Err(BodyLimitError::VariablesTooLarge)
+            }
+            Field::Escaped if exceeds(self.limits.max_query_bytes) => {
+                Err(BodyLimitError::QueryTooLarge)
+            }
+            Field::Escaped if exceeds(self.limits.max_variables_bytes) => {
+                Err(BodyLimitError::VariablesTooLarge)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scan(limits: FieldLimits, chunks: &[&str]) -> Result<(), BodyLimitError> {
+        let mut scanner = RequestScanner::new(limits);
+        chunks
+            .iter()
+            .try_for_each(|chunk| scanner.scan(chunk.as_bytes()))
+    }
+
+    const LIMITS: FieldLimits = FieldLimits {
+        max_query_bytes: Some(20),
+        max_variables_bytes: Some(20),
+    };
+
+    #[test]
+    fn it_accepts_requests_within_limits() {
+        assert!(scan(
+            LIMITS,
+            &[
+                r#"{"query": "{ me }", "variables": {"a": 1}, "extensions": {"#,
+                r#""persistedQuery": "a very long value that isn't limited"}}"#
+            ]
+        )
+        .is_ok());
+        // keys in nested objects, or in strings, are not the fields of the request
+        assert!(scan(
+            LIMITS,
+            &[r#"{"extensions": {"query": "a very long value that isn't limited"}, "operationName": "query: \"x\", variables:"}"#]
+        )
+        .is_ok());
+        // multipart requests are not scanned
+        assert!(scan(
+            LIMITS,
+            &["--boundary\r\n", "a very long value that isn't limited"]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn it_rejects_oversized_fields_across_chunks() {
+        assert!(matches!(
+            scan(
+                LIMITS,
+                &[
+                    r#"{"operationName": "a", "qu"#,
+                    r#"ery": "{ me { name "#,
+                    "id } }"
+                ]
+            ),
+            Err(BodyLimitError::QueryTooLarge)
+        ));
+        assert!(matches!(
+            scan(
+                LIMITS,
+                &[r#"{"variables": {"ids": ["1", "2", "#, r#""3", "4"]}}"#]
+            ),
+            Err(BodyLimitError::VariablesTooLarge)
+        ));
+        assert!(matches!(
+            scan(LIMITS, &[r#"{"query": "{ me { name id } } "}"#]),
+            Err(BodyLimitError::QueryTooLarge)
+        ));
+    }
+
+    #[test]
+    fn it_scans_each_request_of_a_batch() {
+        assert!(scan(LIMITS, &[r#"[{"query": "{ me }"}, {"query": "{ me }"}]"#]).is_ok());
+        assert!(matches!(
+            scan(
+                LIMITS,
+                &[r#"[{"query": "{ me }"}, {"query": "{ me { name id } }"}]"#]
+            ),
+            Err(BodyLimitError::QueryTooLarge)
+        ));
+    }
+}
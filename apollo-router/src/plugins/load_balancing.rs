@@ -0,0 +1,554 @@
+//! Load balancing of subgraph requests over several endpoints
+//!
+//! A subgraph can be given a list of URLs, or a URL whose host name resolves to the addresses of
+//! the subgraph instances. That host name is resolved again periodically. An endpoint failing
+//! repeatedly is ejected for a while, and the requests are sent to the other endpoints.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::header::HOST;
+use http::uri::Authority;
+use http::uri::Scheme;
+use http::HeaderValue;
+use http::Uri;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::Service;
+use tower::ServiceExt;
+
+use crate::error::ConfigurationError;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::subgraph;
+
+/// Load balancing of the subgraph requests over several endpoints
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct LoadBalancingConfig {
+    /// Endpoints per subgraph name
+    subgraphs: HashMap<String, SubgraphEndpoints>,
+}
+
+/// Endpoints of a subgraph. Either `urls` or `dns_url` must be set
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SubgraphEndpoints {
+    #[serde(default)]
+    /// URLs of the subgraph instances
+    urls: Vec<String>,
+    /// HTTP URL whose host name resolves to the addresses of the subgraph instances
+    dns_url: Option<String>,
+    #[serde(default)]
+    /// How the endpoint of a request is selected (default: round_robin)
+    strategy: LoadBalancingStrategy,
+    #[serde(default = "default_max_failures")]
+    /// Number of consecutive failures after which an endpoint is ejected, 0 disables ejection (default: 5)
+    max_failures: u32,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_ejection_time"
+    )]
+    #[schemars(with = "String", default = "default_ejection_time")]
+    /// How long an ejected endpoint does not receive requests (default: 30s)
+    ejection_time: Duration,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_resolution_interval"
+    )]
+    #[schemars(with = "String", default = "default_resolution_interval")]
+    /// Interval between resolutions of the host name of `dns_url` (default: 30s)
+    resolution_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum LoadBalancingStrategy {
+    /// Endpoints receive requests in turn
+    #[default]
+    RoundRobin,
+    /// The endpoint with the fewest requests in flight receives the request
+    LeastLoaded,
+}
+
+fn default_max_failures() -> u32 {
+    5
+}
+
+fn default_ejection_time() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_resolution_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    uri: Uri,
+    /// Host header of the requests sent to a resolved address
+    host: Option<HeaderValue>,
+    in_flight: AtomicUsize,
+    consecutive_failures: AtomicU32,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn new(uri: Uri, host: Option<HeaderValue>) -> Arc<Self> {
+        Arc::new(Self {
+            uri,
+            host,
+            in_flight: AtomicUsize::new(0),
+            consecutive_failures: AtomicU32::new(0),
+            ejected_until: Mutex::new(None),
+        })
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        match *self.ejected_until.lock() {
+            Some(ejected_until) => ejected_until <= now,
+            None => true,
+        }
+    }
+}
+
+/// Keeps track of the requests in flight on an endpoint, even if the request is cancelled
+struct InFlight(Arc<Endpoint>);
+
+impl InFlight {
+    fn new(endpoint: Arc<Endpoint>) -> Self {
+        endpoint.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(endpoint)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct Balancer {
+    subgraph_name: String,
+    strategy: LoadBalancingStrategy,
+    max_failures: u32,
+    ejection_time: Duration,
+    endpoints: RwLock<Vec<Arc<Endpoint>>>,
+    next: AtomicUsize,
+}
+
+impl Balancer {
+    fn new(subgraph_name: &str, config: &SubgraphEndpoints, endpoints: Vec<Arc<Endpoint>>) -> Self {
+        Self {
+            subgraph_name: subgraph_name.to_string(),
+            strategy: config.strategy,
+            max_failures: config.max_failures,
+            ejection_time: config.ejection_time,
+            endpoints: RwLock::new(endpoints),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    fn select(&self) -> Option<Arc<Endpoint>> {
+        let endpoints = self.endpoints.read();
+        let now = Instant::now();
+        let mut candidates = endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_available(now))
+            .collect::<Vec<_>>();
+        // When every endpoint is ejected, they are all used rather than failing every request
+        if candidates.is_empty() {
+            candidates = endpoints.iter().collect();
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let endpoint = match self.strategy {
+            LoadBalancingStrategy::RoundRobin => candidates[start % candidates.len()],
+            // Starting from a different endpoint each time spreads the requests between
+            // endpoints that have the same load
+            LoadBalancingStrategy::LeastLoaded => (0..candidates.len())
+                .map(|i| candidates[(start + i) % candidates.len()])
+                .min_by_key(|endpoint| endpoint.in_flight.load(Ordering::Relaxed))
+                .expect("there is at least one candidate"),
+        };
+        Some(endpoint.clone())
+    }
+
+    fn record(&self, endpoint: &Endpoint, success: bool) {
+        if success {
+            endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+            return;
+        }
+        let failures = endpoint
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        if self.max_failures > 0 && failures >= self.max_failures {
+            endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+            *endpoint.ejected_until.lock() = Some(Instant::now() + self.ejection_time);
+            tracing::warn!(
+                "ejecting endpoint {} of subgraph {} after {failures} consecutive failures",
+                endpoint.uri,
+                self.subgraph_name
+            );
+            u64_counter!(
+                "apollo.router.load_balancing.ejections",
+                "Number of subgraph endpoints ejected after consecutive failures",
+                1,
+                subgraph.name = self.subgraph_name.clone()
+            );
+        }
+    }
+
+    /// Replaces the endpoints by the resolved addresses, keeping the state of the addresses
+    /// that did not change
+    fn update_addresses(&self, dns_url: &Uri, addresses: &[SocketAddr]) {
+        let mut endpoints = self.endpoints.write();
+        let updated = addresses
+            .iter()
+            .filter_map(|address| {
+                let uri = address_uri(dns_url, address)?;
+                Some(
+                    endpoints
+                        .iter()
+                        .find(|endpoint| endpoint.uri == uri)
+                        .cloned()
+                        .unwrap_or_else(|| Endpoint::new(uri, host_header(dns_url))),
+                )
+            })
+            .collect::<Vec<_>>();
+        if !updated.is_empty() {
+            *endpoints = updated;
+        }
+    }
+}
+
+/// The URL with its host replaced by the address
+fn address_uri(dns_url: &Uri, address: &SocketAddr) -> Option<Uri> {
+    let mut parts = dns_url.clone().into_parts();
+    parts.authority = Some(Authority::from_str(&address.to_string()).ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+fn host_header(dns_url: &Uri) -> Option<HeaderValue> {
+    dns_url
+        .authority()
+        .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+}
+
+async fn resolve(dns_url: &Uri) -> Result<Vec<SocketAddr>, std::io::Error> {
+    let host = dns_url.host().unwrap_or_default();
+    let port = dns_url.port_u16().unwrap_or(80);
+    let mut addresses = tokio::net::lookup_host((host, port))
+        .await?
+        .collect::<Vec<_>>();
+    addresses.sort();
+    addresses.dedup();
+    Ok(addresses)
+}
+
+async fn resolve_periodically(balancer: Weak<Balancer>, dns_url: Uri, interval: Duration) {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        interval.tick().await;
+        if balancer.strong_count() == 0 {
+            break;
+        }
+        let result = resolve(&dns_url).await;
+        let Some(balancer) = balancer.upgrade() else {
+            break;
+        };
+        match result {
+            Ok(addresses) => balancer.update_addresses(&dns_url, &addresses),
+            Err(err) => {
+                tracing::warn!(
+                    "could not resolve the endpoints of subgraph {}, keeping the previous ones: {err}",
+                    balancer.subgraph_name
+                );
+            }
+        }
+    }
+}
+
+fn invalid_configuration(error: String) -> BoxError {
+    ConfigurationError::InvalidConfiguration {
+        message: "bad configuration for load_balancing plugin",
+        error,
+    }
+    .into()
+}
+
+struct LoadBalancing {
+    balancers: HashMap<String, Arc<Balancer>>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for LoadBalancing {
+    type Config = LoadBalancingConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let mut balancers = HashMap::new();
+        for (subgraph_name, config) in &init.config.subgraphs {
+            let balancer = match (&config.dns_url, config.urls.is_empty()) {
+                (None, false) => {
+                    let endpoints = config
+                        .urls
+                        .iter()
+                        .map(|url| {
+                            Uri::from_str(url)
+                                .map(|uri| Endpoint::new(uri, None))
+                                .map_err(|err| {
+                                    invalid_configuration(format!(
+                                        "invalid URL {url} for subgraph {subgraph_name}: {err}"
+                                    ))
+                                })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Arc::new(Balancer::new(subgraph_name, config, endpoints))
+                }
+                (Some(dns_url), true) => {
+                    let dns_url = Uri::from_str(dns_url).map_err(|err| {
+                        invalid_configuration(format!(
+                            "invalid DNS URL {dns_url} for subgraph {subgraph_name}: {err}"
+                        ))
+                    })?;
+                    // Requests are sent to IP addresses, which cannot be used to verify the
+                    // certificate of the subgraph
+                    if dns_url.scheme() != Some(&Scheme::HTTP) || dns_url.host().is_none() {
+                        return Err(invalid_configuration(format!(
+                            "the DNS URL of subgraph {subgraph_name} must be an http:// URL"
+                        )));
+                    }
+                    // Until the host name is resolved, requests are sent to the URL itself
+                    let balancer = Arc::new(Balancer::new(
+                        subgraph_name,
+                        config,
+                        vec![Endpoint::new(dns_url.clone(), None)],
+                    ));
+                    match resolve(&dns_url).await {
+                        Ok(addresses) => balancer.update_addresses(&dns_url, &addresses),
+                        Err(err) => {
+                            tracing::warn!(
+                                "could not resolve the endpoints of subgraph {subgraph_name}: {err}"
+                            );
+                        }
+                    }
+                    tokio::spawn(resolve_periodically(
+                        Arc::downgrade(&balancer),
+                        dns_url,
+                        config.resolution_interval,
+                    ));
+                    balancer
+                }
+                _ => {
+                    return Err(invalid_configuration(format!(
+                        "subgraph {subgraph_name} must have either a list of URLs or a DNS URL"
+                    )));
+                }
+            };
+            balancers.insert(subgraph_name.clone(), balancer);
+        }
+
+        Ok(LoadBalancing { balancers })
+    }
+
+    fn subgraph_service(
+        &self,
+        subgraph_name: &str,
+        service: subgraph::BoxService,
+    ) -> subgraph::BoxService {
+        match self.balancers.get(subgraph_name) {
+            Some(balancer) => LoadBalancedService {
+                inner: service,
+                balancer: balancer.clone(),
+            }
+            .boxed(),
+            None => service,
+        }
+    }
+}
+
+struct LoadBalancedService {
+    inner: subgraph::BoxService,
+    balancer: Arc<Balancer>,
+}
+
+impl Service<subgraph::Request> for LoadBalancedService {
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<subgraph::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: subgraph::Request) -> Self::Future {
+        let Some(endpoint) = self.balancer.select() else {
+            return self.inner.call(request);
+        };
+        *request.subgraph_request.uri_mut() = endpoint.uri.clone();
+        if let Some(host) = &endpoint.host {
+            request
+                .subgraph_request
+                .headers_mut()
+                .insert(HOST, host.clone());
+        }
+
+        let balancer = self.balancer.clone();
+        let in_flight = InFlight::new(endpoint);
+        let future = self.inner.call(request);
+        async move {
+            let response = future.await;
+            let success = matches!(
+                &response,
+                Ok(response) if !response.response.status().is_server_error()
+            );
+            balancer.record(&in_flight.0, success);
+            response
+        }
+        .boxed()
+    }
+}
+
+register_plugin!("apollo", "load_balancing", LoadBalancing);
+
+#[cfg(test)]
+mod tests {
+    use tower::service_fn;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+
+    fn balancer(strategy: LoadBalancingStrategy, urls: &[&str]) -> Arc<Balancer> {
+        let config = SubgraphEndpoints {
+            urls: vec![],
+            dns_url: None,
+            strategy,
+            max_failures: 2,
+            ejection_time: Duration::from_secs(60),
+            resolution_interval: default_resolution_interval(),
+        };
+        let endpoints = urls
+            .iter()
+            .map(|url| Endpoint::new(Uri::from_str(url).unwrap(), None))
+            .collect();
+        Arc::new(Balancer::new("products", &config, endpoints))
+    }
+
+    fn selected(balancer: &Balancer) -> String {
+        balancer.select().unwrap().uri.to_string()
+    }
+
+    #[test]
+    fn selects_endpoints_in_turn() {
+        let balancer = balancer(
+            LoadBalancingStrategy::RoundRobin,
+            &["http://a/graphql", "http://b/graphql"],
+        );
+        assert_eq!(selected(&balancer), "http://a/graphql");
+        assert_eq!(selected(&balancer), "http://b/graphql");
+        assert_eq!(selected(&balancer), "http://a/graphql");
+    }
+
+    #[test]
+    fn selects_the_least_loaded_endpoint() {
+        let balancer = balancer(
+            LoadBalancingStrategy::LeastLoaded,
+            &["http://a/graphql", "http://b/graphql"],
+        );
+        let _in_flight = InFlight::new(balancer.select().unwrap());
+        let _in_flight = InFlight::new(balancer.select().unwrap());
+        let _in_flight_on_a = InFlight::new(balancer.endpoints.read()[0].clone());
+        assert_eq!(selected(&balancer), "http://b/graphql");
+        assert_eq!(selected(&balancer), "http://b/graphql");
+    }
+
+    #[tokio::test]
+    async fn ejects_failing_endpoints() {
+        async {
+            let balancer = balancer(
+                LoadBalancingStrategy::RoundRobin,
+                &["http://a/graphql", "http://b/graphql"],
+            );
+            let service = service_fn(|request: subgraph::Request| async move {
+                let status = if request.subgraph_request.uri().host() == Some("a") {
+                    http::StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    http::StatusCode::OK
+                };
+                Ok::<_, BoxError>(
+                    subgraph::Response::fake_builder()
+                        .status_code(status)
+                        .context(request.context)
+                        .build(),
+                )
+            });
+            let mut service = LoadBalancedService {
+                inner: service.boxed(),
+                balancer: balancer.clone(),
+            };
+
+            for _ in 0..4 {
+                service
+                    .ready()
+                    .await
+                    .unwrap()
+                    .call(subgraph::Request::fake_builder().build())
+                    .await
+                    .unwrap();
+            }
+            assert_counter!(
+                "apollo.router.load_balancing.ejections",
+                1,
+                "subgraph.name" = "products"
+            );
+            assert_eq!(selected(&balancer), "http://b/graphql");
+            assert_eq!(selected(&balancer), "http://b/graphql");
+            assert!(balancer
+                .endpoints
+                .read()
+                .iter()
+                .all(|endpoint| endpoint.in_flight.load(Ordering::Relaxed) == 0));
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[test]
+    fn keeps_the_state_of_resolved_addresses() {
+        let dns_url = Uri::from_str("http://products.internal:4001/graphql").unwrap();
+        let balancer = balancer(LoadBalancingStrategy::RoundRobin, &[]);
+        let first: SocketAddr = "10.0.0.1:4001".parse().unwrap();
+        let second: SocketAddr = "10.0.0.2:4001".parse().unwrap();
+
+        balancer.update_addresses(&dns_url, &[first, second]);
+        let endpoint = balancer.select().unwrap();
+        assert_eq!(endpoint.uri.to_string(), "http://10.0.0.1:4001/graphql");
+        assert_eq!(endpoint.host.as_ref().unwrap(), "products.internal:4001");
+        endpoint.consecutive_failures.store(1, Ordering::Relaxed);
+
+        balancer.update_addresses(&dns_url, &[first]);
+        let endpoints = balancer.endpoints.read();
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(endpoints[0].consecutive_failures.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Maintenance mode
+//!
+//! While maintenance mode is enabled, only the allow-listed queries are served, the others are
+//! rejected with a `MAINTENANCE_MODE` error. In read-only mode, queries are still served and
+//! mutations are rejected, with a `READ_ONLY_MODE` error. Both modes can be enabled in the
+//! configuration or toggled at runtime through the admin API.
+//!
+//! Operations are checked on the operation selected in the parsed document, since the operation
+//! name is chosen by the client: the allow list only applies to queries, so a mutation cannot be
+//! served by naming it after an allowed operation.
+
+use std::ops::ControlFlow;
+
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::error::Error;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::services::supergraph;
+use crate::services::SupergraphRequest;
+use crate::services::SupergraphResponse;
+
+#[derive(Debug, Clone)]
+struct Maintenance {
+    config: MaintenanceConfig,
+}
+
+/// Maintenance mode configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct MaintenanceConfig {
+    /// Set to true to reject the operations that are not allow-listed queries
+    pub(crate) enabled: bool,
+    /// Set to true to reject mutations, while queries and subscriptions are still served
+    pub(crate) read_only: bool,
+    /// Names of the queries that are still served in maintenance mode
+    pub(crate) allowed_operations: Vec<String>,
+}
+
+impl MaintenanceConfig {
+    /// Returns the mode rejecting the executed operation, if any
+    fn rejects(&self, doc: Option<&ParsedDocument>) -> Option<Mode> {
+        // The operation is selected and validated by the query analysis, before query planning.
+        // Without it, the operation type is unknown so it is rejected.
+        let Some(operation) = doc.map(|doc| &doc.operation) else {
+            return Some(if self.enabled {
+                Mode::Maintenance
+            } else {
+                Mode::ReadOnly
+            });
+        };
+        if self.enabled {
+            let allowed = operation.is_query()
+                && operation.name.as_ref().is_some_and(|name| {
+                    self.allowed_operations
+                        .iter()
+                        .any(|allowed| allowed == name.as_str())
+                });
+            (!allowed).then_some(Mode::Maintenance)
+        } else {
+            (self.read_only && operation.is_mutation()).then_some(Mode::ReadOnly)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Maintenance,
+    ReadOnly,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Maintenance {
+    type Config = MaintenanceConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(Maintenance {
+            config: init.config,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.config.enabled && !self.config.read_only {
+            return service;
+        }
+
+        let config = self.config.clone();
+        ServiceBuilder::new()
+            .checkpoint(move |req: SupergraphRequest| {
+                let doc = req
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<ParsedDocument>().cloned());
+                let (mode, message, code) = match config.rejects(doc.as_ref()) {
+                    Some(Mode::Maintenance) => (
+                        "maintenance",
+                        "The router is in maintenance mode",
+                        "MAINTENANCE_MODE",
+                    ),
+                    Some(Mode::ReadOnly) => (
+                        "read_only",
+                        "Mutations are rejected while the router is in read-only mode",
+                        "READ_ONLY_MODE",
+                    ),
+                    None => return Ok(ControlFlow::Continue(req)),
+                };
+                u64_counter!(
+                    "apollo.router.maintenance.rejected",
+                    "Number of operations rejected by the maintenance or read-only mode",
+                    1,
+                    mode = mode
+                );
+                let error = Error::builder()
+                    .message(message.to_string())
+                    .extension_code(code)
+                    .build();
+                let res = SupergraphResponse::builder()
+                    .error(error)
+                    .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                    .context(req.context)
+                    .build()?;
+                Ok(ControlFlow::Break(res))
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+register_plugin!("apollo", "maintenance", Maintenance);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use apollo_compiler::ast;
+    use serde_json::json;
+    use tower::Service;
+
+    use super::*;
+    use crate::graphql;
+    use crate::metrics::FutureMetricsExt;
+    use crate::plugin::test::MockSupergraphService;
+    use crate::services::layers::query_analysis::ParsedDocumentInner;
+    use crate::Context;
+
+    async fn maintenance(config: serde_json::Value) -> Maintenance {
+        Maintenance::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Default::default(),
+        ))
+        .await
+        .unwrap()
+    }
+
+    fn request(operation: &str) -> SupergraphRequest {
+        let schema = "type Query { a: Int } type Mutation { a: Int }";
+        let ast = ast::Document::parse(format!("{schema} {operation}"), "").unwrap();
+        let (_schema, executable) = ast.to_mixed_validate().unwrap();
+
+        let context = Context::new();
+        context.extensions().with_lock(|mut lock| {
+            lock.insert::<ParsedDocument>(
+                ParsedDocumentInner::new(ast, Arc::new(executable), None, Default::default())
+                    .unwrap(),
+            )
+        });
+        SupergraphRequest::fake_builder()
+            .query(operation)
+            .context(context)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_rejects_operations_in_maintenance_mode() {
+        let mut mock = MockSupergraphService::new();
+        mock.expect_call().times(1).returning(|req| {
+            Ok(SupergraphResponse::fake_builder()
+                .context(req.context)
+                .build()
+                .unwrap())
+        });
+        let mut service = maintenance(json!({
+            "enabled": true,
+            "allowed_operations": ["HealthCheck"]
+        }))
+        .await
+        .supergraph_service(mock.boxed());
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request("query HealthCheck { a }"))
+            .await
+            .unwrap();
+        assert_eq!(response.response.status(), StatusCode::OK);
+
+        // A mutation named after an allowed query is rejected too
+        for operation in [
+            "query GetProducts { a }",
+            "{ a }",
+            "mutation HealthCheck { a }",
+        ] {
+            let mut response = service
+                .ready()
+                .await
+                .unwrap()
+                .call(request(operation))
+                .await
+                .unwrap();
+            assert_eq!(response.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            let body: graphql::Response = response.next_response().await.unwrap();
+            assert_eq!(
+                body.errors[0].extensions.get("code").unwrap(),
+                "MAINTENANCE_MODE"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_rejects_mutations_in_read_only_mode() {
+        async {
+            let mut mock = MockSupergraphService::new();
+            mock.expect_call().times(1).returning(|req| {
+                Ok(SupergraphResponse::fake_builder()
+                    .context(req.context)
+                    .build()
+                    .unwrap())
+            });
+            let mut service = maintenance(json!({
+                "read_only": true,
+                "allowed_operations": ["GetProducts"]
+            }))
+            .await
+            .supergraph_service(mock.boxed());
+
+            let response = service
+                .ready()
+                .await
+                .unwrap()
+                .call(request("query GetProducts { a }"))
+                .await
+                .unwrap();
+            assert_eq!(response.response.status(), StatusCode::OK);
+
+            // The allow list does not apply to mutations
+            for operation in ["mutation UpdateProduct { a }", "mutation GetProducts { a }"] {
+                let mut response = service
+                    .ready()
+                    .await
+                    .unwrap()
+                    .call(request(operation))
+                    .await
+                    .unwrap();
+                assert_eq!(response.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+                let body: graphql::Response = response.next_response().await.unwrap();
+                assert_eq!(
+                    body.errors[0].extensions.get("code").unwrap(),
+                    "READ_ONLY_MODE"
+                );
+            }
+            assert_counter!(
+                "apollo.router.maintenance.rejected",
+                2,
+                "mode" = "read_only"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+}
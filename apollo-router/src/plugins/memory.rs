@@ -0,0 +1,134 @@
+//! Memory instrumentation, available when the router is built with the `memory-instrumentation`
+//! feature
+//!
+//! The heap statistics of jemalloc are reported as metrics. In debug mode, the memory allocated
+//! while processing each request is estimated and recorded on its router span, to find the
+//! requests causing large allocations, such as large responses or query plans.
+
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use opentelemetry::Key;
+use opentelemetry_api::metrics::ObservableGauge;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+use tracing::Span;
+
+use crate::allocator::AllocationTracked;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::PluginInit;
+use crate::plugin::PluginPrivate;
+use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
+use crate::register_private_plugin;
+use crate::services::router;
+
+const ALLOCATED_BYTES: Key = Key::from_static_str("apollo.router.allocated_bytes");
+
+/// Memory instrumentation
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct Conf {
+    /// Report the heap statistics of jemalloc as the `apollo.router.memory.heap` metric, on
+    /// Linux. Defaults to true
+    heap_metrics: bool,
+    /// Estimate the memory allocated while processing each request, and record it as the
+    /// `apollo.router.allocated_bytes` attribute of the router span. Defaults to false
+    debug: bool,
+}
+
+impl Default for Conf {
+    fn default() -> Self {
+        Self {
+            heap_metrics: true,
+            debug: false,
+        }
+    }
+}
+
+struct Memory {
+    config: Conf,
+    // Kept alive until the plugin is dropped
+    heap_gauge: Mutex<Option<ObservableGauge<u64>>>,
+}
+
+#[async_trait::async_trait]
+impl PluginPrivate for Memory {
+    type Config = Conf;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(Self {
+            config: init.config,
+            heap_gauge: Mutex::new(None),
+        })
+    }
+
+    fn activate(&self) {
+        if !self.config.heap_metrics {
+            return;
+        }
+        let mut heap_gauge = self.heap_gauge.lock().expect("lock poisoned");
+        if heap_gauge.is_none() {
+            *heap_gauge = create_heap_gauge();
+        }
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        if !self.config.debug {
+            return service;
+        }
+        ServiceBuilder::new()
+            .map_future_with_request_data(
+                |_: &router::Request| Span::current(),
+                |span: Span, future: BoxFuture<'static, router::ServiceResult>| {
+                    AllocationTracked::new(future).map(move |(result, allocated)| {
+                        span.set_span_dyn_attribute(
+                            ALLOCATED_BYTES,
+                            i64::try_from(allocated).unwrap_or(i64::MAX).into(),
+                        );
+                        result
+                    })
+                },
+            )
+            .service(service)
+            .boxed()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn create_heap_gauge() -> Option<ObservableGauge<u64>> {
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_api::metrics::Unit;
+    use opentelemetry_api::KeyValue;
+
+    use crate::allocator::HeapStats;
+    use crate::metrics::meter_provider;
+
+    Some(
+        meter_provider()
+            .meter("apollo/router")
+            .u64_observable_gauge("apollo.router.memory.heap")
+            .with_description("Heap statistics of the memory allocator")
+            .with_unit(Unit::new("By"))
+            .with_callback(|gauge| match HeapStats::read() {
+                Ok(stats) => {
+                    gauge.observe(stats.allocated, &[KeyValue::new("type", "allocated")]);
+                    gauge.observe(stats.active, &[KeyValue::new("type", "active")]);
+                    gauge.observe(stats.resident, &[KeyValue::new("type", "resident")]);
+                    gauge.observe(stats.mapped, &[KeyValue::new("type", "mapped")]);
+                }
+                Err(err) => tracing::debug!("could not read the heap statistics: {err}"),
+            })
+            .init(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn create_heap_gauge() -> Option<ObservableGauge<u64>> {
+    None
+}
+
+register_private_plugin!("apollo", "memory", Memory);
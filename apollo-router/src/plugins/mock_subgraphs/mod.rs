@@ -0,0 +1,413 @@
+//! Mock subgraphs for local development
+//!
+//! Subgraphs are replaced with responses generated from their schema, or with canned
+//! responses per operation, so that a supergraph can be run without all of its services.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::Poll;
+
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Schema;
+use futures::future::ready;
+use futures::future::Ready;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt as TowerServiceExt;
+
+use crate::graphql;
+use crate::layers::ServiceExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::subgraph;
+use crate::services::subgraph::SubgraphRequestId;
+use crate::Context;
+
+mod generate;
+
+/// Mock subgraphs configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct MockSubgraphsConfig {
+    /// Answer the requests to subgraphs that cannot be reached with mock responses
+    replace_unreachable: bool,
+    /// Mock configuration of specific subgraphs, by subgraph name
+    subgraphs: HashMap<String, MockSubgraphConfig>,
+}
+
+/// Mock configuration of a subgraph
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct MockSubgraphConfig {
+    /// Always answer the requests to this subgraph with mock responses
+    enabled: bool,
+    /// Canned GraphQL responses, by subgraph operation name. Other operations get responses
+    /// generated from the subgraph schema
+    fixtures: HashMap<String, serde_json::Value>,
+}
+
+struct MockSubgraphs {
+    replace_unreachable: bool,
+    services: HashMap<String, (bool, MockSubgraphService)>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for MockSubgraphs {
+    type Config = MockSubgraphsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let mut config = init.config;
+        for subgraph_name in config.subgraphs.keys() {
+            if !init.subgraph_schemas.contains_key(subgraph_name) {
+                tracing::warn!("cannot mock unknown subgraph '{subgraph_name}'");
+            }
+        }
+
+        let mut services = HashMap::new();
+        for (subgraph_name, schema) in init.subgraph_schemas.iter() {
+            let subgraph_config = config.subgraphs.remove(subgraph_name).unwrap_or_default();
+            let fixtures: HashMap<String, graphql::Response> = subgraph_config
+                .fixtures
+                .into_iter()
+                .map(|(operation_name, response)| {
+                    serde_json::from_value(response)
+                        .map(|response| (operation_name.clone(), response))
+                        .map_err(|err| {
+                            format!(
+                                "invalid fixture for operation '{operation_name}' of subgraph '{subgraph_name}': {err}"
+                            )
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+            services.insert(
+                subgraph_name.clone(),
+                (
+                    subgraph_config.enabled,
+                    MockSubgraphService {
+                        subgraph_name: subgraph_name.clone(),
+                        schema: schema.clone(),
+                        fixtures: Arc::new(fixtures),
+                    },
+                ),
+            );
+        }
+
+        Ok(MockSubgraphs {
+            replace_unreachable: config.replace_unreachable,
+            services,
+        })
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        let Some((enabled, mock)) = self.services.get(name).cloned() else {
+            return service;
+        };
+        if enabled {
+            return mock.boxed();
+        }
+        if !self.replace_unreachable {
+            return service;
+        }
+
+        service
+            .map_future_with_request_data(
+                |req: &subgraph::Request| {
+                    (
+                        req.subgraph_request.body().clone(),
+                        req.context.clone(),
+                        req.id.clone(),
+                    )
+                },
+                move |(body, context, id): (graphql::Request, Context, SubgraphRequestId), f| {
+                    let mock = mock.clone();
+                    async move {
+                        match f.await {
+                            Ok(response) => Ok(response),
+                            Err(err) => {
+                                tracing::warn!(
+                                    "subgraph '{}' could not be reached, answering with a mock response: {err}",
+                                    mock.subgraph_name
+                                );
+                                Ok(mock.respond(&body, context, id))
+                            }
+                        }
+                    }
+                },
+            )
+            .boxed()
+    }
+}
+
+/// Subgraph service answering with canned responses, or with responses generated from the
+/// subgraph schema
+#[derive(Clone)]
+pub(crate) struct MockSubgraphService {
+    subgraph_name: String,
+    schema: Arc<Valid<Schema>>,
+    fixtures: Arc<HashMap<String, graphql::Response>>,
+}
+
+impl MockSubgraphService {
+    fn respond(
+        &self,
+        body: &graphql::Request,
+        context: Context,
+        id: SubgraphRequestId,
+    ) -> subgraph::Response {
+        let fixture = body
+            .operation_name
+            .as_ref()
+            .and_then(|operation_name| self.fixtures.get(operation_name));
+        let response = match fixture {
+            Some(fixture) => fixture.clone(),
+            None => match generate::generate_data(
+                &self.schema,
+                body.query.as_deref().unwrap_or_default(),
+                body.operation_name.as_deref(),
+                &body.variables,
+            ) {
+                Ok(data) => graphql::Response::builder().data(data).build(),
+                Err(message) => graphql::Response::builder()
+                    .error(
+                        graphql::Error::builder()
+                            .message(format!("could not generate a mock response: {message}"))
+                            .extension_code("MOCK_SUBGRAPH_ERROR")
+                            .build(),
+                    )
+                    .build(),
+            },
+        };
+        subgraph::Response::new_from_response(
+            http::Response::new(response),
+            context,
+            self.subgraph_name.clone(),
+            id,
+        )
+    }
+}
+
+impl tower::Service<subgraph::Request> for MockSubgraphService {
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: subgraph::Request) -> Self::Future {
+        ready(Ok(self.respond(
+            req.subgraph_request.body(),
+            req.context,
+            req.id,
+        )))
+    }
+}
+
+register_plugin!("experimental", "mock_subgraphs", MockSubgraphs);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::service_fn;
+
+    use super::*;
+    use crate::query_planner::fetch::SubgraphSchemas;
+
+    const SUBGRAPH_SCHEMA: &str = r#"
+        type Query {
+          topProducts(first: Int): [Product]
+          node(id: ID!): Node
+        }
+
+        interface Node {
+          id: ID!
+        }
+
+        type Product implements Node @key(fields: "upc") {
+          id: ID!
+          upc: String!
+          price: Float
+          inStock: Boolean
+          status: Status
+        }
+
+        enum Status {
+          AVAILABLE
+          SOLD_OUT
+        }
+
+        type _Service {
+          sdl: String
+        }
+
+        scalar _Any
+
+        union _Entity = Product
+
+        extend type Query {
+          _entities(representations: [_Any!]!): [_Entity]!
+          _service: _Service!
+        }
+
+        directive @key(fields: String!) repeatable on OBJECT | INTERFACE
+    "#;
+
+    async fn subgraph_service(
+        config: serde_json::Value,
+        service: subgraph::BoxService,
+    ) -> subgraph::BoxService {
+        let schema = Schema::parse_and_validate(SUBGRAPH_SCHEMA, "products.graphql").unwrap();
+        let subgraph_schemas: SubgraphSchemas = [("products".to_string(), Arc::new(schema))].into();
+        let plugin = MockSubgraphs::new(
+            PluginInit::fake_builder()
+                .config(serde_json::from_value(config).unwrap())
+                .subgraph_schemas(Arc::new(subgraph_schemas))
+                .build(),
+        )
+        .await
+        .unwrap();
+        plugin.subgraph_service("products", service)
+    }
+
+    fn unreachable_service() -> subgraph::BoxService {
+        service_fn(|_req: subgraph::Request| async {
+            Err::<subgraph::Response, BoxError>("connection refused".into())
+        })
+        .boxed()
+    }
+
+    async fn call(
+        service: subgraph::BoxService,
+        query: &str,
+        operation_name: &str,
+        variables: serde_json_bytes::Value,
+    ) -> graphql::Response {
+        let request = subgraph::Request::fake_builder()
+            .subgraph_request(
+                http::Request::builder()
+                    .body(
+                        graphql::Request::fake_builder()
+                            .query(query)
+                            .operation_name(operation_name)
+                            .variables(variables.as_object().unwrap().clone())
+                            .build(),
+                    )
+                    .unwrap(),
+            )
+            .build();
+        service.oneshot(request).await.unwrap().response.into_body()
+    }
+
+    #[tokio::test]
+    async fn it_generates_responses_from_the_schema() {
+        let service = subgraph_service(
+            json!({ "subgraphs": { "products": { "enabled": true } } }),
+            unreachable_service(),
+        )
+        .await;
+
+        let response = call(
+            service,
+            "query TopProducts__products__0($first: Int) { topProducts(first: $first) { __typename upc price inStock status } node(id: 1) { ... on Product { id } } }",
+            "TopProducts__products__0",
+            serde_json_bytes::json!({ "first": 2 }),
+        )
+        .await;
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({
+                "data": {
+                    "topProducts": [{
+                        "__typename": "Product",
+                        "upc": "upc",
+                        "price": 1.0,
+                        "inStock": true,
+                        "status": "AVAILABLE"
+                    }],
+                    "node": { "id": "1" }
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_generates_entities_from_representations() {
+        let service = subgraph_service(
+            json!({ "subgraphs": { "products": { "enabled": true } } }),
+            unreachable_service(),
+        )
+        .await;
+
+        let response = call(
+            service,
+            "query Entities__products__1($representations: [_Any!]!) { _entities(representations: $representations) { ... on Product { upc price } } }",
+            "Entities__products__1",
+            serde_json_bytes::json!({ "representations": [
+                { "__typename": "Product", "upc": "1" },
+                { "__typename": "Product", "upc": "2" }
+            ] }),
+        )
+        .await;
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({
+                "data": {
+                    "_entities": [
+                        { "upc": "upc", "price": 1.0 },
+                        { "upc": "upc", "price": 1.0 }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_answers_with_fixtures() {
+        let fixture = json!({ "data": { "topProducts": [{ "upc": "42" }] } });
+        let service = subgraph_service(
+            json!({ "subgraphs": { "products": {
+                "enabled": true,
+                "fixtures": { "TopProducts__products__0": fixture }
+            } } }),
+            unreachable_service(),
+        )
+        .await;
+
+        let response = call(
+            service,
+            "query TopProducts__products__0 { topProducts { upc } }",
+            "TopProducts__products__0",
+            serde_json_bytes::json!({}),
+        )
+        .await;
+        assert_eq!(serde_json::to_value(response).unwrap(), fixture);
+    }
+
+    #[tokio::test]
+    async fn it_replaces_unreachable_subgraphs() {
+        let service = subgraph_service(
+            json!({ "replace_unreachable": true }),
+            unreachable_service(),
+        )
+        .await;
+        let response = call(
+            service,
+            "query Products__products__0 { topProducts { upc } }",
+            "Products__products__0",
+            serde_json_bytes::json!({}),
+        )
+        .await;
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({ "data": { "topProducts": [{ "upc": "upc" }] } })
+        );
+
+        // Without the option, the error of the subgraph service is returned
+        let service = subgraph_service(json!({}), unreachable_service()).await;
+        let request = subgraph::Request::fake_builder().build();
+        assert!(service.oneshot(request).await.is_err());
+    }
+}
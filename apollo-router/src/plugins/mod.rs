@@ -20,23 +20,33 @@ macro_rules! schemar_fn {
     };
 }
 
+mod api_keys;
+mod audit;
 pub(crate) mod authentication;
 pub(crate) mod authorization;
 pub(crate) mod cache;
 mod coprocessor;
 pub(crate) mod csrf;
 mod demand_control;
-mod expose_query_plan;
+pub(crate) mod expose_query_plan;
 pub(crate) mod file_uploads;
 mod fleet_detector;
 mod forbid_mutations;
 mod headers;
 mod include_subgraph_errors;
 pub(crate) mod limits;
+mod load_balancing;
+mod maintenance;
+#[cfg(feature = "memory-instrumentation")]
+mod memory;
+mod mock_subgraphs;
+mod operation_filter;
 pub(crate) mod override_url;
 pub(crate) mod progressive_override;
 mod record_replay;
 pub(crate) mod rhai;
+mod schema_usage;
+mod subgraph_transform;
 pub(crate) mod subscription;
 pub(crate) mod telemetry;
 #[cfg(test)]
@@ -0,0 +1,295 @@
+//! Allow or deny operations by signature
+//!
+//! Operations are identified by their usage reporting signature, the normalized form of the
+//! operation that Apollo Studio displays, so that a known abusive operation can be blocked
+//! without redeploying subgraphs. Operations are filtered before query planning, so denied
+//! operations are never planned.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use apollo_compiler::validation::Valid;
+use http::StatusCode;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::apollo_studio_interop::generate_usage_reporting;
+use crate::error::Error;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::serde::deserialize_regex;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::telemetry::config::ApolloSignatureNormalizationAlgorithm;
+use crate::plugins::telemetry::config::Conf as TelemetryConfig;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::register_plugin;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::services::supergraph;
+use crate::services::SupergraphRequest;
+use crate::services::SupergraphResponse;
+
+schemar_fn!(
+    pattern_regex,
+    String,
+    "Matches operation signatures with a regular expression"
+);
+
+#[derive(Debug, Clone)]
+struct OperationFilter {
+    config: OperationFilterConfig,
+    supergraph_schema: Arc<Valid<apollo_compiler::Schema>>,
+    /// Signatures are generated like usage reports, so that they match those of Apollo Studio
+    signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
+    redacted_arguments: Vec<String>,
+}
+
+/// Operation filter configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct OperationFilterConfig {
+    /// Rules applied to all clients
+    all: OperationFilterRules,
+    /// Rules applied to specific clients, by client name, in addition to the rules in `all`
+    clients: HashMap<String, OperationFilterRules>,
+}
+
+/// Allow and deny lists of operation signatures
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct OperationFilterRules {
+    /// If set, only the operations matching one of these patterns are allowed
+    allow: Option<Vec<OperationPattern>>,
+    /// The operations matching one of these patterns are denied
+    deny: Vec<OperationPattern>,
+}
+
+/// Pattern matched against an operation signature
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum OperationPattern {
+    /// Matches the operation signature exactly
+    Exact(String),
+    /// Matches operation signatures starting with this prefix
+    Prefix(String),
+    #[schemars(schema_with = "pattern_regex")]
+    #[serde(deserialize_with = "deserialize_regex")]
+    /// Matches operation signatures with a regular expression
+    Regex(Regex),
+}
+
+impl OperationPattern {
+    fn matches(&self, signature: &str) -> bool {
+        match self {
+            OperationPattern::Exact(exact) => signature == exact,
+            OperationPattern::Prefix(prefix) => signature.starts_with(prefix.as_str()),
+            OperationPattern::Regex(regex) => regex.is_match(signature),
+        }
+    }
+}
+
+impl OperationFilterRules {
+    fn is_empty(&self) -> bool {
+        self.allow.is_none() && self.deny.is_empty()
+    }
+
+    fn allows(&self, signature: &str) -> bool {
+        !self.deny.iter().any(|pattern| pattern.matches(signature))
+            && self.allow.as_ref().map_or(true, |allow| {
+                allow.iter().any(|pattern| pattern.matches(signature))
+            })
+    }
+}
+
+impl OperationFilterConfig {
+    /// The client name can be chosen by the client, so client rules can only restrict the
+    /// operations allowed by the rules in `all`
+    fn allows(&self, client_name: Option<&str>, signature: &str) -> bool {
+        self.all.allows(signature)
+            && client_name
+                .and_then(|client_name| self.clients.get(client_name))
+                .map_or(true, |rules| rules.allows(signature))
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for OperationFilter {
+    type Config = OperationFilterConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let telemetry = init
+            .full_config
+            .as_ref()
+            .and_then(|config| config.get("telemetry"))
+            .and_then(|telemetry| serde_json::from_value::<TelemetryConfig>(telemetry.clone()).ok())
+            .unwrap_or_default();
+        Ok(OperationFilter {
+            config: init.config,
+            supergraph_schema: init.supergraph_schema,
+            signature_normalization_algorithm: telemetry.apollo.signature_normalization_algorithm,
+            redacted_arguments: telemetry.apollo.redaction.arguments,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if self.config.all.is_empty() && self.config.clients.values().all(|r| r.is_empty()) {
+            return service;
+        }
+
+        let this = self.clone();
+        ServiceBuilder::new()
+            .checkpoint(move |req: SupergraphRequest| {
+                // Operations that could not be parsed are rejected by query planning
+                let Some(doc) = req
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<ParsedDocument>().cloned())
+                else {
+                    return Ok(ControlFlow::Continue(req));
+                };
+                let client_name: Option<String> = req.context.get(CLIENT_NAME).ok().flatten();
+                let signature = generate_usage_reporting(
+                    &doc.executable,
+                    &doc.executable,
+                    &req.supergraph_request.body().operation_name,
+                    &this.supergraph_schema,
+                    &this.signature_normalization_algorithm,
+                    &this.redacted_arguments,
+                )
+                .stats_report_key;
+                if this.config.allows(client_name.as_deref(), &signature) {
+                    Ok(ControlFlow::Continue(req))
+                } else {
+                    let error = Error::builder()
+                        .message("Operation denied".to_string())
+                        .extension_code("OPERATION_DENIED")
+                        .build();
+                    let res = SupergraphResponse::builder()
+                        .error(error)
+                        .status_code(StatusCode::FORBIDDEN)
+                        .context(req.context)
+                        .build()?;
+                    Ok(ControlFlow::Break(res))
+                }
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+register_plugin!("apollo", "operation_filter", OperationFilter);
+
+#[cfg(test)]
+mod tests {
+    use apollo_compiler::ast;
+    use serde_json::json;
+
+    use super::*;
+    use crate::graphql;
+    use crate::plugin::test::MockSupergraphService;
+    use crate::services::layers::query_analysis::ParsedDocumentInner;
+    use crate::Context;
+
+    const SCHEMA: &str = "type Query { topProducts: [Product] } type Product { name: String }";
+    const OPERATION: &str = "query TopProducts { topProducts { name } }";
+    const SIGNATURE: &str = "# TopProducts\nquery TopProducts{topProducts{name}}";
+
+    async fn execute(
+        config: serde_json::Value,
+        client_name: Option<&str>,
+    ) -> (StatusCode, graphql::Response) {
+        let mut mock_service = MockSupergraphService::new();
+        mock_service
+            .expect_call()
+            .times(0..2)
+            .returning(move |_| Ok(SupergraphResponse::fake_builder().build().unwrap()));
+
+        let service_stack = OperationFilter::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Arc::new(SCHEMA.to_string()),
+        ))
+        .await
+        .expect("couldn't create operation_filter plugin")
+        .supergraph_service(mock_service.boxed());
+
+        let ast = ast::Document::parse(format!("{SCHEMA} {OPERATION}"), "").unwrap();
+        let (_schema, executable) = ast.to_mixed_validate().unwrap();
+        let context = Context::new();
+        context.extensions().with_lock(|mut lock| {
+            lock.insert::<ParsedDocument>(
+                ParsedDocumentInner::new(ast, Arc::new(executable), None, Default::default())
+                    .unwrap(),
+            )
+        });
+        if let Some(client_name) = client_name {
+            context
+                .insert(CLIENT_NAME, client_name.to_string())
+                .unwrap();
+        }
+        let request = SupergraphRequest::fake_builder()
+            .query(OPERATION)
+            .operation_name("TopProducts")
+            .context(context)
+            .build()
+            .unwrap();
+
+        let mut response = service_stack.oneshot(request).await.unwrap();
+        (
+            response.response.status(),
+            response.next_response().await.unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_denies_matching_operations() {
+        for pattern in [
+            json!({ "exact": SIGNATURE }),
+            json!({ "prefix": "# TopProducts\n" }),
+            json!({ "regex": "topProducts\\{.*name" }),
+        ] {
+            let (status, response) = execute(json!({ "all": { "deny": [pattern] } }), None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            assert_eq!(response.errors[0].extensions["code"], "OPERATION_DENIED");
+        }
+    }
+
+    #[tokio::test]
+    async fn it_only_lets_allowed_operations_through() {
+        let (status, _) = execute(
+            json!({ "all": { "allow": [{ "prefix": "# Other" }] } }),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, response) =
+            execute(json!({ "all": { "allow": [{ "prefix": "# Top" }] } }), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_applies_client_rules_in_addition_to_all() {
+        // A client name with its own rules does not bypass the rules of all clients
+        let config = json!({
+            "all": { "deny": [{ "exact": SIGNATURE }] },
+            "clients": { "trusted": {} }
+        });
+        let (status, _) = execute(config.clone(), Some("trusted")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = execute(config, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let config = json!({
+            "clients": { "restricted": { "deny": [{ "prefix": "# Top" }] } }
+        });
+        let (status, _) = execute(config.clone(), Some("restricted")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = execute(config, Some("other")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
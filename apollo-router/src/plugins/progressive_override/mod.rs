@@ -5,6 +5,7 @@ use std::sync::Arc;
 use apollo_compiler::schema::ExtendedType;
 use apollo_compiler::validation::Valid;
 use apollo_compiler::Schema;
+use apollo_federation::query_plan::query_planner::OverrideLabelResolution;
 use dashmap::DashMap;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -50,7 +51,7 @@ pub(crate) struct ProgressiveOverridePlugin {
 }
 
 type LabelsFromSchema = (
-    Arc<HashMap<Arc<String>, Arc<f64>>>,
+    Arc<HashMap<Arc<String>, OverrideLabelResolution>>,
     Arc<HashSet<Arc<String>>>,
 );
 
@@ -108,12 +109,8 @@ fn collect_labels_from_schema(schema: &Schema) -> LabelsFromSchema {
 
     let static_percentages = percentages
         .into_iter()
-        .filter_map(|unparsed_label| {
-            unparsed_label
-                .strip_prefix("percent(")
-                .and_then(|unparsed_label| unparsed_label.strip_suffix(')'))
-                .and_then(|percent_as_string| percent_as_string.parse::<f64>().ok())
-                .map(|parsed_float| (Arc::new(unparsed_label.to_string()), Arc::new(parsed_float)))
+        .filter_map(|label| {
+            OverrideLabelResolution::from_label(&label).map(|resolution| (label, resolution))
         })
         .collect::<HashMap<_, _>>();
 
@@ -177,12 +174,10 @@ impl Plugin for ProgressiveOverridePlugin {
             .map_request(move |request: supergraph::Request| {
                 // evaluate each percentage-based label in the schema
                 let percentage_override_labels =
-                    percentage_labels.iter().filter_map(|(label, percentage)| {
-                        if rand::random::<f64>() * 100.0 >= **percentage {
-                            None
-                        } else {
-                            Some(label.clone())
-                        }
+                    percentage_labels.iter().filter_map(|(label, resolution)| {
+                        resolution
+                            .is_enabled(rand::random::<f64>)
+                            .then(|| label.clone())
                     });
 
                 // collect any externally-resolved labels from the context
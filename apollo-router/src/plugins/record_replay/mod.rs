@@ -1,3 +1,4 @@
 mod record;
 mod recording;
 mod replay;
+mod replay_subgraphs;
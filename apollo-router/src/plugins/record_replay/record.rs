@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -9,6 +10,7 @@ use tower::BoxError;
 use tower::ServiceBuilder;
 use tower::ServiceExt as TowerServiceExt;
 
+use super::recording::recorded_headers;
 use super::recording::Recording;
 use super::recording::RequestDetails;
 use super::recording::ResponseDetails;
@@ -17,7 +19,6 @@ use crate::layers::ServiceBuilderExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
 use crate::services::execution;
-use crate::services::external::externalize_header_map;
 use crate::services::router;
 use crate::services::router::body::RouterBody;
 use crate::services::subgraph;
@@ -34,9 +35,12 @@ struct RecordConfig {
     /// The path to the directory where recordings will be stored. Defaults to
     /// the current working directory.
     storage_path: Option<PathBuf>,
+    /// Headers whose values are replaced in recordings, in addition to the
+    /// `authorization`, `cookie`, `proxy-authorization` and `set-cookie` headers.
+    redacted_headers: Option<Vec<String>>,
 }
 
-fn default_storage_path() -> PathBuf {
+pub(super) fn default_storage_path() -> PathBuf {
     std::env::current_dir().expect("failed to get current directory")
 }
 
@@ -45,6 +49,7 @@ struct Record {
     enabled: bool,
     supergraph_sdl: Arc<String>,
     storage_path: Arc<Path>,
+    redacted_headers: Arc<HashSet<String>>,
 }
 
 register_plugin!("experimental", "record", Record);
@@ -63,6 +68,14 @@ impl Plugin for Record {
             enabled: init.config.enabled,
             supergraph_sdl: init.supergraph_sdl.clone(),
             storage_path: storage_path.clone().into(),
+            redacted_headers: Arc::new(
+                init.config
+                    .redacted_headers
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|name| name.to_lowercase())
+                    .collect(),
+            ),
         };
 
         if init.config.enabled {
@@ -83,10 +96,12 @@ impl Plugin for Record {
         }
 
         let dir = self.storage_path.clone();
+        let redacted_headers = self.redacted_headers.clone();
 
         ServiceBuilder::new()
             .map_future(move |future| {
                 let dir = dir.clone();
+                let redacted_headers = redacted_headers.clone();
 
                 async move {
                     let res: router::Response = future.await?;
@@ -101,7 +116,7 @@ impl Plugin for Record {
                             .with_lock(|mut lock| lock.remove::<Recording>());
 
                         if let Some(mut recording) = recording {
-                            let res_headers = externalize_header_map(&headers)?;
+                            let res_headers = recorded_headers(&headers, &redacted_headers)?;
                             recording.client_response.headers = res_headers;
 
                             let filename = recording.filename();
@@ -146,6 +161,7 @@ impl Plugin for Record {
         }
 
         let supergraph_sdl = self.supergraph_sdl.clone();
+        let redacted_headers = self.redacted_headers.clone();
 
         ServiceBuilder::new()
             .map_request(move |req: supergraph::Request| {
@@ -173,8 +189,9 @@ impl Plugin for Record {
                     let query = req.supergraph_request.body().query.clone();
                     let operation_name = req.supergraph_request.body().operation_name.clone();
                     let variables = req.supergraph_request.body().variables.clone();
-                    let headers = externalize_header_map(req.supergraph_request.headers())
-                        .expect("failed to externalize header map");
+                    let headers =
+                        recorded_headers(req.supergraph_request.headers(), &redacted_headers)
+                            .expect("failed to externalize header map");
                     let method = req.supergraph_request.method().to_string();
                     let uri = req.supergraph_request.uri().to_string();
 
@@ -234,20 +251,26 @@ impl Plugin for Record {
         }
 
         let subgraph_name = String::from(subgraph_name);
+        let request_redacted_headers = self.redacted_headers.clone();
+        let redacted_headers = self.redacted_headers.clone();
 
         ServiceBuilder::new()
             .map_future_with_request_data(
-                |req: &subgraph::Request| RequestDetails {
+                move |req: &subgraph::Request| RequestDetails {
                     query: req.subgraph_request.body().query.clone(),
                     operation_name: req.subgraph_request.body().operation_name.clone(),
                     variables: req.subgraph_request.body().variables.clone(),
-                    headers: externalize_header_map(req.subgraph_request.headers())
-                        .expect("failed to externalize header map"),
+                    headers: recorded_headers(
+                        req.subgraph_request.headers(),
+                        &request_redacted_headers,
+                    )
+                    .expect("failed to externalize header map"),
                     method: req.subgraph_request.method().to_string(),
                     uri: req.subgraph_request.uri().to_string(),
                 },
                 move |req: RequestDetails, future| {
                     let subgraph_name = subgraph_name.clone();
+                    let redacted_headers = redacted_headers.clone();
                     async move {
                         let res: subgraph::ServiceResult = future.await;
 
@@ -261,8 +284,9 @@ impl Plugin for Record {
                                 let subgraph = Subgraph {
                                     subgraph_name,
                                     response: ResponseDetails {
-                                        headers: externalize_header_map(
-                                            &res.response.headers().clone(),
+                                        headers: recorded_headers(
+                                            res.response.headers(),
+                                            &redacted_headers,
                                         )
                                         .expect("failed to externalize header map"),
                                         chunks: vec![res.response.body().clone()],
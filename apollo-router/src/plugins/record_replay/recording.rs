@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use http::HeaderMap;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json_bytes::ByteString;
@@ -9,8 +11,19 @@ use serde_json_bytes::Map;
 use serde_json_bytes::Value;
 use sha2::Digest;
 use sha2::Sha256;
+use tower::BoxError;
 
 use crate::graphql::Response;
+use crate::services::external::externalize_header_map;
+
+/// Headers whose values are never written to recordings
+const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+];
+const REDACTED_HEADER_VALUE: &str = "<redacted>";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub(crate) struct Recording {
@@ -43,7 +56,7 @@ pub(crate) struct RequestDetails {
     pub(crate) query: Option<String>,
     pub(crate) operation_name: Option<String>,
     pub(crate) variables: Map<ByteString, Value>,
-    pub(crate) headers: HashMap<String, Vec<String>>,
+    pub(crate) headers: Headers,
     pub(crate) method: String,
     pub(crate) uri: String,
 }
@@ -51,7 +64,7 @@ pub(crate) struct RequestDetails {
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub(crate) struct ResponseDetails {
     pub(crate) chunks: Vec<Response>,
-    pub(crate) headers: HashMap<String, Vec<String>>,
+    pub(crate) headers: Headers,
 }
 
 #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -61,4 +74,51 @@ pub(crate) struct Subgraph {
     pub(crate) response: ResponseDetails,
 }
 
-pub(crate) type Subgraphs = HashMap<String, Subgraph>;
+// Ordered maps keep recordings of the same traffic identical
+pub(crate) type Subgraphs = BTreeMap<String, Subgraph>;
+pub(crate) type Headers = BTreeMap<String, Vec<String>>;
+
+/// Converts headers to their recorded form, replacing the values of sensitive headers and of
+/// the `redacted_headers` (lowercase names).
+pub(super) fn recorded_headers(
+    headers: &HeaderMap,
+    redacted_headers: &HashSet<String>,
+) -> Result<Headers, BoxError> {
+    Ok(externalize_header_map(headers)?
+        .into_iter()
+        .map(|(name, values)| {
+            if SENSITIVE_HEADERS.contains(&name.as_str()) || redacted_headers.contains(&name) {
+                let redacted = vec![REDACTED_HEADER_VALUE.to_string(); values.len()];
+                (name, redacted)
+            } else {
+                (name, values)
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn it_redacts_sensitive_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-api-key", HeaderValue::from_static("secret"));
+        headers.insert("x-request-id", HeaderValue::from_static("1"));
+
+        let recorded =
+            recorded_headers(&headers, &HashSet::from(["x-api-key".to_string()])).unwrap();
+        assert_eq!(
+            serde_json::to_value(recorded).unwrap(),
+            serde_json::json!({
+                "authorization": ["<redacted>"],
+                "x-api-key": ["<redacted>"],
+                "x-request-id": ["1"]
+            })
+        );
+    }
+}
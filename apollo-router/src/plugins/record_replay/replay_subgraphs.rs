@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::fs;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt as TowerServiceExt;
+
+use super::record::default_storage_path;
+use super::recording::Recording;
+use super::recording::Subgraph;
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::subgraph;
+
+/// Subgraph replay configuration.
+#[derive(Debug, Clone, serde::Deserialize, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ReplayConfig {
+    /// The replay plugin is disabled by default.
+    enabled: bool,
+    /// The path to the directory containing the recordings to replay. Defaults
+    /// to the current working directory.
+    storage_path: Option<PathBuf>,
+}
+
+/// Answers subgraph requests with the responses found in recordings, instead
+/// of sending them to the subgraphs.
+#[derive(Debug)]
+struct ReplaySubgraphs {
+    enabled: bool,
+    /// Recorded fetches by subgraph name and operation name
+    fetches: Arc<HashMap<String, HashMap<String, Vec<Subgraph>>>>,
+}
+
+register_plugin!("experimental", "replay", ReplaySubgraphs);
+
+#[async_trait::async_trait]
+impl Plugin for ReplaySubgraphs {
+    type Config = ReplayConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let fetches = if init.config.enabled {
+            let storage_path = init
+                .config
+                .storage_path
+                .unwrap_or_else(default_storage_path);
+            load_fetches(&storage_path).await?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            enabled: init.config.enabled,
+            fetches: Arc::new(fetches),
+        })
+    }
+
+    fn subgraph_service(
+        &self,
+        subgraph_name: &str,
+        service: subgraph::BoxService,
+    ) -> subgraph::BoxService {
+        if !self.enabled {
+            return service;
+        }
+
+        let subgraph_name = String::from(subgraph_name);
+        let fetches = self.fetches.clone();
+
+        ServiceBuilder::new()
+            .checkpoint(move |req: subgraph::Request| {
+                let body = req.subgraph_request.body();
+                let operation_name = body
+                    .operation_name
+                    .clone()
+                    .unwrap_or("UnnamedOperation".to_string());
+                let recorded = fetches
+                    .get(&subgraph_name)
+                    .and_then(|operations| operations.get(&operation_name))
+                    .and_then(|recorded| {
+                        // Prefer the fetch recorded with the same variables
+                        recorded
+                            .iter()
+                            .find(|fetch| fetch.request.variables == body.variables)
+                            .or_else(|| recorded.first())
+                    });
+
+                let response = match recorded.and_then(|fetch| fetch.response.chunks.first()) {
+                    Some(response) => response.clone(),
+                    None => graphql::Response::builder()
+                        .error(
+                            graphql::Error::builder()
+                                .message(format!(
+                                    "no recorded response for operation '{operation_name}' of subgraph '{subgraph_name}'"
+                                ))
+                                .extension_code("RECORDING_NOT_FOUND")
+                                .build(),
+                        )
+                        .build(),
+                };
+
+                Ok(ControlFlow::Break(subgraph::Response::new_from_response(
+                    http::Response::new(response),
+                    req.context.clone(),
+                    subgraph_name.clone(),
+                    req.id.clone(),
+                )))
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Loads the subgraph fetches of all the recordings in a directory
+async fn load_fetches(
+    dir: &Path,
+) -> Result<HashMap<String, HashMap<String, Vec<Subgraph>>>, BoxError> {
+    let mut paths = Vec::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    // Load recordings in a stable order, so that the same fetch is replayed
+    // when several recordings contain it
+    paths.sort();
+
+    let mut fetches: HashMap<String, HashMap<String, Vec<Subgraph>>> = HashMap::new();
+    for path in paths {
+        let recording: Recording = serde_json::from_str(&fs::read_to_string(&path).await?)
+            .map_err(|err| format!("invalid recording {}: {err}", path.display()))?;
+        for (operation_name, fetch) in recording.subgraph_fetches.unwrap_or_default() {
+            fetches
+                .entry(fetch.subgraph_name.clone())
+                .or_default()
+                .entry(operation_name)
+                .or_default()
+                .push(fetch);
+        }
+    }
+    Ok(fetches)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::plugin::test::MockSubgraphService;
+
+    async fn replay_service(dir: &Path) -> subgraph::BoxService {
+        let recording = json!({
+            "supergraph_sdl": "",
+            "client_request": {
+                "query": "{ topProducts { name } }",
+                "operation_name": null,
+                "variables": {},
+                "headers": {},
+                "method": "POST",
+                "uri": "http://localhost/"
+            },
+            "client_response": { "chunks": [], "headers": {} },
+            "formatted_query_plan": null,
+            "subgraph_fetches": {
+                "TopProducts__products__0": {
+                    "subgraph_name": "products",
+                    "request": {
+                        "query": "query TopProducts__products__0{topProducts{name}}",
+                        "operation_name": "TopProducts__products__0",
+                        "variables": {},
+                        "headers": {},
+                        "method": "POST",
+                        "uri": "http://products/"
+                    },
+                    "response": {
+                        "chunks": [{ "data": { "topProducts": [{ "name": "Table" }] } }],
+                        "headers": {}
+                    }
+                }
+            }
+        });
+        std::fs::write(
+            dir.join("TopProducts.json"),
+            serde_json::to_string(&recording).unwrap(),
+        )
+        .unwrap();
+
+        let plugin = ReplaySubgraphs::new(PluginInit::fake_new(
+            ReplayConfig {
+                enabled: true,
+                storage_path: Some(dir.to_path_buf()),
+            },
+            Default::default(),
+        ))
+        .await
+        .unwrap();
+        // The subgraph is never called
+        plugin.subgraph_service("products", MockSubgraphService::new().boxed())
+    }
+
+    async fn call(service: subgraph::BoxService, operation_name: &str) -> graphql::Response {
+        let request = subgraph::Request::fake_builder()
+            .subgraph_request(
+                http::Request::builder()
+                    .body(
+                        graphql::Request::fake_builder()
+                            .operation_name(operation_name)
+                            .build(),
+                    )
+                    .unwrap(),
+            )
+            .build();
+        service.oneshot(request).await.unwrap().response.into_body()
+    }
+
+    #[tokio::test]
+    async fn it_replays_recorded_subgraph_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let response = call(replay_service(dir.path()).await, "TopProducts__products__0").await;
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({ "data": { "topProducts": [{ "name": "Table" }] } })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_does_not_call_subgraphs_for_missing_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        let response = call(replay_service(dir.path()).await, "Other__products__0").await;
+        assert_eq!(response.errors[0].extensions["code"], "RECORDING_NOT_FOUND");
+    }
+}
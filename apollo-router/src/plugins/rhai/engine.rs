@@ -2,6 +2,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use std::time::SystemTime;
 
 use base64::prelude::BASE64_STANDARD;
@@ -1711,6 +1712,7 @@ pub(crate) struct RhaiService {
     pub(super) service: ServiceStep,
     pub(super) engine: Arc<Engine>,
     pub(super) ast: AST,
+    pub(super) execution_timeout: Option<Duration>,
 }
 
 impl Rhai {
@@ -1724,6 +1726,7 @@ impl Rhai {
         let block = self.block.load();
         let rhai_service = RhaiService {
             scope: scope.clone(),
+            execution_timeout: self.execution_timeouts.of(&service),
             service,
             engine: block.engine.clone(),
             ast: block.ast.clone(),
@@ -1757,8 +1760,30 @@ impl Rhai {
         Ok(())
     }
 
-    pub(super) fn new_rhai_engine(path: Option<PathBuf>, sdl: String, main: PathBuf) -> Engine {
+    pub(super) fn new_rhai_engine(
+        path: Option<PathBuf>,
+        sdl: String,
+        main: PathBuf,
+        execution_timeouts: bool,
+    ) -> Engine {
         let mut engine = Engine::new();
+        // Terminate callbacks which run for longer than allowed. The start and the timeout of
+        // the callback being executed are tracked by `execute()`.
+        if execution_timeouts {
+            engine.on_progress(move |_operations| {
+                let deadline = super::CALLBACK_DEADLINE.with(|deadline| deadline.get());
+                match deadline {
+                    Some((started_at, timeout)) if started_at.elapsed() > timeout => Some(
+                        format!(
+                            "callback exceeded its execution timeout of {}",
+                            humantime::format_duration(timeout)
+                        )
+                        .into(),
+                    ),
+                    _ => None,
+                }
+            });
+        }
         // If we pass in a path, use it to configure our engine
         // with a FileModuleResolver which allows import to work
         // in scripts.
@@ -1,5 +1,6 @@
 //! Customization via Rhai.
 
+use std::cell::Cell;
 use std::fmt;
 use std::ops::ControlFlow;
 use std::path::PathBuf;
@@ -51,6 +52,15 @@ mod engine;
 
 pub(crate) const RHAI_SPAN_NAME: &str = "rhai_plugin";
 
+thread_local! {
+    /// When the Rhai callback currently executing on this thread started, and its timeout.
+    ///
+    /// Callbacks run synchronously, so this is what the engine progress handler uses to
+    /// enforce the execution timeout of the stage the callback belongs to.
+    static CALLBACK_DEADLINE: Cell<Option<(std::time::Instant, Duration)>> =
+        const { Cell::new(None) };
+}
+
 mod execution;
 mod router;
 mod subgraph;
@@ -67,11 +77,13 @@ impl EngineBlock {
         scripts: Option<PathBuf>,
         main: PathBuf,
         sdl: Arc<String>,
+        execution_timeouts: ExecutionTimeouts,
     ) -> Result<Self, BoxError> {
         let engine = Arc::new(Rhai::new_rhai_engine(
             scripts,
             sdl.to_string(),
             main.clone(),
+            execution_timeouts.is_enabled(),
         ));
         let ast = engine
             .compile_file(main.clone())
@@ -103,6 +115,7 @@ impl EngineBlock {
 /// we'll use ArcSwap to accomplish our goal.
 struct Rhai {
     block: Arc<ArcSwap<EngineBlock>>,
+    execution_timeouts: ExecutionTimeouts,
     park_flag: Arc<AtomicBool>,
     watcher_handle: Option<std::thread::JoinHandle<()>>,
 }
@@ -115,6 +128,74 @@ pub(crate) struct Conf {
     scripts: Option<PathBuf>,
     /// The main entry point for Rhai script evaluation
     main: Option<String>,
+    /// The maximum duration of a single callback (`map_request`, `map_response`, ...)
+    /// execution. Callbacks exceeding it are terminated and fail the request. Defaults to no limit.
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    execution_timeout: Option<Duration>,
+    /// The maximum duration of a single callback execution for the callbacks of a stage,
+    /// overriding `execution_timeout`
+    #[serde(default)]
+    stage_execution_timeouts: StageExecutionTimeouts,
+}
+
+/// The maximum duration of a single callback execution, per stage
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct StageExecutionTimeouts {
+    /// The execution timeout of the router stage callbacks
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    router: Option<Duration>,
+    /// The execution timeout of the supergraph stage callbacks
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    supergraph: Option<Duration>,
+    /// The execution timeout of the execution stage callbacks
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    execution: Option<Duration>,
+    /// The execution timeout of the subgraph stage callbacks
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    subgraph: Option<Duration>,
+}
+
+/// The execution timeouts of the callbacks of each stage, resolved from the configuration
+#[derive(Clone, Copy, Debug, Default)]
+struct ExecutionTimeouts {
+    router: Option<Duration>,
+    supergraph: Option<Duration>,
+    execution: Option<Duration>,
+    subgraph: Option<Duration>,
+}
+
+impl ExecutionTimeouts {
+    fn new(default: Option<Duration>, stages: StageExecutionTimeouts) -> Self {
+        Self {
+            router: stages.router.or(default),
+            supergraph: stages.supergraph.or(default),
+            execution: stages.execution.or(default),
+            subgraph: stages.subgraph.or(default),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.router.is_some()
+            || self.supergraph.is_some()
+            || self.execution.is_some()
+            || self.subgraph.is_some()
+    }
+
+    /// The execution timeout of the callbacks registered by a service
+    fn of(&self, service: &ServiceStep) -> Option<Duration> {
+        match service {
+            ServiceStep::Router(_) => self.router,
+            ServiceStep::Supergraph(_) => self.supergraph,
+            ServiceStep::Execution(_) => self.execution,
+            ServiceStep::Subgraph(_) => self.subgraph,
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -134,6 +215,10 @@ impl Plugin for Rhai {
         };
 
         let main = scripts_path.join(main_file);
+        let execution_timeouts = ExecutionTimeouts::new(
+            init.config.execution_timeout,
+            init.config.stage_execution_timeouts,
+        );
 
         let watched_path = scripts_path.clone();
         let watched_main = main.clone();
@@ -143,6 +228,7 @@ impl Plugin for Rhai {
             Some(scripts_path),
             main,
             sdl,
+            execution_timeouts,
         )?));
         let watched_block = block.clone();
 
@@ -182,6 +268,7 @@ impl Plugin for Rhai {
                                         Some(watching_path.clone()),
                                         watched_main.clone(),
                                         watched_sdl.clone(),
+                                        execution_timeouts,
                                     ) {
                                         Ok(eb) => {
                                             tracing::info!("updating rhai execution engine");
@@ -217,6 +304,7 @@ impl Plugin for Rhai {
 
         Ok(Self {
             block,
+            execution_timeouts,
             park_flag,
             watcher_handle: Some(watcher_handle),
         })
@@ -812,14 +900,20 @@ fn execute(
     callback: &FnPtr,
     args: impl FuncArgs,
 ) -> Result<Dynamic, Box<EvalAltResult>> {
-    if callback.is_curried() {
+    let deadline = rhai_service
+        .execution_timeout
+        .map(|timeout| (std::time::Instant::now(), timeout));
+    let previous_deadline = CALLBACK_DEADLINE.with(|current| current.replace(deadline));
+    let result = if callback.is_curried() {
         callback.call(&rhai_service.engine, &rhai_service.ast, args)
     } else {
         let mut guard = rhai_service.scope.lock().unwrap();
         rhai_service
             .engine
             .call_fn(&mut guard, &rhai_service.ast, callback.fn_name(), args)
-    }
+    };
+    CALLBACK_DEADLINE.with(|current| current.set(previous_deadline));
+    result
 }
 
 register_plugin!("apollo", "rhai", Rhai);
@@ -3,6 +3,7 @@
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use std::time::SystemTime;
 
 use http::HeaderMap;
@@ -234,7 +235,7 @@ async fn rhai_plugin_execution_service_error() -> Result<(), BoxError> {
 // A Rhai engine suitable for minimal testing. There are no scripts and the SDL is an empty
 // string.
 fn new_rhai_test_engine() -> Engine {
-    Rhai::new_rhai_engine(None, "".to_string(), PathBuf::new())
+    Rhai::new_rhai_engine(None, "".to_string(), PathBuf::new(), false)
 }
 
 // Some of these tests rely extensively on internal implementation details of the tracing_test crate.
@@ -873,3 +874,44 @@ async fn it_can_access_demand_control_context() -> Result<(), BoxError> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn it_terminates_callbacks_exceeding_the_execution_timeout_of_their_stage() {
+    let mut mock_service = MockSupergraphService::new();
+    // The callback never completes, so the request never reaches the next service
+    mock_service.expect_call().never();
+
+    // The timeout of the supergraph stage overrides the default one, which the test would not
+    // complete within
+    let dyn_plugin: Box<dyn DynPlugin> = crate::plugin::plugins()
+        .find(|factory| factory.name == "apollo.rhai")
+        .expect("Plugin not found")
+        .create_instance_without_schema(&serde_json::json!({
+            "scripts": "tests/fixtures",
+            "main": "execution_timeout.rhai",
+            "execution_timeout": "1h",
+            "stage_execution_timeouts": { "supergraph": "10ms" }
+        }))
+        .await
+        .unwrap();
+    let mut supergraph_service = dyn_plugin.supergraph_service(BoxService::new(mock_service));
+
+    let mut supergraph_resp = tokio::time::timeout(
+        Duration::from_secs(10),
+        supergraph_service
+            .ready()
+            .await
+            .unwrap()
+            .call(SupergraphRequest::fake_builder().build().unwrap()),
+    )
+    .await
+    .expect("the callback was not terminated")
+    .unwrap();
+
+    assert_eq!(
+        supergraph_resp.response.status(),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+    let resp = supergraph_resp.next_response().await.unwrap();
+    assert!(resp.errors[0].message.contains("Script terminated"));
+}
@@ -0,0 +1,633 @@
+//! Schema usage insights
+//!
+//! Aggregates the fields referenced by the operations over a rolling window, and serves a report
+//! of the usage of every field of the schema, so that the fields that are never requested can be
+//! found without sending usage reports to Apollo Studio.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::validation::Valid;
+use http::StatusCode;
+use multimap::MultiMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::service_fn;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::apollo_studio_interop::UsageReporting;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::register_plugin;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::services::supergraph;
+use crate::Endpoint;
+use crate::ListenAddr;
+
+/// Number of buckets of the rolling window: the usage is expired one bucket at a time
+const WINDOW_BUCKETS: u32 = 24;
+
+/// Number of operations a bucket counts the requests of before adding them to its fields
+const MAX_BUCKET_OPERATIONS: usize = 1000;
+
+/// The name the requests of the clients over `max_clients` are counted under
+const OTHER_CLIENTS: &str = "(other)";
+
+/// The usage is kept across schema and configuration reloads
+static SCHEMA_USAGE: Lazy<Arc<UsageStore>> = Lazy::new(Default::default);
+
+/// Schema usage insights configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct SchemaUsageConfig {
+    /// Set to true to aggregate the usage of the schema fields and serve the usage report
+    enabled: bool,
+    /// The duration of the rolling window over which the usage is aggregated
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    window: Duration,
+    /// The maximum number of clients the usage is reported for. The requests of other clients
+    /// are counted under the `(other)` name, until a client that sent no request during the
+    /// window is forgotten
+    max_clients: usize,
+    /// How long the last time a client requested a field is kept. It is kept at least for the
+    /// duration of the window
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    last_seen_retention: Duration,
+    /// The socket address and port the usage report is served on
+    listen: ListenAddr,
+    /// The path of the usage report
+    path: String,
+}
+
+impl Default for SchemaUsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: Duration::from_secs(7 * 24 * 60 * 60),
+            max_clients: 100,
+            last_seen_retention: Duration::from_secs(30 * 24 * 60 * 60),
+            listen: SocketAddr::from_str("127.0.0.1:9090").unwrap().into(),
+            path: "/schema-usage".to_string(),
+        }
+    }
+}
+
+/// Values per field coordinate, by type name then field name, and client
+type FieldsByClient<T> = HashMap<String, HashMap<String, HashMap<Arc<str>, T>>>;
+
+/// Usage of the schema fields, over a rolling window
+#[derive(Default)]
+struct UsageStore {
+    inner: Mutex<UsageData>,
+}
+
+#[derive(Default)]
+struct UsageData {
+    /// Number of requests per field and client, in each bucket of the window
+    buckets: VecDeque<Bucket>,
+    /// The last time each field was requested by each client, during the retention period
+    last_seen: FieldsByClient<SystemTime>,
+    /// The clients the usage is reported for, with the last time they sent a request
+    clients: HashMap<Arc<str>, SystemTime>,
+}
+
+struct Bucket {
+    start: SystemTime,
+    /// Requests per operation, added to `fields` when the bucket is closed or reported, so that
+    /// a request only updates the counters of its operation
+    operations: HashMap<String, OperationRequests>,
+    /// Number of requests per field and client
+    fields: FieldsByClient<u64>,
+}
+
+struct OperationRequests {
+    usage: Arc<UsageReporting>,
+    /// Number of requests and time of the last one, per client
+    clients: HashMap<Arc<str>, (u64, SystemTime)>,
+}
+
+impl Bucket {
+    fn new(start: SystemTime) -> Self {
+        Self {
+            start,
+            operations: HashMap::new(),
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Adds the requests of the operations to the fields they reference
+    fn add_operations_to_fields(&mut self, last_seen: &mut FieldsByClient<SystemTime>) {
+        for (_, operation) in self.operations.drain() {
+            for (type_name, fields) in &operation.usage.referenced_fields_by_type {
+                for field_name in &fields.field_names {
+                    let requests = field_entry(&mut self.fields, type_name, field_name);
+                    let field_last_seen = field_entry(last_seen, type_name, field_name);
+                    for (client, (count, time)) in &operation.clients {
+                        *requests.entry(client.clone()).or_default() += count;
+                        let client_last_seen =
+                            field_last_seen.entry(client.clone()).or_insert(*time);
+                        *client_last_seen = (*client_last_seen).max(*time);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The values of a field, only allocating its coordinate the first time it is seen
+fn field_entry<'a, T>(
+    fields: &'a mut FieldsByClient<T>,
+    type_name: &str,
+    field_name: &str,
+) -> &'a mut HashMap<Arc<str>, T> {
+    if !fields.contains_key(type_name) {
+        fields.insert(type_name.to_string(), HashMap::new());
+    }
+    let type_fields = fields.get_mut(type_name).expect("the type was just added");
+    if !type_fields.contains_key(field_name) {
+        type_fields.insert(field_name.to_string(), HashMap::new());
+    }
+    type_fields
+        .get_mut(field_name)
+        .expect("the field was just added")
+}
+
+impl UsageData {
+    /// Removes the buckets that are entirely out of the window
+    fn expire(&mut self, now: SystemTime, window: Duration) {
+        let bucket_duration = window / WINDOW_BUCKETS;
+        while let Some(bucket) = self.buckets.front() {
+            if bucket.start + bucket_duration + window > now {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Starts a new bucket when the last one is over. This is when the usage out of the window
+    /// or the retention period is removed, and the clients without requests in the window are
+    /// forgotten.
+    fn rotate(&mut self, now: SystemTime, config: &SchemaUsageConfig) {
+        let bucket_duration = config.window / WINDOW_BUCKETS;
+        if self
+            .buckets
+            .back()
+            .is_some_and(|bucket| bucket.start + bucket_duration > now)
+        {
+            return;
+        }
+        if let Some(bucket) = self.buckets.back_mut() {
+            bucket.add_operations_to_fields(&mut self.last_seen);
+        }
+        self.expire(now, config.window);
+        self.buckets.push_back(Bucket::new(now));
+
+        let retention = config.last_seen_retention.max(config.window);
+        for fields in self.last_seen.values_mut() {
+            for clients in fields.values_mut() {
+                clients.retain(|_, last_seen| *last_seen + retention > now);
+            }
+            fields.retain(|_, clients| !clients.is_empty());
+        }
+        self.last_seen.retain(|_, fields| !fields.is_empty());
+        self.clients
+            .retain(|_, last_seen| *last_seen + config.window > now);
+    }
+
+    /// The name a client is counted under, only allocated the first time it is seen
+    fn client(&mut self, name: &str, now: SystemTime, max_clients: usize) -> Arc<str> {
+        let name = if self.clients.contains_key(name) || self.clients.len() < max_clients {
+            name
+        } else {
+            OTHER_CLIENTS
+        };
+        let client = match self.clients.get_key_value(name) {
+            Some((client, _)) => client.clone(),
+            None => Arc::from(name),
+        };
+        self.clients.insert(client.clone(), now);
+        client
+    }
+}
+
+impl UsageStore {
+    fn record(
+        &self,
+        usage: &Arc<UsageReporting>,
+        client_name: &str,
+        now: SystemTime,
+        config: &SchemaUsageConfig,
+    ) {
+        let mut data = self.inner.lock();
+        data.rotate(now, config);
+        let client = data.client(client_name, now, config.max_clients);
+        let UsageData {
+            buckets, last_seen, ..
+        } = &mut *data;
+        let bucket = buckets.back_mut().expect("a bucket was just added");
+        if !bucket.operations.contains_key(&usage.stats_report_key) {
+            if bucket.operations.len() >= MAX_BUCKET_OPERATIONS {
+                bucket.add_operations_to_fields(last_seen);
+            }
+            bucket.operations.insert(
+                usage.stats_report_key.clone(),
+                OperationRequests {
+                    usage: usage.clone(),
+                    clients: HashMap::new(),
+                },
+            );
+        }
+        let requests = bucket
+            .operations
+            .get_mut(&usage.stats_report_key)
+            .expect("the operation was just added")
+            .clients
+            .entry(client)
+            .or_insert((0, now));
+        requests.0 += 1;
+        requests.1 = now;
+    }
+
+    fn report(&self, schema_fields: &[String], now: SystemTime, window: Duration) -> UsageReport {
+        let mut data = self.inner.lock();
+        data.expire(now, window);
+        let UsageData {
+            buckets, last_seen, ..
+        } = &mut *data;
+        for bucket in buckets.iter_mut() {
+            bucket.add_operations_to_fields(last_seen);
+        }
+
+        let mut requests: HashMap<(&str, &str), HashMap<&str, u64>> = HashMap::new();
+        for bucket in buckets.iter() {
+            for (type_name, fields) in &bucket.fields {
+                for (field_name, clients) in fields {
+                    let field_requests = requests
+                        .entry((type_name.as_str(), field_name.as_str()))
+                        .or_default();
+                    for (client_name, count) in clients {
+                        *field_requests.entry(client_name.as_ref()).or_default() += count;
+                    }
+                }
+            }
+        }
+
+        let fields: Vec<FieldUsage> = schema_fields
+            .iter()
+            .map(|coordinate| {
+                let (type_name, field_name) = coordinate
+                    .split_once('.')
+                    .unwrap_or((coordinate.as_str(), ""));
+                let field_requests = requests
+                    .remove(&(type_name, field_name))
+                    .unwrap_or_default();
+                let last_seen = last_seen
+                    .get(type_name)
+                    .and_then(|fields| fields.get(field_name));
+                let mut clients: Vec<ClientUsage> = last_seen
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, last_seen)| ClientUsage {
+                        name: name.to_string(),
+                        requests: field_requests.get(name.as_ref()).copied().unwrap_or(0),
+                        last_seen: format_time(*last_seen),
+                    })
+                    .collect();
+                clients.sort_by(|a, b| a.name.cmp(&b.name));
+                FieldUsage {
+                    coordinate: coordinate.clone(),
+                    requests: field_requests.values().sum(),
+                    last_seen: last_seen
+                        .and_then(|clients| clients.values().max())
+                        .map(|last_seen| format_time(*last_seen)),
+                    clients,
+                }
+            })
+            .collect();
+
+        UsageReport {
+            window_start: format_time(now - window),
+            unused_fields: fields
+                .iter()
+                .filter(|field| field.requests == 0)
+                .map(|field| field.coordinate.clone())
+                .collect(),
+            fields,
+        }
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+/// The usage report served by the endpoint
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageReport {
+    /// The start of the rolling window the request counts are aggregated over
+    window_start: String,
+    /// The coordinates of the fields that were not requested during the window
+    unused_fields: Vec<String>,
+    fields: Vec<FieldUsage>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FieldUsage {
+    coordinate: String,
+    /// Number of requests during the window
+    requests: u64,
+    /// The last time the field was requested, even before the window
+    last_seen: Option<String>,
+    clients: Vec<ClientUsage>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientUsage {
+    name: String,
+    requests: u64,
+    last_seen: String,
+}
+
+/// The coordinates of the fields of the object and interface types of the schema, except the
+/// inaccessible ones
+fn schema_fields(schema: &Valid<apollo_compiler::Schema>) -> Vec<String> {
+    let mut coordinates = Vec::new();
+    for (type_name, ty) in &schema.types {
+        if ty.is_built_in() || ty.directives().has("inaccessible") {
+            continue;
+        }
+        let fields = match ty {
+            ExtendedType::Object(object) => &object.fields,
+            ExtendedType::Interface(interface) => &interface.fields,
+            _ => continue,
+        };
+        for (field_name, field) in fields {
+            if !field.directives.has("inaccessible") {
+                coordinates.push(format!("{type_name}.{field_name}"));
+            }
+        }
+    }
+    coordinates.sort();
+    coordinates
+}
+
+struct SchemaUsage {
+    config: SchemaUsageConfig,
+    store: Arc<UsageStore>,
+    schema_fields: Arc<[String]>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for SchemaUsage {
+    type Config = SchemaUsageConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(SchemaUsage {
+            schema_fields: schema_fields(&init.supergraph_schema).into(),
+            config: init.config,
+            store: SCHEMA_USAGE.clone(),
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.config.enabled {
+            return service;
+        }
+
+        let store = self.store.clone();
+        let config = self.config.clone();
+        service
+            .map_response(move |response: supergraph::Response| {
+                let usage = response
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<Arc<UsageReporting>>().cloned());
+                if let Some(usage) = usage {
+                    let client_name: String = response
+                        .context
+                        .get(CLIENT_NAME)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    store.record(&usage, &client_name, SystemTime::now(), &config);
+                }
+                response
+            })
+            .boxed()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if !self.config.enabled {
+            return map;
+        }
+
+        let store = self.store.clone();
+        let schema_fields = self.schema_fields.clone();
+        let window = self.config.window;
+        let endpoint = service_fn(move |req: router::Request| {
+            let report = store.report(&schema_fields, SystemTime::now(), window);
+            async move {
+                Ok::<_, BoxError>(router::Response {
+                    response: http::Response::builder()
+                        .status(StatusCode::OK)
+                        .header(http::header::CONTENT_TYPE, "application/json")
+                        .body::<Body>(serde_json::to_vec(&report)?.into())
+                        .map_err(BoxError::from)?,
+                    context: req.context,
+                })
+            }
+        });
+        tracing::info!(
+            "Schema usage report exposed at {}{}",
+            self.config.listen,
+            self.config.path
+        );
+        map.insert(
+            self.config.listen.clone(),
+            Endpoint::from_router_service(self.config.path.clone(), endpoint.boxed()),
+        );
+        map
+    }
+}
+
+register_plugin!("apollo", "schema_usage", SchemaUsage);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apollo_studio_interop::ReferencedFieldsForType;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn config(window: Duration) -> SchemaUsageConfig {
+        SchemaUsageConfig {
+            window,
+            ..Default::default()
+        }
+    }
+
+    fn usage(fields: &[(&str, &[&str])]) -> Arc<UsageReporting> {
+        let stats_report_key = fields
+            .iter()
+            .map(|(type_name, field_names)| format!("{type_name}{{{}}}", field_names.join(" ")))
+            .collect::<String>();
+        Arc::new(UsageReporting {
+            stats_report_key,
+            referenced_fields_by_type: fields
+                .iter()
+                .map(|(type_name, field_names)| {
+                    (
+                        type_name.to_string(),
+                        ReferencedFieldsForType {
+                            field_names: field_names.iter().map(|name| name.to_string()).collect(),
+                            is_interface: false,
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn it_reports_unused_fields_and_clients() {
+        let schema_fields: Vec<String> = ["Query.me", "User.id", "User.name"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let store = UsageStore::default();
+        let window = 24 * HOUR;
+        let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+        store.record(
+            &usage(&[("Query", &["me"]), ("User", &["name"])]),
+            "web",
+            start,
+            &config(window),
+        );
+        store.record(
+            &usage(&[("Query", &["me"])]),
+            "ios",
+            start + HOUR,
+            &config(window),
+        );
+
+        let report = store.report(&schema_fields, start + 2 * HOUR, window);
+        assert_eq!(report.unused_fields, vec!["User.id"]);
+        let me = &report.fields[0];
+        assert_eq!(me.requests, 2);
+        assert_eq!(me.last_seen, Some(format_time(start + HOUR)));
+        let clients: Vec<_> = me
+            .clients
+            .iter()
+            .map(|client| (client.name.as_str(), client.requests))
+            .collect();
+        assert_eq!(clients, vec![("ios", 1), ("web", 1)]);
+    }
+
+    #[test]
+    fn it_expires_usage_out_of_the_window() {
+        let schema_fields = vec!["Query.me".to_string()];
+        let store = UsageStore::default();
+        let window = 24 * HOUR;
+        let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+        store.record(&usage(&[("Query", &["me"])]), "web", start, &config(window));
+
+        let report = store.report(&schema_fields, start + 12 * HOUR, window);
+        assert!(report.unused_fields.is_empty());
+
+        // The last time a field was seen is kept after its requests left the window
+        let report = store.report(&schema_fields, start + 26 * HOUR, window);
+        assert_eq!(report.unused_fields, vec!["Query.me"]);
+        assert_eq!(report.fields[0].last_seen, Some(format_time(start)));
+        assert_eq!(report.fields[0].clients[0].requests, 0);
+    }
+
+    #[test]
+    fn it_caps_the_number_of_clients() {
+        let schema_fields = vec!["Query.me".to_string()];
+        let store = UsageStore::default();
+        let config = SchemaUsageConfig {
+            window: 24 * HOUR,
+            max_clients: 2,
+            ..Default::default()
+        };
+        let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+        for client in ["web", "ios", "android", "tv"] {
+            store.record(&usage(&[("Query", &["me"])]), client, start, &config);
+        }
+        let report = store.report(&schema_fields, start, config.window);
+        let clients: Vec<_> = report.fields[0]
+            .clients
+            .iter()
+            .map(|client| (client.name.as_str(), client.requests))
+            .collect();
+        assert_eq!(clients, vec![("(other)", 2), ("ios", 1), ("web", 1)]);
+
+        // Clients without requests during the window are forgotten, which makes room for others
+        store.record(
+            &usage(&[("Query", &["me"])]),
+            "android",
+            start + 25 * HOUR,
+            &config,
+        );
+        let report = store.report(&schema_fields, start + 25 * HOUR, config.window);
+        let clients: Vec<_> = report.fields[0]
+            .clients
+            .iter()
+            .map(|client| (client.name.as_str(), client.requests))
+            .collect();
+        assert_eq!(
+            clients,
+            vec![("(other)", 0), ("android", 1), ("ios", 0), ("web", 0)]
+        );
+    }
+
+    #[test]
+    fn it_expires_last_seen_after_the_retention() {
+        let schema_fields = vec!["Query.me".to_string(), "User.name".to_string()];
+        let store = UsageStore::default();
+        let config = SchemaUsageConfig {
+            window: 24 * HOUR,
+            last_seen_retention: 48 * HOUR,
+            ..Default::default()
+        };
+        let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+        store.record(
+            &usage(&[("Query", &["me"]), ("User", &["name"])]),
+            "web",
+            start,
+            &config,
+        );
+        store.record(
+            &usage(&[("Query", &["me"])]),
+            "web",
+            start + 49 * HOUR,
+            &config,
+        );
+
+        let report = store.report(&schema_fields, start + 49 * HOUR, config.window);
+        assert_eq!(
+            report.fields[0].last_seen,
+            Some(format_time(start + 49 * HOUR))
+        );
+        assert_eq!(report.fields[1].last_seen, None);
+        assert!(report.fields[1].clients.is_empty());
+    }
+}
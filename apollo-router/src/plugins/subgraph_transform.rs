@@ -0,0 +1,443 @@
+//! Transformation of the traffic of subgraphs that do not follow the GraphQL over HTTP specification
+//!
+//! Legacy services behind older gateways can expect renamed headers or a wrapped request, and can
+//! return the GraphQL response inside an envelope, with errors that do not have the shape of
+//! GraphQL errors. This plugin rewrites the subgraph HTTP requests and responses, so that the
+//! rest of the router only sees standard payloads.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::FutureExt;
+use http::header::CONTENT_LENGTH;
+use http::HeaderName;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Map;
+use serde_json::Value;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::PluginInit;
+use crate::plugin::PluginPrivate;
+use crate::register_private_plugin;
+use crate::services::http::BoxService;
+use crate::services::http::HttpRequest;
+use crate::services::http::HttpResponse;
+use crate::services::router::body::get_body_bytes;
+use crate::services::router::body::RouterBody;
+
+/// Transformation of the requests and responses of subgraphs
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct TransformConfig {
+    /// Transformations per subgraph name
+    subgraphs: HashMap<String, SubgraphTransform>,
+}
+
+/// Transformation of the requests and responses of a subgraph
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct SubgraphTransform {
+    /// Request headers to rename, from the name set by the router to the name expected by the subgraph
+    rename_request_headers: HashMap<String, String>,
+    /// Key of the object wrapping the GraphQL request in the request body
+    request_envelope: Option<String>,
+    /// Dot separated path of the GraphQL response in the response body
+    response_envelope: Option<String>,
+    /// Mapping of the errors of the subgraph into GraphQL errors
+    errors: Option<ErrorMapping>,
+}
+
+/// Mapping of non standard errors into GraphQL errors
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct ErrorMapping {
+    /// Dot separated path of the errors in the response. It can contain a list of errors, a single error or a message
+    path: String,
+    /// Dot separated path of the message in an error
+    message: String,
+    /// Dot separated path of the error code in an error, moved to the `code` extension
+    code: Option<String>,
+    /// Dot separated path of an object in an error, whose fields are moved to the extensions
+    extensions: Option<String>,
+    /// Code of the errors without any code
+    default_code: Option<String>,
+}
+
+impl Default for ErrorMapping {
+    fn default() -> Self {
+        Self {
+            path: "errors".to_string(),
+            message: "message".to_string(),
+            code: None,
+            extensions: None,
+            default_code: None,
+        }
+    }
+}
+
+struct SubgraphTransformPlugin {
+    subgraphs: HashMap<String, Arc<SubgraphTransform>>,
+}
+
+#[async_trait::async_trait]
+impl PluginPrivate for SubgraphTransformPlugin {
+    type Config = TransformConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        for (subgraph, transform) in &init.config.subgraphs {
+            for (from, to) in &transform.rename_request_headers {
+                if HeaderName::try_from(from.as_str()).is_err()
+                    || HeaderName::try_from(to.as_str()).is_err()
+                {
+                    return Err(format!(
+                        "invalid header name in the transformation of subgraph {subgraph}: {from} -> {to}"
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(Self {
+            subgraphs: init
+                .config
+                .subgraphs
+                .into_iter()
+                .map(|(name, transform)| (name, Arc::new(transform)))
+                .collect(),
+        })
+    }
+
+    fn http_client_service(&self, subgraph_name: &str, service: BoxService) -> BoxService {
+        let Some(transform) = self.subgraphs.get(subgraph_name).cloned() else {
+            return service;
+        };
+        let response_transform = transform.clone();
+
+        ServiceBuilder::new()
+            .oneshot_checkpoint_async(move |request: HttpRequest| {
+                let transform = transform.clone();
+                async move { Ok(ControlFlow::Continue(transform.request(request).await?)) }.boxed()
+            })
+            .service(service)
+            .and_then(move |response: HttpResponse| {
+                let transform = response_transform.clone();
+                async move { transform.response(response).await }
+            })
+            .boxed()
+    }
+}
+
+impl SubgraphTransform {
+    async fn request(&self, request: HttpRequest) -> Result<HttpRequest, BoxError> {
+        let HttpRequest {
+            http_request,
+            context,
+        } = request;
+        let (mut parts, body) = http_request.into_parts();
+
+        for (from, to) in &self.rename_request_headers {
+            let values: Vec<_> = parts
+                .headers
+                .get_all(from.as_str())
+                .iter()
+                .cloned()
+                .collect();
+            parts.headers.remove(from.as_str());
+            let to = HeaderName::try_from(to.as_str())?;
+            for value in values {
+                parts.headers.append(to.clone(), value);
+            }
+        }
+
+        let body = match &self.request_envelope {
+            Some(envelope) => {
+                let bytes = get_body_bytes(body).await?;
+                let request: Value = serde_json::from_slice(&bytes)?;
+                let mut wrapped = Map::new();
+                wrapped.insert(envelope.clone(), request);
+                parts.headers.remove(CONTENT_LENGTH);
+                RouterBody::from(serde_json::to_vec(&Value::Object(wrapped))?)
+            }
+            None => body,
+        };
+
+        Ok(HttpRequest {
+            http_request: http::Request::from_parts(parts, body),
+            context,
+        })
+    }
+
+    async fn response(&self, response: HttpResponse) -> Result<HttpResponse, BoxError> {
+        if self.response_envelope.is_none() && self.errors.is_none() {
+            return Ok(response);
+        }
+
+        let HttpResponse {
+            http_response,
+            context,
+        } = response;
+        let (mut parts, body) = http_response.into_parts();
+        let bytes = get_body_bytes(body).await?;
+
+        // bodies that are not JSON are left for the subgraph service to report
+        let body = match self.transform_response_body(&bytes) {
+            Some(transformed) => {
+                parts.headers.remove(CONTENT_LENGTH);
+                Bytes::from(serde_json::to_vec(&transformed)?)
+            }
+            None => bytes,
+        };
+
+        Ok(HttpResponse {
+            http_response: http::Response::from_parts(parts, RouterBody::from(body)),
+            context,
+        })
+    }
+
+    fn transform_response_body(&self, bytes: &[u8]) -> Option<Value> {
+        let mut body: Value = serde_json::from_slice(bytes).ok()?;
+
+        if let Some(envelope) = &self.response_envelope {
+            if let Some(response) = take_path(&mut body, envelope) {
+                body = response;
+            }
+        }
+
+        if let Some(mapping) = &self.errors {
+            if let Some(errors) = take_path(&mut body, &mapping.path) {
+                let errors = mapping.map_errors(errors);
+                if let Some(object) = body.as_object_mut() {
+                    if !errors.is_empty() {
+                        object.insert("errors".to_string(), Value::Array(errors));
+                    }
+                }
+            }
+        }
+
+        Some(body)
+    }
+}
+
+impl ErrorMapping {
+    fn map_errors(&self, errors: Value) -> Vec<Value> {
+        match errors {
+            Value::Null => Vec::new(),
+            Value::Array(errors) => errors
+                .into_iter()
+                .map(|error| self.map_error(error))
+                .collect(),
+            error => vec![self.map_error(error)],
+        }
+    }
+
+    fn map_error(&self, error: Value) -> Value {
+        let (message, mut error) = match error {
+            Value::Object(error) => {
+                let mut error = Value::Object(error);
+                let message = match take_path(&mut error, &self.message) {
+                    Some(Value::String(message)) => message,
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                (message, error)
+            }
+            // errors can be plain messages
+            Value::String(message) => (message, Value::Object(Map::new())),
+            other => (other.to_string(), Value::Object(Map::new())),
+        };
+        let code = self
+            .code
+            .as_ref()
+            .and_then(|path| take_path(&mut error, path))
+            .map(|code| match code {
+                Value::String(code) => Value::String(code),
+                other => Value::String(other.to_string()),
+            });
+        let custom_extensions = self
+            .extensions
+            .as_ref()
+            .and_then(|path| take_path(&mut error, path));
+
+        let mut mapped = Map::new();
+        mapped.insert("message".to_string(), Value::String(message));
+        let Value::Object(mut error) = error else {
+            unreachable!("errors are converted to objects")
+        };
+        for key in ["locations", "path"] {
+            if let Some(value) = error.remove(key) {
+                mapped.insert(key.to_string(), value);
+            }
+        }
+
+        let mut extensions = match error.remove("extensions") {
+            Some(Value::Object(extensions)) => extensions,
+            _ => Map::new(),
+        };
+        if let Some(Value::Object(custom_extensions)) = custom_extensions {
+            extensions.extend(custom_extensions);
+        }
+        if let Some(code) = code {
+            extensions.insert("code".to_string(), code);
+        } else if let Some(default_code) = &self.default_code {
+            extensions
+                .entry("code")
+                .or_insert_with(|| Value::String(default_code.clone()));
+        }
+        if !extensions.is_empty() {
+            mapped.insert("extensions".to_string(), Value::Object(extensions));
+        }
+
+        Value::Object(mapped)
+    }
+}
+
+/// Removes the value at a dot separated path of JSON objects
+fn take_path(value: &mut Value, path: &str) -> Option<Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (
+            parent
+                .split('.')
+                .try_fold(value, |value, key| value.get_mut(key))?,
+            key,
+        ),
+        None => (value, path),
+    };
+    parent.as_object_mut()?.remove(key)
+}
+
+register_private_plugin!(
+    "apollo",
+    "experimental_subgraph_transform",
+    SubgraphTransformPlugin
+);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::Service;
+
+    use super::*;
+
+    fn transform(config: Value) -> SubgraphTransform {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn unwraps_response_envelope_and_maps_errors() {
+        let transform = transform(json!({
+            "response_envelope": "result.graphql",
+            "errors": {
+                "path": "failures",
+                "message": "msg",
+                "code": "error_code",
+                "extensions": "details",
+                "default_code": "SUBGRAPH_ERROR"
+            }
+        }));
+
+        let body = json!({
+            "result": {
+                "graphql": {
+                    "data": { "product": null },
+                    "failures": [
+                        {
+                            "msg": "product not found",
+                            "error_code": 404,
+                            "path": ["product"],
+                            "details": { "id": "1" }
+                        },
+                        "internal error"
+                    ]
+                }
+            }
+        });
+
+        assert_eq!(
+            transform
+                .transform_response_body(&serde_json::to_vec(&body).unwrap())
+                .unwrap(),
+            json!({
+                "data": { "product": null },
+                "errors": [
+                    {
+                        "message": "product not found",
+                        "path": ["product"],
+                        "extensions": { "id": "1", "code": "404" }
+                    },
+                    {
+                        "message": "internal error",
+                        "extensions": { "code": "SUBGRAPH_ERROR" }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_standard_errors() {
+        let transform = transform(json!({
+            "errors": {}
+        }));
+
+        let body = json!({
+            "data": null,
+            "errors": [{
+                "message": "error",
+                "extensions": { "code": "NOT_FOUND" }
+            }]
+        });
+
+        assert_eq!(
+            transform
+                .transform_response_body(&serde_json::to_vec(&body).unwrap())
+                .unwrap(),
+            body
+        );
+        assert!(transform.transform_response_body(b"not json").is_none());
+    }
+
+    #[tokio::test]
+    async fn renames_headers_and_wraps_request() {
+        let transform = transform(json!({
+            "rename_request_headers": { "x-operation-name": "x-legacy-operation" },
+            "request_envelope": "payload"
+        }));
+
+        let request = HttpRequest {
+            http_request: http::Request::builder()
+                .header("x-operation-name", "GetProduct")
+                .header(CONTENT_LENGTH, "20")
+                .body(RouterBody::from(r#"{"query":"{a}"}"#))
+                .unwrap(),
+            context: Default::default(),
+        };
+
+        let mut service = tower::service_fn(|request: HttpRequest| async move {
+            assert_eq!(
+                request.http_request.headers().get("x-legacy-operation"),
+                Some(&http::HeaderValue::from_static("GetProduct"))
+            );
+            assert!(!request
+                .http_request
+                .headers()
+                .contains_key("x-operation-name"));
+            assert!(!request.http_request.headers().contains_key(CONTENT_LENGTH));
+            let body = get_body_bytes(request.http_request.into_body())
+                .await
+                .unwrap();
+            assert_eq!(
+                serde_json::from_slice::<Value>(&body).unwrap(),
+                json!({ "payload": { "query": "{a}" } })
+            );
+            Ok::<_, BoxError>(())
+        });
+        let request = transform.request(request).await.unwrap();
+        service.call(request).await.unwrap();
+    }
+}
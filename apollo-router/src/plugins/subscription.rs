@@ -71,6 +71,9 @@ pub(crate) struct SubscriptionConfig {
     /// Enable the deduplication of subscription (for example if we detect the exact same request to subgraph we won't open a new websocket to the subgraph in passthrough mode)
     /// (default: true)
     pub(crate) enable_deduplication: bool,
+    /// Headers of the subgraph request taken into account to deduplicate subscriptions, the other headers are ignored
+    /// (default: all the headers)
+    pub(crate) deduplication_headers: Option<Vec<String>>,
     /// This is a limit to only have maximum X opened subscriptions at the same time. By default if it's not set there is no limit.
     pub(crate) max_opened_subscriptions: Option<usize>,
     /// It represent the capacity of the in memory queue to know how many events we can keep in a buffer
@@ -83,6 +86,7 @@ impl Default for SubscriptionConfig {
             enabled: true,
             mode: Default::default(),
             enable_deduplication: true,
+            deduplication_headers: None,
             max_opened_subscriptions: None,
             queue_capacity: None,
         }
@@ -223,6 +227,34 @@ pub(crate) struct WebSocketConfiguration {
     /// Heartbeat interval for graphql-ws protocol (default: disabled)
     #[serde(default = "HeartbeatInterval::new_disabled")]
     pub(crate) heartbeat_interval: HeartbeatInterval,
+    /// Share WebSocket connections between subscriptions to this subgraph
+    #[serde(default)]
+    pub(crate) multiplexing: WebSocketMultiplexing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+/// Multiplexing of subscriptions over shared WebSocket connections
+pub(crate) struct WebSocketMultiplexing {
+    /// Send subscriptions with the same headers and connection params through the same connection (default: false)
+    pub(crate) enabled: bool,
+    /// Maximum number of connections opened to the subgraph (default: 10)
+    pub(crate) max_connections: usize,
+    /// Maximum number of subscriptions sent through one connection (default: 100)
+    pub(crate) max_subscriptions_per_connection: usize,
+    /// Number of reconnection attempts when a connection is lost, before failing its subscriptions (default: 5)
+    pub(crate) reconnection_attempts: usize,
+}
+
+impl Default for WebSocketMultiplexing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_connections: 10,
+            max_subscriptions_per_connection: 100,
+            reconnection_attempts: 5,
+        }
+    }
 }
 
 fn default_path() -> String {
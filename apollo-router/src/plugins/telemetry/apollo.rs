@@ -4,19 +4,25 @@ use std::fmt::Display;
 use std::num::NonZeroUsize;
 use std::ops::AddAssign;
 use std::sync::OnceLock;
+use std::time::Duration;
 use std::time::SystemTime;
 
+use hmac::Hmac;
+use hmac::Mac;
 use http::header::HeaderName;
 use itertools::Itertools;
 use schemars::JsonSchema;
 use serde::ser::SerializeMap;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Sha256;
 use url::Url;
 use uuid::Uuid;
 
+use super::apollo_report_sink::ReportSinksConfig;
 use super::config::ApolloMetricsReferenceMode;
 use super::config::ApolloSignatureNormalizationAlgorithm;
+use super::config::ApolloUsageReportingMode;
 use super::config::Sampler;
 use super::metrics::apollo::studio::ContextualizedStats;
 use super::metrics::apollo::studio::SingleStats;
@@ -103,9 +109,19 @@ pub(crate) struct Config {
     /// Configuration for batch processing.
     pub(crate) batch_processor: BatchProcessorConfig,
 
+    /// The length of the windows that usage statistics are aggregated over before being reported
+    /// to Apollo Studio. Defaults to `batch_processor.scheduled_delay`.
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) experimental_report_window: Option<Duration>,
+
     /// Configure the way errors are transmitted to Apollo Studio
     pub(crate) errors: ErrorsConfiguration,
 
+    /// Redaction applied to the variables of traces and to operation signatures, whatever the
+    /// other options
+    pub(crate) redaction: RedactionConfiguration,
+
     /// Set the signature normalization algorithm to use when sending Apollo usage reports.
     pub(crate) signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
 
@@ -114,6 +130,90 @@ pub(crate) struct Config {
 
     /// Enable field metrics that are generated without FTV1 to be sent to Apollo Studio.
     pub(crate) experimental_local_field_metrics: bool,
+
+    /// Set what the usage reports sent to Apollo Studio contain.
+    pub(crate) experimental_usage_reporting_mode: ApolloUsageReportingMode,
+
+    /// Destinations of the usage reports, in addition to or instead of Apollo Studio.
+    pub(crate) experimental_report_sinks: ReportSinksConfig,
+
+    // Set by the router for the tenants that report their usage to their own graph, keyed by
+    // tenant name.
+    #[schemars(skip)]
+    pub(crate) tenants: HashMap<String, TenantGraph>,
+}
+
+/// The graph the usage of a tenant is reported to
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TenantGraph {
+    pub(crate) apollo_key: String,
+    pub(crate) apollo_graph_ref: String,
+    pub(crate) schema_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Default)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct RedactionConfiguration {
+    /// Never send variable values, even if `send_variable_values` includes them
+    pub(crate) variable_values: bool,
+    /// Variables with these names are removed from traces, along with their values, and the
+    /// literal values of the arguments with these names are removed from operation signatures.
+    /// Operations usually name their variables after the argument they are passed to
+    pub(crate) arguments: Vec<String>,
+    /// Replace the strings of variable values with their HMAC-SHA256, keyed with `hash_key`
+    pub(crate) hash_string_literals: bool,
+    /// The key of the string hashes. Without it, a random key is generated when the router
+    /// starts, so hashes cannot be compared between router instances or restarts
+    pub(crate) hash_key: Option<String>,
+}
+
+/// Key of the string hashes when `hash_key` is not configured
+static RANDOM_HASH_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+impl RedactionConfiguration {
+    /// Redacts the JSON encoded variable values of a trace. This is applied when the variables
+    /// are recorded on the supergraph span, so every exporter sends redacted variables.
+    pub(crate) fn redact_variables(&self, variables_json: &mut HashMap<String, String>) {
+        variables_json.retain(|name, _| !self.arguments.contains(name));
+        for value in variables_json.values_mut() {
+            if self.variable_values {
+                // The value of a private variable is the empty string
+                value.clear();
+            } else if self.hash_string_literals && !value.is_empty() {
+                *value = match serde_json::from_str::<serde_json::Value>(value) {
+                    Ok(mut json) => {
+                        self.hash_strings(&mut json);
+                        json.to_string()
+                    }
+                    Err(_) => String::new(),
+                };
+            }
+        }
+    }
+
+    fn hash_strings(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(string) => {
+                let key = match &self.hash_key {
+                    Some(key) => key.as_bytes(),
+                    None => RANDOM_HASH_KEY.get_or_init(rand::random),
+                };
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+                mac.update(string.as_bytes());
+                *string = hex::encode(mac.finalize().into_bytes());
+            }
+            serde_json::Value::Array(values) => {
+                values.iter_mut().for_each(|value| self.hash_strings(value))
+            }
+            serde_json::Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.hash_strings(value)),
+            serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, JsonSchema, Default)]
@@ -213,10 +313,15 @@ impl Default for Config {
             send_headers: ForwardHeaders::None,
             send_variable_values: ForwardValues::None,
             batch_processor: BatchProcessorConfig::default(),
+            experimental_report_window: None,
             errors: ErrorsConfiguration::default(),
+            redaction: RedactionConfiguration::default(),
             signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm::default(),
             experimental_local_field_metrics: false,
+            experimental_usage_reporting_mode: ApolloUsageReportingMode::default(),
+            experimental_report_sinks: ReportSinksConfig::default(),
             metrics_reference_mode: ApolloMetricsReferenceMode::default(),
+            tenants: HashMap::new(),
         }
     }
 }
@@ -293,18 +398,41 @@ impl Default for ForwardValues {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub(crate) enum SingleReport {
     Stats(SingleStatsReport),
     Traces(TracesReport),
 }
 
+impl SingleReport {
+    /// Removes what the usage reporting mode does not report, returns `None` if nothing is left.
+    pub(crate) fn for_mode(self, mode: ApolloUsageReportingMode) -> Option<SingleReport> {
+        match (mode, self) {
+            (ApolloUsageReportingMode::Full, report) => Some(report),
+            (ApolloUsageReportingMode::SignaturesOnly, SingleReport::Traces(_)) => None,
+            (ApolloUsageReportingMode::SignaturesOnly, SingleReport::Stats(mut report)) => {
+                report
+                    .stats
+                    .values_mut()
+                    .for_each(SingleStats::strip_details);
+                Some(SingleReport::Stats(report))
+            }
+        }
+    }
+}
+
 #[derive(Default, Debug, Serialize)]
 pub(crate) struct Report {
     pub(crate) traces_per_query: HashMap<String, TracesAndStats>,
     #[serde(serialize_with = "serialize_licensed_operation_count_by_type")]
     pub(crate) licensed_operation_count_by_type:
         HashMap<(OperationKind, Option<OperationSubType>), LicensedOperationCountByType>,
+    /// When the first record of the aggregation window of this report was added
+    #[serde(skip)]
+    pub(crate) start_time: Option<SystemTime>,
+    /// When the aggregation window of this report was closed
+    #[serde(skip)]
+    pub(crate) end_time: Option<SystemTime>,
 }
 
 #[derive(Clone, Default, Debug, Serialize, PartialEq, Eq, Hash)]
@@ -384,6 +512,24 @@ impl Report {
         aggregated_report
     }
 
+    /// Whether the report has neither operation counts nor traces and stats
+    pub(crate) fn is_empty(&self) -> bool {
+        self.licensed_operation_count_by_type.is_empty() && self.traces_per_query.is_empty()
+    }
+
+    /// Closes the aggregation window of this report. Records added afterwards reopen it, so a
+    /// report that could not be submitted keeps its start time.
+    pub(crate) fn close_window(&mut self, end_time: SystemTime) {
+        if self.start_time.is_some() {
+            self.end_time = Some(end_time);
+        }
+    }
+
+    /// The duration of the aggregation window of this report, once closed
+    pub(crate) fn window_duration(&self) -> Option<Duration> {
+        self.end_time?.duration_since(self.start_time?).ok()
+    }
+
     pub(crate) fn build_proto_report(
         &self,
         header: ReportHeader,
@@ -391,7 +537,8 @@ impl Report {
     ) -> crate::plugins::telemetry::apollo_exporter::proto::reports::Report {
         let mut report = crate::plugins::telemetry::apollo_exporter::proto::reports::Report {
             header: Some(header),
-            end_time: Some(SystemTime::now().into()),
+            // Reports that were not aggregated over a window, such as traces, end when sent
+            end_time: Some(self.end_time.unwrap_or_else(SystemTime::now).into()),
             operation_count_by_type: self
                 .licensed_operation_count_by_type
                 .values()
@@ -415,6 +562,8 @@ impl Report {
 
 impl AddAssign<SingleReport> for Report {
     fn add_assign(&mut self, report: SingleReport) {
+        self.start_time.get_or_insert_with(SystemTime::now);
+        self.end_time = None;
         match report {
             SingleReport::Stats(stats) => self.add_assign(stats),
             SingleReport::Traces(traces) => self.add_assign(traces),
@@ -7,6 +7,7 @@ use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use bytes::BytesMut;
 use flate2::write::GzEncoder;
@@ -29,7 +30,9 @@ use url::Url;
 
 use super::apollo::Report;
 use super::apollo::SingleReport;
+use super::apollo_report_sink::ReportSinksConfig;
 use super::config::ApolloMetricsReferenceMode;
+use super::config::ApolloUsageReportingMode;
 use crate::plugins::telemetry::tracing::BatchProcessorConfig;
 
 const BACKOFF_INCREMENT: Duration = Duration::from_millis(50);
@@ -94,9 +97,12 @@ pub(crate) struct ApolloExporter {
     strip_traces: AtomicBool,
     studio_backoff: Mutex<Instant>,
     metrics_reference_mode: ApolloMetricsReferenceMode,
+    usage_reporting_mode: ApolloUsageReportingMode,
+    report_sinks: ReportSinksConfig,
 }
 
 impl ApolloExporter {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         endpoint: &Url,
         batch_config: &BatchProcessorConfig,
@@ -104,6 +110,8 @@ impl ApolloExporter {
         apollo_graph_ref: &str,
         schema_id: &str,
         metrics_reference_mode: ApolloMetricsReferenceMode,
+        usage_reporting_mode: ApolloUsageReportingMode,
+        report_sinks: &ReportSinksConfig,
     ) -> Result<ApolloExporter, BoxError> {
         let header = proto::reports::ReportHeader {
             graph_ref: apollo_graph_ref.to_string(),
@@ -133,14 +141,19 @@ impl ApolloExporter {
             strip_traces: Default::default(),
             studio_backoff: Mutex::new(Instant::now()),
             metrics_reference_mode,
+            usage_reporting_mode,
+            report_sinks: report_sinks.clone(),
         })
     }
 
-    pub(crate) fn start(self) -> Sender {
+    pub(crate) fn start(self, report_window: Duration) -> Sender {
         let (tx, mut rx) = mpsc::channel::<SingleReport>(self.batch_config.max_queue_size);
         tokio::spawn(async move {
-            let timeout = tokio::time::interval(self.batch_config.scheduled_delay);
+            let timeout = tokio::time::interval(report_window);
+            // The report submitted to Apollo Studio, which keeps what Studio did not accept yet
             let mut report = Report::default();
+            // The report written to the sinks, which only has the records of the current window
+            let mut sink_report = Report::default();
             let mut backoff_warn = true;
 
             tokio::pin!(timeout);
@@ -151,8 +164,25 @@ impl ApolloExporter {
                     // pseudo-random and may never choose the timeout tick
                     biased;
                     _ = timeout.tick() => {
-                        match self.submit_report(std::mem::take(&mut report)).await {
-                            Ok(_) => backoff_warn = true,
+                        let now = SystemTime::now();
+                        sink_report.close_window(now);
+                        self.write_to_sinks(&std::mem::take(&mut sink_report)).await;
+                        if !self.report_sinks.send_to_apollo {
+                            continue;
+                        }
+                        report.close_window(now);
+                        let window_duration = report.window_duration();
+                        match self.submit_to_studio(std::mem::take(&mut report)).await {
+                            Ok(_) => {
+                                backoff_warn = true;
+                                if let Some(window_duration) = window_duration {
+                                    f64_histogram!(
+                                        "apollo.router.telemetry.studio.report_window",
+                                        "Duration of the aggregation windows of the usage reports submitted to Studio",
+                                        window_duration.as_secs_f64()
+                                    );
+                                }
+                            }
                             Err(err) => {
                                 match err {
                                     ApolloExportError::StudioBackoff(unsubmitted, remaining) => {
@@ -169,7 +199,19 @@ impl ApolloExporter {
                     }
                     single_report = rx.recv() => {
                         if let Some(r) = single_report {
-                            report += r;
+                            // Everything goes through here before leaving the router, so this
+                            // is where the usage reporting mode is enforced
+                            if let Some(r) = r.for_mode(self.usage_reporting_mode) {
+                                match (self.report_sinks.sinks.is_empty(), self.report_sinks.send_to_apollo) {
+                                    (true, true) => report += r,
+                                    (false, true) => {
+                                        sink_report += r.clone();
+                                        report += r;
+                                    }
+                                    (false, false) => sink_report += r,
+                                    (true, false) => {}
+                                }
+                            }
                         } else {
                             tracing::debug!("terminating apollo exporter");
                             break;
@@ -178,17 +220,59 @@ impl ApolloExporter {
                 };
             }
 
-            if let Err(e) = self.submit_report(std::mem::take(&mut report)).await {
-                tracing::error!("failed to submit Apollo report: {}", e)
+            let now = SystemTime::now();
+            sink_report.close_window(now);
+            self.write_to_sinks(&sink_report).await;
+            if self.report_sinks.send_to_apollo {
+                report.close_window(now);
+                if let Err(e) = self.submit_to_studio(report).await {
+                    tracing::error!("failed to submit Apollo report: {}", e)
+                }
             }
         });
         Sender::Apollo(tx)
     }
 
+    /// Writes a report to the sinks, then submits it to Apollo Studio if enabled. A report that
+    /// Studio does not accept is not handed back to be submitted again.
     pub(crate) async fn submit_report(&self, report: Report) -> Result<(), ApolloExportError> {
-        // We may be sending traces but with no operation count
-        if report.licensed_operation_count_by_type.is_empty() && report.traces_per_query.is_empty()
+        self.write_to_sinks(&report).await;
+        if !self.report_sinks.send_to_apollo {
+            return Ok(());
+        }
+        self.submit_to_studio(report).await
+    }
+
+    /// Writes a report to every sink. Failures are logged, sinks are not retried.
+    async fn write_to_sinks(&self, report: &Report) {
+        if self.report_sinks.sinks.is_empty() || report.is_empty() {
+            return;
+        }
+        let extended_references_enabled = matches!(
+            self.metrics_reference_mode,
+            ApolloMetricsReferenceMode::Extended
+        );
+        let proto_report =
+            report.build_proto_report(self.header.clone(), extended_references_enabled);
+        let report_type = if proto_report
+            .traces_per_query
+            .values()
+            .any(|traces_and_stats| !traces_and_stats.trace.is_empty())
         {
+            ROUTER_REPORT_TYPE_TRACES
+        } else {
+            ROUTER_REPORT_TYPE_METRICS
+        };
+        for sink in &self.report_sinks.sinks {
+            if let Err(e) = sink.write(&self.client, &proto_report, report_type).await {
+                tracing::error!("failed to write Apollo report to {}: {}", sink, e);
+            }
+        }
+    }
+
+    async fn submit_to_studio(&self, report: Report) -> Result<(), ApolloExportError> {
+        // We may be sending traces but with no operation count
+        if report.is_empty() {
             return Ok(());
         }
 
@@ -364,6 +448,74 @@ pub(crate) fn get_uname() -> Result<String, std::io::Error> {
     ))
 }
 
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+    use crate::plugins::telemetry::apollo::LicensedOperationCountByType;
+    use crate::plugins::telemetry::apollo_report_sink::ReportSink;
+    use crate::plugins::telemetry::metrics::apollo::studio::SingleStatsReport;
+    use crate::query_planner::OperationKind;
+
+    fn single_report() -> SingleReport {
+        SingleReport::Stats(SingleStatsReport {
+            licensed_operation_count_by_type: Some(LicensedOperationCountByType {
+                r#type: OperationKind::Query,
+                subtype: None,
+                licensed_operation_count: 1,
+            }),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn sinks_receive_each_window_once_while_studio_backs_off() {
+        let studio = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&studio)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reports.bin");
+        let exporter = ApolloExporter::new(
+            &studio.uri().parse().unwrap(),
+            &BatchProcessorConfig::default(),
+            "key",
+            "graph@current",
+            "schema",
+            ApolloMetricsReferenceMode::default(),
+            ApolloUsageReportingMode::default(),
+            &ReportSinksConfig {
+                send_to_apollo: true,
+                sinks: vec![ReportSink::File { path: path.clone() }],
+            },
+        )
+        .unwrap();
+        *exporter.studio_backoff.lock().unwrap() = Instant::now() + Duration::from_secs(60);
+        let sender = exporter.start(Duration::from_millis(100));
+
+        sender.send(single_report());
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        sender.send(single_report());
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        // Studio keeps both records to submit them when it accepts reports again, but the
+        // sinks only get each one once
+        let contents = std::fs::read(&path).unwrap();
+        let mut buf = contents.as_slice();
+        for _ in 0..2 {
+            let report = proto::reports::Report::decode_length_delimited(&mut buf).unwrap();
+            assert_eq!(report.operation_count_by_type[0].operation_count, 1);
+        }
+        assert!(buf.is_empty());
+    }
+}
+
 #[allow(unreachable_pub)]
 pub(crate) mod proto {
     pub(crate) mod reports {
@@ -0,0 +1,282 @@
+//! Local destinations of Apollo usage reports
+//!
+//! Every report submitted to Apollo Studio can also be written, with the same protobuf data
+//! model, to destinations consumed by self-hosted analytics.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
+use http::header::CONTENT_TYPE;
+use prost::Message;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tower::BoxError;
+use url::Url;
+
+use super::apollo_exporter::proto;
+
+/// Destinations of the usage reports
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ReportSinksConfig {
+    /// Send the reports to Apollo Studio (default: true)
+    pub(crate) send_to_apollo: bool,
+    /// Destinations that receive a copy of every report
+    pub(crate) sinks: Vec<ReportSink>,
+}
+
+impl ReportSinksConfig {
+    /// The destinations of the reports of a router without an Apollo key and graph ref, which
+    /// can only write them to the sinks
+    pub(crate) fn sinks_only(&self) -> Self {
+        Self {
+            send_to_apollo: false,
+            sinks: self.sinks.clone(),
+        }
+    }
+}
+
+impl Default for ReportSinksConfig {
+    fn default() -> Self {
+        Self {
+            send_to_apollo: true,
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// Destination of the usage reports
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ReportSink {
+    /// Append the reports to a file, each one encoded as a length-delimited protobuf `Report`
+    File {
+        /// The path of the file. It is created if it does not exist
+        path: PathBuf,
+    },
+    /// Produce each report, encoded as a protobuf `Report`, as a record of a Kafka topic through
+    /// a Kafka REST proxy
+    Kafka {
+        /// The URL of the REST proxy
+        rest_proxy: Url,
+        /// The topic the records are produced to
+        topic: String,
+        /// Headers added to each request, such as credentials for the proxy
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Send each report, encoded as a protobuf `Report`, as the body of an OTLP log record, using
+    /// the OTLP/HTTP JSON encoding
+    OtlpLogs {
+        /// The URL of the logs endpoint of the collector, such as `http://localhost:4318/v1/logs`
+        endpoint: Url,
+        /// Headers added to each request, such as credentials for the collector
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+impl fmt::Display for ReportSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportSink::File { path } => write!(f, "file {}", path.display()),
+            ReportSink::Kafka { topic, .. } => write!(f, "Kafka topic {topic}"),
+            ReportSink::OtlpLogs { endpoint, .. } => write!(f, "OTLP logs endpoint {endpoint}"),
+        }
+    }
+}
+
+impl ReportSink {
+    pub(crate) async fn write(
+        &self,
+        client: &reqwest::Client,
+        report: &proto::reports::Report,
+        report_type: &str,
+    ) -> Result<(), BoxError> {
+        match self {
+            ReportSink::File { path } => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(&report.encode_length_delimited_to_vec())
+                    .await?;
+                file.flush().await?;
+            }
+            ReportSink::Kafka {
+                rest_proxy,
+                topic,
+                headers,
+            } => {
+                let body = json!({
+                    "records": [{ "value": BASE64_STANDARD.encode(report.encode_to_vec()) }]
+                });
+                client
+                    .post(rest_proxy.join(&format!("topics/{topic}"))?)
+                    .headers(header_map(headers)?)
+                    .header(CONTENT_TYPE, "application/vnd.kafka.binary.v2+json")
+                    .body(body.to_string())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            ReportSink::OtlpLogs { endpoint, headers } => {
+                client
+                    .post(endpoint.clone())
+                    .headers(header_map(headers)?)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(otlp_logs_request(report, report_type).to_string())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn header_map(headers: &HashMap<String, String>) -> Result<http::HeaderMap, BoxError> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            Ok::<_, BoxError>((
+                http::HeaderName::try_from(name.as_str())?,
+                http::HeaderValue::try_from(value.as_str())?,
+            ))
+        })
+        .collect()
+}
+
+/// An `ExportLogsServiceRequest` in the OTLP JSON encoding, with one log record per report
+fn otlp_logs_request(report: &proto::reports::Report, report_type: &str) -> serde_json::Value {
+    let string_attribute =
+        |key: &str, value: &str| json!({ "key": key, "value": { "stringValue": value } });
+    let header = report.header.clone().unwrap_or_default();
+    let time_unix_nano = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [
+                    string_attribute("service.name", "apollo-router"),
+                    string_attribute("apollo.graph.ref", &header.graph_ref),
+                    string_attribute("apollo.schema.id", &header.executable_schema_id),
+                ]
+            },
+            "scopeLogs": [{
+                "scope": { "name": "apollo.usage_reporting" },
+                "logRecords": [{
+                    // 64 bit integers are encoded as strings in OTLP JSON
+                    "timeUnixNano": time_unix_nano.to_string(),
+                    "body": { "bytesValue": BASE64_STANDARD.encode(report.encode_to_vec()) },
+                    "attributes": [string_attribute("apollo.report.type", report_type)]
+                }]
+            }]
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+
+    fn report() -> proto::reports::Report {
+        proto::reports::Report {
+            header: Some(proto::reports::ReportHeader {
+                graph_ref: "graph@current".to_string(),
+                ..Default::default()
+            }),
+            traces_pre_aggregated: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_length_delimited_reports_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reports.bin");
+        let sink = ReportSink::File { path: path.clone() };
+        let client = reqwest::Client::new();
+        sink.write(&client, &report(), "metrics").await.unwrap();
+        sink.write(&client, &report(), "metrics").await.unwrap();
+
+        let contents = std::fs::read(&path).unwrap();
+        let mut buf = contents.as_slice();
+        for _ in 0..2 {
+            let decoded = proto::reports::Report::decode_length_delimited(&mut buf).unwrap();
+            assert_eq!(decoded, report());
+        }
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn produces_reports_to_kafka_rest_proxy() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/topics/usage"))
+            .and(header(
+                "content-type",
+                "application/vnd.kafka.binary.v2+json",
+            ))
+            .and(header("authorization", "Basic secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let sink = ReportSink::Kafka {
+            rest_proxy: server.uri().parse().unwrap(),
+            topic: "usage".to_string(),
+            headers: HashMap::from([("authorization".to_string(), "Basic secret".to_string())]),
+        };
+        sink.write(&reqwest::Client::new(), &report(), "metrics")
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let value = BASE64_STANDARD
+            .decode(body["records"][0]["value"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            proto::reports::Report::decode(value.as_slice()).unwrap(),
+            report()
+        );
+    }
+
+    #[test]
+    fn encodes_reports_as_otlp_log_records() {
+        let request = otlp_logs_request(&report(), "traces");
+        let resource_logs = &request["resourceLogs"][0];
+        assert_eq!(
+            resource_logs["resource"]["attributes"][1]["value"]["stringValue"],
+            "graph@current"
+        );
+        let record = &resource_logs["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["attributes"][0]["value"]["stringValue"], "traces");
+        let body = BASE64_STANDARD
+            .decode(record["body"]["bytesValue"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            proto::reports::Report::decode(body.as_slice()).unwrap(),
+            report()
+        );
+    }
+}
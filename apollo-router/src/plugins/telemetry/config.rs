@@ -240,6 +240,8 @@ pub(crate) struct ExposeTraceId {
     pub(crate) header_name: Option<HeaderName>,
     /// Format of the trace ID in response headers
     pub(crate) format: TraceIdFormat,
+    /// Expose the trace_id in the `traceId` extension of every GraphQL error of the response
+    pub(crate) error_extension: bool,
 }
 
 #[derive(Clone, Default, Debug, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -304,6 +306,19 @@ pub(crate) enum ApolloMetricsReferenceMode {
     Standard,
 }
 
+/// Apollo usage reporting modes.
+#[derive(Clone, Default, Debug, Deserialize, JsonSchema, Copy, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ApolloUsageReportingMode {
+    /// Report traces, and statistics with their client and field level details.
+    #[default]
+    Full,
+    /// Only report operation signatures with their request counts, latencies and errors counts.
+    /// Traces, client names and versions, field level statistics and referenced fields are not
+    /// reported.
+    SignaturesOnly,
+}
+
 /// Configure propagation of traces. In general you won't have to do this as these are automatically configured
 /// along with any exporter you configure.
 #[derive(Clone, Default, Debug, Deserialize, JsonSchema)]
@@ -700,6 +715,11 @@ fn parent_based(sampler: opentelemetry::sdk::trace::Sampler) -> opentelemetry::s
 
 impl Conf {
     pub(crate) fn calculate_field_level_instrumentation_ratio(&self) -> Result<f64, Error> {
+        // Subgraph traces are only used for field level statistics, which are not reported
+        if self.apollo.experimental_usage_reporting_mode == ApolloUsageReportingMode::SignaturesOnly
+        {
+            return Ok(0.0);
+        }
         // Because when datadog is enabled the global sampling is overriden to always_on
         if self
             .exporters
@@ -794,6 +814,19 @@ impl Conf {
             _ => ApolloSignatureNormalizationAlgorithm::default(),
         }
     }
+
+    /// The arguments whose literal values are removed from operation signatures
+    pub(crate) fn redacted_arguments(configuration: &Configuration) -> Vec<String> {
+        match configuration.apollo_plugins.plugins.get("telemetry") {
+            Some(telemetry_config) => {
+                match serde_json::from_value::<Conf>(telemetry_config.clone()) {
+                    Ok(conf) => conf.apollo.redaction.arguments,
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -16,8 +16,10 @@ use serde::de::MapAccess;
 use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Deserializer;
+use tracing_subscriber::EnvFilter;
 
 use crate::configuration::ConfigurationError;
+use crate::executable::router_log_filter;
 use crate::plugins::telemetry::config::AttributeValue;
 use crate::plugins::telemetry::config::TraceIdFormat;
 use crate::plugins::telemetry::config_new::experimental_when_header::HeaderLoggingCondition;
@@ -50,15 +52,22 @@ impl Logging {
         });
 
         if misconfiguration {
-            Err(ConfigurationError::InvalidConfiguration {
+            return Err(ConfigurationError::InvalidConfiguration {
                 message: "'experimental_when_header' configuration for logging is invalid",
                 error: String::from(
                     "body and headers must not be both false because it doesn't enable any logs",
                 ),
-            })
-        } else {
-            Ok(())
+            });
         }
+
+        EnvFilter::try_new(self.common.log_filter("info")).map_err(|err| {
+            ConfigurationError::InvalidConfiguration {
+                message: "the log levels of the logging configuration are invalid",
+                error: err.to_string(),
+            }
+        })?;
+
+        Ok(())
     }
 
     /// Returns if we should display the request/response headers and body given the `SupergraphRequest`
@@ -84,6 +93,29 @@ pub(crate) struct LoggingCommon {
     pub(crate) service_namespace: Option<String>,
     /// The Open Telemetry resource
     pub(crate) resource: BTreeMap<String, AttributeValue>,
+    /// The log level, with the same syntax as the `--log` command line option, such as `debug` or
+    /// `hyper=debug,info`. When set, it replaces the log level given on the command line or in the
+    /// environment, and is applied again when the configuration is reloaded
+    pub(crate) level: Option<String>,
+    /// Log levels of specific targets, such as `apollo_router::services::subgraph_service: debug`
+    pub(crate) targets: BTreeMap<String, String>,
+}
+
+impl LoggingCommon {
+    /// The log filter directives, from the log level given on the command line and the configured levels
+    pub(crate) fn log_filter(&self, default_log_level: &str) -> String {
+        let mut log_filter = self
+            .level
+            .as_deref()
+            .map(router_log_filter)
+            .unwrap_or_else(|| default_log_level.to_string());
+        for (target, level) in &self.targets {
+            log_filter.push_str(&format!(",{target}={level}"));
+        }
+        // manually filter salsa logs because some of them run at the INFO level https://github.com/salsa-rs/salsa/issues/425
+        log_filter.push_str(",salsa=error");
+        log_filter
+    }
 }
 
 impl ConfigResource for LoggingCommon {
@@ -476,6 +508,7 @@ mod test {
     use crate::plugins::telemetry::config_new::experimental_when_header::HeaderLoggingCondition;
     use crate::plugins::telemetry::config_new::logging::Format;
     use crate::plugins::telemetry::config_new::logging::Logging;
+    use crate::plugins::telemetry::config_new::logging::LoggingCommon;
     use crate::services::SupergraphRequest;
     #[test]
     fn format_de() {
@@ -489,6 +522,33 @@ mod test {
         assert_eq!(format, Format::Json(Default::default()));
     }
 
+    #[test]
+    fn log_filter() {
+        let common = serde_json::from_value::<LoggingCommon>(json!({
+            "targets": {
+                "apollo_router::services::subgraph_service": "debug",
+                "hyper": "warn"
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            common.log_filter("info"),
+            "info,apollo_router::services::subgraph_service=debug,hyper=warn,salsa=error"
+        );
+
+        let common = serde_json::from_value::<LoggingCommon>(json!({ "level": "error" })).unwrap();
+        assert_eq!(
+            common.log_filter("info"),
+            "info,apollo_router=error,salsa=error"
+        );
+
+        let logging = serde_json::from_value::<Logging>(json!({
+            "common": { "targets": { "hyper": "not_a_level" } }
+        }))
+        .unwrap();
+        assert!(logging.validate().is_err());
+    }
+
     #[test]
     fn test_logging_conf_validation() {
         let logging_conf = Logging {
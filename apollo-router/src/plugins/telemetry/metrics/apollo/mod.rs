@@ -18,7 +18,9 @@ use crate::plugins::telemetry::apollo::router_id;
 use crate::plugins::telemetry::apollo::Config;
 use crate::plugins::telemetry::apollo_exporter::get_uname;
 use crate::plugins::telemetry::apollo_exporter::ApolloExporter;
+use crate::plugins::telemetry::apollo_report_sink::ReportSinksConfig;
 use crate::plugins::telemetry::config::ApolloMetricsReferenceMode;
+use crate::plugins::telemetry::config::ApolloUsageReportingMode;
 use crate::plugins::telemetry::config::MetricsCommon;
 use crate::plugins::telemetry::metrics::CustomAggregationSelector;
 use crate::plugins::telemetry::metrics::MetricsBuilder;
@@ -37,7 +39,9 @@ fn default_buckets() -> Vec<f64> {
 
 impl MetricsConfigurator for Config {
     fn enabled(&self) -> bool {
-        self.apollo_key.is_some() && self.apollo_graph_ref.is_some()
+        (self.apollo_key.is_some() && self.apollo_graph_ref.is_some())
+            || !self.tenants.is_empty()
+            || !self.experimental_report_sinks.sinks.is_empty()
     }
 
     fn apply(
@@ -47,6 +51,26 @@ impl MetricsConfigurator for Config {
     ) -> Result<MetricsBuilder, BoxError> {
         tracing::debug!("configuring Apollo metrics");
         static ENABLED: AtomicBool = AtomicBool::new(false);
+        for (name, graph) in &self.tenants {
+            tracing::debug!(tenant = %name, "creating Apollo metrics exporter of tenant");
+            let exporter = ApolloExporter::new(
+                &self.endpoint,
+                &self.batch_processor,
+                &graph.apollo_key,
+                &graph.apollo_graph_ref,
+                &graph.schema_id,
+                self.metrics_reference_mode,
+                self.experimental_usage_reporting_mode,
+                &self.experimental_report_sinks,
+            )?;
+            builder.tenant_apollo_metrics_senders.insert(
+                name.clone(),
+                exporter.start(
+                    self.experimental_report_window
+                        .unwrap_or(self.batch_processor.scheduled_delay),
+                ),
+            );
+        }
         Ok(match self {
             Config {
                 endpoint,
@@ -55,7 +79,10 @@ impl MetricsConfigurator for Config {
                 apollo_graph_ref: Some(reference),
                 schema_id,
                 batch_processor,
+                experimental_report_window,
                 metrics_reference_mode,
+                experimental_usage_reporting_mode,
+                experimental_report_sinks,
                 ..
             } => {
                 if !ENABLED.swap(true, Ordering::Relaxed) {
@@ -69,12 +96,19 @@ impl MetricsConfigurator for Config {
                     reference,
                     schema_id,
                     batch_processor,
+                    experimental_report_window.unwrap_or(batch_processor.scheduled_delay),
                     *metrics_reference_mode,
+                    *experimental_usage_reporting_mode,
+                    experimental_report_sinks,
                 )?;
                 // env variable EXPERIMENTAL_APOLLO_OTLP_METRICS_ENABLED will disappear without warning in future
+                // OTLP metrics have client and field level attributes, so they are not sent when
+                // only signatures are reported, and they are only sent to Apollo Studio
                 if std::env::var("EXPERIMENTAL_APOLLO_OTLP_METRICS_ENABLED")
                     .unwrap_or_else(|_| "true".to_string())
                     == "true"
+                    && *experimental_usage_reporting_mode == ApolloUsageReportingMode::Full
+                    && experimental_report_sinks.send_to_apollo
                 {
                     builder = Self::configure_apollo_otlp_metrics(
                         builder,
@@ -87,6 +121,32 @@ impl MetricsConfigurator for Config {
                 }
                 builder
             }
+            Config {
+                endpoint,
+                apollo_graph_ref,
+                schema_id,
+                batch_processor,
+                experimental_report_window,
+                metrics_reference_mode,
+                experimental_usage_reporting_mode,
+                experimental_report_sinks,
+                ..
+            } if !experimental_report_sinks.sinks.is_empty() => {
+                ENABLED.swap(false, Ordering::Relaxed);
+                tracing::debug!("no Apollo key and graph ref, usage reports are only written to the report sinks");
+                Self::configure_apollo_metrics(
+                    builder,
+                    endpoint,
+                    "",
+                    apollo_graph_ref.as_deref().unwrap_or_default(),
+                    schema_id,
+                    batch_processor,
+                    experimental_report_window.unwrap_or(batch_processor.scheduled_delay),
+                    *metrics_reference_mode,
+                    *experimental_usage_reporting_mode,
+                    &experimental_report_sinks.sinks_only(),
+                )?
+            }
             _ => {
                 ENABLED.swap(false, Ordering::Relaxed);
                 builder
@@ -150,6 +210,7 @@ impl Config {
         Ok(builder)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn configure_apollo_metrics(
         mut builder: MetricsBuilder,
         endpoint: &Url,
@@ -157,7 +218,10 @@ impl Config {
         reference: &str,
         schema_id: &str,
         batch_processor: &BatchProcessorConfig,
+        report_window: Duration,
         metrics_reference_mode: ApolloMetricsReferenceMode,
+        usage_reporting_mode: ApolloUsageReportingMode,
+        report_sinks: &ReportSinksConfig,
     ) -> Result<MetricsBuilder, BoxError> {
         let batch_processor_config = batch_processor;
         tracing::debug!(endpoint = %endpoint, "creating Apollo metrics exporter");
@@ -168,9 +232,11 @@ impl Config {
             reference,
             schema_id,
             metrics_reference_mode,
+            usage_reporting_mode,
+            report_sinks,
         )?;
 
-        builder.apollo_metrics_sender = exporter.start();
+        builder.apollo_metrics_sender = exporter.start(report_window);
         Ok(builder)
     }
 }
@@ -198,6 +264,7 @@ mod test {
     use crate::plugins::telemetry::apollo::default_buffer_size;
     use crate::plugins::telemetry::apollo::ENDPOINT_DEFAULT;
     use crate::plugins::telemetry::apollo_exporter::Sender;
+    use crate::plugins::telemetry::apollo_report_sink::ReportSink;
     use crate::plugins::telemetry::Telemetry;
     use crate::plugins::telemetry::STUDIO_EXCLUDE;
     use crate::query_planner::OperationKind;
@@ -229,6 +296,30 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn apollo_metrics_enabled_with_report_sinks_only() -> Result<(), BoxError> {
+        let dir = tempfile::tempdir()?;
+        let plugin = create_plugin_with_apollo_config(super::super::apollo::Config {
+            endpoint: Url::parse("http://example.com")?,
+            apollo_key: None,
+            apollo_graph_ref: None,
+            client_name_header: HeaderName::from_static("name_header"),
+            client_version_header: HeaderName::from_static("version_header"),
+            buffer_size: default_buffer_size(),
+            schema_id: "schema_sha".to_string(),
+            experimental_report_sinks: ReportSinksConfig {
+                send_to_apollo: true,
+                sinks: vec![ReportSink::File {
+                    path: dir.path().join("reports.bin"),
+                }],
+            },
+            ..Default::default()
+        })
+        .await?;
+        assert!(matches!(plugin.apollo_metrics_sender, Sender::Apollo(_)));
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn apollo_metrics_single_operation() -> Result<(), BoxError> {
         let query = "query {topProducts{name}}";
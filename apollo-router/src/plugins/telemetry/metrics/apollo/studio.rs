@@ -11,6 +11,8 @@ use super::histogram::ListLengthHistogram;
 use crate::apollo_studio_interop::AggregatedExtendedReferenceStats;
 use crate::apollo_studio_interop::ExtendedReferenceStats;
 use crate::apollo_studio_interop::ReferencedEnums;
+use crate::graphql;
+use crate::json_ext::PathElement;
 use crate::plugins::telemetry::apollo::LicensedOperationCountByType;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::EnumStats;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::InputFieldStats;
@@ -18,20 +20,35 @@ use crate::plugins::telemetry::apollo_exporter::proto::reports::InputTypeStats;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::ReferencedFieldsForType;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::StatsContext;
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleStatsReport {
     pub(crate) request_id: Uuid,
     pub(crate) stats: HashMap<String, SingleStats>,
     pub(crate) licensed_operation_count_by_type: Option<LicensedOperationCountByType>,
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleStats {
     pub(crate) stats_with_context: SingleContextualizedStats,
     pub(crate) referenced_fields_by_type: HashMap<String, ReferencedFieldsForType>,
 }
 
-#[derive(Default, Debug, Serialize)]
+impl SingleStats {
+    /// Keeps the counts of the operation, without client information or field level details
+    pub(crate) fn strip_details(&mut self) {
+        let stats = &mut self.stats_with_context;
+        stats.context.client_name.clear();
+        stats.context.client_version.clear();
+        stats.query_latency_stats.root_error_stats = Default::default();
+        stats.per_type_stat.clear();
+        stats.extended_references = Default::default();
+        stats.enum_response_references = Default::default();
+        stats.local_per_type_stat.clear();
+        self.referenced_fields_by_type.clear();
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleContextualizedStats {
     pub(crate) context: StatsContext,
     pub(crate) query_latency_stats: SingleQueryLatencyStats,
@@ -42,7 +59,7 @@ pub(crate) struct SingleContextualizedStats {
     pub(crate) local_per_type_stat: HashMap<String, LocalTypeStat>,
 }
 // TODO Make some of these fields bool
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleQueryLatencyStats {
     pub(crate) latency: Duration,
     pub(crate) cache_hit: bool,
@@ -57,19 +74,37 @@ pub(crate) struct SingleQueryLatencyStats {
     pub(crate) without_field_instrumentation: bool,
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SinglePathErrorStats {
     pub(crate) children: HashMap<String, SinglePathErrorStats>,
     pub(crate) errors_count: u64,
     pub(crate) requests_with_errors_count: u64,
 }
 
-#[derive(Default, Debug, Serialize)]
+impl SinglePathErrorStats {
+    /// Counts errors at their response path, without list indices. A path counts once in
+    /// `requests_with_errors_count` however many errors it has. Errors without a path are not
+    /// attributed to any field.
+    pub(crate) fn record_errors(&mut self, errors: &[graphql::Error]) {
+        for path in errors.iter().filter_map(|error| error.path.as_ref()) {
+            let mut stats = &mut *self;
+            for element in path.iter() {
+                if let PathElement::Key(name, _) = element {
+                    stats = stats.children.entry(name.clone()).or_default();
+                }
+            }
+            stats.errors_count += 1;
+            stats.requests_with_errors_count = 1;
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleTypeStat {
     pub(crate) per_field_stat: HashMap<String, SingleFieldStat>,
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct SingleFieldStat {
     pub(crate) return_type: String,
     pub(crate) errors_count: u64,
@@ -470,9 +505,12 @@ impl From<AggregatedExtendedReferenceStats>
 mod test {
     use std::collections::HashMap;
     use std::time::Duration;
+    use std::time::SystemTime;
 
     use super::*;
     use crate::plugins::telemetry::apollo::Report;
+    use crate::plugins::telemetry::apollo::SingleReport;
+    use crate::plugins::telemetry::config::ApolloUsageReportingMode;
     use crate::query_planner::OperationKind;
 
     #[test]
@@ -509,6 +547,94 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_path_error_stats_from_response_errors() {
+        let error = |path: Option<&str>| {
+            graphql::Error::builder()
+                .message("error")
+                .and_path(path.map(crate::json_ext::Path::from))
+                .extension_code("ERROR")
+                .build()
+        };
+        let mut stats = SinglePathErrorStats::default();
+        stats.record_errors(&[
+            error(Some("topProducts/0/reviews/1/author")),
+            error(Some("topProducts/2/reviews/0/author")),
+            error(Some("topProducts")),
+            error(None),
+        ]);
+        stats.record_errors(&[error(Some("topProducts/1/reviews"))]);
+
+        assert_eq!(stats.errors_count, 0);
+        let top_products = &stats.children["topProducts"];
+        assert_eq!(top_products.errors_count, 1);
+        assert_eq!(top_products.requests_with_errors_count, 1);
+        let reviews = &top_products.children["reviews"];
+        assert_eq!(reviews.errors_count, 1);
+        assert_eq!(reviews.requests_with_errors_count, 1);
+        let author = &reviews.children["author"];
+        assert_eq!(author.errors_count, 2);
+        assert_eq!(author.requests_with_errors_count, 1);
+        assert!(author.children.is_empty());
+    }
+
+    #[test]
+    fn test_signatures_only() {
+        let metric_1 = create_test_metric("client_1", "version_1", "report_key_1");
+        let metric_2 = create_test_metric("client_2", "version_1", "report_key_1");
+        let mut report = Report::default();
+        for metric in [metric_1, metric_2] {
+            if let Some(metric) =
+                SingleReport::Stats(metric).for_mode(ApolloUsageReportingMode::SignaturesOnly)
+            {
+                report += metric;
+            }
+        }
+        assert!(SingleReport::Traces(Default::default())
+            .for_mode(ApolloUsageReportingMode::SignaturesOnly)
+            .is_none());
+
+        let traces_and_stats = &report.traces_per_query["report_key_1"];
+        assert!(traces_and_stats.referenced_fields_by_type.is_empty());
+        // Both clients are counted in the same context
+        assert_eq!(traces_and_stats.stats_with_context.len(), 1);
+        let (context, stats) = traces_and_stats.stats_with_context.iter().next().unwrap();
+        assert!(context.client_name.is_empty());
+        assert!(context.client_version.is_empty());
+        assert!(stats.per_type_stat.is_empty());
+        assert!(stats.local_per_type_stat.is_empty());
+        assert!(stats
+            .query_latency_stats
+            .root_error_stats
+            .children
+            .is_empty());
+        assert_eq!(stats.query_latency_stats.request_latencies.total_i64(), 2);
+        assert_eq!(report.licensed_operation_count_by_type.len(), 1);
+    }
+
+    #[test]
+    fn test_report_window() {
+        let mut report = Report::default();
+        report.close_window(SystemTime::now());
+        assert_eq!(report.window_duration(), None);
+
+        report += SingleReport::Stats(create_test_metric("client_1", "version_1", "report_key_1"));
+        let start_time = report.start_time.unwrap();
+        let end_time = start_time + Duration::from_secs(5);
+        report.close_window(end_time);
+        assert_eq!(report.window_duration(), Some(Duration::from_secs(5)));
+
+        // Adding records reopens the window, without moving its start
+        report += SingleReport::Stats(create_test_metric("client_1", "version_1", "report_key_1"));
+        assert_eq!(report.start_time, Some(start_time));
+        assert_eq!(report.end_time, None);
+
+        let end_time = start_time + Duration::from_secs(10);
+        report.close_window(end_time);
+        let proto_report = report.build_proto_report(Default::default(), false);
+        assert_eq!(proto_report.end_time, Some(end_time.into()));
+    }
+
     fn create_test_metric(
         client_name: &str,
         client_version: &str,
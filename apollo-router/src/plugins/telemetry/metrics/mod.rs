@@ -433,6 +433,7 @@ pub(crate) struct MetricsBuilder {
     pub(crate) prometheus_meter_provider: Option<opentelemetry::sdk::metrics::MeterProvider>,
     pub(crate) custom_endpoints: MultiMap<ListenAddr, Endpoint>,
     pub(crate) apollo_metrics_sender: Sender,
+    pub(crate) tenant_apollo_metrics_senders: HashMap<String, Sender>,
     pub(crate) resource: Resource,
 }
 
@@ -448,6 +449,7 @@ impl MetricsBuilder {
             prometheus_meter_provider: None,
             custom_endpoints: MultiMap::new(),
             apollo_metrics_sender: Sender::default(),
+            tenant_apollo_metrics_senders: HashMap::new(),
         }
     }
 }
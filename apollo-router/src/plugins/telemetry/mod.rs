@@ -9,6 +9,7 @@ use std::time::Instant;
 use ::tracing::info_span;
 use ::tracing::Span;
 use axum::headers::HeaderName;
+use bytes::Bytes;
 use config_new::cache::CacheInstruments;
 use config_new::instruments::InstrumentsConfig;
 use config_new::instruments::StaticInstrument;
@@ -24,6 +25,7 @@ use http::HeaderValue;
 use http::StatusCode;
 use metrics::apollo::studio::SingleLimitsStats;
 use metrics::local_type_stats::LocalTypeStatRecorder;
+use mime::APPLICATION_JSON;
 use multimap::MultiMap;
 use once_cell::sync::OnceCell;
 use opentelemetry::global::GlobalTracerProvider;
@@ -60,6 +62,7 @@ use uuid::Uuid;
 use self::apollo::ForwardValues;
 use self::apollo::LicensedOperationCountByType;
 use self::apollo::OperationSubType;
+use self::apollo::RedactionConfiguration;
 use self::apollo::SingleReport;
 use self::apollo_exporter::proto;
 use self::apollo_exporter::Sender;
@@ -75,6 +78,7 @@ use self::config_new::spans::Spans;
 use self::metrics::apollo::studio::SingleTypeStat;
 use self::metrics::AttributesForwardConf;
 use self::reload::reload_fmt;
+use self::reload::reload_log_filter;
 pub(crate) use self::span_factory::SpanMode;
 use self::tracing::apollo_telemetry::APOLLO_PRIVATE_DURATION_NS;
 use self::tracing::apollo_telemetry::CLIENT_NAME_KEY;
@@ -85,6 +89,7 @@ use crate::apollo_studio_interop::UsageReporting;
 use crate::context::CONTAINS_GRAPHQL_ERROR;
 use crate::context::OPERATION_KIND;
 use crate::context::OPERATION_NAME;
+use crate::graphql;
 use crate::graphql::ResponseVisitor;
 use crate::layers::instrument::InstrumentLayer;
 use crate::layers::ServiceBuilderExt;
@@ -133,7 +138,9 @@ use crate::query_planner::OperationKind;
 use crate::register_private_plugin;
 use crate::router_factory::Endpoint;
 use crate::services::execution;
+use crate::services::layers::content_negotiation::GRAPHQL_JSON_RESPONSE_HEADER_VALUE;
 use crate::services::router;
+use crate::services::router::body::get_body_bytes;
 use crate::services::subgraph;
 use crate::services::subgraph::Request;
 use crate::services::subgraph::Response;
@@ -150,6 +157,7 @@ use crate::ListenAddr;
 pub(crate) mod apollo;
 pub(crate) mod apollo_exporter;
 pub(crate) mod apollo_otlp_exporter;
+pub(crate) mod apollo_report_sink;
 pub(crate) mod config;
 pub(crate) mod config_new;
 pub(crate) mod consts;
@@ -170,7 +178,7 @@ pub(crate) mod utils;
 
 // Tracing consts
 pub(crate) const CLIENT_NAME: &str = "apollo_telemetry::client_name";
-const CLIENT_VERSION: &str = "apollo_telemetry::client_version";
+pub(crate) const CLIENT_VERSION: &str = "apollo_telemetry::client_version";
 const SUBGRAPH_FTV1: &str = "apollo_telemetry::subgraph_ftv1";
 pub(crate) const STUDIO_EXCLUDE: &str = "apollo_telemetry::studio::exclude";
 pub(crate) const LOGGING_DISPLAY_HEADERS: &str = "apollo_telemetry::logging::display_headers";
@@ -198,6 +206,8 @@ pub(crate) struct Telemetry {
     supergraph_schema_id: Arc<String>,
     custom_endpoints: MultiMap<ListenAddr, Endpoint>,
     apollo_metrics_sender: apollo_exporter::Sender,
+    /// Senders of the tenants reporting to their own graph, keyed by tenant name
+    tenant_apollo_metrics_senders: Arc<HashMap<String, apollo_exporter::Sender>>,
     field_level_instrumentation_ratio: f64,
     pub(crate) graphql_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     router_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
@@ -311,6 +321,7 @@ impl PluginPrivate for Telemetry {
         Ok(Telemetry {
             custom_endpoints: metrics_builder.custom_endpoints,
             apollo_metrics_sender: metrics_builder.apollo_metrics_sender,
+            tenant_apollo_metrics_senders: Arc::new(metrics_builder.tenant_apollo_metrics_senders),
             supergraph_schema_id: init.supergraph_schema_id,
             field_level_instrumentation_ratio,
             activation: Mutex::new(TelemetryActivation {
@@ -474,13 +485,38 @@ impl PluginPrivate for Telemetry {
                     async move {
                         let span = Span::current();
                         span.set_span_dyn_attributes(custom_attributes);
-                        let response: Result<router::Response, BoxError> = fut.await;
+                        let response: Result<router::Response, BoxError> = match fut.await {
+                            Ok(response)
+                                if config.exporters.tracing.response_trace_id.error_extension =>
+                            {
+                                match trace_id() {
+                                    Some(trace_id) => {
+                                        let trace_id = format_trace_id(
+                                            &config.exporters.tracing.response_trace_id.format,
+                                            trace_id,
+                                        );
+                                        Ok(add_trace_id_to_router_errors(response, &trace_id).await)
+                                    }
+                                    None => Ok(response),
+                                }
+                            }
+                            response => response,
+                        };
 
                         span.record(
                             APOLLO_PRIVATE_DURATION_NS,
                             start.elapsed().as_nanos() as i64,
                         );
 
+                        // Plugins such as API keys can identify the client after the request
+                        // headers were read, the span reports the client stored in the context
+                        let client_name: Option<String> = ctx.get(CLIENT_NAME).ok().flatten();
+                        let client_version: Option<String> = ctx.get(CLIENT_VERSION).ok().flatten();
+                        span.set_span_dyn_attributes([
+                            KeyValue::new(CLIENT_NAME_KEY, client_name.unwrap_or_default()),
+                            KeyValue::new(CLIENT_VERSION_KEY, client_version.unwrap_or_default()),
+                        ]);
+
                         let expose_trace_id = &config.exporters.tracing.response_trace_id;
                         if let Ok(response) = &response {
                             span.set_span_dyn_attributes(
@@ -535,6 +571,7 @@ impl PluginPrivate for Telemetry {
                                     OperationKind::Query,
                                     None,
                                     Default::default(),
+                                    Default::default(),
                                 );
                             }
 
@@ -599,22 +636,23 @@ impl PluginPrivate for Telemetry {
                 });
 
                 // Append the trace ID with the right format, based on the config
-                let format_id = |trace_id: TraceId| {
-                    let id = match config.exporters.tracing.response_trace_id.format {
-                        TraceIdFormat::Hexadecimal | TraceIdFormat::OpenTelemetry => format!("{:032x}", trace_id),
-                        TraceIdFormat::Decimal => format!("{}", u128::from_be_bytes(trace_id.to_bytes())),
-                        TraceIdFormat::Datadog => trace_id.to_datadog(),
-                        TraceIdFormat::Uuid => Uuid::from_bytes(trace_id.to_bytes()).to_string(),
-                    };
-
-                    HeaderValue::from_str(&id).ok()
-                };
+                let formatted_trace_id = trace_id().map(|trace_id| {
+                    format_trace_id(&config.exporters.tracing.response_trace_id.format, trace_id)
+                });
                 if let (Some(header_name), Some(trace_id)) = (
                     expose_trace_id_header,
-                    trace_id().and_then(format_id),
+                    formatted_trace_id.as_deref().and_then(|id| HeaderValue::from_str(id).ok()),
                 ) {
                     resp.response.headers_mut().append(header_name, trace_id);
                 }
+                // Errors are correlated with the trace through their `traceId` extension
+                let error_trace_id = formatted_trace_id
+                    .filter(|_| config.exporters.tracing.response_trace_id.error_extension);
+                if error_trace_id.is_some() {
+                    resp.context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(ErrorsWithTraceId));
+                }
 
                 if resp.context.contains_key(LOGGING_DISPLAY_HEADERS) {
                     let sorted_headers = resp
@@ -626,7 +664,10 @@ impl PluginPrivate for Telemetry {
                     ::tracing::info!(http.response.headers = ?sorted_headers, "Supergraph response headers");
                 }
                 let display_body = resp.context.contains_key(LOGGING_DISPLAY_BODY);
-                resp.map_stream(move |gql_response| {
+                resp.map_stream(move |mut gql_response| {
+                    if let Some(trace_id) = &error_trace_id {
+                        add_trace_id_to_errors(&mut gql_response, trace_id);
+                    }
                     if display_body {
                         ::tracing::info!(http.response.body = ?gql_response, "Supergraph GraphQL response");
                     }
@@ -899,11 +940,48 @@ impl PluginPrivate for Telemetry {
         *self.cache_custom_instruments.write() = cache_custom_instruments;
 
         reload_fmt(create_fmt_layer(&self.config));
+        reload_log_filter(&self.config.exporters.logging.common);
         activation.is_active = true;
     }
 }
 
 impl Telemetry {
+    /// Telemetry of the router pipeline of a tenant.
+    ///
+    /// It shares the configuration and instruments of this instance, which remains the only one
+    /// owning the global tracer and meter providers: activating or dropping it does nothing.
+    /// The usage of the tenant is reported to its own graph if it has one.
+    pub(crate) fn for_tenant(&self, name: &str, supergraph_schema_id: Arc<String>) -> Telemetry {
+        Telemetry {
+            config: self.config.clone(),
+            supergraph_schema_id,
+            custom_endpoints: MultiMap::new(),
+            apollo_metrics_sender: self
+                .tenant_apollo_metrics_senders
+                .get(name)
+                .unwrap_or(&self.apollo_metrics_sender)
+                .clone(),
+            tenant_apollo_metrics_senders: Default::default(),
+            field_level_instrumentation_ratio: self.field_level_instrumentation_ratio,
+            graphql_custom_instruments: RwLock::new(self.graphql_custom_instruments.read().clone()),
+            router_custom_instruments: RwLock::new(self.router_custom_instruments.read().clone()),
+            supergraph_custom_instruments: RwLock::new(
+                self.supergraph_custom_instruments.read().clone(),
+            ),
+            subgraph_custom_instruments: RwLock::new(
+                self.subgraph_custom_instruments.read().clone(),
+            ),
+            cache_custom_instruments: RwLock::new(self.cache_custom_instruments.read().clone()),
+            activation: Mutex::new(TelemetryActivation {
+                tracer_provider: None,
+                public_meter_provider: None,
+                public_prometheus_meter_provider: None,
+                private_meter_provider: None,
+                is_active: true,
+            }),
+        }
+    }
+
     fn create_propagator(config: &config::Conf) -> TextMapCompositePropagator {
         let propagation = &config.exporters.tracing.propagation;
 
@@ -976,13 +1054,15 @@ impl Telemetry {
         Ok(builder)
     }
 
+    /// Encodes the variables recorded on the supergraph span. This is the only place variables
+    /// are added to traces, so the redaction applies to every exporter.
     fn filter_variables_values(
         variables: &Map<ByteString, Value>,
         forward_rules: &ForwardValues,
+        redaction: &RedactionConfiguration,
     ) -> String {
         let nb_var = variables.len();
-        #[allow(clippy::mutable_key_type)] // False positive lint
-        let variables = variables
+        let mut variables = variables
             .iter()
             .map(|(name, value)| {
                 if match &forward_rules {
@@ -992,17 +1072,18 @@ impl Telemetry {
                     ForwardValues::Except(except) => !except.contains(&name.as_str().to_string()),
                 } {
                     (
-                        name,
+                        name.as_str().to_string(),
                         serde_json::to_string(value).unwrap_or_else(|_| "<unknown>".to_string()),
                     )
                 } else {
-                    (name, "".to_string())
+                    (name.as_str().to_string(), "".to_string())
                 }
             })
             .fold(HashMap::with_capacity(nb_var), |mut acc, (name, value)| {
                 acc.insert(name, value);
                 acc
             });
+        redaction.redact_variables(&mut variables);
 
         match serde_json::to_string(&variables) {
             Ok(result) => result,
@@ -1329,6 +1410,7 @@ impl Telemetry {
                         operation_kind,
                         operation_subtype,
                         Default::default(),
+                        Default::default(),
                     );
                 }
                 let mut metric_attrs = Vec::new();
@@ -1369,6 +1451,7 @@ impl Telemetry {
                         operation_kind,
                         Some(OperationSubType::SubscriptionRequest),
                         Default::default(),
+                        Default::default(),
                     );
                 }
                 Ok(router_response.map(move |response_stream| {
@@ -1382,12 +1465,17 @@ impl Telemetry {
                     // responses. To avoid submitting duplicates, the recorder's contents are drained each time
                     // metrics are submitted.
                     let mut local_stat_recorder = LocalTypeStatRecorder::new();
+                    // Errors are attributed to their response path when the subgraphs do not send
+                    // FTV1 traces. Like the local field stats, they are aggregated across the
+                    // responses of a deferred operation.
+                    let mut response_error_stats = SinglePathErrorStats::default();
 
                     response_stream
                         .enumerate()
                         .map(move |(idx, response)| {
                             let has_errors = !response.errors.is_empty();
                             if !matches!(sender, Sender::Noop) {
+                                response_error_stats.record_errors(&response.errors);
                                 if let (true, Some(query)) = (
                                     config.apollo.experimental_local_field_metrics,
                                     ctx.unsupported_executable_document(),
@@ -1418,6 +1506,7 @@ impl Telemetry {
                                                     .local_type_stats
                                                     .drain()
                                                     .collect(),
+                                                std::mem::take(&mut response_error_stats),
                                             );
                                         }
                                     } else {
@@ -1434,6 +1523,7 @@ impl Telemetry {
                                             operation_kind,
                                             Some(OperationSubType::SubscriptionEvent),
                                             local_stat_recorder.local_type_stats.drain().collect(),
+                                            std::mem::take(&mut response_error_stats),
                                         );
                                     }
                                 } else {
@@ -1448,6 +1538,7 @@ impl Telemetry {
                                             operation_kind,
                                             None,
                                             local_stat_recorder.local_type_stats.drain().collect(),
+                                            std::mem::take(&mut response_error_stats),
                                         );
                                     }
                                 }
@@ -1471,6 +1562,7 @@ impl Telemetry {
         operation_kind: OperationKind,
         operation_subtype: Option<OperationSubType>,
         local_per_type_stat: HashMap<String, LocalTypeStat>,
+        response_error_stats: SinglePathErrorStats,
     ) {
         let metrics = if let Some(usage_reporting) = context
             .extensions()
@@ -1500,7 +1592,13 @@ impl Telemetry {
             } else {
                 let traces = Self::subgraph_ftv1_traces(context);
                 let per_type_stat = Self::per_type_stat(&traces, field_level_instrumentation_ratio);
-                let root_error_stats = Self::per_path_error_stats(&traces);
+                // Subgraph errors are in both the FTV1 traces and the response, so they are only
+                // counted in one tree: the response paths are used when no subgraph sent a trace
+                let root_error_stats = if traces.is_empty() {
+                    response_error_stats
+                } else {
+                    Self::per_path_error_stats(&traces)
+                };
                 let strategy = context.get_demand_control_context().map(|c| c.strategy);
                 let limits_stats = context.extensions().with_lock(|guard| {
                     let query_limits = guard.get::<OperationLimits<u32>>();
@@ -1831,6 +1929,93 @@ impl TelemetryActivation {
     }
 }
 
+const TRACE_ID_ERROR_EXTENSION: &str = "traceId";
+
+/// Marks the responses whose errors got the trace ID in the supergraph service
+#[derive(Clone)]
+struct ErrorsWithTraceId;
+
+fn format_trace_id(format: &TraceIdFormat, trace_id: TraceId) -> String {
+    match format {
+        TraceIdFormat::Hexadecimal | TraceIdFormat::OpenTelemetry => format!("{:032x}", trace_id),
+        TraceIdFormat::Decimal => format!("{}", u128::from_be_bytes(trace_id.to_bytes())),
+        TraceIdFormat::Datadog => trace_id.to_datadog(),
+        TraceIdFormat::Uuid => Uuid::from_bytes(trace_id.to_bytes()).to_string(),
+    }
+}
+
+/// Adds the trace ID to the errors of the responses that did not go through the supergraph
+/// service, like the ones rejecting a request before it is parsed.
+///
+/// Those responses are a single JSON object, so they are buffered to be modified.
+async fn add_trace_id_to_router_errors(
+    response: router::Response,
+    trace_id: &str,
+) -> router::Response {
+    let already_added = response
+        .context
+        .extensions()
+        .with_lock(|lock| lock.contains_key::<ErrorsWithTraceId>());
+    let is_json = match response.response.headers().get(header::CONTENT_TYPE) {
+        Some(content_type) => content_type.to_str().is_ok_and(|content_type| {
+            content_type.starts_with(APPLICATION_JSON.essence_str())
+                || content_type.starts_with(GRAPHQL_JSON_RESPONSE_HEADER_VALUE)
+        }),
+        None => true,
+    };
+    if already_added || !is_json {
+        return response;
+    }
+
+    let router::Response { response, context } = response;
+    let (mut parts, body) = response.into_parts();
+    let body = match get_body_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            let body = router::Body::wrap_stream(once(ready(Err::<Bytes, _>(err))));
+            return router::Response {
+                response: http::Response::from_parts(parts, body),
+                context,
+            };
+        }
+    };
+    let body = match serde_json::from_slice::<graphql::Response>(&body) {
+        Ok(mut graphql_response) if !graphql_response.errors.is_empty() => {
+            add_trace_id_to_errors(&mut graphql_response, trace_id);
+            match serde_json::to_vec(&graphql_response) {
+                Ok(modified) => {
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                    modified.into()
+                }
+                Err(_) => body,
+            }
+        }
+        _ => body,
+    };
+
+    router::Response {
+        response: http::Response::from_parts(parts, router::Body::from(body)),
+        context,
+    }
+}
+
+/// Adds the trace ID to the extensions of the errors that don't already have one
+fn add_trace_id_to_errors(response: &mut graphql::Response, trace_id: &str) {
+    let errors = response.errors.iter_mut().chain(
+        response
+            .incremental
+            .iter_mut()
+            .flat_map(|incremental| incremental.errors.iter_mut()),
+    );
+    for error in errors {
+        if !error.extensions.contains_key(TRACE_ID_ERROR_EXTENSION) {
+            error
+                .extensions
+                .insert(TRACE_ID_ERROR_EXTENSION, Value::from(trace_id));
+        }
+    }
+}
+
 fn filter_headers(headers: &HeaderMap, forward_rules: &ForwardHeaders) -> String {
     if let ForwardHeaders::None = forward_rules {
         return String::from("{}");
@@ -2153,6 +2338,8 @@ mod tests {
     use tracing_subscriber::layer::SubscriberExt;
     use tracing_subscriber::Layer;
 
+    use super::add_trace_id_to_errors;
+    use super::add_trace_id_to_router_errors;
     use super::apollo::ForwardHeaders;
     use super::CustomTraceIdPropagator;
     use super::Telemetry;
@@ -3153,6 +3340,70 @@ mod tests {
         .await;
     }
 
+    #[test]
+    fn it_adds_trace_id_to_errors() {
+        let mut response = graphql::Response::builder()
+            .error(
+                Error::builder()
+                    .message("first")
+                    .extension_code("A")
+                    .build(),
+            )
+            .error(
+                Error::builder()
+                    .message("second")
+                    .extension_code("B")
+                    .extension("traceId", "existing")
+                    .build(),
+            )
+            .build();
+        add_trace_id_to_errors(&mut response, "0123");
+        assert_eq!(
+            response.errors[0].extensions.get("traceId"),
+            Some(&json!("0123"))
+        );
+        assert_eq!(
+            response.errors[1].extensions.get("traceId"),
+            Some(&json!("existing"))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_adds_trace_id_to_router_errors() {
+        let error_response = || {
+            crate::services::router::Response::error_builder()
+                .error(
+                    Error::builder()
+                        .message("invalid request")
+                        .extension_code("INVALID")
+                        .build(),
+                )
+                .status_code(StatusCode::BAD_REQUEST)
+                .header(CONTENT_TYPE, "application/json")
+                .context(crate::Context::new())
+                .build()
+                .unwrap()
+        };
+
+        let response = add_trace_id_to_router_errors(error_response(), "0123").await;
+        let body: Value =
+            serde_json::from_slice(&get_body_bytes(response.response.into_body()).await.unwrap())
+                .unwrap();
+        assert_eq!(body["errors"][0]["extensions"]["traceId"], "0123");
+
+        // the errors of responses from the supergraph service already have it
+        let response = error_response();
+        response
+            .context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(super::ErrorsWithTraceId));
+        let response = add_trace_id_to_router_errors(response, "0123").await;
+        let body: Value =
+            serde_json::from_slice(&get_body_bytes(response.response.into_body()).await.unwrap())
+                .unwrap();
+        assert!(body["errors"][0]["extensions"]["traceId"].is_null());
+    }
+
     #[test]
     fn it_test_send_headers_to_studio() {
         let fw_headers = ForwardHeaders::Only(vec![
@@ -3448,4 +3699,68 @@ mod tests {
         .with_metrics()
         .await;
     }
+
+    #[test]
+    fn test_filter_variables_values_redaction() {
+        use super::apollo::ForwardValues;
+        use super::apollo::RedactionConfiguration;
+
+        let variables = json!({
+            "id": "1234",
+            "password": "hunter2",
+            "filter": { "name": "tea", "tags": ["green"], "limit": 3 },
+        });
+        let variables = variables.as_object().unwrap();
+        let filter = |forward: &ForwardValues, redaction: &RedactionConfiguration| {
+            serde_json::from_str::<HashMap<String, String>>(&Telemetry::filter_variables_values(
+                variables, forward, redaction,
+            ))
+            .unwrap()
+        };
+
+        let all = filter(&ForwardValues::All, &RedactionConfiguration::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all["id"], r#""1234""#);
+
+        let redacted = filter(
+            &ForwardValues::All,
+            &RedactionConfiguration {
+                arguments: vec!["password".to_string()],
+                hash_string_literals: true,
+                hash_key: Some("secret".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(redacted.len(), 2);
+        // HMAC-SHA256 of "1234" keyed with "secret"
+        assert_eq!(
+            redacted["id"],
+            r#""55124a287e8ddc58a97eb3eea634a4d3185428d552de1a2b5bd49511355ababa""#
+        );
+        let filter_value: Value = serde_json::from_str(&redacted["filter"]).unwrap();
+        assert_eq!(filter_value["limit"], 3);
+        assert_eq!(filter_value["tags"][0].as_str().unwrap().len(), 64);
+
+        // Without a key, strings are hashed with a random key
+        let random_key = filter(
+            &ForwardValues::All,
+            &RedactionConfiguration {
+                hash_string_literals: true,
+                ..Default::default()
+            },
+        );
+        assert_ne!(random_key["id"], redacted["id"]);
+
+        // Variable values are never sent, even if they are forwarded
+        let private = filter(
+            &ForwardValues::All,
+            &RedactionConfiguration {
+                variable_values: true,
+                hash_string_literals: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(private.len(), 3);
+        assert!(private.values().all(String::is_empty));
+    }
 }
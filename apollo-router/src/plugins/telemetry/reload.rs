@@ -1,4 +1,5 @@
 use std::io::IsTerminal;
+use std::sync::Mutex;
 
 use anyhow::anyhow;
 use anyhow::Result;
@@ -21,11 +22,13 @@ use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::Registry;
 
+use super::config_new::logging::LoggingCommon;
 use super::config_new::logging::RateLimit;
 use super::dynamic_attribute::DynAttributeLayer;
 use super::fmt_layer::FmtLayer;
 use super::formatters::json::Json;
 use super::metrics::span_metrics_exporter::SpanMetricsLayer;
+use crate::executable::router_log_filter;
 use crate::metrics::layer::MetricsLayer;
 use crate::metrics::meter_provider;
 use crate::plugins::telemetry::formatters::filter_metric_events;
@@ -52,6 +55,34 @@ static FMT_LAYER_HANDLE: OnceCell<
     Handle<Box<dyn Layer<LayeredTracer> + Send + Sync>, LayeredTracer>,
 > = OnceCell::new();
 
+type LayeredFmt = Layered<
+    MetricsLayer,
+    Layered<
+        tracing_subscriber::reload::Layer<
+            Box<dyn Layer<LayeredTracer> + Send + Sync>,
+            LayeredTracer,
+        >,
+        LayeredTracer,
+    >,
+>;
+
+static FILTER_HANDLE: OnceCell<Handle<EnvFilter, LayeredFmt>> = OnceCell::new();
+
+/// The log level given on the command line or in the environment
+static DEFAULT_LOG_LEVEL: OnceCell<String> = OnceCell::new();
+
+/// The log filter of the configuration, and the one set through the admin API which takes
+/// precedence over it until it is removed
+static LOG_FILTERS: Mutex<LogFilters> = Mutex::new(LogFilters {
+    configured: None,
+    overridden: None,
+});
+
+struct LogFilters {
+    configured: Option<String>,
+    overridden: Option<String>,
+}
+
 pub(super) static METRICS_LAYER: OnceCell<MetricsLayer> = OnceCell::new();
 pub(crate) fn metrics_layer() -> &'static MetricsLayer {
     METRICS_LAYER.get_or_init(|| MetricsLayer::new(meter_provider().clone()))
@@ -86,13 +117,17 @@ pub(crate) fn init_telemetry(log_level: &str) -> Result<()> {
     let (fmt_layer, fmt_handle) = tracing_subscriber::reload::Layer::new(fmt);
 
     let metrics_layer = metrics_layer();
+    let mut filter_handle = None;
 
     // Stash the reload handles so that we can hot reload later
     OPENTELEMETRY_TRACER_HANDLE
-        .get_or_try_init(move || {
+        .get_or_try_init(|| {
             // manually filter salsa logs because some of them run at the INFO level https://github.com/salsa-rs/salsa/issues/425
-            let log_level = format!("{log_level},salsa=error");
-            tracing::debug!("Running the router with log level set to {log_level}");
+            let log_filter = format!("{log_level},salsa=error");
+            tracing::debug!("Running the router with log level set to {log_filter}");
+            let (filter_layer, handle) =
+                tracing_subscriber::reload::Layer::new(EnvFilter::try_new(log_filter)?);
+            filter_handle = Some(handle);
             // Env filter is separate because of https://github.com/tokio-rs/tracing/issues/1629
             // the tracing registry is only created once
             tracing_subscriber::registry()
@@ -101,7 +136,7 @@ pub(crate) fn init_telemetry(log_level: &str) -> Result<()> {
                 .with(opentelemetry_layer)
                 .with(fmt_layer)
                 .with(metrics_layer.clone())
-                .with(EnvFilter::try_new(log_level)?)
+                .with(filter_layer)
                 .try_init()?;
 
             Ok(hot_tracer)
@@ -110,6 +145,10 @@ pub(crate) fn init_telemetry(log_level: &str) -> Result<()> {
     FMT_LAYER_HANDLE
         .set(fmt_handle)
         .map_err(|_| anyhow!("failed to set fmt layer handle"))?;
+    if let Some(filter_handle) = filter_handle {
+        let _ = FILTER_HANDLE.set(filter_handle);
+        let _ = DEFAULT_LOG_LEVEL.set(log_level.to_string());
+    }
 
     Ok(())
 }
@@ -120,6 +159,48 @@ pub(super) fn reload_fmt(layer: Box<dyn Layer<LayeredTracer> + Send + Sync>) {
     }
 }
 
+/// Applies the log levels of the logging configuration, on top of the log level given on the
+/// command line
+pub(super) fn reload_log_filter(config: &LoggingCommon) {
+    if let Some(default_log_level) = DEFAULT_LOG_LEVEL.get() {
+        let mut filters = LOG_FILTERS.lock().expect("lock poisoned");
+        let log_filter = config.log_filter(default_log_level);
+        if filters.overridden.is_none() {
+            apply_log_filter(&log_filter);
+        }
+        filters.configured = Some(log_filter);
+    }
+}
+
+/// Overrides the log level of the configuration, or restores it if `level` is `None`
+pub(crate) fn override_log_level(level: Option<String>) {
+    let Some(default_log_level) = DEFAULT_LOG_LEVEL.get() else {
+        return;
+    };
+    let mut filters = LOG_FILTERS.lock().expect("lock poisoned");
+    filters.overridden = level.map(|level| format!("{},salsa=error", router_log_filter(&level)));
+    let log_filter = filters
+        .overridden
+        .clone()
+        .or_else(|| filters.configured.clone())
+        .unwrap_or_else(|| format!("{default_log_level},salsa=error"));
+    apply_log_filter(&log_filter);
+}
+
+fn apply_log_filter(log_filter: &str) {
+    if let Some(handle) = FILTER_HANDLE.get() {
+        match EnvFilter::try_new(log_filter) {
+            Ok(filter) => {
+                if handle.reload(filter).is_ok() {
+                    tracing::debug!("Running the router with log level set to {log_filter}");
+                }
+            }
+            // The log levels are validated with the configuration and by the admin API
+            Err(err) => tracing::error!("invalid log level '{log_filter}': {err}"),
+        }
+    }
+}
+
 pub(crate) fn apollo_opentelemetry_initialized() -> bool {
     OPENTELEMETRY_TRACER_HANDLE.get().is_some()
 }
@@ -138,6 +138,7 @@ impl SpanMode {
                     apollo_private.graphql.variables = Telemetry::filter_variables_values(
                         &request.supergraph_request.body().variables,
                         &send_variable_values,
+                        &config.redaction,
                     ),
                 );
 
@@ -161,6 +162,7 @@ impl SpanMode {
                     apollo_private.graphql.variables = Telemetry::filter_variables_values(
                         &request.supergraph_request.body().variables,
                         &send_variable_values,
+                        &config.redaction,
                     )
                 )
             }
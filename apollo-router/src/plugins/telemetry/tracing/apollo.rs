@@ -1,4 +1,6 @@
 //! Tracing configuration for apollo telemetry.
+use std::borrow::Cow;
+
 use opentelemetry::sdk::trace::BatchSpanProcessor;
 use opentelemetry::sdk::trace::Builder;
 use serde::Serialize;
@@ -7,6 +9,7 @@ use tower::BoxError;
 use crate::plugins::telemetry::apollo::Config;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::Trace;
 use crate::plugins::telemetry::config;
+use crate::plugins::telemetry::config::ApolloUsageReportingMode;
 use crate::plugins::telemetry::config_new::spans::Spans;
 use crate::plugins::telemetry::span_factory::SpanMode;
 use crate::plugins::telemetry::tracing::apollo_telemetry;
@@ -14,7 +17,9 @@ use crate::plugins::telemetry::tracing::TracingConfigurator;
 
 impl TracingConfigurator for Config {
     fn enabled(&self) -> bool {
-        self.apollo_key.is_some() && self.apollo_graph_ref.is_some()
+        ((self.apollo_key.is_some() && self.apollo_graph_ref.is_some())
+            || !self.experimental_report_sinks.sinks.is_empty())
+            && self.experimental_usage_reporting_mode == ApolloUsageReportingMode::Full
     }
 
     fn apply(
@@ -24,21 +29,21 @@ impl TracingConfigurator for Config {
         spans_config: &Spans,
     ) -> Result<Builder, BoxError> {
         tracing::debug!("configuring Apollo tracing");
+        // Without an Apollo key and graph ref, traces are only written to the report sinks
+        let (apollo_key, report_sinks) = match (&self.apollo_key, &self.apollo_graph_ref) {
+            (Some(apollo_key), Some(_)) => (
+                apollo_key.as_str(),
+                Cow::Borrowed(&self.experimental_report_sinks),
+            ),
+            _ => ("", Cow::Owned(self.experimental_report_sinks.sinks_only())),
+        };
         let exporter = apollo_telemetry::Exporter::builder()
             .endpoint(&self.endpoint)
             .otlp_endpoint(&self.experimental_otlp_endpoint)
             .otlp_tracing_protocol(&self.experimental_otlp_tracing_protocol)
             .otlp_tracing_sampler(&self.experimental_otlp_tracing_sampler)
-            .apollo_key(
-                self.apollo_key
-                    .as_ref()
-                    .expect("apollo_key is checked in the enabled function, qed"),
-            )
-            .apollo_graph_ref(
-                self.apollo_graph_ref
-                    .as_ref()
-                    .expect("apollo_graph_ref is checked in the enabled function, qed"),
-            )
+            .apollo_key(apollo_key)
+            .apollo_graph_ref(self.apollo_graph_ref.as_deref().unwrap_or_default())
             .schema_id(&self.schema_id)
             .buffer_size(self.buffer_size)
             .field_execution_sampler(&self.field_level_instrumentation_sampler)
@@ -46,6 +51,9 @@ impl TracingConfigurator for Config {
             .errors_configuration(&self.errors)
             .use_legacy_request_span(matches!(spans_config.mode, SpanMode::Deprecated))
             .metrics_reference_mode(self.metrics_reference_mode)
+            .usage_reporting_mode(self.experimental_usage_reporting_mode)
+            .report_sinks(&*report_sinks)
+            .tenants(&self.tenants)
             .build()?;
         Ok(builder.with_span_processor(
             BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
@@ -56,7 +64,7 @@ impl TracingConfigurator for Config {
 }
 
 // List of signature and trace by request_id
-#[derive(Default, Debug, Serialize)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub(crate) struct TracesReport {
     // signature and trace
     pub(crate) traces: Vec<(String, Trace)>,
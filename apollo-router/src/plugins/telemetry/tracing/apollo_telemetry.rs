@@ -43,6 +43,7 @@ use crate::plugins::telemetry::apollo::ErrorConfiguration;
 use crate::plugins::telemetry::apollo::ErrorsConfiguration;
 use crate::plugins::telemetry::apollo::OperationSubType;
 use crate::plugins::telemetry::apollo::SingleReport;
+use crate::plugins::telemetry::apollo::TenantGraph;
 use crate::plugins::telemetry::apollo_exporter::proto;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::http::Method;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::http::Values;
@@ -63,7 +64,9 @@ use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::Limits;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::QueryPlanNode;
 use crate::plugins::telemetry::apollo_exporter::ApolloExporter;
 use crate::plugins::telemetry::apollo_otlp_exporter::ApolloOtlpExporter;
+use crate::plugins::telemetry::apollo_report_sink::ReportSinksConfig;
 use crate::plugins::telemetry::config::ApolloMetricsReferenceMode;
+use crate::plugins::telemetry::config::ApolloUsageReportingMode;
 use crate::plugins::telemetry::config::Sampler;
 use crate::plugins::telemetry::config::SamplerOption;
 use crate::plugins::telemetry::config_new::cost::APOLLO_PRIVATE_COST_ACTUAL;
@@ -111,6 +114,8 @@ const APOLLO_PRIVATE_HTTP_RESPONSE_HEADERS: Key =
 pub(crate) const APOLLO_PRIVATE_OPERATION_SIGNATURE: Key =
     Key::from_static_str("apollo_private.operation_signature");
 pub(crate) const APOLLO_PRIVATE_FTV1: Key = Key::from_static_str("apollo_private.ftv1");
+/// Name of the tenant serving the request, set on the root span
+pub(crate) const APOLLO_PRIVATE_TENANT: Key = Key::from_static_str("apollo_private.tenant");
 const PATH: Key = Key::from_static_str("graphql.path");
 const SUBGRAPH_NAME: Key = Key::from_static_str("apollo.subgraph.name");
 pub(crate) const CLIENT_NAME_KEY: Key = Key::from_static_str("client.name");
@@ -301,6 +306,9 @@ pub(crate) struct Exporter {
     span_lru_size_instrument: SpanLruSizeInstrument,
     #[derivative(Debug = "ignore")]
     report_exporter: Option<Arc<ApolloExporter>>,
+    /// Exporters of the tenants reporting to their own graph, keyed by tenant name
+    #[derivative(Debug = "ignore")]
+    tenant_report_exporters: HashMap<String, Arc<ApolloExporter>>,
     #[derivative(Debug = "ignore")]
     otlp_exporter: Option<Arc<ApolloOtlpExporter>>,
     otlp_tracing_ratio: f64,
@@ -354,10 +362,15 @@ impl Exporter {
         batch_config: &'a BatchProcessorConfig,
         use_legacy_request_span: Option<bool>,
         metrics_reference_mode: ApolloMetricsReferenceMode,
+        usage_reporting_mode: ApolloUsageReportingMode,
+        report_sinks: &'a ReportSinksConfig,
+        tenants: &'a HashMap<String, TenantGraph>,
     ) -> Result<Self, BoxError> {
         tracing::debug!("creating studio exporter");
 
         let otlp_tracing_ratio = match otlp_tracing_sampler {
+            // OTLP traces are only sent to Apollo Studio, the report sinks need them all in reports
+            _ if !report_sinks.send_to_apollo => 0f64,
             SamplerOption::TraceIdRatioBased(ratio) => {
                 // can't use std::cmp::min because f64 is not Ord
                 if *ratio > 1.0 {
@@ -385,10 +398,30 @@ impl Exporter {
                     apollo_graph_ref,
                     schema_id,
                     metrics_reference_mode,
+                    usage_reporting_mode,
+                    report_sinks,
                 )?))
             } else {
                 None
             },
+            tenant_report_exporters: tenants
+                .iter()
+                .map(|(name, graph)| {
+                    Ok((
+                        name.clone(),
+                        Arc::new(ApolloExporter::new(
+                            endpoint,
+                            batch_config,
+                            &graph.apollo_key,
+                            &graph.apollo_graph_ref,
+                            &graph.schema_id,
+                            metrics_reference_mode,
+                            usage_reporting_mode,
+                            report_sinks,
+                        )?),
+                    ))
+                })
+                .collect::<Result<_, BoxError>>()?,
             otlp_exporter: if otlp_tracing_ratio > 0f64 {
                 Some(Arc::new(ApolloOtlpExporter::new(
                     otlp_endpoint,
@@ -1067,6 +1100,8 @@ impl SpanExporter for Exporter {
         // We do what we can, and if there are any traces that are not complete then we keep them for the next export event.
         // We may get spans that simply don't complete. These need to be cleaned up after a period. It's the price of using ftv1.
         let mut traces: Vec<(String, proto::reports::Trace)> = Vec::new();
+        let mut tenant_traces: HashMap<String, Vec<(String, proto::reports::Trace)>> =
+            HashMap::new();
         let mut otlp_trace_spans: Vec<Vec<SpanData>> = Vec::new();
 
         // Decide whether to send via OTLP or reports proto based on the sampling config.  Roll dice if using a percentage rollout.
@@ -1078,9 +1113,25 @@ impl SpanExporter for Exporter {
             if span.attributes.get(&APOLLO_PRIVATE_REQUEST).is_some()
                 || span.name == SUBSCRIPTION_EVENT_SPAN_NAME
             {
+                // Traces of tenants reporting to their own graph always use the reports protocol
+                let tenant = span
+                    .attributes
+                    .get(&APOLLO_PRIVATE_TENANT)
+                    .map(|tenant| tenant.as_str().into_owned())
+                    .filter(|tenant| self.tenant_report_exporters.contains_key(tenant));
                 let root_span: LightSpanData =
                     LightSpanData::from_span_data(span, &self.include_attr_names);
-                if send_otlp {
+                if let Some(tenant) = tenant {
+                    if let Ok(extracted_traces) = self.extract_traces(root_span) {
+                        let traces = tenant_traces.entry(tenant).or_default();
+                        for mut trace in extracted_traces {
+                            let operation_signature = std::mem::take(&mut trace.signature);
+                            if !operation_signature.is_empty() {
+                                traces.push((operation_signature, trace));
+                            }
+                        }
+                    }
+                } else if send_otlp {
                     let grouped_trace_spans = self.group_by_trace(root_span);
                     if let Some(trace) = self
                         .otlp_exporter
@@ -1149,7 +1200,25 @@ impl SpanExporter for Exporter {
             None => None,
         };
 
+        let tenant_reports: Vec<_> = tenant_traces
+            .into_iter()
+            .filter(|(_, traces)| !traces.is_empty())
+            .filter_map(|(tenant, traces)| {
+                let exporter = self.tenant_report_exporters.get(&tenant)?.clone();
+                let mut report = telemetry::apollo::Report::default();
+                report += SingleReport::Traces(TracesReport { traces });
+                Some((exporter, report))
+            })
+            .collect();
+
         let fut = async move {
+            for (exporter, report) in tenant_reports {
+                exporter
+                    .submit_report(report)
+                    .map_err(|e| TraceError::ExportFailed(Box::new(e)))
+                    .await?;
+            }
+
             if send_otlp && !otlp_trace_spans.is_empty() {
                 otlp_exporter
                     .as_ref()
@@ -0,0 +1,77 @@
+//! Deadline of the requests that return partial results when they time out
+//!
+//! With the `partial` timeout policy, the router timeout does not cancel the whole request.
+//! Its deadline is stored in the context instead, and query planning and subgraph fetches that
+//! are still running when it expires fail with timeout errors, so that the data gathered so far
+//! is returned to the client.
+//!
+//! Subscriptions do not have a deadline: their events are executed long after the initial
+//! request, with the same context.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::timeout::Elapsed;
+use crate::Context;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RequestDeadline(Instant);
+
+impl RequestDeadline {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self(Instant::now() + timeout)
+    }
+
+    /// Stores the deadline in the context extensions
+    pub(crate) fn insert(self, context: &Context) {
+        context.extensions().with_lock(|mut lock| lock.insert(self));
+    }
+
+    /// Removes the deadline from the context extensions
+    pub(crate) fn remove(context: &Context) {
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.remove::<RequestDeadline>());
+    }
+
+    /// Runs `future` until the deadline of the request, if it has one
+    pub(crate) async fn run<F: Future>(context: &Context, future: F) -> Result<F::Output, Elapsed> {
+        let deadline = context
+            .extensions()
+            .with_lock(|lock| lock.get::<RequestDeadline>().copied());
+        match deadline {
+            Some(RequestDeadline(deadline)) => tokio::time::timeout_at(deadline, future)
+                .await
+                .map_err(|_| Elapsed::new()),
+            None => Ok(future.await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn futures_stop_at_the_deadline() {
+        let context = Context::new();
+        assert_eq!(
+            RequestDeadline::run(&context, async { 1 }).await.unwrap(),
+            1
+        );
+
+        RequestDeadline::new(Duration::from_millis(20)).insert(&context);
+        assert_eq!(
+            RequestDeadline::run(&context, async { 1 }).await.unwrap(),
+            1
+        );
+        let slow = tokio::time::sleep(Duration::from_secs(10));
+        assert!(RequestDeadline::run(&context, slow).await.is_err());
+
+        RequestDeadline::remove(&context);
+        let slow = tokio::time::sleep(Duration::from_millis(50));
+        assert!(RequestDeadline::run(&context, slow).await.is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+//! Request hedging
+//!
+//! When an idempotent subgraph fetch takes longer than a percentile of the recent fetch
+//! latencies, a second identical request is sent, and the first successful response is used.
+//! The other request is cancelled.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::select;
+use futures::future::BoxFuture;
+use futures::future::Either;
+use futures::FutureExt;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+use tower::ServiceExt;
+
+use super::idempotency::Idempotency;
+use crate::services::subgraph;
+
+/// Number of recent fetch latencies used to compute the hedging delay
+const MAX_LATENCY_SAMPLES: usize = 1000;
+/// No request is hedged until enough latencies were recorded
+const MIN_LATENCY_SAMPLES: usize = 20;
+/// The hedging delay is computed again at most once per interval
+const DELAY_RECOMPUTE_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+struct Latencies {
+    samples: VecDeque<Duration>,
+    delay: Option<Duration>,
+    computed_at: Option<Instant>,
+}
+
+impl Latencies {
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() >= MAX_LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    fn delay(&mut self, percentile: f64, min_delay: Duration) -> Option<Duration> {
+        let now = Instant::now();
+        if self
+            .computed_at
+            .is_some_and(|computed_at| now.duration_since(computed_at) < DELAY_RECOMPUTE_INTERVAL)
+        {
+            return self.delay;
+        }
+
+        self.delay = if self.samples.len() < MIN_LATENCY_SAMPLES {
+            None
+        } else {
+            let mut latencies: Vec<Duration> = self.samples.iter().copied().collect();
+            latencies.sort_unstable();
+            let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+            latencies
+                .get(rank.saturating_sub(1))
+                .map(|latency| (*latency).max(min_delay))
+        };
+        self.computed_at = Some(now);
+        self.delay
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct HedgingLayer {
+    subgraph_name: Arc<String>,
+    percentile: f64,
+    min_delay: Duration,
+    latencies: Arc<Mutex<Latencies>>,
+}
+
+impl HedgingLayer {
+    pub(crate) fn new(subgraph_name: &str, percentile: f64, min_delay: Duration) -> Self {
+        Self {
+            subgraph_name: Arc::new(subgraph_name.to_string()),
+            percentile,
+            min_delay,
+            latencies: Arc::new(Mutex::new(Latencies {
+                samples: VecDeque::new(),
+                delay: None,
+                computed_at: None,
+            })),
+        }
+    }
+}
+
+impl<S> Layer<S> for HedgingLayer {
+    type Service = Hedging<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Hedging {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Hedging<S> {
+    inner: S,
+    config: HedgingLayer,
+}
+
+impl<S> Service<subgraph::Request> for Hedging<S>
+where
+    S: Service<subgraph::Request, Response = subgraph::Response, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<subgraph::Response, BoxError>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Each request is sent through its own clone of the inner service, which is only
+        // polled when the request is sent
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: subgraph::Request) -> Self::Future {
+        let config = self.config.clone();
+        let inner = self.inner.clone();
+        let idempotent = request
+            .subgraph_request
+            .extensions()
+            .get::<Idempotency>()
+            .is_some_and(|idempotency| *idempotency == Idempotency::Idempotent);
+        let delay = if idempotent {
+            config
+                .latencies
+                .lock()
+                .unwrap()
+                .delay(config.percentile, config.min_delay)
+        } else {
+            None
+        };
+
+        async move {
+            let start = Instant::now();
+            let Some(delay) = delay else {
+                let response = inner.oneshot(request).await;
+                config.latencies.lock().unwrap().record(start.elapsed());
+                return response;
+            };
+
+            let hedged_request = request.clone();
+            let primary = inner.clone().oneshot(request).boxed();
+            let primary = match select(primary, Box::pin(tokio::time::sleep(delay))).await {
+                Either::Left((response, _)) => {
+                    config.latencies.lock().unwrap().record(start.elapsed());
+                    return response;
+                }
+                Either::Right((_, primary)) => primary,
+            };
+
+            let hedge = inner.oneshot(hedged_request).boxed();
+            // The first successful response wins, the other request is dropped
+            let (response, hedge_won) = match select(primary, hedge).await {
+                Either::Left((Ok(response), _)) => (Ok(response), false),
+                Either::Right((Ok(response), _)) => (Ok(response), true),
+                Either::Left((Err(_), hedge)) => (hedge.await, true),
+                Either::Right((Err(_), primary)) => (primary.await, false),
+            };
+            config.latencies.lock().unwrap().record(start.elapsed());
+            u64_counter!(
+                "apollo.router.shaping.hedged_requests",
+                "Number of subgraph requests that were sent a second time because they were slow",
+                1,
+                subgraph.name = config.subgraph_name.to_string(),
+                winner = if hedge_won { "hedge" } else { "primary" }
+            );
+            response
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use tower::service_fn;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+
+    fn request(idempotency: Idempotency) -> subgraph::Request {
+        let mut request = subgraph::Request::fake_builder().build();
+        request
+            .subgraph_request
+            .extensions_mut()
+            .insert(idempotency);
+        request
+    }
+
+    #[test]
+    fn delay_is_a_percentile_of_the_latencies() {
+        let layer = HedgingLayer::new("products", 90.0, Duration::from_millis(5));
+        let mut latencies = layer.latencies.lock().unwrap();
+        for i in 1..MIN_LATENCY_SAMPLES {
+            latencies.record(Duration::from_millis(i as u64));
+        }
+        assert_eq!(latencies.delay(90.0, Duration::from_millis(5)), None);
+
+        latencies.computed_at = None;
+        latencies.record(Duration::from_millis(20));
+        assert_eq!(
+            latencies.delay(90.0, Duration::from_millis(5)),
+            Some(Duration::from_millis(18))
+        );
+        latencies.computed_at = None;
+        assert_eq!(
+            latencies.delay(90.0, Duration::from_millis(50)),
+            Some(Duration::from_millis(50))
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_idempotent_requests_are_hedged() {
+        async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let service_calls = calls.clone();
+            let service = service_fn(move |request: subgraph::Request| {
+                let call = service_calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    // The first call is slow, so the hedged request wins
+                    if call == 0 {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Ok::<_, BoxError>(
+                        subgraph::Response::fake_builder()
+                            .context(request.context)
+                            .build(),
+                    )
+                }
+            });
+            let layer = HedgingLayer::new("products", 50.0, Duration::from_millis(1));
+            {
+                let mut latencies = layer.latencies.lock().unwrap();
+                for _ in 0..MIN_LATENCY_SAMPLES {
+                    latencies.record(Duration::from_millis(1));
+                }
+            }
+            let hedging = layer.layer(service);
+
+            let start = Instant::now();
+            hedging
+                .clone()
+                .oneshot(request(Idempotency::Idempotent))
+                .await
+                .unwrap();
+            assert!(start.elapsed() < Duration::from_secs(5));
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+            assert_counter!(
+                "apollo.router.shaping.hedged_requests",
+                1,
+                "subgraph.name" = "products",
+                "winner" = "hedge"
+            );
+
+            // Non idempotent requests are never hedged
+            calls.store(1, Ordering::SeqCst);
+            hedging
+                .oneshot(request(Idempotency::NonIdempotent))
+                .await
+                .unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        }
+        .with_metrics()
+        .await;
+    }
+}
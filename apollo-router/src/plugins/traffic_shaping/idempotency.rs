@@ -0,0 +1,146 @@
+//! Classification of the subgraph requests that can safely be sent more than once
+//!
+//! Queries are idempotent, subscriptions never are, and mutations are only idempotent if all
+//! their root fields were declared as such in the configuration of the subgraph.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use apollo_compiler::ast;
+
+use crate::query_planner::fetch::OperationKind;
+use crate::services::subgraph;
+
+/// Whether a subgraph request can be retried or hedged.
+///
+/// It is stored in the extensions of the HTTP request sent to the subgraph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Idempotency {
+    Idempotent,
+    NonIdempotent,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IdempotencyClassifier {
+    /// Root fields of the mutations known to be idempotent
+    idempotent_mutations: Arc<HashSet<String>>,
+}
+
+impl IdempotencyClassifier {
+    pub(crate) fn new(idempotent_mutations: &[String]) -> Self {
+        Self {
+            idempotent_mutations: Arc::new(idempotent_mutations.iter().cloned().collect()),
+        }
+    }
+
+    pub(crate) fn classify(&self, request: &subgraph::Request) -> Idempotency {
+        match request.operation_kind {
+            OperationKind::Query => Idempotency::Idempotent,
+            OperationKind::Subscription => Idempotency::NonIdempotent,
+            OperationKind::Mutation => {
+                if !self.idempotent_mutations.is_empty() && self.is_idempotent_mutation(request) {
+                    Idempotency::Idempotent
+                } else {
+                    Idempotency::NonIdempotent
+                }
+            }
+        }
+    }
+
+    /// Returns true if every root field of the mutation is in the list of idempotent mutations
+    fn is_idempotent_mutation(&self, request: &subgraph::Request) -> bool {
+        let body = request.subgraph_request.body();
+        let Some(query) = body.query.as_deref() else {
+            return false;
+        };
+        let Ok(document) = ast::Document::parse(query, "subgraph_request.graphql") else {
+            return false;
+        };
+        let operation_name = body.operation_name.as_deref();
+        let mut operations = document.definitions.iter().filter_map(|definition| {
+            definition
+                .as_operation_definition()
+                .filter(|operation| operation.operation_type == ast::OperationType::Mutation)
+        });
+        let operation = match operation_name {
+            Some(name) => operations.find(|operation| {
+                operation
+                    .name
+                    .as_ref()
+                    .is_some_and(|operation| operation.as_str() == name)
+            }),
+            None => operations.next(),
+        };
+        let Some(operation) = operation else {
+            return false;
+        };
+
+        // Fragments at the root of the operation are not expected in subgraph fetches, they
+        // are conservatively considered non-idempotent
+        operation
+            .selection_set
+            .iter()
+            .all(|selection| match selection {
+                ast::Selection::Field(field) => {
+                    field.name.as_str() == "__typename"
+                        || self.idempotent_mutations.contains(field.name.as_str())
+                }
+                _ => false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql;
+
+    fn request(operation_kind: OperationKind, query: &str) -> subgraph::Request {
+        subgraph::Request::fake_builder()
+            .operation_kind(operation_kind)
+            .subgraph_request(
+                http::Request::builder()
+                    .body(graphql::Request::fake_builder().query(query).build())
+                    .unwrap(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn classifies_operations() {
+        let classifier = IdempotencyClassifier::new(&["setName".to_string()]);
+
+        assert_eq!(
+            classifier.classify(&request(OperationKind::Query, "{ me { id } }")),
+            Idempotency::Idempotent
+        );
+        assert_eq!(
+            classifier.classify(&request(
+                OperationKind::Subscription,
+                "subscription { updated }"
+            )),
+            Idempotency::NonIdempotent
+        );
+        assert_eq!(
+            classifier.classify(&request(
+                OperationKind::Mutation,
+                "mutation { setName(name: \"a\") { id } __typename }"
+            )),
+            Idempotency::Idempotent
+        );
+        assert_eq!(
+            classifier.classify(&request(
+                OperationKind::Mutation,
+                "mutation { setName(name: \"a\") { id } createUser { id } }"
+            )),
+            Idempotency::NonIdempotent
+        );
+        assert_eq!(
+            IdempotencyClassifier::default().classify(&request(
+                OperationKind::Mutation,
+                "mutation { setName(name: \"a\") { id } }"
+            )),
+            Idempotency::NonIdempotent
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Load shedding
+//!
+//! Rejects requests as soon as they arrive when the router is overloaded, instead of letting
+//! them wait in the buffers of the pipeline until they time out.
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::graphql;
+
+/// Number of recent request latencies used to compute the 99th percentile
+const MAX_LATENCY_SAMPLES: usize = 1000;
+/// The 99th percentile latency is computed again at most once per interval
+const LATENCY_RECOMPUTE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The load shedding error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LoadShed {
+    /// Too many requests are being processed
+    Concurrency,
+    /// The recent requests are too slow
+    Latency,
+}
+
+impl LoadShed {
+    pub(crate) fn reason(&self) -> &'static str {
+        match self {
+            LoadShed::Concurrency => "concurrency",
+            LoadShed::Latency => "latency",
+        }
+    }
+}
+
+impl fmt::Display for LoadShed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("the router is overloaded")
+    }
+}
+
+impl From<LoadShed> for graphql::Error {
+    fn from(_: LoadShed) -> Self {
+        graphql::Error::builder()
+            .message(String::from(
+                "The router is overloaded, your request has been rejected",
+            ))
+            .extension_code("REQUEST_LOAD_SHED")
+            .build()
+    }
+}
+
+impl error::Error for LoadShed {}
+
+/// Tracks the requests in flight and their latency, to reject new requests over the limits
+#[derive(Debug, Clone)]
+pub(crate) struct LoadShedder {
+    max_concurrent_requests: Option<NonZeroUsize>,
+    max_p99_latency: Option<Duration>,
+    latency_window: Duration,
+    state: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    in_flight: AtomicUsize,
+    latencies: Mutex<Latencies>,
+}
+
+#[derive(Debug)]
+struct Latencies {
+    samples: VecDeque<(Instant, Duration)>,
+    p99: Duration,
+    computed_at: Option<Instant>,
+}
+
+impl Latencies {
+    fn record(&mut self, now: Instant, latency: Duration) {
+        if self.samples.len() >= MAX_LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, latency));
+    }
+
+    fn p99(&mut self, now: Instant, window: Duration) -> Duration {
+        if self
+            .computed_at
+            .is_some_and(|computed_at| now.duration_since(computed_at) < LATENCY_RECOMPUTE_INTERVAL)
+        {
+            return self.p99;
+        }
+
+        // Old samples are forgotten, so that the router accepts requests again once the
+        // slow ones are not recent anymore
+        while self
+            .samples
+            .front()
+            .is_some_and(|(recorded_at, _)| now.duration_since(*recorded_at) > window)
+        {
+            self.samples.pop_front();
+        }
+        let mut latencies: Vec<Duration> =
+            self.samples.iter().map(|(_, latency)| *latency).collect();
+        latencies.sort_unstable();
+        self.p99 = latencies
+            .get((latencies.len() * 99).div_ceil(100).saturating_sub(1))
+            .copied()
+            .unwrap_or_default();
+        self.computed_at = Some(now);
+        self.p99
+    }
+}
+
+impl LoadShedder {
+    pub(crate) fn new(
+        max_concurrent_requests: Option<NonZeroUsize>,
+        max_p99_latency: Option<Duration>,
+        latency_window: Duration,
+    ) -> Self {
+        Self {
+            max_concurrent_requests,
+            max_p99_latency,
+            latency_window,
+            state: Arc::new(State {
+                in_flight: AtomicUsize::new(0),
+                latencies: Mutex::new(Latencies {
+                    samples: VecDeque::new(),
+                    p99: Duration::ZERO,
+                    computed_at: None,
+                }),
+            }),
+        }
+    }
+
+    /// Admits a request, unless the router is overloaded. The request is counted as in flight
+    /// until the permit is dropped.
+    pub(crate) fn try_acquire(&self) -> Result<LoadSheddingPermit, LoadShed> {
+        if let Some(max_p99_latency) = self.max_p99_latency {
+            let p99 = self
+                .state
+                .latencies
+                .lock()
+                .unwrap()
+                .p99(Instant::now(), self.latency_window);
+            if p99 > max_p99_latency {
+                return Err(LoadShed::Latency);
+            }
+        }
+
+        let in_flight = self.state.in_flight.fetch_add(1, Ordering::AcqRel);
+        if self
+            .max_concurrent_requests
+            .is_some_and(|max| in_flight >= max.get())
+        {
+            self.state.in_flight.fetch_sub(1, Ordering::AcqRel);
+            return Err(LoadShed::Concurrency);
+        }
+
+        Ok(LoadSheddingPermit {
+            state: self.state.clone(),
+            start: Instant::now(),
+        })
+    }
+}
+
+/// A request admitted by the [`LoadShedder`]
+#[derive(Debug)]
+pub(crate) struct LoadSheddingPermit {
+    state: Arc<State>,
+    start: Instant,
+}
+
+impl Drop for LoadSheddingPermit {
+    fn drop(&mut self) {
+        self.state.in_flight.fetch_sub(1, Ordering::AcqRel);
+        let now = Instant::now();
+        self.state
+            .latencies
+            .lock()
+            .unwrap()
+            .record(now, now.duration_since(self.start));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_limits_concurrent_requests() {
+        let shedder = LoadShedder::new(NonZeroUsize::new(2), None, Duration::from_secs(10));
+        let first = shedder.try_acquire().unwrap();
+        let _second = shedder.try_acquire().unwrap();
+        assert_eq!(shedder.try_acquire().unwrap_err(), LoadShed::Concurrency);
+
+        drop(first);
+        let _third = shedder.try_acquire().unwrap();
+        assert_eq!(shedder.try_acquire().unwrap_err(), LoadShed::Concurrency);
+    }
+
+    #[test]
+    fn it_computes_the_p99_latency_over_a_window() {
+        let start = Instant::now();
+        let mut latencies = Latencies {
+            samples: VecDeque::new(),
+            p99: Duration::ZERO,
+            computed_at: None,
+        };
+        for i in 1..=100 {
+            latencies.record(start, Duration::from_millis(i));
+        }
+        assert_eq!(
+            latencies.p99(start, Duration::from_secs(10)),
+            Duration::from_millis(99)
+        );
+
+        // Samples older than the window are forgotten
+        let later = start + Duration::from_secs(11);
+        latencies.record(later, Duration::from_millis(1));
+        assert_eq!(
+            latencies.p99(later, Duration::from_secs(10)),
+            Duration::from_millis(1)
+        );
+    }
+}
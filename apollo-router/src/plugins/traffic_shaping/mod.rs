@@ -5,30 +5,48 @@
 //! * Timeout
 //! * Compression
 //! * Rate limiting
+//! * Load shedding
+//! * Idempotency classification
+//! * Request hedging
+//! * Partial results on timeout
 //!
+pub(crate) mod deadline;
 mod deduplication;
+mod hedging;
+mod idempotency;
+mod load_shedding;
 pub(crate) mod rate;
 pub(crate) mod timeout;
 
 use std::collections::HashMap;
 use std::num::NonZeroU64;
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
 use std::sync::Mutex;
 use std::time::Duration;
 
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use http::header::CONTENT_ENCODING;
+use http::header::RETRY_AFTER;
 use http::HeaderValue;
 use http::StatusCode;
 use schemars::JsonSchema;
 use serde::Deserialize;
+use tower::filter::FilterLayer;
 use tower::util::Either;
 use tower::BoxError;
 use tower::Service;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use self::deadline::RequestDeadline;
 use self::deduplication::QueryDeduplicationLayer;
+use self::hedging::HedgingLayer;
+use self::idempotency::IdempotencyClassifier;
+use self::load_shedding::LoadShedder;
+use self::load_shedding::LoadSheddingPermit;
+use self::rate::ClientRateLimit;
 use self::rate::RateLimitLayer;
 use self::rate::RateLimited;
 use self::timeout::Elapsed;
@@ -39,13 +57,17 @@ use crate::graphql;
 use crate::layers::ServiceBuilderExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::plugins::telemetry::CLIENT_NAME;
 use crate::register_plugin;
 use crate::services::http::service::Compression;
+use crate::services::router;
 use crate::services::subgraph;
 use crate::services::supergraph;
 use crate::services::SubgraphRequest;
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// With the partial timeout policy, time given to build the response after the deadline
+const PARTIAL_RESPONSE_GRACE_PERIOD: Duration = Duration::from_secs(1);
 pub(crate) const APOLLO_TRAFFIC_SHAPING: &str = "apollo.traffic_shaping";
 
 trait Merge {
@@ -70,6 +92,11 @@ struct Shaping {
     experimental_http2: Option<Http2Config>,
     /// DNS resolution strategy for subgraphs
     dns_resolution_strategy: Option<DnsResolutionStrategy>,
+    /// Root fields of the mutations that can safely be sent more than once. Queries are always
+    /// considered idempotent, subscriptions and other mutations never are
+    idempotent_mutations: Option<Vec<String>>,
+    /// Send a second request when an idempotent subgraph request is slow
+    experimental_hedging: Option<HedgingConf>,
 }
 
 #[derive(PartialEq, Default, Debug, Clone, Deserialize, JsonSchema)]
@@ -107,6 +134,16 @@ impl Merge for Shaping {
                     .as_ref()
                     .or(fallback.dns_resolution_strategy.as_ref())
                     .cloned(),
+                idempotent_mutations: self
+                    .idempotent_mutations
+                    .as_ref()
+                    .or(fallback.idempotent_mutations.as_ref())
+                    .cloned(),
+                experimental_hedging: self
+                    .experimental_hedging
+                    .as_ref()
+                    .or(fallback.experimental_hedging.as_ref())
+                    .cloned(),
             },
         }
     }
@@ -136,10 +173,28 @@ impl Merge for SubgraphShaping {
 struct RouterShaping {
     /// Enable global rate limiting
     global_rate_limit: Option<RateLimitConf>,
+    /// Enable rate limiting per client. Clients are identified by the header configured in
+    /// `telemetry.apollo.client_name_header`, requests without it share a single limit
+    client_rate_limit: Option<RateLimitConf>,
     #[serde(deserialize_with = "humantime_serde::deserialize", default)]
     #[schemars(with = "String", default)]
     /// Enable timeout for incoming requests
     timeout: Option<Duration>,
+    /// What to do with requests that reach the timeout (default: fail)
+    timeout_policy: Option<TimeoutPolicy>,
+    /// Reject incoming requests right away when the router is overloaded
+    load_shedding: Option<LoadSheddingConf>,
+}
+
+#[derive(PartialEq, Default, Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum TimeoutPolicy {
+    #[default]
+    /// Fail the whole request
+    Fail,
+    /// Return the data gathered before the timeout, with timeout errors for the subgraph requests
+    /// that did not complete
+    Partial,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
@@ -169,6 +224,62 @@ struct RateLimitConf {
     interval: Duration,
 }
 
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LoadSheddingConf {
+    /// Maximum number of requests processed at the same time
+    max_concurrent_requests: Option<NonZeroUsize>,
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    /// Maximum 99th percentile latency of the recent requests
+    max_p99_latency: Option<Duration>,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_latency_window"
+    )]
+    #[schemars(with = "String", default = "default_latency_window")]
+    /// Period over which the 99th percentile latency is measured (default: 10s)
+    latency_window: Duration,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_retry_after"
+    )]
+    #[schemars(with = "String", default = "default_retry_after")]
+    /// Value of the `Retry-After` header sent with rejected requests (default: 1s)
+    retry_after: Duration,
+}
+
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct HedgingConf {
+    #[serde(default = "default_hedging_percentile")]
+    /// Percentile of the recent request latencies after which a second request is sent (default: 95)
+    percentile: f64,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_hedging_min_delay"
+    )]
+    #[schemars(with = "String", default = "default_hedging_min_delay")]
+    /// Minimum delay before a second request is sent (default: 10ms)
+    min_delay: Duration,
+}
+
+fn default_hedging_percentile() -> f64 {
+    95.0
+}
+
+fn default_hedging_min_delay() -> Duration {
+    Duration::from_millis(10)
+}
+
+fn default_latency_window() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_retry_after() -> Duration {
+    Duration::from_secs(1)
+}
+
 impl Merge for RateLimitConf {
     fn merge(&self, fallback: Option<&Self>) -> Self {
         match fallback {
@@ -186,7 +297,10 @@ impl Merge for RateLimitConf {
 pub(crate) struct TrafficShaping {
     config: Config,
     rate_limit_router: Option<RateLimitLayer>,
+    rate_limit_clients: Option<ClientRateLimit>,
     rate_limit_subgraphs: Mutex<HashMap<String, RateLimitLayer>>,
+    hedging_subgraphs: Mutex<HashMap<String, HedgingLayer>>,
+    load_shedder: Option<LoadShedder>,
 }
 
 #[async_trait::async_trait]
@@ -216,15 +330,116 @@ impl Plugin for TrafficShaping {
                 }
             })
             .transpose()?;
+        let rate_limit_clients = init
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.client_rate_limit.as_ref())
+            .map(|client_rate_limit_conf| {
+                if client_rate_limit_conf.interval.as_millis() > u64::MAX as u128 {
+                    Err(ConfigurationError::InvalidConfiguration {
+                        message: "bad configuration for traffic_shaping plugin",
+                        error: format!(
+                            "cannot set an interval for the rate limit greater than {} ms",
+                            u64::MAX
+                        ),
+                    })
+                } else {
+                    Ok(ClientRateLimit::new(
+                        client_rate_limit_conf.capacity,
+                        client_rate_limit_conf.interval,
+                    ))
+                }
+            })
+            .transpose()?;
+        let load_shedder = init
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.load_shedding.as_ref())
+            .map(|conf| {
+                LoadShedder::new(
+                    conf.max_concurrent_requests,
+                    conf.max_p99_latency,
+                    conf.latency_window,
+                )
+            });
+
+        let invalid_hedging_percentile = init
+            .config
+            .all
+            .iter()
+            .chain(init.config.subgraphs.values())
+            .filter_map(|shaping| shaping.shaping.experimental_hedging.as_ref())
+            .any(|hedging| !(hedging.percentile > 0.0 && hedging.percentile <= 100.0));
+        if invalid_hedging_percentile {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad configuration for traffic_shaping plugin",
+                error: "the hedging percentile must be greater than 0 and at most 100".to_string(),
+            }
+            .into());
+        }
 
         {
             Ok(Self {
                 config: init.config,
                 rate_limit_router,
+                rate_limit_clients,
                 rate_limit_subgraphs: Mutex::new(HashMap::new()),
+                hedging_subgraphs: Mutex::new(HashMap::new()),
+                load_shedder,
             })
         }
     }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        let Some(load_shedder) = self.load_shedder.clone() else {
+            return service;
+        };
+        let retry_after = self
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.load_shedding.as_ref())
+            .map(|conf| conf.retry_after)
+            .unwrap_or_else(default_retry_after);
+
+        ServiceBuilder::new()
+            .checkpoint(
+                move |req: router::Request| match load_shedder.try_acquire() {
+                    Ok(permit) => {
+                        req.context
+                            .extensions()
+                            .with_lock(|mut lock| lock.insert(permit));
+                        Ok(ControlFlow::Continue(req))
+                    }
+                    Err(shed) => {
+                        u64_counter!(
+                            "apollo.router.shaping.load_shed",
+                            "Number of requests rejected because the router is overloaded",
+                            1,
+                            reason = shed.reason()
+                        );
+                        let res = router::Response::error_builder()
+                            .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                            .header(RETRY_AFTER, retry_after.as_secs().max(1).to_string())
+                            .error::<graphql::Error>(shed.into())
+                            .context(req.context)
+                            .build()?;
+                        Ok(ControlFlow::Break(res))
+                    }
+                },
+            )
+            .service(service)
+            .map_response(|res: router::Response| {
+                // The request is not in flight anymore once the response head is ready
+                res.context
+                    .extensions()
+                    .with_lock(|mut lock| lock.remove::<LoadSheddingPermit>());
+                res
+            })
+            .boxed()
+    }
 }
 
 pub(crate) type TrafficShapingSubgraphFuture<S> = Either<
@@ -264,6 +479,19 @@ impl TrafficShaping {
             + 'static,
         <S as Service<supergraph::Request>>::Future: std::marker::Send,
     {
+        let timeout = self
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.timeout)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let timeout_policy = self
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.timeout_policy)
+            .unwrap_or_default();
+
         ServiceBuilder::new()
             .map_future_with_request_data(
                 |req: &supergraph::Request| req.context.clone(),
@@ -291,14 +519,29 @@ impl TrafficShaping {
                     .boxed()
                 },
             )
-            .layer(TimeoutLayer::new(
-                self.config
-                    .router
-                    .as_ref()
-                    .and_then(|r| r.timeout)
-                    .unwrap_or(DEFAULT_TIMEOUT),
-            ))
+            .layer(TimeoutLayer::new(match timeout_policy {
+                TimeoutPolicy::Fail => timeout,
+                TimeoutPolicy::Partial => timeout + PARTIAL_RESPONSE_GRACE_PERIOD,
+            }))
+            .map_request(move |req: supergraph::Request| {
+                if timeout_policy == TimeoutPolicy::Partial {
+                    RequestDeadline::new(timeout).insert(&req.context);
+                }
+                req
+            })
             .option_layer(self.rate_limit_router.clone())
+            .option_layer(self.rate_limit_clients.clone().map(|rate_limit| {
+                FilterLayer::new(move |req: supergraph::Request| {
+                    let client_name: String = req
+                        .context
+                        .get(CLIENT_NAME)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    rate_limit.try_acquire(&client_name)?;
+                    Ok::<_, BoxError>(req)
+                })
+            }))
             .service(service)
     }
 
@@ -343,6 +586,27 @@ impl TrafficShaping {
                         })
                         .clone()
                 });
+            let hedging = config
+                .shaping
+                .experimental_hedging
+                .as_ref()
+                .map(|hedging_conf| {
+                    self.hedging_subgraphs
+                        .lock()
+                        .unwrap()
+                        .entry(name.to_string())
+                        .or_insert_with(|| {
+                            HedgingLayer::new(name, hedging_conf.percentile, hedging_conf.min_delay)
+                        })
+                        .clone()
+                });
+            let idempotency_classifier = IdempotencyClassifier::new(
+                config
+                    .shaping
+                    .idempotent_mutations
+                    .as_deref()
+                    .unwrap_or_default(),
+            );
 
             Either::A(ServiceBuilder::new()
 
@@ -379,9 +643,13 @@ impl TrafficShaping {
                         .timeout
                         .unwrap_or(DEFAULT_TIMEOUT),
                     ))
+                    .option_layer(hedging)
                     .option_layer(rate_limit)
                 .service(service)
                 .map_request(move |mut req: SubgraphRequest| {
+                    let idempotency = idempotency_classifier.classify(&req);
+                    req.subgraph_request.extensions_mut().insert(idempotency);
+
                     if let Some(compression) = config.shaping.compression {
                         let compression_header_val = HeaderValue::from_str(&compression.to_string()).expect("compression is manually implemented and already have the right values; qed");
                         req.subgraph_request.headers_mut().insert(CONTENT_ENCODING, compression_header_val);
@@ -441,6 +709,7 @@ mod test {
     use crate::services::SupergraphResponse;
     use crate::spec::Schema;
     use crate::Configuration;
+    use crate::Context;
 
     static EXPECTED_RESPONSE: Lazy<Bytes> = Lazy::new(|| {
         Bytes::from_static(r#"{"data":{"topProducts":[{"upc":"1","name":"Table","reviews":[{"id":"1","product":{"name":"Table"},"author":{"id":"1","name":"Ada Lovelace"}},{"id":"4","product":{"name":"Table"},"author":{"id":"2","name":"Alan Turing"}}]},{"upc":"2","name":"Couch","reviews":[{"id":"2","product":{"name":"Couch"},"author":{"id":"1","name":"Ada Lovelace"}}]}]}}"#.as_bytes())
@@ -902,4 +1171,172 @@ mod test {
             .errors
             .is_empty());
     }
+
+    #[tokio::test]
+    async fn it_returns_partial_results_on_timeout() {
+        let config = serde_yaml::from_str::<serde_json::Value>(
+            r#"
+        router:
+            timeout: 100ms
+            timeout_policy: partial
+        "#,
+        )
+        .unwrap();
+        let plugin = get_traffic_shaping_plugin(&config).await;
+
+        // Execution of a plan where one subgraph answers right away and the other one is too slow
+        let service = tower::service_fn(|req: SupergraphRequest| async move {
+            let slow_fetch = tokio::time::sleep(Duration::from_secs(10));
+            let elapsed = RequestDeadline::run(&req.context, slow_fetch)
+                .await
+                .unwrap_err();
+            SupergraphResponse::fake_builder()
+                .data(json!({ "fast": 1, "slow": null }))
+                .errors(vec![elapsed.into()])
+                .context(req.context)
+                .build()
+        });
+
+        let mut response = plugin
+            .as_any()
+            .downcast_ref::<TrafficShaping>()
+            .unwrap()
+            .supergraph_service_internal(service)
+            .oneshot(SupergraphRequest::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.response.status(), StatusCode::OK);
+        let response = response.next_response().await.unwrap();
+        assert_eq!(response.data, Some(json!({ "fast": 1, "slow": null })));
+        assert_eq!(
+            response.errors[0].extensions.get("code").unwrap(),
+            "REQUEST_TIMEOUT"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_rate_limit_router_requests_per_client() {
+        let config = serde_yaml::from_str::<serde_json::Value>(
+            r#"
+        router:
+            client_rate_limit:
+                capacity: 1
+                interval: 100ms
+            timeout: 500ms
+        "#,
+        )
+        .unwrap();
+
+        let plugin = get_traffic_shaping_plugin(&config).await;
+        let mut mock_service = MockSupergraphService::new();
+        mock_service.expect_clone().returning(|| {
+            let mut mock_service = MockSupergraphService::new();
+
+            mock_service.expect_clone().returning(|| {
+                let mut mock_service = MockSupergraphService::new();
+                mock_service.expect_call().times(0..2).returning(move |_| {
+                    Ok(SupergraphResponse::fake_builder()
+                        .data(json!({ "test": 1234_u32 }))
+                        .build()
+                        .unwrap())
+                });
+                mock_service
+            });
+            mock_service
+        });
+
+        let call = |client_name: &'static str| {
+            let service = plugin
+                .as_any()
+                .downcast_ref::<TrafficShaping>()
+                .unwrap()
+                .supergraph_service_internal(mock_service.clone());
+            async move {
+                let context = Context::new();
+                context
+                    .insert(CLIENT_NAME, client_name.to_string())
+                    .unwrap();
+                service
+                    .oneshot(
+                        SupergraphRequest::fake_builder()
+                            .context(context)
+                            .build()
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()
+                    .next_response()
+                    .await
+                    .unwrap()
+                    .errors
+            }
+        };
+
+        assert!(call("client_a").await.is_empty());
+        assert_eq!(
+            call("client_a").await[0].extensions.get("code").unwrap(),
+            "REQUEST_RATE_LIMITED"
+        );
+        // other clients have their own limit
+        assert!(call("client_b").await.is_empty());
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(call("client_a").await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_sheds_router_requests_over_the_concurrency_limit() {
+        let config = serde_yaml::from_str::<serde_json::Value>(
+            r#"
+        router:
+            load_shedding:
+                max_concurrent_requests: 1
+                retry_after: 5s
+        "#,
+        )
+        .unwrap();
+
+        let plugin = get_traffic_shaping_plugin(&config).await;
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let released = Arc::new(tokio::sync::Mutex::new(Some(released)));
+        let call = || {
+            let released = released.clone();
+            plugin
+                .router_service(
+                    tower::service_fn(move |req: router::Request| {
+                        let released = released.clone();
+                        async move {
+                            // The first request waits until it is released
+                            if let Some(released) = released.lock().await.take() {
+                                let _ = released.await;
+                            }
+                            Ok::<_, BoxError>(
+                                router::Response::fake_builder()
+                                    .context(req.context)
+                                    .build()
+                                    .unwrap(),
+                            )
+                        }
+                    })
+                    .boxed(),
+                )
+                .oneshot(router::Request::fake_builder().build().unwrap())
+        };
+
+        let first = tokio::spawn(call());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let shed = call().await.unwrap();
+        assert_eq!(shed.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.response.headers().get(RETRY_AFTER).unwrap(), "5");
+
+        release.send(()).unwrap();
+        assert_eq!(
+            first.await.unwrap().unwrap().response.status(),
+            StatusCode::OK
+        );
+
+        let accepted = call().await.unwrap();
+        assert_eq!(accepted.response.status(), StatusCode::OK);
+    }
 }
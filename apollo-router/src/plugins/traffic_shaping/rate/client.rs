@@ -0,0 +1,134 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::num::NonZeroU64;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use lru::LruCache;
+
+use super::RateLimitLayer;
+use super::RateLimited;
+
+/// Client names come from request headers, so we bound the number of limits we keep track of.
+/// Past that, the limit of the least recently seen client is forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Applies a separate rate limit to each client, identified by its client name
+#[derive(Debug, Clone)]
+pub(crate) struct ClientRateLimit<K: Hash + Eq = String> {
+    capacity: NonZeroU64,
+    interval: Duration,
+    clients: Arc<Mutex<LruCache<K, TrackedClient>>>,
+}
+
+#[derive(Debug)]
+struct TrackedClient {
+    rate_limit: RateLimitLayer,
+    last_seen: Instant,
+}
+
+impl<K: Hash + Eq> ClientRateLimit<K> {
+    pub(crate) fn new(capacity: NonZeroU64, interval: Duration) -> Self {
+        Self {
+            capacity,
+            interval,
+            clients: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_TRACKED_CLIENTS).expect("MAX_TRACKED_CLIENTS is not zero"),
+            ))),
+        }
+    }
+
+    /// Counts a request against the rate limit of this client.
+    pub(crate) fn try_acquire<Q>(&self, client: &Q) -> Result<(), RateLimited>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let now = Instant::now();
+        let rate_limit = {
+            let mut clients = self.clients.lock().unwrap();
+            // Clients are ordered from the least recently seen one. The rate limit of a client
+            // idle for two intervals is back to its initial state, so it can be forgotten
+            let idle_timeout = self.interval.saturating_mul(2);
+            while clients.peek_lru().is_some_and(|(_, tracked)| {
+                now.saturating_duration_since(tracked.last_seen) >= idle_timeout
+            }) {
+                clients.pop_lru();
+            }
+
+            match clients.get_mut(client) {
+                Some(tracked) => {
+                    tracked.last_seen = now;
+                    tracked.rate_limit.clone()
+                }
+                None => {
+                    let rate_limit = RateLimitLayer::new(self.capacity, self.interval);
+                    clients.push(
+                        client.to_owned(),
+                        TrackedClient {
+                            rate_limit: rate_limit.clone(),
+                            last_seen: now,
+                        },
+                    );
+                    rate_limit
+                }
+            }
+        };
+
+        rate_limit.try_acquire()
+    }
+
+    #[cfg(test)]
+    fn tracked_clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_limits_each_client_separately() {
+        let rate_limit: ClientRateLimit =
+            ClientRateLimit::new(NonZeroU64::new(1).unwrap(), Duration::from_secs(60));
+
+        assert!(rate_limit.try_acquire("a").is_ok());
+        assert!(rate_limit.try_acquire("a").is_err());
+        assert!(rate_limit.try_acquire("b").is_ok());
+    }
+
+    #[test]
+    fn it_forgets_idle_clients() {
+        let rate_limit: ClientRateLimit =
+            ClientRateLimit::new(NonZeroU64::new(10).unwrap(), Duration::from_millis(10));
+
+        for client in 0..100 {
+            assert!(rate_limit.try_acquire(&client.to_string()).is_ok());
+        }
+        assert_eq!(rate_limit.tracked_clients(), 100);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(rate_limit.try_acquire("a").is_ok());
+        assert_eq!(rate_limit.tracked_clients(), 1);
+    }
+
+    #[test]
+    fn it_bounds_the_number_of_tracked_clients() {
+        let rate_limit: ClientRateLimit =
+            ClientRateLimit::new(NonZeroU64::new(1).unwrap(), Duration::from_secs(60));
+
+        assert!(rate_limit.try_acquire("first").is_ok());
+        assert!(rate_limit.try_acquire("first").is_err());
+        for client in 0..MAX_TRACKED_CLIENTS {
+            assert!(rate_limit.try_acquire(&client.to_string()).is_ok());
+        }
+        assert_eq!(rate_limit.tracked_clients(), MAX_TRACKED_CLIENTS);
+        // new clients get their own limit instead of sharing one, and the least recently seen
+        // client was forgotten
+        assert!(rate_limit.try_acquire("first").is_ok());
+    }
+}
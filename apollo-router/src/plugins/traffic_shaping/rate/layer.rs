@@ -8,8 +8,10 @@ use std::time::UNIX_EPOCH;
 
 use tower::Layer;
 
+use super::service;
 use super::Rate;
 use super::RateLimit;
+use super::RateLimited;
 /// Enforces a rate limit on the number of requests the underlying
 /// service can handle over a period of time.
 #[derive(Debug, Clone)]
@@ -36,6 +38,20 @@ impl RateLimitLayer {
             current_nb_requests: Arc::new(AtomicUsize::new(1)),
         }
     }
+
+    /// Counts a request against the rate limit, without going through a service.
+    pub(crate) fn try_acquire(&self) -> Result<(), RateLimited> {
+        if service::try_acquire(
+            &self.rate,
+            &self.window_start,
+            &self.previous_nb_requests,
+            &self.current_nb_requests,
+        ) {
+            Ok(())
+        } else {
+            Err(RateLimited::new())
+        }
+    }
 }
 
 impl<S> Layer<S> for RateLimitLayer {
@@ -1,5 +1,6 @@
 //! Limit the rate at which requests are processed.
 
+mod client;
 mod error;
 pub(crate) mod future;
 mod layer;
@@ -7,6 +8,7 @@ mod layer;
 mod rate;
 pub(crate) mod service;
 
+pub(crate) use self::client::ClientRateLimit;
 pub(crate) use self::error::RateLimited;
 pub(crate) use self::layer::RateLimitLayer;
 pub(crate) use self::rate::Rate;
@@ -36,44 +36,16 @@ where
     type Future = ResponseFuture<S::Future>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let time_unit = self.rate.per().as_millis() as u64;
-
-        let updated =
-            self.window_start
-                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |window_start| {
-                    let duration_now = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .expect("system time must be after EPOCH")
-                        .as_millis() as u64;
-                    if duration_now - window_start > self.rate.per().as_millis() as u64 {
-                        Some(duration_now)
-                    } else {
-                        None
-                    }
-                });
-        // If it has been updated
-        if let Ok(_updated_window_start) = updated {
-            self.previous_nb_requests.swap(
-                self.current_nb_requests.load(Ordering::SeqCst),
-                Ordering::SeqCst,
-            );
-            self.current_nb_requests.swap(1, Ordering::SeqCst);
-        }
-
-        let estimated_cap = (self.previous_nb_requests.load(Ordering::SeqCst)
-            * (time_unit
-                .checked_sub(self.window_start.load(Ordering::SeqCst))
-                .unwrap_or_default()
-                / time_unit) as usize)
-            + self.current_nb_requests.load(Ordering::SeqCst);
-
-        if estimated_cap as u64 > self.rate.num() {
+        if !try_acquire(
+            &self.rate,
+            &self.window_start,
+            &self.previous_nb_requests,
+            &self.current_nb_requests,
+        ) {
             tracing::trace!("rate limit exceeded; sleeping.");
             return Poll::Ready(Err(RateLimited::new().into()));
         }
 
-        self.current_nb_requests.fetch_add(1, Ordering::SeqCst);
-
         Poll::Ready(ready!(self.inner.poll_ready(cx)).map_err(Into::into))
     }
 
@@ -81,3 +53,44 @@ where
         ResponseFuture::new(self.inner.call(request))
     }
 }
+
+/// Counts a request against the sliding window, returns false if the rate limit is exceeded
+pub(super) fn try_acquire(
+    rate: &Rate,
+    window_start: &AtomicU64,
+    previous_nb_requests: &AtomicUsize,
+    current_nb_requests: &AtomicUsize,
+) -> bool {
+    let time_unit = rate.per().as_millis() as u64;
+
+    let updated = window_start.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |window_start| {
+        let duration_now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time must be after EPOCH")
+            .as_millis() as u64;
+        if duration_now - window_start > rate.per().as_millis() as u64 {
+            Some(duration_now)
+        } else {
+            None
+        }
+    });
+    // If it has been updated
+    if let Ok(_updated_window_start) = updated {
+        previous_nb_requests.swap(current_nb_requests.load(Ordering::SeqCst), Ordering::SeqCst);
+        current_nb_requests.swap(1, Ordering::SeqCst);
+    }
+
+    let estimated_cap = (previous_nb_requests.load(Ordering::SeqCst)
+        * (time_unit
+            .checked_sub(window_start.load(Ordering::SeqCst))
+            .unwrap_or_default()
+            / time_unit) as usize)
+        + current_nb_requests.load(Ordering::SeqCst);
+
+    if estimated_cap as u64 > rate.num() {
+        return false;
+    }
+
+    current_nb_requests.fetch_add(1, Ordering::SeqCst);
+    true
+}
@@ -0,0 +1,198 @@
+//! Server-Sent Events transport for subscriptions
+//!
+//! Follows the "distinct connections mode" of the GraphQL over SSE protocol: each subscription
+//! uses its own connection, each event is sent as a `next` event, and the end of the
+//! subscription is signaled with a `complete` event.
+//!
+//! Subscriptions cannot be resumed: events are not kept once sent, so they have no id, and a
+//! client that reconnects executes a new subscription.
+
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::stream::select;
+use futures::stream::StreamExt;
+use futures::Stream;
+use tokio_stream::once;
+use tokio_stream::wrappers::IntervalStream;
+
+use crate::graphql;
+
+#[cfg(test)]
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(10);
+#[cfg(not(test))]
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Delay before the client reconnects, in milliseconds, sent with the first event
+const RETRY_DELAY_MS: u64 = 1000;
+
+pub(crate) const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error("serialization error")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Debug)]
+enum MessageKind {
+    Heartbeat,
+    Message(graphql::Response),
+    Eof,
+}
+
+pub(crate) struct EventStream {
+    stream: Pin<Box<dyn Stream<Item = MessageKind> + Send>>,
+    is_first_chunk: bool,
+    is_terminated: bool,
+}
+
+impl EventStream {
+    pub(crate) fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = graphql::Response> + Send + 'static,
+    {
+        let stream = select(
+            stream
+                .map(MessageKind::Message)
+                .chain(once(MessageKind::Eof)),
+            IntervalStream::new(tokio::time::interval_at(
+                tokio::time::Instant::now() + HEARTBEAT_INTERVAL,
+                HEARTBEAT_INTERVAL,
+            ))
+            .map(|_| MessageKind::Heartbeat),
+        )
+        .boxed();
+
+        Self {
+            stream,
+            is_first_chunk: true,
+            is_terminated: false,
+        }
+    }
+
+    fn start_chunk(&mut self) -> Vec<u8> {
+        if self.is_first_chunk {
+            self.is_first_chunk = false;
+            format!("retry: {RETRY_DELAY_MS}\n").into_bytes()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.is_terminated {
+            return Poll::Ready(None);
+        }
+        match self.stream.as_mut().poll_next(cx) {
+            Poll::Ready(message) => match message {
+                // Comments are ignored by clients but keep the connection alive through proxies
+                Some(MessageKind::Heartbeat) => Poll::Ready(Some(Ok(Bytes::from_static(b":\n\n")))),
+                Some(MessageKind::Message(response)) => {
+                    let is_still_open =
+                        response.has_next.unwrap_or(false) || response.subscribed.unwrap_or(false);
+                    let mut buf = self.start_chunk();
+
+                    // Gracefully closed at the server side
+                    let is_empty = response.data.as_ref().map_or(true, |data| data.is_null())
+                        && response.errors.is_empty()
+                        && response.extensions.is_empty();
+                    if is_still_open || !is_empty {
+                        buf.extend_from_slice(b"event: next\ndata: ");
+                        serde_json::to_writer(&mut buf, &response)?;
+                        buf.extend_from_slice(b"\n\n");
+                    }
+                    if !is_still_open {
+                        self.is_terminated = true;
+                        buf.extend_from_slice(b"event: complete\ndata:\n\n");
+                    }
+
+                    Poll::Ready(Some(Ok(buf.into())))
+                }
+                Some(MessageKind::Eof) => {
+                    // If the stream ends or is empty
+                    let mut buf = self.start_chunk();
+                    buf.extend_from_slice(b"event: complete\ndata:\n\n");
+                    self.is_terminated = true;
+
+                    Poll::Ready(Some(Ok(buf.into())))
+                }
+                None => {
+                    self.is_terminated = true;
+                    Poll::Ready(None)
+                }
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events_and_completion() {
+        let responses = vec![
+            graphql::Response::builder()
+                .data(json!({"userWasCreated": {"name": "foo"}}))
+                .subscribed(true)
+                .build(),
+            graphql::Response::builder()
+                .data(json!({"userWasCreated": {"name": "bar"}}))
+                .subscribed(true)
+                .build(),
+        ];
+        let events = EventStream::new(stream::iter(responses))
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            events,
+            vec![
+                "retry: 1000\nevent: next\ndata: {\"data\":{\"userWasCreated\":{\"name\":\"foo\"}}}\n\n",
+                "event: next\ndata: {\"data\":{\"userWasCreated\":{\"name\":\"bar\"}}}\n\n",
+                "event: complete\ndata:\n\n",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_and_error() {
+        let responses = stream::once(async {
+            tokio::time::sleep(HEARTBEAT_INTERVAL * 3).await;
+            graphql::Response::builder()
+                .error(
+                    graphql::Error::builder()
+                        .message("subgraph connection lost")
+                        .extension_code("WEBSOCKET_CONNECTION_ERROR")
+                        .build(),
+                )
+                .subscribed(false)
+                .build()
+        });
+        let events = EventStream::new(responses)
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect::<Vec<_>>()
+            .await;
+
+        assert!(events.len() > 1);
+        assert_eq!(events[0], ":\n\n");
+        let last = events.last().unwrap();
+        assert!(last.starts_with("retry: 1000\nevent: next\ndata: {\"errors\":"));
+        assert!(last.ends_with("\n\nevent: complete\ndata:\n\n"));
+    }
+}
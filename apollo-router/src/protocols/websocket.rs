@@ -54,7 +54,7 @@ impl From<WebSocketProtocol> for HeaderValue {
 }
 
 impl WebSocketProtocol {
-    fn subscribe(&self, id: String, payload: graphql::Request) -> ClientMessage {
+    pub(crate) fn subscribe(&self, id: String, payload: graphql::Request) -> ClientMessage {
         match self {
             // old
             WebSocketProtocol::SubscriptionsTransportWs => ClientMessage::OldStart { id, payload },
@@ -63,7 +63,7 @@ impl WebSocketProtocol {
         }
     }
 
-    fn complete(&self, id: String) -> ClientMessage {
+    pub(crate) fn complete(&self, id: String) -> ClientMessage {
         match self {
             // old
             WebSocketProtocol::SubscriptionsTransportWs => ClientMessage::OldStop { id },
@@ -183,7 +183,7 @@ impl From<ServerError> for Vec<graphql::Error> {
 }
 
 impl ServerMessage {
-    fn into_graphql_response(self) -> (Option<graphql::Response>, bool) {
+    pub(crate) fn into_graphql_response(self) -> (Option<graphql::Response>, bool) {
         match self {
             ServerMessage::Next { id: _, mut payload } => {
                 payload.subscribed = Some(true);
@@ -295,6 +295,11 @@ where
         })
     }
 
+    /// Returns the underlying stream, once the connection has been acknowledged
+    pub(crate) fn into_inner(self) -> S {
+        self.stream
+    }
+
     pub(crate) async fn into_subscription(
         mut self,
         request: graphql::Request,
@@ -3,6 +3,8 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::ControlFlow;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -71,6 +73,7 @@ pub(crate) struct BridgeQueryPlanner {
     enable_authorization_directives: bool,
     _federation_instrument: ObservableGauge<u64>,
     signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
+    redacted_arguments: Vec<String>,
     introspection: Arc<IntrospectionCache>,
 }
 
@@ -80,6 +83,15 @@ pub(crate) enum PlannerMode {
     Rust(Arc<QueryPlanner>),
 }
 
+/// Cancels query planning on the compute pool when the future waiting for it is dropped
+struct CancelPlanningOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelPlanningOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
 fn federation_version_instrument(federation_version: Option<i64>) -> ObservableGauge<u64> {
     meter_provider()
         .meter("apollo/router")
@@ -129,6 +141,7 @@ impl PlannerMode {
         doc: &ParsedDocument,
         operation: Option<String>,
         plan_options: PlanOptions,
+        priority: compute_job::Priority,
         // Initialization code that needs mutable access to the plan,
         // before we potentially share it in Arc with a background thread
         // for "both" mode.
@@ -138,12 +151,15 @@ impl PlannerMode {
             PlannerMode::Rust(rust_planner) => {
                 let doc = doc.clone();
                 let rust_planner = rust_planner.clone();
-                let priority = compute_job::Priority::P8; // High priority
+                let cancellation_token = Arc::new(AtomicBool::new(false));
+                let _cancel_on_drop = CancelPlanningOnDrop(cancellation_token.clone());
                 let (plan, mut root_node) = compute_job::execute(priority, move || {
                     let start = Instant::now();
 
                     let query_plan_options = QueryPlanOptions {
                         override_conditions: plan_options.override_conditions,
+                        disabled_subgraphs: plan_options.disabled_subgraphs,
+                        cancellation_token: Some(cancellation_token),
                     };
 
                     let result = operation
@@ -214,15 +230,41 @@ impl BridgeQueryPlanner {
         configuration: Arc<Configuration>,
         introspection_cache: Arc<IntrospectionCache>,
     ) -> Result<Self, ServiceBuildError> {
-        let planner = PlannerMode::Rust(PlannerMode::rust(&schema, &configuration)?);
+        Self::new_sharing(schema, configuration, introspection_cache, None).await
+    }
 
-        let subgraph_schemas = Arc::new(planner.subgraphs().await?);
+    /// Creates a planner, sharing the query planner and the subgraph schemas of the previous one
+    /// if they were built from the same supergraph SDL with the same options
+    pub(crate) async fn new_sharing(
+        schema: Arc<Schema>,
+        configuration: Arc<Configuration>,
+        introspection_cache: Arc<IntrospectionCache>,
+        previous: Option<&Self>,
+    ) -> Result<Self, ServiceBuildError> {
+        let (planner, subgraph_schemas) = match previous {
+            Some(previous)
+                // The planner only depends on the supergraph SDL, which a new launch of the same
+                // schema does not change
+                if previous.schema.schema_id == schema.schema_id
+                    && previous.configuration.rust_query_planner_config()
+                        == configuration.rust_query_planner_config() =>
+            {
+                tracing::debug!("reusing the query planner of the previous schema");
+                (previous.planner.clone(), previous.subgraph_schemas.clone())
+            }
+            _ => {
+                let planner = PlannerMode::Rust(PlannerMode::rust(&schema, &configuration)?);
+                let subgraph_schemas = Arc::new(planner.subgraphs().await?);
+                (planner, subgraph_schemas)
+            }
+        };
 
         let enable_authorization_directives =
             AuthorizationPlugin::enable_directives(&configuration, &schema)?;
         let federation_instrument = federation_version_instrument(schema.federation_version());
         let signature_normalization_algorithm =
             TelemetryConfig::signature_normalization_algorithm(&configuration);
+        let redacted_arguments = TelemetryConfig::redacted_arguments(&configuration);
 
         Ok(Self {
             planner,
@@ -232,6 +274,7 @@ impl BridgeQueryPlanner {
             configuration,
             _federation_instrument: federation_instrument,
             signature_normalization_algorithm,
+            redacted_arguments,
             introspection: introspection_cache,
         })
     }
@@ -297,19 +340,27 @@ impl BridgeQueryPlanner {
         key: CacheKeyMetadata,
         selections: Query,
         plan_options: PlanOptions,
+        original_doc: &ParsedDocument,
         doc: &ParsedDocument,
         query_metrics: OperationLimits<u32>,
+        priority: compute_job::Priority,
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
         let plan_result = self
             .planner
-            .plan(doc, operation.clone(), plan_options, |root_node| {
-                root_node.init_parsed_operations_and_hash_subqueries(
-                    &self.subgraph_schemas,
-                    &self.schema.raw_sdl,
-                )?;
-                root_node.extract_authorization_metadata(self.schema.supergraph_schema(), &key);
-                Ok(())
-            })
+            .plan(
+                doc,
+                operation.clone(),
+                plan_options,
+                priority,
+                |root_node| {
+                    root_node.init_parsed_operations_and_hash_subqueries(
+                        &self.subgraph_schemas,
+                        &self.schema.raw_sdl,
+                    )?;
+                    root_node.extract_authorization_metadata(self.schema.supergraph_schema(), &key);
+                    Ok(())
+                },
+            )
             .await?;
         let QueryPlanResult {
             query_plan: QueryPlan { node },
@@ -318,25 +369,15 @@ impl BridgeQueryPlanner {
         } = plan_result;
 
         // If the query is filtered, we want to generate the signature using the original query and generate the
-        // reference using the filtered query. To do this, we need to re-parse the original query here.
-        let signature_doc = if original_query != filtered_query {
-            Query::parse_document(
-                &original_query,
-                operation.clone().as_deref(),
-                &self.schema,
-                &self.configuration,
-            )
-            .unwrap_or(doc.clone())
-        } else {
-            doc.clone()
-        };
-
+        // reference using the filtered query. The original document comes from the query analysis cache,
+        // so it does not need to be parsed again.
         let usage_reporting = generate_usage_reporting(
-            &signature_doc.executable,
+            &original_doc.executable,
             &doc.executable,
             &operation,
             self.schema.supergraph_schema(),
             &self.signature_normalization_algorithm,
+            &self.redacted_arguments,
         );
 
         if let Some(node) = node {
@@ -384,11 +425,13 @@ impl Service<QueryPlannerRequest> for BridgeQueryPlanner {
             document,
             metadata,
             plan_options,
+            warm_up,
         } = req;
+        let priority = planning_priority(warm_up);
 
         let this = self.clone();
         let fut = async move {
-            let mut doc = document;
+            let mut doc = document.clone();
 
             let api_schema = this.schema.api_schema();
             match add_defer_labels(api_schema, &doc.ast) {
@@ -427,7 +470,9 @@ impl Service<QueryPlannerRequest> for BridgeQueryPlanner {
                         metadata,
                         plan_options,
                     },
+                    &document,
                     doc,
+                    priority,
                 )
                 .await;
 
@@ -451,7 +496,9 @@ impl BridgeQueryPlanner {
     async fn get(
         &self,
         mut key: QueryKey,
+        original_doc: &ParsedDocument,
         mut doc: ParsedDocument,
+        priority: compute_job::Priority,
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
         let mut query_metrics = Default::default();
         let mut selections = self
@@ -557,8 +604,10 @@ impl BridgeQueryPlanner {
             key.metadata,
             selections,
             key.plan_options,
+            original_doc,
             &doc,
             query_metrics,
+            priority,
         )
         .await
     }
@@ -582,6 +631,15 @@ pub(super) struct QueryPlan {
     pub(super) node: Option<Arc<PlanNode>>,
 }
 
+/// Client requests waiting for a plan go before warming up the cache
+fn planning_priority(warm_up: bool) -> compute_job::Priority {
+    if warm_up {
+        compute_job::Priority::P2 // Low priority
+    } else {
+        compute_job::Priority::P8 // High priority
+    }
+}
+
 pub(crate) fn metric_query_planning_plan_duration(planner: &'static str, elapsed: f64) {
     f64_histogram!(
         "apollo.router.query_planning.plan.duration",
@@ -612,11 +670,14 @@ pub(crate) fn metric_rust_qp_init(init_error_kind: Option<&'static str>) {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use serde_json::json;
     use test_log::test;
     use tower::ServiceExt;
 
     use super::*;
+    use crate::error::IntoGraphQLErrors;
     use crate::metrics::FutureMetricsExt as _;
     use crate::services::subgraph;
     use crate::services::supergraph;
@@ -686,6 +747,75 @@ mod tests {
         .await;
     }
 
+    #[test(tokio::test)]
+    async fn it_shares_the_planner_of_unchanged_schemas() {
+        let config: Arc<Configuration> = Arc::default();
+        let schema = Arc::new(Schema::parse(EXAMPLE_SCHEMA, &config).unwrap());
+        let introspection = Arc::new(IntrospectionCache::new(&config));
+        let previous =
+            BridgeQueryPlanner::new(schema.clone(), config.clone(), introspection.clone())
+                .await
+                .unwrap();
+        let shares_planner =
+            |planner: &BridgeQueryPlanner| match (&planner.planner, &previous.planner) {
+                (PlannerMode::Rust(planner), PlannerMode::Rust(previous)) => {
+                    Arc::ptr_eq(planner, previous)
+                }
+            };
+
+        let planner = BridgeQueryPlanner::new_sharing(
+            schema.clone(),
+            config.clone(),
+            introspection.clone(),
+            Some(&previous),
+        )
+        .await
+        .unwrap();
+        assert!(shares_planner(&planner));
+        assert!(Arc::ptr_eq(
+            &planner.subgraph_schemas,
+            &previous.subgraph_schemas
+        ));
+
+        // the query planner depends on the query planning options
+        let other_config = Arc::new(
+            Configuration::fake_builder()
+                .experimental_type_conditioned_fetching(true)
+                .build()
+                .unwrap(),
+        );
+        let planner = BridgeQueryPlanner::new_sharing(
+            schema.clone(),
+            other_config,
+            introspection.clone(),
+            Some(&previous),
+        )
+        .await
+        .unwrap();
+        assert!(!shares_planner(&planner));
+
+        // a schema parsed again from the same SDL, as for a new launch, shares the planner
+        let reparsed = Arc::new(Schema::parse(EXAMPLE_SCHEMA, &config).unwrap());
+        let planner = BridgeQueryPlanner::new_sharing(
+            reparsed,
+            config.clone(),
+            introspection.clone(),
+            Some(&previous),
+        )
+        .await
+        .unwrap();
+        assert!(shares_planner(&planner));
+
+        // but not a different schema
+        let changed =
+            Arc::new(Schema::parse(&format!("{EXAMPLE_SCHEMA}\n# changed\n"), &config).unwrap());
+        let planner =
+            BridgeQueryPlanner::new_sharing(changed, config, introspection, Some(&previous))
+                .await
+                .unwrap();
+        assert!(!shares_planner(&planner));
+    }
+
     #[test(tokio::test)]
     async fn empty_query_plan_should_be_a_planner_error() {
         let config = Default::default();
@@ -720,7 +850,9 @@ mod tests {
                 selections,
                 PlanOptions::default(),
                 &doc,
-                query_metrics
+                &doc,
+                query_metrics,
+                compute_job::Priority::P8,
             )
                 .await
                 .unwrap_err();
@@ -748,6 +880,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_planning_priority() {
+        assert!(matches!(
+            planning_priority(false),
+            compute_job::Priority::P8
+        ));
+        assert!(matches!(planning_priority(true), compute_job::Priority::P2));
+    }
+
+    #[test(tokio::test)]
+    async fn test_max_fetch_nodes() {
+        let query = "{ me { id } book(isbn: \"1\") { isbn } }";
+        let mut configuration: Configuration = Default::default();
+        configuration
+            .supergraph
+            .query_planning
+            .experimental_max_fetch_nodes = Some(2);
+        assert!(plan_with_configuration(
+            configuration.clone(),
+            EXAMPLE_SCHEMA,
+            query,
+            query,
+            None,
+            PlanOptions::default(),
+        )
+        .await
+        .is_ok());
+
+        configuration
+            .supergraph
+            .query_planning
+            .experimental_max_fetch_nodes = Some(1);
+        let err = plan_with_configuration(
+            configuration,
+            EXAMPLE_SCHEMA,
+            query,
+            query,
+            None,
+            PlanOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            QueryPlannerError::FederationError(FederationErrorBridge::QueryPlanTooLarge(_))
+        ));
+        let errors = err.into_graphql_errors().unwrap();
+        assert_eq!(
+            errors[0].extensions.get("code").unwrap(),
+            "QUERY_PLAN_TOO_LARGE"
+        );
+    }
+
+    #[test(tokio::test)]
+    async fn test_planning_timeout() {
+        let query = include_str!("testdata/query.graphql");
+        let mut configuration: Configuration = Default::default();
+        configuration
+            .supergraph
+            .query_planning
+            .experimental_planning_timeout = Some(Duration::ZERO);
+        let err = plan_with_configuration(
+            configuration,
+            EXAMPLE_SCHEMA,
+            query,
+            query,
+            None,
+            PlanOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            QueryPlannerError::FederationError(FederationErrorBridge::QueryPlanningTimeout(_))
+        ));
+        let errors = err.into_graphql_errors().unwrap();
+        assert_eq!(
+            errors[0].extensions.get("code").unwrap(),
+            "QUERY_PLANNING_TIMEOUT"
+        );
+    }
+
     #[test(tokio::test)]
     async fn test_single_aliased_root_typename() {
         let result = plan(
@@ -790,6 +1004,63 @@ mod tests {
         }
     }
 
+    #[test(tokio::test)]
+    async fn test_introspection_cache_is_keyed_by_operation() {
+        let mut configuration: Configuration = Default::default();
+        configuration.supergraph.introspection = true;
+        let configuration = Arc::new(configuration);
+
+        let schema = Schema::parse(EXAMPLE_SCHEMA, &configuration).unwrap();
+        let planner = BridgeQueryPlanner::new(
+            schema.into(),
+            configuration.clone(),
+            Arc::new(IntrospectionCache::new(&configuration)),
+        )
+        .await
+        .unwrap();
+
+        let query = "query A { __schema { queryType { name } } } \
+                     query B { __type(name: \"User\") { name } }";
+        for (operation_name, expected) in [
+            (
+                "A",
+                r#"{"data":{"__schema":{"queryType":{"name":"Query"}}}}"#,
+            ),
+            ("B", r#"{"data":{"__type":{"name":"User"}}}"#),
+            (
+                "A",
+                r#"{"data":{"__schema":{"queryType":{"name":"Query"}}}}"#,
+            ),
+        ] {
+            let doc = Query::parse_document(
+                query,
+                Some(operation_name),
+                &planner.schema(),
+                &configuration,
+            )
+            .unwrap();
+            let result = planner
+                .get(
+                    QueryKey {
+                        original_query: query.to_string(),
+                        filtered_query: query.to_string(),
+                        operation_name: Some(operation_name.to_string()),
+                        metadata: CacheKeyMetadata::default(),
+                        plan_options: PlanOptions::default(),
+                    },
+                    &doc,
+                    doc.clone(),
+                    compute_job::Priority::P8,
+                )
+                .await
+                .unwrap();
+            let QueryPlannerContent::CachedIntrospectionResponse { response } = result else {
+                panic!("expected an introspection response");
+            };
+            assert_eq!(expected, serde_json::to_string(&response).unwrap());
+        }
+    }
+
     #[test(tokio::test)]
     async fn test_subselections() {
         let mut configuration: Configuration = Default::default();
@@ -1073,7 +1344,9 @@ mod tests {
                     metadata: CacheKeyMetadata::default(),
                     plan_options: PlanOptions::default(),
                 },
-                doc,
+                &doc,
+                doc.clone(),
+                compute_job::Priority::P8,
             )
             .await
             .unwrap();
@@ -1106,6 +1379,25 @@ mod tests {
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
         let mut configuration: Configuration = Default::default();
         configuration.supergraph.introspection = true;
+        plan_with_configuration(
+            configuration,
+            schema,
+            original_query,
+            filtered_query,
+            operation_name,
+            plan_options,
+        )
+        .await
+    }
+
+    async fn plan_with_configuration(
+        configuration: Configuration,
+        schema: &str,
+        original_query: &str,
+        filtered_query: &str,
+        operation_name: Option<String>,
+        plan_options: PlanOptions,
+    ) -> Result<QueryPlannerContent, QueryPlannerError> {
         let configuration = Arc::new(configuration);
 
         let schema = Schema::parse(schema, &configuration).unwrap();
@@ -1133,7 +1425,9 @@ mod tests {
                     metadata: CacheKeyMetadata::default(),
                     plan_options,
                 },
-                doc,
+                &doc,
+                doc.clone(),
+                compute_job::Priority::P8,
             )
             .await
     }
@@ -36,14 +36,30 @@ impl BridgeQueryPlannerPool {
     pub(crate) async fn new(
         schema: Arc<Schema>,
         configuration: Arc<Configuration>,
+    ) -> Result<Self, ServiceBuildError> {
+        Self::new_sharing(schema, configuration, None).await
+    }
+
+    /// Creates the pool, sharing the query planner of the previous pool when the schema and the
+    /// query planning options did not change
+    pub(crate) async fn new_sharing(
+        schema: Arc<Schema>,
+        configuration: Arc<Configuration>,
+        previous: Option<&Self>,
     ) -> Result<Self, ServiceBuildError> {
         // All query planners in the pool now share the same introspection cache.
         // This allows meaningful gauges, and it makes sense that queries should be cached across all planners.
         let introspection_cache = Arc::new(IntrospectionCache::new(&configuration));
 
-        let delegate =
-            BridgeQueryPlanner::new(schema.clone(), configuration, introspection_cache.clone())
-                .await?;
+        let delegate = BridgeQueryPlanner::new_sharing(
+            schema.clone(),
+            configuration,
+            introspection_cache.clone(),
+            previous.map(|previous| match &previous.pool_mode {
+                PoolMode::PassThrough { delegate } => delegate,
+            }),
+        )
+        .await?;
 
         Ok(Self {
             subgraph_schemas: delegate.subgraph_schemas(),
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::path::Path;
 use std::sync::Arc;
 use std::task;
 
@@ -10,6 +11,7 @@ use indexmap::IndexMap;
 use query_planner::QueryPlannerPlugin;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
+use serde::Deserialize;
 use sha2::Digest;
 use sha2::Sha256;
 use tower::BoxError;
@@ -19,17 +21,22 @@ use tower_service::Service;
 use tracing::Instrument;
 
 use super::fetch::QueryHash;
+use crate::apollo_studio_interop::normalize_operation;
+use crate::apollo_studio_interop::NormalizationOptions;
 use crate::apollo_studio_interop::UsageReporting;
 use crate::cache::estimate_size;
 use crate::cache::storage::InMemoryCache;
 use crate::cache::storage::ValueType;
 use crate::cache::DeduplicatingCache;
+use crate::compute_job;
 use crate::configuration::PersistedQueriesPrewarmQueryPlanCache;
 use crate::error::CacheResolverError;
+use crate::error::FederationErrorBridge;
 use crate::error::QueryPlannerError;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
+use crate::plugins::telemetry::config::Conf as TelemetryConfig;
 use crate::plugins::telemetry::utils::Timer;
 use crate::query_planner::fetch::SubgraphSchemas;
 use crate::query_planner::BridgeQueryPlannerPool;
@@ -51,6 +58,32 @@ pub(crate) type InMemoryCachePlanner =
     InMemoryCache<CachingQueryKey, Result<QueryPlannerContent, Arc<QueryPlannerError>>>;
 pub(crate) const APOLLO_OPERATION_ID: &str = "apollo_operation_id";
 
+/// An operation of the warm up manifest, planned before a new schema or configuration serves traffic
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct WarmUpOperation {
+    pub(crate) query: String,
+    #[serde(default)]
+    pub(crate) operation_name: Option<String>,
+}
+
+/// Reads the warm up manifest: a JSON array of `{"query": ..., "operationName": ...}` objects
+pub(crate) async fn load_warm_up_manifest(path: &Path) -> Result<Vec<WarmUpOperation>, BoxError> {
+    let content = tokio::fs::read(path).await.map_err(|e| {
+        format!(
+            "could not read the warm up manifest at {}: {e}",
+            path.display()
+        )
+    })?;
+    serde_json::from_slice(&content).map_err(|e| {
+        format!(
+            "could not parse the warm up manifest at {}: {e}",
+            path.display()
+        )
+        .into()
+    })
+}
+
 /// A query planner wrapper that caches results.
 ///
 /// The query planner performs LRU caching.
@@ -64,6 +97,7 @@ pub(crate) struct CachingQueryPlanner<T: Clone> {
     subgraph_schemas: Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>>,
     plugins: Arc<Plugins>,
     enable_authorization_directives: bool,
+    disabled_subgraphs: Vec<String>,
     config_mode_hash: Arc<QueryHash>,
 }
 
@@ -81,6 +115,15 @@ fn init_query_plan_from_redis(
     Ok(())
 }
 
+/// Planning timeouts depend on the load of the router, so they are not cached: the next request
+/// for the same operation plans it again
+fn can_cache_error(error: &QueryPlannerError) -> bool {
+    !matches!(
+        error,
+        QueryPlannerError::FederationError(FederationErrorBridge::QueryPlanningTimeout(_))
+    )
+}
+
 impl<T: Clone + 'static> CachingQueryPlanner<T>
 where
     T: tower::Service<
@@ -109,8 +152,19 @@ where
         let enable_authorization_directives =
             AuthorizationPlugin::enable_directives(configuration, &schema).unwrap_or(false);
 
+        // The disabled subgraphs are part of the cache key, so keep them in a stable order
+        let mut disabled_subgraphs = configuration
+            .supergraph
+            .query_planning
+            .experimental_disabled_subgraphs
+            .clone();
+        disabled_subgraphs.sort();
+        disabled_subgraphs.dedup();
+
         let mut hasher = StructHasher::new();
         configuration.rust_query_planner_config().hash(&mut hasher);
+        // The usage reporting of the cached plans depends on the redacted arguments
+        TelemetryConfig::redacted_arguments(configuration).hash(&mut hasher);
         let config_mode_hash = Arc::new(QueryHash(hasher.finalize()));
 
         Ok(Self {
@@ -120,6 +174,7 @@ where
             subgraph_schemas,
             plugins: Arc::new(plugins),
             enable_authorization_directives,
+            disabled_subgraphs,
             config_mode_hash,
         })
     }
@@ -128,6 +183,14 @@ where
         self.cache.in_memory_cache()
     }
 
+    pub(crate) fn delegate(&self) -> &T {
+        &self.delegate
+    }
+
+    pub(crate) async fn clear_cache(&self) {
+        self.cache.clear_in_memory().await
+    }
+
     pub(crate) async fn warm_up(
         &mut self,
         query_analysis: &QueryAnalysisLayer,
@@ -136,6 +199,7 @@ where
         count: Option<usize>,
         experimental_reuse_query_plans: bool,
         experimental_pql_prewarm: &PersistedQueriesPrewarmQueryPlanCache,
+        warm_up_operations: &[WarmUpOperation],
     ) {
         let _timer = Timer::new(|duration| {
             f64_histogram!(
@@ -166,6 +230,7 @@ where
                         |(
                             CachingQueryKey {
                                 query,
+                                normalized_query: _,
                                 operation,
                                 hash,
                                 metadata,
@@ -179,7 +244,10 @@ where
                             operation_name: operation.clone(),
                             hash: Some(hash.clone()),
                             metadata: metadata.clone(),
-                            plan_options: plan_options.clone(),
+                            plan_options: PlanOptions {
+                                disabled_subgraphs: self.disabled_subgraphs.clone(),
+                                ..plan_options.clone()
+                            },
                             config_mode: self.config_mode_hash.clone(),
                         },
                     )
@@ -198,12 +266,13 @@ where
 
         let capacity = if should_warm_with_pqs {
             cache_keys.len()
+                + warm_up_operations.len()
                 + persisted_queries_operations
                     .as_ref()
                     .map(|ops| ops.len())
                     .unwrap_or(0)
         } else {
-            cache_keys.len()
+            cache_keys.len() + warm_up_operations.len()
         };
 
         if capacity > 0 {
@@ -224,7 +293,10 @@ where
                         operation_name: None,
                         hash: None,
                         metadata: CacheKeyMetadata::default(),
-                        plan_options: PlanOptions::default(),
+                        plan_options: PlanOptions {
+                            disabled_subgraphs: self.disabled_subgraphs.clone(),
+                            ..Default::default()
+                        },
                         config_mode: self.config_mode_hash.clone(),
                     });
                 }
@@ -233,6 +305,27 @@ where
 
         all_cache_keys.extend(cache_keys.into_iter());
 
+        // operations of the manifest are added last so that they are the least likely to be evicted
+        all_cache_keys.extend(
+            warm_up_operations
+                .iter()
+                .map(|operation| WarmUpCachingQueryKey {
+                    query: operation.query.clone(),
+                    operation_name: operation.operation_name.clone(),
+                    hash: None,
+                    metadata: CacheKeyMetadata::default(),
+                    plan_options: PlanOptions {
+                        disabled_subgraphs: self.disabled_subgraphs.clone(),
+                        ..Default::default()
+                    },
+                    config_mode: self.config_mode_hash.clone(),
+                }),
+        );
+
+        // Operations are planned in parallel, with at most one per thread of the compute pool.
+        // They have a low priority there, so they use the threads that client requests leave idle
+        let max_concurrency = compute_job::thread_pool_size();
+        let mut planning = tokio::task::JoinSet::new();
         let mut count = 0usize;
         let mut reused = 0usize;
         for WarmUpCachingQueryKey {
@@ -253,6 +346,7 @@ where
             };
 
             let caching_key = CachingQueryKey {
+                normalized_query: normalized_query(&doc, &query, operation_name.as_deref()),
                 query: query.clone(),
                 operation: operation_name.clone(),
                 hash: doc.hash.clone(),
@@ -307,32 +401,49 @@ where
                     document: doc,
                     metadata: caching_key.metadata,
                     plan_options: caching_key.plan_options,
+                    warm_up: true,
                 };
 
-                let res = match service.ready().await {
-                    Ok(service) => service.call(request).await,
+                if planning.len() >= max_concurrency {
+                    count += planning
+                        .join_next()
+                        .await
+                        .and_then(Result::ok)
+                        .unwrap_or_default();
+                }
+                let response = match service.ready().await {
+                    Ok(service) => service.call(request),
                     Err(_) => break,
                 };
 
-                match res {
-                    Ok(QueryPlannerResponse { content, .. }) => {
-                        if let Some(content) = content.clone() {
-                            count += 1;
-                            tokio::spawn(async move {
-                                entry.insert(Ok(content.clone())).await;
-                            });
+                // Spawned so that planning progresses while waiting for other cache entries. The
+                // tasks are aborted if the warm up is dropped, which cancels their planning
+                planning.spawn(async move {
+                    match response.await {
+                        Ok(QueryPlannerResponse { content, .. }) => {
+                            if let Some(content) = content {
+                                entry.insert(Ok(content)).await;
+                                1
+                            } else {
+                                0
+                            }
+                        }
+                        Err(error) => {
+                            let error = Arc::new(error);
+                            if can_cache_error(&error) {
+                                entry.insert(Err(error)).await;
+                            } else {
+                                entry.send(Err(error)).await;
+                            }
+                            1
                         }
                     }
-                    Err(error) => {
-                        count += 1;
-                        let e = Arc::new(error);
-                        tokio::spawn(async move {
-                            entry.insert(Err(e)).await;
-                        });
-                    }
-                }
+                });
             }
         }
+        while let Some(planned) = planning.join_next().await {
+            count += planned.unwrap_or_default();
+        }
 
         tracing::debug!("warmed up the query planner cache with {count} queries planned and {reused} queries reused");
     }
@@ -417,6 +528,7 @@ where
                 .get(LABELS_TO_OVERRIDE_KEY)
                 .unwrap_or_default()
                 .unwrap_or_default(),
+            disabled_subgraphs: self.disabled_subgraphs.clone(),
         };
 
         let doc = match request
@@ -442,6 +554,11 @@ where
             .unwrap_or_default();
 
         let caching_key = CachingQueryKey {
+            normalized_query: normalized_query(
+                &doc,
+                &request.query,
+                request.operation_name.as_deref(),
+            ),
             query: request.query.clone(),
             operation: request.operation_name.to_owned(),
             hash: doc.hash.clone(),
@@ -528,7 +645,11 @@ where
                             let e = Arc::new(error);
                             let err = e.clone();
                             tokio::spawn(async move {
-                                entry.insert(Err(err)).await;
+                                if can_cache_error(&err) {
+                                    entry.insert(Err(err)).await;
+                                } else {
+                                    entry.send(Err(err)).await;
+                                }
                             });
                             if let Some(usage_reporting) = e.usage_reporting() {
                                 context.extensions().with_lock(|mut lock| {
@@ -584,9 +705,26 @@ fn stats_report_key_hash(stats_report_key: &str) -> String {
     hex::encode(result)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Normalized text of the operation, so that operations that only differ by comments or
+/// whitespace share a query plan. Falls back to the raw query if the operation is not found.
+fn normalized_query(doc: &ParsedDocument, query: &str, operation_name: Option<&str>) -> String {
+    normalize_operation(
+        &doc.executable,
+        operation_name,
+        NormalizationOptions::default(),
+    )
+    .map(|normalized| normalized.text)
+    .unwrap_or_else(|| query.to_string())
+}
+
+/// In-memory key of the query plan cache.
+///
+/// The raw `query` is kept to plan the operation again during warm-up, but keys are compared on
+/// `normalized_query`.
+#[derive(Debug, Clone)]
 pub(crate) struct CachingQueryKey {
     pub(crate) query: String,
+    pub(crate) normalized_query: String,
     pub(crate) schema_id: Arc<String>,
     pub(crate) operation: Option<String>,
     pub(crate) hash: Arc<QueryHash>,
@@ -595,6 +733,32 @@ pub(crate) struct CachingQueryKey {
     pub(crate) config_mode: Arc<QueryHash>,
 }
 
+impl PartialEq for CachingQueryKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized_query == other.normalized_query
+            && self.schema_id == other.schema_id
+            && self.operation == other.operation
+            && self.hash == other.hash
+            && self.metadata == other.metadata
+            && self.plan_options == other.plan_options
+            && self.config_mode == other.config_mode
+    }
+}
+
+impl Eq for CachingQueryKey {}
+
+impl Hash for CachingQueryKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized_query.hash(state);
+        self.schema_id.hash(state);
+        self.operation.hash(state);
+        self.hash.hash(state);
+        self.metadata.hash(state);
+        self.plan_options.hash(state);
+        self.config_mode.hash(state);
+    }
+}
+
 const ROUTER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 impl std::fmt::Display for CachingQueryKey {
@@ -794,6 +958,234 @@ mod tests {
             .is_err());
     }
 
+    #[test(tokio::test)]
+    async fn test_plan_shared_by_equivalent_queries() {
+        let planned = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut delegate = MockMyQueryPlanner::new();
+        let planned_by_delegate = planned.clone();
+        delegate.expect_clone().returning(move || {
+            let planned = planned_by_delegate.clone();
+            let mut planner = MockMyQueryPlanner::new();
+            planner.expect_sync_call().returning(move |request| {
+                // Client requests are planned with a higher priority than warm up
+                assert!(!request.warm_up);
+                planned.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(QueryPlannerError::UnhandledPlannerResult)
+            });
+            planner
+        });
+
+        let configuration = Arc::new(crate::Configuration::default());
+        let schema = include_str!("testdata/schema.graphql");
+        let schema = Arc::new(Schema::parse(schema, &configuration).unwrap());
+
+        let mut planner = CachingQueryPlanner::new(
+            delegate,
+            schema.clone(),
+            Default::default(),
+            &configuration,
+            IndexMap::default(),
+        )
+        .await
+        .unwrap();
+
+        // Only comments and whitespace differ, so the second query uses the cached plan
+        for query in [
+            "query Me { me { username } }",
+            "# a comment\nquery Me {\n  me {\n    username\n  }\n}",
+        ] {
+            let doc = Query::parse_document(query, None, &schema, &configuration).unwrap();
+            let context = Context::new();
+            context
+                .extensions()
+                .with_lock(|mut lock| lock.insert::<ParsedDocument>(doc));
+
+            assert!(planner
+                .call(query_planner::CachingRequest::new(
+                    query.to_string(),
+                    Some("Me".into()),
+                    context
+                ))
+                .await
+                .is_err());
+        }
+        assert_eq!(planned.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn test_warm_up_with_manifest() {
+        let manifest = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            manifest.path(),
+            r#"[{"query": "query Me { me { username } }", "operationName": "Me"}]"#,
+        )
+        .unwrap();
+        let operations = load_warm_up_manifest(manifest.path()).await.unwrap();
+        assert_eq!(
+            operations,
+            vec![WarmUpOperation {
+                query: "query Me { me { username } }".to_string(),
+                operation_name: Some("Me".to_string()),
+            }]
+        );
+
+        let planned = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut delegate = MockMyQueryPlanner::new();
+        let planned_by_delegate = planned.clone();
+        delegate.expect_clone().returning(move || {
+            let planned = planned_by_delegate.clone();
+            let mut planner = MockMyQueryPlanner::new();
+            planner.expect_sync_call().returning(move |request| {
+                assert_eq!(request.operation_name.as_deref(), Some("Me"));
+                assert!(request.warm_up);
+                planned.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(QueryPlannerError::UnhandledPlannerResult)
+            });
+            planner
+        });
+
+        let configuration = Arc::new(crate::Configuration::default());
+        let schema = include_str!("testdata/schema.graphql");
+        let schema = Arc::new(Schema::parse(schema, &configuration).unwrap());
+
+        let mut planner = CachingQueryPlanner::new(
+            delegate,
+            schema.clone(),
+            Default::default(),
+            &configuration,
+            IndexMap::default(),
+        )
+        .await
+        .unwrap();
+
+        planner
+            .warm_up(
+                &QueryAnalysisLayer::new(schema, configuration.clone()).await,
+                &PersistedQueryLayer::new(&configuration).await.unwrap(),
+                None,
+                None,
+                false,
+                &Default::default(),
+                &operations,
+            )
+            .await;
+        assert_eq!(planned.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert!(
+            load_warm_up_manifest(Path::new("/nonexistent/warm-up.json"))
+                .await
+                .is_err()
+        );
+    }
+
+    /// Plans nothing, but records how many plans are in progress at the same time. Each plan waits
+    /// until `expected_concurrency` plans were in progress together.
+    #[derive(Clone)]
+    struct ConcurrencyTrackingPlanner {
+        expected_concurrency: usize,
+        in_progress: Arc<std::sync::atomic::AtomicUsize>,
+        max_in_progress: Arc<std::sync::atomic::AtomicUsize>,
+        planned: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Service<QueryPlannerRequest> for ConcurrencyTrackingPlanner {
+        type Response = QueryPlannerResponse;
+
+        type Error = QueryPlannerError;
+
+        type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut task::Context<'_>,
+        ) -> task::Poll<Result<(), Self::Error>> {
+            task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: QueryPlannerRequest) -> Self::Future {
+            assert!(req.warm_up);
+            let this = self.clone();
+            Box::pin(async move {
+                let in_progress = this
+                    .in_progress
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+                    + 1;
+                this.max_in_progress
+                    .fetch_max(in_progress, std::sync::atomic::Ordering::SeqCst);
+                while this
+                    .max_in_progress
+                    .load(std::sync::atomic::Ordering::SeqCst)
+                    < this.expected_concurrency
+                {
+                    tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                }
+                this.in_progress
+                    .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                this.planned
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(QueryPlannerError::UnhandledPlannerResult)
+            })
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn test_warm_up_plans_concurrently() {
+        let max_concurrency = compute_job::thread_pool_size();
+        let delegate = ConcurrencyTrackingPlanner {
+            expected_concurrency: max_concurrency,
+            in_progress: Default::default(),
+            max_in_progress: Default::default(),
+            planned: Default::default(),
+        };
+        let configuration = Arc::new(crate::Configuration::default());
+        let schema = include_str!("testdata/schema.graphql");
+        let schema = Arc::new(Schema::parse(schema, &configuration).unwrap());
+
+        let mut planner = CachingQueryPlanner::new(
+            delegate.clone(),
+            schema.clone(),
+            Default::default(),
+            &configuration,
+            IndexMap::default(),
+        )
+        .await
+        .unwrap();
+
+        // More operations than threads in the compute pool, each with its own cache entry
+        let operations: Vec<_> = (0..max_concurrency * 2 + 1)
+            .map(|i| WarmUpOperation {
+                query: format!("query Op{i} {{ me {{ username }} }}"),
+                operation_name: Some(format!("Op{i}")),
+            })
+            .collect();
+        // Planning one operation at a time would never reach the expected concurrency
+        tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            planner.warm_up(
+                &QueryAnalysisLayer::new(schema, configuration.clone()).await,
+                &PersistedQueryLayer::new(&configuration).await.unwrap(),
+                None,
+                None,
+                false,
+                &Default::default(),
+                &operations,
+            ),
+        )
+        .await
+        .expect("warm up plans concurrently");
+
+        assert_eq!(
+            delegate.planned.load(std::sync::atomic::Ordering::SeqCst),
+            operations.len()
+        );
+        assert_eq!(
+            delegate
+                .max_in_progress
+                .load(std::sync::atomic::Ordering::SeqCst),
+            max_concurrency
+        );
+    }
+
     macro_rules! test_query_plan {
         () => {
             include_str!("testdata/query_plan.json")
@@ -0,0 +1,260 @@
+//! Merging of the entity fetches that run in parallel in a query plan
+//!
+//! When several branches of a parallel node fetch the same fields of the same entities from
+//! a subgraph, their representations are sent in a single `_entities` fetch, and the entities
+//! are then inserted at the paths of every branch.
+
+use std::time::Instant;
+
+use futures::future::join_all;
+use futures::future::BoxFuture;
+use indexmap::IndexSet;
+use tracing::Instrument;
+
+use super::execution::record_fetch_timing;
+use super::execution::ExecutionParameters;
+use super::fetch::FetchNode;
+use super::fetch::Variables;
+use super::FlattenNode;
+use super::PlanNode;
+use super::FETCH_SPAN_NAME;
+use crate::axum_factory::RequestCancellation;
+use crate::error::Error;
+use crate::json_ext::Object;
+use crate::json_ext::Path;
+use crate::json_ext::Value;
+use crate::json_ext::ValueExt;
+
+/// Entity fetches of a parallel node that can be sent to the subgraph as a single fetch
+pub(crate) struct EntityBatch<'a> {
+    fetches: Vec<(&'a FetchNode, Path)>,
+}
+
+impl<'a> EntityBatch<'a> {
+    /// Separates the entity fetches that can be merged from the other nodes of a parallel node
+    pub(crate) fn from_parallel_nodes(
+        parameters: &ExecutionParameters<'_>,
+        nodes: &'a [PlanNode],
+        current_dir: &Path,
+    ) -> (Vec<EntityBatch<'a>>, Vec<&'a PlanNode>) {
+        let mut groups: Vec<Vec<(&'a PlanNode, &'a FetchNode, Path)>> = Vec::new();
+        let mut others = Vec::new();
+
+        for node in nodes {
+            let fetch = match node {
+                PlanNode::Flatten(FlattenNode { path, node: inner }) => match inner.as_ref() {
+                    PlanNode::Fetch(fetch)
+                        if parameters
+                            .entity_batching
+                            .for_subgraph(&fetch.service_name)
+                            .is_some() =>
+                    {
+                        Some((fetch, current_dir.join(path.remove_empty_key_root())))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match fetch {
+                Some((fetch, dir)) => {
+                    match groups
+                        .iter_mut()
+                        .find(|group| group[0].1.can_batch_with(fetch))
+                    {
+                        Some(group) => group.push((node, fetch, dir)),
+                        None => groups.push(vec![(node, fetch, dir)]),
+                    }
+                }
+                None => others.push(node),
+            }
+        }
+
+        let mut batches = Vec::new();
+        for group in groups {
+            if group.len() == 1 {
+                others.push(group[0].0);
+            } else {
+                batches.push(EntityBatch {
+                    fetches: group
+                        .into_iter()
+                        .map(|(_, fetch, dir)| (fetch, dir))
+                        .collect(),
+                });
+            }
+        }
+
+        (batches, others)
+    }
+
+    pub(crate) fn execute(
+        self,
+        parameters: &'a ExecutionParameters<'a>,
+        parent_value: &'a Value,
+    ) -> BoxFuture<'a, (Value, Vec<Error>)> {
+        Box::pin(async move {
+            // The client closed the connection, we won't send unused trafic to subgraphs
+            if RequestCancellation::is_cancelled(parameters.context) {
+                return (Value::Object(Object::default()), Vec::new());
+            }
+
+            let max_batch_size = self
+                .fetches
+                .first()
+                .and_then(|(fetch, _)| parameters.entity_batching.for_subgraph(&fetch.service_name))
+                .and_then(|config| config.max_batch_size)
+                .map(|size| size.get())
+                .unwrap_or(usize::MAX);
+
+            let mut pending: Vec<PendingFetch> = Vec::new();
+            for (fetch, current_dir) in self.fetches {
+                let Some(variables) = fetch.variables(parameters, parent_value, &current_dir)
+                else {
+                    continue;
+                };
+                let next = PendingFetch::new(fetch, current_dir, variables);
+                match pending.last_mut() {
+                    Some(last)
+                        if last.representations.len() + next.representations.len()
+                            <= max_batch_size =>
+                    {
+                        last.merge(next)
+                    }
+                    _ => pending.push(next),
+                }
+            }
+
+            let results = join_all(pending.into_iter().map(|fetch| fetch.send(parameters))).await;
+            let mut value = Value::default();
+            let mut errors = Vec::new();
+            for (v, err) in results {
+                value.type_aware_deep_merge(v, parameters.schema);
+                errors.extend(err);
+            }
+            (value, errors)
+        })
+    }
+}
+
+/// A fetch with the representations of the fetches merged into it
+struct PendingFetch<'a> {
+    fetch: &'a FetchNode,
+    /// Paths of the fetch and of the fetches merged into it
+    dirs: Vec<Path>,
+    variables: Object,
+    representations: IndexSet<Value>,
+    inverted_paths: Vec<Vec<Path>>,
+    merged: usize,
+}
+
+impl<'a> PendingFetch<'a> {
+    fn new(fetch: &'a FetchNode, current_dir: Path, variables: Variables) -> Self {
+        let Variables {
+            mut variables,
+            inverted_paths,
+            ..
+        } = variables;
+        let representations = match variables.remove("representations") {
+            Some(Value::Array(representations)) => representations.into_iter().collect(),
+            _ => IndexSet::default(),
+        };
+        Self {
+            fetch,
+            dirs: vec![current_dir],
+            variables,
+            representations,
+            inverted_paths,
+            merged: 0,
+        }
+    }
+
+    fn merge(&mut self, other: PendingFetch<'a>) {
+        for (representation, paths) in other.representations.into_iter().zip(other.inverted_paths) {
+            match self.representations.get_index_of(&representation) {
+                Some(index) => self.inverted_paths[index].extend(paths),
+                None => {
+                    self.representations.insert(representation);
+                    self.inverted_paths.push(paths);
+                }
+            }
+        }
+        self.dirs.extend(other.dirs);
+        self.merged += 1 + other.merged;
+    }
+
+    async fn send(self, parameters: &'a ExecutionParameters<'a>) -> (Value, Vec<Error>) {
+        let PendingFetch {
+            fetch,
+            dirs,
+            mut variables,
+            representations,
+            inverted_paths,
+            merged,
+        } = self;
+        let current_dir = &dirs[0];
+
+        if merged > 0 {
+            u64_counter!(
+                "apollo.router.operations.entity_batching",
+                "Number of entity fetches merged into another fetch to the same subgraph",
+                merged as u64,
+                subgraph.name = fetch.service_name.to_string()
+            );
+        }
+
+        variables.insert(
+            "representations",
+            Value::Array(Vec::from_iter(representations)),
+        );
+        let fetch_time_offset = parameters.context.created_at.elapsed().as_nanos() as i64;
+        let start = Instant::now();
+        let (value, errors) = fetch
+            .fetch_with_variables(
+                parameters,
+                current_dir,
+                Variables {
+                    variables,
+                    inverted_paths,
+                    contextual_arguments: None,
+                },
+            )
+            .instrument(tracing::info_span!(
+                FETCH_SPAN_NAME,
+                "otel.kind" = "INTERNAL",
+                "apollo.subgraph.name" = fetch.service_name.as_ref(),
+                "apollo_private.sent_time_offset" = fetch_time_offset
+            ))
+            .await;
+
+        for dir in &dirs {
+            record_fetch_timing(
+                parameters.context,
+                &fetch.service_name,
+                dir,
+                fetch_time_offset,
+                start,
+            );
+        }
+
+        // Errors that are not about a specific entity are reported at the path of the fetch,
+        // they concern each of the merged fetches
+        let errors = errors
+            .into_iter()
+            .flat_map(|error| {
+                if dirs.len() > 1 && error.path.as_ref() == Some(current_dir) {
+                    dirs.iter()
+                        .map(|dir| {
+                            let mut error = error.clone();
+                            error.path = Some(dir.clone());
+                            error
+                        })
+                        .collect()
+                } else {
+                    vec![error]
+                }
+            })
+            .collect();
+
+        (value, errors)
+    }
+}
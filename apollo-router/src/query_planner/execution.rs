@@ -1,20 +1,24 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use apollo_compiler::validation::Valid;
 use futures::future::join_all;
 use futures::prelude::*;
+use serde_json_bytes::json;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::BroadcastStream;
 use tracing::Instrument;
 
+use super::entity_batching::EntityBatch;
 use super::log;
 use super::subscription::SubscriptionHandle;
 use super::DeferredNode;
 use super::PlanNode;
 use super::QueryPlan;
-use crate::axum_factory::CanceledRequest;
+use crate::axum_factory::RequestCancellation;
+use crate::configuration::EntityBatching;
 use crate::error::Error;
 use crate::graphql::Request;
 use crate::graphql::Response;
@@ -32,6 +36,7 @@ use crate::query_planner::DEFER_DEFERRED_SPAN_NAME;
 use crate::query_planner::DEFER_PRIMARY_SPAN_NAME;
 use crate::query_planner::DEFER_SPAN_NAME;
 use crate::query_planner::FETCH_SPAN_NAME;
+use crate::query_planner::FETCH_TIMINGS_CONTEXT_KEY;
 use crate::query_planner::FLATTEN_SPAN_NAME;
 use crate::query_planner::PARALLEL_SPAN_NAME;
 use crate::query_planner::SEQUENCE_SPAN_NAME;
@@ -54,6 +59,8 @@ impl QueryPlan {
         sender: mpsc::Sender<Response>,
         subscription_handle: Option<SubscriptionHandle>,
         subscription_config: &'a Option<SubscriptionConfig>,
+        entity_batching: &'a Arc<EntityBatching>,
+        parallel_mutations: bool,
         initial_value: Option<Value>,
     ) -> Response {
         let root = Path::empty();
@@ -75,6 +82,8 @@ impl QueryPlan {
                     subscription_handle: &subscription_handle,
                     subscription_config,
                     subgraph_schemas,
+                    entity_batching,
+                    parallel_mutations,
                 },
                 &root,
                 &initial_value.unwrap_or_default(),
@@ -113,6 +122,8 @@ pub(crate) struct ExecutionParameters<'a> {
     pub(crate) root_node: &'a PlanNode,
     pub(crate) subscription_handle: &'a Option<SubscriptionHandle>,
     pub(crate) subscription_config: &'a Option<SubscriptionConfig>,
+    pub(crate) entity_batching: &'a Arc<EntityBatching>,
+    pub(crate) parallel_mutations: bool,
 }
 
 impl PlanNode {
@@ -157,8 +168,35 @@ impl PlanNode {
                     value = Value::default();
                     errors = Vec::new();
                     async {
+                        // Mutation root fields must be resolved serially, in the order they
+                        // appear in the plan, even if a plan groups them in a parallel node,
+                        // unless the configuration opted into running them concurrently
+                        if !parameters.parallel_mutations
+                            && nodes.iter().any(|node| node.contains_mutations())
+                        {
+                            for node in nodes {
+                                let (v, err) = node
+                                    .execute_recursively(
+                                        parameters,
+                                        current_dir,
+                                        parent_value,
+                                        sender.clone(),
+                                    )
+                                    .in_current_span()
+                                    .await;
+                                value.type_aware_deep_merge(v, parameters.schema);
+                                errors.extend(err.into_iter());
+                            }
+                            return;
+                        }
+
+                        // Entity fetches to the same subgraph with the same selection are
+                        // merged into a single fetch
+                        let (batches, nodes) =
+                            EntityBatch::from_parallel_nodes(parameters, nodes, current_dir);
+
                         let mut stream: stream::FuturesUnordered<_> = nodes
-                            .iter()
+                            .into_iter()
                             .map(|plan| {
                                 plan.execute_recursively(
                                     parameters,
@@ -168,6 +206,9 @@ impl PlanNode {
                                 )
                                 .in_current_span()
                             })
+                            .chain(batches.into_iter().map(|batch| {
+                                batch.execute(parameters, parent_value).in_current_span()
+                            }))
                             .collect();
 
                         while let Some((v, err)) = stream.next().in_current_span().await {
@@ -230,14 +271,11 @@ impl PlanNode {
 
                     // The client closed the connection, we are still executing the request pipeline,
                     // but we won't send unused trafic to subgraph
-                    if parameters
-                        .context
-                        .extensions()
-                        .with_lock(|lock| lock.get::<CanceledRequest>().is_some())
-                    {
+                    if RequestCancellation::is_cancelled(parameters.context) {
                         value = Value::Object(Object::default());
                         errors = Vec::new();
                     } else {
+                        let start = Instant::now();
                         let (v, e) = fetch_node
                             .fetch_node(parameters, parent_value, current_dir)
                             .instrument(tracing::info_span!(
@@ -247,6 +285,13 @@ impl PlanNode {
                                 "apollo_private.sent_time_offset" = fetch_time_offset
                             ))
                             .await;
+                        record_fetch_timing(
+                            parameters.context,
+                            &fetch_node.service_name,
+                            current_dir,
+                            fetch_time_offset,
+                            start,
+                        );
                         value = v;
                         errors = e;
                     }
@@ -299,6 +344,8 @@ impl PlanNode {
                                         subscription_handle: parameters.subscription_handle,
                                         subscription_config: parameters.subscription_config,
                                         subgraph_schemas: parameters.subgraph_schemas,
+                                        entity_batching: parameters.entity_batching,
+                                        parallel_mutations: parameters.parallel_mutations,
                                     },
                                     current_dir,
                                     &value,
@@ -446,6 +493,8 @@ impl DeferredNode {
         let query = parameters.query.clone();
         let subscription_handle = parameters.subscription_handle.clone();
         let subscription_config = parameters.subscription_config.clone();
+        let entity_batching = parameters.entity_batching.clone();
+        let parallel_mutations = parameters.parallel_mutations;
         let mut primary_receiver = primary_sender.subscribe();
         let mut value = parent_value.clone();
         let depends_json = serde_json::to_string(&self.depends).unwrap_or_default();
@@ -486,6 +535,8 @@ impl DeferredNode {
                             subscription_handle: &subscription_handle,
                             subscription_config: &subscription_config,
                             subgraph_schemas: &subgraph_schemas,
+                            entity_batching: &entity_batching,
+                            parallel_mutations,
                         },
                         &Path::default(),
                         &value,
@@ -553,3 +604,28 @@ impl DeferredNode {
         }
     }
 }
+
+/// Adds the timing of a fetch to the ones exposed with the query plan, if they were requested
+pub(super) fn record_fetch_timing(
+    context: &Context,
+    service_name: &str,
+    path: &Path,
+    start_offset: i64,
+    start: Instant,
+) {
+    if context.contains_key(FETCH_TIMINGS_CONTEXT_KEY) {
+        let timing = json!({
+            "service": service_name,
+            "path": path.to_string(),
+            "startOffsetNanos": start_offset,
+            "durationNanos": start.elapsed().as_nanos() as u64,
+        });
+        context.upsert_json_value(FETCH_TIMINGS_CONTEXT_KEY, |timings| match timings {
+            Value::Array(mut timings) => {
+                timings.push(timing);
+                Value::Array(timings)
+            }
+            _ => Value::Array(vec![timing]),
+        });
+    }
+}
@@ -8,6 +8,8 @@ use apollo_compiler::ExecutableDocument;
 use indexmap::IndexSet;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
 use tower::ServiceExt;
 use tracing::instrument;
 use tracing::Instrument;
@@ -19,6 +21,9 @@ use super::selection::Selection;
 use super::subgraph_context::build_operation_with_aliasing;
 use super::subgraph_context::ContextualArguments;
 use super::subgraph_context::SubgraphContext;
+use crate::apollo_studio_interop::normalize_operation;
+use crate::apollo_studio_interop::NormalizationOptions;
+use crate::axum_factory::RequestCancellation;
 use crate::error::Error;
 use crate::error::FetchError;
 use crate::error::ValidationErrors;
@@ -32,6 +37,7 @@ use crate::json_ext::Value;
 use crate::json_ext::ValueExt;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::traffic_shaping::deadline::RequestDeadline;
 use crate::services::SubgraphRequest;
 use crate::spec::query::change::QueryHashVisitor;
 use crate::spec::Schema;
@@ -371,6 +377,41 @@ impl FetchNode {
         parameters: &'a ExecutionParameters<'a>,
         data: &'a Value,
         current_dir: &'a Path,
+    ) -> (Value, Vec<Error>) {
+        match self.variables(parameters, data, current_dir) {
+            Some(variables) => {
+                self.fetch_with_variables(parameters, current_dir, variables)
+                    .await
+            }
+            None => (Value::Object(Object::default()), Vec::new()),
+        }
+    }
+
+    pub(super) fn variables(
+        &self,
+        parameters: &ExecutionParameters<'_>,
+        data: &Value,
+        current_dir: &Path,
+    ) -> Option<Variables> {
+        Variables::new(
+            &self.requires,
+            &self.variable_usages,
+            data,
+            current_dir,
+            // Needs the original request here
+            parameters.supergraph_request,
+            parameters.schema,
+            &self.input_rewrites,
+            &self.context_rewrites,
+        )
+    }
+
+    /// Sends the fetch to the subgraph, with variables computed beforehand
+    pub(super) async fn fetch_with_variables<'a>(
+        &'a self,
+        parameters: &'a ExecutionParameters<'a>,
+        current_dir: &'a Path,
+        variables: Variables,
     ) -> (Value, Vec<Error>) {
         let FetchNode {
             operation,
@@ -384,22 +425,7 @@ impl FetchNode {
             variables,
             inverted_paths: paths,
             contextual_arguments,
-        } = match Variables::new(
-            &self.requires,
-            &self.variable_usages,
-            data,
-            current_dir,
-            // Needs the original request here
-            parameters.supergraph_request,
-            parameters.schema,
-            &self.input_rewrites,
-            &self.context_rewrites,
-        ) {
-            Some(variables) => variables,
-            None => {
-                return (Value::Object(Object::default()), Vec::new());
-            }
-        };
+        } = variables;
 
         let alias_query_string; // this exists outside the if block to allow the as_str() to be longer lived
         let aliased_operation = if let Some(ctx_arg) = contextual_arguments {
@@ -468,10 +494,27 @@ impl FetchNode {
             .create(service_name)
             .expect("we already checked that the service exists during planning; qed");
 
-        let (_parts, response) = match service
-            .oneshot(subgraph_request)
-            .instrument(tracing::trace_span!("subfetch_stream"))
-            .await
+        let subfetch = RequestDeadline::run(
+            parameters.context,
+            service
+                .oneshot(subgraph_request)
+                .instrument(tracing::trace_span!("subfetch_stream")),
+        );
+        let response = match RequestCancellation::run(parameters.context, subfetch).await {
+            // The client closed the connection, the response would not be used
+            None => return (Value::Object(Object::default()), Vec::new()),
+            Some(Ok(response)) => response,
+            Some(Err(elapsed)) => {
+                let mut error = graphql::Error::from(elapsed);
+                error.path = Some(current_dir.to_owned());
+                error
+                    .extensions
+                    .insert("service", service_name.to_string().into());
+                return (Value::default(), vec![error]);
+            }
+        };
+
+        let (_parts, response) = match response
             // TODO this is a problem since it restores details about failed service
             // when errors have been redacted in the include_subgraph_errors module.
             // Unfortunately, not easy to fix here, because at this point we don't
@@ -560,7 +603,11 @@ impl FetchNode {
                         // the error's path has the format '/_entities/1/other' so we ignore the
                         // first element and then get the index
                         match path.0.get(1) {
-                            Some(json_ext::PathElement::Index(i)) => {
+                            // an error targeting an entity we did not ask for cannot be
+                            // attached to any value, it goes to the fetch's path instead
+                            Some(json_ext::PathElement::Index(i))
+                                if inverted_paths.get(*i).is_some_and(|v| !v.is_empty()) =>
+                            {
                                 for values_path in
                                     inverted_paths.get(*i).iter().flat_map(|v| v.iter())
                                 {
@@ -675,6 +722,37 @@ impl FetchNode {
         &self.operation_kind
     }
 
+    /// Whether the entities fetched by this node and the other one can be requested in a single
+    /// fetch: both select the same fields of the same entities, only the operation names differ
+    pub(crate) fn can_batch_with(&self, other: &FetchNode) -> bool {
+        self.is_batchable()
+            && other.is_batchable()
+            && self.service_name == other.service_name
+            && self.requires == other.requires
+            && self.variable_usages == other.variable_usages
+            && self.input_rewrites == other.input_rewrites
+            && self.output_rewrites == other.output_rewrites
+            && self.authorization == other.authorization
+            && self.unnamed_operation() == other.unnamed_operation()
+    }
+
+    fn is_batchable(&self) -> bool {
+        // deferred fetches and fetches using contextual arguments need the results of this
+        // specific fetch, so they are left alone
+        !self.requires.is_empty()
+            && self.operation_kind == OperationKind::Query
+            && self.id.is_none()
+            && self.context_rewrites.is_none()
+    }
+
+    fn unnamed_operation(&self) -> std::borrow::Cow<'_, str> {
+        let operation = self.operation.as_serialized();
+        match &self.operation_name {
+            Some(name) => operation.replacen(name.as_ref(), "", 1).into(),
+            None => operation.into(),
+        }
+    }
+
     pub(crate) fn init_parsed_operation(
         &mut self,
         subgraph_schemas: &SubgraphSchemas,
@@ -692,14 +770,30 @@ impl FetchNode {
         let schema = &subgraph_schemas[self.service_name.as_ref()];
         let doc = self.operation.init_parsed(schema)?;
 
-        if let Ok(hash) = QueryHashVisitor::hash_query(
+        let hash = match QueryHashVisitor::hash_query(
             schema,
             supergraph_schema_hash,
             doc,
             self.operation_name.as_deref(),
         ) {
-            self.schema_aware_hash = Arc::new(QueryHash(hash));
-        }
+            Ok(hash) => hash,
+            // Fall back to the normalized operation so that different operations
+            // do not share entries in the caches keyed by this hash
+            Err(_) => match normalize_operation(
+                doc,
+                self.operation_name.as_deref(),
+                NormalizationOptions::default(),
+            ) {
+                Some(normalized) => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(supergraph_schema_hash.as_bytes());
+                    hasher.update(normalized.hash().as_bytes());
+                    hasher.finalize().to_vec()
+                }
+                None => return Ok(()),
+            },
+        };
+        self.schema_aware_hash = Arc::new(QueryHash(hash));
         Ok(())
     }
 
@@ -730,3 +824,58 @@ impl FetchNode {
         ));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    #[test]
+    fn entity_errors_with_unknown_index_are_not_dropped() {
+        let schema =
+            Schema::parse(include_str!("testdata/schema.graphql"), &Default::default()).unwrap();
+        let fetch_node: FetchNode = serde_json::from_value(serde_json::json!({
+            "serviceName": "product",
+            "requires": [{
+                "kind": "InlineFragment",
+                "typeCondition": "Book",
+                "selections": [
+                    { "kind": "Field", "name": "__typename" },
+                    { "kind": "Field", "name": "isbn" }
+                ]
+            }],
+            "variableUsages": [],
+            "operation": "query($representations:[_Any!]!){_entities(representations:$representations){...on Book{title}}}",
+            "operationKind": "query"
+        }))
+        .unwrap();
+
+        let current_dir = Path::from("topProducts/@");
+        let inverted_paths = vec![vec![Path::from("topProducts/0")]];
+        let response = graphql::Response::builder()
+            .data(json!({ "_entities": [{ "title": "a title" }] }))
+            .errors(vec![
+                graphql::Error::builder()
+                    .message("known entity")
+                    .path(Path::from("_entities/0/title"))
+                    .extension_code("ERROR")
+                    .build(),
+                graphql::Error::builder()
+                    .message("unknown entity")
+                    .path(Path::from("_entities/3/title"))
+                    .extension_code("ERROR")
+                    .build(),
+            ])
+            .build();
+
+        let (value, errors) =
+            fetch_node.response_at_path(&schema, &current_dir, inverted_paths, response);
+
+        assert_eq!(value, json!({ "topProducts": [{ "title": "a title" }] }));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, Some(Path::from("topProducts/0/title")));
+        assert_eq!(errors[1].message, "unknown entity");
+        assert_eq!(errors[1].path, Some(current_dir));
+    }
+}
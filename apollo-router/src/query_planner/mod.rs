@@ -14,6 +14,7 @@ pub(crate) mod bridge_query_planner;
 mod bridge_query_planner_pool;
 mod caching_query_planner;
 mod convert;
+mod entity_batching;
 mod execution;
 pub(crate) mod fetch;
 mod labeler;
@@ -35,6 +36,9 @@ pub(crate) const CONDITION_SPAN_NAME: &str = "condition";
 pub(crate) const CONDITION_IF_SPAN_NAME: &str = "condition_if";
 pub(crate) const CONDITION_ELSE_SPAN_NAME: &str = "condition_else";
 
+/// Fetch timings are only recorded by the query plan execution when this key is present
+pub(crate) const FETCH_TIMINGS_CONTEXT_KEY: &str = "experimental::expose_query_plan.fetch_timings";
+
 // The code resides in a separate submodule to allow writing a log filter activating it
 // separately from the query planner logs, as follows:
 // `router -s supergraph.graphql --log info,crate::query_planner::log=trace`
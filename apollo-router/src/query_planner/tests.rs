@@ -18,11 +18,13 @@ use super::PlanNode;
 use super::Primary;
 use super::QueryPlan;
 use crate::apollo_studio_interop::UsageReporting;
+use crate::configuration::EntityBatching;
 use crate::graphql;
 use crate::json_ext::Path;
 use crate::json_ext::PathElement;
 use crate::plugin;
 use crate::plugin::test::MockSubgraph;
+use crate::plugins::traffic_shaping::deadline::RequestDeadline;
 use crate::query_planner;
 use crate::query_planner::fetch::FetchNode;
 use crate::query_planner::fetch::SubgraphOperation;
@@ -121,6 +123,8 @@ async fn mock_subgraph_service_withf_panics_should_be_reported_as_service_closed
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -185,6 +189,8 @@ async fn fetch_includes_operation_name() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -192,6 +198,237 @@ async fn fetch_includes_operation_name() {
     assert!(succeeded.load(Ordering::SeqCst), "incorrect operation name");
 }
 
+#[tokio::test]
+async fn parallel_entity_fetches_are_batched() {
+    let fetch = |path: &str, operation_name: &str| {
+        serde_json::json!({
+            "kind": "Flatten",
+            "path": [path, "@"],
+            "node": {
+                "kind": "Fetch",
+                "serviceName": "books",
+                "requires": [{
+                    "kind": "InlineFragment",
+                    "typeCondition": "Book",
+                    "selections": [
+                        { "kind": "Field", "name": "__typename" },
+                        { "kind": "Field", "name": "isbn" }
+                    ]
+                }],
+                "variableUsages": [],
+                "operation": format!("query {operation_name}($representations:[_Any!]!){{_entities(representations:$representations){{...on Book{{title}}}}}}"),
+                "operationName": operation_name,
+                "operationKind": "query"
+            }
+        })
+    };
+    let query_plan: QueryPlan = QueryPlan {
+        root: serde_json::from_value(serde_json::json!({
+            "kind": "Parallel",
+            "nodes": [
+                fetch("topProducts", "TopBooks__books__1"),
+                fetch("books", "TopBooks__books__2"),
+            ]
+        }))
+        .unwrap(),
+        formatted_query_plan: Default::default(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    let mut mock_books_service = plugin::test::MockSubgraphService::new();
+    mock_books_service.expect_clone().return_once(|| {
+        let mut mock_books_service = plugin::test::MockSubgraphService::new();
+        mock_books_service
+            .expect_call()
+            .times(1)
+            .withf(|request| {
+                let body = request.subgraph_request.body();
+                body.operation_name.as_deref() == Some("TopBooks__books__1")
+                    && body.variables.get("representations")
+                        == Some(&json!([
+                            { "__typename": "Book", "isbn": "1" },
+                            { "__typename": "Book", "isbn": "2" },
+                            { "__typename": "Book", "isbn": "3" }
+                        ]))
+            })
+            .returning(|_| {
+                Ok(SubgraphResponse::fake_builder()
+                    .data(json!({ "_entities": [
+                        { "title": "one" },
+                        { "title": "two" },
+                        { "title": "three" }
+                    ] }))
+                    .build())
+            });
+        mock_books_service
+    });
+
+    let (sender, _) = tokio::sync::mpsc::channel(10);
+    let sf = Arc::new(SubgraphServiceFactory {
+        services: Arc::new(HashMap::from([(
+            "books".into(),
+            Arc::new(mock_books_service) as Arc<dyn MakeSubgraphService>,
+        )])),
+        plugins: Default::default(),
+    });
+
+    let response = query_plan
+        .execute(
+            &Context::new(),
+            &sf,
+            &Default::default(),
+            &Arc::new(Schema::parse(test_schema!(), &Default::default()).unwrap()),
+            &Default::default(),
+            sender,
+            None,
+            &None,
+            &Arc::new(EntityBatching {
+                enabled: true,
+                ..Default::default()
+            }),
+            false,
+            Some(json!({
+                "topProducts": [
+                    { "__typename": "Book", "isbn": "1" },
+                    { "__typename": "Book", "isbn": "2" }
+                ],
+                "books": [
+                    { "__typename": "Book", "isbn": "2" },
+                    { "__typename": "Book", "isbn": "3" }
+                ]
+            })),
+        )
+        .await;
+
+    assert!(response.errors.is_empty());
+    assert_eq!(
+        response.data,
+        Some(json!({
+            "topProducts": [{ "title": "one" }, { "title": "two" }],
+            "books": [{ "title": "two" }, { "title": "three" }]
+        }))
+    );
+}
+
+#[tokio::test]
+async fn batched_entity_fetch_errors_and_timings_are_reported_for_each_fetch() {
+    let fetch = |path: &str, operation_name: &str| {
+        serde_json::json!({
+            "kind": "Flatten",
+            "path": [path, "@"],
+            "node": {
+                "kind": "Fetch",
+                "serviceName": "books",
+                "requires": [{
+                    "kind": "InlineFragment",
+                    "typeCondition": "Book",
+                    "selections": [
+                        { "kind": "Field", "name": "__typename" },
+                        { "kind": "Field", "name": "isbn" }
+                    ]
+                }],
+                "variableUsages": [],
+                "operation": format!("query {operation_name}($representations:[_Any!]!){{_entities(representations:$representations){{...on Book{{title}}}}}}"),
+                "operationName": operation_name,
+                "operationKind": "query"
+            }
+        })
+    };
+    let query_plan: QueryPlan = QueryPlan {
+        root: serde_json::from_value(serde_json::json!({
+            "kind": "Parallel",
+            "nodes": [
+                fetch("topProducts", "TopBooks__books__1"),
+                fetch("books", "TopBooks__books__2"),
+            ]
+        }))
+        .unwrap(),
+        formatted_query_plan: Default::default(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    let mut mock_books_service = plugin::test::MockSubgraphService::new();
+    mock_books_service.expect_clone().return_once(|| {
+        let mut mock_books_service = plugin::test::MockSubgraphService::new();
+        mock_books_service.expect_call().times(1).returning(|_| {
+            Ok(SubgraphResponse::fake_builder()
+                .error(
+                    graphql::Error::builder()
+                        .message("books are unavailable")
+                        .extension_code("UNAVAILABLE")
+                        .build(),
+                )
+                .build())
+        });
+        mock_books_service
+    });
+
+    let (sender, _) = tokio::sync::mpsc::channel(10);
+    let sf = Arc::new(SubgraphServiceFactory {
+        services: Arc::new(HashMap::from([(
+            "books".into(),
+            Arc::new(mock_books_service) as Arc<dyn MakeSubgraphService>,
+        )])),
+        plugins: Default::default(),
+    });
+
+    let context = Context::new();
+    context.insert_json_value(crate::query_planner::FETCH_TIMINGS_CONTEXT_KEY, json!([]));
+    let response = query_plan
+        .execute(
+            &context,
+            &sf,
+            &Default::default(),
+            &Arc::new(Schema::parse(test_schema!(), &Default::default()).unwrap()),
+            &Default::default(),
+            sender,
+            None,
+            &None,
+            &Arc::new(EntityBatching {
+                enabled: true,
+                ..Default::default()
+            }),
+            false,
+            Some(json!({
+                "topProducts": [{ "__typename": "Book", "isbn": "1" }],
+                "books": [{ "__typename": "Book", "isbn": "2" }]
+            })),
+        )
+        .await;
+
+    let error_paths: Vec<_> = response
+        .errors
+        .iter()
+        .map(|error| error.path.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(error_paths, ["/topProducts/@", "/books/@"]);
+
+    let timings = context
+        .get_json_value(crate::query_planner::FETCH_TIMINGS_CONTEXT_KEY)
+        .unwrap();
+    let timing_paths: Vec<_> = timings
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|timing| timing["path"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(timing_paths, ["/topProducts/@", "/books/@"]);
+}
+
 #[tokio::test]
 async fn fetch_makes_post_requests() {
     let query_plan: QueryPlan = QueryPlan {
@@ -246,6 +483,8 @@ async fn fetch_makes_post_requests() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -401,6 +640,8 @@ async fn defer() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -511,6 +752,8 @@ async fn defer_if_condition() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -534,6 +777,8 @@ async fn defer_if_condition() {
             default_sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -566,6 +811,8 @@ async fn defer_if_condition() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
@@ -663,11 +910,510 @@ async fn dependent_mutations() {
             sender,
             None,
             &None,
+            &Default::default(),
+            false,
             None,
         )
         .await;
 }
 
+fn ordered_mutation_service(
+    name: &'static str,
+    calls: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    responses: Vec<graphql::Response>,
+) -> plugin::test::MockSubgraphService {
+    let responses = Arc::new(std::sync::Mutex::new(responses.into_iter()));
+    let mut mock_service = plugin::test::MockSubgraphService::new();
+    mock_service.expect_clone().returning(move || {
+        let calls = calls.clone();
+        let responses = responses.clone();
+        let mut mock_service = plugin::test::MockSubgraphService::new();
+        mock_service.expect_call().times(1).returning(move |_| {
+            calls.lock().unwrap().push(name);
+            let response = responses.lock().unwrap().next().unwrap();
+            Ok(SubgraphResponse::fake_builder()
+                .data(response.data.unwrap_or_default())
+                .errors(response.errors)
+                .build())
+        });
+        mock_service
+    });
+    mock_service
+}
+
+async fn execute_mutation_plan(
+    root: &str,
+    a_responses: Vec<graphql::Response>,
+    b_responses: Vec<graphql::Response>,
+) -> (Vec<&'static str>, graphql::Response) {
+    let schema = include_str!("../testdata/a_b_supergraph.graphql");
+
+    let query_plan: QueryPlan = QueryPlan {
+        formatted_query_plan: Default::default(),
+        root: serde_json::from_str(root).unwrap(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sf = Arc::new(SubgraphServiceFactory {
+        services: Arc::new(HashMap::from([
+            (
+                "A".into(),
+                Arc::new(ordered_mutation_service("A", calls.clone(), a_responses))
+                    as Arc<dyn MakeSubgraphService>,
+            ),
+            (
+                "B".into(),
+                Arc::new(ordered_mutation_service("B", calls.clone(), b_responses))
+                    as Arc<dyn MakeSubgraphService>,
+            ),
+        ])),
+        plugins: Default::default(),
+    });
+
+    let (sender, _) = tokio::sync::mpsc::channel(10);
+    let response = query_plan
+        .execute(
+            &Context::new(),
+            &sf,
+            &Default::default(),
+            &Arc::new(Schema::parse(schema, &Default::default()).unwrap()),
+            &Default::default(),
+            sender,
+            None,
+            &None,
+            &Default::default(),
+            false,
+            None,
+        )
+        .await;
+
+    let calls = calls.lock().unwrap().clone();
+    (calls, response)
+}
+
+#[tokio::test]
+async fn mutations_execute_serially_with_interleaved_failures() {
+    // generated from:
+    // mutation {
+    //   first: mutationB
+    //   second: mutationA { __typename }
+    //   third: mutationB
+    // }
+    let root = r#"{
+        "kind": "Sequence",
+        "nodes": [
+            {
+                "kind": "Fetch",
+                "serviceName": "B",
+                "variableUsages": [],
+                "operation": "mutation{first:mutationB}",
+                "operationKind": "mutation"
+            },
+            {
+                "kind": "Fetch",
+                "serviceName": "A",
+                "variableUsages": [],
+                "operation": "mutation{second:mutationA{__typename}}",
+                "operationKind": "mutation"
+            },
+            {
+                "kind": "Fetch",
+                "serviceName": "B",
+                "variableUsages": [],
+                "operation": "mutation{third:mutationB}",
+                "operationKind": "mutation"
+            }
+        ]
+    }"#;
+
+    let (calls, response) = execute_mutation_plan(
+        root,
+        vec![graphql::Response::builder()
+            .data(json!({ "second": null }))
+            .error(
+                graphql::Error::builder()
+                    .message("second mutation failed")
+                    .extension_code("MUTATION_FAILED")
+                    .build(),
+            )
+            .build()],
+        vec![
+            graphql::Response::builder()
+                .data(json!({ "first": true }))
+                .build(),
+            graphql::Response::builder()
+                .data(json!({ "third": true }))
+                .build(),
+        ],
+    )
+    .await;
+
+    // a failing root field does not prevent the following ones from executing
+    assert_eq!(calls, vec!["B", "A", "B"]);
+    assert_eq!(
+        response.data,
+        Some(json!({ "first": true, "second": null, "third": true }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, "second mutation failed");
+}
+
+#[tokio::test]
+async fn mutations_in_parallel_nodes_execute_serially() {
+    let root = r#"{
+        "kind": "Parallel",
+        "nodes": [
+            {
+                "kind": "Fetch",
+                "serviceName": "A",
+                "variableUsages": [],
+                "operation": "mutation{first:mutationA{__typename}}",
+                "operationKind": "mutation"
+            },
+            {
+                "kind": "Fetch",
+                "serviceName": "B",
+                "variableUsages": [],
+                "operation": "mutation{second:mutationB}",
+                "operationKind": "mutation"
+            }
+        ]
+    }"#;
+
+    let (calls, response) = execute_mutation_plan(
+        root,
+        vec![graphql::Response::builder()
+            .error(
+                graphql::Error::builder()
+                    .message("first mutation failed")
+                    .extension_code("MUTATION_FAILED")
+                    .build(),
+            )
+            .build()],
+        vec![graphql::Response::builder()
+            .data(json!({ "second": true }))
+            .build()],
+    )
+    .await;
+
+    assert_eq!(calls, vec!["A", "B"]);
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, "first mutation failed");
+}
+
+/// Executes a parallel node of two mutation fetches against subgraphs that take some time to
+/// respond, and returns the time window of each subgraph call, in the order they started
+async fn mutation_call_windows(
+    parallel_mutations: bool,
+) -> Vec<(&'static str, std::time::Instant, std::time::Instant)> {
+    let schema = include_str!("../testdata/a_b_supergraph.graphql");
+    let root = r#"{
+        "kind": "Parallel",
+        "nodes": [
+            {
+                "kind": "Fetch",
+                "serviceName": "A",
+                "variableUsages": [],
+                "operation": "mutation{first:mutationA{__typename}}",
+                "operationKind": "mutation"
+            },
+            {
+                "kind": "Fetch",
+                "serviceName": "B",
+                "variableUsages": [],
+                "operation": "mutation{second:mutationB}",
+                "operationKind": "mutation"
+            }
+        ]
+    }"#;
+
+    let query_plan: QueryPlan = QueryPlan {
+        formatted_query_plan: Default::default(),
+        root: serde_json::from_str(root).unwrap(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    let windows = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let slow_service = |name: &'static str| {
+        let windows = windows.clone();
+        tower::service_fn(move |_request: crate::services::SubgraphRequest| {
+            let windows = windows.clone();
+            async move {
+                let start = std::time::Instant::now();
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                windows
+                    .lock()
+                    .unwrap()
+                    .push((name, start, std::time::Instant::now()));
+                Ok::<_, tower::BoxError>(SubgraphResponse::fake_builder().build())
+            }
+        })
+    };
+    let sf = Arc::new(SubgraphServiceFactory {
+        services: Arc::new(HashMap::from([
+            (
+                "A".into(),
+                Arc::new(slow_service("A")) as Arc<dyn MakeSubgraphService>,
+            ),
+            (
+                "B".into(),
+                Arc::new(slow_service("B")) as Arc<dyn MakeSubgraphService>,
+            ),
+        ])),
+        plugins: Default::default(),
+    });
+
+    let (sender, _) = tokio::sync::mpsc::channel(10);
+    query_plan
+        .execute(
+            &Context::new(),
+            &sf,
+            &Default::default(),
+            &Arc::new(Schema::parse(schema, &Default::default()).unwrap()),
+            &Default::default(),
+            sender,
+            None,
+            &None,
+            &Default::default(),
+            parallel_mutations,
+            None,
+        )
+        .await;
+
+    let mut windows = windows.lock().unwrap().clone();
+    windows.sort_by_key(|(_, start, _)| *start);
+    windows
+}
+
+#[tokio::test]
+async fn mutation_calls_do_not_overlap() {
+    let windows = mutation_call_windows(false).await;
+
+    assert_eq!(
+        windows.iter().map(|(name, _, _)| *name).collect::<Vec<_>>(),
+        vec!["A", "B"]
+    );
+    // each mutation starts after the previous one ended
+    let (_, _, first_end) = windows[0];
+    let (_, second_start, _) = windows[1];
+    assert!(first_end <= second_start);
+}
+
+#[tokio::test]
+async fn mutation_calls_overlap_when_parallel_mutations_are_enabled() {
+    let windows = mutation_call_windows(true).await;
+
+    assert_eq!(windows.len(), 2);
+    let (_, _, first_end) = windows[0];
+    let (_, second_start, _) = windows[1];
+    assert!(second_start < first_end);
+}
+
+#[tokio::test]
+async fn fetches_stop_at_the_request_deadline() {
+    let schema = include_str!("../testdata/a_b_supergraph.graphql");
+    let root = r#"{
+        "kind": "Sequence",
+        "nodes": [
+            {
+                "kind": "Fetch",
+                "serviceName": "A",
+                "variableUsages": [],
+                "operation": "mutation{first:mutationA{__typename}}",
+                "operationKind": "mutation"
+            },
+            {
+                "kind": "Fetch",
+                "serviceName": "B",
+                "variableUsages": [],
+                "operation": "mutation{second:mutationB}",
+                "operationKind": "mutation"
+            }
+        ]
+    }"#;
+
+    let query_plan: QueryPlan = QueryPlan {
+        formatted_query_plan: Default::default(),
+        root: serde_json::from_str(root).unwrap(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    let fast_service = tower::service_fn(|_request: crate::services::SubgraphRequest| async {
+        Ok::<_, tower::BoxError>(
+            SubgraphResponse::fake_builder()
+                .data(json!({ "first": { "__typename": "Mutation" } }))
+                .build(),
+        )
+    });
+    let slow_service = tower::service_fn(|_request: crate::services::SubgraphRequest| async {
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+        Ok::<_, tower::BoxError>(
+            SubgraphResponse::fake_builder()
+                .data(json!({ "second": true }))
+                .build(),
+        )
+    });
+    let sf = Arc::new(SubgraphServiceFactory {
+        services: Arc::new(HashMap::from([
+            (
+                "A".into(),
+                Arc::new(fast_service) as Arc<dyn MakeSubgraphService>,
+            ),
+            (
+                "B".into(),
+                Arc::new(slow_service) as Arc<dyn MakeSubgraphService>,
+            ),
+        ])),
+        plugins: Default::default(),
+    });
+
+    let context = Context::new();
+    RequestDeadline::new(std::time::Duration::from_millis(100)).insert(&context);
+    let (sender, _) = tokio::sync::mpsc::channel(10);
+    let response = query_plan
+        .execute(
+            &context,
+            &sf,
+            &Default::default(),
+            &Arc::new(Schema::parse(schema, &Default::default()).unwrap()),
+            &Default::default(),
+            sender,
+            None,
+            &None,
+            &Default::default(),
+            false,
+            None,
+        )
+        .await;
+
+    // The data of the fetch that completed is kept, and the slow one gets a timeout error
+    assert_eq!(
+        response.data,
+        Some(json!({ "first": { "__typename": "Mutation" } }))
+    );
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].extensions.get("code").unwrap(),
+        "REQUEST_TIMEOUT"
+    );
+    assert_eq!(response.errors[0].extensions.get("service").unwrap(), "B");
+}
+
+#[tokio::test]
+async fn skipped_selections_are_not_fetched() {
+    let schema = include_str!("../testdata/a_b_supergraph.graphql");
+
+    let query_plan: QueryPlan = QueryPlan {
+        // generated from:
+        // query($include: Boolean!) {
+        //   query @include(if: $include)
+        // }
+        formatted_query_plan: Default::default(),
+        root: serde_json::from_str(
+            r#"{
+                "kind": "Condition",
+                "condition": "include",
+                "ifClause": {
+                    "kind": "Fetch",
+                    "serviceName": "A",
+                    "variableUsages": [],
+                    "operation": "{query}",
+                    "operationKind": "query"
+                }
+            }"#,
+        )
+        .unwrap(),
+        usage_reporting: UsageReporting {
+            stats_report_key: "this is a test report key".to_string(),
+            referenced_fields_by_type: Default::default(),
+        }
+        .into(),
+        query: Arc::new(Query::empty()),
+        query_metrics: Default::default(),
+        estimated_size: Default::default(),
+    };
+
+    for (include, expected_calls) in [(false, 0), (true, 1)] {
+        let mut mock_a_service = plugin::test::MockSubgraphService::new();
+        mock_a_service
+            .expect_clone()
+            .times(expected_calls)
+            .returning(|| {
+                let mut mock_a_service = plugin::test::MockSubgraphService::new();
+                mock_a_service.expect_call().times(1).returning(|_| {
+                    Ok(SubgraphResponse::fake_builder()
+                        .data(json!({ "query": true }))
+                        .build())
+                });
+                mock_a_service
+            });
+
+        let sf = Arc::new(SubgraphServiceFactory {
+            services: Arc::new(HashMap::from([(
+                "A".into(),
+                Arc::new(mock_a_service) as Arc<dyn MakeSubgraphService>,
+            )])),
+            plugins: Default::default(),
+        });
+
+        let supergraph_request = Arc::new(
+            http::Request::builder()
+                .body(
+                    graphql::Request::builder()
+                        .variable("include", include)
+                        .build(),
+                )
+                .unwrap(),
+        );
+
+        let (sender, _) = tokio::sync::mpsc::channel(10);
+        let response = query_plan
+            .execute(
+                &Context::new(),
+                &sf,
+                &supergraph_request,
+                &Arc::new(Schema::parse(schema, &Default::default()).unwrap()),
+                &Default::default(),
+                sender,
+                None,
+                &None,
+                &Default::default(),
+                false,
+                None,
+            )
+            .await;
+
+        if include {
+            assert_eq!(response.data, Some(json!({ "query": true })));
+        } else {
+            assert_eq!(response.data, Some(json!(null)));
+        }
+        assert!(response.errors.is_empty());
+    }
+}
+
 #[tokio::test]
 async fn alias_renaming() {
     let schema = r#"schema
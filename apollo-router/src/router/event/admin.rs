@@ -0,0 +1,439 @@
+//! Runtime controls of the admin API
+//!
+//! The admin API does not act on the router directly: each command is sent to the state machine
+//! as an [`Event`], and applied in order with the configuration, schema and license updates.
+//! Only `GET /info` is answered directly, with the [`RouterInfo`] of the running router, along
+//! with the `/query_graph` debugging routes which export the query graph of the current schema.
+
+use std::sync::Arc;
+
+use apollo_compiler::ExecutableDocument;
+use apollo_federation::query_graph::build_federated_query_graph;
+use apollo_federation::query_graph::output;
+use futures::prelude::*;
+use http::header::AUTHORIZATION;
+use http::header::CONTENT_TYPE;
+use http::request::Parts;
+use http::Method;
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tower::service_fn;
+use tower::BoxError;
+use tower::ServiceExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::configuration::admin::Admin;
+use crate::plugins::authentication::constant_time_eq;
+use crate::router::Event;
+use crate::router::RouterInfo;
+use crate::services::router;
+use crate::services::router::body::get_body_bytes;
+use crate::services::router::Body;
+use crate::spec::Schema;
+use crate::Endpoint;
+
+/// Sends the commands of the admin API to the state machine
+#[derive(Clone)]
+pub(crate) struct AdminSource {
+    sender: mpsc::UnboundedSender<Event>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogLevelCommand {
+    level: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToggleCommand {
+    enabled: bool,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    #[default]
+    Dot,
+    Json,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryGraphParams {
+    #[serde(default)]
+    format: GraphFormat,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct TraversalCommand {
+    query: String,
+    operation_name: Option<String>,
+    #[serde(default)]
+    format: GraphFormat,
+}
+
+impl AdminSource {
+    pub(crate) fn new() -> (Self, impl Stream<Item = Event>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, UnboundedReceiverStream::new(receiver))
+    }
+
+    /// The endpoint of the admin API, with the commands under the configured path
+    pub(crate) fn endpoint(
+        &self,
+        config: &Admin,
+        info: RouterInfo,
+        schema: Arc<Schema>,
+    ) -> Endpoint {
+        let sender = self.sender.clone();
+        let prefix = config.path.clone();
+        let authorization = format!("Bearer {}", config.api_key.as_deref().unwrap_or_default());
+        let info = Arc::new(info);
+        Endpoint::from_router_service(
+            format!("{prefix}/*command"),
+            service_fn(move |request: router::Request| {
+                let sender = sender.clone();
+                let prefix = prefix.clone();
+                let authorization = authorization.clone();
+                let info = info.clone();
+                let schema = schema.clone();
+                async move {
+                    let (parts, body) = request.router_request.into_parts();
+                    let path = parts.uri.path().strip_prefix(prefix.as_str());
+                    let mut content_type = "application/json";
+                    let (status, body) = if !authorized(&parts, &authorization) {
+                        (
+                            StatusCode::UNAUTHORIZED,
+                            json!({ "status": "invalid API key" }).to_string(),
+                        )
+                    } else if parts.method == Method::GET && path == Some("/info") {
+                        (StatusCode::OK, serde_json::to_string(&*info)?)
+                    } else if path.is_some_and(|path| path.starts_with("/query_graph")) {
+                        match query_graph(&parts, body, &prefix, schema).await {
+                            Ok((graph_content_type, graph)) => {
+                                content_type = graph_content_type;
+                                (StatusCode::OK, graph)
+                            }
+                            Err((status, message)) => {
+                                (status, json!({ "status": message }).to_string())
+                            }
+                        }
+                    } else {
+                        let (status, message) = match command(&parts, body, &prefix).await {
+                            Ok(event) => {
+                                tracing::info!(command = ?event, "admin command received");
+                                match sender.send(event) {
+                                    Ok(()) => (StatusCode::ACCEPTED, "accepted".to_string()),
+                                    Err(_) => (
+                                        StatusCode::SERVICE_UNAVAILABLE,
+                                        "the router is shutting down".to_string(),
+                                    ),
+                                }
+                            }
+                            Err(error) => error,
+                        };
+                        (status, json!({ "status": message }).to_string())
+                    };
+                    Ok::<_, BoxError>(router::Response {
+                        response: http::Response::builder()
+                            .status(status)
+                            .header(CONTENT_TYPE, content_type)
+                            .body(Body::from(body))?,
+                        context: request.context,
+                    })
+                }
+            })
+            .boxed(),
+        )
+    }
+}
+
+fn authorized(parts: &Parts, authorization: &str) -> bool {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .is_some_and(|header| constant_time_eq(header.as_bytes(), authorization.as_bytes()))
+}
+
+/// Exports the query graph of the current schema, or the traversal of an operation over it.
+///
+/// The graph is built on demand: it is only needed to debug why fields cannot be planned.
+async fn query_graph(
+    parts: &Parts,
+    body: Body,
+    prefix: &str,
+    schema: Arc<Schema>,
+) -> Result<(&'static str, String), (StatusCode, String)> {
+    let path = parts.uri.path().strip_prefix(prefix).unwrap_or_default();
+    let traversal = match (&parts.method, path) {
+        (&Method::GET, "/query_graph") => None,
+        (&Method::POST, "/query_graph/traversal") => {
+            Some(json_body::<TraversalCommand>(body).await?)
+        }
+        (method, path) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("unknown admin command {method} {path}"),
+            ))
+        }
+    };
+    let format = match &traversal {
+        Some(traversal) => traversal.format,
+        None => {
+            serde_urlencoded::from_str::<QueryGraphParams>(parts.uri.query().unwrap_or_default())
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid parameters: {e}")))?
+                .format
+        }
+    };
+
+    let internal_error =
+        |e: &dyn std::fmt::Display| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    tokio::task::spawn_blocking(move || {
+        let supergraph = schema.federation_supergraph();
+        let api_schema = schema.api_schema().0.clone();
+        let graph = build_federated_query_graph(
+            supergraph.schema.clone(),
+            api_schema.clone(),
+            Some(false),
+            Some(true),
+        )
+        .map_err(|e| internal_error(&e))?;
+
+        let Some(traversal) = traversal else {
+            return match format {
+                GraphFormat::Dot => Ok(("text/vnd.graphviz", output::to_dot(&graph))),
+                GraphFormat::Json => Ok((
+                    "application/json",
+                    serde_json::to_string(&output::to_json(&graph))
+                        .map_err(|e| internal_error(&e))?,
+                )),
+            };
+        };
+        let document = ExecutableDocument::parse_and_validate(
+            api_schema.schema(),
+            traversal.query,
+            "query.graphql",
+        )
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.errors.to_string()))?;
+        let operation_traversal =
+            output::traverse_operation(&graph, &document, traversal.operation_name.as_deref())
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        match format {
+            GraphFormat::Dot => Ok((
+                "text/vnd.graphviz",
+                output::to_dot_with_traversal(&graph, &operation_traversal),
+            )),
+            GraphFormat::Json => Ok((
+                "application/json",
+                serde_json::to_string(&operation_traversal).map_err(|e| internal_error(&e))?,
+            )),
+        }
+    })
+    .await
+    .map_err(|e| internal_error(&e))?
+}
+
+/// Converts an admin API request, already authorized by the endpoint, to the event sent to the
+/// state machine
+async fn command(parts: &Parts, body: Body, prefix: &str) -> Result<Event, (StatusCode, String)> {
+    let path = parts.uri.path().strip_prefix(prefix).unwrap_or_default();
+    match (&parts.method, path) {
+        (&Method::PUT, "/log_level") => {
+            let LogLevelCommand { level } = json_body(body).await?;
+            if let Some(level) = &level {
+                EnvFilter::try_new(level).map_err(|e| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!("invalid log level '{level}': {e}"),
+                    )
+                })?;
+            }
+            Ok(Event::SetLogLevel(level))
+        }
+        (&Method::PUT, "/maintenance") => {
+            let ToggleCommand { enabled } = json_body(body).await?;
+            Ok(Event::SetMaintenanceMode(enabled))
+        }
+        (&Method::PUT, "/read_only") => {
+            let ToggleCommand { enabled } = json_body(body).await?;
+            Ok(Event::SetReadOnlyMode(enabled))
+        }
+        (&Method::POST, "/caches/purge") => Ok(Event::PurgeCaches),
+        (&Method::POST, "/schema/fetch") => Ok(Event::FetchSchema),
+        (method, path) => Err((
+            StatusCode::NOT_FOUND,
+            format!("unknown admin command {method} {path}"),
+        )),
+    }
+}
+
+async fn json_body<T: for<'de> Deserialize<'de>>(body: Body) -> Result<T, (StatusCode, String)> {
+    let bytes = get_body_bytes(body)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid body: {e}")))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid body: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn call(
+        endpoint: &Endpoint,
+        method: Method,
+        path: &str,
+        authorization: &str,
+        body: &str,
+    ) -> StatusCode {
+        let request = http::Request::builder()
+            .method(method)
+            .uri(format!("http://127.0.0.1:8089{path}"))
+            .header(AUTHORIZATION, authorization)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let router = endpoint.clone().into_router();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn it_sends_admin_commands_to_the_state_machine() {
+        let (source, events) = AdminSource::new();
+        let config = Admin {
+            enabled: true,
+            api_key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        let configuration = Default::default();
+        let schema = crate::spec::Schema::parse(
+            include_str!("../../testdata/minimal_supergraph.graphql"),
+            &configuration,
+        )
+        .unwrap();
+        let endpoint = source.endpoint(
+            &config,
+            RouterInfo::new(&schema, &configuration),
+            Arc::new(schema),
+        );
+        let key = "Bearer secret";
+
+        assert_eq!(
+            call(&endpoint, Method::GET, "/admin/info", key, "").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(&endpoint, Method::GET, "/admin/info", "Bearer nope", "").await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(&endpoint, Method::GET, "/admin/query_graph", key, "").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::GET,
+                "/admin/query_graph?format=json",
+                key,
+                ""
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::POST,
+                "/admin/query_graph/traversal",
+                key,
+                r#"{"query":"{ me }","format":"json"}"#
+            )
+            .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::POST,
+                "/admin/query_graph/traversal",
+                key,
+                r#"{"query":"{ unknown }"}"#
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::GET,
+                "/admin/query_graph",
+                "Bearer nope",
+                ""
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::POST,
+                "/admin/caches/purge",
+                "Bearer nope",
+                ""
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call(
+                &endpoint,
+                Method::PUT,
+                "/admin/log_level",
+                key,
+                r#"{"level":"a=b=c"}"#
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call(&endpoint, Method::GET, "/admin/unknown", key, "").await,
+            StatusCode::NOT_FOUND
+        );
+
+        let accepted = [
+            (Method::PUT, "/admin/log_level", r#"{"level":"debug"}"#),
+            (Method::PUT, "/admin/log_level", r#"{"level":null}"#),
+            (Method::PUT, "/admin/maintenance", r#"{"enabled":true}"#),
+            (Method::PUT, "/admin/read_only", r#"{"enabled":false}"#),
+            (Method::POST, "/admin/caches/purge", ""),
+            (Method::POST, "/admin/schema/fetch", ""),
+        ];
+        for (method, path, body) in accepted {
+            assert_eq!(
+                call(&endpoint, method, path, key, body).await,
+                StatusCode::ACCEPTED
+            );
+        }
+
+        drop(endpoint);
+        drop(source);
+        let events: Vec<String> = events.map(|event| format!("{event:?}")).collect().await;
+        assert_eq!(
+            events,
+            [
+                "SetLogLevel(Some(\"debug\"))",
+                "SetLogLevel(None)",
+                "SetMaintenanceMode(true)",
+                "SetReadOnlyMode(false)",
+                "PurgeCaches",
+                "FetchSchema",
+            ]
+        );
+    }
+}
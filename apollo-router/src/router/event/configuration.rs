@@ -1,21 +1,30 @@
 use std::path::Path;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 
 use derivative::Derivative;
 use derive_more::Display;
 use derive_more::From;
 use futures::prelude::*;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
 
 use crate::router::Event;
 use crate::router::Event::NoMoreConfiguration;
+use crate::router::Event::Reload;
 use crate::router::Event::UpdateConfiguration;
 use crate::uplink::UplinkConfig;
 use crate::Configuration;
 
 type ConfigurationStream = Pin<Box<dyn Stream<Item = Configuration> + Send>>;
 
+/// The secrets TTL of a configuration, and the configuration its secrets are compared against.
+type SecretsState = (Option<Duration>, Option<serde_json::Value>);
+
 /// The user supplied config. Either a static instance or a stream for hot reloading.
 #[derive(From, Display, Derivative)]
 #[derivative(Debug)]
@@ -89,30 +98,51 @@ impl ConfigurationSource {
                 } else {
                     match ConfigurationSource::read_config(&path) {
                         Ok(mut configuration) => {
+                            let (secrets_sender, secrets_receiver) =
+                                watch::channel(secrets_state(&configuration));
+                            let refresh_secrets = ConfigurationSource::refresh_secrets(
+                                path.clone(),
+                                secrets_receiver,
+                                uplink_config.clone(),
+                            );
                             if watch {
-                                crate::files::watch(&path)
-                                    .filter_map(move |_| {
-                                        let path = path.clone();
-                                        let uplink_config = uplink_config.clone();
-                                        async move {
-                                            match ConfigurationSource::read_config_async(&path)
-                                                .await
-                                            {
-                                                Ok(mut configuration) => {
-                                                    configuration.uplink = uplink_config.clone();
-                                                    Some(UpdateConfiguration(configuration))
-                                                }
-                                                Err(err) => {
-                                                    tracing::error!("{}", err);
-                                                    None
-                                                }
+                                let secrets_sender = Arc::new(secrets_sender);
+                                let watch = crate::files::watch(&path).filter_map(move |_| {
+                                    let path = path.clone();
+                                    let uplink_config = uplink_config.clone();
+                                    let secrets_sender = secrets_sender.clone();
+                                    async move {
+                                        match ConfigurationSource::read_config_async(&path).await {
+                                            Ok(mut configuration) => {
+                                                // Secrets are now refreshed with the TTL of the
+                                                // reloaded configuration
+                                                secrets_sender
+                                                    .send_replace(secrets_state(&configuration));
+                                                configuration.uplink = uplink_config.clone();
+                                                Some(UpdateConfiguration(configuration))
+                                            }
+                                            Err(err) => {
+                                                tracing::error!("{}", err);
+                                                None
                                             }
                                         }
+                                    }
+                                });
+                                let (reload_sender, reload_receiver) = mpsc::channel(1);
+                                let mut tenant_files = TenantFilesWatcher::new(reload_sender);
+                                stream::select(watch, refresh_secrets)
+                                    .inspect(move |event| {
+                                        if let UpdateConfiguration(configuration) = event {
+                                            tenant_files.update(configuration);
+                                        }
                                     })
                                     .boxed()
+                                    .select(ReceiverStream::new(reload_receiver))
+                                    .boxed()
                             } else {
                                 configuration.uplink = uplink_config.clone();
                                 stream::once(future::ready(UpdateConfiguration(configuration)))
+                                    .chain(refresh_secrets)
                                     .boxed()
                             }
                         }
@@ -128,6 +158,60 @@ impl ConfigurationSource {
         .boxed()
     }
 
+    /// Reads the configuration file again each time its secrets expire, and emits it if the
+    /// secrets changed
+    ///
+    /// The state is replaced each time the watched configuration file is reloaded, and the stream
+    /// ends once the configuration has no secrets TTL and cannot be reloaded anymore.
+    fn refresh_secrets(
+        path: PathBuf,
+        reloads: watch::Receiver<SecretsState>,
+        uplink_config: Option<UplinkConfig>,
+    ) -> impl Stream<Item = Event> {
+        let state = reloads.borrow().clone();
+        stream::unfold(
+            (state, reloads),
+            move |((secrets_ttl, previous), mut reloads)| {
+                let path = path.clone();
+                let uplink_config = uplink_config.clone();
+                async move {
+                    let Some(secrets_ttl) = secrets_ttl else {
+                        // Wait for a reloaded configuration, that may have a secrets TTL
+                        reloads.changed().await.ok()?;
+                        let state = reloads.borrow_and_update().clone();
+                        return Some((None, (state, reloads)));
+                    };
+                    tokio::select! {
+                        _ = tokio::time::sleep(secrets_ttl) => {}
+                        Ok(()) = reloads.changed() => {
+                            let state = reloads.borrow_and_update().clone();
+                            return Some((None, (state, reloads)));
+                        }
+                    }
+                    match ConfigurationSource::read_config_async(&path).await {
+                        Ok(mut configuration) => {
+                            let state = secrets_state(&configuration);
+                            if configuration.validated_yaml == previous {
+                                return Some((None, (state, reloads)));
+                            }
+                            tracing::info!("reloading the configuration as its secrets changed");
+                            configuration.uplink = uplink_config;
+                            Some((Some(UpdateConfiguration(configuration)), (state, reloads)))
+                        }
+                        Err(err) => {
+                            tracing::error!(
+                                "failed to refresh the secrets of the configuration: {}",
+                                err
+                            );
+                            Some((None, ((Some(secrets_ttl), previous), reloads)))
+                        }
+                    }
+                }
+            },
+        )
+        .filter_map(future::ready)
+    }
+
     fn read_config(path: &Path) -> Result<Configuration, ReadConfigError> {
         let config = std::fs::read_to_string(path)?;
         config.parse().map_err(ReadConfigError::Validation)
@@ -138,6 +222,82 @@ impl ConfigurationSource {
     }
 }
 
+/// Watches the schema and configuration files of the tenants of the latest configuration, and
+/// sends a reload event when one of them changes
+struct TenantFilesWatcher {
+    paths: Vec<PathBuf>,
+    tasks: Vec<JoinHandle<()>>,
+    sender: mpsc::Sender<Event>,
+}
+
+impl TenantFilesWatcher {
+    fn new(sender: mpsc::Sender<Event>) -> Self {
+        Self {
+            paths: Vec::new(),
+            tasks: Vec::new(),
+            sender,
+        }
+    }
+
+    fn update(&mut self, configuration: &Configuration) {
+        let paths: Vec<PathBuf> = configuration
+            .experimental_tenants
+            .iter()
+            .flat_map(|tenant| {
+                std::iter::once(tenant.supergraph_path.clone())
+                    .chain(tenant.configuration_path.clone())
+            })
+            .collect();
+        if paths == self.paths {
+            return;
+        }
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        for path in &paths {
+            if !path.exists() {
+                // The router reports the missing file when it loads the tenant
+                tracing::error!(
+                    "cannot watch the tenant file at path '{}' as it does not exist",
+                    path.display()
+                );
+                continue;
+            }
+            let sender = self.sender.clone();
+            // The first event of the watch is emitted on creation, and not on a change
+            let mut watch = crate::files::watch(path).skip(1).boxed();
+            let path = path.clone();
+            self.tasks.push(tokio::spawn(async move {
+                while watch.next().await.is_some() {
+                    tracing::info!(
+                        "reloading the router as the tenant file at path '{}' changed",
+                        path.display()
+                    );
+                    if sender.send(Reload).await.is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+        self.paths = paths;
+    }
+}
+
+impl Drop for TenantFilesWatcher {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+fn secrets_state(configuration: &Configuration) -> SecretsState {
+    (
+        configuration.secrets_ttl,
+        configuration.validated_yaml.clone(),
+    )
+}
+
 #[derive(From, Display)]
 enum ReadConfigError {
     /// could not read configuration: {0}
@@ -189,6 +349,59 @@ mod tests {
         assert!(event.is_none() || matches!(event, Some((Some(NoMoreConfiguration), _))));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tenant_files_watching() {
+        let (supergraph_path, mut supergraph_file) = create_temp_file();
+        write_and_flush(
+            &mut supergraph_file,
+            include_str!("../../testdata/minimal_supergraph.graphql"),
+        )
+        .await;
+        let (path, mut file) = create_temp_file();
+        let contents = format!(
+            "experimental_tenants:\n  - name: acme\n    match:\n      header:\n        name: x-tenant\n        value: acme\n    supergraph_path: {}\n",
+            supergraph_path.display()
+        );
+        write_and_flush(&mut file, &contents).await;
+        let mut stream = ConfigurationSource::File {
+            path,
+            watch: true,
+            delay: None,
+        }
+        .into_stream(Some(UplinkConfig::default()))
+        .boxed();
+
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            UpdateConfiguration(_)
+        ));
+
+        // Changing the schema of a tenant reloads the router
+        write_and_flush(&mut supergraph_file, "type Query { changed: String }").await;
+        assert!(matches!(stream.next().await.unwrap(), Reload));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn secrets_refreshed_with_the_ttl_of_reloaded_configurations() {
+        let (path, mut file) = create_temp_file();
+        let contents = include_str!("../../testdata/supergraph_config.router.yaml");
+        write_and_flush(&mut file, contents).await;
+        let (sender, receiver) = watch::channel((None, None));
+        let mut stream = ConfigurationSource::refresh_secrets(path, receiver, None).boxed();
+
+        // Nothing is refreshed until a reloaded configuration has a secrets TTL
+        assert!(stream.next().now_or_never().is_none());
+        sender.send_replace((Some(Duration::from_millis(10)), None));
+        assert!(matches!(
+            stream.next().await.unwrap(),
+            UpdateConfiguration(_)
+        ));
+
+        // The refreshed configuration has no secrets TTL, and cannot be reloaded anymore
+        drop(sender);
+        assert!(stream.next().await.is_none());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn config_by_file_missing() {
         let mut stream = ConfigurationSource::File {
@@ -1,22 +1,30 @@
+mod admin;
 mod configuration;
 mod license;
 mod reload;
 mod schema;
 mod shutdown;
+mod subgraphs;
 
 use std::fmt::Debug;
 use std::fmt::Formatter;
 
+pub(crate) use admin::AdminSource;
 pub use configuration::ConfigurationSource;
 pub use license::LicenseSource;
 pub(crate) use reload::ReloadSource;
 pub use schema::SchemaSource;
 pub use shutdown::ShutdownSource;
 
+use self::Event::FetchSchema;
 use self::Event::NoMoreConfiguration;
 use self::Event::NoMoreLicense;
 use self::Event::NoMoreSchema;
+use self::Event::PurgeCaches;
 use self::Event::Reload;
+use self::Event::SetLogLevel;
+use self::Event::SetMaintenanceMode;
+use self::Event::SetReadOnlyMode;
 use self::Event::Shutdown;
 use self::Event::UpdateConfiguration;
 use self::Event::UpdateLicense;
@@ -48,6 +56,21 @@ pub(crate) enum Event {
     /// Artificial hot reload for chaos testing
     Reload,
 
+    /// Override the log level, or restore the configured one.
+    SetLogLevel(Option<String>),
+
+    /// Enable or disable maintenance mode.
+    SetMaintenanceMode(bool),
+
+    /// Enable or disable read-only mode.
+    SetReadOnlyMode(bool),
+
+    /// Purge the in-memory caches of the running router.
+    PurgeCaches,
+
+    /// Fetch the schema from uplink without waiting for the next poll.
+    FetchSchema,
+
     /// The server should gracefully shutdown.
     Shutdown,
 }
@@ -76,6 +99,21 @@ impl Debug for Event {
             Reload => {
                 write!(f, "ForcedHotReload")
             }
+            SetLogLevel(level) => {
+                write!(f, "SetLogLevel({level:?})")
+            }
+            SetMaintenanceMode(enabled) => {
+                write!(f, "SetMaintenanceMode({enabled})")
+            }
+            SetReadOnlyMode(enabled) => {
+                write!(f, "SetReadOnlyMode({enabled})")
+            }
+            PurgeCaches => {
+                write!(f, "PurgeCaches")
+            }
+            FetchSchema => {
+                write!(f, "FetchSchema")
+            }
             Shutdown => {
                 write!(f, "Shutdown")
             }
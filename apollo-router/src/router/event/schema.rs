@@ -50,6 +50,16 @@ pub enum SchemaSource {
     #[display(fmt = "Registry")]
     Registry(UplinkConfig),
 
+    /// A directory of subgraph schemas, composed by the router.
+    #[display(fmt = "Subgraphs")]
+    Subgraphs {
+        /// The directory of the subgraph schemas and of their `supergraph.yaml` routing config.
+        path: PathBuf,
+
+        /// `true` to watch the files for changes and compose them again.
+        watch: bool,
+    },
+
     /// A list of URLs to fetch the schema from.
     #[display(fmt = "URLs")]
     URLs {
@@ -158,6 +168,9 @@ impl SchemaSource {
                     })
                     .boxed()
             }
+            SchemaSource::Subgraphs { path, watch } => {
+                super::subgraphs::stream(path, watch).boxed()
+            }
             SchemaSource::URLs {
                 urls,
                 watch,
@@ -0,0 +1,464 @@
+//! Local composition of subgraph schemas, for development
+//!
+//! A directory holds the SDL files of the subgraphs and a `supergraph.yaml` routing config,
+//! in the format used by `rover dev`:
+//!
+//! ```yaml
+//! subgraphs:
+//!   products:
+//!     routing_url: http://localhost:4001/graphql
+//!     schema:
+//!       file: ./products.graphql
+//!   reviews:
+//!     routing_url: http://localhost:4002/graphql
+//!     schema:
+//!       subgraph_url: http://localhost:4002/graphql
+//! ```
+//!
+//! The schema file of a subgraph defaults to `<name>.graphql` in the directory. With a
+//! `subgraph_url`, the schema is fetched from the running subgraph with the `_service { sdl }`
+//! query, and fetched again every `introspection_interval`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use apollo_federation::subgraph::Subgraph;
+use apollo_federation::Supergraph;
+use futures::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use tower::BoxError;
+use url::Url;
+
+use crate::router::Event;
+use crate::router::Event::UpdateSchema;
+use crate::uplink::schema::SchemaState;
+
+/// The name of the routing config in the subgraphs directory
+pub(crate) const ROUTING_CONFIG: &str = "supergraph.yaml";
+
+const SERVICE_SDL_QUERY: &str = "query SubgraphIntrospectQuery { _service { sdl } }";
+
+#[derive(Deserialize)]
+struct RoutingConfig {
+    subgraphs: BTreeMap<String, SubgraphConfig>,
+    #[serde(with = "humantime_serde", default = "default_introspection_interval")]
+    introspection_interval: Duration,
+}
+
+fn default_introspection_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+#[derive(Deserialize)]
+struct SubgraphConfig {
+    routing_url: String,
+    schema: Option<SubgraphSchema>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SubgraphSchema {
+    File { file: PathBuf },
+    Introspection { subgraph_url: Url },
+}
+
+/// The schemas composed from a subgraphs directory
+struct Sources {
+    routing_config: PathBuf,
+    introspection_interval: Duration,
+    subgraphs: Vec<(String, String, SubgraphSchema)>,
+}
+
+/// The name, routing URL and SDL of a subgraph
+type SubgraphSdl = (String, String, String);
+
+impl Sources {
+    fn read(dir: &Path) -> Result<Self, BoxError> {
+        let routing_config = dir.join(ROUTING_CONFIG);
+        let config: RoutingConfig = serde_yaml::from_str(
+            &std::fs::read_to_string(&routing_config)
+                .map_err(|e| format!("could not read {}: {e}", routing_config.display()))?,
+        )
+        .map_err(|e| format!("invalid {}: {e}", routing_config.display()))?;
+        let subgraphs = config
+            .subgraphs
+            .into_iter()
+            .map(|(name, subgraph)| {
+                let schema = match subgraph.schema {
+                    Some(SubgraphSchema::File { file }) => SubgraphSchema::File {
+                        file: dir.join(file),
+                    },
+                    Some(schema) => schema,
+                    None => SubgraphSchema::File {
+                        file: dir.join(format!("{name}.graphql")),
+                    },
+                };
+                (name, subgraph.routing_url, schema)
+            })
+            .collect();
+        Ok(Self {
+            routing_config,
+            introspection_interval: config.introspection_interval,
+            subgraphs,
+        })
+    }
+
+    fn files(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.routing_config.as_path()).chain(self.subgraphs.iter().filter_map(
+            |(_, _, schema)| match schema {
+                SubgraphSchema::File { file } => Some(file.as_path()),
+                SubgraphSchema::Introspection { .. } => None,
+            },
+        ))
+    }
+
+    /// The interval between introspections, if a subgraph schema is introspected
+    fn introspection_interval(&self) -> Option<Duration> {
+        self.subgraphs
+            .iter()
+            .any(|(_, _, schema)| matches!(schema, SubgraphSchema::Introspection { .. }))
+            .then_some(self.introspection_interval)
+    }
+
+    /// Reads or fetches the SDL of each subgraph
+    async fn load(&self, client: &reqwest::Client) -> Result<Vec<SubgraphSdl>, BoxError> {
+        let mut subgraphs = Vec::with_capacity(self.subgraphs.len());
+        for (name, routing_url, schema) in &self.subgraphs {
+            let sdl = match schema {
+                SubgraphSchema::File { file } => tokio::fs::read_to_string(file)
+                    .await
+                    .map_err(|e| format!("could not read {}: {e}", file.display()))?,
+                SubgraphSchema::Introspection { subgraph_url } => {
+                    introspect(client, subgraph_url).await.map_err(|e| {
+                        format!("could not introspect subgraph '{name}' at {subgraph_url}: {e}")
+                    })?
+                }
+            };
+            subgraphs.push((name.clone(), routing_url.clone(), sdl));
+        }
+        Ok(subgraphs)
+    }
+}
+
+/// Fetches the SDL of a running subgraph with the `_service { sdl }` query
+async fn introspect(client: &reqwest::Client, subgraph_url: &Url) -> Result<String, BoxError> {
+    let response: Value = client
+        .post(subgraph_url.as_str())
+        .json(&json!({ "query": SERVICE_SDL_QUERY }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    response
+        .pointer("/data/_service/sdl")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+        .ok_or_else(|| format!("invalid response: {response}").into())
+}
+
+/// Composes subgraphs into a supergraph schema
+fn compose(subgraphs: &[SubgraphSdl]) -> Result<String, BoxError> {
+    let subgraphs = subgraphs
+        .iter()
+        .map(|(name, url, sdl)| {
+            Subgraph::parse_and_expand(name, url, sdl)
+                .map_err(|e| BoxError::from(format!("invalid subgraph '{name}': {e}")))
+        })
+        .collect::<Result<Vec<_>, BoxError>>()?;
+    let (supergraph, hints) = Supergraph::compose_with_diagnostics(subgraphs.iter().collect())
+        .map_err(|failure| failure.to_string())?;
+    for hint in hints {
+        tracing::warn!("{hint}");
+    }
+    Ok(supergraph.schema.schema().to_string())
+}
+
+/// Composes the subgraphs of a directory, then composes them again each time one of them
+/// changes if `watch` is set
+///
+/// Composition errors are logged and the router keeps serving the previous supergraph.
+pub(crate) fn stream(dir: PathBuf, watch: bool) -> impl Stream<Item = Event> {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::error!(reason = %err, "failed to build http client");
+            return stream::empty().boxed();
+        }
+    };
+    let composer = Composer {
+        dir,
+        watch,
+        client,
+        first: true,
+        changes: stream::empty().boxed(),
+        introspection_interval: None,
+        subgraphs: Vec::new(),
+    };
+    stream::unfold(composer, |mut composer| async move {
+        if !composer.changed().await {
+            return None;
+        }
+        let event = composer.update().await;
+        Some((event, composer))
+    })
+    .filter_map(future::ready)
+    .boxed()
+}
+
+struct Composer {
+    dir: PathBuf,
+    watch: bool,
+    client: reqwest::Client,
+    first: bool,
+    changes: stream::BoxStream<'static, ()>,
+    introspection_interval: Option<Duration>,
+    /// The subgraphs of the last composition
+    subgraphs: Vec<SubgraphSdl>,
+}
+
+impl Composer {
+    /// Waits until the subgraphs may have changed, returning `false` when there is nothing left
+    /// to watch
+    async fn changed(&mut self) -> bool {
+        if self.first {
+            self.first = false;
+            return true;
+        }
+        if !self.watch {
+            return false;
+        }
+        match self.introspection_interval {
+            Some(interval) => {
+                tokio::select! {
+                    Some(()) = self.changes.next() => {}
+                    _ = tokio::time::sleep(interval) => {}
+                }
+                true
+            }
+            None => self.changes.next().await.is_some(),
+        }
+    }
+
+    async fn update(&mut self) -> Option<Event> {
+        let sources = Sources::read(&self.dir);
+        if self.watch {
+            // The files are watched before they are read, so that no change is missed. The
+            // routing config is read again on each change, so that subgraphs can be added and
+            // removed while the router is running.
+            let files: Vec<PathBuf> = match &sources {
+                Ok(sources) => sources.files().map(Path::to_path_buf).collect(),
+                Err(_) => vec![self.dir.join(ROUTING_CONFIG)],
+            };
+            self.changes = watch_files(files);
+            self.introspection_interval = sources
+                .as_ref()
+                .ok()
+                .and_then(Sources::introspection_interval);
+        }
+
+        let subgraphs = match sources {
+            Ok(sources) => sources.load(&self.client).await,
+            Err(err) => Err(err),
+        };
+        let subgraphs = match subgraphs {
+            Ok(subgraphs) => subgraphs,
+            Err(err) => {
+                tracing::error!(reason = %err, "failed to load subgraph schemas");
+                return None;
+            }
+        };
+        // Introspected schemas are fetched on each interval, but only composed when they change
+        if subgraphs == self.subgraphs {
+            return None;
+        }
+        let composed = compose(&subgraphs);
+        self.subgraphs = subgraphs;
+        match composed {
+            Ok(sdl) => {
+                tracing::info!(
+                    "composed supergraph from the subgraphs in {}",
+                    self.dir.display()
+                );
+                Some(UpdateSchema(SchemaState {
+                    sdl,
+                    launch_id: None,
+                }))
+            }
+            Err(err) => {
+                tracing::error!(reason = %err, "failed to compose supergraph");
+                None
+            }
+        }
+    }
+}
+
+fn watch_files(files: Vec<PathBuf>) -> stream::BoxStream<'static, ()> {
+    // Each watch starts with an event telling to read the file, which is skipped since the
+    // subgraphs are read right after. Files that do not exist yet are watched as well, so that
+    // the subgraphs are composed again once they are created.
+    stream::select_all(
+        files
+            .into_iter()
+            .map(|file| crate::files::watch(&file).skip(1).boxed()),
+    )
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use wiremock::matchers::body_partial_json;
+    use wiremock::matchers::method;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+
+    const PRODUCTS: &str = r#"
+extend schema @link(url: "https://specs.apollo.dev/federation/v2.3", import: ["@key"])
+
+type Query {
+  products: [Product]
+}
+
+type Product @key(fields: "upc") {
+  upc: String!
+}
+"#;
+
+    const REVIEWS: &str = r#"
+extend schema @link(url: "https://specs.apollo.dev/federation/v2.3", import: ["@key"])
+
+type Product @key(fields: "upc") {
+  upc: String!
+  reviews: [String]
+}
+"#;
+
+    fn write(dir: &Path, name: &str, contents: &str) {
+        let mut file = std::fs::File::create(dir.join(name)).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file.sync_all().unwrap();
+    }
+
+    async fn compose_dir(dir: &Path) -> Result<String, BoxError> {
+        let subgraphs = Sources::read(dir)?.load(&reqwest::Client::new()).await?;
+        compose(&subgraphs)
+    }
+
+    #[tokio::test]
+    async fn it_composes_the_subgraphs_of_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ROUTING_CONFIG,
+            "subgraphs:\n  products:\n    routing_url: http://products\n  reviews:\n    routing_url: http://reviews\n    schema:\n      file: ./schemas/reviews.graphql\n",
+        );
+        write(dir.path(), "products.graphql", PRODUCTS);
+        std::fs::create_dir(dir.path().join("schemas")).unwrap();
+        write(dir.path(), "schemas/reviews.graphql", REVIEWS);
+
+        let sdl = compose_dir(dir.path()).await.unwrap();
+        assert!(sdl.contains(r#"PRODUCTS @join__graph(name: "products", url: "http://products")"#));
+        assert!(sdl.contains("reviews: [String]"));
+        crate::spec::Schema::parse(&sdl, &Default::default()).unwrap();
+
+        write(dir.path(), "products.graphql", "type Query {");
+        let err = compose_dir(dir.path()).await.unwrap_err().to_string();
+        assert!(err.contains("invalid subgraph 'products'"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn it_composes_again_when_a_subgraph_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ROUTING_CONFIG,
+            "subgraphs:\n  products:\n    routing_url: http://products\n",
+        );
+        write(dir.path(), "products.graphql", PRODUCTS);
+
+        let mut stream = stream(dir.path().to_path_buf(), true).boxed();
+        assert!(
+            matches!(stream.next().await.unwrap(), UpdateSchema(schema) if !schema.sdl.contains("reviews"))
+        );
+
+        // The new subgraph is written first, it is not watched until it is in the routing config
+        write(dir.path(), "reviews.graphql", REVIEWS);
+        write(
+            dir.path(),
+            ROUTING_CONFIG,
+            "subgraphs:\n  products:\n    routing_url: http://products\n  reviews:\n    routing_url: http://reviews\n",
+        );
+        assert!(
+            matches!(stream.next().await.unwrap(), UpdateSchema(schema) if schema.sdl.contains("reviews"))
+        );
+    }
+
+    #[tokio::test]
+    async fn it_composes_once_a_missing_file_is_created() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ROUTING_CONFIG,
+            "subgraphs:\n  products:\n    routing_url: http://products\n",
+        );
+
+        // Nothing is composed until the schema of the subgraph is written
+        let mut stream = stream(dir.path().to_path_buf(), true).boxed();
+        assert!(futures::poll!(stream.next()).is_pending());
+
+        write(dir.path(), "products.graphql", PRODUCTS);
+        assert!(matches!(stream.next().await.unwrap(), UpdateSchema(_)));
+    }
+
+    #[tokio::test]
+    async fn it_composes_again_when_an_introspected_subgraph_changes() {
+        let mock_server = MockServer::start().await;
+        let respond_with = |sdl: &str| {
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "data": { "_service": { "sdl": sdl } } }))
+        };
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "query": SERVICE_SDL_QUERY })))
+            .respond_with(respond_with(PRODUCTS))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ROUTING_CONFIG,
+            &format!(
+                "introspection_interval: 100ms\nsubgraphs:\n  products:\n    routing_url: http://products\n    schema:\n      subgraph_url: {}\n",
+                mock_server.uri()
+            ),
+        );
+
+        let mut stream = stream(dir.path().to_path_buf(), true).boxed();
+        assert!(
+            matches!(stream.next().await.unwrap(), UpdateSchema(schema) if !schema.sdl.contains("reviews"))
+        );
+
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(respond_with(
+                &PRODUCTS.replace("upc: String!", "upc: String!\n  reviews: [String]"),
+            ))
+            .mount(&mock_server)
+            .await;
+        assert!(
+            matches!(stream.next().await.unwrap(), UpdateSchema(schema) if schema.sdl.contains("reviews"))
+        );
+    }
+}
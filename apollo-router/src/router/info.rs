@@ -0,0 +1,91 @@
+//! Build and schema information of a running router, for fleet auditing
+
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::spec::Schema;
+use crate::Configuration;
+
+/// What a router instance is built from and currently serves
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RouterInfo {
+    version: &'static str,
+    git_sha: Option<&'static str>,
+    supported_supergraph_specs: Vec<String>,
+    schema_id: String,
+    launch_id: Option<String>,
+    config_hash: Option<String>,
+}
+
+impl RouterInfo {
+    pub(crate) fn new(schema: &Schema, configuration: &Configuration) -> Self {
+        Self {
+            version: std::env!("CARGO_PKG_VERSION"),
+            git_sha: option_env!("APOLLO_ROUTER_GIT_SHA"),
+            supported_supergraph_specs: apollo_federation::router_supported_supergraph_specs()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            schema_id: schema.schema_id.to_string(),
+            launch_id: schema.launch_id.as_deref().cloned(),
+            // The configuration is hashed after expansion, so that routers configured through
+            // different environment variables can be told apart
+            config_hash: configuration
+                .validated_yaml
+                .as_ref()
+                .map(|yaml| hex::encode(Sha256::digest(yaml.to_string()))),
+        }
+    }
+
+    /// Logs the information once a router is started or reloaded
+    pub(crate) fn log(&self) {
+        tracing::info!(
+            version = self.version,
+            git_sha = self.git_sha.unwrap_or("unknown"),
+            schema_id = %self.schema_id,
+            launch_id = self.launch_id.as_deref().unwrap_or_default(),
+            config_hash = self.config_hash.as_deref().unwrap_or_default(),
+            supported_supergraph_specs = %self.supported_supergraph_specs.join(", "),
+            "router info"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_identifies_the_schema_and_configuration() {
+        let configuration = Configuration {
+            validated_yaml: Some(serde_json::json!({"supergraph": {"introspection": true}})),
+            ..Default::default()
+        };
+        let schema = Schema::parse(
+            include_str!("../testdata/minimal_supergraph.graphql"),
+            &configuration,
+        )
+        .unwrap();
+
+        let info = RouterInfo::new(&schema, &configuration);
+        assert_eq!(info.version, std::env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.schema_id, *schema.schema_id);
+        assert!(info
+            .supported_supergraph_specs
+            .contains(&"https://specs.apollo.dev/join/v0.3".to_string()));
+
+        let other = Configuration {
+            validated_yaml: Some(serde_json::json!({"supergraph": {"introspection": false}})),
+            ..configuration.clone()
+        };
+        assert_ne!(
+            info.config_hash,
+            RouterInfo::new(&schema, &other).config_hash
+        );
+        assert!(RouterInfo::new(&schema, &Configuration::default())
+            .config_hash
+            .is_none());
+    }
+}
@@ -3,6 +3,7 @@
 
 mod error;
 mod event;
+mod info;
 
 use std::pin::Pin;
 use std::sync::Arc;
@@ -10,6 +11,7 @@ use std::task::Context;
 use std::task::Poll;
 
 pub use error::ApolloRouterError;
+pub(crate) use event::AdminSource;
 pub use event::ConfigurationSource;
 pub(crate) use event::Event;
 pub use event::LicenseSource;
@@ -23,6 +25,7 @@ use futures::channel::mpsc::SendError;
 use futures::channel::oneshot;
 use futures::prelude::*;
 use futures::FutureExt;
+pub(crate) use info::RouterInfo;
 #[cfg(test)]
 use tokio::sync::Notify;
 use tokio::sync::RwLock;
@@ -132,12 +135,14 @@ impl RouterHttpServer {
         is_telemetry_disabled: Option<bool>,
     ) -> RouterHttpServer {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let (admin, admin_events) = AdminSource::new();
         let event_stream = generate_event_stream(
             shutdown.unwrap_or(ShutdownSource::CtrlC),
             configuration.unwrap_or_default(),
             schema,
             uplink,
             license.unwrap_or_default(),
+            admin_events,
             shutdown_receiver,
         );
         let server_factory = AxumHttpServerFactory::new();
@@ -146,7 +151,8 @@ impl RouterHttpServer {
             is_telemetry_disabled.unwrap_or(false),
             server_factory,
             router_factory,
-        );
+        )
+        .with_admin(admin);
         let listen_addresses = state_machine.listen_addresses.clone();
         let result = spawn(
             async move { state_machine.process_events(event_stream).await }
@@ -231,6 +237,7 @@ fn generate_event_stream(
     schema: SchemaSource,
     uplink_config: Option<UplinkConfig>,
     license: LicenseSource,
+    admin_events: impl Stream<Item = Event> + Send + 'static,
     shutdown_receiver: oneshot::Receiver<()>,
 ) -> impl Stream<Item = Event> {
     let reload_source = ReloadSource::default();
@@ -240,6 +247,7 @@ fn generate_event_stream(
         schema.into_stream().boxed(),
         license.into_stream().boxed(),
         reload_source.clone().into_stream().boxed(),
+        admin_events.boxed(),
         configuration
             .into_stream(uplink_config)
             .map(move |config_event| {
@@ -353,6 +361,7 @@ mod tests {
     use crate::router::Event::UpdateConfiguration;
     use crate::router::Event::UpdateLicense;
     use crate::router::Event::UpdateSchema;
+    use crate::test_harness::ReloadHandle;
     use crate::uplink::license_enforcement::LicenseState;
     use crate::uplink::schema::SchemaState;
     use crate::Configuration;
@@ -380,6 +389,57 @@ mod tests {
         router_handle.shutdown().await.unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reload_handle() {
+        let configuration =
+            Configuration::from_str(include_str!("../testdata/supergraph_config.router.yaml"))
+                .unwrap();
+        let schema = include_str!("../testdata/supergraph.graphql");
+        let (reload, schema_source, configuration_source) =
+            ReloadHandle::new(schema, configuration);
+        let mut router_handle = RouterHttpServer::builder()
+            .schema(schema_source)
+            .configuration(configuration_source)
+            .start();
+        let listen_address = router_handle
+            .listen_address()
+            .await
+            .expect("router failed to start");
+
+        let request = Request::builder()
+            .query("{ __schema { queryType { name } } }")
+            .build();
+        let response = query(&listen_address, &request).await.unwrap();
+        assert!(!response.errors.is_empty());
+
+        reload.reload_configuration(
+            Configuration::from_str(
+                r#"
+supergraph:
+  listen: 127.0.0.1:0
+  introspection: true
+health_check:
+  listen: 127.0.0.1:0
+"#,
+            )
+            .unwrap(),
+        );
+        let mut response = response;
+        // the configuration is applied asynchronously
+        for _ in 0..50 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            let Some(listen_address) = router_handle.listen_address().await else {
+                continue;
+            };
+            response = query(&listen_address, &request).await.unwrap();
+            if response.errors.is_empty() {
+                break;
+            }
+        }
+        assert!(response.errors.is_empty());
+        router_handle.shutdown().await.unwrap();
+    }
+
     async fn assert_federated_response(listen_addr: &ListenAddr, request: &str) {
         let request = Request::builder().query(request).build();
         let expected = query(listen_addr, &request).await.unwrap();
@@ -3,7 +3,10 @@ use std::io;
 use std::sync::Arc;
 
 use apollo_compiler::validation::Valid;
+use apollo_federation::diff::diff_schemas;
+use apollo_federation::diff::ChangeSeverity;
 use axum::response::IntoResponse;
+use futures::future::BoxFuture;
 use http::StatusCode;
 use indexmap::IndexMap;
 use multimap::MultiMap;
@@ -17,6 +20,7 @@ use tower::ServiceExt;
 use tower_service::Service;
 use tracing::Instrument;
 
+use crate::configuration::tenants::Tenant;
 use crate::configuration::Configuration;
 use crate::configuration::ConfigurationError;
 use crate::configuration::TlsClient;
@@ -27,9 +31,12 @@ use crate::plugin::PluginFactory;
 use crate::plugin::PluginInit;
 use crate::plugins::subscription::Subscription;
 use crate::plugins::subscription::APOLLO_SUBSCRIPTION_PLUGIN;
+use crate::plugins::telemetry::apollo::TenantGraph;
 use crate::plugins::telemetry::reload::apollo_opentelemetry_initialized;
+use crate::plugins::telemetry::Telemetry;
 use crate::plugins::traffic_shaping::TrafficShaping;
 use crate::plugins::traffic_shaping::APOLLO_TRAFFIC_SHAPING;
+use crate::query_planner::load_warm_up_manifest;
 use crate::query_planner::BridgeQueryPlannerPool;
 use crate::services::apollo_graph_reference;
 use crate::services::apollo_key;
@@ -39,15 +46,18 @@ use crate::services::layers::query_analysis::QueryAnalysisLayer;
 use crate::services::new_service::ServiceFactory;
 use crate::services::router;
 use crate::services::router::service::RouterCreator;
+use crate::services::router::service::TenantRouter;
 use crate::services::subgraph;
 use crate::services::transport;
 use crate::services::HasConfig;
+use crate::services::HasPlugins;
 use crate::services::HasSchema;
 use crate::services::PluggableSupergraphServiceBuilder;
 use crate::services::Plugins;
 use crate::services::SubgraphService;
 use crate::services::SupergraphCreator;
 use crate::spec::Schema;
+use crate::uplink::schema::SchemaState;
 use crate::ListenAddr;
 
 pub(crate) const STARTING_SPAN_NAME: &str = "starting";
@@ -123,6 +133,11 @@ pub(crate) trait RouterFactory:
     type Future: Send;
 
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint>;
+
+    /// Purges the in-memory caches of the query planner, query parsing and APQ
+    fn purge_caches(&self) -> BoxFuture<'static, ()> {
+        Box::pin(futures::future::ready(()))
+    }
 }
 
 /// Factory for creating a RouterFactory
@@ -159,6 +174,28 @@ impl RouterSuperServiceFactory for YamlRouterFactory {
         previous_router: Option<&'a Self::RouterFactory>,
         extra_plugins: Option<Vec<(String, Box<dyn DynPlugin>)>>,
     ) -> Result<Self::RouterFactory, BoxError> {
+        // Tenants are served by their own router pipelines, but their telemetry goes through the
+        // telemetry plugin of the main configuration. It is the only one owning the global
+        // exporters, and it reports the usage of each tenant to the graph of the tenant.
+        let mut loaded_tenants = Vec::with_capacity(configuration.experimental_tenants.len());
+        let mut tenant_graphs = Map::new();
+        for tenant in &configuration.experimental_tenants {
+            let previous_tenant = previous_router.and_then(|router| router.tenant(&tenant.name));
+            let (tenant_configuration, tenant_schema) =
+                load_tenant(tenant, &configuration, previous_tenant).await?;
+            if let Some(graph) = tenant_graph(tenant, &tenant_schema)? {
+                tenant_graphs.insert(tenant.name.clone(), serde_json::to_value(graph)?);
+            }
+            loaded_tenants.push((tenant.clone(), tenant_configuration, tenant_schema));
+        }
+        let configuration = if tenant_graphs.is_empty() {
+            configuration
+        } else {
+            let mut configuration = (*configuration).clone();
+            inject_tenant_graphs(tenant_graphs, &mut configuration);
+            Arc::new(configuration)
+        };
+
         // we have to create a telemetry plugin before creating everything else, to generate a trace
         // of router and plugin creation
         let plugin_registry = &*crate::plugin::PLUGINS;
@@ -182,7 +219,7 @@ impl RouterSuperServiceFactory for YamlRouterFactory {
                                 .config(plugin_config.clone())
                                 .supergraph_sdl(schema.raw_sdl.clone())
                                 .supergraph_schema_id(schema.schema_id.clone())
-                                .supergraph_schema(Arc::new(schema.supergraph_schema().clone()))
+                                .supergraph_schema(schema.supergraph_schema_arc())
                                 .notify(configuration.notify.clone())
                                 .build(),
                         )
@@ -204,18 +241,193 @@ impl RouterSuperServiceFactory for YamlRouterFactory {
         }
 
         let router_span = tracing::info_span!(STARTING_SPAN_NAME);
-        Self.inner_create(
-            configuration,
-            schema,
-            previous_router,
-            initial_telemetry_plugin,
-            extra_plugins,
-        )
-        .instrument(router_span)
+        let router = Self
+            .inner_create(
+                configuration,
+                schema,
+                previous_router,
+                initial_telemetry_plugin,
+                extra_plugins,
+            )
+            .instrument(router_span.clone())
+            .await?;
+
+        let plugins = router.supergraph_creator.plugins();
+        let telemetry = plugins
+            .get("apollo.telemetry")
+            .and_then(|plugin| plugin.as_any().downcast_ref::<Telemetry>());
+        let mut tenants = Vec::with_capacity(loaded_tenants.len());
+        for (tenant, tenant_configuration, tenant_schema) in loaded_tenants {
+            let tenant_telemetry: Option<Box<dyn DynPlugin>> = telemetry.map(|telemetry| {
+                telemetry
+                    .for_tenant(&tenant.name, tenant_schema.schema_id.clone())
+                    .into()
+            });
+            let tenant_router = Self
+                .inner_create(
+                    tenant_configuration,
+                    tenant_schema,
+                    previous_router.and_then(|router| router.tenant(&tenant.name)),
+                    tenant_telemetry,
+                    None,
+                )
+                .instrument(
+                    tracing::info_span!(parent: &router_span, "tenant", tenant.name = %tenant.name),
+                )
+                .await?;
+            tenants.push(TenantRouter {
+                name: tenant.name,
+                matcher: tenant.matcher,
+                router: tenant_router,
+            });
+        }
+        Ok(router.with_tenants(tenants))
+    }
+}
+
+/// Loads the configuration and the schema of a tenant. The schema of the previous router of the
+/// tenant is reused if it did not change.
+async fn load_tenant(
+    tenant: &Tenant,
+    configuration: &Configuration,
+    previous_router: Option<&RouterCreator>,
+) -> Result<(Arc<Configuration>, Arc<Schema>), BoxError> {
+    let tenant_configuration = match &tenant.configuration_path {
+        Some(path) => {
+            let yaml = tokio::fs::read_to_string(path).await.map_err(|e| {
+                format!(
+                    "could not read the configuration of tenant '{}' at {}: {e}",
+                    tenant.name,
+                    path.display()
+                )
+            })?;
+            let tenant_configuration: Configuration = yaml.parse()?;
+            if !tenant_configuration.experimental_tenants.is_empty() {
+                return Err(format!(
+                    "the configuration of tenant '{}' cannot declare tenants",
+                    tenant.name
+                )
+                .into());
+            }
+            tenant_configuration
+        }
+        None => {
+            let mut tenant_configuration = configuration.clone();
+            tenant_configuration.experimental_tenants.clear();
+            tenant_configuration
+        }
+    };
+
+    let sdl = tokio::fs::read_to_string(&tenant.supergraph_path)
         .await
+        .map_err(|e| {
+            format!(
+                "could not read the schema of tenant '{}' at {}: {e}",
+                tenant.name,
+                tenant.supergraph_path.display()
+            )
+        })?;
+    let previous_schema = previous_router.map(|router| router.supergraph_creator.schema());
+    let schema = Schema::parse_or_reuse(
+        Arc::new(SchemaState {
+            sdl,
+            launch_id: None,
+        }),
+        &tenant_configuration,
+        previous_schema.as_ref(),
+    )?;
+    Ok((Arc::new(tenant_configuration), schema))
+}
+
+/// The graph the usage of a tenant is reported to, if it is not the graph of the main
+/// configuration. The graph ref and API key of the tenant default to those of the router.
+fn tenant_graph(tenant: &Tenant, schema: &Schema) -> Result<Option<TenantGraph>, BoxError> {
+    if tenant.graph_ref.is_none() && tenant.apollo_key.is_none() {
+        return Ok(None);
+    }
+    match (
+        tenant.apollo_key.clone().or_else(apollo_key),
+        tenant.graph_ref.clone().or_else(apollo_graph_reference),
+    ) {
+        (Some(apollo_key), Some(apollo_graph_ref)) => Ok(Some(TenantGraph {
+            apollo_key,
+            apollo_graph_ref,
+            schema_id: schema.schema_id.to_string(),
+        })),
+        _ => Err(format!(
+            "tenant '{}' needs both a graph ref and an API key to report its usage",
+            tenant.name
+        )
+        .into()),
     }
 }
 
+/// Sets the graphs of the tenants in the Apollo telemetry configuration of the main router
+fn inject_tenant_graphs(tenant_graphs: Map<String, Value>, configuration: &mut Configuration) {
+    let telemetry = configuration
+        .apollo_plugins
+        .plugins
+        .entry("telemetry")
+        .or_insert_with(|| Value::Object(Default::default()));
+    if let Some(telemetry) = telemetry.as_object_mut() {
+        let apollo = telemetry
+            .entry("apollo")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Some(apollo) = apollo.as_object_mut() {
+            apollo.insert("tenants".to_string(), Value::Object(tenant_graphs));
+        }
+    }
+}
+
+/// Logs the changes between the previous and new API schemas, and rejects the new schema if it
+/// contains breaking changes and the configuration requires it.
+fn check_schema_changes(
+    previous: &Schema,
+    new: &Schema,
+    configuration: &Configuration,
+) -> Result<(), BoxError> {
+    if previous.schema_id == new.schema_id {
+        return Ok(());
+    }
+
+    let changes = diff_schemas(previous.api_schema(), new.api_schema());
+    let count = |severity| {
+        changes
+            .iter()
+            .filter(|change| change.severity == severity)
+            .count()
+    };
+    let breaking = count(ChangeSeverity::Breaking);
+    tracing::info!(
+        breaking,
+        dangerous = count(ChangeSeverity::Dangerous),
+        safe = count(ChangeSeverity::Safe),
+        "API schema updated"
+    );
+    for change in &changes {
+        if change.severity == ChangeSeverity::Breaking {
+            tracing::warn!(
+                kind = ?change.kind,
+                coordinate = %change.coordinate,
+                "breaking schema change: {}",
+                change.message
+            );
+        }
+    }
+
+    if breaking > 0
+        && configuration
+            .supergraph
+            .experimental_reject_breaking_schema_changes
+    {
+        return Err(format!(
+            "the schema update contains {breaking} breaking change(s) and `supergraph.experimental_reject_breaking_schema_changes` is enabled"
+        )
+        .into());
+    }
+    Ok(())
+}
+
 impl YamlRouterFactory {
     async fn inner_create<'a>(
         &'a mut self,
@@ -225,6 +437,14 @@ impl YamlRouterFactory {
         initial_telemetry_plugin: Option<Box<dyn DynPlugin>>,
         extra_plugins: Option<Vec<(String, Box<dyn DynPlugin>)>>,
     ) -> Result<RouterCreator, BoxError> {
+        if let Some(previous_router) = previous_router {
+            check_schema_changes(
+                &previous_router.supergraph_creator.schema(),
+                &schema,
+                &configuration,
+            )?;
+        }
+
         let mut supergraph_creator = self
             .inner_create_supergraph(
                 configuration.clone(),
@@ -241,6 +461,15 @@ impl YamlRouterFactory {
 
         let persisted_query_layer = Arc::new(PersistedQueryLayer::new(&configuration).await?);
 
+        let warm_up_operations = match &configuration
+            .supergraph
+            .query_planning
+            .experimental_warm_up_manifest
+        {
+            Some(path) => load_warm_up_manifest(path).await?,
+            None => Vec::new(),
+        };
+
         if let Some(previous_router) = previous_router {
             let previous_cache = previous_router.previous_cache();
 
@@ -257,6 +486,7 @@ impl YamlRouterFactory {
                     &configuration
                         .persisted_queries
                         .experimental_prewarm_query_plan_cache,
+                    &warm_up_operations,
                 )
                 .await;
         } else {
@@ -273,6 +503,7 @@ impl YamlRouterFactory {
                     &configuration
                         .persisted_queries
                         .experimental_prewarm_query_plan_cache,
+                    &warm_up_operations,
                 )
                 .await;
         };
@@ -295,10 +526,13 @@ impl YamlRouterFactory {
     ) -> Result<SupergraphCreator, BoxError> {
         let query_planner_span = tracing::info_span!("query_planner_creation");
         // QueryPlannerService takes an UnplannedRequest and outputs PlannedRequest
-        let bridge_query_planner =
-            BridgeQueryPlannerPool::new(schema.clone(), configuration.clone())
-                .instrument(query_planner_span)
-                .await?;
+        let bridge_query_planner = BridgeQueryPlannerPool::new_sharing(
+            schema.clone(),
+            configuration.clone(),
+            previous_supergraph.map(SupergraphCreator::query_planner_pool),
+        )
+        .instrument(query_planner_span)
+        .await?;
 
         let schema_changed = previous_supergraph
             .map(|supergraph_creator| supergraph_creator.schema().raw_sdl == schema.raw_sdl)
@@ -494,6 +728,36 @@ Details: Object field "Product.reviews"'s inner type "Review" does not refer to
     );
 }
 
+/// benchmark only helper method measuring how long it takes to reload a router with an
+/// unchanged schema, parsing it again or reusing the previously parsed one
+///
+/// not meant to be used directly
+pub async fn measure_schema_reload(
+    schema: &str,
+    configuration: &str,
+    reuse_schema: bool,
+) -> std::time::Duration {
+    let configuration: Arc<Configuration> = Arc::new(serde_yaml::from_str(configuration).unwrap());
+    let schema_state = Arc::new(SchemaState {
+        sdl: schema.to_string(),
+        launch_id: None,
+    });
+    let schema = Schema::parse_or_reuse(schema_state.clone(), &configuration, None).unwrap();
+    let previous_router = YamlRouterFactory
+        .create(false, configuration.clone(), schema.clone(), None, None)
+        .await
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    let previous_schema = reuse_schema.then_some(&schema);
+    let schema = Schema::parse_or_reuse(schema_state, &configuration, previous_schema).unwrap();
+    let _router = YamlRouterFactory
+        .create(false, configuration, schema, Some(&previous_router), None)
+        .await
+        .unwrap();
+    start.elapsed()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn add_plugin(
     name: String,
@@ -505,7 +769,9 @@ pub(crate) async fn add_plugin(
     subgraph_schemas: Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>>,
     launch_id: Option<Arc<String>>,
     notify: &crate::notification::Notify<String, crate::graphql::Response>,
+    full_config: Option<Value>,
     plugin_instances: &mut Plugins,
+    startup_configs: &mut HashMap<String, Value>,
     errors: &mut Vec<ConfigurationError>,
 ) {
     match factory
@@ -518,11 +784,14 @@ pub(crate) async fn add_plugin(
                 .subgraph_schemas(subgraph_schemas)
                 .launch_id(launch_id)
                 .notify(notify.clone())
+                .and_full_config(full_config)
                 .build(),
         )
         .await
     {
         Ok(plugin) => {
+            // The plugin is started once every plugin of the router was created
+            startup_configs.insert(name.clone(), plugin_config.clone());
             let _ = plugin_instances.insert(name, plugin);
         }
         Err(err) => errors.push(ConfigurationError::PluginConfiguration {
@@ -532,6 +801,114 @@ pub(crate) async fn add_plugin(
     }
 }
 
+/// A plugin whose startup hook ran successfully.
+///
+/// Its shutdown hook runs exactly once: either when the router it belongs to fails to be created,
+/// or when it is dropped, which happens once the router was replaced or stopped and the last
+/// request using it completed.
+struct StartedPlugin {
+    plugin: Option<Box<dyn DynPlugin>>,
+}
+
+impl StartedPlugin {
+    fn new(plugin: Box<dyn DynPlugin>) -> Self {
+        Self {
+            plugin: Some(plugin),
+        }
+    }
+
+    fn plugin(&self) -> &dyn DynPlugin {
+        self.plugin
+            .as_deref()
+            .expect("the plugin is only taken on shutdown")
+    }
+}
+
+#[async_trait::async_trait]
+impl DynPlugin for StartedPlugin {
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        self.plugin().router_service(service)
+    }
+
+    fn supergraph_service(
+        &self,
+        service: crate::services::supergraph::BoxService,
+    ) -> crate::services::supergraph::BoxService {
+        self.plugin().supergraph_service(service)
+    }
+
+    fn execution_service(
+        &self,
+        service: crate::services::execution::BoxService,
+    ) -> crate::services::execution::BoxService {
+        self.plugin().execution_service(service)
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        self.plugin().subgraph_service(name, service)
+    }
+
+    fn http_client_service(
+        &self,
+        name: &str,
+        service: crate::services::http::BoxService,
+    ) -> crate::services::http::BoxService {
+        self.plugin().http_client_service(name, service)
+    }
+
+    fn name(&self) -> &'static str {
+        self.plugin().name()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        self.plugin().web_endpoints()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self.plugin().as_any()
+    }
+
+    #[cfg(test)]
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self.plugin
+            .as_deref_mut()
+            .expect("the plugin is only taken on shutdown")
+            .as_any_mut()
+    }
+
+    fn activate(&self) {
+        self.plugin().activate()
+    }
+
+    async fn startup(&mut self, _config: &serde_json::Value) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) {
+        if let Some(mut plugin) = self.plugin.take() {
+            plugin.shutdown().await;
+        }
+    }
+}
+
+impl Drop for StartedPlugin {
+    fn drop(&mut self) {
+        if let Some(mut plugin) = self.plugin.take() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn(async move { plugin.shutdown().await });
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        "plugin {} dropped outside of a runtime, it will not be shut down",
+                        plugin.name()
+                    );
+                }
+            }
+        }
+    }
+}
+
 pub(crate) async fn create_plugins(
     configuration: &Configuration,
     schema: &Schema,
@@ -539,7 +916,7 @@ pub(crate) async fn create_plugins(
     initial_telemetry_plugin: Option<Box<dyn DynPlugin>>,
     extra_plugins: Option<Vec<(String, Box<dyn DynPlugin>)>>,
 ) -> Result<Plugins, BoxError> {
-    let supergraph_schema = Arc::new(schema.supergraph_schema().clone());
+    let supergraph_schema = schema.supergraph_schema_arc();
     let supergraph_schema_id = schema.schema_id.clone();
     let mut apollo_plugins_config = configuration.apollo_plugins.clone().plugins;
     let user_plugins_config = configuration.plugins.clone().plugins.unwrap_or_default();
@@ -562,6 +939,8 @@ pub(crate) async fn create_plugins(
         .collect();
     let mut errors = Vec::new();
     let mut plugin_instances = Plugins::default();
+    // Configurations of the plugins created by `add_plugin`, that go through their startup hook
+    let mut startup_configs = HashMap::new();
 
     // Use function-like macros to avoid borrow conflicts of captures
     macro_rules! add_plugin {
@@ -576,7 +955,9 @@ pub(crate) async fn create_plugins(
                 subgraph_schemas.clone(),
                 schema.launch_id.clone(),
                 &configuration.notify.clone(),
+                configuration.validated_yaml.clone(),
                 &mut plugin_instances,
+                &mut startup_configs,
                 &mut errors,
             )
             .await;
@@ -635,12 +1016,12 @@ pub(crate) async fn create_plugins(
                 let user_span = tracing::info_span!("user_plugin", "name" = &name);
 
                 async {
-                    if let Some(factory) =
-                        plugin_registry.iter().find(|factory| factory.name == name)
-                    {
-                        add_plugin!(name, factory, plugin_config);
-                    } else {
-                        errors.push(ConfigurationError::PluginUnknown(name))
+                    match crate::plugin::plugin_factory(&name) {
+                        Ok(Some(factory)) => {
+                            add_plugin!(name, factory, plugin_config);
+                        }
+                        Ok(None) => errors.push(ConfigurationError::PluginUnknown(name)),
+                        Err(err) => errors.push(err),
                     }
                 }
                 .instrument(user_span)
@@ -669,19 +1050,29 @@ pub(crate) async fn create_plugins(
     add_mandatory_apollo_plugin!("limits");
     add_mandatory_apollo_plugin!("traffic_shaping");
     add_mandatory_apollo_plugin!("fleet_detector");
+    #[cfg(feature = "memory-instrumentation")]
+    add_mandatory_apollo_plugin!("memory");
     add_optional_apollo_plugin!("forbid_mutations");
+    add_optional_apollo_plugin!("maintenance");
+    add_optional_apollo_plugin!("operation_filter");
     add_optional_apollo_plugin!("subscription");
     add_optional_apollo_plugin!("override_subgraph_url");
+    add_optional_apollo_plugin!("load_balancing");
+    add_optional_apollo_plugin!("experimental_subgraph_transform");
     add_optional_apollo_plugin!("authorization");
     add_optional_apollo_plugin!("authentication");
+    add_optional_apollo_plugin!("api_keys");
     add_optional_apollo_plugin!("preview_file_uploads");
     add_optional_apollo_plugin!("preview_entity_cache");
+    add_optional_apollo_plugin!("experimental_cache_control");
     add_mandatory_apollo_plugin!("progressive_override");
     add_optional_apollo_plugin!("demand_control");
 
     // This relative ordering is documented in `docs/source/customizations/native.mdx`:
     add_optional_apollo_plugin!("rhai");
     add_optional_apollo_plugin!("coprocessor");
+    #[cfg(feature = "wasm")]
+    add_optional_apollo_plugin!("wasm");
     add_user_plugins!();
 
     // Macros above remove from `apollo_plugin_factories`, so anything left at the end
@@ -706,6 +1097,10 @@ pub(crate) async fn create_plugins(
             .collect::<Vec<&&String>>()
     );
 
+    if errors.is_empty() {
+        plugin_instances = start_plugins(plugin_instances, startup_configs, &mut errors).await;
+    }
+
     if !errors.is_empty() {
         for error in &errors {
             tracing::error!("{:#}", error);
@@ -720,6 +1115,41 @@ pub(crate) async fn create_plugins(
     }
 }
 
+/// Runs the startup hook of the plugins created by `add_plugin`, in order, once every plugin of
+/// the router exists. The initial telemetry plugin and the extra plugins are kept as they are.
+///
+/// If a plugin fails to start, the plugins that already started are shut down in reverse order.
+async fn start_plugins(
+    plugin_instances: Plugins,
+    mut startup_configs: HashMap<String, Value>,
+    errors: &mut Vec<ConfigurationError>,
+) -> Plugins {
+    let mut started_plugins = Plugins::default();
+    let mut started = Vec::new();
+    for (name, mut plugin) in plugin_instances {
+        let Some(config) = startup_configs.remove(&name) else {
+            let _ = started_plugins.insert(name, plugin);
+            continue;
+        };
+        if let Err(err) = plugin.startup(&config).await {
+            errors.push(ConfigurationError::PluginStartup {
+                plugin: name,
+                error: err.to_string(),
+            });
+            // Plugins that already started will never go live, let them release their resources
+            for name in started.iter().rev() {
+                if let Some(plugin) = started_plugins.get_mut(name) {
+                    plugin.shutdown().await;
+                }
+            }
+            break;
+        }
+        started.push(name.clone());
+        let _ = started_plugins.insert(name, Box::new(StartedPlugin::new(plugin)));
+    }
+    started_plugins
+}
+
 fn inject_schema_id(schema_id: Option<&str>, configuration: &mut Value) {
     if configuration.get("apollo").is_none() {
         // Warning: this must be done here, otherwise studio reporting will not work
@@ -748,13 +1178,20 @@ mod test {
     use schemars::JsonSchema;
     use serde::Deserialize;
     use serde_json::json;
+    use serde_json::Map;
     use tower_http::BoxError;
 
     use crate::configuration::Configuration;
+    use crate::plugin::DynPlugin;
     use crate::plugin::Plugin;
     use crate::plugin::PluginInit;
+    use crate::plugins::telemetry::apollo::TenantGraph;
     use crate::register_plugin;
+    use crate::router_factory::check_schema_changes;
     use crate::router_factory::inject_schema_id;
+    use crate::router_factory::inject_tenant_graphs;
+    use crate::router_factory::load_tenant;
+    use crate::router_factory::tenant_graph;
     use crate::router_factory::RouterSuperServiceFactory;
     use crate::router_factory::YamlRouterFactory;
     use crate::spec::Schema;
@@ -804,6 +1241,74 @@ mod test {
 
     register_plugin!("test", "always_fails_to_start", AlwaysFailsToStartPlugin);
 
+    // Always fails its startup hook plugin
+
+    #[derive(Debug)]
+    struct AlwaysFailsStartupPlugin {}
+
+    #[async_trait::async_trait]
+    impl Plugin for AlwaysFailsStartupPlugin {
+        type Config = Conf;
+
+        async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+            tracing::debug!("{}", init.config.name);
+            Ok(AlwaysFailsStartupPlugin {})
+        }
+
+        async fn startup(&mut self, config: &serde_json::Value) -> Result<(), BoxError> {
+            Err(BoxError::from(format!("cannot start {}", config["name"])))
+        }
+    }
+
+    register_plugin!("test", "always_fails_startup", AlwaysFailsStartupPlugin);
+
+    // Records its startup and shutdown hooks plugin
+
+    static LIFECYCLE_EVENTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    #[derive(Debug)]
+    struct RecordsLifecyclePlugin {
+        name: String,
+    }
+
+    #[async_trait::async_trait]
+    impl Plugin for RecordsLifecyclePlugin {
+        type Config = Conf;
+
+        async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+            Ok(RecordsLifecyclePlugin {
+                name: init.config.name,
+            })
+        }
+
+        async fn startup(&mut self, _config: &serde_json::Value) -> Result<(), BoxError> {
+            LIFECYCLE_EVENTS
+                .lock()
+                .unwrap()
+                .push(format!("startup {}", self.name));
+            Ok(())
+        }
+
+        async fn shutdown(&mut self) {
+            LIFECYCLE_EVENTS
+                .lock()
+                .unwrap()
+                .push(format!("shutdown {}", self.name));
+        }
+    }
+
+    register_plugin!("test", "records_lifecycle", RecordsLifecyclePlugin);
+
+    fn lifecycle_events(name: &str) -> Vec<String> {
+        LIFECYCLE_EVENTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.ends_with(&format!(" {name}")))
+            .cloned()
+            .collect()
+    }
+
     #[tokio::test]
     async fn test_yaml_no_extras() {
         let config = Configuration::builder().build().unwrap();
@@ -855,6 +1360,95 @@ mod test {
         assert!(service.is_err())
     }
 
+    #[tokio::test]
+    async fn test_yaml_plugins_always_fails_startup() {
+        let config: Configuration = serde_yaml::from_str(
+            r#"
+            plugins:
+                test.always_starts_and_stops:
+                    name: albert
+                test.always_fails_startup:
+                    name: albert
+        "#,
+        )
+        .unwrap();
+        let service = create_service(config).await;
+        assert!(service.is_err())
+    }
+
+    #[test]
+    fn test_registered_plugin_names_are_unique() {
+        for factory in crate::plugin::plugins() {
+            assert!(
+                crate::plugin::plugin_factory(&factory.name).is_ok(),
+                "{} is registered more than once",
+                factory.name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropped_router_shuts_down_started_plugins() {
+        let config: Configuration = serde_yaml::from_str(
+            r#"
+            plugins:
+                test.records_lifecycle:
+                    name: carol
+        "#,
+        )
+        .unwrap();
+        let schema = include_str!("testdata/supergraph.graphql");
+        let schema = Schema::parse(schema, &config).unwrap();
+        let extra: Box<dyn DynPlugin> = Box::new(RecordsLifecyclePlugin {
+            name: "dave".to_string(),
+        });
+
+        let router = YamlRouterFactory
+            .create(
+                false,
+                Arc::new(config),
+                Arc::new(schema),
+                None,
+                Some(vec![("extra".to_string(), extra)]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(lifecycle_events("carol"), vec!["startup carol"]);
+
+        drop(router);
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while lifecycle_events("carol").len() < 2 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the started plugin was not shut down");
+        assert_eq!(
+            lifecycle_events("carol"),
+            vec!["startup carol", "shutdown carol"]
+        );
+        // The extra plugin never started, so it is not shut down either
+        assert!(lifecycle_events("dave").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plugins_start_once_every_plugin_is_created() {
+        let config: Configuration = serde_yaml::from_str(
+            r#"
+            plugins:
+                test.records_lifecycle:
+                    name: erin
+                test.always_fails_to_start:
+                    name: albert
+        "#,
+        )
+        .unwrap();
+        let service = create_service(config).await;
+        assert!(service.is_err());
+        // The second plugin could not be created, so the first one never started
+        assert!(lifecycle_events("erin").is_empty());
+    }
+
     async fn create_service(config: Configuration) -> Result<(), BoxError> {
         let schema = include_str!("testdata/supergraph.graphql");
         let schema = Schema::parse(schema, &config)?;
@@ -886,4 +1480,74 @@ mod test {
             "8e2021d131b23684671c3b85f82dfca836908c6a541bbd5c3772c66e7f8429d8"
         );
     }
+
+    #[test]
+    fn test_reject_breaking_schema_changes() {
+        let previous_sdl = include_str!("testdata/minimal_supergraph.graphql");
+        let field = "me: String @join__field(graph: SUBGRAPH_A)";
+        let added_field_sdl = previous_sdl.replace(
+            field,
+            &format!("{field}\n  name: String @join__field(graph: SUBGRAPH_A)"),
+        );
+        let removed_field_sdl =
+            previous_sdl.replace(field, "name: String @join__field(graph: SUBGRAPH_A)");
+
+        let config: Configuration = serde_yaml::from_str(
+            "supergraph:\n  experimental_reject_breaking_schema_changes: true",
+        )
+        .unwrap();
+        let previous = Schema::parse(previous_sdl, &config).unwrap();
+        let added_field = Schema::parse(&added_field_sdl, &config).unwrap();
+        let removed_field = Schema::parse(&removed_field_sdl, &config).unwrap();
+
+        assert!(check_schema_changes(&previous, &added_field, &config).is_ok());
+        assert!(check_schema_changes(&previous, &removed_field, &config).is_err());
+        assert!(check_schema_changes(&previous, &removed_field, &Configuration::default()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_load_tenant() {
+        let supergraph_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/testdata/minimal_supergraph.graphql"
+        );
+        let config: Configuration = serde_json::from_value(json!({
+            "experimental_tenants": [{
+                "name": "acme",
+                "match": { "host": "acme.example.com" },
+                "supergraph_path": supergraph_path,
+                "graph_ref": "acme@current",
+                "apollo_key": "service:acme:key",
+            }]
+        }))
+        .unwrap();
+
+        let tenant = &config.experimental_tenants[0];
+        let (tenant_config, tenant_schema) = load_tenant(tenant, &config, None).await.unwrap();
+        assert!(tenant_config.experimental_tenants.is_empty());
+        assert_eq!(
+            tenant_schema.raw_sdl.as_str(),
+            include_str!("testdata/minimal_supergraph.graphql")
+        );
+
+        // The usage of the tenant is reported by the telemetry of the main router
+        let graph = tenant_graph(tenant, &tenant_schema).unwrap().unwrap();
+        let mut main_config = config.clone();
+        inject_tenant_graphs(
+            Map::from_iter([("acme".to_string(), serde_json::to_value(&graph).unwrap())]),
+            &mut main_config,
+        );
+        let telemetry = serde_json::from_value::<crate::plugins::telemetry::config::Conf>(
+            main_config.apollo_plugins.plugins["telemetry"].clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            telemetry.apollo.tenants["acme"],
+            TenantGraph {
+                apollo_key: "service:acme:key".to_string(),
+                apollo_graph_ref: "acme@current".to_string(),
+                schema_id: tenant_schema.schema_id.to_string(),
+            }
+        );
+    }
 }
@@ -33,6 +33,8 @@ use tracing_core::Level;
 
 use crate::apollo_studio_interop::extract_enums_from_response;
 use crate::apollo_studio_interop::ReferencedEnums;
+use crate::configuration::EntityBatching;
+use crate::configuration::ResponseValidation;
 use crate::graphql::Error;
 use crate::graphql::IncrementalResponse;
 use crate::graphql::Response;
@@ -67,6 +69,9 @@ pub(crate) struct ExecutionService {
     /// Subscription config if enabled
     subscription_config: Option<SubscriptionConfig>,
     apollo_telemetry_config: Option<ApolloTelemetryConfig>,
+    entity_batching: Arc<EntityBatching>,
+    parallel_mutations: bool,
+    response_validation: ResponseValidation,
 }
 
 type CloseSignal = broadcast::Sender<()>;
@@ -156,6 +161,8 @@ impl ExecutionService {
                 sender,
                 subscription_handle.clone(),
                 &self.subscription_config,
+                &self.entity_batching,
+                self.parallel_mutations,
                 req.source_stream_value,
             )
             .await;
@@ -192,6 +199,7 @@ impl ExecutionService {
         };
 
         let execution_span = Span::current();
+        let response_validation = self.response_validation;
 
         let stream = stream
             .map(move |mut response: Response| {
@@ -242,6 +250,7 @@ impl ExecutionService {
                         &schema,
                         &mut nullified_paths,
                         metrics_ref_mode,
+                        response_validation,
                         &context,
                         response,
                     )
@@ -252,6 +261,38 @@ impl ExecutionService {
         ExecutionResponse::new_from_response(http::Response::new(stream as _), ctx)
     }
 
+    /// Reports the values of a response that do not match the API schema
+    fn report_invalid_values(
+        response_validation: ResponseValidation,
+        invalid_values: Vec<Error>,
+        response: &mut Response,
+    ) {
+        u64_counter!(
+            "apollo.router.operations.response_validation",
+            "Number of response values that do not match the API schema",
+            invalid_values.len() as u64
+        );
+        for error in &invalid_values {
+            tracing::warn!(
+                path = ?error.path,
+                "response validation failed: {}",
+                error.message
+            );
+        }
+
+        if response_validation == ResponseValidation::Strict {
+            response
+                .errors
+                .extend(invalid_values.into_iter().map(|error| Error {
+                    extensions: Object::from_iter([(
+                        "code".into(),
+                        Value::from("RESPONSE_VALIDATION_FAILED"),
+                    )]),
+                    ..error
+                }));
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn process_graphql_response(
         query: &Arc<Query>,
@@ -260,6 +301,7 @@ impl ExecutionService {
         schema: &Arc<Schema>,
         nullified_paths: &mut Vec<Path>,
         metrics_ref_mode: ApolloMetricsReferenceMode,
+        response_validation: ResponseValidation,
         context: &crate::Context,
         mut response: Response,
     ) -> Option<Response> {
@@ -294,6 +336,8 @@ impl ExecutionService {
 
         tracing::debug_span!("format_response").in_scope(|| {
             let mut paths = Vec::new();
+            let mut invalid_values = Vec::new();
+            let validate = response_validation != ResponseValidation::Disabled;
             if !query.unauthorized.paths.is_empty() {
                 if query.unauthorized.errors.log {
                     let unauthorized_paths = query.unauthorized.paths.iter().map(|path| path.to_string()).collect::<Vec<_>>();
@@ -330,6 +374,7 @@ impl ExecutionService {
                     variables.clone(),
                     schema.api_schema(),
                     variables_set,
+                    validate.then_some(&mut invalid_values),
                 );
             }
 
@@ -340,10 +385,15 @@ impl ExecutionService {
                         variables.clone(),
                         schema.api_schema(),
                         variables_set,
+                        validate.then_some(&mut invalid_values),
                     )
                     ,
             );
 
+            if !invalid_values.is_empty() {
+                Self::report_invalid_values(response_validation, invalid_values, &mut response);
+            }
+
             for error in response.errors.iter_mut() {
                 if let Some(path) = &mut error.path {
                     // Check if path can be matched to the supergraph query and truncate if not
@@ -635,6 +685,9 @@ pub(crate) struct ExecutionServiceFactory {
     pub(crate) subgraph_schemas: Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>>,
     pub(crate) plugins: Arc<Plugins>,
     pub(crate) subgraph_service_factory: Arc<SubgraphServiceFactory>,
+    pub(crate) entity_batching: Arc<EntityBatching>,
+    pub(crate) parallel_mutations: bool,
+    pub(crate) response_validation: ResponseValidation,
 }
 
 impl ServiceFactory<ExecutionRequest> for ExecutionServiceFactory {
@@ -663,6 +716,9 @@ impl ServiceFactory<ExecutionRequest> for ExecutionServiceFactory {
                         subscription_config: subscription_plugin_conf,
                         subgraph_schemas: self.subgraph_schemas.clone(),
                         apollo_telemetry_config: apollo_telemetry_conf,
+                        entity_batching: self.entity_batching.clone(),
+                        parallel_mutations: self.parallel_mutations,
+                        response_validation: self.response_validation,
                     }
                     .boxed(),
                     |acc, (_, e)| e.execution_service(acc),
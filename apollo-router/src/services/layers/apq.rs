@@ -71,6 +71,13 @@ impl APQLayer {
         Self { cache: None }
     }
 
+    /// Removes the persisted queries stored in memory
+    pub(crate) async fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear_in_memory().await;
+        }
+    }
+
     pub(crate) async fn supergraph_request(
         &self,
         request: SupergraphRequest,
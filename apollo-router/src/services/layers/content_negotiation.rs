@@ -3,12 +3,15 @@ use std::ops::ControlFlow;
 use http::header::ACCEPT;
 use http::header::CONTENT_TYPE;
 use http::HeaderMap;
+use http::HeaderValue;
 use http::Method;
 use http::StatusCode;
 use mediatype::names::APPLICATION;
+use mediatype::names::CHARSET;
 use mediatype::names::JSON;
 use mediatype::names::MIXED;
 use mediatype::names::MULTIPART;
+use mediatype::names::TEXT;
 use mediatype::names::_STAR;
 use mediatype::MediaTypeList;
 use mediatype::ReadParams;
@@ -21,6 +24,7 @@ use tower::ServiceExt;
 use crate::graphql;
 use crate::layers::sync_checkpoint::CheckpointService;
 use crate::layers::ServiceExt as _;
+use crate::protocols::sse::EVENT_STREAM_CONTENT_TYPE;
 use crate::services::router;
 use crate::services::router::service::MULTIPART_DEFER_CONTENT_TYPE_HEADER_VALUE;
 use crate::services::router::service::MULTIPART_SUBSCRIPTION_CONTENT_TYPE_HEADER_VALUE;
@@ -91,11 +95,12 @@ where
                 if accepts.wildcard
                     || accepts.multipart_defer
                     || accepts.multipart_subscription
+                    || accepts.event_stream
                     || accepts.json
                 {
-                    req.context
-                        .extensions()
-                        .with_lock(|mut lock| lock.insert(accepts));
+                    req.context.extensions().with_lock(|mut lock| {
+                        lock.insert(accepts);
+                    });
 
                     Ok(ControlFlow::Continue(req))
                 } else {
@@ -143,15 +148,22 @@ where
                 let ClientRequestAccepts {
                     wildcard: accepts_wildcard,
                     json: accepts_json,
+                    graphql_response_json: accepts_graphql_response_json,
                     multipart_defer: accepts_multipart_defer,
                     multipart_subscription: accepts_multipart_subscription,
+                    event_stream: accepts_event_stream,
                 } = context.extensions().with_lock(|lock| {
                     lock.get::<ClientRequestAccepts>()
                         .cloned()
                         .unwrap_or_default()
                 });
 
-                if !res.has_next.unwrap_or_default() && (accepts_json || accepts_wildcard) {
+                if !res.has_next.unwrap_or_default() && accepts_graphql_response_json {
+                    parts.headers.insert(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(GRAPHQL_JSON_RESPONSE_HEADER_VALUE),
+                    );
+                } else if !res.has_next.unwrap_or_default() && (accepts_json || accepts_wildcard) {
                     parts
                         .headers
                         .insert(CONTENT_TYPE, APPLICATION_JSON_HEADER_VALUE.clone());
@@ -165,6 +177,11 @@ where
                         CONTENT_TYPE,
                         MULTIPART_SUBSCRIPTION_CONTENT_TYPE_HEADER_VALUE.clone(),
                     );
+                } else if accepts_event_stream {
+                    parts.headers.insert(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(EVENT_STREAM_CONTENT_TYPE),
+                    );
                 }
                 (parts, res)
             })
@@ -172,7 +189,8 @@ where
     }
 }
 
-/// Returns true if the headers content type is `application/json` or `application/graphql-response+json`
+/// Returns true if the headers content type is `application/json` or `application/graphql-response+json`,
+/// with no charset or the UTF-8 charset required by the GraphQL over HTTP specification
 fn content_type_is_json(headers: &HeaderMap) -> bool {
     headers.get_all(CONTENT_TYPE).iter().any(|value| {
         value
@@ -183,10 +201,13 @@ fn content_type_is_json(headers: &HeaderMap) -> bool {
                 list.any(|mime| {
                     mime.as_ref()
                         .map(|mime| {
-                            (mime.ty == APPLICATION && mime.subty == JSON)
+                            ((mime.ty == APPLICATION && mime.subty == JSON)
                                 || (mime.ty == APPLICATION
                                     && mime.subty.as_str() == "graphql-response"
-                                    && mime.suffix == Some(JSON))
+                                    && mime.suffix == Some(JSON)))
+                                && mime.get_param(CHARSET).map_or(true, |charset| {
+                                    charset.unquoted_str().eq_ignore_ascii_case("utf-8")
+                                })
                         })
                         .unwrap_or(false)
                 })
@@ -206,14 +227,17 @@ fn parse_accept(headers: &HeaderMap) -> ClientRequestAccepts {
         if let Ok(str) = value.to_str() {
             for result in MediaTypeList::new(str) {
                 if let Ok(mime) = result {
+                    let graphql_response_json = mime.ty == APPLICATION
+                        && mime.subty.as_str() == "graphql-response"
+                        && mime.suffix == Some(JSON);
                     if !accepts.json
-                        && ((mime.ty == APPLICATION && mime.subty == JSON)
-                            || (mime.ty == APPLICATION
-                                && mime.subty.as_str() == "graphql-response"
-                                && mime.suffix == Some(JSON)))
+                        && ((mime.ty == APPLICATION && mime.subty == JSON) || graphql_response_json)
                     {
                         accepts.json = true
                     }
+                    if !accepts.graphql_response_json && graphql_response_json {
+                        accepts.graphql_response_json = true
+                    }
                     if !accepts.wildcard && (mime.ty == _STAR && mime.subty == _STAR) {
                         accepts.wildcard = true
                     }
@@ -237,6 +261,11 @@ fn parse_accept(headers: &HeaderMap) -> ClientRequestAccepts {
                             accepts.multipart_subscription = true
                         }
                     }
+                    if !accepts.event_stream
+                        && (mime.ty == TEXT && mime.subty.as_str() == "event-stream")
+                    {
+                        accepts.event_stream = true
+                    }
                 }
             }
         }
@@ -249,8 +278,6 @@ fn parse_accept(headers: &HeaderMap) -> ClientRequestAccepts {
 
 #[cfg(test)]
 mod tests {
-    use http::HeaderValue;
-
     use super::*;
 
     #[test]
@@ -284,6 +311,7 @@ mod tests {
         default_headers.append(ACCEPT, HeaderValue::from_static("foo/bar"));
         let accepts = parse_accept(&default_headers);
         assert!(accepts.json);
+        assert!(accepts.graphql_response_json);
 
         let mut default_headers = HeaderMap::new();
         default_headers.insert(
@@ -293,5 +321,33 @@ mod tests {
         default_headers.append(ACCEPT, HeaderValue::from_static(MULTIPART_DEFER_ACCEPT));
         let accepts = parse_accept(&default_headers);
         assert!(accepts.multipart_defer);
+
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(ACCEPT, HeaderValue::from_static(EVENT_STREAM_CONTENT_TYPE));
+        let accepts = parse_accept(&default_headers);
+        assert!(accepts.event_stream);
+        assert!(!accepts.json);
+        assert!(!accepts.graphql_response_json);
+    }
+
+    #[test]
+    fn it_checks_content_type_header() {
+        let content_type_is_json_for = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(value));
+            content_type_is_json(&headers)
+        };
+
+        assert!(content_type_is_json_for("application/json"));
+        assert!(content_type_is_json_for("application/json; charset=utf-8"));
+        assert!(content_type_is_json_for(
+            "application/json; charset=\"UTF-8\""
+        ));
+        assert!(content_type_is_json_for(GRAPHQL_JSON_RESPONSE_HEADER_VALUE));
+        assert!(!content_type_is_json_for(
+            "application/json; charset=iso-8859-1"
+        ));
+        assert!(!content_type_is_json_for("application/graphql"));
+        assert!(!content_type_is_json_for("text/plain"));
     }
 }
@@ -3,10 +3,15 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::RwLock;
+use std::time::Instant;
 
 use apollo_compiler::ast;
 use futures::prelude::*;
+use indexmap::IndexMap;
+use opentelemetry_api::metrics::MeterProvider as _;
+use opentelemetry_api::metrics::ObservableGauge;
 use reqwest::Client;
 use serde::Deserialize;
 use serde::Serialize;
@@ -14,6 +19,7 @@ use tokio::fs::read_to_string;
 use tokio::sync::mpsc;
 use tower::BoxError;
 
+use crate::metrics::meter_provider;
 use crate::uplink::persisted_queries_manifest_stream::MaybePersistedQueriesManifestChunks;
 use crate::uplink::persisted_queries_manifest_stream::PersistedQueriesManifestChunk;
 use crate::uplink::persisted_queries_manifest_stream::PersistedQueriesManifestQuery;
@@ -207,11 +213,16 @@ pub(crate) struct PersistedQueryManifestPollerState {
     pub(crate) freeform_graphql_behavior: FreeformGraphQLBehavior,
 }
 
+/// The operations of the chunks fetched by the last sync, by chunk ID, in the order of the chunk
+/// list. Chunks are immutable, so only the chunks added since the last sync need to be downloaded.
+type ChunkCache = Arc<Mutex<IndexMap<String, Arc<Vec<Operation>>>>>;
+
 /// Manages polling uplink for persisted query chunks and unpacking those chunks into a [`PersistedQueryManifest`].
 #[derive(Debug)]
 pub(crate) struct PersistedQueryManifestPoller {
     pub(crate) state: Arc<RwLock<PersistedQueryManifestPollerState>>,
     _drop_signal: mpsc::Sender<()>,
+    _list_size_gauge: ObservableGauge<u64>,
 }
 
 impl PersistedQueryManifestPoller {
@@ -304,6 +315,7 @@ impl PersistedQueryManifestPoller {
             );
 
             Ok(Self {
+                _list_size_gauge: list_size_gauge(state.clone()),
                 state,
                 _drop_signal: mpsc::channel::<()>(1).0,
             })
@@ -352,6 +364,7 @@ impl PersistedQueryManifestPoller {
             }
 
             Ok(Self {
+                _list_size_gauge: list_size_gauge(state.clone()),
                 state,
                 _drop_signal,
             })
@@ -444,6 +457,36 @@ impl PersistedQueryManifestPoller {
     }
 }
 
+fn list_size_gauge(state: Arc<RwLock<PersistedQueryManifestPollerState>>) -> ObservableGauge<u64> {
+    meter_provider()
+        .meter("apollo/router")
+        .u64_observable_gauge("apollo.router.persisted_queries.list.size")
+        .with_description("Number of operations in the persisted query list")
+        .with_callback(move |observer| {
+            if let Ok(state) = state.read() {
+                observer.observe(state.persisted_query_manifest.len() as u64, &[]);
+            }
+        })
+        .init()
+}
+
+/// Number of operations added to and removed from the manifest by an update. An operation
+/// whose body changed counts as both removed and added.
+fn manifest_delta(
+    previous: &PersistedQueryManifest,
+    next: &PersistedQueryManifest,
+) -> (usize, usize) {
+    let added = next
+        .iter()
+        .filter(|(id, body)| previous.get(*id) != Some(*body))
+        .count();
+    let removed = previous
+        .iter()
+        .filter(|(id, body)| next.get(*id) != Some(*body))
+        .count();
+    (added, removed)
+}
+
 async fn poll_uplink(
     uplink_config: UplinkConfig,
     state: Arc<RwLock<PersistedQueryManifestPollerState>>,
@@ -453,18 +496,21 @@ async fn poll_uplink(
     http_client: Client,
 ) {
     let http_client = http_client.clone();
+    let chunk_cache = ChunkCache::default();
     let mut uplink_executor = stream::select_all(vec![
         stream_from_uplink_transforming_new_response::<
             PersistedQueriesManifestQuery,
             MaybePersistedQueriesManifestChunks,
-            Option<PersistedQueryManifest>,
+            Option<(PersistedQueryManifest, Instant)>,
         >(uplink_config.clone(), move |response| {
             let http_client = http_client.clone();
+            let chunk_cache = chunk_cache.clone();
+            let received_at = Instant::now();
             Box::new(Box::pin(async move {
                 match response {
-                    Some(chunks) => manifest_from_chunks(chunks, http_client)
+                    Some(chunks) => manifest_from_chunks(chunks, http_client, &chunk_cache)
                         .await
-                        .map(Some)
+                        .map(|manifest| Some((manifest, received_at)))
                         .map_err(|err| {
                             format!("could not download persisted query lists: {}", err).into()
                         }),
@@ -473,7 +519,9 @@ async fn poll_uplink(
             }))
         })
         .map(|res| match res {
-            Ok(Some(new_manifest)) => ManifestPollEvent::NewManifest(new_manifest),
+            Ok(Some((new_manifest, received_at))) => {
+                ManifestPollEvent::NewManifest(new_manifest, received_at)
+            }
             Ok(None) => ManifestPollEvent::NoPersistedQueryList {
                 graph_ref: uplink_config.apollo_graph_ref.clone(),
             },
@@ -502,7 +550,7 @@ async fn poll_uplink(
 
     while let Some(event) = uplink_executor.next().await {
         match event {
-            ManifestPollEvent::NewManifest(new_manifest) => {
+            ManifestPollEvent::NewManifest(new_manifest, received_at) => {
                 let freeform_graphql_behavior = if config.persisted_queries.safelist.enabled {
                     if config.persisted_queries.safelist.require_id {
                         FreeformGraphQLBehavior::DenyAll {
@@ -530,13 +578,43 @@ async fn poll_uplink(
                     freeform_graphql_behavior,
                 };
 
-                state
+                // The whole state is swapped at once, so requests never see a partially
+                // applied update
+                let (added, removed) = state
                     .write()
                     .map(|mut locked_state| {
+                        let delta = manifest_delta(
+                            &locked_state.persisted_query_manifest,
+                            &new_state.persisted_query_manifest,
+                        );
                         *locked_state = new_state;
+                        delta
                     })
                     .expect("could not acquire write lock on persisted query manifest state");
 
+                tracing::info!(
+                    "Updated the persisted query list: {} operations added, {} removed.",
+                    added,
+                    removed
+                );
+                u64_counter!(
+                    "apollo.router.persisted_queries.list.changes",
+                    "Number of operations added to or removed from the persisted query list",
+                    added as u64,
+                    change = "added"
+                );
+                u64_counter!(
+                    "apollo.router.persisted_queries.list.changes",
+                    "Number of operations added to or removed from the persisted query list",
+                    removed as u64,
+                    change = "removed"
+                );
+                f64_histogram!(
+                    "apollo.router.persisted_queries.sync.lag",
+                    "Time between the notification of a new persisted query list by Uplink and its application, in seconds",
+                    received_at.elapsed().as_secs_f64()
+                );
+
                 send_startup_event_or_log_error(
                     &mut ready_sender_once,
                     ManifestPollResultOnStartup::LoadedOperations,
@@ -594,18 +672,39 @@ async fn poll_uplink(
 async fn manifest_from_chunks(
     new_chunks: Vec<PersistedQueriesManifestChunk>,
     http_client: Client,
+    chunk_cache: &ChunkCache,
 ) -> Result<PersistedQueryManifest, BoxError> {
-    let mut new_persisted_query_manifest = PersistedQueryManifest::new();
     tracing::debug!("ingesting new persisted queries: {:?}", &new_chunks);
+    let previous_chunks = chunk_cache
+        .lock()
+        .expect("could not acquire lock on persisted query chunk cache")
+        .clone();
+    // The chunks are kept in the order of the list, so that when an operation is in several
+    // chunks, the body of the last one is used whatever the chunks fetched before
+    let mut chunks = IndexMap::with_capacity(new_chunks.len());
     // TODO: consider doing these fetches in parallel
     for new_chunk in new_chunks {
-        add_chunk_to_operations(
-            new_chunk,
-            &mut new_persisted_query_manifest,
-            http_client.clone(),
-        )
-        .await?
+        let operations = match previous_chunks.get(&new_chunk.id) {
+            Some(operations) => operations.clone(),
+            None => Arc::new(fetch_chunk_operations(&new_chunk, http_client.clone()).await?),
+        };
+        chunks.insert(new_chunk.id, operations);
+    }
+
+    let mut new_persisted_query_manifest = PersistedQueryManifest::new();
+    for operation in chunks.values().flat_map(|operations| operations.iter()) {
+        new_persisted_query_manifest.insert(
+            FullPersistedQueryOperationId {
+                operation_id: operation.id.clone(),
+                client_name: operation.client_name.clone(),
+            },
+            operation.body.clone(),
+        );
     }
+    // Chunks that are no longer part of the list are forgotten
+    *chunk_cache
+        .lock()
+        .expect("could not acquire lock on persisted query chunk cache") = chunks;
 
     tracing::info!(
         "Loaded {} persisted queries.",
@@ -615,25 +714,15 @@ async fn manifest_from_chunks(
     Ok(new_persisted_query_manifest)
 }
 
-async fn add_chunk_to_operations(
-    chunk: PersistedQueriesManifestChunk,
-    operations: &mut PersistedQueryManifest,
+async fn fetch_chunk_operations(
+    chunk: &PersistedQueriesManifestChunk,
     http_client: Client,
-) -> Result<(), BoxError> {
+) -> Result<Vec<Operation>, BoxError> {
     let mut it = chunk.urls.iter().peekable();
     while let Some(chunk_url) = it.next() {
         match fetch_chunk(http_client.clone(), chunk_url).await {
             Ok(chunk) => {
-                for operation in chunk.operations {
-                    operations.insert(
-                        FullPersistedQueryOperationId {
-                            operation_id: operation.id,
-                            client_name: operation.client_name,
-                        },
-                        operation.body,
-                    );
-                }
-                return Ok(());
+                return Ok(chunk.operations);
             }
             Err(e) => {
                 if it.peek().is_some() {
@@ -694,7 +783,7 @@ async fn fetch_chunk(http_client: Client, chunk_url: &String) -> Result<SignedUr
 /// Types of events produced by the manifest poller.
 #[derive(Debug)]
 pub(crate) enum ManifestPollEvent {
-    NewManifest(PersistedQueryManifest),
+    NewManifest(PersistedQueryManifest, Instant),
     NoPersistedQueryList { graph_ref: String },
     Err(BoxError),
     Shutdown,
@@ -749,6 +838,47 @@ mod tests {
         assert_eq!(manifest_manager.get_operation_body(&id, None), Some(body))
     }
 
+    #[tokio::test]
+    async fn manifest_uses_the_last_chunk_containing_an_operation() {
+        let operations = |body: &str| {
+            Arc::new(vec![Operation {
+                id: "id".to_string(),
+                body: body.to_string(),
+                client_name: None,
+            }])
+        };
+        let chunk = |id: &str| PersistedQueriesManifestChunk {
+            id: id.to_string(),
+            urls: Vec::new(),
+        };
+        let operation_id = FullPersistedQueryOperationId {
+            operation_id: "id".to_string(),
+            client_name: None,
+        };
+
+        // the chunks are already cached, so they are not fetched
+        let chunk_cache = ChunkCache::default();
+        for id in 0..20 {
+            chunk_cache
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), operations(&format!("{{ chunk{id} }}")));
+        }
+        for _ in 0..10 {
+            let manifest = manifest_from_chunks(
+                (0..20).map(|id| chunk(&id.to_string())).collect(),
+                Client::new(),
+                &chunk_cache,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                manifest.get(&operation_id).map(String::as_str),
+                Some("{ chunk19 }")
+            );
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn poller_wont_start_without_uplink_connection() {
         let uplink_endpoint = Url::parse("https://definitely.not.uplink").unwrap();
@@ -782,6 +912,26 @@ mod tests {
         assert_eq!(manifest_manager.get_operation_body(&id, None), Some(body))
     }
 
+    #[test]
+    fn manifest_delta_counts_changes() {
+        let id = |operation_id: &str| FullPersistedQueryOperationId {
+            operation_id: operation_id.to_string(),
+            client_name: None,
+        };
+        let previous = PersistedQueryManifest::from([
+            (id("kept"), "{ kept }".to_string()),
+            (id("changed"), "{ before }".to_string()),
+            (id("removed"), "{ removed }".to_string()),
+        ]);
+        let next = PersistedQueryManifest::from([
+            (id("kept"), "{ kept }".to_string()),
+            (id("changed"), "{ after }".to_string()),
+            (id("added"), "{ added }".to_string()),
+        ]);
+        assert_eq!(manifest_delta(&previous, &next), (2, 2));
+        assert_eq!(manifest_delta(&next, &next), (0, 0));
+    }
+
     #[test]
     fn safelist_body_normalization() {
         let safelist = FreeformGraphQLSafelist::new(&PersistedQueryManifest::from([
@@ -0,0 +1,327 @@
+//! Records the freeform operations executed by the router and periodically writes them to a
+//! persisted query manifest file, which can be published to bootstrap a safelist.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::configuration::PersistedQueriesManifestGeneration;
+
+const MANIFEST_FORMAT: &str = "apollo-persisted-query-manifest";
+const MANIFEST_VERSION: u64 = 1;
+
+/// An operation of the generated manifest
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub(crate) struct RecordedOperation {
+    pub(crate) id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) operation_type: Option<String>,
+    pub(crate) body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GeneratedManifest<'a> {
+    format: &'static str,
+    version: u64,
+    operations: Vec<&'a RecordedOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExistingManifest {
+    operations: Vec<RecordedOperation>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    operation: RecordedOperation,
+    last_seen: Instant,
+}
+
+#[derive(Debug)]
+pub(crate) struct PersistedQueryManifestRecorder {
+    path: PathBuf,
+    /// Each recorder writes to its own temporary file, so that the recorder of the previous
+    /// configuration can still write its last manifest while the new one is running
+    temporary_path: PathBuf,
+    window: Duration,
+    max_operations: usize,
+    operations: Mutex<HashMap<String, Entry>>,
+}
+
+impl PersistedQueryManifestRecorder {
+    /// Creates the recorder and starts writing the manifest every `export_interval`.
+    ///
+    /// The operations of a manifest previously written to the same path are kept, so that
+    /// restarts and reloads do not lose the operations observed so far.
+    pub(crate) async fn new(config: &PersistedQueriesManifestGeneration) -> Arc<Self> {
+        let recorder = Arc::new(Self {
+            path: config.path.clone(),
+            temporary_path: temporary_path(&config.path),
+            window: config.window,
+            max_operations: config.max_operations,
+            operations: Mutex::new(load_manifest(&config.path).await),
+        });
+
+        let weak_recorder = Arc::downgrade(&recorder);
+        let export_interval = config.export_interval;
+        tokio::spawn(async move {
+            export_periodically(weak_recorder, export_interval).await;
+        });
+
+        recorder
+    }
+
+    /// Records an operation body seen by the router
+    pub(crate) fn record(&self, body: &str, name: Option<&str>, operation_type: &str) {
+        let id = hex::encode(Sha256::digest(body.as_bytes()));
+        let now = Instant::now();
+
+        let mut operations = self.operations.lock();
+        if let Some(entry) = operations.get_mut(&id) {
+            entry.last_seen = now;
+            return;
+        }
+        if operations.len() >= self.max_operations {
+            tracing::debug!(
+                "persisted query manifest generation reached its limit of {} operations",
+                self.max_operations
+            );
+            return;
+        }
+        operations.insert(
+            id.clone(),
+            Entry {
+                operation: RecordedOperation {
+                    id,
+                    name: name.map(str::to_string),
+                    operation_type: Some(operation_type.to_string()),
+                    body: body.to_string(),
+                },
+                last_seen: now,
+            },
+        );
+    }
+
+    /// Returns the operations seen during the window, sorted by name and id,
+    /// and forgets the older ones
+    fn operations_in_window(&self) -> Vec<RecordedOperation> {
+        let now = Instant::now();
+        let mut operations = self.operations.lock();
+        operations.retain(|_, entry| now.duration_since(entry.last_seen) <= self.window);
+        let mut recorded = operations
+            .values()
+            .map(|entry| entry.operation.clone())
+            .collect::<Vec<_>>();
+        recorded.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+        recorded
+    }
+
+    fn manifest(&self) -> Result<String, serde_json::Error> {
+        let operations = self.operations_in_window();
+        serde_json::to_string_pretty(&GeneratedManifest {
+            format: MANIFEST_FORMAT,
+            version: MANIFEST_VERSION,
+            operations: operations.iter().collect(),
+        })
+    }
+
+    /// Writes the manifest to the configured path. It blocks, so it must not run on the runtime
+    pub(crate) fn export(&self) -> Result<(), std::io::Error> {
+        write_manifest(&self.manifest()?, &self.path, &self.temporary_path)
+    }
+}
+
+impl Drop for PersistedQueryManifestRecorder {
+    fn drop(&mut self) {
+        let manifest = match self.manifest() {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                tracing::error!("could not serialize the persisted query manifest: {err}");
+                return;
+            }
+        };
+        let path = std::mem::take(&mut self.path);
+        let temporary_path = std::mem::take(&mut self.temporary_path);
+        let write = move || {
+            if let Err(err) = write_manifest(&manifest, &path, &temporary_path) {
+                tracing::error!(
+                    "could not write the persisted query manifest to {}: {err}",
+                    path.display()
+                );
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+/// Writes the manifest to a temporary file and moves it to the configured path,
+/// so that readers never see a partially written manifest
+fn write_manifest(
+    manifest: &str,
+    path: &Path,
+    temporary_path: &Path,
+) -> Result<(), std::io::Error> {
+    std::fs::write(temporary_path, manifest)?;
+    std::fs::rename(temporary_path, path)
+}
+
+/// A temporary file next to the manifest, so that it can be renamed to the manifest
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(format!(".{}.tmp", Uuid::new_v4()));
+    temporary_path.into()
+}
+
+async fn export_periodically(recorder: Weak<PersistedQueryManifestRecorder>, interval: Duration) {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        interval.tick().await;
+        let Some(recorder) = recorder.upgrade() else {
+            break;
+        };
+        let result = tokio::task::spawn_blocking(move || {
+            let result = recorder.export();
+            (result, recorder.path.clone())
+        })
+        .await;
+        match result {
+            Ok((Err(err), path)) => {
+                tracing::error!(
+                    "could not write the persisted query manifest to {}: {err}",
+                    path.display()
+                );
+            }
+            Ok((Ok(()), _)) => {}
+            Err(err) => {
+                tracing::error!("persisted query manifest export task failed: {err}");
+            }
+        }
+    }
+}
+
+/// Reads the operations of a previously generated manifest, considering them seen now
+async fn load_manifest(path: &Path) -> HashMap<String, Entry> {
+    let Ok(contents) = tokio::fs::read_to_string(path).await else {
+        return HashMap::new();
+    };
+    let manifest: ExistingManifest = match serde_json::from_str(&contents) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            tracing::warn!(
+                "ignoring the existing persisted query manifest at {}: {err}",
+                path.display()
+            );
+            return HashMap::new();
+        }
+    };
+    let now = Instant::now();
+    manifest
+        .operations
+        .into_iter()
+        .map(|operation| {
+            (
+                operation.id.clone(),
+                Entry {
+                    operation,
+                    last_seen: now,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(path: PathBuf, window: Duration) -> PersistedQueryManifestRecorder {
+        PersistedQueryManifestRecorder {
+            temporary_path: temporary_path(&path),
+            path,
+            window,
+            max_operations: 2,
+            operations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_and_exports_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let recorder = recorder(path.clone(), Duration::from_secs(60));
+
+        recorder.record("query B { b }", Some("B"), "query");
+        recorder.record("query A { a }", Some("A"), "query");
+        recorder.record("query A { a }", Some("A"), "query");
+        // The limit is reached
+        recorder.record("mutation C { c }", Some("C"), "mutation");
+        recorder.export().unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(manifest["format"], MANIFEST_FORMAT);
+        assert_eq!(manifest["version"], 1);
+        let operations = manifest["operations"].as_array().unwrap();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0]["name"], "A");
+        assert_eq!(operations[0]["type"], "query");
+        assert_eq!(operations[0]["body"], "query A { a }");
+        assert_eq!(
+            operations[0]["id"],
+            hex::encode(Sha256::digest("query A { a }".as_bytes()))
+        );
+        assert_eq!(operations[1]["name"], "B");
+
+        // The generated manifest can be loaded back
+        assert_eq!(load_manifest(&path).await.len(), 2);
+    }
+
+    #[test]
+    fn recorders_use_their_own_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let previous = recorder(path.clone(), Duration::from_secs(60));
+        let current = recorder(path.clone(), Duration::from_secs(60));
+        assert_ne!(previous.temporary_path, current.temporary_path);
+        assert_eq!(previous.temporary_path.parent(), path.parent());
+
+        previous.record("{ a }", None, "query");
+        previous.export().unwrap();
+        current.export().unwrap();
+        assert!(!previous.temporary_path.exists());
+        assert!(!current.temporary_path.exists());
+    }
+
+    #[test]
+    fn forgets_operations_outside_of_the_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = recorder(dir.path().join("manifest.json"), Duration::from_millis(10));
+
+        recorder.record("{ a }", None, "query");
+        std::thread::sleep(Duration::from_millis(20));
+        recorder.record("{ b }", None, "query");
+
+        let operations = recorder.operations_in_window();
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].body, "{ b }");
+        assert_eq!(operations[0].name, None);
+    }
+}
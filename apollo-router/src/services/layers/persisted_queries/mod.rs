@@ -1,7 +1,7 @@
 mod id_extractor;
 mod manifest_poller;
+mod manifest_recorder;
 
-#[cfg(test)]
 use std::sync::Arc;
 
 use http::header::CACHE_CONTROL;
@@ -11,6 +11,7 @@ use id_extractor::PersistedQueryIdExtractor;
 pub use manifest_poller::FullPersistedQueryOperationId;
 pub use manifest_poller::PersistedQueryManifest;
 pub(crate) use manifest_poller::PersistedQueryManifestPoller;
+use manifest_recorder::PersistedQueryManifestRecorder;
 use tower::BoxError;
 
 use super::query_analysis::ParsedDocument;
@@ -32,6 +33,8 @@ pub(crate) struct PersistedQueryLayer {
     /// Manages polling uplink for persisted queries and caches the current
     /// value of the manifest and projected safelist. None if the layer is disabled.
     pub(crate) manifest_poller: Option<PersistedQueryManifestPoller>,
+    /// Records the freeform operations into a generated manifest. None if manifest generation is disabled.
+    manifest_recorder: Option<Arc<PersistedQueryManifestRecorder>>,
     introspection_enabled: bool,
 }
 
@@ -47,16 +50,27 @@ impl PersistedQueryLayer {
     /// Create a new [`PersistedQueryLayer`] from CLI options, YAML configuration,
     /// and optionally, an existing persisted query manifest poller.
     pub(crate) async fn new(configuration: &Configuration) -> Result<Self, BoxError> {
+        let manifest_generation = &configuration
+            .persisted_queries
+            .experimental_manifest_generation;
+        let manifest_recorder = if manifest_generation.enabled {
+            Some(PersistedQueryManifestRecorder::new(manifest_generation).await)
+        } else {
+            None
+        };
+
         if configuration.persisted_queries.enabled {
             Ok(Self {
                 manifest_poller: Some(
                     PersistedQueryManifestPoller::new(configuration.clone()).await?,
                 ),
+                manifest_recorder,
                 introspection_enabled: configuration.supergraph.introspection,
             })
         } else {
             Ok(Self {
                 manifest_poller: None,
+                manifest_recorder,
                 introspection_enabled: configuration.supergraph.introspection,
             })
         }
@@ -182,6 +196,10 @@ impl PersistedQueryLayer {
         &self,
         request: SupergraphRequest,
     ) -> Result<SupergraphRequest, SupergraphResponse> {
+        if let Some(manifest_recorder) = &self.manifest_recorder {
+            record_freeform_operation(manifest_recorder, &request);
+        }
+
         let manifest_poller = match &self.manifest_poller {
             // PQ feature entirely disabled; just pass through.
             None => return Ok(request),
@@ -284,6 +302,39 @@ impl PersistedQueryLayer {
     }
 }
 
+/// Records the freeform operations that were not resolved from the manifest, except for introspection
+fn record_freeform_operation(
+    manifest_recorder: &PersistedQueryManifestRecorder,
+    request: &SupergraphRequest,
+) {
+    let Some(operation_body) = request.supergraph_request.body().query.as_ref() else {
+        return;
+    };
+    let doc = request.context.extensions().with_lock(|lock| {
+        if lock.get::<UsedQueryIdFromManifest>().is_some() {
+            None
+        } else {
+            lock.get::<ParsedDocument>().cloned()
+        }
+    });
+    let Some(doc) = doc else {
+        return;
+    };
+    if doc
+        .executable
+        .operations
+        .iter()
+        .all(|op| op.is_introspection(&doc.executable))
+    {
+        return;
+    }
+    manifest_recorder.record(
+        operation_body,
+        doc.operation.name.as_ref().map(|name| name.as_str()),
+        doc.operation.operation_type.name(),
+    );
+}
+
 fn log_unknown_operation(operation_body: &str) {
     tracing::warn!(message = "unknown operation", operation_body);
 }
@@ -419,6 +470,7 @@ mod tests {
     use crate::assert_snapshot_subscriber;
     use crate::configuration::Apq;
     use crate::configuration::PersistedQueries;
+    use crate::configuration::PersistedQueriesManifestGeneration;
     use crate::configuration::PersistedQueriesSafelist;
     use crate::configuration::Supergraph;
     use crate::metrics::FutureMetricsExt;
@@ -1318,4 +1370,60 @@ mod tests {
             .expect("could not get response from pq layer");
         assert_eq!(response.errors, vec![graphql_err_cannot_send_id_and_body()]);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn manifest_generation_records_freeform_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let config = Configuration::fake_builder()
+            .persisted_query(
+                PersistedQueries::builder()
+                    .enabled(false)
+                    .experimental_manifest_generation(PersistedQueriesManifestGeneration {
+                        enabled: true,
+                        path: path.clone(),
+                        ..Default::default()
+                    })
+                    .build(),
+            )
+            .supergraph(Supergraph::fake_builder().introspection(true).build())
+            .build()
+            .unwrap();
+        let pq_layer = PersistedQueryLayer::new(&config).await.unwrap();
+        let schema = Arc::new(
+            Schema::parse(
+                include_str!("../../../testdata/supergraph.graphql"),
+                &Default::default(),
+            )
+            .unwrap(),
+        );
+        let query_analysis_layer = QueryAnalysisLayer::new(schema, Arc::new(config)).await;
+
+        for body in [
+            "query SomeQuery { me { id } }",
+            "query SomeQuery { me { id } }",
+            "{ __typename }",
+        ] {
+            let request = run_first_two_layers(&pq_layer, &query_analysis_layer, body, false).await;
+            pq_layer
+                .supergraph_request_with_analyzed_query(request)
+                .await
+                .ok()
+                .expect("pq layer second hook returned error response");
+        }
+
+        // The manifest is written when the layer is dropped
+        drop(pq_layer);
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            manifest["operations"],
+            json!([{
+                "id": "e31cc5f37d41a6fcf60363f8fab48aff556bfdbde7d4566a05f687b4a17d5b10",
+                "name": "SomeQuery",
+                "type": "query",
+                "body": "query SomeQuery { me { id } }",
+            }])
+        );
+    }
 }
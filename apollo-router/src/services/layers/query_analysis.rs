@@ -11,6 +11,8 @@ use apollo_compiler::ExecutableDocument;
 use apollo_compiler::Node;
 use http::StatusCode;
 use lru::LruCache;
+use sha2::Digest;
+use sha2::Sha256;
 use tokio::sync::Mutex;
 
 use crate::apollo_studio_interop::generate_extended_references;
@@ -38,21 +40,83 @@ use crate::Context;
 
 /// [`Layer`] for QueryAnalysis implementation.
 #[derive(Clone)]
-#[allow(clippy::type_complexity)]
 pub(crate) struct QueryAnalysisLayer {
     pub(crate) schema: Arc<Schema>,
     configuration: Arc<Configuration>,
-    cache: Arc<Mutex<LruCache<QueryAnalysisKey, Result<(Context, ParsedDocument), SpecError>>>>,
+    cache: Arc<Mutex<QueryAnalysisCache>>,
     enable_authorization_directives: bool,
     metrics_reference_mode: ApolloMetricsReferenceMode,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct QueryAnalysisKey {
-    query: String,
+    schema_id: Arc<String>,
+    /// SHA-256 of the query string
+    query_hash: [u8; 32],
     operation_name: Option<String>,
 }
 
+type QueryAnalysisEntry = Result<(Context, ParsedDocument), SpecError>;
+
+/// Parsed and validated operations, bounded by their number and by the total length of their
+/// query strings
+struct QueryAnalysisCache {
+    entries: LruCache<QueryAnalysisKey, (QueryAnalysisEntry, usize)>,
+    max_bytes: Option<usize>,
+    bytes: usize,
+}
+
+impl QueryAnalysisCache {
+    fn new(configuration: &Configuration) -> Self {
+        let query_planning = &configuration.supergraph.query_planning;
+        Self {
+            entries: LruCache::new(query_planning.cache.in_memory.limit),
+            max_bytes: query_planning.experimental_parsed_operations_max_bytes,
+            bytes: 0,
+        }
+    }
+
+    fn get(&mut self, key: &QueryAnalysisKey) -> Option<QueryAnalysisEntry> {
+        let entry = self.entries.get(key).map(|(entry, _)| entry.clone());
+        if entry.is_some() {
+            u64_counter!(
+                "apollo_router_cache_hit_count",
+                "Number of cache hits",
+                1,
+                kind = "query parsing",
+                storage = "memory"
+            );
+        } else {
+            u64_counter!(
+                "apollo_router_cache_miss_count",
+                "Number of cache misses",
+                1,
+                kind = "query parsing",
+                storage = "memory"
+            );
+        }
+        entry
+    }
+
+    fn put(&mut self, key: QueryAnalysisKey, entry: QueryAnalysisEntry, size: usize) {
+        if self.max_bytes.is_some_and(|max_bytes| size > max_bytes) {
+            return;
+        }
+        self.bytes += size;
+        if let Some((_, (_, evicted_size))) = self.entries.push(key, (entry, size)) {
+            self.bytes -= evicted_size;
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            while self.bytes > max_bytes {
+                let Some((_, (_, evicted_size))) = self.entries.pop_lru() else {
+                    break;
+                };
+                self.bytes -= evicted_size;
+            }
+        }
+    }
+}
+
 impl QueryAnalysisLayer {
     pub(crate) async fn new(schema: Arc<Schema>, configuration: Arc<Configuration>) -> Self {
         let enable_authorization_directives =
@@ -61,20 +125,20 @@ impl QueryAnalysisLayer {
 
         Self {
             schema,
-            cache: Arc::new(Mutex::new(LruCache::new(
-                configuration
-                    .supergraph
-                    .query_planning
-                    .cache
-                    .in_memory
-                    .limit,
-            ))),
+            cache: Arc::new(Mutex::new(QueryAnalysisCache::new(&configuration))),
             enable_authorization_directives,
             configuration,
             metrics_reference_mode,
         }
     }
 
+    /// Removes all the parsed operations from the cache
+    pub(crate) async fn clear_cache(&self) {
+        let mut cache = self.cache.lock().await;
+        cache.entries.clear();
+        cache.bytes = 0;
+    }
+
     pub(crate) async fn parse_document(
         &self,
         query: &str,
@@ -141,26 +205,20 @@ impl QueryAnalysisLayer {
             .query
             .clone()
             .expect("query presence was already checked");
-        let entry = self
-            .cache
-            .lock()
-            .await
-            .get(&QueryAnalysisKey {
-                query: query.clone(),
-                operation_name: op_name.clone(),
-            })
-            .cloned();
+        let key = QueryAnalysisKey {
+            schema_id: self.schema.schema_id.clone(),
+            query_hash: Sha256::digest(query.as_bytes()).into(),
+            operation_name: op_name.clone(),
+        };
+        let entry = self.cache.lock().await.get(&key);
 
         let res = match entry {
             None => match self.parse_document(&query, op_name.as_deref()).await {
                 Err(errors) => {
-                    (*self.cache.lock().await).put(
-                        QueryAnalysisKey {
-                            query,
-                            operation_name: op_name.clone(),
-                        },
-                        Err(errors.clone()),
-                    );
+                    self.cache
+                        .lock()
+                        .await
+                        .put(key, Err(errors.clone()), query.len());
                     Err(errors)
                 }
                 Ok(doc) => {
@@ -183,12 +241,10 @@ impl QueryAnalysisLayer {
                         .insert(OPERATION_KIND, operation_kind)
                         .expect("cannot insert operation kind in the context; this is a bug");
 
-                    (*self.cache.lock().await).put(
-                        QueryAnalysisKey {
-                            query,
-                            operation_name: op_name.clone(),
-                        },
+                    self.cache.lock().await.put(
+                        key,
                         Ok((context.clone(), doc.clone())),
+                        query.len(),
                     );
 
                     Ok((context, doc))
@@ -331,3 +387,44 @@ impl PartialEq for ParsedDocumentInner {
 }
 
 impl Eq for ParsedDocumentInner {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(query: &str) -> QueryAnalysisKey {
+        QueryAnalysisKey {
+            schema_id: Arc::new("schema".to_string()),
+            query_hash: Sha256::digest(query.as_bytes()).into(),
+            operation_name: None,
+        }
+    }
+
+    #[test]
+    fn cache_is_bounded_by_query_size() {
+        let mut configuration = Configuration::default();
+        configuration
+            .supergraph
+            .query_planning
+            .experimental_parsed_operations_max_bytes = Some(10);
+        let mut cache = QueryAnalysisCache::new(&configuration);
+
+        let entry = || Err(SpecError::UnknownOperation("Q".to_string()));
+        cache.put(key("{ a }"), entry(), 4);
+        cache.put(key("{ b }"), entry(), 4);
+        assert!(cache.get(&key("{ a }")).is_some());
+
+        // `{ b }` is the least recently used entry
+        cache.put(key("{ c }"), entry(), 4);
+        assert!(cache.get(&key("{ b }")).is_none());
+        assert!(cache.get(&key("{ a }")).is_some());
+        assert!(cache.get(&key("{ c }")).is_some());
+        assert_eq!(cache.bytes, 8);
+
+        // Operations larger than the limit are not kept and do not evict other entries
+        cache.put(key("{ d }"), entry(), 12);
+        assert!(cache.get(&key("{ d }")).is_none());
+        assert!(cache.get(&key("{ a }")).is_some());
+        assert_eq!(cache.bytes, 8);
+    }
+}
@@ -1,7 +1,4 @@
-//!  (A)utomatic (P)ersisted (Q)ueries cache.
-//!
-//!  For more information on APQ see:
-//!  <https://www.apollographql.com/docs/apollo-server/performance/apq/>
+//! Static pages served by the router: the homepage and Apollo Sandbox.
 
 use std::ops::ControlFlow;
 
@@ -30,16 +27,21 @@ pub(crate) struct StaticPageLayer {
 }
 
 impl StaticPageLayer {
-    pub(crate) fn new(configuration: &Configuration) -> Self {
+    pub(crate) fn new(configuration: &Configuration) -> Result<Self, BoxError> {
         let static_page = if configuration.sandbox.enabled {
             Some(Bytes::from(sandbox_page_content()))
         } else if configuration.homepage.enabled {
-            Some(Bytes::from(home_page_content(&configuration.homepage)))
+            match &configuration.homepage.path {
+                Some(path) => Some(Bytes::from(std::fs::read(path).map_err(|err| {
+                    format!("could not read the homepage at {}: {}", path.display(), err)
+                })?)),
+                None => Some(Bytes::from(home_page_content(&configuration.homepage))),
+            }
         } else {
             None
         };
 
-        Self { static_page }
+        Ok(Self { static_page })
     }
 }
 
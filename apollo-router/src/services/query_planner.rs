@@ -20,6 +20,9 @@ use crate::Context;
 pub(crate) struct PlanOptions {
     /// Which labels to override during query planning
     pub(crate) override_conditions: Vec<String>,
+    /// Which subgraphs to avoid during query planning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) disabled_subgraphs: Vec<String>,
 }
 
 assert_impl_all!(Request: Send);
@@ -32,6 +35,9 @@ pub(crate) struct Request {
     pub(crate) document: ParsedDocument,
     pub(crate) metadata: crate::plugins::authorization::CacheKeyMetadata,
     pub(crate) plan_options: PlanOptions,
+    /// Set when the query is planned to warm up the cache rather than for a client request, to
+    /// plan it with a lower priority
+    pub(crate) warm_up: bool,
 }
 
 #[buildstructor::buildstructor]
@@ -53,6 +59,7 @@ impl Request {
             document,
             metadata,
             plan_options,
+            warm_up: false,
         }
     }
 }
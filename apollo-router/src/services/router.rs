@@ -40,6 +40,7 @@ pub type Body = hyper::Body;
 pub type Error = hyper::Error;
 
 pub mod body;
+pub(crate) mod error_detail;
 pub(crate) mod service;
 #[cfg(test)]
 mod tests;
@@ -381,7 +382,10 @@ impl Response {
 pub(crate) struct ClientRequestAccepts {
     pub(crate) multipart_defer: bool,
     pub(crate) multipart_subscription: bool,
+    pub(crate) event_stream: bool,
     pub(crate) json: bool,
+    /// The client explicitly accepts `application/graphql-response+json`
+    pub(crate) graphql_response_json: bool,
     pub(crate) wildcard: bool,
 }
 
@@ -0,0 +1,314 @@
+//! Verbosity of the errors sent to clients
+//!
+//! The level of a request is selected by the outermost layer of the router pipeline, which
+//! applies it to every response that does not have it applied yet: router errors, errors of
+//! router stage plugins and the response to a panic. The router service applies it to the
+//! responses it streams, as it produces them.
+
+use std::sync::Arc;
+
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use mime::APPLICATION_JSON;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::BoxError;
+
+use crate::configuration::Supergraph;
+use crate::graphql;
+use crate::json_ext::Object;
+use crate::json_ext::Value;
+use crate::plugins::authentication::constant_time_eq;
+use crate::services::layers::content_negotiation::GRAPHQL_JSON_RESPONSE_HEADER_VALUE;
+use crate::services::router;
+use crate::services::router::body::get_body_bytes;
+use crate::services::router::body::RouterBody;
+use crate::Context;
+
+/// Header sent by trusted callers to receive errors with the `full` detail level
+pub(crate) const ERROR_DETAIL_OVERRIDE_HEADER_NAME: &str = "apollo-error-detail-override";
+
+/// Message of the errors sent with the `minimal` detail level
+const MINIMAL_ERROR_MESSAGE: &str = "An error occurred while processing the request";
+
+/// How much of the errors is sent to clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ErrorDetailLevel {
+    /// Errors are sent as they are
+    #[default]
+    Full,
+    /// The message, locations, path and code of errors are sent. Every other extension, such as
+    /// `details` or `errorChain`, is removed
+    CodesOnly,
+    /// The path and code of errors are sent, with a generic message
+    Minimal,
+}
+
+/// Marks the responses whose errors already have the detail level of the request applied
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ErrorDetailApplied;
+
+impl ErrorDetailLevel {
+    /// The level selected for the request of this context
+    pub(crate) fn of(context: &Context) -> Self {
+        context
+            .extensions()
+            .with_lock(|lock| lock.get::<ErrorDetailLevel>().copied())
+            .unwrap_or_default()
+    }
+
+    /// Removes the parts of the errors of a router response that are not sent with this level,
+    /// unless it is marked with [`ErrorDetailApplied`]. Only JSON bodies are modified, other
+    /// values than the errors are sent as they are.
+    pub(crate) async fn apply_to_router_response(
+        self,
+        response: router::Response,
+    ) -> Result<router::Response, BoxError> {
+        let router::Response { response, context } = response;
+        let is_json = response.headers().get(CONTENT_TYPE).map_or(true, |value| {
+            value.to_str().is_ok_and(|value| {
+                value.starts_with(APPLICATION_JSON.essence_str())
+                    || value.starts_with(GRAPHQL_JSON_RESPONSE_HEADER_VALUE)
+            })
+        });
+        if self == ErrorDetailLevel::Full
+            || !is_json
+            || response.extensions().get::<ErrorDetailApplied>().is_some()
+        {
+            return Ok(router::Response { response, context });
+        }
+
+        let (parts, body) = response.into_parts();
+        let bytes = get_body_bytes(body).await?;
+        let body = match serde_json::from_slice::<Value>(&bytes) {
+            Ok(mut value) => {
+                if let Some(response) = value.as_object_mut() {
+                    self.apply_to_error_values(response.get_mut("errors"));
+                    if let Some(Value::Array(incremental)) = response.get_mut("incremental") {
+                        for incremental in incremental.iter_mut().filter_map(Value::as_object_mut) {
+                            self.apply_to_error_values(incremental.get_mut("errors"));
+                        }
+                    }
+                }
+                RouterBody::from(serde_json::to_vec(&value)?)
+            }
+            Err(_) => RouterBody::from(bytes),
+        };
+        Ok(router::Response {
+            response: http::Response::from_parts(parts, body.into_inner()),
+            context,
+        })
+    }
+
+    fn apply_to_error_values(self, errors: Option<&mut Value>) {
+        let Some(Value::Array(errors)) = errors else {
+            return;
+        };
+        for error in errors {
+            if let Ok(mut parsed) = serde_json_bytes::from_value::<graphql::Error>(error.clone()) {
+                self.apply_to_error(&mut parsed);
+                if let Ok(value) = serde_json_bytes::to_value(&parsed) {
+                    *error = value;
+                }
+            }
+        }
+    }
+
+    /// Removes the parts of the errors of a response that are not sent with this level
+    pub(crate) fn apply(self, response: &mut graphql::Response) {
+        if self == ErrorDetailLevel::Full {
+            return;
+        }
+        let incremental_errors = response
+            .incremental
+            .iter_mut()
+            .flat_map(|incremental| incremental.errors.iter_mut());
+        for error in response.errors.iter_mut().chain(incremental_errors) {
+            self.apply_to_error(error);
+        }
+    }
+
+    /// Removes the parts of an error that are not sent with this level
+    pub(crate) fn apply_to_error(self, error: &mut graphql::Error) {
+        match self {
+            ErrorDetailLevel::Full => {}
+            ErrorDetailLevel::CodesOnly => retain_code(&mut error.extensions),
+            ErrorDetailLevel::Minimal => {
+                retain_code(&mut error.extensions);
+                error.message = MINIMAL_ERROR_MESSAGE.to_string();
+                error.locations.clear();
+            }
+        }
+    }
+}
+
+fn retain_code(extensions: &mut Object) {
+    let code = extensions.remove("code");
+    *extensions = Object::new();
+    if let Some(code) = code {
+        extensions.insert("code", code);
+    }
+}
+
+/// Selects the error detail level of each request
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ErrorDetail {
+    level: ErrorDetailLevel,
+    override_secret: Option<Arc<str>>,
+}
+
+impl ErrorDetail {
+    pub(crate) fn new(configuration: &Supergraph) -> Self {
+        Self {
+            level: configuration.error_detail_level,
+            override_secret: configuration
+                .error_detail_override_secret
+                .as_deref()
+                .map(Into::into),
+        }
+    }
+
+    /// The level of a request: trusted callers sending the override secret receive every
+    /// detail, other callers receive the configured level
+    pub(crate) fn level_for(&self, headers: &HeaderMap) -> ErrorDetailLevel {
+        let is_trusted = self.override_secret.as_deref().is_some_and(|secret| {
+            headers
+                .get(ERROR_DETAIL_OVERRIDE_HEADER_NAME)
+                .is_some_and(|value| constant_time_eq(value.as_bytes(), secret.as_bytes()))
+        });
+        if is_trusted {
+            ErrorDetailLevel::Full
+        } else {
+            self.level
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use serde_json_bytes::json;
+
+    use super::*;
+    use crate::json_ext::Path;
+
+    fn error() -> graphql::Error {
+        graphql::Error::builder()
+            .message("could not connect to the database at 10.0.0.12")
+            .locations(vec![graphql::Location { line: 1, column: 3 }])
+            .path(Path::from("topProducts/0/name"))
+            .extension_code("SUBREQUEST_HTTP_ERROR")
+            .extension("details", "connection refused")
+            .extension("errorChain", json!(["timeout"]))
+            .build()
+    }
+
+    #[test]
+    fn full_level_keeps_errors() {
+        let mut response = graphql::Response::builder().error(error()).build();
+        ErrorDetailLevel::Full.apply(&mut response);
+        assert_eq!(response.errors, vec![error()]);
+    }
+
+    #[test]
+    fn codes_only_level_removes_extensions() {
+        let mut response = graphql::Response::builder().error(error()).build();
+        ErrorDetailLevel::CodesOnly.apply(&mut response);
+        let error = &response.errors[0];
+        assert_eq!(
+            error.message,
+            "could not connect to the database at 10.0.0.12"
+        );
+        assert_eq!(error.locations.len(), 1);
+        assert_eq!(
+            serde_json_bytes::Value::Object(error.extensions.clone()),
+            json!({ "code": "SUBREQUEST_HTTP_ERROR" })
+        );
+    }
+
+    #[test]
+    fn minimal_level_removes_messages() {
+        let mut response = graphql::Response::builder().error(error()).build();
+        ErrorDetailLevel::Minimal.apply(&mut response);
+        let error = &response.errors[0];
+        assert_eq!(error.message, MINIMAL_ERROR_MESSAGE);
+        assert!(error.locations.is_empty());
+        assert_eq!(error.path, Some(Path::from("topProducts/0/name")));
+        assert_eq!(
+            serde_json_bytes::Value::Object(error.extensions.clone()),
+            json!({ "code": "SUBREQUEST_HTTP_ERROR" })
+        );
+    }
+
+    #[tokio::test]
+    async fn router_responses_are_rewritten_unless_marked() {
+        let body = json!({ "data": null, "errors": [error()] });
+        let response = || {
+            http::Response::builder()
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .body(RouterBody::from(serde_json::to_vec(&body).unwrap()).into_inner())
+                .unwrap()
+        };
+        let body_of = |response: router::Response| async move {
+            let bytes = get_body_bytes(response.response.into_body()).await.unwrap();
+            serde_json::from_slice::<Value>(&bytes).unwrap()
+        };
+
+        let response = router::Response {
+            response: response(),
+            context: Context::new(),
+        };
+        let response = ErrorDetailLevel::CodesOnly
+            .apply_to_router_response(response)
+            .await
+            .unwrap();
+        assert_eq!(
+            body_of(response).await,
+            json!({
+                "data": null,
+                "errors": [{
+                    "message": "could not connect to the database at 10.0.0.12",
+                    "locations": [{ "line": 1, "column": 3 }],
+                    "path": ["topProducts", 0, "name"],
+                    "extensions": { "code": "SUBREQUEST_HTTP_ERROR" }
+                }]
+            })
+        );
+
+        let mut marked = response();
+        marked.extensions_mut().insert(ErrorDetailApplied);
+        let response = router::Response {
+            response: marked,
+            context: Context::new(),
+        };
+        let response = ErrorDetailLevel::CodesOnly
+            .apply_to_router_response(response)
+            .await
+            .unwrap();
+        assert_eq!(body_of(response).await, body);
+    }
+
+    #[test]
+    fn trusted_callers_receive_full_errors() {
+        let configuration = Supergraph::fake_builder()
+            .error_detail_level(ErrorDetailLevel::Minimal)
+            .error_detail_override_secret("s3cr3t".to_string())
+            .build();
+        let error_detail = ErrorDetail::new(&configuration);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(error_detail.level_for(&headers), ErrorDetailLevel::Minimal);
+        headers.insert(
+            ERROR_DETAIL_OVERRIDE_HEADER_NAME,
+            HeaderValue::from_static("wrong"),
+        );
+        assert_eq!(error_detail.level_for(&headers), ErrorDetailLevel::Minimal);
+        headers.insert(
+            ERROR_DETAIL_OVERRIDE_HEADER_NAME,
+            HeaderValue::from_static("s3cr3t"),
+        );
+        assert_eq!(error_detail.level_for(&headers), ErrorDetailLevel::Full);
+    }
+}
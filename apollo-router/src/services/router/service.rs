@@ -12,10 +12,12 @@ use bytes::BytesMut;
 use futures::future::join_all;
 use futures::future::ready;
 use futures::future::BoxFuture;
+use futures::future::FutureExt;
 use futures::stream;
 use futures::stream::once;
 use futures::stream::StreamExt;
 use futures::TryFutureExt;
+use http::header::CACHE_CONTROL;
 use http::header::CONTENT_TYPE;
 use http::header::VARY;
 use http::request::Parts;
@@ -27,28 +29,40 @@ use http::StatusCode;
 use http_body::Body as _;
 use mime::APPLICATION_JSON;
 use multimap::MultiMap;
+use tower::service_fn;
 use tower::BoxError;
 use tower::Layer;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 use tower_service::Service;
 use tracing::Instrument;
+use tracing::Span;
 
 use super::Body;
 use super::ClientRequestAccepts;
-use crate::axum_factory::CanceledRequest;
+use crate::axum_factory::RequestCancellation;
 use crate::batching::Batch;
 use crate::batching::BatchQuery;
 use crate::cache::DeduplicatingCache;
+use crate::configuration::tenants::TenantMatch;
 use crate::configuration::Batching;
 use crate::configuration::BatchingMode;
 use crate::context::CONTAINS_GRAPHQL_ERROR;
+use crate::context::TENANT_NAME;
 use crate::graphql;
 use crate::http_ext;
+use crate::layers::catch_panic::CatchPanicLayer;
+use crate::layers::catch_panic::CatchPanicStream;
+use crate::layers::catch_panic::InternalPanic;
+use crate::layers::ServiceBuilderExt;
 #[cfg(test)]
 use crate::plugin::test::MockSupergraphService;
+use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
+use crate::plugins::telemetry::tracing::apollo_telemetry::APOLLO_PRIVATE_TENANT;
 use crate::protocols::multipart::Multipart;
 use crate::protocols::multipart::ProtocolMode;
+use crate::protocols::sse::EventStream;
+use crate::protocols::sse::EVENT_STREAM_CONTENT_TYPE;
 use crate::query_planner::InMemoryCachePlanner;
 use crate::router_factory::RouterFactory;
 use crate::services::layers::apq::APQLayer;
@@ -61,6 +75,9 @@ use crate::services::new_service::ServiceFactory;
 use crate::services::router;
 use crate::services::router::body::get_body_bytes;
 use crate::services::router::body::RouterBody;
+use crate::services::router::error_detail::ErrorDetail;
+use crate::services::router::error_detail::ErrorDetailApplied;
+use crate::services::router::error_detail::ErrorDetailLevel;
 #[cfg(test)]
 use crate::services::supergraph;
 use crate::services::HasPlugins;
@@ -237,6 +254,7 @@ impl RouterService {
         &self,
         supergraph_request: SupergraphRequest,
     ) -> Result<router::Response, BoxError> {
+        let error_detail_level = ErrorDetailLevel::of(&supergraph_request.context);
         let mut request_res = self
             .persisted_query_layer
             .supergraph_request(supergraph_request);
@@ -263,20 +281,25 @@ impl RouterService {
         let ClientRequestAccepts {
             wildcard: accepts_wildcard,
             json: accepts_json,
+            graphql_response_json: accepts_graphql_response_json,
             multipart_defer: accepts_multipart_defer,
             multipart_subscription: accepts_multipart_subscription,
+            event_stream: accepts_event_stream,
         } = context
             .extensions()
             .with_lock(|lock| lock.get().cloned())
             .unwrap_or_default();
 
-        let (mut parts, mut body) = response.into_parts();
+        let (mut parts, body) = response.into_parts();
         process_vary_header(&mut parts.headers);
-
-        if context
-            .extensions()
-            .with_lock(|lock| lock.get::<CanceledRequest>().is_some())
-        {
+        // Responses are streamed, so the level is applied here rather than by the outermost layer
+        let mut body = body.map(move |mut response| {
+            error_detail_level.apply(&mut response);
+            response
+        });
+        parts.extensions.insert(ErrorDetailApplied);
+
+        if RequestCancellation::is_cancelled(&context) {
             parts.status = StatusCode::from_u16(499)
                 .expect("499 is not a standard status code but common enough");
         }
@@ -304,9 +327,22 @@ impl RouterService {
                         Self::count_errors(&response.errors);
                     }
 
-                    parts
-                        .headers
-                        .insert(CONTENT_TYPE, APPLICATION_JSON_HEADER_VALUE.clone());
+                    if accepts_graphql_response_json {
+                        parts.headers.insert(
+                            CONTENT_TYPE,
+                            HeaderValue::from_static(GRAPHQL_JSON_RESPONSE_HEADER_VALUE),
+                        );
+                        // With `application/graphql-response+json`, a response without data is a
+                        // request error, which the GraphQL over HTTP specification requires to
+                        // be sent with a 4xx or 5xx status code
+                        if response.data.is_none() && parts.status.is_success() {
+                            parts.status = StatusCode::BAD_REQUEST;
+                        }
+                    } else {
+                        parts
+                            .headers
+                            .insert(CONTENT_TYPE, APPLICATION_JSON_HEADER_VALUE.clone());
+                    }
                     tracing::trace_span!("serialize_response").in_scope(|| {
                         let body = serde_json::to_string(&response)?;
                         Ok(router::Response {
@@ -377,6 +413,42 @@ impl RouterService {
                     });
 
                     Ok(RouterResponse { response, context })
+                } else if accepts_event_stream {
+                    parts.headers.insert(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(EVENT_STREAM_CONTENT_TYPE),
+                    );
+                    parts
+                        .headers
+                        .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+                    if !response.errors.is_empty() {
+                        Self::count_errors(&response.errors);
+                    }
+
+                    // Useful when you're using a proxy like nginx which enable proxy_buffering by default (http://nginx.org/en/docs/http/ngx_http_proxy_module.html#proxy_buffering)
+                    parts.headers.insert(
+                        ACCEL_BUFFERING_HEADER_NAME.clone(),
+                        ACCEL_BUFFERING_HEADER_VALUE.clone(),
+                    );
+                    let body = body.inspect(|response| {
+                        if !response.errors.is_empty() {
+                            Self::count_errors(&response.errors);
+                        }
+                    });
+                    // The first response of a subscription only tells that it has been created
+                    let event_stream = match response.subscribed {
+                        Some(true) => EventStream::new(body),
+                        _ => EventStream::new(once(ready(response)).chain(body)),
+                    };
+
+                    Ok(RouterResponse {
+                        response: http::Response::from_parts(
+                            parts,
+                            RouterBody::wrap_stream(event_stream).into_inner(),
+                        ),
+                        context,
+                    })
                 } else {
                     u64_counter!(
                         "apollo.router.graphql_error",
@@ -454,7 +526,7 @@ impl RouterService {
         // Requests can be cancelled at any point of the router pipeline, but all failures bubble back
         // up through here, so we can catch them without having to specially handle batch queries in
         // other portions of the codebase.
-        let futures = supergraph_requests
+        let futures: Vec<_> = supergraph_requests
             .into_iter()
             .map(|supergraph_request| async {
                 // We clone the context here, because if the request results in an Err, the
@@ -479,14 +551,25 @@ impl RouterService {
                 }
 
                 result
-            });
+            })
+            .collect();
 
         // Use join_all to preserve ordering of concurrent operations
         // (Short circuit processing and propagate any errors in the batch)
         // Note: We use `join_all` here since it awaits all futures before returning, thus allowing us to
         // handle cancellation logic without fear of the other futures getting killed.
-        let mut results: Vec<router::Response> = join_all(futures)
-            .await
+        // When the concurrency of a batch is limited, `buffered` also preserves the ordering and
+        // awaits all the futures.
+        let results = match self.batch_concurrency(&context, futures.len()) {
+            Some(concurrency) => {
+                stream::iter(futures)
+                    .buffered(concurrency)
+                    .collect::<Vec<_>>()
+                    .await
+            }
+            None => join_all(futures).await,
+        };
+        let mut results: Vec<router::Response> = results
             .into_iter()
             .collect::<Result<Vec<router::Response>, BoxError>>()?;
 
@@ -520,6 +603,17 @@ impl RouterService {
         }
     }
 
+    /// The number of operations of a batch executed at the same time, if it is limited
+    fn batch_concurrency(&self, context: &Context, batch_size: usize) -> Option<usize> {
+        let concurrency = self.batching.maximum_concurrency?.get();
+        // With subgraph batching, a subgraph batch is only sent once every operation of the
+        // client batch reached the subgraph service, so all of them must run at the same time
+        let subgraph_batching = context
+            .extensions()
+            .with_lock(|lock| lock.contains_key::<BatchQuery>());
+        (concurrency < batch_size && !subgraph_batching).then_some(concurrency)
+    }
+
     async fn translate_query_request(
         &self,
         parts: &Parts,
@@ -666,6 +760,19 @@ impl RouterService {
         let mut results = Vec::with_capacity(ok_results.len());
         let batch_size = ok_results.len();
 
+        if let Some(maximum_size) = self.batching.maximum_size {
+            if is_batch && batch_size > maximum_size.get() {
+                return Err(TranslateError {
+                    status: StatusCode::UNPROCESSABLE_ENTITY,
+                    error: "batch limit exceeded",
+                    extension_code: "BATCH_LIMIT_EXCEEDED",
+                    extension_details: format!(
+                        "the batch contains {batch_size} operations, but the maximum batch size is {maximum_size}"
+                    ),
+                });
+            }
+        }
+
         // Modifying our Context extensions.
         // If we are processing a batch (is_batch == true), insert our batching configuration.
         // If subgraph batching configuration exists and is enabled for any of our subgraphs, we create our shared batch details
@@ -718,6 +825,7 @@ impl RouterService {
             let client_request_accepts_opt = context
                 .extensions()
                 .with_lock(|lock| lock.get::<ClientRequestAccepts>().cloned());
+            let error_detail_level = ErrorDetailLevel::of(context);
             // We are only going to insert a BatchQuery if Subgraph processing is enabled
             let b_for_index_opt = if let Some(shared_batch_details) = &shared_batch_details {
                 Some(
@@ -737,6 +845,7 @@ impl RouterService {
                 if let Some(client_request_accepts) = client_request_accepts_opt {
                     lock.insert(client_request_accepts);
                 }
+                lock.insert(error_detail_level);
                 lock.insert(self.batching.clone());
                 // We are only going to insert a BatchQuery if Subgraph processing is enabled
                 if let Some(b_for_index) = b_for_index_opt {
@@ -844,12 +953,44 @@ pub(crate) struct RouterCreator {
     pub(crate) persisted_query_layer: Arc<PersistedQueryLayer>,
     query_analysis_layer: QueryAnalysisLayer,
     batching: Batching,
+    error_detail: ErrorDetail,
+    tenants: Arc<[TenantRouter]>,
+}
+
+/// The router of a tenant, selected for the requests matching its configuration
+#[derive(Clone)]
+pub(crate) struct TenantRouter {
+    pub(crate) name: String,
+    pub(crate) matcher: TenantMatch,
+    pub(crate) router: RouterCreator,
 }
 
 impl ServiceFactory<router::Request> for RouterCreator {
     type Service = router::BoxService;
     fn create(&self) -> Self::Service {
-        self.make().boxed()
+        if self.tenants.is_empty() {
+            return self.make().boxed();
+        }
+
+        let creator = self.clone();
+        service_fn(move |request: router::Request| {
+            let tenant = creator
+                .tenants
+                .iter()
+                .find(|tenant| tenant.matcher.matches(&request.router_request));
+            let router = match tenant {
+                Some(tenant) => {
+                    let _ = request.context.insert(TENANT_NAME, tenant.name.clone());
+                    // Lets the Apollo exporter send the trace to the graph of the tenant
+                    Span::current()
+                        .set_span_dyn_attribute(APOLLO_PRIVATE_TENANT, tenant.name.clone().into());
+                    tenant.router.make()
+                }
+                None => creator.make(),
+            };
+            router.oneshot(request)
+        })
+        .boxed()
     }
 }
 
@@ -868,6 +1009,19 @@ impl RouterFactory for RouterCreator {
             .for_each(|p| mm.extend(p.web_endpoints()));
         mm
     }
+
+    fn purge_caches(&self) -> BoxFuture<'static, ()> {
+        let routers: Vec<RouterCreator> = std::iter::once(self.clone())
+            .chain(self.tenants.iter().map(|tenant| tenant.router.clone()))
+            .collect();
+        Box::pin(async move {
+            for router in routers {
+                router.supergraph_creator.clear_query_plan_cache().await;
+                router.query_analysis_layer.clear_cache().await;
+                router.apq_layer.clear_cache().await;
+            }
+        })
+    }
 }
 
 impl RouterCreator {
@@ -877,7 +1031,7 @@ impl RouterCreator {
         supergraph_creator: Arc<SupergraphCreator>,
         configuration: Arc<Configuration>,
     ) -> Result<Self, BoxError> {
-        let static_page = StaticPageLayer::new(&configuration);
+        let static_page = StaticPageLayer::new(&configuration)?;
         let apq_layer = if configuration.apq.enabled {
             APQLayer::with_cache(
                 DeduplicatingCache::from_configuration(&configuration.apq.router.cache, "APQ")
@@ -902,9 +1056,24 @@ impl RouterCreator {
             query_analysis_layer,
             persisted_query_layer,
             batching: configuration.batching.clone(),
+            error_detail: ErrorDetail::new(&configuration.supergraph),
+            tenants: Arc::new([]),
         })
     }
 
+    /// Sends the requests matching a tenant to its router instead of this one
+    pub(crate) fn with_tenants(mut self, tenants: Vec<TenantRouter>) -> Self {
+        self.tenants = tenants.into();
+        self
+    }
+
+    pub(crate) fn tenant(&self, name: &str) -> Option<&RouterCreator> {
+        self.tenants
+            .iter()
+            .find(|tenant| tenant.name == name)
+            .map(|tenant| &tenant.router)
+    }
+
     pub(crate) fn make(
         &self,
     ) -> impl Service<
@@ -921,7 +1090,36 @@ impl RouterCreator {
             self.batching.clone(),
         ));
 
+        let error_detail = self.error_detail.clone();
         ServiceBuilder::new()
+            .map_future_with_request_data(
+                move |req: &router::Request| {
+                    // Selected before any plugin runs, so that every response has the same level
+                    let error_detail_level = error_detail.level_for(req.router_request.headers());
+                    req.context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(error_detail_level));
+                    (req.context.clone(), error_detail_level)
+                },
+                |(context, error_detail_level): (Context, ErrorDetailLevel), future| {
+                    async move {
+                        let request_id: Arc<str> = context.id.as_str().into();
+                        let result: router::ServiceResult = future.await;
+                        let response = match result {
+                            Err(err) if err.is::<InternalPanic>() => {
+                                internal_panic_response(context)?
+                            }
+                            result => result?,
+                        };
+                        let response = error_detail_level
+                            .apply_to_router_response(response)
+                            .await?;
+                        Ok(response.map(|body| catch_body_panics(body, request_id)))
+                    }
+                    .boxed()
+                },
+            )
+            .layer(CatchPanicLayer)
             .layer(self.static_page.clone())
             .service(
                 self.supergraph_creator
@@ -933,6 +1131,33 @@ impl RouterCreator {
     }
 }
 
+/// Streamed bodies, such as deferred responses and subscriptions, run code while they are polled
+/// by hyper, after the response future completed. Their panics are caught too.
+fn catch_body_panics(body: router::Body, request_id: Arc<str>) -> router::Body {
+    // Bodies of a known size are already in memory
+    if http_body::Body::size_hint(&body).exact().is_some() {
+        return body;
+    }
+    router::Body::wrap_stream(CatchPanicStream::new(body, Some(request_id)))
+}
+
+/// The response to a request whose processing panicked. The panic itself is logged by the panic
+/// hook, the request id allows finding it.
+fn internal_panic_response(context: Context) -> router::ServiceResult {
+    let request_id = context.id.clone();
+    router::Response::error_builder()
+        .status_code(StatusCode::INTERNAL_SERVER_ERROR)
+        .error(
+            graphql::Error::builder()
+                .message("internal error while processing the request")
+                .extension_code("APOLLO_ROUTER_INTERNAL_PANIC")
+                .extension("requestId", request_id)
+                .build(),
+        )
+        .context(context)
+        .build()
+}
+
 impl RouterCreator {
     pub(crate) fn previous_cache(&self) -> InMemoryCachePlanner {
         self.supergraph_creator.previous_cache()
@@ -1,7 +1,10 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use futures::stream::StreamExt;
+use http::header::ACCEPT;
 use http::header::CONTENT_TYPE;
 use http::header::VARY;
 use http::HeaderMap;
@@ -13,10 +16,14 @@ use serde_json_bytes::json;
 use tower::ServiceExt;
 use tower_service::Service;
 
+use crate::configuration::Supergraph;
 use crate::graphql;
+use crate::services::layers::content_negotiation::GRAPHQL_JSON_RESPONSE_HEADER_VALUE;
 use crate::services::router;
 use crate::services::router::body::get_body_bytes;
+use crate::services::router::error_detail::ErrorDetailLevel;
 use crate::services::router::service::from_supergraph_mock_callback;
+use crate::services::router::service::from_supergraph_mock_callback_and_configuration;
 use crate::services::router::service::process_vary_header;
 use crate::services::subgraph;
 use crate::services::supergraph;
@@ -24,6 +31,7 @@ use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
 use crate::services::MULTIPART_DEFER_CONTENT_TYPE;
 use crate::test_harness::make_fake_batch;
+use crate::Configuration;
 use crate::Context;
 
 // Test Vary processing
@@ -184,6 +192,131 @@ async fn it_fails_on_no_query() {
     assert!(response.errors[0].extensions.contains_key("code"));
 }
 
+#[tokio::test]
+async fn it_turns_panics_into_internal_errors() {
+    let router_service =
+        from_supergraph_mock_callback(move |_req| panic!("the supergraph service panicked")).await;
+
+    let context = Context::new();
+    let request_id = context.id.clone();
+    let request = SupergraphRequest::fake_builder()
+        .query("{ me { name } }")
+        .context(context)
+        .build()
+        .expect("expecting valid request")
+        .try_into()
+        .unwrap();
+
+    let response = router_service.oneshot(request).await.unwrap();
+    assert_eq!(
+        response.response.status(),
+        http::StatusCode::INTERNAL_SERVER_ERROR
+    );
+    let response = response
+        .into_graphql_response_stream()
+        .await
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        response.errors[0].extensions.get("code"),
+        Some(&json!("APOLLO_ROUTER_INTERNAL_PANIC"))
+    );
+    assert_eq!(
+        response.errors[0].extensions.get("requestId"),
+        Some(&json!(request_id))
+    );
+}
+
+#[tokio::test]
+async fn it_applies_the_error_detail_level_to_internal_errors() {
+    let configuration = Configuration::fake_builder()
+        .supergraph(
+            Supergraph::fake_builder()
+                .error_detail_level(ErrorDetailLevel::Minimal)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let router_service = from_supergraph_mock_callback_and_configuration(
+        move |_req| panic!("the supergraph service panicked"),
+        Arc::new(configuration),
+    )
+    .await;
+
+    let request = SupergraphRequest::fake_builder()
+        .query("{ me { name } }")
+        .build()
+        .expect("expecting valid request")
+        .try_into()
+        .unwrap();
+
+    let response = router_service
+        .oneshot(request)
+        .await
+        .unwrap()
+        .into_graphql_response_stream()
+        .await
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        response.errors[0].message,
+        "An error occurred while processing the request"
+    );
+    assert_eq!(
+        serde_json_bytes::Value::Object(response.errors[0].extensions.clone()),
+        json!({ "code": "APOLLO_ROUTER_INTERNAL_PANIC" })
+    );
+}
+
+#[tokio::test]
+async fn it_negotiates_the_graphql_response_content_type() {
+    async fn call(accept: &'static str) -> router::Response {
+        let router_service = from_supergraph_mock_callback(move |req| {
+            let response = graphql::Response::builder()
+                .error(
+                    graphql::Error::builder()
+                        .message("denied")
+                        .extension_code("DENIED")
+                        .build(),
+                )
+                .build();
+            Ok(SupergraphResponse::new_from_graphql_response(
+                response,
+                req.context,
+            ))
+        })
+        .await;
+        let request = SupergraphRequest::fake_builder()
+            .query("{ me { name } }")
+            .header(ACCEPT, accept)
+            .build()
+            .expect("expecting valid request")
+            .try_into()
+            .unwrap();
+        router_service.oneshot(request).await.unwrap()
+    }
+
+    // A request error is sent with a 4xx status code with `application/graphql-response+json`
+    let response = call("application/graphql-response+json, application/json;q=0.9").await;
+    assert_eq!(response.response.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.response.headers().get(CONTENT_TYPE).unwrap(),
+        GRAPHQL_JSON_RESPONSE_HEADER_VALUE
+    );
+
+    // The status code is left unchanged with `application/json`
+    let response = call(APPLICATION_JSON.essence_str()).await;
+    assert_eq!(response.response.status(), http::StatusCode::OK);
+    assert_eq!(
+        response.response.headers().get(CONTENT_TYPE).unwrap(),
+        APPLICATION_JSON.essence_str()
+    );
+}
+
 #[tokio::test]
 async fn test_http_max_request_bytes() {
     /// Size of the JSON serialization of the request created by `fn canned_new`
@@ -320,6 +453,115 @@ async fn it_processes_a_valid_query_batch() {
     assert_eq!(expected_response, data);
 }
 
+#[tokio::test]
+async fn it_rejects_a_query_batch_over_the_maximum_size() {
+    async fn with_maximum_size(maximum_size: usize) -> router::Response {
+        // The fake batch contains 2 operations
+        let http_request = make_fake_batch(
+            supergraph::Request::canned_builder()
+                .build()
+                .unwrap()
+                .supergraph_request,
+            None,
+        );
+        let config = serde_json::json!({
+            "batching": {
+                "enabled": true,
+                "mode" : "batch_http_link",
+                "maximum_size": maximum_size
+            }
+        });
+        crate::TestHarness::builder()
+            .configuration_json(config)
+            .unwrap()
+            .build_router()
+            .await
+            .unwrap()
+            .oneshot(router::Request::from(http_request))
+            .await
+            .unwrap()
+    }
+
+    let response = with_maximum_size(2).await.response;
+    assert_eq!(response.status(), http::StatusCode::OK);
+
+    let response = with_maximum_size(1).await.response;
+    assert_eq!(response.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let data: serde_json::Value =
+        serde_json::from_slice(&get_body_bytes(response.into_body()).await.unwrap()).unwrap();
+    assert_eq!(
+        data["errors"][0]["extensions"]["code"],
+        "BATCH_LIMIT_EXCEEDED"
+    );
+}
+
+#[tokio::test]
+async fn it_processes_a_query_batch_with_limited_concurrency() {
+    async fn peak_subgraph_calls(maximum_concurrency: usize) -> usize {
+        // The fake batch contains 2 operations, each of them making a single subgraph call
+        let http_request = make_fake_batch(
+            supergraph::Request::canned_builder()
+                .query("{ topProducts { upc } }")
+                .build()
+                .unwrap()
+                .supergraph_request,
+            None,
+        );
+        let config = serde_json::json!({
+            "batching": {
+                "enabled": true,
+                "mode" : "batch_http_link",
+                "maximum_concurrency": maximum_concurrency
+            }
+        });
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (in_flight_2, peak_2) = (in_flight.clone(), peak.clone());
+        let response = crate::TestHarness::builder()
+            .configuration_json(config)
+            .unwrap()
+            .subgraph_hook(move |_, _| {
+                let (in_flight, peak) = (in_flight_2.clone(), peak_2.clone());
+                tower::service_fn(move |request: subgraph::Request| {
+                    let (in_flight, peak) = (in_flight.clone(), peak.clone());
+                    async move {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, tower::BoxError>(
+                            subgraph::Response::fake_builder()
+                                .context(request.context)
+                                .data(json!({ "topProducts": [{ "upc": "1" }] }))
+                                .build(),
+                        )
+                    }
+                })
+                .boxed()
+            })
+            .build_router()
+            .await
+            .unwrap()
+            .oneshot(router::Request::from(http_request))
+            .await
+            .unwrap()
+            .response;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let data: serde_json::Value =
+            serde_json::from_slice(&get_body_bytes(response.into_body()).await.unwrap()).unwrap();
+        // The responses are returned in the order of the operations
+        let responses = data.as_array().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0], responses[1]);
+        assert!(responses[0]["errors"].is_null());
+
+        peak.load(Ordering::SeqCst)
+    }
+
+    assert_eq!(peak_subgraph_calls(1).await, 1);
+    assert_eq!(peak_subgraph_calls(2).await, 2);
+}
+
 #[tokio::test]
 async fn it_will_not_process_a_query_batch_without_enablement() {
     let expected_response: serde_json::Value = serde_json::from_str(include_str!(
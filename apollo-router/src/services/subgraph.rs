@@ -377,8 +377,10 @@ impl Response {
 }
 
 impl Request {
-    #[allow(dead_code)]
-    pub(crate) fn to_sha256(&self) -> String {
+    /// Hashes the request to deduplicate subscriptions
+    ///
+    /// If `headers` is set, only these headers are part of the hash.
+    pub(crate) fn to_sha256(&self, headers: Option<&[String]>) -> String {
         let mut hasher = Sha256::new();
         let http_req = &self.subgraph_request;
         hasher.update(http_req.method().as_str().as_bytes());
@@ -405,7 +407,13 @@ impl Request {
         }
 
         // this assumes headers are in the same order
-        for (name, value) in http_req.headers() {
+        for (name, value) in http_req.headers().iter().filter(|(name, _)| {
+            headers.map_or(true, |headers| {
+                headers
+                    .iter()
+                    .any(|header| header.eq_ignore_ascii_case(name.as_str()))
+            })
+        }) {
             hasher.update(name.as_str().as_bytes());
             hasher.update(value.to_str().unwrap_or("ERROR").as_bytes());
         }
@@ -7,7 +7,9 @@ use std::sync::Arc;
 use std::task::Poll;
 
 use bytes::Bytes;
+use bytes::BytesMut;
 use futures::future::BoxFuture;
+use futures::Stream;
 use futures::StreamExt;
 use futures::TryFutureExt;
 use http::header::ACCEPT;
@@ -27,7 +29,9 @@ use opentelemetry::Key;
 use opentelemetry::KeyValue;
 use rustls::RootCertStore;
 use serde::Serialize;
+use serde_json::value::RawValue;
 use tokio::select;
+use tokio::sync::broadcast;
 use tokio::sync::oneshot;
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::connect_async_tls_with_config;
@@ -40,6 +44,8 @@ use tracing::instrument;
 use tracing::Instrument;
 use uuid::Uuid;
 
+use self::cross_request_batching::CrossRequestBatcher;
+use self::websocket_pool::WebSocketPool;
 use super::http::HttpClientServiceFactory;
 use super::http::HttpRequest;
 use super::layers::content_negotiation::GRAPHQL_JSON_RESPONSE_HEADER_VALUE;
@@ -56,8 +62,10 @@ use crate::error::FetchError;
 use crate::error::SubgraphBatchingError;
 use crate::graphql;
 use crate::json_ext::Object;
+use crate::notification::HandleSink;
 use crate::plugins::authentication::subgraph::SigningParamsConfig;
 use crate::plugins::file_uploads;
+use crate::plugins::limits::response_size::ResponseSizeLimit;
 use crate::plugins::subscription::create_verifier;
 use crate::plugins::subscription::CallbackMode;
 use crate::plugins::subscription::SubscriptionConfig;
@@ -80,6 +88,9 @@ use crate::Configuration;
 use crate::Context;
 use crate::Notify;
 
+mod cross_request_batching;
+mod websocket_pool;
+
 const PERSISTED_QUERY_NOT_FOUND_EXTENSION_CODE: &str = "PERSISTED_QUERY_NOT_FOUND";
 const PERSISTED_QUERY_NOT_SUPPORTED_EXTENSION_CODE: &str = "PERSISTED_QUERY_NOT_SUPPORTED";
 const PERSISTED_QUERY_NOT_FOUND_MESSAGE: &str = "PersistedQueryNotFound";
@@ -133,6 +144,10 @@ pub(crate) struct SubgraphService {
     /// Subscription config if enabled
     subscription_config: Option<SubscriptionConfig>,
     notify: Notify<String, graphql::Response>,
+    /// Batches the requests of different client requests, if enabled for this subgraph
+    cross_request_batcher: Option<Arc<CrossRequestBatcher>>,
+    /// Shares websocket connections between subscriptions, if multiplexing is enabled for this subgraph
+    websocket_pool: Option<Arc<WebSocketPool>>,
 }
 
 impl SubgraphService {
@@ -152,13 +167,23 @@ impl SubgraphService {
             .map(|apq| apq.enabled)
             .unwrap_or(configuration.apq.subgraph.all.enabled);
 
-        SubgraphService::new(
+        let cross_request_batching = configuration.batching.experimental_cross_request.get(&name);
+        let cross_request_batcher = cross_request_batching.enabled.then(|| {
+            Arc::new(CrossRequestBatcher::new(
+                name.clone(),
+                cross_request_batching,
+            ))
+        });
+
+        let mut service = SubgraphService::new(
             name,
             enable_apq,
             subscription_config,
             configuration.notify.clone(),
             client_factory,
-        )
+        )?;
+        service.cross_request_batcher = cross_request_batcher;
+        Ok(service)
     }
 
     pub(crate) fn new(
@@ -168,12 +193,28 @@ impl SubgraphService {
         notify: Notify<String, graphql::Response>,
         client_factory: crate::services::http::HttpClientServiceFactory,
     ) -> Result<Self, BoxError> {
+        let service: String = service.into();
+        let websocket_pool = subscription_config
+            .as_ref()
+            .and_then(|subscription_config| {
+                match subscription_config.mode.get_subgraph_config(&service) {
+                    Some(SubscriptionMode::Passthrough(ws_conf))
+                        if ws_conf.multiplexing.enabled =>
+                    {
+                        Some(Arc::new(WebSocketPool::new(service.clone(), &ws_conf)))
+                    }
+                    _ => None,
+                }
+            });
+
         Ok(Self {
             client_factory,
-            service: Arc::new(service.into()),
+            service: Arc::new(service),
             apq: Arc::new(<AtomicBool>::new(enable_apq)),
             subscription_config,
             notify,
+            cross_request_batcher: None,
+            websocket_pool,
         })
     }
 }
@@ -230,7 +271,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                 }
             };
             if subscription_config.enable_deduplication {
-                request.to_sha256()
+                request.to_sha256(subscription_config.deduplication_headers.as_deref())
             } else {
                 Uuid::new_v4().to_string()
             }
@@ -252,6 +293,10 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
 
         let mut notify = self.notify.clone();
 
+        let cross_request_batcher = self.cross_request_batcher.clone();
+
+        let websocket_pool = self.websocket_pool.clone();
+
         let make_calls = async move {
             // Subscription handling
             if request.operation_kind == OperationKind::Subscription
@@ -275,6 +320,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                             service_name,
                             ws_conf,
                             hashed_request,
+                            websocket_pool,
                         )
                         .await;
                     }
@@ -389,6 +435,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                     context,
                     client_factory.clone(),
                     &service_name,
+                    cross_request_batcher.as_deref(),
                 )
                 .await;
             }
@@ -426,6 +473,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                 context.clone(),
                 client_factory.clone(),
                 &service_name,
+                cross_request_batcher.as_deref(),
             )
             .await?;
 
@@ -443,6 +491,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                         context,
                         client_factory.clone(),
                         &service_name,
+                        cross_request_batcher.as_deref(),
                     )
                     .await
                 }
@@ -454,6 +503,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                         context,
                         client_factory.clone(),
                         &service_name,
+                        cross_request_batcher.as_deref(),
                     )
                     .await
                 }
@@ -473,6 +523,7 @@ async fn call_websocket(
     service_name: String,
     subgraph_cfg: &WebSocketConfiguration,
     subscription_hash: String,
+    websocket_pool: Option<Arc<WebSocketPool>>,
 ) -> Result<SubgraphResponse, BoxError> {
     let operation_name = request
         .subgraph_request
@@ -637,6 +688,22 @@ async fn call_websocket(
         "graphql.operation.name" = %operation_name,
     );
 
+    if let Some(websocket_pool) = websocket_pool {
+        let subscription = websocket_pool
+            .subscribe(request, connection_params, body)
+            .instrument(subgraph_req_span)
+            .await?;
+        let (handle_sink, handle_stream) = handle.split();
+        forward_subscription_events(subscription, handle_sink, connection_closed_signal);
+        subscription_stream_tx.send(Box::pin(handle_stream)).await?;
+
+        return Ok(SubgraphResponse::builder()
+            .context(context)
+            .subgraph_name(service_name)
+            .extensions(Object::default())
+            .build());
+    }
+
     let (ws_stream, mut resp) = match request.uri().scheme_str() {
         Some("wss") => {
             connect_async_tls_with_config(request, None, false, None)
@@ -687,7 +754,24 @@ async fn call_websocket(
         })?;
 
     let (handle_sink, handle_stream) = handle.split();
+    forward_subscription_events(gql_stream, handle_sink, connection_closed_signal);
+    subscription_stream_tx.send(Box::pin(handle_stream)).await?;
+
+    Ok(SubgraphResponse::new_from_response(
+        resp.map(|_| graphql::Response::default()),
+        context,
+        service_name,
+        subgraph_request_id,
+    ))
+}
 
+/// Forwards the events of a subgraph subscription to its (possibly deduplicated) subscribers,
+/// until the stream ends or the client connection is closed
+fn forward_subscription_events(
+    gql_stream: impl Stream<Item = graphql::Response> + Send + 'static,
+    handle_sink: HandleSink<String, graphql::Response>,
+    connection_closed_signal: Option<broadcast::Receiver<()>>,
+) {
     tokio::task::spawn(async move {
         match connection_closed_signal {
             Some(mut connection_closed_signal) => select! {
@@ -710,15 +794,6 @@ async fn call_websocket(
             }
         }
     });
-
-    subscription_stream_tx.send(Box::pin(handle_stream)).await?;
-
-    Ok(SubgraphResponse::new_from_response(
-        resp.map(|_| graphql::Response::default()),
-        context,
-        service_name,
-        subgraph_request_id,
-    ))
 }
 
 // Utility function to extract uri details.
@@ -762,12 +837,14 @@ fn http_response_to_graphql_response(
             // Application json does not expect a valid graphql response if not 2xx.
             // If parse fails then attach the entire payload as an error
             tracing::debug_span!("parse_subgraph_response").in_scope(|| {
-                // Application graphql json expects valid graphql response
-                let mut original_response = String::from_utf8_lossy(&body).to_string();
-                if original_response.is_empty() {
-                    original_response = "<empty response body>".into()
-                }
+                // The text of the body is only needed if it cannot be parsed. Cloning the bytes
+                // does not copy them
+                let original_body = body.clone();
                 graphql::Response::from_bytes(service_name, body).unwrap_or_else(|_error| {
+                    let mut original_response = String::from_utf8_lossy(&original_body).to_string();
+                    if original_response.is_empty() {
+                        original_response = "<empty response body>".into()
+                    }
                     graphql::Response::builder()
                         .error(
                             FetchError::SubrequestMalformedResponse {
@@ -965,41 +1042,28 @@ pub(crate) async fn process_batch(
     }
 
     tracing::debug!("parts: {parts:?}, content_type: {content_type:?}, body: {body:?}");
-    let value =
-        serde_json::from_slice(&body.ok_or(FetchError::SubrequestMalformedResponse {
-            service: service.to_string(),
-            reason: "no body in response".to_string(),
-        })??)
-        .map_err(|error| FetchError::SubrequestMalformedResponse {
+    let body = body.ok_or(FetchError::SubrequestMalformedResponse {
+        service: service.to_string(),
+        reason: "no body in response".to_string(),
+    })??;
+
+    // The responses of the batch are only delimited here, not parsed: each one is a slice of the
+    // body, parsed like the body of a single response, without copying or serializing it again
+    let responses: Vec<&RawValue> =
+        serde_json::from_slice(&body).map_err(|error| FetchError::SubrequestMalformedResponse {
             service: service.to_string(),
             reason: error.to_string(),
         })?;
-
-    tracing::debug!("json value from body is: {value:?}");
-
-    let array = ensure_array!(value).map_err(|error| FetchError::SubrequestMalformedResponse {
-        service: service.to_string(),
-        reason: error.to_string(),
-    })?;
-    let mut graphql_responses = Vec::with_capacity(array.len());
-    for value in array {
-        let object =
-            ensure_object!(value).map_err(|error| FetchError::SubrequestMalformedResponse {
+    let mut graphql_responses = Vec::with_capacity(responses.len());
+    for response in responses {
+        if !response.get().starts_with('{') {
+            return Err(FetchError::SubrequestMalformedResponse {
                 service: service.to_string(),
-                reason: error.to_string(),
-            })?;
-
-        // Map our Vec<u8> into Bytes
-        // Map our serde conversion error to a FetchError
-        let body = Some(
-            serde_json::to_vec(&object)
-                .map(|v| v.into())
-                .map_err(|error| FetchError::SubrequestMalformedResponse {
-                    service: service.to_string(),
-                    reason: error.to_string(),
-                }),
-        );
+                reason: "invalid type, expected an object".to_string(),
+            });
+        }
 
+        let body = Some(Ok(body.slice_ref(response.get().as_bytes())));
         let graphql_response =
             http_response_to_graphql_response(&service, content_type.clone(), body, &parts);
         graphql_responses.push(graphql_response);
@@ -1194,6 +1258,7 @@ async fn call_http(
     context: Context,
     client_factory: HttpClientServiceFactory,
     service_name: &str,
+    cross_request_batcher: Option<&CrossRequestBatcher>,
 ) -> Result<SubgraphResponse, BoxError> {
     // We use configuration to determine if calls may be batched. If we have Batching
     // configuration, then we check (batch_include()) if the current subgraph has batching enabled
@@ -1222,6 +1287,9 @@ async fn call_http(
                 service: service_name.to_string(),
                 reason: format!("tx receive failed: {err}"),
             })?
+    } else if let Some(batcher) = cross_request_batcher.filter(|batcher| batcher.accepts(&request))
+    {
+        batcher.call(client_factory, request, body).await
     } else {
         tracing::debug!("we called http");
         let client = client_factory.create(service_name);
@@ -1517,18 +1585,9 @@ async fn do_fetch(
     let content_type = get_graphql_content_type(service_name, &parts);
 
     let body = if content_type.is_ok() {
-        let body = body
-            .to_bytes()
+        let body = read_response_body(context, service_name, &parts, body)
             .instrument(tracing::debug_span!("aggregate_response_data"))
-            .await
-            .map_err(|err| {
-                tracing::error!(fetch_error = ?err);
-                FetchError::SubrequestHttpError {
-                    status_code: Some(parts.status.as_u16()),
-                    service: service_name.to_string(),
-                    reason: err.to_string(),
-                }
-            });
+            .await;
         if let Ok(body) = &body {
             if display_body {
                 tracing::info!(
@@ -1562,6 +1621,55 @@ async fn do_fetch(
     Ok((parts, content_type, body))
 }
 
+/// Reads the response body, within the size limits of the request if any
+async fn read_response_body(
+    context: &Context,
+    service_name: &str,
+    parts: &Parts,
+    mut body: RouterBody,
+) -> Result<Bytes, FetchError> {
+    let http_error = |err: hyper::Error| {
+        tracing::error!(fetch_error = ?err);
+        FetchError::SubrequestHttpError {
+            status_code: Some(parts.status.as_u16()),
+            service: service_name.to_string(),
+            reason: err.to_string(),
+        }
+    };
+    let limit = context
+        .extensions()
+        .with_lock(|lock| lock.get::<ResponseSizeLimit>().cloned());
+    let Some(limit) = limit else {
+        return body.to_bytes().await.map_err(http_error);
+    };
+
+    // A body received in a single chunk is kept as is, it is only copied if there are several
+    let mut first_chunk = None;
+    let mut bytes = BytesMut::new();
+    let mut received = 0;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(http_error)?;
+        limit.consume(received, chunk.len()).map_err(|err| {
+            tracing::error!(fetch_error = %err, "aborting the subgraph response");
+            FetchError::SubrequestResponseTooLarge {
+                service: service_name.to_string(),
+                reason: err.to_string(),
+            }
+        })?;
+        received += chunk.len();
+        if received == chunk.len() {
+            first_chunk = Some(chunk);
+            continue;
+        }
+        if let Some(first_chunk) = first_chunk.take() {
+            bytes.reserve(received);
+            bytes.extend_from_slice(&first_chunk);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(first_chunk.unwrap_or_else(|| bytes.freeze()))
+}
+
 fn get_websocket_request(
     service_name: String,
     mut parts: http::request::Parts,
@@ -1852,6 +1960,44 @@ mod tests {
         server.await.unwrap();
     }
 
+    // starts a local server emulating a subgraph answering array batches, that counts the
+    // requests it receives
+    async fn emulate_subgraph_batches(
+        listener: TcpListener,
+        count: Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        let make_svc = make_service_fn(move |_conn| {
+            let count = count.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: http::Request<Body>| {
+                    let count = count.clone();
+                    async move {
+                        count.fetch_add(1, Relaxed);
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        // each operation is answered with its own query, to check that
+                        // the responses are sent back to the right requests
+                        let answer = |request: &serde_json::Value| serde_json::json!({ "data": { "query": request["query"] } });
+                        let response = match serde_json::from_slice(&body).unwrap() {
+                            serde_json::Value::Array(requests) => {
+                                serde_json::Value::Array(requests.iter().map(answer).collect())
+                            }
+                            request => answer(&request),
+                        };
+                        Ok::<_, Infallible>(
+                            http::Response::builder()
+                                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                                .status(StatusCode::OK)
+                                .body(Body::from(response.to_string()))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::from_tcp(listener).unwrap().serve(make_svc);
+        server.await.unwrap();
+    }
+
     // starts a local server emulating a subgraph returning bad response format
     async fn emulate_subgraph_application_graphql_response(listener: TcpListener) {
         async fn handle(_request: http::Request<Body>) -> Result<http::Response<Body>, Infallible> {
@@ -2361,12 +2507,14 @@ mod tests {
                             path: Some(String::from("/ws")),
                             protocol: WebSocketProtocol::default(),
                             heartbeat_interval: HeartbeatInterval::new_disabled(),
+                            multiplexing: Default::default(),
                         },
                     )]
                     .into(),
                 }),
             },
             enable_deduplication: true,
+            deduplication_headers: None,
             max_opened_subscriptions: None,
             queue_capacity: None,
         }
@@ -2504,6 +2652,139 @@ mod tests {
         assert!(response.response.body().errors.is_empty());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subgraph_service_response_too_large() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        tokio::task::spawn(emulate_subgraph_application_json_response(listener));
+        let subgraph_service = SubgraphService::new(
+            "test",
+            true,
+            None,
+            Notify::default(),
+            HttpClientServiceFactory::from_config(
+                "test",
+                &Configuration::default(),
+                crate::configuration::shared::Client::default(),
+            ),
+        )
+        .expect("can create a SubgraphService");
+
+        let context = Context::new();
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(ResponseSizeLimit::new(Some(5), None)));
+        let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+        let response = subgraph_service
+            .oneshot(
+                SubgraphRequest::builder()
+                    .supergraph_request(supergraph_request("query"))
+                    .subgraph_request(subgraph_http_request(url, "query"))
+                    .operation_kind(OperationKind::Query)
+                    .subgraph_name(String::from("test"))
+                    .context(context)
+                    .build(),
+            )
+            .await
+            .unwrap();
+        let errors = &response.response.body().errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].extensions.get("code").unwrap(),
+            "SUBREQUEST_RESPONSE_TOO_LARGE"
+        );
+        assert_eq!(
+            errors[0].message,
+            "response from 'test' was too large: subgraph response body exceeded the limit of 5 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_keeps_single_chunk_response_bodies_without_copying() {
+        let context = Context::new();
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(ResponseSizeLimit::new(Some(100), None)));
+        let (parts, _) = http::Response::builder().body(()).unwrap().into_parts();
+
+        let bytes = Bytes::from_static(br#"{"data":{}}"#);
+        let body = read_response_body(&context, "test", &parts, RouterBody::from(bytes.clone()))
+            .await
+            .unwrap();
+        assert_eq!(body.as_ptr(), bytes.as_ptr());
+
+        let chunks = futures::stream::iter([
+            Ok::<_, std::io::Error>(Bytes::from_static(br#"{"data":"#)),
+            Ok(Bytes::from_static(b"{}}")),
+        ]);
+        let body = read_response_body(&context, "test", &parts, RouterBody::wrap_stream(chunks))
+            .await
+            .unwrap();
+        assert_eq!(body, bytes);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subgraph_service_cross_request_batching() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        tokio::task::spawn(emulate_subgraph_batches(listener, count.clone()));
+
+        let mut configuration = Configuration::default();
+        configuration.batching.experimental_cross_request.all =
+            crate::configuration::CrossRequestBatching {
+                enabled: true,
+                max_size: std::num::NonZeroUsize::new(2).unwrap(),
+                max_wait: std::time::Duration::from_millis(200),
+            };
+        let subgraph_service = SubgraphService::from_config(
+            "test",
+            &configuration,
+            None,
+            HttpClientServiceFactory::from_config(
+                "test",
+                &configuration,
+                crate::configuration::shared::Client::default(),
+            ),
+        )
+        .expect("can create a SubgraphService");
+
+        let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+        let call = |query: &'static str, authorization: &'static str| {
+            let mut subgraph_request = subgraph_http_request(url.clone(), query);
+            subgraph_request.headers_mut().insert(
+                header::AUTHORIZATION,
+                HeaderValue::from_static(authorization),
+            );
+            subgraph_service.clone().oneshot(
+                SubgraphRequest::builder()
+                    .supergraph_request(supergraph_request(query))
+                    .subgraph_request(subgraph_request)
+                    .operation_kind(OperationKind::Query)
+                    .subgraph_name(String::from("test"))
+                    .context(Context::new())
+                    .build(),
+            )
+        };
+
+        // the first two requests fill a batch, the third one has other credentials and is
+        // sent on its own once the wait is over
+        let (first, second, third) = tokio::join!(
+            call("{ a }", "alice"),
+            call("{ b }", "alice"),
+            call("{ c }", "bob")
+        );
+        for (response, query) in [(first, "{ a }"), (second, "{ b }"), (third, "{ c }")] {
+            let response = response.unwrap();
+            assert!(response.response.body().errors.is_empty());
+            assert_eq!(
+                response.response.body().data,
+                Some(serde_json_bytes::json!({ "query": query }))
+            );
+        }
+        assert_eq!(count.load(Relaxed), 2);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     #[cfg(not(target_os = "macos"))]
     async fn test_subgraph_service_panic() {
@@ -3366,4 +3647,40 @@ mod tests {
             .build();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_subscription_deduplication_headers() {
+        let url = Uri::from_static("http://localhost:4001/graphql");
+        let query = "subscription {\n  userWasCreated {\n    username\n  }\n}";
+        let request = |authorization: &'static str, request_id: &'static str| {
+            let mut subgraph_request = subgraph_http_request(url.clone(), query);
+            let headers = subgraph_request.headers_mut();
+            headers.insert(
+                http::header::AUTHORIZATION,
+                HeaderValue::from_static(authorization),
+            );
+            headers.insert("x-request-id", HeaderValue::from_static(request_id));
+            SubgraphRequest::builder()
+                .supergraph_request(supergraph_request(query))
+                .subgraph_request(subgraph_request)
+                .operation_kind(OperationKind::Subscription)
+                .subgraph_name(String::from("test"))
+                .context(Context::new())
+                .build()
+        };
+
+        let headers = vec![String::from("Authorization")];
+        assert_ne!(
+            request("Bearer a", "1").to_sha256(None),
+            request("Bearer a", "2").to_sha256(None)
+        );
+        assert_eq!(
+            request("Bearer a", "1").to_sha256(Some(headers.as_slice())),
+            request("Bearer a", "2").to_sha256(Some(headers.as_slice()))
+        );
+        assert_ne!(
+            request("Bearer a", "1").to_sha256(Some(headers.as_slice())),
+            request("Bearer b", "1").to_sha256(Some(headers.as_slice()))
+        );
+    }
 }
@@ -0,0 +1,205 @@
+//! Cross-request batching of subgraph requests
+//!
+//! The requests sent to a subgraph by different client requests within a short window are
+//! coalesced into a single array batch. Only requests with the same URI, method and headers
+//! are batched together, so the headers and credentials of a client request are never used
+//! to send the operations of another one.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+use tower::BoxError;
+use tracing::Instrument;
+
+use super::call_single_http;
+use super::notify_batch_query;
+use super::process_batch;
+use crate::configuration::CrossRequestBatching;
+use crate::error::FetchError;
+use crate::graphql;
+use crate::plugins::file_uploads;
+use crate::query_planner::OperationKind;
+use crate::services::http::HttpClientServiceFactory;
+use crate::services::router::body::RouterBody;
+use crate::services::SubgraphRequest;
+use crate::services::SubgraphResponse;
+
+/// Coalesces the requests sent to a subgraph into batches
+pub(crate) struct CrossRequestBatcher {
+    service: String,
+    max_size: usize,
+    max_wait: Duration,
+    pending: Arc<Mutex<Vec<PendingBatch>>>,
+    next_id: AtomicU64,
+}
+
+/// A batch waiting for more requests
+struct PendingBatch {
+    id: u64,
+    requests: Vec<PendingRequest>,
+}
+
+struct PendingRequest {
+    request: SubgraphRequest,
+    body: graphql::Request,
+    tx: oneshot::Sender<Result<SubgraphResponse, BoxError>>,
+}
+
+impl CrossRequestBatcher {
+    pub(crate) fn new(service: String, config: &CrossRequestBatching) -> Self {
+        Self {
+            service,
+            max_size: config.max_size.get(),
+            max_wait: config.max_wait,
+            pending: Default::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether a request can be sent in a batch with the requests of other clients
+    pub(crate) fn accepts(&self, request: &SubgraphRequest) -> bool {
+        request.operation_kind == OperationKind::Query
+            && !file_uploads::has_file_uploads(&request.subgraph_request)
+    }
+
+    /// Adds a request to a batch, and waits for its response.
+    ///
+    /// A batch is sent once it is full, or once its first request waited for `max_wait`.
+    pub(crate) async fn call(
+        &self,
+        client_factory: HttpClientServiceFactory,
+        request: SubgraphRequest,
+        body: graphql::Request,
+    ) -> Result<SubgraphResponse, BoxError> {
+        let (tx, rx) = oneshot::channel();
+        let full_batch = {
+            let mut pending = self.pending.lock();
+            let index = match pending.iter().position(|batch| batch.can_include(&request)) {
+                Some(index) => index,
+                None => {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    pending.push(PendingBatch {
+                        id,
+                        requests: Vec::with_capacity(self.max_size),
+                    });
+                    self.flush_after_max_wait(id, client_factory.clone());
+                    pending.len() - 1
+                }
+            };
+            pending[index]
+                .requests
+                .push(PendingRequest { request, body, tx });
+            (pending[index].requests.len() >= self.max_size).then(|| pending.remove(index))
+        };
+
+        // The batch is sent from a separate task, so that it is not dropped with the client
+        // request that filled it
+        if let Some(batch) = full_batch {
+            tokio::task::spawn(
+                batch
+                    .send(client_factory, self.service.clone())
+                    .in_current_span(),
+            );
+        }
+
+        rx.await
+            .map_err(|err| FetchError::SubrequestBatchingError {
+                service: self.service.clone(),
+                reason: format!("tx receive failed: {err}"),
+            })?
+    }
+
+    fn flush_after_max_wait(&self, id: u64, client_factory: HttpClientServiceFactory) {
+        let pending = self.pending.clone();
+        let max_wait = self.max_wait;
+        let service = self.service.clone();
+        tokio::task::spawn(
+            async move {
+                tokio::time::sleep(max_wait).await;
+                let batch = {
+                    let mut pending = pending.lock();
+                    pending
+                        .iter()
+                        .position(|batch| batch.id == id)
+                        .map(|index| pending.remove(index))
+                };
+                if let Some(batch) = batch {
+                    batch.send(client_factory, service).await;
+                }
+            }
+            .in_current_span(),
+        );
+    }
+}
+
+impl PendingBatch {
+    fn can_include(&self, request: &SubgraphRequest) -> bool {
+        let Some(first) = self.requests.first() else {
+            return true;
+        };
+        let first = &first.request.subgraph_request;
+        let other = &request.subgraph_request;
+        first.uri() == other.uri()
+            && first.method() == other.method()
+            && first.version() == other.version()
+            && first.headers() == other.headers()
+    }
+
+    async fn send(self, client_factory: HttpClientServiceFactory, service: String) {
+        let mut requests = self.requests;
+
+        // No other request came in time, it is sent on its own
+        if requests.len() == 1 {
+            let PendingRequest { request, body, tx } =
+                requests.pop().expect("the batch has one request");
+            let context = request.context.clone();
+            let client = client_factory.create(&service);
+            let response = call_single_http(request, body, context, client, &service).await;
+            if tx.send(response).is_err() {
+                tracing::debug!(service, "the subgraph request was canceled");
+            }
+            return;
+        }
+
+        let mut contexts = Vec::with_capacity(requests.len());
+        let mut bodies = Vec::with_capacity(requests.len());
+        let mut senders = Vec::with_capacity(requests.len());
+        let mut parts = None;
+        for PendingRequest { request, body, tx } in requests {
+            contexts.push((request.context, request.id));
+            bodies.push(body);
+            senders.push(tx);
+            // All the requests of the batch have the same URI and headers
+            if parts.is_none() {
+                parts = Some(request.subgraph_request.into_parts().0);
+            }
+        }
+        let parts = parts.expect("the batch has at least one request");
+
+        let listener_count = senders.len();
+        let result = match serde_json::to_string(&bodies) {
+            Ok(body) => {
+                process_batch(
+                    client_factory,
+                    service.clone(),
+                    contexts,
+                    http::Request::from_parts(parts, RouterBody::from(body)),
+                    listener_count,
+                )
+                .await
+            }
+            Err(err) => Err(FetchError::SubrequestBatchingError {
+                service: service.clone(),
+                reason: format!("cannot serialize the batch: {err}"),
+            }),
+        };
+
+        if let Err(error) = notify_batch_query(service.clone(), senders, result).await {
+            tracing::error!(service, %error, "failed to notify the requests of a batch");
+        }
+    }
+}
@@ -0,0 +1,781 @@
+//! Multiplexing of subgraph subscriptions over shared WebSocket connections
+//!
+//! Subscriptions sent to a subgraph with the same URI, headers and connection params are sent
+//! through the same WebSocket connection, each of them with its own operation id. A connection
+//! is closed once its last subscription ends. When a connection is lost, it is reopened with an
+//! exponential backoff and its active subscriptions are sent again.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures::Sink;
+use futures::SinkExt;
+use futures::Stream;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde_json_bytes::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::connect_async_tls_with_config;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use uuid::Uuid;
+
+use crate::error::FetchError;
+use crate::graphql;
+use crate::plugins::subscription::WebSocketConfiguration;
+use crate::plugins::subscription::WebSocketMultiplexing;
+use crate::protocols::websocket::convert_websocket_stream;
+use crate::protocols::websocket::ClientMessage;
+use crate::protocols::websocket::Error as WebSocketError;
+use crate::protocols::websocket::GraphqlWebSocket;
+use crate::protocols::websocket::ServerMessage;
+use crate::protocols::websocket::WebSocketProtocol;
+
+/// Number of events waiting to be read by a subscriber before it is considered stalled
+const EVENTS_CAPACITY: usize = 128;
+const INITIAL_RECONNECTION_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECTION_DELAY: Duration = Duration::from_secs(10);
+/// Number of heartbeat intervals without any message from the subgraph before a connection is considered lost
+const MISSED_HEARTBEATS: u32 = 3;
+
+trait Connection:
+    Stream<Item = serde_json::Result<ServerMessage>>
+    + Sink<ClientMessage, Error = WebSocketError>
+    + Send
+    + Unpin
+{
+}
+
+impl<T> Connection for T where
+    T: Stream<Item = serde_json::Result<ServerMessage>>
+        + Sink<ClientMessage, Error = WebSocketError>
+        + Send
+        + Unpin
+{
+}
+
+type BoxConnection = Box<dyn Connection>;
+
+/// Shares the WebSocket connections to a subgraph between its subscriptions
+pub(crate) struct WebSocketPool {
+    service: String,
+    protocol: WebSocketProtocol,
+    heartbeat_interval: Option<Duration>,
+    max_subscriptions_per_connection: usize,
+    reconnection_attempts: usize,
+    /// One permit per opened connection
+    permits: Arc<Semaphore>,
+    connections: Mutex<HashMap<String, Vec<ConnectionHandle>>>,
+}
+
+#[derive(Clone)]
+struct ConnectionHandle {
+    commands: mpsc::UnboundedSender<Command>,
+    subscriptions: Arc<AtomicUsize>,
+}
+
+enum Command {
+    Subscribe {
+        id: String,
+        request: graphql::Request,
+        events: mpsc::Sender<graphql::Response>,
+    },
+    Unsubscribe {
+        id: String,
+    },
+}
+
+/// What is needed to open a connection, kept to reconnect
+struct ConnectionTarget {
+    request: http::Request<()>,
+    connection_params: Option<Value>,
+}
+
+impl ConnectionTarget {
+    fn key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.request.uri().to_string().as_bytes());
+        let mut headers: Vec<_> = self
+            .request
+            .headers()
+            .iter()
+            // the key is generated for each connection
+            .filter(|(name, _)| **name != http::header::SEC_WEBSOCKET_KEY)
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .collect();
+        headers.sort();
+        for (name, value) in headers {
+            hasher.update(name.as_bytes());
+            hasher.update(value);
+        }
+        if let Some(connection_params) = &self.connection_params {
+            hasher.update(connection_params.to_string().as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+impl WebSocketPool {
+    pub(crate) fn new(service: String, configuration: &WebSocketConfiguration) -> Self {
+        let WebSocketMultiplexing {
+            max_connections,
+            max_subscriptions_per_connection,
+            reconnection_attempts,
+            ..
+        } = configuration.multiplexing;
+        Self {
+            service,
+            protocol: configuration.protocol,
+            heartbeat_interval: configuration.heartbeat_interval.into_option(),
+            max_subscriptions_per_connection,
+            reconnection_attempts,
+            permits: Arc::new(Semaphore::new(max_connections)),
+            connections: Default::default(),
+        }
+    }
+
+    /// Sends a subscription through a connection matching the request, opening one if needed
+    pub(crate) async fn subscribe(
+        &self,
+        request: http::Request<()>,
+        connection_params: Option<Value>,
+        body: graphql::Request,
+    ) -> Result<PooledSubscription, FetchError> {
+        let target = ConnectionTarget {
+            request,
+            connection_params,
+        };
+        let key = target.key();
+        let id = Uuid::new_v4().to_string();
+        let (events_sender, events) = mpsc::channel(EVENTS_CAPACITY);
+        let mut command = Command::Subscribe {
+            id: id.clone(),
+            request: body,
+            events: events_sender,
+        };
+        let mut target = Some(target);
+
+        loop {
+            let handle = match self.available_connection(&key) {
+                Some(handle) => handle,
+                None => {
+                    let target = match target.take() {
+                        Some(target) => target,
+                        None => {
+                            return Err(self.error("cannot open a websocket connection".to_string()))
+                        }
+                    };
+                    let handle = self.open(target).await?;
+                    self.connections
+                        .lock()
+                        .entry(key.clone())
+                        .or_default()
+                        .push(handle.clone());
+                    handle
+                }
+            };
+
+            handle.subscriptions.fetch_add(1, Ordering::SeqCst);
+            match handle.commands.send(command) {
+                Ok(()) => {
+                    return Ok(PooledSubscription {
+                        id,
+                        events,
+                        commands: handle.commands,
+                    })
+                }
+                // the connection has been closed in the meantime, try another one
+                Err(mpsc::error::SendError(returned)) => {
+                    handle.subscriptions.fetch_sub(1, Ordering::SeqCst);
+                    command = returned;
+                }
+            }
+        }
+    }
+
+    /// Returns the least used opened connection for this key, if it can take one more subscription
+    fn available_connection(&self, key: &str) -> Option<ConnectionHandle> {
+        let mut connections = self.connections.lock();
+        let handles = connections.get_mut(key)?;
+        handles.retain(|handle| !handle.commands.is_closed());
+        let handle = handles
+            .iter()
+            .min_by_key(|handle| handle.subscriptions.load(Ordering::SeqCst))
+            .filter(|handle| {
+                handle.subscriptions.load(Ordering::SeqCst) < self.max_subscriptions_per_connection
+            })
+            .cloned();
+        if handles.is_empty() {
+            connections.remove(key);
+        }
+        handle
+    }
+
+    async fn open(&self, target: ConnectionTarget) -> Result<ConnectionHandle, FetchError> {
+        let permit = self.permits.clone().try_acquire_owned().map_err(|_| {
+            self.error("the maximum number of websocket connections has been reached".to_string())
+        })?;
+        let connection_id = Uuid::new_v4().to_string();
+        let connection = connect(&self.service, &target, &connection_id, self.protocol).await?;
+
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let subscriptions = Arc::new(AtomicUsize::new(0));
+        let task = ConnectionTask {
+            service: self.service.clone(),
+            connection_id,
+            target,
+            protocol: self.protocol,
+            heartbeat_interval: self.heartbeat_interval,
+            reconnection_attempts: self.reconnection_attempts,
+            subscriptions: HashMap::new(),
+            count: subscriptions.clone(),
+            commands: receiver,
+            _permit: permit,
+        };
+        tokio::task::spawn(task.run(connection));
+
+        Ok(ConnectionHandle {
+            commands,
+            subscriptions,
+        })
+    }
+
+    fn error(&self, reason: String) -> FetchError {
+        FetchError::SubrequestWsError {
+            service: self.service.clone(),
+            reason,
+        }
+    }
+}
+
+async fn connect(
+    service: &str,
+    target: &ConnectionTarget,
+    connection_id: &str,
+    protocol: WebSocketProtocol,
+) -> Result<BoxConnection, FetchError> {
+    let error = |reason: String| FetchError::SubrequestWsError {
+        service: service.to_string(),
+        reason,
+    };
+
+    let mut request = target
+        .request
+        .uri()
+        .clone()
+        .into_client_request()
+        .map_err(|err| error(format!("cannot create websocket client request: {err}")))?;
+    // only the key is generated for each connection, everything else comes from the subgraph request
+    let key = request
+        .headers_mut()
+        .remove(http::header::SEC_WEBSOCKET_KEY);
+    *request.headers_mut() = target.request.headers().clone();
+    if let Some(key) = key {
+        request
+            .headers_mut()
+            .insert(http::header::SEC_WEBSOCKET_KEY, key);
+    }
+
+    let (ws_stream, _) = match request.uri().scheme_str() {
+        Some("wss") => connect_async_tls_with_config(request, None, false, None).await,
+        _ => connect_async(request).await,
+    }
+    .map_err(|err| error(format!("cannot connect websocket to subgraph: {err}")))?;
+
+    let socket = GraphqlWebSocket::new(
+        convert_websocket_stream(ws_stream, connection_id.to_string()),
+        connection_id.to_string(),
+        protocol,
+        target.connection_params.clone(),
+    )
+    .await
+    .map_err(|err| {
+        error(format!(
+            "cannot get the GraphQL websocket stream: {}",
+            err.message
+        ))
+    })?;
+
+    Ok(Box::new(socket.into_inner()))
+}
+
+struct Subscription {
+    request: graphql::Request,
+    events: mpsc::Sender<graphql::Response>,
+}
+
+enum Disconnection {
+    /// All the subscriptions have ended
+    Idle,
+    /// The connection to the subgraph has been lost
+    Lost,
+}
+
+/// Owns a connection and routes its messages to the subscriptions
+struct ConnectionTask {
+    service: String,
+    connection_id: String,
+    target: ConnectionTarget,
+    protocol: WebSocketProtocol,
+    heartbeat_interval: Option<Duration>,
+    reconnection_attempts: usize,
+    subscriptions: HashMap<String, Subscription>,
+    count: Arc<AtomicUsize>,
+    commands: mpsc::UnboundedReceiver<Command>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ConnectionTask {
+    async fn run(mut self, mut connection: BoxConnection) {
+        loop {
+            match self.serve(&mut connection).await {
+                Disconnection::Idle => {
+                    if let Err(err) = connection.send(ClientMessage::CloseWebsocket).await {
+                        tracing::trace!("cannot close the websocket connection: {err:?}");
+                    }
+                    return;
+                }
+                Disconnection::Lost => {
+                    tracing::warn!(
+                        apollo.subgraph.name = %self.service,
+                        "websocket connection to subgraph {:?} lost, reconnecting",
+                        self.service
+                    );
+                    match self.reconnect().await {
+                        Some(reconnected) => connection = reconnected,
+                        None => {
+                            self.fail();
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn serve(&mut self, connection: &mut BoxConnection) -> Disconnection {
+        let mut heartbeat = self.heartbeat_interval.and_then(|duration| {
+            (self.protocol == WebSocketProtocol::GraphqlWs).then(|| {
+                let mut interval =
+                    tokio::time::interval_at(tokio::time::Instant::now() + duration, duration);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                (interval, duration * MISSED_HEARTBEATS)
+            })
+        });
+        let mut last_message = tokio::time::Instant::now();
+        let mut accepting = !self.commands.is_closed();
+        let mut served = !self.subscriptions.is_empty();
+
+        loop {
+            select! {
+                command = self.commands.recv(), if accepting => match command {
+                    Some(Command::Subscribe { id, request, events }) => {
+                        let message = self.protocol.subscribe(id.clone(), request.clone());
+                        self.subscriptions.insert(id, Subscription { request, events });
+                        served = true;
+                        if connection.send(message).await.is_err() {
+                            return Disconnection::Lost;
+                        }
+                    }
+                    Some(Command::Unsubscribe { id }) => {
+                        if self.remove(&id) {
+                            let _ = connection.send(self.protocol.complete(id)).await;
+                        }
+                    }
+                    None => {
+                        accepting = false;
+                        if self.subscriptions.is_empty() {
+                            return Disconnection::Idle;
+                        }
+                    }
+                },
+                message = connection.next() => {
+                    last_message = tokio::time::Instant::now();
+                    match message {
+                        Some(Ok(ServerMessage::Ping { .. })) => {
+                            if connection.send(ClientMessage::Pong { payload: None }).await.is_err() {
+                                return Disconnection::Lost;
+                            }
+                        }
+                        Some(Ok(message)) => {
+                            if let Some(disconnection) = self.route(connection, message).await {
+                                return disconnection;
+                            }
+                        }
+                        Some(Err(err)) => {
+                            tracing::error!(apollo.subgraph.name = %self.service, "cannot deserialize websocket server message: {err:?}");
+                        }
+                        None => return Disconnection::Lost,
+                    }
+                },
+                _ = async {
+                    match heartbeat.as_mut() {
+                        Some((interval, _)) => interval.tick().await,
+                        None => futures::future::pending().await,
+                    }
+                } => {
+                    let timeout = heartbeat.as_ref().map(|(_, timeout)| *timeout).unwrap_or_default();
+                    if last_message.elapsed() > timeout
+                        || connection.send(ClientMessage::Ping { payload: None }).await.is_err()
+                    {
+                        return Disconnection::Lost;
+                    }
+                }
+            }
+
+            if accepting && served && self.subscriptions.is_empty() {
+                // no new subscription can be sent through this connection, the ones
+                // already queued are handled before closing it
+                self.commands.close();
+            }
+        }
+    }
+
+    /// Sends a message from the subgraph to its subscription
+    ///
+    /// The other subscriptions of the connection must not wait for a subscriber that does not
+    /// read its events, so a subscription whose events are not read is ended.
+    async fn route(
+        &mut self,
+        connection: &mut BoxConnection,
+        message: ServerMessage,
+    ) -> Option<Disconnection> {
+        let id = match &message {
+            ServerMessage::Next { id, .. }
+            | ServerMessage::Error { id, .. }
+            | ServerMessage::Complete { id } => id.clone(),
+            _ => return None,
+        };
+        if id == self.connection_id {
+            // the connection has been closed by the subgraph
+            return Some(Disconnection::Lost);
+        }
+
+        let (response, completed) = message.into_graphql_response();
+        if let Some(response) = response {
+            let delivered = match self.subscriptions.get(&id) {
+                Some(subscription) => match subscription.events.try_send(response) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        tracing::warn!(
+                            apollo.subgraph.name = %self.service,
+                            "ending a subscription to subgraph {:?} because its events are not read",
+                            self.service
+                        );
+                        false
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                },
+                None => true,
+            };
+            // the subgraph stops sending events for a subscription that has ended
+            if !delivered
+                && !completed
+                && self.remove(&id)
+                && connection
+                    .send(self.protocol.complete(id.clone()))
+                    .await
+                    .is_err()
+            {
+                return Some(Disconnection::Lost);
+            }
+        }
+        if completed {
+            self.remove(&id);
+        }
+
+        None
+    }
+
+    async fn reconnect(&mut self) -> Option<BoxConnection> {
+        let mut delay = INITIAL_RECONNECTION_DELAY;
+        for attempt in 1..=self.reconnection_attempts {
+            tokio::time::sleep(delay).await;
+            match connect(
+                &self.service,
+                &self.target,
+                &self.connection_id,
+                self.protocol,
+            )
+            .await
+            {
+                Ok(mut connection) => {
+                    u64_counter!(
+                        "apollo.router.operations.subscriptions.reconnections",
+                        "Number of reconnections of multiplexed subgraph websocket connections",
+                        1,
+                        subgraph.service.name = self.service.clone()
+                    );
+                    let mut resubscribed = true;
+                    for (id, subscription) in &self.subscriptions {
+                        let message = self
+                            .protocol
+                            .subscribe(id.clone(), subscription.request.clone());
+                        if connection.send(message).await.is_err() {
+                            resubscribed = false;
+                            break;
+                        }
+                    }
+                    if resubscribed {
+                        return Some(connection);
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        apollo.subgraph.name = %self.service,
+                        "websocket reconnection attempt {attempt} to subgraph {:?} failed: {err}",
+                        self.service
+                    );
+                }
+            }
+            delay = (delay * 2).min(MAX_RECONNECTION_DELAY);
+        }
+
+        None
+    }
+
+    /// Ends the subscriptions of a connection that cannot be reopened
+    fn fail(&mut self) {
+        self.commands.close();
+        while let Ok(command) = self.commands.try_recv() {
+            if let Command::Subscribe {
+                id,
+                request,
+                events,
+            } = command
+            {
+                self.subscriptions
+                    .insert(id, Subscription { request, events });
+            }
+        }
+        for (_, subscription) in self.subscriptions.drain() {
+            let _ = subscription.events.try_send(
+                graphql::Response::builder()
+                    .error(
+                        graphql::Error::builder()
+                            .message(format!(
+                                "websocket connection to subgraph '{}' has been lost",
+                                self.service
+                            ))
+                            .extension_code("WEBSOCKET_CONNECTION_ERROR")
+                            .build(),
+                    )
+                    .subscribed(false)
+                    .build(),
+            );
+        }
+        self.count.store(0, Ordering::SeqCst);
+    }
+
+    fn remove(&mut self, id: &str) -> bool {
+        let removed = self.subscriptions.remove(id).is_some();
+        if removed {
+            self.count.fetch_sub(1, Ordering::SeqCst);
+        }
+        removed
+    }
+}
+
+/// Events of a subscription sent through a shared connection
+///
+/// The subscription is completed on the subgraph when this stream is dropped.
+pub(crate) struct PooledSubscription {
+    id: String,
+    events: mpsc::Receiver<graphql::Response>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Stream for PooledSubscription {
+    type Item = graphql::Response;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Drop for PooledSubscription {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Unsubscribe {
+            id: std::mem::take(&mut self.id),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(token: &str) -> ConnectionTarget {
+        let mut request = "ws://localhost:4001/ws"
+            .into_client_request()
+            .expect("valid request");
+        request.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_str(token).unwrap(),
+        );
+        ConnectionTarget {
+            request,
+            connection_params: None,
+        }
+    }
+
+    #[test]
+    fn connection_key_ignores_websocket_key() {
+        // each request gets a different Sec-WebSocket-Key
+        assert_eq!(target("a").key(), target("a").key());
+        assert_ne!(target("a").key(), target("b").key());
+
+        let mut with_params = target("a");
+        with_params.connection_params = Some(serde_json_bytes::json!({ "token": "a" }));
+        assert_ne!(target("a").key(), with_params.key());
+    }
+
+    /// Connection to a fake subgraph
+    struct FakeConnection {
+        messages: mpsc::UnboundedReceiver<ServerMessage>,
+        sent: mpsc::UnboundedSender<ClientMessage>,
+    }
+
+    impl Stream for FakeConnection {
+        type Item = serde_json::Result<ServerMessage>;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> Poll<Option<Self::Item>> {
+            self.messages.poll_recv(cx).map(|message| message.map(Ok))
+        }
+    }
+
+    impl Sink<ClientMessage> for FakeConnection {
+        type Error = WebSocketError;
+
+        fn poll_ready(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: ClientMessage) -> Result<(), Self::Error> {
+            let _ = self.sent.send(item);
+            Ok(())
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(
+            self: Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn stalled_subscriber_does_not_block_the_connection() {
+        let (subgraph, messages) = mpsc::unbounded_channel();
+        let (sent, mut client_messages) = mpsc::unbounded_channel();
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let task = ConnectionTask {
+            service: "test".to_string(),
+            connection_id: "connection".to_string(),
+            target: target("a"),
+            protocol: WebSocketProtocol::GraphqlWs,
+            heartbeat_interval: None,
+            reconnection_attempts: 0,
+            subscriptions: HashMap::new(),
+            count: Arc::new(AtomicUsize::new(2)),
+            commands: receiver,
+            _permit: Arc::new(Semaphore::new(1)).try_acquire_owned().unwrap(),
+        };
+        tokio::task::spawn(task.run(Box::new(FakeConnection { messages, sent })));
+
+        // the stalled subscriber never reads its events
+        let (stalled_events, _stalled) = mpsc::channel(1);
+        let (active_events, mut active) = mpsc::channel(1);
+        for (id, events) in [("stalled", stalled_events), ("active", active_events)] {
+            commands
+                .send(Command::Subscribe {
+                    id: id.to_string(),
+                    request: graphql::Request::default(),
+                    events,
+                })
+                .unwrap();
+            assert!(matches!(
+                client_messages.recv().await,
+                Some(ClientMessage::Subscribe { .. })
+            ));
+        }
+
+        let next = |id: &str| ServerMessage::Next {
+            id: id.to_string(),
+            payload: graphql::Response::builder()
+                .data(serde_json_bytes::json!({ "id": id }))
+                .build(),
+        };
+        for _ in 0..3 {
+            subgraph.send(next("stalled")).unwrap();
+        }
+        subgraph.send(next("active")).unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), active.recv())
+            .await
+            .expect("the active subscriber should not wait for the stalled one")
+            .unwrap();
+        assert_eq!(
+            event.data,
+            Some(serde_json_bytes::json!({ "id": "active" }))
+        );
+
+        // the stalled subscription is ended on the subgraph
+        match tokio::time::timeout(Duration::from_secs(5), client_messages.recv()).await {
+            Ok(Some(ClientMessage::Complete { id })) => assert_eq!(id, "stalled"),
+            other => panic!("expected the stalled subscription to be completed, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_is_bounded() {
+        let pool = WebSocketPool::new(
+            "test".to_string(),
+            &WebSocketConfiguration {
+                path: None,
+                protocol: WebSocketProtocol::default(),
+                heartbeat_interval: crate::plugins::subscription::HeartbeatInterval::new_disabled(),
+                multiplexing: WebSocketMultiplexing {
+                    enabled: true,
+                    max_connections: 0,
+                    ..Default::default()
+                },
+            },
+        );
+        let target = target("a");
+        let err = pool
+            .subscribe(target.request, None, graphql::Request::default())
+            .await
+            .err()
+            .expect("the pool is full");
+        assert!(err
+            .to_string()
+            .contains("maximum number of websocket connections"));
+    }
+}
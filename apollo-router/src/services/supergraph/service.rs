@@ -40,6 +40,7 @@ use crate::plugins::telemetry::config_new::events::SupergraphEventResponse;
 use crate::plugins::telemetry::consts::QUERY_PLANNING_SPAN_NAME;
 use crate::plugins::telemetry::tracing::apollo_telemetry::APOLLO_PRIVATE_DURATION_NS;
 use crate::plugins::telemetry::LOGGING_DISPLAY_BODY;
+use crate::plugins::traffic_shaping::deadline::RequestDeadline;
 use crate::plugins::traffic_shaping::TrafficShaping;
 use crate::plugins::traffic_shaping::APOLLO_TRAFFIC_SHAPING;
 use crate::query_planner::subscription::SubscriptionHandle;
@@ -48,6 +49,7 @@ use crate::query_planner::subscription::SUBSCRIPTION_EVENT_SPAN_NAME;
 use crate::query_planner::BridgeQueryPlannerPool;
 use crate::query_planner::CachingQueryPlanner;
 use crate::query_planner::InMemoryCachePlanner;
+use crate::query_planner::WarmUpOperation;
 use crate::router_factory::create_plugins;
 use crate::router_factory::create_subgraph_services;
 use crate::services::execution::QueryPlan;
@@ -168,7 +170,7 @@ async fn service_call(
     let body = req.supergraph_request.body();
     let variables = body.variables.clone();
 
-    let QueryPlannerResponse { content, errors } = match plan_query(
+    let planned = plan_query(
         planning,
         body.operation_name.clone(),
         context.clone(),
@@ -182,21 +184,23 @@ async fn service_call(
             .query
             .clone()
             .unwrap_or_default(),
-    )
-    .await
-    {
-        Ok(resp) => resp,
-        Err(err) => match err.into_graphql_errors() {
-            Ok(gql_errors) => {
-                return Ok(SupergraphResponse::infallible_builder()
-                    .context(context)
-                    .errors(gql_errors)
-                    .status_code(StatusCode::BAD_REQUEST) // If it's a graphql error we return a status code 400
-                    .build());
-            }
-            Err(err) => return Err(err.into()),
-        },
-    };
+    );
+    // Nothing can be returned to the client without a plan, so a request
+    // reaching its deadline during planning fails even with partial results enabled
+    let QueryPlannerResponse { content, errors } =
+        match RequestDeadline::run(&context, planned).await? {
+            Ok(resp) => resp,
+            Err(err) => match err.into_graphql_errors() {
+                Ok(gql_errors) => {
+                    return Ok(SupergraphResponse::infallible_builder()
+                        .context(context)
+                        .errors(gql_errors)
+                        .status_code(StatusCode::BAD_REQUEST) // If it's a graphql error we return a status code 400
+                        .build());
+                }
+                Err(err) => return Err(err.into()),
+            },
+        };
 
     if !errors.is_empty() {
         return Ok(SupergraphResponse::infallible_builder()
@@ -275,6 +279,7 @@ async fn service_call(
             let ClientRequestAccepts {
                 multipart_defer: accepts_multipart_defer,
                 multipart_subscription: accepts_multipart_subscription,
+                event_stream: accepts_event_stream,
                 ..
             } = context
                 .extensions()
@@ -282,12 +287,12 @@ async fn service_call(
                 .unwrap_or_default();
             let mut subscription_tx = None;
             if (is_deferred && !accepts_multipart_defer)
-                || (is_subscription && !accepts_multipart_subscription)
+                || (is_subscription && !accepts_multipart_subscription && !accepts_event_stream)
             {
                 let (error_message, error_code) = if is_deferred {
                     (String::from("the router received a query with the @defer directive but the client does not accept multipart/mixed HTTP responses. To enable @defer support, add the HTTP header 'Accept: multipart/mixed;deferSpec=20220824'"), "DEFER_BAD_HEADER")
                 } else {
-                    (String::from("the router received a query with a subscription but the client does not accept multipart/mixed HTTP responses. To enable subscription support, add the HTTP header 'Accept: multipart/mixed;subscriptionSpec=1.0'"), "SUBSCRIPTION_BAD_HEADER")
+                    (String::from("the router received a query with a subscription but the client does not accept multipart/mixed HTTP responses. To enable subscription support, add the HTTP header 'Accept: multipart/mixed;subscriptionSpec=1.0' or 'Accept: text/event-stream'"), "SUBSCRIPTION_BAD_HEADER")
                 };
                 let mut response = SupergraphResponse::new_from_graphql_response(
                     graphql::Response::builder()
@@ -306,6 +311,9 @@ async fn service_call(
                 Ok(res)
             } else {
                 if is_subscription {
+                    // Subscription events are executed with the same context long after the
+                    // deadline of the initial request, so they must not be cut short by it
+                    RequestDeadline::remove(&context);
                     let ctx = context.clone();
                     let (subs_tx, subs_rx) = mpsc::channel(1);
                     let query_plan = plan.clone();
@@ -573,7 +581,9 @@ async fn subscription_task(
                         subgraph_schemas: execution_service_factory.subgraph_schemas.clone(),
                         plugins: plugins.clone(),
                         subgraph_service_factory: Arc::new(SubgraphServiceFactory::new(subgraph_services.into_iter().map(|(k, v)| (k, Arc::new(v) as Arc<dyn MakeSubgraphService>)).collect(), plugins.clone())),
-
+                        entity_batching: Arc::new(conf.supergraph.experimental_entity_batching.clone()),
+                        parallel_mutations: conf.supergraph.experimental_parallel_mutations,
+                        response_validation: conf.supergraph.experimental_response_validation,
                     };
                 }
             }
@@ -895,6 +905,11 @@ impl SupergraphCreator {
                 subgraph_schemas: self.query_planner_service.subgraph_schemas(),
                 plugins: self.plugins.clone(),
                 subgraph_service_factory: self.subgraph_service_factory.clone(),
+                entity_batching: Arc::new(
+                    self.config.supergraph.experimental_entity_batching.clone(),
+                ),
+                parallel_mutations: self.config.supergraph.experimental_parallel_mutations,
+                response_validation: self.config.supergraph.experimental_response_validation,
             })
             .schema(self.schema.clone())
             .notify(self.config.notify.clone())
@@ -926,6 +941,14 @@ impl SupergraphCreator {
         self.query_planner_service.previous_cache()
     }
 
+    pub(crate) fn query_planner_pool(&self) -> &BridgeQueryPlannerPool {
+        self.query_planner_service.delegate()
+    }
+
+    pub(crate) async fn clear_query_plan_cache(&self) {
+        self.query_planner_service.clear_cache().await
+    }
+
     pub(crate) async fn warm_up_query_planner(
         &mut self,
         query_parser: &QueryAnalysisLayer,
@@ -934,6 +957,7 @@ impl SupergraphCreator {
         count: Option<usize>,
         experimental_reuse_query_plans: bool,
         experimental_pql_prewarm: &PersistedQueriesPrewarmQueryPlanCache,
+        warm_up_operations: &[WarmUpOperation],
     ) {
         self.query_planner_service
             .warm_up(
@@ -943,6 +967,7 @@ impl SupergraphCreator {
                 count,
                 experimental_reuse_query_plans,
                 experimental_pql_prewarm,
+                warm_up_operations,
             )
             .await
     }
@@ -7,11 +7,13 @@ use tower::ServiceExt;
 use tower_service::Service;
 
 use crate::graphql;
+use crate::json_ext::Object;
 use crate::plugin::test::MockSubgraph;
 use crate::services::router::ClientRequestAccepts;
 use crate::services::subgraph;
 use crate::services::supergraph;
 use crate::spec::Schema;
+use crate::test_harness::normalize_response;
 use crate::test_harness::MockedSubgraphs;
 use crate::Configuration;
 use crate::Context;
@@ -56,6 +58,137 @@ async fn nullability_formatting() {
     insta::assert_json_snapshot!(response);
 }
 
+/// An operation executed against a supergraph with mocked subgraphs, read from a file in
+/// `testdata/execution`
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExecutionFixture {
+    /// The name of a supergraph schema in `src/testdata`
+    supergraph: String,
+    #[serde(default = "default_fixture_configuration")]
+    configuration: serde_json::Value,
+    query: String,
+    #[serde(default)]
+    variables: Object,
+    subgraphs: HashMap<String, Vec<FixtureSubgraphRequest>>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureSubgraphRequest {
+    request: serde_json::Value,
+    response: serde_json::Value,
+}
+
+fn default_fixture_configuration() -> serde_json::Value {
+    serde_json::json!({"include_subgraph_errors": { "all": true } })
+}
+
+/// Executes every fixture of `testdata/execution`, and snapshots its normalized responses
+#[tokio::test]
+async fn execution_fixtures() {
+    let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut paths: Vec<_> =
+        std::fs::read_dir(manifest_dir.join("src/services/supergraph/testdata/execution"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let fixture: ExecutionFixture =
+            serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let schema =
+            std::fs::read_to_string(manifest_dir.join("src/testdata").join(&fixture.supergraph))
+                .unwrap();
+
+        let mut subgraphs = MockedSubgraphs::default();
+        for (subgraph_name, requests) in fixture.subgraphs {
+            let mut builder = MockSubgraph::builder();
+            for FixtureSubgraphRequest { request, response } in requests {
+                builder = builder.with_json(request, response);
+            }
+            // MockedSubgraphs is keyed by static names
+            subgraphs.insert(Box::leak(subgraph_name.into_boxed_str()), builder.build());
+        }
+
+        let service = TestHarness::builder()
+            .configuration_json(fixture.configuration)
+            .unwrap()
+            .schema(&schema)
+            .extra_plugin(subgraphs)
+            .build_supergraph()
+            .await
+            .unwrap();
+
+        let request = supergraph::Request::fake_builder()
+            .context(defer_context())
+            .query(fixture.query)
+            .variables(fixture.variables)
+            .build()
+            .unwrap();
+        let mut stream = service.oneshot(request).await.unwrap();
+        let mut responses = Vec::new();
+        while let Some(mut response) = stream.next_response().await {
+            normalize_response(&mut response);
+            responses.push(response);
+        }
+
+        insta::with_settings!({ snapshot_suffix => name }, {
+            insta::assert_json_snapshot!(responses);
+        });
+    }
+}
+
+#[tokio::test]
+async fn mocked_subgraph_failures_delays_and_received_requests() {
+    let user = MockSubgraph::builder()
+        .with_failure(
+            serde_json::json! {{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+            "connection refused",
+        )
+        .with_delay(Duration::from_millis(50))
+        .build();
+    let subgraphs = MockedSubgraphs(
+        [("user", user.clone()), ("orga", MockSubgraph::default())]
+            .into_iter()
+            .collect(),
+    );
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(subgraphs)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query("query { currentUser { activeOrganization { id creatorUser { name } } } }")
+        .build()
+        .unwrap();
+    let start = std::time::Instant::now();
+    let response = service
+        .oneshot(request)
+        .await
+        .unwrap()
+        .next_response()
+        .await
+        .unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(response.errors[0].message.contains("connection refused"));
+    // the clone kept by the test sees the calls of the mock used by the router
+    assert_eq!(user.call_count(), 1);
+    assert!(user.received_requests()[0]
+        .query
+        .as_deref()
+        .unwrap()
+        .contains("currentUser"));
+}
+
 #[tokio::test]
 async fn root_selection_set_statically_skipped() {
     let subgraphs = MockedSubgraphs(
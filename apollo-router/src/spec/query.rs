@@ -118,7 +118,8 @@ impl Query {
     /// Re-format the response value to match this query.
     ///
     /// This will discard unrequested fields and re-order the output to match the order of the
-    /// query.
+    /// query. If `validation` is set, the values of the response that do not match the schema
+    /// are reported in it.
     #[tracing::instrument(skip_all, level = "trace")]
     pub(crate) fn format_response(
         &self,
@@ -126,6 +127,7 @@ impl Query {
         variables: Object,
         schema: &ApiSchema,
         defer_conditions: BooleanValues,
+        validation: Option<&mut Vec<Error>>,
     ) -> Vec<Path> {
         let data = std::mem::take(&mut response.data);
 
@@ -145,6 +147,7 @@ impl Query {
                                 schema,
                                 errors: Vec::new(),
                                 nullified: Vec::new(),
+                                invalid_values: Vec::new(),
                             };
 
                             response.data = Some(
@@ -166,6 +169,9 @@ impl Query {
                                     response.extensions.insert("valueCompletion", value);
                                 }
                             }
+                            if let Some(validation) = validation {
+                                parameters.report_invalid_values(validation);
+                            }
 
                             return parameters.nullified;
                         }
@@ -200,6 +206,7 @@ impl Query {
                         schema,
                         errors: Vec::new(),
                         nullified: Vec::new(),
+                        invalid_values: Vec::new(),
                     };
 
                     response.data = Some(
@@ -220,6 +227,9 @@ impl Query {
                             response.extensions.insert("valueCompletion", value);
                         }
                     }
+                    if let Some(validation) = validation {
+                        parameters.report_invalid_values(validation);
+                    }
 
                     return parameters.nullified;
                 }
@@ -230,6 +240,12 @@ impl Query {
             }
             _ => {
                 failfast_debug!("invalid type for data in response. data: {:#?}", data);
+                if let Some(validation) = validation.filter(|_| data.is_some()) {
+                    validation.push(Error {
+                        message: "Invalid value for the response data".to_string(),
+                        ..Error::default()
+                    });
+                }
             }
         }
 
@@ -432,7 +448,11 @@ impl Query {
                         Ok(()) => Ok(()),
                     }
                 }
-                _ => Ok(()),
+                Value::Null => Ok(()),
+                _ => {
+                    parameters.invalid_value(path, field_type);
+                    Ok(())
+                }
             },
             executable::Type::Named(name) if name == "Int" => {
                 let opt = if input.is_i64() {
@@ -448,6 +468,7 @@ impl Query {
                 if opt.is_some() {
                     *output = input.clone();
                 } else {
+                    parameters.invalid_value_unless_null(input, path, field_type);
                     *output = Value::Null;
                 }
                 Ok(())
@@ -456,6 +477,7 @@ impl Query {
                 if input.as_f64().is_some() {
                     *output = input.clone();
                 } else {
+                    parameters.invalid_value_unless_null(input, path, field_type);
                     *output = Value::Null;
                 }
                 Ok(())
@@ -464,6 +486,7 @@ impl Query {
                 if input.as_bool().is_some() {
                     *output = input.clone();
                 } else {
+                    parameters.invalid_value_unless_null(input, path, field_type);
                     *output = Value::Null;
                 }
                 Ok(())
@@ -472,6 +495,7 @@ impl Query {
                 if input.as_str().is_some() {
                     *output = input.clone();
                 } else {
+                    parameters.invalid_value_unless_null(input, path, field_type);
                     *output = Value::Null;
                 }
                 Ok(())
@@ -480,6 +504,7 @@ impl Query {
                 if input.is_string() || input.is_i64() || input.is_u64() || input.is_f64() {
                     *output = input.clone();
                 } else {
+                    parameters.invalid_value_unless_null(input, path, field_type);
                     *output = Value::Null;
                 }
                 Ok(())
@@ -499,11 +524,13 @@ impl Query {
                                     *output = input.clone();
                                     Ok(())
                                 } else {
+                                    parameters.invalid_value(path, field_type);
                                     *output = Value::Null;
                                     Ok(())
                                 }
                             }
                             None => {
+                                parameters.invalid_value_unless_null(input, path, field_type);
                                 *output = Value::Null;
                                 Ok(())
                             }
@@ -524,6 +551,7 @@ impl Query {
                             let Some(ExtendedType::Object(_) | ExtendedType::Interface(_)) =
                                 parameters.schema.types.get(input_type)
                             else {
+                                parameters.invalid_value(path, field_type);
                                 parameters.nullified.push(Path::from_response_slice(path));
                                 *output = Value::Null;
                                 return Ok(());
@@ -567,6 +595,7 @@ impl Query {
                         Ok(())
                     }
                     _ => {
+                        parameters.invalid_value_unless_null(input, path, field_type);
                         parameters.nullified.push(Path::from_response_slice(path));
                         *output = Value::Null;
                         Ok(())
@@ -1030,9 +1059,38 @@ struct FormatParameters<'a> {
     variables: &'a Object,
     errors: Vec<Error>,
     nullified: Vec<Path>,
+    invalid_values: Vec<Error>,
     schema: &'a ApiSchema,
 }
 
+impl FormatParameters<'_> {
+    /// Records a value of the response that does not match its type in the schema
+    fn invalid_value(&mut self, path: &[ResponsePathElement<'_>], expected: &executable::Type) {
+        self.invalid_values.push(Error {
+            message: format!("Invalid value for type {expected}"),
+            path: Some(Path::from_response_slice(path)),
+            ..Error::default()
+        });
+    }
+
+    fn invalid_value_unless_null(
+        &mut self,
+        input: &Value,
+        path: &[ResponsePathElement<'_>],
+        expected: &executable::Type,
+    ) {
+        if !input.is_null() {
+            self.invalid_value(path, expected);
+        }
+    }
+
+    /// Adds the type and nullability mismatches found while formatting to `validation`
+    fn report_invalid_values(&mut self, validation: &mut Vec<Error>) {
+        validation.extend(self.errors.iter().cloned());
+        validation.append(&mut self.invalid_values);
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Operation {
     pub(crate) name: Option<String>,
@@ -50,6 +50,7 @@ struct FormatTest {
     expected: Option<serde_json_bytes::Value>,
     expected_errors: Option<serde_json_bytes::Value>,
     expected_extensions: Option<serde_json_bytes::Value>,
+    expected_invalid_values: Option<serde_json_bytes::Value>,
     federation_version: FederationVersion,
 }
 
@@ -105,6 +106,11 @@ impl FormatTest {
         self
     }
 
+    fn expected_invalid_values(mut self, v: serde_json_bytes::Value) -> Self {
+        self.expected_invalid_values = Some(v);
+        self
+    }
+
     fn fed2(mut self) -> Self {
         self.federation_version = FederationVersion::Fed2;
         self
@@ -128,6 +134,7 @@ impl FormatTest {
         let query =
             Query::parse(query, None, &schema, &Default::default()).expect("could not parse query");
         let mut response = Response::builder().data(response).build();
+        let mut invalid_values = Vec::new();
 
         query.format_response(
             &mut response,
@@ -138,6 +145,7 @@ impl FormatTest {
                 .clone(),
             api_schema,
             BooleanValues { bits: 0 },
+            Some(&mut invalid_values),
         );
 
         if let Some(e) = self.expected {
@@ -157,6 +165,10 @@ impl FormatTest {
                 e
             );
         }
+
+        if let Some(e) = self.expected_invalid_values {
+            assert_eq_and_ordered_json!(serde_json_bytes::to_value(&invalid_values).unwrap(), e);
+        }
     }
 }
 
@@ -232,6 +244,61 @@ fn reformat_typename_of_query_not_named_query() {
         .test();
 }
 
+#[test]
+fn reports_response_values_not_matching_the_schema() {
+    FormatTest::builder()
+        .schema(
+            "type Query {
+                count: Int
+                name: String
+                status: Status
+                tags: [String]
+                me: User
+            }
+            type User {
+                id: String!
+            }
+            enum Status {
+                ACTIVE
+                INACTIVE
+            }",
+        )
+        .query("{ count name status tags me { id } }")
+        .response(json! {{
+            "count": "one",
+            "name": null,
+            "status": "DELETED",
+            "tags": "a",
+            "me": { "id": null },
+        }})
+        .expected(json! {{
+            "count": null,
+            "name": null,
+            "status": null,
+            "tags": null,
+            "me": null,
+        }})
+        .expected_invalid_values(json! {[
+            {
+                "message": "Cannot return null for non-nullable field User.id",
+                "path": ["me", "id"]
+            },
+            {
+                "message": "Invalid value for type Int",
+                "path": ["count"]
+            },
+            {
+                "message": "Invalid value for type Status",
+                "path": ["status"]
+            },
+            {
+                "message": "Invalid value for type [String]",
+                "path": ["tags"]
+            },
+        ]})
+        .test();
+}
+
 #[test]
 fn reformat_response_data_field() {
     FormatTest::builder()
@@ -5118,6 +5185,7 @@ fn fragment_on_interface_on_query() {
         Default::default(),
         api_schema,
         BooleanValues { bits: 0 },
+        None,
     );
     assert_eq_and_ordered!(
         response.data.as_ref().unwrap(),
@@ -5829,6 +5897,7 @@ fn filtered_defer_fragment() {
         Object::new(),
         schema.api_schema(),
         BooleanValues { bits: 0 },
+        None,
     );
 
     assert_json_snapshot!(response);
@@ -5838,6 +5907,7 @@ fn filtered_defer_fragment() {
         Object::new(),
         schema.api_schema(),
         BooleanValues { bits: 0 },
+        None,
     );
 
     assert_json_snapshot!(response);
@@ -12,17 +12,21 @@ use apollo_federation::schema::ValidFederationSchema;
 use apollo_federation::ApiSchemaOptions;
 use apollo_federation::Supergraph;
 use http::Uri;
+use once_cell::sync::OnceCell;
 use semver::Version;
 use semver::VersionReq;
 use sha2::Digest;
 use sha2::Sha256;
 
+use crate::configuration::Contract;
 use crate::error::ParseErrors;
 use crate::error::SchemaError;
 use crate::query_planner::OperationKind;
 use crate::uplink::schema::SchemaState;
 use crate::Configuration;
 
+mod contract;
+
 /// A GraphQL schema.
 pub(crate) struct Schema {
     pub(crate) raw_sdl: Arc<String>,
@@ -32,6 +36,28 @@ pub(crate) struct Schema {
     api_schema: ApiSchema,
     pub(crate) schema_id: Arc<String>,
     pub(crate) launch_id: Option<Arc<String>>,
+    /// The SDL and options the schema was parsed from, to share it across reloads when they
+    /// don't change
+    source: Arc<SchemaState>,
+    options: ParseOptions,
+    /// Shared with the plugins, cloned from the supergraph schema on first use
+    supergraph_schema_arc: OnceCell<Arc<Valid<apollo_compiler::Schema>>>,
+}
+
+/// The configuration options the parsing of a schema depends on
+#[derive(Clone, PartialEq, Eq)]
+struct ParseOptions {
+    defer_support: bool,
+    contract: Contract,
+}
+
+impl ParseOptions {
+    fn new(config: &Configuration) -> Self {
+        Self {
+            defer_support: config.supergraph.defer_support,
+            contract: config.supergraph.experimental_contract.clone(),
+        }
+    }
 }
 
 /// Wrapper type to distinguish from `Schema::definitions` for the supergraph schema
@@ -55,7 +81,7 @@ impl Schema {
         let recursion_limit = parser.recursion_reached();
         tracing::trace!(?recursion_limit, "recursion limit data");
 
-        let definitions = result
+        let mut definitions = result
             .map_err(|invalid| {
                 SchemaError::Parse(ParseErrors {
                     errors: invalid.errors,
@@ -64,6 +90,21 @@ impl Schema {
             .to_schema_validate()
             .map_err(|errors| SchemaError::Validate(errors.into()))?;
 
+        // The schema of a contract is the supergraph schema with the filtered out elements
+        // marked @inaccessible, so it is used for query planning too
+        let options = ParseOptions::new(config);
+        let contract = &options.contract;
+        let sdl = if contract.is_enabled() {
+            let mut schema = definitions.into_inner();
+            contract::apply(&mut schema, contract)?;
+            definitions = schema
+                .validate()
+                .map_err(|errors| SchemaError::Validate(errors.into()))?;
+            definitions.to_string()
+        } else {
+            raw_sdl.sdl.clone()
+        };
+
         let mut subgraphs = HashMap::new();
         // TODO: error if not found?
         if let Some(join_enum) = definitions.get_enum("join__Graph") {
@@ -112,11 +153,11 @@ impl Schema {
         let implementers_map = definitions.implementers_map();
         let supergraph = Supergraph::from_schema(definitions)?;
 
-        let schema_id = Arc::new(Schema::schema_id(&raw_sdl.sdl));
+        let schema_id = Arc::new(Schema::schema_id(&sdl));
 
         let api_schema = supergraph
             .to_api_schema(ApiSchemaOptions {
-                include_defer: config.supergraph.defer_support,
+                include_defer: options.defer_support,
                 ..Default::default()
             })
             .map_err(|e| {
@@ -131,15 +172,60 @@ impl Schema {
                 .as_ref()
                 .map(ToString::to_string)
                 .map(Arc::new),
-            raw_sdl: Arc::new(raw_sdl.sdl.to_string()),
+            raw_sdl: Arc::new(sdl),
             supergraph,
             subgraphs,
             implementers_map,
             api_schema: ApiSchema(api_schema),
             schema_id,
+            source: raw_sdl,
+            options,
+            supergraph_schema_arc: OnceCell::new(),
         })
     }
 
+    /// Parses the schema, unless the previous one was parsed from the same SDL with the same
+    /// options. It is then shared, instead of being parsed and planned for again: only its launch
+    /// id is updated if it changed.
+    pub(crate) fn parse_or_reuse(
+        raw_sdl: Arc<SchemaState>,
+        config: &Configuration,
+        previous: Option<&Arc<Schema>>,
+    ) -> Result<Arc<Self>, SchemaError> {
+        match previous {
+            Some(previous)
+                if previous.source.sdl == raw_sdl.sdl
+                    && previous.options == ParseOptions::new(config) =>
+            {
+                tracing::debug!("the schema did not change, reusing the previously parsed schema");
+                if previous.source.launch_id == raw_sdl.launch_id {
+                    Ok(previous.clone())
+                } else {
+                    Ok(Arc::new(previous.with_source(raw_sdl)))
+                }
+            }
+            _ => Self::parse_arc(raw_sdl, config).map(Arc::new),
+        }
+    }
+
+    /// A shallow copy of the schema, from a source that only differs by its launch id
+    fn with_source(&self, source: Arc<SchemaState>) -> Self {
+        Self {
+            raw_sdl: self.raw_sdl.clone(),
+            supergraph: Supergraph {
+                schema: self.supergraph.schema.clone(),
+            },
+            subgraphs: self.subgraphs.clone(),
+            implementers_map: self.implementers_map.clone(),
+            api_schema: ApiSchema(self.api_schema.0.clone()),
+            schema_id: self.schema_id.clone(),
+            launch_id: source.launch_id.clone().map(Arc::new),
+            source,
+            options: self.options.clone(),
+            supergraph_schema_arc: self.supergraph_schema_arc.clone(),
+        }
+    }
+
     pub(crate) fn federation_supergraph(&self) -> &Supergraph {
         &self.supergraph
     }
@@ -148,6 +234,13 @@ impl Schema {
         self.supergraph.schema.schema()
     }
 
+    /// The supergraph schema, shared with the plugins of every pipeline built from this schema
+    pub(crate) fn supergraph_schema_arc(&self) -> Arc<Valid<apollo_compiler::Schema>> {
+        self.supergraph_schema_arc
+            .get_or_init(|| Arc::new(self.supergraph_schema().clone()))
+            .clone()
+    }
+
     pub(crate) fn schema_id(sdl: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(sdl.as_bytes());
@@ -342,9 +435,12 @@ impl std::fmt::Debug for Schema {
             supergraph: _, // skip
             subgraphs,
             implementers_map,
-            api_schema: _, // skip
-            schema_id: _,  // skip
-            launch_id: _,  // skip
+            api_schema: _,            // skip
+            schema_id: _,             // skip
+            launch_id: _,             // skip
+            source: _,                // skip
+            options: _,               // skip
+            supergraph_schema_arc: _, // skip
         } = self;
         f.debug_struct("Schema")
             .field("raw_sdl", raw_sdl)
@@ -540,6 +636,130 @@ mod tests {
         assert!(!has_in_stock_field(schema.api_schema()));
     }
 
+    #[test]
+    fn contract_api_schema() {
+        let config = Configuration::fake_builder()
+            .supergraph(
+                crate::configuration::Supergraph::fake_builder()
+                    .experimental_contract(crate::configuration::Contract {
+                        include_tags: vec!["public".to_string()],
+                        exclude_tags: vec!["internal".to_string()],
+                    })
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let schema = Schema::parse(
+            include_str!("../testdata/tagged_supergraph.graphql"),
+            &config,
+        )
+        .unwrap();
+        let api_schema = schema.api_schema();
+        let fields = |name: &str| {
+            api_schema
+                .get_object(name)
+                .unwrap()
+                .fields
+                .keys()
+                .map(|field| field.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(fields("Query"), ["me", "products"]);
+        assert_eq!(fields("User"), ["id", "name", "status"]);
+        assert_eq!(fields("Product"), ["upc", "name"]);
+        assert!(api_schema.get_object("AuditEvent").is_none());
+        assert!(api_schema.get_object("Mutation").is_none());
+        let status = api_schema.get_enum("Status").unwrap();
+        assert!(status.values.contains_key("ACTIVE"));
+        assert!(!status.values.contains_key("BANNED"));
+
+        // the arguments and input fields of a removed type are removed, along with the field
+        // when they are required
+        let products = api_schema.type_field("Query", "products").unwrap();
+        let arguments: Vec<_> = products
+            .arguments
+            .iter()
+            .map(|argument| argument.name.as_str())
+            .collect();
+        assert_eq!(arguments, ["filter"]);
+        let filter = api_schema.get_input_object("ProductFilter").unwrap();
+        assert_eq!(
+            filter
+                .fields
+                .keys()
+                .map(|field| field.as_str())
+                .collect::<Vec<_>>(),
+            ["name"]
+        );
+        assert!(api_schema.get_input_object("CostRange").is_none());
+        assert!(api_schema.get_enum("Region").is_none());
+
+        // the schema used for query planning is the filtered one
+        assert!(schema.raw_sdl.contains("@inaccessible"));
+        assert!(schema
+            .supergraph_schema()
+            .get_object("User")
+            .unwrap()
+            .fields
+            .contains_key("email"));
+
+        let schema = Schema::parse(
+            include_str!("../testdata/tagged_supergraph.graphql"),
+            &Default::default(),
+        )
+        .unwrap();
+        assert!(schema.api_schema().get_object("AuditEvent").is_some());
+    }
+
+    #[test]
+    fn it_reuses_unchanged_schemas() {
+        let sdl = include_str!("../testdata/minimal_supergraph.graphql");
+        let source = |launch_id: Option<&str>| {
+            Arc::new(SchemaState {
+                sdl: sdl.to_string(),
+                launch_id: launch_id.map(ToString::to_string),
+            })
+        };
+        let config = Configuration::default();
+        let previous = Schema::parse_or_reuse(source(Some("first")), &config, None).unwrap();
+
+        let unchanged =
+            Schema::parse_or_reuse(source(Some("first")), &config, Some(&previous)).unwrap();
+        assert!(Arc::ptr_eq(&previous, &unchanged));
+        assert!(Arc::ptr_eq(
+            &previous.supergraph_schema_arc(),
+            &unchanged.supergraph_schema_arc()
+        ));
+
+        // only the launch id is updated
+        let relaunched =
+            Schema::parse_or_reuse(source(Some("second")), &config, Some(&previous)).unwrap();
+        assert!(!Arc::ptr_eq(&previous, &relaunched));
+        assert_eq!(
+            relaunched.launch_id.as_deref().map(String::as_str),
+            Some("second")
+        );
+        assert!(Arc::ptr_eq(&previous.raw_sdl, &relaunched.raw_sdl));
+        assert!(Arc::ptr_eq(
+            &previous.supergraph_schema_arc(),
+            &relaunched.supergraph_schema_arc()
+        ));
+
+        // the API schema depends on the configuration
+        let config = Configuration::fake_builder()
+            .supergraph(
+                crate::configuration::Supergraph::fake_builder()
+                    .defer_support(false)
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let reparsed =
+            Schema::parse_or_reuse(source(Some("first")), &config, Some(&previous)).unwrap();
+        assert!(!Arc::ptr_eq(&previous.raw_sdl, &reparsed.raw_sdl));
+    }
+
     #[test]
     fn federation_version() {
         // @core directive
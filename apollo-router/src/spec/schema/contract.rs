@@ -0,0 +1,342 @@
+//! Contract variants of the supergraph schema
+//!
+//! The elements of the supergraph schema filtered out by the `@tag` configuration of the contract
+//! are marked `@inaccessible`, so they are removed from the API schema used for validation,
+//! introspection and query planning.
+
+use std::collections::HashSet;
+
+use apollo_compiler::ast::Argument;
+use apollo_compiler::ast::Directive;
+use apollo_compiler::ast::DirectiveDefinition;
+use apollo_compiler::ast::DirectiveLocation;
+use apollo_compiler::ast::FieldDefinition;
+use apollo_compiler::ast::InputValueDefinition;
+use apollo_compiler::ast::Value;
+use apollo_compiler::name;
+use apollo_compiler::schema::Component;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+
+use super::Schema;
+use crate::configuration::Contract;
+use crate::error::SchemaError;
+
+const TAG_SPEC_URL: &str = "https://specs.apollo.dev/tag";
+const INACCESSIBLE_SPEC_URL: &str = "https://specs.apollo.dev/inaccessible";
+
+/// Marks `@inaccessible` the elements of the supergraph schema that are not part of the contract.
+///
+/// An element tagged with one of the excluded tags is removed. If included tags are configured,
+/// the fields of object and interface types are removed, unless they or their parent type are
+/// tagged with one of them. Then the fields returning a removed type are removed as well, along
+/// with the types and unions left without any field or member. Arguments and input object fields
+/// of a removed type are removed too, or their field or input object if they are required.
+pub(crate) fn apply(
+    schema: &mut apollo_compiler::Schema,
+    contract: &Contract,
+) -> Result<(), SchemaError> {
+    let tag_directive =
+        Schema::directive_name(schema, TAG_SPEC_URL, ">=0.1.0", "tag").ok_or_else(|| {
+            SchemaError::Api(
+                "contracts require a supergraph schema using the @tag directive".to_string(),
+            )
+        })?;
+    let inaccessible = Directive {
+        name: inaccessible_directive_name(schema)?,
+        arguments: Vec::new(),
+    };
+    let filter = TagFilter {
+        tag_directive,
+        contract,
+        inaccessible,
+    };
+
+    for ty in schema.types.values_mut() {
+        if ty.is_built_in() {
+            continue;
+        }
+        let type_tags = filter.tags(ty.directives());
+        if filter.excludes(&type_tags) {
+            filter.mark_type(ty);
+            continue;
+        }
+        let type_included = filter.includes(&type_tags);
+
+        match ty {
+            ExtendedType::Object(object) => filter.filter_fields(
+                object.make_mut().fields.values_mut().map(|f| f.make_mut()),
+                type_included,
+            ),
+            ExtendedType::Interface(interface) => filter.filter_fields(
+                interface
+                    .make_mut()
+                    .fields
+                    .values_mut()
+                    .map(|f| f.make_mut()),
+                type_included,
+            ),
+            ExtendedType::Enum(enum_) => {
+                for value in enum_.make_mut().values.values_mut() {
+                    let tags = filter.tags(&value.directives);
+                    if filter.excludes(&tags) {
+                        filter.mark(&mut value.make_mut().directives);
+                    }
+                }
+            }
+            ExtendedType::InputObject(input_object) => {
+                for field in input_object.make_mut().fields.values_mut() {
+                    let tags = filter.tags(&field.directives);
+                    if filter.excludes(&tags) {
+                        filter.mark(&mut field.make_mut().directives);
+                    }
+                }
+            }
+            ExtendedType::Scalar(_) | ExtendedType::Union(_) => {}
+        }
+    }
+
+    filter.remove_unreachable_elements(schema);
+    Ok(())
+}
+
+/// Name of the `@inaccessible` directive in the schema, adding its definition if the supergraph
+/// schema does not use it yet
+fn inaccessible_directive_name(schema: &mut apollo_compiler::Schema) -> Result<Name, SchemaError> {
+    if let Some(name) =
+        Schema::directive_name(schema, INACCESSIBLE_SPEC_URL, ">=0.1.0", "inaccessible")
+    {
+        return Name::new(&name)
+            .map_err(|_| SchemaError::Api(format!("invalid @inaccessible directive name {name}")));
+    }
+
+    schema
+        .schema_definition
+        .make_mut()
+        .directives
+        .push(Component::new(Directive {
+            name: name!("link"),
+            arguments: vec![
+                Node::new(Argument {
+                    name: name!("url"),
+                    value: format!("{INACCESSIBLE_SPEC_URL}/v0.2").into(),
+                }),
+                Node::new(Argument {
+                    name: name!("for"),
+                    value: Node::new(Value::Enum(name!("SECURITY"))),
+                }),
+            ],
+        }));
+    schema.directive_definitions.insert(
+        name!("inaccessible"),
+        Node::new(DirectiveDefinition {
+            description: None,
+            name: name!("inaccessible"),
+            arguments: Vec::new(),
+            repeatable: false,
+            locations: vec![
+                DirectiveLocation::FieldDefinition,
+                DirectiveLocation::Object,
+                DirectiveLocation::Interface,
+                DirectiveLocation::Union,
+                DirectiveLocation::ArgumentDefinition,
+                DirectiveLocation::Scalar,
+                DirectiveLocation::Enum,
+                DirectiveLocation::EnumValue,
+                DirectiveLocation::InputObject,
+                DirectiveLocation::InputFieldDefinition,
+            ],
+        }),
+    );
+    Ok(name!("inaccessible"))
+}
+
+struct TagFilter<'a> {
+    tag_directive: String,
+    contract: &'a Contract,
+    inaccessible: Directive,
+}
+
+impl TagFilter<'_> {
+    fn tags<'d, I: AsRef<Directive>>(&self, directives: &'d [I]) -> Vec<&'d str> {
+        directives
+            .iter()
+            .map(|directive| directive.as_ref())
+            .filter(|directive| directive.name.as_str() == self.tag_directive)
+            .filter_map(|directive| directive.specified_argument_by_name("name")?.as_str())
+            .collect()
+    }
+
+    fn excludes(&self, tags: &[&str]) -> bool {
+        tags.iter()
+            .any(|tag| self.contract.exclude_tags.iter().any(|t| t == tag))
+    }
+
+    fn includes(&self, tags: &[&str]) -> bool {
+        self.contract.include_tags.is_empty()
+            || tags
+                .iter()
+                .any(|tag| self.contract.include_tags.iter().any(|t| t == tag))
+    }
+
+    fn is_marked<I: AsRef<Directive>>(&self, directives: &[I]) -> bool {
+        directives
+            .iter()
+            .any(|directive| directive.as_ref().name == self.inaccessible.name)
+    }
+
+    // generic so it handles ast::DirectiveList and schema::DirectiveList
+    fn mark<I: AsRef<Directive> + From<Directive>>(&self, directives: &mut Vec<I>) {
+        if !self.is_marked(directives) {
+            directives.push(self.inaccessible.clone().into());
+        }
+    }
+
+    fn mark_type(&self, ty: &mut ExtendedType) {
+        match ty {
+            ExtendedType::Scalar(scalar) => self.mark(&mut scalar.make_mut().directives),
+            ExtendedType::Object(object) => self.mark(&mut object.make_mut().directives),
+            ExtendedType::Interface(interface) => self.mark(&mut interface.make_mut().directives),
+            ExtendedType::Union(union_) => self.mark(&mut union_.make_mut().directives),
+            ExtendedType::Enum(enum_) => self.mark(&mut enum_.make_mut().directives),
+            ExtendedType::InputObject(input_object) => {
+                self.mark(&mut input_object.make_mut().directives)
+            }
+        }
+    }
+
+    fn filter_fields<'f>(
+        &self,
+        fields: impl Iterator<Item = &'f mut FieldDefinition>,
+        type_included: bool,
+    ) {
+        for field in fields {
+            let tags = self.tags(&field.directives);
+            if self.excludes(&tags) || !(type_included || self.includes(&tags)) {
+                self.mark(&mut field.directives);
+            } else {
+                self.filter_arguments(&mut field.arguments);
+            }
+        }
+    }
+
+    fn filter_arguments(&self, arguments: &mut [Node<InputValueDefinition>]) {
+        for argument in arguments {
+            let tags = self.tags(&argument.directives);
+            if self.excludes(&tags) {
+                self.mark(&mut argument.make_mut().directives);
+            }
+        }
+    }
+
+    /// Removes the fields returning a removed type, and the types left without any field, until
+    /// every accessible field, argument and input object field has an accessible type
+    fn remove_unreachable_elements(&self, schema: &mut apollo_compiler::Schema) {
+        loop {
+            let inaccessible_types: HashSet<Name> = schema
+                .types
+                .iter()
+                .filter(|(_, ty)| self.is_marked(ty.directives()))
+                .map(|(name, _)| name.clone())
+                .collect();
+            let mut changed = false;
+
+            for ty in schema.types.values_mut() {
+                if ty.is_built_in() || self.is_marked(ty.directives()) {
+                    continue;
+                }
+                let has_members = match ty {
+                    ExtendedType::Object(object) => self.remove_unreachable_fields(
+                        object.make_mut().fields.values_mut().map(|f| f.make_mut()),
+                        &inaccessible_types,
+                        &mut changed,
+                    ),
+                    ExtendedType::Interface(interface) => self.remove_unreachable_fields(
+                        interface
+                            .make_mut()
+                            .fields
+                            .values_mut()
+                            .map(|f| f.make_mut()),
+                        &inaccessible_types,
+                        &mut changed,
+                    ),
+                    ExtendedType::Union(union_) => union_
+                        .members
+                        .iter()
+                        .any(|member| !inaccessible_types.contains(&member.name)),
+                    ExtendedType::InputObject(input_object) => {
+                        let input_object = input_object.make_mut();
+                        self.remove_unreachable_input_values(
+                            input_object.fields.values_mut().map(|f| f.make_mut()),
+                            &inaccessible_types,
+                            &mut changed,
+                        ) && input_object
+                            .fields
+                            .values()
+                            .any(|field| !self.is_marked(&field.directives))
+                    }
+                    _ => true,
+                };
+                if !has_members {
+                    self.mark_type(ty);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    /// Returns true if some fields are still accessible
+    fn remove_unreachable_fields<'f>(
+        &self,
+        fields: impl Iterator<Item = &'f mut FieldDefinition>,
+        inaccessible_types: &HashSet<Name>,
+        changed: &mut bool,
+    ) -> bool {
+        let mut has_fields = false;
+        for field in fields {
+            if self.is_marked(&field.directives) {
+                continue;
+            }
+            if inaccessible_types.contains(field.ty.inner_named_type())
+                || !self.remove_unreachable_input_values(
+                    field.arguments.iter_mut().map(|a| a.make_mut()),
+                    inaccessible_types,
+                    changed,
+                )
+            {
+                self.mark(&mut field.directives);
+                *changed = true;
+            } else {
+                has_fields = true;
+            }
+        }
+        has_fields
+    }
+    /// Removes the arguments or input object fields of a removed type. Returns false if one of
+    /// the removed values is required, then its field or input object must be removed as well
+    fn remove_unreachable_input_values<'v>(
+        &self,
+        values: impl Iterator<Item = &'v mut InputValueDefinition>,
+        inaccessible_types: &HashSet<Name>,
+        changed: &mut bool,
+    ) -> bool {
+        let mut required_values_accessible = true;
+        for value in values {
+            let mut removed = self.is_marked(&value.directives);
+            if !removed && inaccessible_types.contains(value.ty.inner_named_type()) {
+                self.mark(&mut value.directives);
+                *changed = true;
+                removed = true;
+            }
+            if removed && value.ty.is_non_null() && value.default_value.is_none() {
+                required_values_accessible = false;
+            }
+        }
+        required_values_accessible
+    }
+}
@@ -3,16 +3,22 @@ use std::fmt::Formatter;
 use std::sync::Arc;
 
 use futures::prelude::*;
+use serde_json::Value;
 use tokio::sync::mpsc;
 #[cfg(test)]
 use tokio::sync::Notify;
 use tokio::sync::OwnedRwLockWriteGuard;
 use tokio::sync::RwLock;
 use ApolloRouterError::ServiceCreationError;
+use Event::FetchSchema;
 use Event::NoMoreConfiguration;
 use Event::NoMoreLicense;
 use Event::NoMoreSchema;
+use Event::PurgeCaches;
 use Event::Reload;
+use Event::SetLogLevel;
+use Event::SetMaintenanceMode;
+use Event::SetReadOnlyMode;
 use Event::Shutdown;
 use State::Errored;
 use State::Running;
@@ -32,7 +38,10 @@ use crate::configuration::Configuration;
 use crate::configuration::Discussed;
 use crate::configuration::ListenAddr;
 use crate::plugins::telemetry::reload::apollo_opentelemetry_initialized;
+use crate::plugins::telemetry::reload::override_log_level;
+use crate::router::AdminSource;
 use crate::router::Event::UpdateLicense;
+use crate::router::RouterInfo;
 use crate::router_factory::RouterFactory;
 use crate::router_factory::RouterSuperServiceFactory;
 use crate::spec::Schema;
@@ -63,6 +72,9 @@ enum State<FA: RouterSuperServiceFactory> {
         configuration: Arc<Configuration>,
         _metrics: Option<Metrics>,
         schema: Arc<SchemaState>,
+        /// The schema parsed from the schema state, shared with the next router if it does not
+        /// change
+        parsed_schema: Arc<Schema>,
         license: LicenseState,
         server_handle: Option<HttpServerHandle>,
         router_service_factory: FA::RouterFactory,
@@ -92,6 +104,25 @@ impl<FA: RouterSuperServiceFactory> Debug for State<FA> {
 }
 
 impl<FA: RouterSuperServiceFactory> State<FA> {
+    fn configuration(&self) -> Option<Arc<Configuration>> {
+        match self {
+            Startup { configuration, .. } => configuration.clone(),
+            Running { configuration, .. } => Some(configuration.clone()),
+            Stopped | Errored(_) => None,
+        }
+    }
+
+    async fn purge_caches(&self) {
+        if let Running {
+            router_service_factory,
+            ..
+        } = self
+        {
+            router_service_factory.purge_caches().await;
+            tracing::info!(event = STATE_CHANGE, "caches purged");
+        }
+    }
+
     async fn no_more_configuration(self) -> Self {
         match self {
             Startup {
@@ -146,6 +177,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                             state_machine,
                             &mut None,
                             None,
+                            None,
+                            false,
                             configuration.clone(),
                             schema.clone(),
                             *license,
@@ -162,6 +195,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             }
             Running {
                 schema,
+                parsed_schema,
                 configuration,
                 license,
                 server_handle,
@@ -184,7 +218,10 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                 // Have things actually changed?
                 let (mut license_reload, mut schema_reload, mut configuration_reload) =
                     (false, false, false);
+                let mut changed_sections = Vec::new();
                 if let Some(new_configuration) = new_configuration {
+                    changed_sections =
+                        changed_configuration_sections(configuration, &new_configuration);
                     *configuration = new_configuration;
                     configuration_reload = true;
                 }
@@ -206,11 +243,16 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                     new_schema = schema_reload,
                     new_license = license_reload,
                     new_configuration = configuration_reload,
+                    changed_configuration_sections = ?changed_sections,
                     event = STATE_CHANGE,
                     "processing event"
                 );
 
                 let need_reload = schema_reload || license_reload || configuration_reload;
+                // The router service can be kept when only the HTTP server configuration changed
+                let rebuild_router_service = schema_reload
+                    || license_reload
+                    || !only_http_server_sections(&changed_sections);
 
                 if need_reload {
                     // We update the running config. This is OK even in the case that the router could not reload as we always want to retain the latest information for when we try to reload next.
@@ -221,6 +263,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                         state_machine,
                         server_handle,
                         Some(router_service_factory),
+                        Some(parsed_schema),
+                        rebuild_router_service,
                         configuration.clone(),
                         schema.clone(),
                         *license,
@@ -234,6 +278,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                                 new_schema = schema_reload,
                                 new_license = license_reload,
                                 new_configuration = configuration_reload,
+                                changed_configuration_sections = ?changed_sections,
+                                rebuilt_router_service = rebuild_router_service,
                                 event = STATE_CHANGE,
                                 "reload complete"
                             );
@@ -308,6 +354,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         state_machine: &mut StateMachine<S, FA>,
         server_handle: &mut Option<HttpServerHandle>,
         previous_router_service_factory: Option<&FA::RouterFactory>,
+        previous_schema: Option<&Arc<Schema>>,
+        rebuild_router_service: bool,
         configuration: Arc<Configuration>,
         schema_state: Arc<SchemaState>,
         license: LicenseState,
@@ -318,10 +366,9 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         S: HttpServerFactory,
         FA: RouterSuperServiceFactory,
     {
-        let schema = Arc::new(
-            Schema::parse_arc(schema_state.clone(), &configuration)
-                .map_err(|e| ServiceCreationError(e.to_string().into()))?,
-        );
+        let schema = Schema::parse_or_reuse(schema_state.clone(), &configuration, previous_schema)
+            .map_err(|e| ServiceCreationError(e.to_string().into()))?;
+        let info = RouterInfo::new(&schema, &configuration);
         // Check the license
         let report = LicenseEnforcementReport::build(&configuration, &schema);
 
@@ -358,22 +405,38 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             license
         };
 
-        let router_service_factory = state_machine
-            .router_configurator
-            .create(
-                state_machine.is_telemetry_disabled,
-                configuration.clone(),
-                schema,
-                previous_router_service_factory,
-                None,
-            )
-            .await
-            .map_err(ServiceCreationError)?;
+        let router_service_factory = match previous_router_service_factory {
+            Some(previous) if !rebuild_router_service => previous.clone(),
+            _ => state_machine
+                .router_configurator
+                .create(
+                    state_machine.is_telemetry_disabled,
+                    configuration.clone(),
+                    schema.clone(),
+                    previous_router_service_factory,
+                    None,
+                )
+                .await
+                .map_err(ServiceCreationError)?,
+        };
         // used to track if there are still in flight connections when shutting down
         let (all_connections_stopped_sender, all_connections_stopped_signal) =
             mpsc::channel::<()>(1);
         all_connections_stopped_signals.push(all_connections_stopped_signal);
-        let web_endpoints = router_service_factory.web_endpoints();
+        let mut web_endpoints = router_service_factory.web_endpoints();
+        if let Some(admin) = &state_machine.admin {
+            if configuration.admin.enabled {
+                tracing::info!(
+                    "Admin API exposed at {}{}",
+                    configuration.admin.listen,
+                    configuration.admin.path
+                );
+                web_endpoints.insert(
+                    configuration.admin.listen.clone(),
+                    admin.endpoint(&configuration.admin, info.clone(), schema.clone()),
+                );
+            }
+        }
 
         // The point of no return. We take the previous server handle.
         let server_handle = match server_handle.take() {
@@ -417,6 +480,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             discussed.log_preview_used(yaml);
         }
 
+        info.log();
+
         let metrics =
             apollo_opentelemetry_initialized().then(|| Metrics::new(&configuration, &license));
 
@@ -424,6 +489,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             configuration,
             _metrics: metrics,
             schema: schema_state,
+            parsed_schema: schema,
             license,
             server_handle: Some(server_handle),
             router_service_factory,
@@ -448,6 +514,10 @@ where
     router_configurator: FA,
     pub(crate) listen_addresses: Arc<RwLock<ListenAddresses>>,
     listen_addresses_guard: Option<OwnedRwLockWriteGuard<ListenAddresses>>,
+    admin: Option<AdminSource>,
+    /// Maintenance plugin options set through the admin API, they take precedence over the
+    /// configuration
+    maintenance_overrides: serde_json::Map<String, Value>,
     #[cfg(test)]
     notify_updated: Arc<Notify>,
 }
@@ -477,11 +547,19 @@ where
             router_configurator: router_factory,
             listen_addresses,
             listen_addresses_guard,
+            admin: None,
+            maintenance_overrides: Default::default(),
             #[cfg(test)]
             notify_updated: Default::default(),
         }
     }
 
+    /// Exposes the admin API when it is enabled in the configuration
+    pub(crate) fn with_admin(mut self, admin: AdminSource) -> Self {
+        self.admin = Some(admin);
+        self
+    }
+
     #[cfg(test)]
     pub(crate) fn for_tests(
         http_server_factory: S,
@@ -502,10 +580,48 @@ where
             router_configurator: router_factory,
             listen_addresses,
             listen_addresses_guard,
+            admin: None,
+            maintenance_overrides: Default::default(),
             notify_updated,
         }
     }
 
+    /// Applies the maintenance options set through the admin API to the configuration
+    fn apply_maintenance_overrides(&self, mut configuration: Configuration) -> Configuration {
+        if !self.maintenance_overrides.is_empty() {
+            let maintenance = configuration
+                .apollo_plugins
+                .plugins
+                .entry("maintenance")
+                .or_insert_with(|| Value::Object(Default::default()));
+            if let Value::Object(maintenance) = maintenance {
+                maintenance.extend(self.maintenance_overrides.clone());
+            }
+        }
+        configuration
+    }
+
+    /// Overrides a maintenance option and reloads the router with it
+    async fn override_maintenance(
+        &mut self,
+        state: State<FA>,
+        option: &str,
+        enabled: bool,
+    ) -> State<FA> {
+        self.maintenance_overrides
+            .insert(option.to_string(), Value::Bool(enabled));
+        match state.configuration() {
+            Some(configuration) => {
+                let configuration =
+                    self.apply_maintenance_overrides(configuration.as_ref().clone());
+                state
+                    .update_inputs(self, None, Some(Arc::new(configuration)), None)
+                    .await
+            }
+            None => state,
+        }
+    }
+
     pub(crate) async fn process_events(
         mut self,
         mut messages: impl Stream<Item = Event> + Unpin,
@@ -533,6 +649,7 @@ where
 
             state = match event {
                 UpdateConfiguration(configuration) => {
+                    let configuration = self.apply_maintenance_overrides(configuration);
                     state
                         .update_inputs(&mut self, None, Some(Arc::new(configuration)), None)
                         .await
@@ -551,6 +668,24 @@ where
                 }
                 Reload => state.update_inputs(&mut self, None, None, None).await,
                 NoMoreLicense => state.no_more_license().await,
+                SetLogLevel(level) => {
+                    override_log_level(level);
+                    state
+                }
+                SetMaintenanceMode(enabled) => {
+                    self.override_maintenance(state, "enabled", enabled).await
+                }
+                SetReadOnlyMode(enabled) => {
+                    self.override_maintenance(state, "read_only", enabled).await
+                }
+                PurgeCaches => {
+                    state.purge_caches().await;
+                    state
+                }
+                FetchSchema => {
+                    crate::uplink::fetch_now();
+                    state
+                }
                 Shutdown => state.shutdown(&self.http_server_factory).await,
             };
 
@@ -593,6 +728,45 @@ where
     }
 }
 
+/// Top level configuration sections that are only read by the HTTP server.
+///
+/// A reload that only changes these sections restarts the HTTP server with the running router
+/// service, rather than rebuilding the plugins and the query planner.
+const HTTP_SERVER_SECTIONS: [&str; 4] = ["admin", "cors", "health_check", "server"];
+
+/// Lists the top level sections of the configuration that differ between two configurations.
+///
+/// Configurations that were not loaded from YAML cannot be compared, none of their sections are
+/// reported as changed.
+fn changed_configuration_sections(previous: &Configuration, new: &Configuration) -> Vec<String> {
+    let (Some(Value::Object(previous)), Some(Value::Object(new))) =
+        (&previous.validated_yaml, &new.validated_yaml)
+    else {
+        return Vec::new();
+    };
+
+    let mut sections: Vec<String> = previous
+        .keys()
+        .chain(new.keys())
+        .filter(|section| previous.get(*section) != new.get(*section))
+        .cloned()
+        .collect();
+    sections.sort();
+    sections.dedup();
+    sections
+}
+
+/// Whether the changed sections are only read by the HTTP server.
+///
+/// No changed section means the configurations could not be compared, so the router service has
+/// to be rebuilt.
+fn only_http_server_sections(changed_sections: &[String]) -> bool {
+    !changed_sections.is_empty()
+        && changed_sections
+            .iter()
+            .all(|section| HTTP_SERVER_SECTIONS.contains(&section.as_str()))
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::SocketAddr;
@@ -685,6 +859,45 @@ mod tests {
             Err(NoLicense)
         );
     }
+    #[test]
+    fn it_lists_changed_configuration_sections() {
+        let mut previous = Configuration::builder().build().unwrap();
+        previous.validated_yaml = Some(json!({
+            "cors": { "allow_any_origin": true },
+            "supergraph": { "listen": "127.0.0.1:4000" },
+            "homepage": { "enabled": true }
+        }));
+        let mut new = Configuration::builder().build().unwrap();
+        new.validated_yaml = Some(json!({
+            "cors": { "allow_any_origin": false },
+            "supergraph": { "listen": "127.0.0.1:4000" },
+            "sandbox": { "enabled": false }
+        }));
+
+        assert_eq!(
+            changed_configuration_sections(&previous, &new),
+            vec!["cors", "homepage", "sandbox"]
+        );
+        assert!(changed_configuration_sections(
+            &previous,
+            &Configuration::builder().build().unwrap()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn it_keeps_the_router_service_for_http_server_sections() {
+        assert!(only_http_server_sections(&[
+            "cors".to_string(),
+            "server".to_string()
+        ]));
+        assert!(!only_http_server_sections(&[
+            "cors".to_string(),
+            "homepage".to_string()
+        ]));
+        assert!(!only_http_server_sections(&[]));
+    }
+
     fn test_config_restricted() -> Configuration {
         let mut config = Configuration::builder().build().unwrap();
         config.validated_yaml =
@@ -984,6 +1197,44 @@ mod tests {
         assert_eq!(shutdown_receivers.0.lock().unwrap().len(), 2);
     }
 
+    #[test(tokio::test)]
+    async fn startup_reload_http_server_configuration() {
+        fn reusable_router() -> MockMyRouterFactory {
+            let mut router = MockMyRouterFactory::new();
+            router.expect_clone().returning(reusable_router);
+            router.expect_web_endpoints().returning(MultiMap::new);
+            router
+        }
+        let mut router_factory = MockMyRouterConfigurator::new();
+        router_factory
+            .expect_create()
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(reusable_router()));
+        let (server_factory, shutdown_receivers) = create_mock_server_factory(2, 1, 1, 1, 1);
+
+        let mut configuration = Configuration::builder().build().unwrap();
+        configuration.validated_yaml = Some(json!({"cors": {"allow_any_origin": false}}));
+        let mut new_configuration = Configuration::builder().build().unwrap();
+        new_configuration.validated_yaml = Some(json!({"cors": {"allow_any_origin": true}}));
+
+        assert_matches!(
+            execute(
+                server_factory,
+                router_factory,
+                stream::iter(vec![
+                    UpdateConfiguration(configuration),
+                    UpdateSchema(example_schema()),
+                    UpdateLicense(LicenseState::default()),
+                    UpdateConfiguration(new_configuration),
+                    Shutdown
+                ])
+            )
+            .await,
+            Ok(())
+        );
+        assert_eq!(shutdown_receivers.0.lock().unwrap().len(), 2);
+    }
+
     #[test(tokio::test)]
     async fn extract_routing_urls() {
         let router_factory = create_mock_router_configurator(1);
@@ -39,6 +39,11 @@ use crate::uplink::license_enforcement::LicenseState;
 
 /// Mocks for services the Apollo Router must integrate with.
 pub mod mocks;
+mod normalize;
+mod reload;
+
+pub use normalize::normalize_response;
+pub use reload::ReloadHandle;
 
 #[cfg(test)]
 pub(crate) mod http_client;
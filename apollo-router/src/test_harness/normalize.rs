@@ -0,0 +1,88 @@
+use serde_json_bytes::Value;
+
+use crate::graphql;
+use crate::json_ext::Object;
+
+/// Placeholder for the values that change from one run to the next
+const REDACTED: &str = "[redacted]";
+
+/// Extensions whose values change from one run to the next
+const VOLATILE_EXTENSIONS: &[&str] = &["traceId", "ftv1"];
+
+/// Normalizes a GraphQL response, so that it can be compared to a snapshot.
+///
+/// * errors are sorted by path then message, as their order depends on which subgraph fetch
+///   completes first
+/// * the keys of extensions are sorted
+/// * trace ids, federated traces and timestamps found in extensions are redacted
+///
+/// The data is left as is: the order of its fields is part of the response format.
+///
+/// ```
+/// use apollo_router::graphql;
+/// use apollo_router::test_harness::normalize_response;
+///
+/// let mut response = graphql::Response::builder()
+///     .error(
+///         graphql::Error::builder()
+///             .message("error")
+///             .extension("traceId", "5e8b0a6e4e9d1b2a")
+///             .extension_code("ERROR")
+///             .build(),
+///     )
+///     .build();
+/// normalize_response(&mut response);
+/// assert_eq!(response.errors[0].extensions["traceId"], "[redacted]");
+/// ```
+pub fn normalize_response(response: &mut graphql::Response) {
+    response.created_at = None;
+    normalize_errors(&mut response.errors);
+    normalize_extensions(&mut response.extensions);
+    for incremental in &mut response.incremental {
+        normalize_errors(&mut incremental.errors);
+        normalize_extensions(&mut incremental.extensions);
+    }
+}
+
+fn normalize_errors(errors: &mut [graphql::Error]) {
+    for error in errors.iter_mut() {
+        normalize_extensions(&mut error.extensions);
+    }
+    errors.sort_by_cached_key(|error| {
+        (
+            error.path.as_ref().map(|path| path.to_string()),
+            error.message.clone(),
+        )
+    });
+}
+
+fn normalize_extensions(extensions: &mut Object) {
+    normalize_object(extensions, |key, value| {
+        if VOLATILE_EXTENSIONS.contains(&key) {
+            *value = Value::String(REDACTED.into());
+        } else {
+            normalize_value(value);
+        }
+    });
+}
+
+fn normalize_value(value: &mut Value) {
+    match value {
+        Value::String(s) if humantime::parse_rfc3339_weak(s.as_str()).is_ok() => {
+            *value = Value::String(REDACTED.into());
+        }
+        Value::Array(values) => values.iter_mut().for_each(normalize_value),
+        Value::Object(object) => normalize_object(object, |_, value| normalize_value(value)),
+        _ => {}
+    }
+}
+
+/// Normalizes the values of an object, and sorts its keys
+fn normalize_object(object: &mut Object, normalize: impl Fn(&str, &mut Value)) {
+    let mut entries: Vec<_> = std::mem::take(object).into_iter().collect();
+    for (key, value) in &mut entries {
+        normalize(key.as_str(), value);
+    }
+    entries.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    *object = entries.into_iter().collect();
+}
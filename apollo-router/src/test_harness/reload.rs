@@ -0,0 +1,74 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+
+use crate::configuration::Configuration;
+use crate::router::ConfigurationSource;
+use crate::router::SchemaSource;
+
+/// Drives the schema and configuration reloads of a [`RouterHttpServer`][crate::RouterHttpServer]
+/// from a test.
+///
+/// The server must be built with the [`SchemaSource`] and [`ConfigurationSource`] returned by
+/// [`ReloadHandle::new`]. Every schema or configuration sent through the handle afterwards
+/// reloads the server, as if it was read from a file being watched.
+///
+/// ```no_run
+/// use apollo_router::test_harness::ReloadHandle;
+/// use apollo_router::Configuration;
+/// use apollo_router::RouterHttpServer;
+///
+/// # #[tokio::main] async fn main() -> Result<(), tower::BoxError> {
+/// # let schema = String::new();
+/// let (reload, schema_source, configuration_source) =
+///     ReloadHandle::new(schema.clone(), Configuration::default());
+/// let mut server = RouterHttpServer::builder()
+///     .schema(schema_source)
+///     .configuration(configuration_source)
+///     .start();
+///
+/// reload.reload_schema(schema);
+/// server.shutdown().await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct ReloadHandle {
+    schemas: mpsc::UnboundedSender<String>,
+    configurations: mpsc::UnboundedSender<Configuration>,
+}
+
+impl ReloadHandle {
+    /// Creates a handle, and the sources of a server starting with the given schema and
+    /// configuration
+    pub fn new(
+        schema: impl Into<String>,
+        configuration: Configuration,
+    ) -> (Self, SchemaSource, ConfigurationSource) {
+        let (schemas, schema_stream) = mpsc::unbounded();
+        let (configurations, configuration_stream) = mpsc::unbounded();
+        let handle = Self {
+            schemas,
+            configurations,
+        };
+        handle.reload_schema(schema);
+        handle.reload_configuration(configuration);
+        (
+            handle,
+            SchemaSource::Stream(schema_stream.boxed()),
+            ConfigurationSource::Stream(configuration_stream.boxed()),
+        )
+    }
+
+    /// Reloads the server with a new supergraph schema
+    pub fn reload_schema(&self, schema: impl Into<String>) {
+        self.schemas
+            .unbounded_send(schema.into())
+            .expect("the server must be running to reload its schema");
+    }
+
+    /// Reloads the server with a new configuration
+    pub fn reload_configuration(&self, configuration: Configuration) {
+        self.configurations
+            .unbounded_send(configuration)
+            .expect("the server must be running to reload its configuration");
+    }
+}
@@ -7,8 +7,10 @@ use futures::Future;
 use futures::Stream;
 use futures::StreamExt;
 use graphql_client::QueryBody;
+use once_cell::sync::Lazy;
 use thiserror::Error;
 use tokio::sync::mpsc::channel;
+use tokio::sync::Notify;
 use tokio_stream::wrappers::ReceiverStream;
 use tower::BoxError;
 use tracing::instrument::WithSubscriber;
@@ -23,6 +25,15 @@ pub(crate) mod schema_stream;
 const GCP_URL: &str = "https://uplink.api.apollographql.com";
 const AWS_URL: &str = "https://aws.uplink.api.apollographql.com";
 
+/// Wakes up the uplink pollers that are waiting for their next poll
+static FETCH_NOW: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Fetches the schema, and the other resources polled from uplink, without waiting for the poll
+/// interval
+pub(crate) fn fetch_now() {
+    FETCH_NOW.notify_waiters();
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum Error {
     #[error("http error")]
@@ -311,7 +322,10 @@ where
                 }
             }
 
-            tokio::time::sleep(uplink_config.poll_interval).await;
+            tokio::select! {
+                _ = tokio::time::sleep(uplink_config.poll_interval) => {}
+                _ = FETCH_NOW.notified() => {}
+            }
         }
     };
     drop(tokio::task::spawn(task.with_current_subscriber()));
@@ -225,6 +225,36 @@ async fn test_cli_config_preview() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_config_validate() {
+    let output = Command::new(IntegrationTest::router_location())
+        .arg("config")
+        .arg("validate")
+        .arg("tests/integration/fixtures/happy.router.yaml")
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("tests/integration/fixtures/happy.router.yaml is a valid configuration"));
+
+    let invalid_config = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(invalid_config.path(), INVALID_CONFIG).unwrap();
+    let output = Command::new(IntegrationTest::router_location())
+        .arg("config")
+        .arg("validate")
+        .arg(invalid_config.path())
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!(
+        "{} is not a valid configuration",
+        invalid_config.path().display()
+    )));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("is a valid configuration"));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_experimental_notice() {
     let mut router = IntegrationTest::builder()